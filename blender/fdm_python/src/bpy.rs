@@ -2,7 +2,7 @@ use std::marker::PhantomData;
 
 use pd2tools_macros::WrapsPyAny;
 use pyo3::types::PyDict;
-use pyo3::{prelude::*, intern, AsPyPointer};
+use pyo3::{prelude::*, intern, Bound};
 use crate::vek_types::*;
 
 macro_rules! get {
@@ -18,16 +18,20 @@ macro_rules! get {
     };
 }
 
+/// Implemented by every wrapper this module generates - the GIL-independent
+/// equivalent of what used to be a bare `&'py PyAny`, now that a wrapper
+/// owns a [`Bound`] smart pointer instead of borrowing one tied to the GIL
+/// token's lifetime.
 pub trait WrapsPyAny<'py> {
     fn py(&self) -> Python<'py>;
     fn as_ptr(&self) -> *mut pyo3::ffi::PyObject;
-    fn as_pyany(&self) -> &'py PyAny;
+    fn as_pyany(&self) -> &Bound<'py, PyAny>;
 }
 
 macro_rules! bpy_struct_wrapper {
     ($name:ident) => {
-        #[derive(Copy,Clone,WrapsPyAny)]
-        pub struct $name<'py>(&'py PyAny);
+        #[derive(Clone,WrapsPyAny)]
+        pub struct $name<'py>(Bound<'py, PyAny>);
         //impl<'py> std::ops::Deref for $name<'py> {
         //    type Target = PyAny;
         //
@@ -46,15 +50,15 @@ macro_rules! attr_get {
     };
     ($getter:ident: $py_name:expr => $type:ty as $converter:path) => {
         pub fn $getter(&self) -> $type {
-            let v: &PyAny = self.0.getattr(intern!{self.0.py(), $py_name}).unwrap();
-            $converter(v)
+            let v: Bound<'py, PyAny> = self.0.getattr(intern!{self.0.py(), $py_name}).unwrap();
+            $converter(&v)
         }
     };
 }
 
 macro_rules! iter_get {
     ($getter: ident: $py_name: expr) => {
-        pub fn $getter(&self) -> impl Iterator<Item=&PyAny> {
+        pub fn $getter(&self) -> impl Iterator<Item=Bound<'py, PyAny>> {
             self.0.getattr(intern!{self.0.py(), $py_name})
             .unwrap()
             .iter()
@@ -69,7 +73,7 @@ macro_rules! iter_get {
             .iter()
             .unwrap()
             .map(Result::unwrap)
-            .map(FromPyObject::extract)
+            .map(|i| i.extract())
             .map(Result::unwrap)
         }
     };
@@ -95,6 +99,82 @@ macro_rules! method {
     }
 }
 
+/// Shared by every `try_` accessor below: turns a failed `getattr`/`call_method`/
+/// `extract` into a [`PyErr`] that names both the wrapper type and the Python
+/// attribute/method it was trying to read, e.g. `Object.matrix_local: ...`,
+/// instead of the bare downcast/attribute error pyo3 would otherwise report.
+fn context_error<T>(py_name: &str, source: PyErr) -> PyErr {
+    pyo3::exceptions::PyAttributeError::new_err(
+        format!("{}.{}: {}", std::any::type_name::<T>(), py_name, source)
+    )
+}
+
+macro_rules! try_attr_get {
+    ($getter:ident: $py_name:expr => $type:ty) => {
+        pub fn $getter(&self) -> PyResult<$type> {
+            self.0.getattr(intern!{self.0.py(), $py_name})
+                .and_then(|v| v.extract())
+                .map_err(|e| context_error::<Self>($py_name, e))
+        }
+    };
+    ($getter:ident: $py_name:expr => $type:ty as $converter:path) => {
+        pub fn $getter(&self) -> PyResult<$type> {
+            self.0.getattr(intern!{self.0.py(), $py_name})
+                .map(|v| $converter(&v))
+                .map_err(|e| context_error::<Self>($py_name, e))
+        }
+    };
+}
+
+macro_rules! try_iter_get {
+    ($getter: ident: $py_name: expr) => {
+        pub fn $getter(&self) -> PyResult<impl Iterator<Item=Bound<'py, PyAny>> + '_> {
+            let it = self.0.getattr(intern!{self.0.py(), $py_name})
+                .and_then(|v| v.iter())
+                .map_err(|e| context_error::<Self>($py_name, e))?;
+            Ok(it.map(Result::unwrap))
+        }
+    };
+    ($getter: ident: $py_name: expr => $type:ty) => {
+        pub fn $getter(&self) -> PyResult<impl Iterator<Item=PyResult<$type>> + '_> {
+            let mut it: TypedPyIterator<$type> = TypedPyIterator(
+                self.0.getattr(intern!{self.0.py(), $py_name})
+                    .and_then(|v| v.iter())
+                    .map_err(|e| context_error::<Self>($py_name, e))?,
+                PhantomData
+            );
+            let py_name = $py_name;
+            Ok(std::iter::from_fn(move || it.try_next().map(|r| r.map_err(|e| context_error::<Self>(py_name, e)))))
+        }
+    };
+}
+
+macro_rules! try_method {
+    ($name:ident: $py_name:literal()) => {
+        pub fn $name(&self) -> PyResult<()> {
+            self.0.call_method0(intern!(self.0.py(), $py_name))
+                .map(|_| ())
+                .map_err(|e| context_error::<Self>($py_name, e))
+        }
+    };
+
+    ($name:ident: $py_name:literal() -> $type:ty $(as $converter:path)?) => {
+        pub fn $name(&self) -> PyResult<$type> {
+            self.0.call_method0(intern!(self.0.py(), $py_name))
+                .and_then(|v| v.extract())
+                .map_err(|e| context_error::<Self>($py_name, e))
+        }
+    };
+
+    ($name:ident: $py_name:literal($($arg:ident: $arg_ty:ty),*) -> $type:ty $(as $converter:path)?) => {
+        pub fn $name(&self $(,$arg: $arg_ty)*) -> PyResult<$type> {
+            self.0.call_method1(intern!(self.0.py(), $py_name), ($($arg,)*))
+                .and_then(|v| v.extract())
+                .map_err(|e| context_error::<Self>($py_name, e))
+        }
+    }
+}
+
 macro_rules! bpy_str_enum {
     ($v:vis enum $name:ident {
         $($variant:ident = $pystr:literal),* $(,)?
@@ -104,7 +184,7 @@ macro_rules! bpy_str_enum {
             $($variant),*
         }
         impl<'py> FromPyObject<'py> for $name {
-            fn extract(ob: &'py PyAny) -> PyResult<Self> {
+            fn extract(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
                 let s: &str = ob.extract()?;
                 match s {
                     $($pystr => Ok(Self::$variant),)*
@@ -147,6 +227,33 @@ pub unsafe trait PodArray {
     }
 }
 
+/// A [`PodArray`] over data read in one `foreach_get` round trip rather than
+/// one Python attribute access per element - see [`foreach_get_pod`].
+pub struct PodBuffer<T: bytemuck::Pod>(Vec<T>);
+unsafe impl<T: bytemuck::Pod> PodArray for PodBuffer<T> {
+    type Item = T;
+    fn as_data_pointer(&self) -> *const Self::Item { self.0.as_ptr() }
+    fn len(&self) -> usize { self.0.len() }
+    fn as_slice(&self) -> &[Self::Item] { &self.0 }
+}
+
+/// Fills a scratch `array.array('f', ...)` via Blender's `foreach_get(attr, seq)`,
+/// which memcpys straight into it through the buffer protocol instead of
+/// creating one Python float (or vector/color) object per element, then reads
+/// that buffer back through [`pyo3::buffer::PyBuffer`] and reinterprets the
+/// flat `f32`s as `T` - e.g. `T = [f32; 3]` for a 3-floats-per-element attribute.
+/// Only useful for attributes Blender represents as floats; non-float data
+/// (`STRING`, ...) has to fall back to per-element iteration instead.
+fn foreach_get_pod<T: bytemuck::Pod>(collection: &Bound<'_, PyAny>, attr: &str, count: usize) -> PyResult<Vec<T>> {
+    let py = collection.py();
+    let components = std::mem::size_of::<T>() / std::mem::size_of::<f32>();
+    let array_mod = py.import("array")?;
+    let flat = array_mod.call_method1("array", ("f", vec![0.0f32; count * components]))?;
+    collection.call_method1(intern!(py, "foreach_get"), (attr, flat))?;
+    let buffer = pyo3::buffer::PyBuffer::<f32>::get(flat)?;
+    Ok(bytemuck::cast_slice(&buffer.to_vec(py)?).to_vec())
+}
+
 macro_rules! bpy_collection {
     ($name:ident, 'array $item:ty) => {
         bpy_struct_wrapper!($name);
@@ -188,10 +295,8 @@ macro_rules! bpy_collection {
     }
 }
 
-//#[derive(Copy,Clone)]
-//struct BpyCollection<'py, T>(&'py PyAny, PhantomData<T>);
-#[derive(Copy,Clone,WrapsPyAny)]
-pub struct BpyCollection<'py, T>(&'py PyAny, PhantomData<T>);
+#[derive(Clone,WrapsPyAny)]
+pub struct BpyCollection<'py, T>(Bound<'py, PyAny>, PhantomData<T>);
 impl<'py,T: FromPyObject<'py>+Clone> IntoIterator for BpyCollection<'py,T>{
   type Item = T;
   type IntoIter = TypedPyIterator<'py, T>;
@@ -206,9 +311,9 @@ impl<'py,T: FromPyObject<'py>+Clone> PropCollection for BpyCollection<'py,T> {
   }
 }
 
-pub struct TypedPyIterator<'py, T>(&'py pyo3::types::PyIterator, PhantomData<T>);
+pub struct TypedPyIterator<'py, T>(Bound<'py, pyo3::types::PyIterator>, PhantomData<T>);
 impl<'py, T> std::ops::Deref for TypedPyIterator<'py, T> {
-    type Target = &'py pyo3::types::PyIterator;
+    type Target = Bound<'py, pyo3::types::PyIterator>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -221,6 +326,14 @@ impl<'py, T: FromPyObject<'py>> Iterator for TypedPyIterator<'py, T> {
         self.0.next().map(|i| i.unwrap().extract().unwrap())
     }
 }
+impl<'py, T: FromPyObject<'py>> TypedPyIterator<'py, T> {
+    /// The fallible counterpart to [`Iterator::next`], for `try_` accessors:
+    /// propagates a bad item (Python-side exception during iteration, or a
+    /// `try_extract` that doesn't match `T`) instead of unwrapping it.
+    pub fn try_next(&mut self) -> Option<PyResult<T>> {
+        self.0.next().map(|i| i.and_then(|i| i.extract()))
+    }
+}
 
 fn vek2f_from_tuple(inp: (f32, f32)) -> Vec2f {
     inp.into()
@@ -230,17 +343,23 @@ fn vek3f_from_tuple(inp: (f32, f32, f32)) -> Vec3f {
     inp.into()
 }
 
-fn vek2f_from_bpy_vec(data: &PyAny) -> Vec2f {
+fn vek2f_from_bpy_vec(data: &Bound<'_, PyAny>) -> Vec2f {
     let tuple = data.call_method0(intern!(data.py(), "to_tuple")).unwrap().extract().unwrap();
     vek2f_from_tuple(tuple)
 }
 
-fn vek3f_from_bpy_vec(data: &PyAny) -> Vec3f {
+fn vek3f_from_bpy_vec(data: &Bound<'_, PyAny>) -> Vec3f {
     let tuple = data.call_method0(intern!(data.py(), "to_tuple")).unwrap().extract().unwrap();
     vek3f_from_tuple(tuple)
 }
 
-fn quaternion_from_bpy_quat(bq: &PyAny) -> Quaternion {
+fn frame_range_from_bpy(data: &Bound<'_, PyAny>) -> (f32, f32) {
+    let start = data.get_item(0).unwrap().extract().unwrap();
+    let end = data.get_item(1).unwrap().extract().unwrap();
+    (start, end)
+}
+
+fn quaternion_from_bpy_quat(bq: &Bound<'_, PyAny>) -> Quaternion {
     let x: f32 = get!(bq, 'attr "x");
     let y: f32 = get!(bq, 'attr "y");
     let z: f32 = get!(bq, 'attr "z");
@@ -248,7 +367,7 @@ fn quaternion_from_bpy_quat(bq: &PyAny) -> Quaternion {
     Quaternion::from_xyzw(x, y, z, w)
 }
 
-fn mat4_from_bpy_matrix(bmat: &PyAny) -> vek::Mat4<f32> {
+fn mat4_from_bpy_matrix(bmat: &Bound<'_, PyAny>) -> vek::Mat4<f32> {
     let mut floats = [[0f32; 4]; 4];
     for r in 0..4 {
         let row = bmat.get_item(r).unwrap();
@@ -260,17 +379,17 @@ fn mat4_from_bpy_matrix(bmat: &PyAny) -> vek::Mat4<f32> {
     vek::Mat4::from_col_arrays(floats)
 }
 
-fn transform_from_bpy_matrix(bmat: &PyAny) -> Transform {
+fn transform_from_bpy_matrix(bmat: &Bound<'_, PyAny>) -> Transform {
     let py_lrs = bmat.call_method0(intern!{bmat.py(), "decompose"}).unwrap();
-    let (py_loc, py_rot, py_scale): (&PyAny, &PyAny, &PyAny) = py_lrs.extract().unwrap();
+    let (py_loc, py_rot, py_scale): (Bound<'_, PyAny>, Bound<'_, PyAny>, Bound<'_, PyAny>) = py_lrs.extract().unwrap();
     Transform {
-        position: vek3f_from_bpy_vec(py_loc),
-        orientation: quaternion_from_bpy_quat(py_rot),
-        scale: vek3f_from_bpy_vec(py_scale)
+        position: vek3f_from_bpy_vec(&py_loc),
+        orientation: quaternion_from_bpy_quat(&py_rot),
+        scale: vek3f_from_bpy_vec(&py_scale)
     }
 }
 
-fn from_bpy_array<const N:usize,T,E>(data: &PyAny) -> T
+fn from_bpy_array<const N:usize,T,E>(data: &Bound<'_, PyAny>) -> T
 where
     T: From<[E; N]>,
     E: Default + Copy + for<'a> FromPyObject<'a>
@@ -283,8 +402,8 @@ where
 }
 
 /// Blender Object
-#[derive(Clone, Copy, WrapsPyAny)]
-pub struct Object<'py>(&'py PyAny);
+#[derive(Clone, WrapsPyAny)]
+pub struct Object<'py>(Bound<'py, PyAny>);
 impl<'py> Object<'py> {
     attr_get!(name: "name" => &str );
     attr_get!(r#type: "type" => ObjectType );
@@ -293,19 +412,23 @@ impl<'py> Object<'py> {
     attr_get!(matrix_local: "matrix_local" => Transform as transform_from_bpy_matrix);
     attr_get!(parent_bone: "parent_bone" => &str);
     attr_get!(matrix_world: "matrix_world" => vek::Mat4<f32> as mat4_from_bpy_matrix);
-    attr_get!(data: "data" => &PyAny);
+    attr_get!(data: "data" => Bound<'py, PyAny>);
+    attr_get!(animation_data: "animation_data" => Option<AnimData<'py>>);
+    attr_get!(pose: "pose" => Option<Pose<'py>>);
+    attr_get!(diesel_collision: "diesel_collision" => Bound<'py, PyAny>);
+    attr_get!(empty_display_size: "empty_display_size" => f32);
 
     iter_get!(iter_modifiers: "modifiers" => Modifier<'py>);
     iter_get!(iter_vertex_groups: "vertex_groups" => VertexGroup);
     iter_get!(iter_material_slots: "material_slots" => MaterialSlot);
     iter_get!(iter_children_recursive: "children_recursive" => Object);
 
-    method!(evaluated_get: "evaluated_get"(depsgraph: &'py PyAny) -> Object<'py>);
-    pub fn to_mesh(&self, preserve_all_data_layers: bool, depsgraph: &'py PyAny) -> Mesh<'py> {
+    method!(evaluated_get: "evaluated_get"(depsgraph: &Bound<'py, PyAny>) -> Object<'py>);
+    pub fn to_mesh(&self, preserve_all_data_layers: bool, depsgraph: &Bound<'py, PyAny>) -> Mesh<'py> {
         let args = PyDict::new(self.0.py());
         args.set_item("preserve_all_data_layers", preserve_all_data_layers).unwrap();
         args.set_item("depsgraph", depsgraph).unwrap();
-        let d = self.0.call_method(intern!(self.0.py(), "to_mesh"), (), Some(args)).unwrap();
+        let d = self.0.call_method(intern!(self.0.py(), "to_mesh"), (), Some(&args)).unwrap();
         Mesh::wrap(d)
     }
     method!(to_mesh_clear: "to_mesh_clear"());
@@ -343,6 +466,24 @@ bpy_str_enum!{
     }
 }
 
+bpy_struct_wrapper!(Scene);
+impl<'py> Scene<'py> {
+    attr_get!(frame_start: "frame_start" => i32);
+    attr_get!(frame_end: "frame_end" => i32);
+    attr_get!(frame_current: "frame_current" => i32);
+    attr_get!(render: "render" => RenderSettings<'py>);
+
+    pub fn frame_set(&self, frame: i32) {
+        self.0.call_method1(intern!(self.0.py(), "frame_set"), (frame,)).unwrap();
+    }
+}
+
+bpy_struct_wrapper!(RenderSettings);
+impl<'py> RenderSettings<'py> {
+    attr_get!(fps: "fps" => i32);
+    attr_get!(fps_base: "fps_base" => f32);
+}
+
 bpy_struct_wrapper!(VertexGroup);
 impl<'py> VertexGroup<'py> {
     attr_get!(name: "name" => &'py str);
@@ -358,9 +499,47 @@ impl<'py> Material<'py> {
     attr_get!(name: "name" => &'py str);
 }
 
-pub struct Bone<'py>(&'py PyAny);
+bpy_struct_wrapper!(Light);
+impl<'py> Light<'py> {
+    attr_get!(r#type: "type" => LightKind);
+    attr_get!(color: "color" => Rgbf as from_bpy_array);
+    attr_get!(energy: "energy" => f32);
+    attr_get!(spot_size: "spot_size" => f32);
+    attr_get!(spot_blend: "spot_blend" => f32);
+    attr_get!(cutoff_distance: "cutoff_distance" => f32);
+    attr_get!(use_shadow: "use_shadow" => bool);
+}
+
+bpy_str_enum!{
+    pub enum LightKind {
+        Point = "POINT",
+        Sun = "SUN",
+        Spot = "SPOT",
+        Area = "AREA"
+    }
+}
+
+bpy_struct_wrapper!(Camera);
+impl<'py> Camera<'py> {
+    attr_get!(r#type: "type" => CameraKind);
+    attr_get!(angle: "angle" => f32);
+    attr_get!(clip_start: "clip_start" => f32);
+    attr_get!(clip_end: "clip_end" => f32);
+    attr_get!(sensor_width: "sensor_width" => f32);
+    attr_get!(sensor_height: "sensor_height" => f32);
+}
+
+bpy_str_enum!{
+    pub enum CameraKind {
+        Perspective = "PERSP",
+        Orthographic = "ORTHO",
+        Panoramic = "PANO"
+    }
+}
+
+pub struct Bone<'py>(Bound<'py, PyAny>);
 impl<'py> Bone<'py> {
-    pub fn wrap(r: &'py PyAny) -> Self {
+    pub fn wrap(r: Bound<'py, PyAny>) -> Self {
         Self(r)
     }
     attr_get!(name: "name" => &str );
@@ -372,8 +551,8 @@ impl<'py> Bone<'py> {
     attr_get!(length: "length" => f32);
 }
 impl<'py> FromPyObject<'py> for Bone<'py> {
-    fn extract(ob: &'py PyAny) -> PyResult<Self> {
-        Ok(Self::wrap(ob))
+    fn extract(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        Ok(Self::wrap(ob.clone()))
     }
 }
 
@@ -385,6 +564,29 @@ impl<'py> Armature<'py> {
 }
 bpy_collection!(ArmatureBones, 'arraydict Bone<'py>);
 
+bpy_struct_wrapper!(AnimData);
+impl<'py> AnimData<'py> {
+    attr_get!(action: "action" => Option<Action<'py>>);
+}
+
+bpy_struct_wrapper!(Action);
+impl<'py> Action<'py> {
+    attr_get!(frame_range: "frame_range" => (f32, f32) as frame_range_from_bpy);
+}
+
+bpy_struct_wrapper!(Pose);
+impl<'py> Pose<'py> {
+    attr_get!(bones: "bones" => PoseBones<'py>);
+}
+bpy_collection!(PoseBones, 'arraydict PoseBone<'py>);
+
+bpy_struct_wrapper!(PoseBone);
+impl<'py> PoseBone<'py> {
+    attr_get!(name: "name" => &'py str);
+    attr_get!(parent: "parent" => Option<PoseBone<'py>>);
+    attr_get!(matrix: "matrix" => vek::Mat4<f32> as mat4_from_bpy_matrix);
+}
+
 bpy_struct_wrapper!(Mesh);
 impl<'py> Mesh<'py> {
     pub fn calc_tangents(&self) -> PyResult<()> {
@@ -396,7 +598,8 @@ impl<'py> Mesh<'py> {
     attr_get!(loop_triangles: "loop_triangles" => MeshLoopTriangles);
     attr_get!(attributes: "attributes" => AttributeGroup);
     attr_get!(uv_layers: "uv_layers" => UvLoopLayers);
-    attr_get!(diesel_settings: "diesel" => &'py PyAny);
+    attr_get!(diesel_settings: "diesel" => Bound<'py, PyAny>);
+    attr_get!(edges: "edges" => MeshEdges);
     iter_get!(iter_vertices: "vertices" => MeshVertex);
 
     method!(calc_loop_triangles: "calc_loop_triangles"());
@@ -404,12 +607,20 @@ impl<'py> Mesh<'py> {
 }
 
 bpy_collection!(MeshVertices, 'array MeshVertex<'py>);
+impl<'py> MeshVertices<'py> {
+    /// Every vertex's `co`, read in one `foreach_get` round trip rather than
+    /// one [`MeshVertex::co`] per vertex - see [`foreach_get_pod`].
+    pub fn co_pod(&self) -> PyResult<PodBuffer<[f32; 3]>> {
+        Ok(PodBuffer(foreach_get_pod(self.as_pyany(), "co", self.len())?))
+    }
+}
 bpy_collection!(MeshVertexGroups, 'array VertexGroupElement<'py>);
 bpy_collection!(MeshLoops, 'array MeshLoop<'py>);
 bpy_collection!(MeshPolygons, 'array MeshPolygon<'py>);
 bpy_collection!(MeshLoopTriangles, 'array MeshLoopTriangle<'py>);
 bpy_collection!(AttributeGroup, 'array Attribute<'py>);
 bpy_collection!(UvLoopLayers, 'array MeshUvLoopLayer<'py>);
+bpy_collection!(MeshEdges, 'array MeshEdge<'py>);
 
 bpy_struct_wrapper!(MeshVertex);
 impl<'py> MeshVertex<'py> {
@@ -433,6 +644,14 @@ impl<'py> MeshLoop<'py> {
 
 }
 
+bpy_struct_wrapper!(MeshEdge);
+impl<'py> MeshEdge<'py> {
+    attr_get!(vertices: "vertices" => [usize; 2] as from_bpy_array);
+    attr_get!(use_edge_sharp: "use_edge_sharp" => bool);
+    attr_get!(use_seam: "use_seam" => bool);
+    attr_get!(crease: "crease" => f32);
+}
+
 bpy_struct_wrapper!(MeshPolygon);
 impl<'py> MeshPolygon<'py> {
     attr_get!(loop_start: "loop_start" => usize);
@@ -462,28 +681,53 @@ impl<'py> Attribute<'py> {
     attr_get!(str_data: "data" => BpyCollection<AttributeScalarValue<&str>>);
     attr_get!(f32_color_data: "data" => BpyCollection<AttributeColorValue>);
     attr_get!(u8_color_data: "data" => BpyCollection<AttributeColorValue>);
+
+    /// Bulk-reads this attribute's values via [`foreach_get_pod`] instead of
+    /// one Python object per element, or `None` if `data_type` isn't one
+    /// Blender exposes as floats (`STRING`, `INT`, `INT8`, `BOOLEAN`), which
+    /// have to fall back to the per-element accessors above.
+    pub fn pod_data(&self) -> PyResult<Option<AttributePod>> {
+        let data = self.as_pyany().getattr(intern!(self.py(), "data"))?;
+        let count = data.len()?;
+        Ok(match self.data_type() {
+            AttributeType::F32 => Some(AttributePod::F32(PodBuffer(foreach_get_pod(&data, "value", count)?))),
+            AttributeType::Vec2f => Some(AttributePod::Vec2f(PodBuffer(foreach_get_pod(&data, "vector", count)?))),
+            AttributeType::Vec3f => Some(AttributePod::Vec3f(PodBuffer(foreach_get_pod(&data, "vector", count)?))),
+            AttributeType::FloatColor | AttributeType::ByteColor => Some(AttributePod::Color(PodBuffer(foreach_get_pod(&data, "color", count)?))),
+            AttributeType::I32 | AttributeType::I8 | AttributeType::Bool | AttributeType::String => None,
+        })
+    }
 }
 
-#[derive(Copy, Clone, WrapsPyAny)]
-pub struct AttributeScalarValue<'py,T>(&'py PyAny, PhantomData<T>);
+/// The bulk-read shapes [`Attribute::pod_data`] can return, one per
+/// float-backed [`AttributeType`].
+pub enum AttributePod {
+    F32(PodBuffer<f32>),
+    Vec2f(PodBuffer<[f32; 2]>),
+    Vec3f(PodBuffer<[f32; 3]>),
+    Color(PodBuffer<[f32; 4]>),
+}
+
+#[derive(Clone, WrapsPyAny)]
+pub struct AttributeScalarValue<'py,T>(Bound<'py, PyAny>, PhantomData<T>);
 impl<'py, T: FromPyObject<'py>> AttributeScalarValue<'py, T> {
     attr_get!(value: "value" => T);
 }
 
-#[derive(Copy, Clone, WrapsPyAny)]
-pub struct AttributeColorValue<'py>(&'py PyAny);
+#[derive(Clone, WrapsPyAny)]
+pub struct AttributeColorValue<'py>(Bound<'py, PyAny>);
 impl<'py> AttributeColorValue<'py> {
     attr_get!(value: "color" => Rgbaf as from_bpy_array);
 }
 
-#[derive(Copy, Clone, WrapsPyAny)]
-pub struct AttributeVek2fValue<'py>(&'py PyAny);
+#[derive(Clone, WrapsPyAny)]
+pub struct AttributeVek2fValue<'py>(Bound<'py, PyAny>);
 impl<'py> AttributeVek2fValue<'py> {
     attr_get!(value: "vector" => Vec2f as vek2f_from_bpy_vec);
 }
 
-#[derive(Copy, Clone, WrapsPyAny)]
-pub struct AttributeVek3fValue<'py>(&'py PyAny);
+#[derive(Clone, WrapsPyAny)]
+pub struct AttributeVek3fValue<'py>(Bound<'py, PyAny>);
 impl<'py> AttributeVek3fValue<'py> {
     attr_get!(value: "vector" => Vec3f as vek3f_from_bpy_vec);
 }
@@ -512,11 +756,19 @@ bpy_str_enum! {
     }
 }
 
-#[derive(Copy, Clone, WrapsPyAny)]
-pub struct MeshUvLoopLayer<'py>(&'py PyAny);
+#[derive(Clone, WrapsPyAny)]
+pub struct MeshUvLoopLayer<'py>(Bound<'py, PyAny>);
 impl<'py> MeshUvLoopLayer<'py> {
     attr_get!(name: "name" => &str);
     attr_get!(uv: "uv" => BpyCollection<AttributeVek2fValue>);
+
+    /// This layer's UVs, read in one `foreach_get` round trip rather than
+    /// one [`AttributeVek2fValue::value`] per loop - see [`foreach_get_pod`].
+    pub fn uv_pod(&self) -> PyResult<PodBuffer<[f32; 2]>> {
+        let data = self.as_pyany().getattr(intern!(self.py(), "uv"))?;
+        let count = data.len()?;
+        Ok(PodBuffer(foreach_get_pod(&data, "vector", count)?))
+    }
 }
 
 bpy_struct_wrapper!(Modifier);
@@ -530,7 +782,7 @@ impl<'py> Modifier<'py> {
     pub fn try_into_armature(self) -> Option<ArmatureModifier<'py>> {
         let typ: &str = get!(self.as_pyany(), 'attr "type");
         if typ == "ARMATURE" {
-            Some(ArmatureModifier::wrap(self.as_pyany()))
+            Some(ArmatureModifier::wrap(self.as_pyany().clone()))
         }
         else {
             None
@@ -552,13 +804,13 @@ impl<'py> ArmatureModifier<'py> {
 
 
 pub mod bmesh {
-    use pyo3::{intern, prelude::*};
+    use pyo3::{intern, prelude::*, Bound};
 
     pub fn new<'py>(py: Python<'py>) -> PyResult<BMesh<'py>> {
         BMesh::new(py)
     }
 
-    pub struct BMesh<'py>(&'py PyAny, Python<'py>);
+    pub struct BMesh<'py>(Bound<'py, PyAny>, Python<'py>);
     impl Drop for BMesh<'_> {
         fn drop(&mut self) {
             match self.0.call_method0(intern!{self.1, "free"}) {
@@ -574,17 +826,30 @@ pub mod bmesh {
                 .map(|bm| BMesh(bm, py))
         }
         pub fn free(self) { }
-        pub fn from_mesh(&self, mesh: &'py PyAny) -> PyResult<()> {
+        pub fn from_mesh(&self, mesh: &Bound<'py, PyAny>) -> PyResult<()> {
             self.0.call_method1(intern!{self.1, "from_mesh"}, (mesh,))
                 .map(|_|())
         }
-        pub fn faces(&self) -> PyResult<&'py PyAny> {
+        pub fn faces(&self) -> PyResult<Bound<'py, PyAny>> {
             self.0.getattr(intern!{self.1, "faces"})
         }
-        pub fn to_mesh(&self, mesh: &'py PyAny) -> PyResult<()> {
+        pub fn verts(&self) -> PyResult<Bound<'py, PyAny>> {
+            self.0.getattr(intern!{self.1, "verts"})
+        }
+        pub fn to_mesh(&self, mesh: &Bound<'py, PyAny>) -> PyResult<()> {
             self.0.call_method1(intern!{self.1, "to_mesh"}, (mesh,))
                 .map(|_|())
         }
+        /// Adds a single vertex at `co`, for building up geometry from scratch rather than
+        /// importing it from an existing mesh via [`BMesh::from_mesh`].
+        pub fn new_vert(&self, co: (f32, f32, f32)) -> PyResult<Bound<'py, PyAny>> {
+            self.verts()?.call_method1(intern!{self.1, "new"}, (co,))
+        }
+        /// Adds a face spanning `verts`, in winding order. `verts` must already have been added
+        /// with [`BMesh::new_vert`].
+        pub fn new_face(&self, verts: &[Bound<'py, PyAny>]) -> PyResult<Bound<'py, PyAny>> {
+            self.faces()?.call_method1(intern!{self.1, "new"}, (verts.to_vec(),))
+        }
     }
     impl IntoPy<pyo3::Py<pyo3::PyAny>> for BMesh<'_> {
         fn into_py(self, _py: Python<'_>) -> pyo3::Py<pyo3::PyAny> {
@@ -593,20 +858,20 @@ pub mod bmesh {
     }
     impl IntoPy<pyo3::Py<pyo3::PyAny>> for &BMesh<'_> {
         fn into_py(self, _py: Python<'_>) -> pyo3::Py<pyo3::PyAny> {
-            self.0.into()
+            self.0.clone().into()
         }
     }
 
-    #[derive(Clone, Copy)]
-    pub struct Ops<'py>(&'py PyModule, Python<'py>);
+    #[derive(Clone)]
+    pub struct Ops<'py>(Py<PyModule>, Python<'py>);
     impl<'py> Ops<'py> {
         pub fn import(py: Python<'py>) -> Self {
-            Self(py.import("bmesh.ops").unwrap(), py)
+            Self(py.import("bmesh.ops").unwrap().into(), py)
         }
-        pub fn triangulate(&self, mesh: &'py BMesh<'py>, faces: &'py PyAny) -> PyResult<&PyAny> {
+        pub fn triangulate(&self, mesh: &BMesh<'py>, faces: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
             let args = pyo3::types::PyDict::new(self.1);
             args.set_item("faces", faces).unwrap();
-            self.0.call_method(intern!{self.1, "triangulate"}, (mesh,), Some(args))
+            self.0.bind(self.1).call_method(intern!{self.1, "triangulate"}, (mesh,), Some(&args))
         }
     }
 }
@@ -615,6 +880,6 @@ bpy_struct_wrapper!(BMathMatrix);
 impl<'py> BMathMatrix<'py> {
     pub fn to_quaternion(&self) -> Quaternion {
         let quat = self.as_pyany().call_method0(intern!{self.py(), "to_quaternion"}).unwrap();
-        quaternion_from_bpy_quat(quat)
+        quaternion_from_bpy_quat(&quat)
     }
 }
\ No newline at end of file
@@ -7,6 +7,7 @@ use model_ir::*;
 type Vec2f = vek::Vec2<f32>;
 type Vec3f = vek::Vec3<f32>;
 type Vec4f = vek::Vec4<f32>;
+type Transform = vek::Transform<f32, f32, f32>;
 
 macro_rules! get {
     ($env:expr, $ob:expr, 'attr $field:literal) => {
@@ -39,6 +40,24 @@ fn vek3f_from_bpy_vec(env: &PyEnv, data: &PyAny) -> Vec3f {
     vek3f_from_tuple(tuple)
 }
 
+fn quaternion_from_bpy_quat(env: &PyEnv, data: &PyAny) -> vek::Quaternion<f32> {
+    let x: f32 = get!(env, data, 'attr "x");
+    let y: f32 = get!(env, data, 'attr "y");
+    let z: f32 = get!(env, data, 'attr "z");
+    let w: f32 = get!(env, data, 'attr "w");
+    vek::Quaternion::from_xyzw(x, y, z, w)
+}
+
+fn transform_from_bpy_matrix(env: &PyEnv, bmat: &PyAny) -> Transform {
+    let py_lrs = bmat.call_method0(intern!{env.python, "decompose"}).unwrap();
+    let (py_loc, py_rot, py_scale): (&PyAny, &PyAny, &PyAny) = py_lrs.extract().unwrap();
+    Transform {
+        position: vek3f_from_bpy_vec(env, py_loc),
+        orientation: quaternion_from_bpy_quat(env, py_rot),
+        scale: vek3f_from_bpy_vec(env, py_scale)
+    }
+}
+
 fn mat4_from_bpy_matrix(bmat: &PyAny) -> vek::Mat4<f32> {
     let mut floats = [[0f32; 4]; 4];
     for c in 0..4 {
@@ -63,14 +82,45 @@ where
     T::from(a)
 }
 
+/// Holds the dependency-graph-evaluated, modifier-applied mesh for `object` for as long as
+/// this value lives, and frees it (via `to_mesh_clear`) on drop.
+struct EvaluatedMesh<'py> {
+    python: Python<'py>,
+    evaluated_object: &'py PyAny,
+    data: &'py PyAny,
+}
+impl<'py> EvaluatedMesh<'py> {
+    fn new(env: &PyEnv<'py>, object: &'py PyAny) -> EvaluatedMesh<'py> {
+        let depsgraph = env.b_c_evaluated_depsgraph_get().unwrap();
+        let evaluated_object = object.call_method1(intern!{env.python, "evaluated_get"}, (depsgraph,)).unwrap();
+        let data = evaluated_object.call_method0(intern!{env.python, "to_mesh"}).unwrap();
+        EvaluatedMesh { python: env.python, evaluated_object, data }
+    }
+}
+impl<'py> Drop for EvaluatedMesh<'py> {
+    fn drop(&mut self) {
+        // The worst that can happen here is we leak memory.
+        let _ = self.evaluated_object.call_method0(intern!{self.python, "to_mesh_clear"});
+    }
+}
+
 pub fn mesh_from_bpy_mesh(env: &PyEnv, data: &PyAny) -> model_ir::Mesh {
     let vertices = get!(env, data, 'iter "vertices")
         .map(|vtx| vek3f_from_bpy_vec(env, get!(env, vtx, 'attr "co")))
         .collect();
 
-    //let edges = get!(env, data, 'iter "edges")
-    //    .map(|ed| get!(env, ed, 'attr "vertices"))
-    //    .collect();
+    let edges = get!(env, data, 'iter "edges")
+        .map(|ed| {
+            let verts: [usize; 2] = from_bpy_array(get!(env, ed, 'attr "vertices"));
+            Edge {
+                a: verts[0],
+                b: verts[1],
+                sharp: get!(env, ed, 'attr "use_edge_sharp"),
+                seam: get!(env, ed, 'attr "use_seam"),
+                crease: get!(env, ed, 'attr "crease"),
+            }
+        })
+        .collect();
 
     let faceloops = get!(env, data, 'iter "loops")
         .map(|lp| Faceloop {
@@ -141,7 +191,7 @@ pub fn mesh_from_bpy_mesh(env: &PyEnv, data: &PyAny) -> model_ir::Mesh {
 
     Mesh {
         vertices,
-        edges: Vec::new(),
+        edges,
         faceloops,
         polygons,
         triangles,
@@ -191,44 +241,64 @@ fn vgroups_from_bpy_verts(env: &PyEnv, data: &PyAny) -> VertexGroups {
     out
 }
 
-fn gather_object_data(env: &PyEnv, object: &PyAny, out: &mut Scene) -> ObjectData {
-    match get!(env, object, 'attr "type") {
-        "MESH" => ObjectData::Mesh(mesh_from_bpy_object(env, object, get!(env, object, 'attr "data"))),
-        "EMPTY" => ObjectData::None,
-        _ => todo!()
-    }
+/// A parent an object can be resolved against: either another object, or a
+/// named bone belonging to an armature object.
+#[derive(Hash, PartialEq, Eq, Debug)]
+enum BpyParent {
+    Object(*mut pyo3::ffi::PyObject),
+    Bone(*mut pyo3::ffi::PyObject, String)
 }
 
 struct SceneBuilder<'py> {
     env: &'py PyEnv<'py>,
     scene: Scene,
-    bpy_obj_to_oid: HashMap<*mut pyo3::ffi::PyObject, ObjectKey>,
-    oid_to_bpy_parent: HashMap<ObjectKey, *mut pyo3::ffi::PyObject>
+    flags: ExportFlags,
+    bpy_parent_to_oid_parent: HashMap<BpyParent, ObjectKey>,
+    child_oid_to_bpy_parent: HashMap<ObjectKey, BpyParent>,
+
+    /// (being_skinned, skeleton, model_to_mid)
+    skin_requests: Vec<(ObjectKey, *mut pyo3::ffi::PyObject, vek::Mat4<f32>)>
 }
 
-impl<'py> SceneBuilder<'py> 
+impl<'py> SceneBuilder<'py>
 {
     fn new(env: &'py PyEnv) -> SceneBuilder<'py> {
         SceneBuilder {
             env,
             scene: Scene::default(),
-            bpy_obj_to_oid: HashMap::new(),
-            oid_to_bpy_parent: HashMap::new()
+            flags: ExportFlags::empty(),
+            bpy_parent_to_oid_parent: HashMap::new(),
+            child_oid_to_bpy_parent: HashMap::new(),
+            skin_requests: Vec::new()
         }
     }
 
     fn set_scale(&mut self, meters_per_unit: f32) { self.scene.meters_per_unit = meters_per_unit }
+    fn set_flags(&mut self, flags: ExportFlags) { self.flags = flags }
     fn set_active_object(&mut self, active_object: ObjectKey) {
         self.scene.active_object = Some(active_object)
     }
-    
+
     fn add_bpy_object(&mut self, object: &PyAny) -> ObjectKey {
-        let odata = match get!(self.env, object, 'attr "type") {
+        // If this is an armature, we have to worry about bone-parented and skinned children.
+        // Children whose parent_type is BONE are parented to a bone.
+        // Children whose parent type is OBJECT but have an Armature Deform modifier are skinned.
+        // Children whose parent_type is ARMATURE just act like that.
+
+        let otype: &str = get!(self.env, object, 'attr "type");
+        let odata = match otype {
             "MESH" => {
-                let data = get!(self.env, object, 'attr "data");
-                ObjectData::Mesh(mesh_from_bpy_object(self.env, object, data))
+                ObjectData::Mesh(if self.flags.contains(ExportFlag::Modifiers) {
+                    let evaluated = EvaluatedMesh::new(self.env, object);
+                    mesh_from_bpy_object(self.env, object, evaluated.data)
+                }
+                else {
+                    let data = get!(self.env, object, 'attr "data");
+                    mesh_from_bpy_object(self.env, object, data)
+                })
             },
             "EMPTY" => ObjectData::None,
+            "ARMATURE" => ObjectData::Armature(self.add_bpy_armature_bones(object)),
             _ => todo!()
         };
 
@@ -236,31 +306,131 @@ impl<'py> SceneBuilder<'py>
             name: get!(self.env, object, 'attr "name"),
             parent: None,
             children: Vec::new(),
-            transform: mat4_from_bpy_matrix(get!(self.env, object, 'attr "matrix_local")),
+            transform: transform_from_bpy_matrix(self.env, get!(self.env, object, 'attr "matrix_local")),
             in_collections: Vec::new(),
-            data: gather_object_data(self.env, object, &mut self.scene),
+            data: odata,
+            skin_role: if otype == "ARMATURE" { SkinRole::Armature } else { SkinRole::None }
         };
         let oid = self.scene.objects.insert(new_obj);
 
-        self.bpy_obj_to_oid.insert(object.as_ptr(), oid);
-        let parent = object.getattr(intern!{self.env.python, "parent"}).unwrap();
+        self.bpy_parent_to_oid_parent.insert(BpyParent::Object(object.as_ptr()), oid);
+        let parent: &PyAny = get!(self.env, object, 'attr "parent");
         if !parent.is_none() {
-            self.oid_to_bpy_parent.insert(oid, parent.as_ptr());
+            let parent_type: &str = get!(self.env, object, 'attr "parent_type");
+            let pkey = match parent_type {
+                "OBJECT" => BpyParent::Object(parent.as_ptr()),
+                "BONE" => {
+                    let bone_name: String = get!(self.env, object, 'attr "parent_bone");
+                    if bone_name.is_empty() {
+                        BpyParent::Object(parent.as_ptr())
+                    }
+                    else {
+                        BpyParent::Bone(parent.as_ptr(), bone_name)
+                    }
+                },
+                "ARMATURE" => {
+                    let model_to_world = mat4_from_bpy_matrix(get!(self.env, object, 'attr "matrix_world"));
+                    self.skin_requests.push((oid, parent.as_ptr(), model_to_world));
+                    BpyParent::Object(parent.as_ptr())
+                },
+                _ => panic!("Unknown parent type {}", parent_type)
+            };
+            self.child_oid_to_bpy_parent.insert(oid, pkey);
         }
-        
+
+        for modifier in get!(self.env, object, 'iter "modifiers") {
+            let mtype: &str = get!(self.env, modifier, 'attr "type");
+            if mtype != "ARMATURE" { continue }
+
+            let skel: &PyAny = get!(self.env, modifier, 'attr "object");
+            if !skel.is_none() {
+                let model_to_world = mat4_from_bpy_matrix(get!(self.env, object, 'attr "matrix_world"));
+                self.skin_requests.push((oid, skel.as_ptr(), model_to_world));
+            }
+            break;
+        }
+
         oid
     }
+
+    fn add_bpy_armature_bones(&mut self, object: &PyAny) -> BindPoseKey {
+        let mut joints = Vec::new();
+
+        let data = get!(self.env, object, 'attr "data");
+        for bpy_bone in get!(self.env, data, 'iter "bones") {
+            // For some reason things being parented to bone tails *isn't* a display trick.
+            // Bones really are stored that way.
+            // So the position of a bone is its head position plus the parent's tail pos.
+            // And the rotation comes from the `matrix` property.
+            let bone_name: String = get!(self.env, bpy_bone, 'attr "name");
+            let head = vek3f_from_bpy_vec(self.env, get!(self.env, bpy_bone, 'attr "head"));
+            let bone_matrix: &PyAny = get!(self.env, bpy_bone, 'attr "matrix");
+            let bone_quat = bone_matrix.call_method0(intern!{self.env.python, "to_quaternion"}).unwrap();
+            let rot = quaternion_from_bpy_quat(self.env, bone_quat);
+            let parent: &PyAny = get!(self.env, bpy_bone, 'attr "parent");
+
+            let parent_tail = if parent.is_none() {
+                Vec3f::zero()
+            }
+            else {
+                vek3f_from_bpy_vec(self.env, get!(self.env, parent, 'attr "tail"))
+            };
+
+            let transform = Transform {
+                position: parent_tail + head,
+                orientation: rot,
+                scale: Vec3f::one()
+            };
+
+            let bone_obj = Object {
+                name: bone_name.clone(),
+                parent: None,
+                children: Vec::new(),
+                transform,
+                in_collections: Vec::new(),
+                data: ObjectData::None,
+                skin_role: SkinRole::Bone,
+            };
+
+            let bone_key = self.scene.objects.insert(bone_obj);
+            self.bpy_parent_to_oid_parent
+                .insert(BpyParent::Bone(object.as_ptr(), bone_name.clone()), bone_key);
+
+            if parent.is_none() {
+                self.child_oid_to_bpy_parent
+                    .insert(bone_key, BpyParent::Object(object.as_ptr()));
+            }
+            else {
+                let parent_name: String = get!(self.env, parent, 'attr "name");
+                self.child_oid_to_bpy_parent
+                    .insert(bone_key, BpyParent::Bone(object.as_ptr(), parent_name));
+            }
+
+            let bonespace_to_bindspace = mat4_from_bpy_matrix(get!(self.env, bpy_bone, 'attr "matrix_local"));
+            joints.push(BindJoint {
+                bone: bone_key,
+                bindspace_to_bonespace: bonespace_to_bindspace.inverted(),
+            });
+        }
+
+        let mid_to_bind = mat4_from_bpy_matrix(get!(self.env, object, 'attr "matrix_world")).inverted();
+
+        self.scene.bind_poses.insert(BindPose {
+            joints,
+            mid_to_bind,
+        })
+    }
 }
 
 impl From<SceneBuilder<'_>> for Scene {
     fn from(mut build: SceneBuilder) -> Self {
-        let mut parent_links = Vec::with_capacity(build.oid_to_bpy_parent.len());
+        let mut parent_links = Vec::with_capacity(build.child_oid_to_bpy_parent.len());
 
         for oid in build.scene.objects.keys() {
-            match build.oid_to_bpy_parent.get(&oid) {
+            match build.child_oid_to_bpy_parent.get(&oid) {
                 None => (),
                 Some(p) => {
-                    let parent_oid = build.bpy_obj_to_oid[p];
+                    let parent_oid = build.bpy_parent_to_oid_parent[p];
                     parent_links.push((oid, parent_oid));
                 },
             }
@@ -271,14 +441,45 @@ impl From<SceneBuilder<'_>> for Scene {
             build.scene.objects[parent].children.push(child);
         }
 
+        for (skinned, bpy_skeleton, model_to_mid) in &build.skin_requests {
+            let skeleton_oid = build.bpy_parent_to_oid_parent[&BpyParent::Object(*bpy_skeleton)];
+            let skeleton_obj = &build.scene.objects[skeleton_oid];
+            let skele_data = match skeleton_obj.data {
+                ObjectData::Armature(a) => &build.scene.bind_poses[a],
+                _ => panic!("Skin reference didn't reference an armature")
+            };
+
+            let joint_names = skele_data.joints.iter()
+                .map(|bj| build.scene.objects[bj.bone].name.as_ref())
+                .collect::<Vec<_>>();
+
+            let skinned_mesh = match &build.scene.objects[*skinned].data {
+                ObjectData::Mesh(me) => me,
+                _ => panic!("Tried to skin a non-mesh")
+            };
+
+            let vgroup_to_joint_mapping = skinned_mesh.vertex_groups.names.iter()
+                .map(|vgn| joint_names.iter().position(|jn| jn == vgn))
+                .map(|i| i.unwrap())
+                .collect::<Vec<_>>();
+
+            let skinned_mesh = match &mut build.scene.objects[*skinned].data {
+                ObjectData::Mesh(me) => me,
+                _ => panic!("Tried to skin a non-mesh")
+            };
+
+            skinned_mesh.skin = Some(SkinReference {
+                armature: skeleton_oid,
+                model_to_mid: *model_to_mid,
+                vgroup_to_joint_mapping,
+            })
+        }
+
         build.scene
     }
 }
 
-pub fn scene_from_bpy_selected(env: &PyEnv, data: &PyAny, meters_per_unit: f32) -> Scene {
-    let mut scene = Scene::default();
-    scene.meters_per_unit = meters_per_unit;
-
+pub fn scene_from_bpy_selected(env: &PyEnv, data: &PyAny, meters_per_unit: f32, flags: ExportFlags) -> Scene {
     // According to the manual, it's O(len(bpy.data.objects)) to use children or children_recusive
     // so we should do a pair of iterations instead of recursing ourselves
     // specifically once over children_recursive to grab everything,
@@ -286,9 +487,9 @@ pub fn scene_from_bpy_selected(env: &PyEnv, data: &PyAny, meters_per_unit: f32)
     //
     // The actual filling in is done in <Scene as From<SceneBuilder>>::from
 
-
     let mut scene = SceneBuilder::new(env);
     scene.set_scale(meters_per_unit);
+    scene.set_flags(flags);
 
     let active = scene.add_bpy_object(data);
     scene.set_active_object(active);
@@ -1,13 +1,16 @@
 use std::collections::{HashMap, BTreeMap};
 use std::rc::Rc;
 use pyo3::{prelude::*, intern};
+use pyo3::types::PyDict;
 use vek::Mat4;
-use crate::bpy::{PropCollection, WrapsPyAny};
+use crate::bpy::{DictPropCollection, PropCollection, WrapsPyAny};
 use crate::{ PyEnv, model_ir, bpy };
 use model_ir::*;
 
 type Vec3f = vek::Vec3<f32>;
 type Transform = vek::Transform<f32, f32, f32>;
+type Quaternion = vek::Quaternion<f32>;
+type Rgbaf = vek::Rgba<f32>;
 
 type PyObjPtr = *mut pyo3::ffi::PyObject;
 
@@ -29,9 +32,18 @@ fn mesh_from_bpy_mesh(data: bpy::Mesh) -> model_ir::Mesh {
         .map(|vtx| vtx.co())
         .collect();
 
-    //let edges = get!(env, data, 'iter "edges")
-    //    .map(|ed| get!(env, ed, 'attr "vertices"))
-    //    .collect();
+    let edges = data.edges().iter()
+        .map(|ed| {
+            let verts = ed.vertices();
+            Edge {
+                a: verts[0],
+                b: verts[1],
+                sharp: ed.use_edge_sharp(),
+                seam: ed.use_seam(),
+                crease: ed.crease(),
+            }
+        })
+        .collect();
 
     let faceloops = data.loops().iter()
         .map(|lp| Faceloop {
@@ -66,8 +78,23 @@ fn mesh_from_bpy_mesh(data: bpy::Mesh) -> model_ir::Mesh {
     let mut faceloop_colors = BTreeMap::new();
     let mut faceloop_uvs = BTreeMap::new();
 
+    let mut custom_float_attributes = BTreeMap::new();
+    let mut custom_int_attributes = BTreeMap::new();
+    let mut custom_bool_attributes = BTreeMap::new();
+    let mut custom_vec2_attributes = BTreeMap::new();
+    let mut custom_vec3_attributes = BTreeMap::new();
+
     for att in data.attributes() {
         use bpy::{AttributeDomain as AD, AttributeType as AT};
+        // Geometry-nodes attributes can live on splines/instances too, but those domains have
+        // no corresponding element in the IR mesh at all, so there's nowhere to put them.
+        let domain = match att.domain() {
+            AD::Point => AttributeDomain::Point,
+            AD::Edge => AttributeDomain::Edge,
+            AD::Face => AttributeDomain::Face,
+            AD::Faceloop => AttributeDomain::Faceloop,
+            AD::Spline | AD::Instance => continue,
+        };
         match (att.domain(), att.data_type()) {
             (AD::Point, AT::ByteColor) |
             (AD::Point, AT::FloatColor) => {
@@ -79,6 +106,26 @@ fn mesh_from_bpy_mesh(data: bpy::Mesh) -> model_ir::Mesh {
                 let data = att.f32_color_data().iter().map(|i| i.value()).collect();
                 faceloop_colors.insert(att.name().to_owned(), data);
             },
+            (_, AT::F32) => {
+                let data = att.f32_data().iter().map(|i| i.value()).collect();
+                custom_float_attributes.insert(att.name().to_owned(), CustomAttribute { domain, data });
+            },
+            (_, AT::I32) => {
+                let data = att.i32_data().iter().map(|i| i.value()).collect();
+                custom_int_attributes.insert(att.name().to_owned(), CustomAttribute { domain, data });
+            },
+            (_, AT::Bool) => {
+                let data = att.bool_data().iter().map(|i| i.value()).collect();
+                custom_bool_attributes.insert(att.name().to_owned(), CustomAttribute { domain, data });
+            },
+            (_, AT::Vec2f) => {
+                let data = att.vec2f_data().iter().map(|i| i.value()).collect();
+                custom_vec2_attributes.insert(att.name().to_owned(), CustomAttribute { domain, data });
+            },
+            (_, AT::Vec3f) => {
+                let data = att.vec3f_data().iter().map(|i| i.value()).collect();
+                custom_vec3_attributes.insert(att.name().to_owned(), CustomAttribute { domain, data });
+            },
             (_,_) => continue
         };
     }
@@ -114,7 +161,7 @@ fn mesh_from_bpy_mesh(data: bpy::Mesh) -> model_ir::Mesh {
 
     Mesh {
         vertices,
-        edges: Vec::new(),
+        edges,
         faceloops,
         polygons,
         triangles,
@@ -123,6 +170,11 @@ fn mesh_from_bpy_mesh(data: bpy::Mesh) -> model_ir::Mesh {
         tangents,
         faceloop_colors,
         faceloop_uvs,
+        custom_float_attributes,
+        custom_int_attributes,
+        custom_bool_attributes,
+        custom_vec2_attributes,
+        custom_vec3_attributes,
         material_names: Vec::new(),
         material_ids: Vec::new(),
         diesel,
@@ -130,6 +182,81 @@ fn mesh_from_bpy_mesh(data: bpy::Mesh) -> model_ir::Mesh {
     }
 }
 
+fn light_from_bpy_light(data: bpy::Light) -> model_ir::Light {
+    let kind = data.r#type();
+    let (spot_angle, spot_blend) = match kind {
+        bpy::LightKind::Spot => (data.spot_size(), data.spot_blend()),
+        _ => (0.0, 0.0)
+    };
+
+    model_ir::Light {
+        kind: match kind {
+            bpy::LightKind::Point => LightKind::Point,
+            bpy::LightKind::Sun => LightKind::Sun,
+            bpy::LightKind::Spot => LightKind::Spot,
+            bpy::LightKind::Area => LightKind::Area,
+        },
+        color: data.color(),
+        energy: data.energy(),
+        spot_angle,
+        spot_blend,
+        range: data.cutoff_distance(),
+        cast_shadows: data.use_shadow(),
+    }
+}
+
+fn camera_from_bpy_camera(data: bpy::Camera) -> model_ir::Camera {
+    let kind = data.r#type();
+
+    model_ir::Camera {
+        kind: match kind {
+            bpy::CameraKind::Perspective => CameraKind::Perspective,
+            bpy::CameraKind::Orthographic => CameraKind::Orthographic,
+            bpy::CameraKind::Panoramic => CameraKind::Panoramic,
+        },
+        fov: data.angle(),
+        near_clip: data.clip_start(),
+        far_clip: data.clip_end(),
+        aspect_ratio: data.sensor_width() / data.sensor_height(),
+    }
+}
+
+/// Reads the `diesel_collision` PropertyGroup off an Empty, mirroring the SWBF addon's
+/// `msh_collision_prim_properties`, and converts it into an IR [`Collision`].
+///
+/// Falls back to deriving the dimensions from `empty_display_size` and the object's own scale
+/// when the PropertyGroup doesn't specify an explicit radius, so collision primitives sized
+/// purely by dragging the empty's display gizmo around still round-trip sensibly.
+fn collision_from_bpy_object(object: &bpy::Object) -> Option<Collision> {
+    let bpy_collision = object.diesel_collision();
+    if bpy_collision.is_none() {
+        return None;
+    }
+
+    let shape: String = get!(bpy_collision, 'attr "shape");
+    let shape = match shape.as_str() {
+        "BOX" => CollisionShape::Box,
+        "CYLINDER" => CollisionShape::Cylinder,
+        _ => CollisionShape::Sphere,
+    };
+
+    let mut radius: f32 = get!(bpy_collision, 'attr "radius");
+    let bpy_extent = bpy_collision.getattr(intern!{bpy_collision.py(), "extent"}).unwrap();
+    let mut extent = Vec3f::new(
+        bpy_extent.get_item(0).unwrap().extract().unwrap(),
+        bpy_extent.get_item(1).unwrap().extract().unwrap(),
+        bpy_extent.get_item(2).unwrap().extract().unwrap(),
+    );
+
+    if radius <= 0.0 {
+        let scale = object.matrix_local().scale;
+        radius = object.empty_display_size() * scale.x;
+        extent = Vec3f::new(radius, radius, object.empty_display_size() * scale.z);
+    }
+
+    Some(Collision { shape, radius, extent })
+}
+
 fn vgroups_from_bpy_verts(data: &bpy::Mesh) -> VertexGroups {
     let bpy_verts = data.vertices();
     let vlen = bpy_verts.len();
@@ -171,13 +298,14 @@ impl<'py> TemporaryMesh<'py> {
         //    modifier.show_viewport = False
         let mut armature_modifiers = Vec::<(bpy::ArmatureModifier, bool)>::new();
         for mo in armature_modifiers_of(&object) {
-            armature_modifiers.push((mo, mo.show_viewport()));
+            let vis = mo.show_viewport();
             mo.set_show_viewport(false);
+            armature_modifiers.push((mo, vis));
         }
 
-        let depsgraph = env.b_c_evaluated_depsgraph_get().unwrap();
-        let evaluated_obj = object.evaluated_get(depsgraph);
-        let mesh = evaluated_obj.to_mesh(true, depsgraph);
+        let depsgraph = env.b_c_evaluated_depsgraph_get().unwrap().as_borrowed().to_owned();
+        let evaluated_obj = object.evaluated_get(&depsgraph);
+        let mesh = evaluated_obj.to_mesh(true, &depsgraph);
 
         if mesh.as_pyany().getattr(intern!(mesh.py(), "uv_layers")).unwrap().len().unwrap() > 0 {
             // Calculate the tangents here, because this can fail if the mesh still has ngons,
@@ -188,7 +316,7 @@ impl<'py> TemporaryMesh<'py> {
                     let bm = bpy::bmesh::new(mesh.py()).unwrap();
                     bm.from_mesh(mesh.as_pyany()).unwrap();
                     let faces = bm.faces().unwrap();
-                    env.bmesh_ops.triangulate(&bm, faces).unwrap();
+                    env.bmesh_ops.triangulate(&bm, &faces).unwrap();
                     bm.to_mesh(mesh.as_pyany()).unwrap();
                     mesh.calc_tangents().unwrap();
                 },
@@ -232,12 +360,20 @@ struct SceneBuilder<'py> {
     bpy_mat_to_matid: HashMap<PyObjPtr, MaterialKey>,
     child_oid_to_bpy_parent: HashMap<ObjectKey, BpyParent>,
     bpy_parent_to_oid_parent: HashMap<BpyParent, ObjectKey>,
-    
+
     /// (being_skinned, skeleton, model_to_mid)
-    skin_requests: Vec<(ObjectKey, PyObjPtr, Mat4<f32>)>
+    skin_requests: Vec<(ObjectKey, PyObjPtr, Mat4<f32>)>,
+
+    /// Every real Blender object gathered so far (i.e. not a synthesized bone `Object`), paired
+    /// with its key, so [`SceneBuilder::sample_animation`] knows what to re-evaluate each frame.
+    animatable_objects: Vec<(bpy::Object<'py>, ObjectKey)>,
+
+    /// Every armature object gathered so far, paired with its `BindPose` key, so
+    /// [`SceneBuilder::sample_bone_animation`] knows which ones to bake a pose-bone track for.
+    armatures: Vec<(bpy::Object<'py>, BindPoseKey)>
 }
 
-impl<'py> SceneBuilder<'py> 
+impl<'py> SceneBuilder<'py>
 {
     fn new(env: &'py PyEnv) -> SceneBuilder<'py> {
         SceneBuilder {
@@ -246,7 +382,9 @@ impl<'py> SceneBuilder<'py>
             bpy_mat_to_matid: HashMap::new(),
             bpy_parent_to_oid_parent: HashMap::new(),
             child_oid_to_bpy_parent: HashMap::new(),
-            skin_requests: Vec::new()
+            skin_requests: Vec::new(),
+            animatable_objects: Vec::new(),
+            armatures: Vec::new()
         }
     }
 
@@ -267,21 +405,33 @@ impl<'py> SceneBuilder<'py>
         let otype = object.r#type();
         let odata = match otype {
             bpy::ObjectType::Mesh => ObjectData::Mesh(self.add_bpy_mesh_instance(&object)),
-            bpy::ObjectType::Empty => ObjectData::None,
-            bpy::ObjectType::Armature => ObjectData::Armature(self.add_bpy_armature_bones(&object)),
+            bpy::ObjectType::Empty => collision_from_bpy_object(&object)
+                .map(ObjectData::Collision)
+                .unwrap_or(ObjectData::None),
+            bpy::ObjectType::Armature => {
+                let bind_pose = self.add_bpy_armature_bones(&object);
+                self.armatures.push((object.clone(), bind_pose));
+                ObjectData::Armature(bind_pose)
+            },
+            bpy::ObjectType::Light => ObjectData::Light(light_from_bpy_light(bpy::Light::wrap(object.data()))),
+            bpy::ObjectType::Camera => ObjectData::Camera(camera_from_bpy_camera(bpy::Camera::wrap(object.data()))),
             _ => todo!()
         };
 
+        let name = object.name();
         let new_obj = Object {
-            name: object.name().into(),
+            name: name.into(),
+            name_hash: pd2tools_rust::diesel_hash::from_str(name),
             parent: None,
             children: Vec::new(),
             transform: object.matrix_local(),
             in_collections: Vec::new(),
             data: odata,
-            skin_role: if otype == bpy::ObjectType::Armature { SkinRole::Armature } else { SkinRole::None }
+            skin_role: if otype == bpy::ObjectType::Armature { SkinRole::Armature } else { SkinRole::None },
+            animation: None,
         };
         let oid = self.scene.objects.insert(new_obj);
+        self.animatable_objects.push((object.clone(), oid));
 
         self.bpy_parent_to_oid_parent.insert(BpyParent::Object(object.as_ptr()), oid);
         let parent = object.parent();
@@ -319,7 +469,7 @@ impl<'py> SceneBuilder<'py>
 
     fn add_bpy_mesh_instance(&mut self, object: &bpy::Object<'py>) -> Mesh {
         let data = TemporaryMesh::from_depgraph(self.env, &object);
-        let mut mesh = mesh_from_bpy_mesh(*data);
+        let mut mesh = mesh_from_bpy_mesh(data.mesh.clone());
 
         mesh.vertex_groups.names = object.iter_vertex_groups()
             .map(|vg| vg.name().into() )
@@ -333,7 +483,7 @@ impl<'py> SceneBuilder<'py>
         mesh.material_names.extend(
             mats.iter()
             .map(|mat| {
-                mat.map(|m| Rc::from(m.name()))
+                mat.as_ref().map(|m| Rc::from(m.name()))
             })
         );
 
@@ -341,7 +491,7 @@ impl<'py> SceneBuilder<'py>
         mesh.material_ids.extend(
             mats.iter()
             .map(|mat| {
-                mat.map(|m| self.add_bpy_material(m))
+                mat.as_ref().map(|m| self.add_bpy_material(m.clone()))
             })
         );
 
@@ -353,8 +503,11 @@ impl<'py> SceneBuilder<'py>
             return self.bpy_mat_to_matid[&mat.as_ptr()]
         }
 
+        let name = mat.name();
         let new_mat = Material {
-            name: mat.name().into(),
+            name: name.into(),
+            name_hash: pd2tools_rust::diesel_hash::from_str(name),
+            ..Default::default()
         };
 
         self.scene.materials.insert(new_mat)
@@ -363,6 +516,14 @@ impl<'py> SceneBuilder<'py>
     fn add_bpy_armature_bones(&mut self, object: &bpy::Object<'py>) -> BindPoseKey {
         let mut joints = Vec::new();
 
+        // An "impure" skeleton - one gathered while posed away from rest, or whose pose
+        // diverges from its edit bones for some other reason - skins correctly only if the
+        // bind matrix reflects the bones' current evaluated pose, not their rest layout. Fall
+        // back to the rest `matrix_local` for bones with no evaluated `PoseBone` at all (no
+        // `pose`, or a bone the pose doesn't mention), which keeps unposed armatures working
+        // exactly as before.
+        let pose_bones = object.pose().map(|p| p.bones());
+
         let data = bpy::Armature::wrap(object.data());
         for bpy_bone in data.iter_bones() {
             // For some reason things being parented to bone tails *isn't* a display trick.
@@ -386,12 +547,14 @@ impl<'py> SceneBuilder<'py>
 
             let bone_obj = Object {
                 name: bone_name.to_owned(),
+                name_hash: pd2tools_rust::diesel_hash::from_str(bone_name),
                 parent: None,
                 children: Vec::new(),
                 transform,
                 in_collections: Vec::new(),
                 data: ObjectData::None,
                 skin_role: SkinRole::Bone,
+                animation: None,
             };
 
             let bone_key = self.scene.objects.insert(bone_obj);
@@ -409,7 +572,10 @@ impl<'py> SceneBuilder<'py>
                 }
             }
 
-            let bonespace_to_bindspace = bpy_bone.matrix_local();
+            let posed_matrix = pose_bones.as_ref()
+                .and_then(|bones| bones.get_key(bone_name))
+                .map(|pb| pb.matrix());
+            let bonespace_to_bindspace = bind_matrix_for_bone(posed_matrix, bpy_bone.matrix_local());
 
             joints.push(BindJoint {
                 bone: bone_key,
@@ -418,16 +584,237 @@ impl<'py> SceneBuilder<'py>
         }
 
         let mid_to_bind = object.matrix_world().inverted();
-        
+
         self.scene.bind_poses.insert(BindPose {
             joints,
             mid_to_bind,
         })
     }
+
+    /// Steps `bpy_scene`'s frame range one frame at a time, re-evaluating the depsgraph at each
+    /// one, and records every gathered object's local-space transform into `scene.animation`.
+    /// Bones aren't covered here: they have no `bpy.types.Object` of their own to re-evaluate,
+    /// so an armature's pose comes from [`Self::sample_bone_animation`] instead.
+    ///
+    /// Leaves every node's `animation` at `None` - i.e. exported as a single static bind pose -
+    /// if the scene only spans one frame, or once decimated a node's keys turn out to never
+    /// actually move.
+    fn sample_animation(&mut self, bpy_scene: bpy::Scene<'py>) {
+        let frame_start = bpy_scene.frame_start();
+        let frame_end = bpy_scene.frame_end();
+        let render = bpy_scene.render();
+        let framerate = render.fps() as f32 / render.fps_base();
+        self.scene.framerate = framerate;
+
+        if frame_end <= frame_start || self.animatable_objects.is_empty() {
+            return;
+        }
+
+        self.scene.start_time = frame_start as f32 / framerate;
+        self.scene.end_time = frame_end as f32 / framerate;
+
+        let original_frame = bpy_scene.frame_current();
+        let mut samples: HashMap<ObjectKey, Vec<(f32, Transform)>> = HashMap::new();
+
+        for frame in frame_start..=frame_end {
+            bpy_scene.frame_set(frame);
+            let depsgraph = self.env.b_c_evaluated_depsgraph_get().unwrap().as_borrowed().to_owned();
+            let time = frame as f32 / framerate;
+            for (object, key) in &self.animatable_objects {
+                let evaluated = object.evaluated_get(&depsgraph);
+                samples.entry(*key).or_default().push((time, evaluated.matrix_local()));
+            }
+        }
+        bpy_scene.frame_set(original_frame);
+
+        // A micrometer at Diesel's 1cm-per-unit scale, and a twentieth of a degree: tight enough
+        // that decimation can't introduce a visible wobble, loose enough to eat floating-point
+        // noise from repeatedly re-evaluating the depsgraph.
+        const POSITION_EPSILON: f32 = 1e-4;
+        const ROTATION_EPSILON: f32 = 1e-3;
+
+        for (key, samples) in samples {
+            let position = decimate_keys(
+                &samples.iter().map(|(t, tf)| (*t, tf.position)).collect::<Vec<_>>(),
+                POSITION_EPSILON,
+                |a, b, t| a + (b - a) * t,
+                |a, b| (a - b).magnitude(),
+            );
+            let rotation = decimate_keys(
+                &samples.iter().map(|(t, tf)| (*t, tf.orientation)).collect::<Vec<_>>(),
+                ROTATION_EPSILON,
+                |a, b, t| Quaternion::slerp(a, b, t.clamp(0.0, 1.0)),
+                |a, b| a.dot(b).abs().clamp(0.0, 1.0).acos(),
+            );
+
+            if position.len() > 1 || rotation.len() > 1 {
+                self.scene.objects[key].animation = Some(ObjectAnimation { position, rotation });
+            }
+        }
+    }
+
+    /// Bakes every gathered armature's driving action into per-bone `BoneAnimation` tracks on
+    /// `scene.bone_animations`. Unlike [`Self::sample_animation`], frames come from the action's
+    /// own `frame_range` rather than the scene's, since an armature is routinely keyed well
+    /// outside whatever range the scene happens to be scrubbed to; an armature with no
+    /// `animation_data.action` is simply left with a static bind pose.
+    ///
+    /// Each `pose_bone.matrix` is already in armature space, so a bone's local transform is
+    /// `parent.matrix⁻¹ · child.matrix` (or just `child.matrix` for a root bone) - the same
+    /// parent-relative convention [`Self::add_bpy_armature_bones`] uses for the rest pose. A
+    /// bone with no entry in the armature's `BindPose` (the action still targets one that's
+    /// since been deleted) is skipped for every frame rather than aborting the bake.
+    fn sample_bone_animation(&mut self, bpy_scene: bpy::Scene<'py>) {
+        let render = bpy_scene.render();
+        let framerate = render.fps() as f32 / render.fps_base();
+        let original_frame = bpy_scene.frame_current();
+
+        for (object, _) in self.armatures.clone() {
+            let Some(action) = object.animation_data().and_then(|ad| ad.action()) else { continue };
+            let Some(pose) = object.pose() else { continue };
+
+            let (start, end) = action.frame_range();
+            let frame_start = start.floor() as i32;
+            let frame_end = end.ceil() as i32;
+            if frame_end <= frame_start { continue; }
+
+            let mut samples: HashMap<ObjectKey, Vec<(f32, Transform)>> = HashMap::new();
+
+            for frame in frame_start..=frame_end {
+                bpy_scene.frame_set(frame);
+                let time = frame as f32 / framerate;
+
+                for pbone in pose.bones().iter() {
+                    let Some(&bone_key) = self.bpy_parent_to_oid_parent
+                        .get(&BpyParent::Bone(object.as_ptr(), pbone.name().to_owned()))
+                    else { continue };
+
+                    let child = pbone.matrix();
+                    let local = match pbone.parent() {
+                        Some(parent) => parent.matrix().inverted() * child,
+                        None => child,
+                    };
+                    samples.entry(bone_key).or_default().push((time, decompose_pose_matrix(local)));
+                }
+            }
+
+            const POSITION_EPSILON: f32 = 1e-4;
+            const ROTATION_EPSILON: f32 = 1e-3;
+            const SCALE_EPSILON: f32 = 1e-4;
+
+            for (key, samples) in samples {
+                let position = decimate_keys(
+                    &samples.iter().map(|(t, tf)| (*t, tf.position)).collect::<Vec<_>>(),
+                    POSITION_EPSILON,
+                    |a, b, t| a + (b - a) * t,
+                    |a, b| (a - b).magnitude(),
+                );
+                let rotation = decimate_keys(
+                    &samples.iter().map(|(t, tf)| (*t, tf.orientation)).collect::<Vec<_>>(),
+                    ROTATION_EPSILON,
+                    |a, b, t| Quaternion::slerp(a, b, t.clamp(0.0, 1.0)),
+                    |a, b| a.dot(b).abs().clamp(0.0, 1.0).acos(),
+                );
+                let scale = decimate_keys(
+                    &samples.iter().map(|(t, tf)| (*t, tf.scale)).collect::<Vec<_>>(),
+                    SCALE_EPSILON,
+                    |a, b, t| a + (b - a) * t,
+                    |a, b| (a - b).magnitude(),
+                );
+
+                if position.len() > 1 || rotation.len() > 1 || scale.len() > 1 {
+                    self.scene.bone_animations.insert(key, BoneAnimation { position, rotation, scale });
+                }
+            }
+        }
+
+        bpy_scene.frame_set(original_frame);
+    }
+}
+
+/// Splits a pose-bone matrix (already in armature space) into the loc/rot/scale form
+/// [`BoneAnimation`]'s tracks are stored as. A column that collapses to zero length (a
+/// degenerate scale) falls back to the corresponding basis vector rather than dividing by zero,
+/// matching how [`crate::model_reader_oil`]'s own matrix decomposition handles the same case.
+fn decompose_pose_matrix(m: Mat4<f32>) -> Transform {
+    let c0 = Vec3f::new(m.cols[0].x, m.cols[0].y, m.cols[0].z);
+    let c1 = Vec3f::new(m.cols[1].x, m.cols[1].y, m.cols[1].z);
+    let c2 = Vec3f::new(m.cols[2].x, m.cols[2].y, m.cols[2].z);
+    let position = Vec3f::new(m.cols[3].x, m.cols[3].y, m.cols[3].z);
+
+    let sx = c0.magnitude();
+    let sy = c1.magnitude();
+    let sz = c2.magnitude();
+    let r0 = if sx > 1e-8 { c0 / sx } else { Vec3f::unit_x() };
+    let r1 = if sy > 1e-8 { c1 / sy } else { Vec3f::unit_y() };
+    let r2 = if sz > 1e-8 { c2 / sz } else { Vec3f::unit_z() };
+
+    Transform {
+        position,
+        orientation: mat3_columns_to_quaternion(r0, r1, r2),
+        scale: Vec3f::new(sx, sy, sz),
+    }
+}
+
+/// Standard trace-based rotation-matrix-to-quaternion conversion, branching on whichever
+/// diagonal term is largest to avoid dividing by a near-zero square root.
+fn mat3_columns_to_quaternion(r0: Vec3f, r1: Vec3f, r2: Vec3f) -> Quaternion {
+    let trace = r0.x + r1.y + r2.z;
+    let q = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        Quaternion { w: 0.25 * s, x: (r1.z - r2.y) / s, y: (r2.x - r0.z) / s, z: (r0.y - r1.x) / s }
+    } else if r0.x > r1.y && r0.x > r2.z {
+        let s = (1.0 + r0.x - r1.y - r2.z).sqrt() * 2.0;
+        Quaternion { w: (r1.z - r2.y) / s, x: 0.25 * s, y: (r1.x + r0.y) / s, z: (r2.x + r0.z) / s }
+    } else if r1.y > r2.z {
+        let s = (1.0 + r1.y - r0.x - r2.z).sqrt() * 2.0;
+        Quaternion { w: (r2.x - r0.z) / s, x: (r1.x + r0.y) / s, y: 0.25 * s, z: (r2.y + r1.z) / s }
+    } else {
+        let s = (1.0 + r2.z - r0.x - r1.y).sqrt() * 2.0;
+        Quaternion { w: (r0.y - r1.x) / s, x: (r2.x + r0.z) / s, y: (r2.y + r1.z) / s, z: 0.25 * s }
+    };
+    q.normalized()
+}
+
+/// Drops any sample that lies within `epsilon` (per `distance`) of linearly interpolating
+/// (per `lerp`) between the last kept sample and the one right after it, so a channel that's
+/// perfectly static - or perfectly linear - collapses down to just its endpoints. If those
+/// endpoints turn out to be within `epsilon` of each other too, collapses further still to a
+/// single key, which is this function's way of saying "this never actually animates".
+pub(crate) fn decimate_keys<T: Copy>(
+    samples: &[(f32, T)],
+    epsilon: f32,
+    lerp: impl Fn(T, T, f32) -> T,
+    distance: impl Fn(T, T) -> f32,
+) -> Vec<(f32, T)> {
+    if samples.len() <= 1 {
+        return samples.to_vec();
+    }
+
+    let mut kept = vec![samples[0]];
+    for i in 1..(samples.len() - 1) {
+        let (t0, v0) = *kept.last().unwrap();
+        let (t1, v1) = samples[i];
+        let (t2, v2) = samples[i + 1];
+        let frac = if t2 > t0 { (t1 - t0) / (t2 - t0) } else { 0.0 };
+        let predicted = lerp(v0, v2, frac);
+        if distance(v1, predicted) > epsilon {
+            kept.push(samples[i]);
+        }
+    }
+    kept.push(*samples.last().unwrap());
+
+    if kept.len() == 2 && distance(kept[0].1, kept[1].1) <= epsilon {
+        kept.truncate(1);
+    }
+
+    kept
 }
 
-impl From<SceneBuilder<'_>> for Scene {
-    fn from(mut build: SceneBuilder) -> Self {
+impl TryFrom<SceneBuilder<'_>> for Scene {
+    type Error = model_ir::NameHashCollisionError;
+
+    fn try_from(mut build: SceneBuilder) -> Result<Self, Self::Error> {
         let mut parent_links = Vec::with_capacity(build.child_oid_to_bpy_parent.len());
 
         dbg!(&build.bpy_parent_to_oid_parent);
@@ -482,25 +869,26 @@ impl From<SceneBuilder<'_>> for Scene {
             })
         }
 
-        build.scene
+        build.scene.check_name_hash_collisions()?;
+        Ok(build.scene)
     }
 }
 
-pub fn scene_from_bpy_selected(env: &PyEnv, data: &PyAny, meters_per_unit: f32, default_author_tag: &str) -> Scene {
+pub fn scene_from_bpy_selected(env: &PyEnv, data: &PyAny, meters_per_unit: f32, default_author_tag: &str) -> PyResult<Scene> {
     // According to the manual, it's O(len(bpy.data.objects)) to use children or children_recusive
     // so we should do a pair of iterations instead of recursing ourselves
     // specifically once over children_recursive to grab everything,
     // and once over the grabbed objects to fill in the relations.
     //
-    // The actual filling in is done in <Scene as From<SceneBuilder>>::from
+    // The actual filling in is done in <Scene as TryFrom<SceneBuilder>>::try_from
 
 
     let mut scene = SceneBuilder::new(env);
     scene.set_scale(meters_per_unit);
 
-    let bpy_scene: &PyAny = get!(env.bpy_context, 'attr "scene");
+    let bpy_scene: &PyAny = get!(env.bpy_context(), 'attr "scene");
     let bpy_scene_diesel: &PyAny = get!(bpy_scene, 'attr "diesel");
-    let blend_data: &PyAny = get!(env.bpy_context, 'attr "blend_data");
+    let blend_data: &PyAny = get!(env.bpy_context(), 'attr "blend_data");
 
     if bpy_scene_diesel.is_none() {
         scene.set_diesel(DieselSceneSettings {
@@ -529,18 +917,370 @@ pub fn scene_from_bpy_selected(env: &PyEnv, data: &PyAny, meters_per_unit: f32,
     }
     
 
-    let data = bpy::Object::wrap(data);
-    let active = scene.add_bpy_object(data);
+    let data = bpy::Object::wrap(data.as_borrowed().to_owned());
+    let active = scene.add_bpy_object(data.clone());
     scene.set_active_object(active);
 
     for b_obj in data.iter_children_recursive() {
         scene.add_bpy_object(b_obj);
     }
 
-    scene.into() 
+    scene.sample_animation(bpy::Scene::wrap(bpy_scene.as_borrowed().to_owned()));
+    scene.sample_bone_animation(bpy::Scene::wrap(bpy_scene.as_borrowed().to_owned()));
+
+    Scene::try_from(scene).map_err(|e| pyo3::exceptions::PyException::new_err(e.to_string()))
+}
+
+/// A bone's bind matrix: its evaluated [`PoseBone`](bpy::PoseBone) matrix when the armature has
+/// one for it, falling back to the edit bone's rest `matrix_local` otherwise - the pure-data
+/// half of [`SceneBuilder::add_bpy_armature_bones`]'s impure-skeleton handling, pulled out on its
+/// own so it's checkable without a live `bpy` runtime (everything else that function does -
+/// reading `object.pose()`/`PoseBone::matrix()`/`Bone::matrix_local()` themselves - is pyo3 calls
+/// into Blender and has no meaningful pure-Rust test).
+fn bind_matrix_for_bone(posed: Option<Mat4<f32>>, rest: Mat4<f32>) -> Mat4<f32> {
+    posed.unwrap_or(rest)
+}
+
+#[cfg(test)]
+mod bind_matrix_tests {
+    use super::*;
+
+    #[test]
+    fn uses_the_posed_matrix_when_one_is_available() {
+        let rest = Mat4::identity();
+        let posed = Mat4::<f32>::scaling_3d(vek::Vec3::new(2.0, 2.0, 2.0));
+        assert_eq!(bind_matrix_for_bone(Some(posed), rest), posed);
+    }
+
+    #[test]
+    fn falls_back_to_the_rest_matrix_when_unposed() {
+        let rest = Mat4::<f32>::scaling_3d(vek::Vec3::new(3.0, 3.0, 3.0));
+        assert_eq!(bind_matrix_for_bone(None, rest), rest);
+    }
 }
 
-//fn mesh_into_bpy_mesh<'py>(env: &PyEnv<'py>, name: &str, input: Mesh) -> bpy::Mesh<'py> {
-//    let me: bpy::Mesh = env.bpy_data.meshes().new(name);
-//    me.from_pydata()
-//}
\ No newline at end of file
+/// Builds a fresh `bpy.types.Mesh` datablock named `name` out of `input`'s positions/edges/faces
+/// via `from_pydata`, the way Blender's own importers do it, then layers `input`'s UV channels,
+/// colour attributes and material slots on top. `materials` is the whole scene's `MaterialKey` ->
+/// `bpy.types.Material` mapping built once by [`materials_into_bpy`], so a slot referencing a
+/// material shared with other meshes doesn't create a duplicate datablock.
+fn mesh_into_bpy_mesh<'py>(env: &PyEnv<'py>, name: &str, input: &Mesh, materials: &HashMap<MaterialKey, &'py PyAny>) -> bpy::Mesh<'py> {
+    let py = env.python;
+    let meshes: &PyAny = env.bpy_data().getattr(intern!{py, "meshes"}).unwrap();
+    let me: &PyAny = meshes.call_method1(intern!{py, "new"}, (name,)).unwrap();
+
+    let verts: Vec<(f32, f32, f32)> = input.vertices.iter().map(|v| (v.x, v.y, v.z)).collect();
+    let edges: Vec<(usize, usize)> = input.edges.iter().map(|e| (e.a, e.b)).collect();
+    let faces: Vec<Vec<usize>> = input.polygons.iter()
+        .map(|p| input.faceloops[p.base..(p.base + p.count)].iter().map(|fl| fl.vertex).collect())
+        .collect();
+
+    me.call_method1(intern!{py, "from_pydata"}, (verts, edges, faces)).unwrap();
+    me.call_method0(intern!{py, "update"}).unwrap();
+
+    let uv_layers: &PyAny = me.getattr(intern!{py, "uv_layers"}).unwrap();
+    for (uv_name, uvs) in &input.faceloop_uvs {
+        let layer: &PyAny = uv_layers.call_method1(intern!{py, "new"}, (uv_name.as_str(),)).unwrap();
+        let data: &PyAny = layer.getattr(intern!{py, "data"}).unwrap();
+        for (i, uv) in uvs.iter().enumerate() {
+            data.get_item(i).unwrap().setattr(intern!{py, "uv"}, (uv.x, uv.y)).unwrap();
+        }
+    }
+
+    let color_attributes: &PyAny = me.getattr(intern!{py, "color_attributes"}).unwrap();
+    for (col_name, cols) in &input.vertex_colors {
+        write_color_attribute(py, color_attributes, col_name, "POINT", cols);
+    }
+    for (col_name, cols) in &input.faceloop_colors {
+        write_color_attribute(py, color_attributes, col_name, "CORNER", cols);
+    }
+
+    let mesh_materials: &PyAny = me.getattr(intern!{py, "materials"}).unwrap();
+    for material_id in input.material_ids.iter().copied() {
+        let bpy_material = material_id.and_then(|key| materials.get(&key).copied());
+        mesh_materials.call_method1(intern!{py, "append"}, (bpy_material,)).unwrap();
+    }
+
+    bpy::Mesh::wrap(me.as_borrowed().to_owned())
+}
+
+/// Writes one `FLOAT_COLOR` attribute named `name` on `domain` ("POINT" for `vertex_colors`,
+/// "CORNER" for `faceloop_colors`) out of `cols`, one RGBA value per entry in `color_attributes`'s
+/// owning mesh's own vertex/faceloop order.
+fn write_color_attribute(py: Python, color_attributes: &PyAny, name: &str, domain: &str, cols: &[Rgbaf]) {
+    let attr: &PyAny = color_attributes.call_method1(intern!{py, "new"}, (name, "FLOAT_COLOR", domain)).unwrap();
+    let data: &PyAny = attr.getattr(intern!{py, "data"}).unwrap();
+    for (i, col) in cols.iter().enumerate() {
+        data.get_item(i).unwrap().setattr(intern!{py, "color"}, (col.r, col.g, col.b, col.a)).unwrap();
+    }
+}
+
+/// Builds one `bpy.types.Material` datablock per entry in `scene.materials`, named after
+/// [`Material::name`]. [`mesh_into_bpy_mesh`] looks these up by [`MaterialKey`] rather than
+/// building its own per mesh, so two meshes sharing a material share the same datablock here too.
+fn materials_into_bpy<'py>(env: &PyEnv<'py>, scene: &Scene) -> HashMap<MaterialKey, &'py PyAny> {
+    let py = env.python;
+    let materials: &PyAny = env.bpy_data().getattr(intern!{py, "materials"}).unwrap();
+    scene.materials.iter()
+        .map(|(key, mat)| {
+            let data: &PyAny = materials.call_method1(intern!{py, "new"}, (mat.name.as_str(),)).unwrap();
+            (key, data)
+        })
+        .collect()
+}
+
+fn light_into_bpy_light<'py>(env: &PyEnv<'py>, name: &str, input: &Light) -> &'py PyAny {
+    let py = env.python;
+    let kind = match input.kind {
+        LightKind::Point => "POINT",
+        LightKind::Sun => "SUN",
+        LightKind::Spot => "SPOT",
+        LightKind::Area => "AREA",
+    };
+    let lights: &PyAny = env.bpy_data().getattr(intern!{py, "lights"}).unwrap();
+    let data: &PyAny = lights.call_method1(intern!{py, "new"}, (name, kind)).unwrap();
+
+    data.setattr(intern!{py, "energy"}, input.energy).unwrap();
+    data.setattr(intern!{py, "color"}, (input.color.r, input.color.g, input.color.b)).unwrap();
+    data.setattr(intern!{py, "use_shadow"}, input.cast_shadows).unwrap();
+    if matches!(input.kind, LightKind::Spot) {
+        data.setattr(intern!{py, "spot_size"}, input.spot_angle).unwrap();
+        data.setattr(intern!{py, "spot_blend"}, input.spot_blend).unwrap();
+    }
+    if input.range > 0.0 {
+        data.setattr(intern!{py, "use_custom_distance"}, true).unwrap();
+        data.setattr(intern!{py, "cutoff_distance"}, input.range).unwrap();
+    }
+    data
+}
+
+fn camera_into_bpy_camera<'py>(env: &PyEnv<'py>, name: &str, input: &Camera) -> &'py PyAny {
+    // Blender's camera datablock has no orthographic/panoramic-vs-perspective field this maps
+    // onto cleanly without also touching `ortho_scale`/panorama settings, so every camera comes
+    // back as a perspective one regardless of `input.kind` - the same approximation
+    // [`crate::model_reader_oil::oil_camera_to_camera`] already had to make on the way in.
+    let py = env.python;
+    let cameras: &PyAny = env.bpy_data().getattr(intern!{py, "cameras"}).unwrap();
+    let data: &PyAny = cameras.call_method1(intern!{py, "new"}, (name,)).unwrap();
+
+    data.setattr(intern!{py, "lens_unit"}, "FOV").unwrap();
+    data.setattr(intern!{py, "angle"}, input.fov).unwrap();
+    data.setattr(intern!{py, "clip_start"}, input.near_clip).unwrap();
+    data.setattr(intern!{py, "clip_end"}, input.far_clip).unwrap();
+    data
+}
+
+/// Sets up `bpy_obj` as a collision-primitive Empty: display as a wireframe gizmo sized to match
+/// the primitive, and stash the shape plus its exact dimensions on `diesel_collision` so the
+/// diesel exporter can emit proper collision bounds rather than having to re-derive them from the
+/// display gizmo.
+fn write_collision_props(py: Python, bpy_obj: &PyAny, input: &Collision) {
+    let display_type = match input.shape {
+        CollisionShape::Sphere => "SPHERE",
+        CollisionShape::Box => "CUBE",
+        CollisionShape::Cylinder => "CIRCLE",
+    };
+    bpy_obj.setattr(intern!{py, "empty_display_type"}, display_type).unwrap();
+    bpy_obj.setattr(intern!{py, "empty_display_size"}, input.radius.max(input.extent.reduce_partial_max())).unwrap();
+
+    let bpy_collision = bpy_obj.getattr(intern!{py, "diesel_collision"}).unwrap();
+    let shape = match input.shape {
+        CollisionShape::Sphere => "SPHERE",
+        CollisionShape::Box => "BOX",
+        CollisionShape::Cylinder => "CYLINDER",
+    };
+    bpy_collision.setattr(intern!{py, "shape"}, shape).unwrap();
+    bpy_collision.setattr(intern!{py, "radius"}, input.radius).unwrap();
+    bpy_collision.setattr(intern!{py, "extent"}, (input.extent.x, input.extent.y, input.extent.z)).unwrap();
+}
+
+/// Builds `scene` out into `bpy.data`/`bpy.context.collection`: one bpy object per
+/// [`model_ir::Object`], wired into the same parent/child tree and given the same local
+/// position/orientation/scale, with mesh/light/camera datablocks for whichever of those each
+/// object carries, collision primitives written back onto their Empty's `diesel_collision`
+/// PropertyGroup, armatures rebuilt bone-by-bone from their [`BindPose`], and mesh skinning
+/// reattached as real vertex groups plus an Armature modifier.
+///
+/// This is the write-side counterpart to [`scene_from_bpy_selected`] that [`crate::model_reader_oil`]
+/// needs to turn a parsed OIL scene into something visible in the current `.blend`. Object
+/// animation is still left unattached - that needs its own dedicated pass over the timeline - and
+/// bone reconstruction is necessarily approximate: [`BindJoint`] carries a bone's head position
+/// and rest orientation but never its length, so [`build_armature_bones`] has to guess a tail
+/// position (from the nearest child joint, or a short default for a leaf bone) rather than
+/// recover the original exactly.
+pub fn scene_to_bpy<'py>(env: &PyEnv<'py>, scene: &Scene) {
+    let py = env.python;
+    let collection: &PyAny = env.bpy_context().getattr(intern!{py, "collection"}).unwrap();
+    let collection_objects: &PyAny = collection.getattr(intern!{py, "objects"}).unwrap();
+    let objects: &PyAny = env.bpy_data().getattr(intern!{py, "objects"}).unwrap();
+    let armatures_data: &PyAny = env.bpy_data().getattr(intern!{py, "armatures"}).unwrap();
+
+    let materials = materials_into_bpy(env, scene);
+
+    // A bone owns no `bpy.types.Object` of its own - it's reconstructed straight into its
+    // armature's `edit_bones` by `build_armature_bones` below - but another object can still be
+    // parented to one (`ParentType::Bone`), so that needs the bone's owning armature up front.
+    let mut bone_owner: HashMap<ObjectKey, ObjectKey> = HashMap::new();
+    for (key, obj) in scene.objects.iter() {
+        if let ObjectData::Armature(bind_pose) = &obj.data {
+            for joint in &scene.bind_poses[*bind_pose].joints {
+                bone_owner.insert(joint.bone, key);
+            }
+        }
+    }
+
+    let mut bpy_objects: HashMap<ObjectKey, &'py PyAny> = HashMap::new();
+    for (key, obj) in scene.objects.iter() {
+        if obj.skin_role == SkinRole::Bone { continue; }
+
+        let data: &PyAny = match &obj.data {
+            ObjectData::None => py.None().into_ref(py),
+            ObjectData::Mesh(me) => mesh_into_bpy_mesh(env, &obj.name, me, &materials).as_pyany().clone().into_gil_ref(),
+            ObjectData::Light(li) => light_into_bpy_light(env, &obj.name, li),
+            ObjectData::Camera(ca) => camera_into_bpy_camera(env, &obj.name, ca),
+            ObjectData::Armature(_) => armatures_data.call_method1(intern!{py, "new"}, (obj.name.as_str(),)).unwrap(),
+            // A collision primitive is just an Empty, same as it was on the way in - there's no
+            // datablock to create, the shape/dimensions all live on the object itself.
+            ObjectData::Collision(_) => py.None().into_ref(py),
+        };
+        let bpy_obj: &PyAny = objects.call_method1(intern!{py, "new"}, (obj.name.as_str(), data)).unwrap();
+        collection_objects.call_method1(intern!{py, "link"}, (bpy_obj,)).unwrap();
+        if let ObjectData::Collision(col) = &obj.data {
+            write_collision_props(py, bpy_obj, col);
+        }
+        bpy_objects.insert(key, bpy_obj);
+    }
+
+    for (key, obj) in scene.objects.iter() {
+        if let ObjectData::Armature(bind_pose) = &obj.data {
+            build_armature_bones(env, bpy_objects[&key], scene, &scene.bind_poses[*bind_pose]);
+        }
+    }
+
+    for (key, obj) in scene.objects.iter() {
+        if obj.skin_role == SkinRole::Bone { continue; }
+        let bpy_obj = bpy_objects[&key];
+
+        if let Some(parent) = obj.parent {
+            if let Some(&bpy_parent) = bpy_objects.get(&parent) {
+                bpy_obj.setattr(intern!{py, "parent"}, bpy_parent).unwrap();
+            }
+            else if let Some(&armature_key) = bone_owner.get(&parent) {
+                bpy_obj.setattr(intern!{py, "parent"}, bpy_objects[&armature_key]).unwrap();
+                bpy_obj.setattr(intern!{py, "parent_type"}, "BONE").unwrap();
+                bpy_obj.setattr(intern!{py, "parent_bone"}, scene.objects[parent].name.as_str()).unwrap();
+            }
+        }
+
+        let t = &obj.transform;
+        bpy_obj.setattr(intern!{py, "rotation_mode"}, "QUATERNION").unwrap();
+        bpy_obj.setattr(intern!{py, "location"}, (t.position.x, t.position.y, t.position.z)).unwrap();
+        bpy_obj.setattr(intern!{py, "rotation_quaternion"},
+            (t.orientation.w, t.orientation.x, t.orientation.y, t.orientation.z)).unwrap();
+        bpy_obj.setattr(intern!{py, "scale"}, (t.scale.x, t.scale.y, t.scale.z)).unwrap();
+    }
+
+    for (key, obj) in scene.objects.iter() {
+        let ObjectData::Mesh(mesh) = &obj.data else { continue };
+        let Some(skin) = &mesh.skin else { continue };
+        let Some(&armature_bpy_obj) = bpy_objects.get(&skin.armature) else { continue };
+        apply_skin(py, bpy_objects[&key], armature_bpy_obj, mesh);
+    }
+}
+
+/// Reconstructs `bind_pose`'s joints as real bones inside `armature_obj`'s (freshly-created,
+/// still-empty) armature data. A joint's head comes straight from its [`model_ir::Object`]'s
+/// local position - already in armature space, the same convention [`SceneBuilder::add_bpy_armature_bones`]
+/// reads back *out* of `bone.head`/`parent.tail` on the way in - and the roll is set from the
+/// rest orientation's local Z axis via `align_roll`. The tail (and so the bone's length) has to
+/// be guessed, since [`BindJoint`] doesn't carry it: a bone with children is stretched out to
+/// the nearest one, and a childless leaf just gets a short default length.
+fn build_armature_bones<'py>(env: &PyEnv<'py>, armature_obj: &'py PyAny, scene: &Scene, bind_pose: &BindPose) {
+    let py = env.python;
+
+    let view_layer: &PyAny = env.bpy_context().getattr(intern!{py, "view_layer"}).unwrap();
+    view_layer.getattr(intern!{py, "objects"}).unwrap()
+        .setattr(intern!{py, "active"}, armature_obj).unwrap();
+
+    let ops_object: &PyAny = py.import(intern!{py, "bpy"}).unwrap()
+        .getattr(intern!{py, "ops"}).unwrap()
+        .getattr(intern!{py, "object"}).unwrap();
+    let enter_edit = PyDict::new(py);
+    enter_edit.set_item("mode", "EDIT").unwrap();
+    ops_object.call_method(intern!{py, "mode_set"}, (), Some(enter_edit)).unwrap();
+
+    let edit_bones: &PyAny = armature_obj.getattr(intern!{py, "data"}).unwrap()
+        .getattr(intern!{py, "edit_bones"}).unwrap();
+
+    let mut created: HashMap<ObjectKey, &PyAny> = HashMap::new();
+    let mut remaining: Vec<usize> = (0..bind_pose.joints.len()).collect();
+    while !remaining.is_empty() {
+        let mut progressed = false;
+        remaining.retain(|&idx| {
+            let joint = &bind_pose.joints[idx];
+            let bone_obj = &scene.objects[joint.bone];
+            let parent_bone = match bone_obj.parent {
+                Some(p) if scene.objects[p].skin_role == SkinRole::Bone => Some(p),
+                _ => None,
+            };
+            if matches!(parent_bone, Some(p) if !created.contains_key(&p)) {
+                return true;
+            }
+
+            let head = bone_obj.transform.position;
+            let rest_y = bone_obj.transform.orientation * Vec3f::unit_y();
+            let length = bone_obj.children.iter()
+                .filter(|&&c| scene.objects[c].skin_role == SkinRole::Bone)
+                .map(|&c| (scene.objects[c].transform.position - head).magnitude())
+                .fold(0.0f32, f32::max);
+            let length = if length > 1e-4 { length } else { 0.1 };
+            let tail = head + rest_y * length;
+
+            let edit_bone: &PyAny = edit_bones.call_method1(intern!{py, "new"}, (bone_obj.name.as_str(),)).unwrap();
+            edit_bone.setattr(intern!{py, "head"}, (head.x, head.y, head.z)).unwrap();
+            edit_bone.setattr(intern!{py, "tail"}, (tail.x, tail.y, tail.z)).unwrap();
+
+            let rest_z = bone_obj.transform.orientation * Vec3f::unit_z();
+            edit_bone.call_method1(intern!{py, "align_roll"}, ((rest_z.x, rest_z.y, rest_z.z),)).unwrap();
+
+            if let Some(parent_key) = parent_bone {
+                edit_bone.setattr(intern!{py, "parent"}, created[&parent_key]).unwrap();
+            }
+
+            created.insert(joint.bone, edit_bone);
+            progressed = true;
+            false
+        });
+        // A bone whose parent never turns up (a malformed/cyclic `BindPose`) is left out rather
+        // than looping forever.
+        if !progressed { break; }
+    }
+
+    let leave_edit = PyDict::new(py);
+    leave_edit.set_item("mode", "OBJECT").unwrap();
+    ops_object.call_method(intern!{py, "mode_set"}, (), Some(leave_edit)).unwrap();
+}
+
+/// Turns `mesh`'s vertex groups back into real `bpy.types.VertexGroup`s with per-vertex weights
+/// on `mesh_obj`, and adds the Armature modifier pointing at `armature_bpy_obj` that makes them
+/// actually deform it - the write-side counterpart of what [`SceneBuilder::add_bpy_mesh_instance`]
+/// reads on the way in. Blender matches a vertex group to a bone purely by name, so this doesn't
+/// need `SkinReference::vgroup_to_joint_mapping` at all: the group names already came back as
+/// [`VertexGroups::names`] on the mesh itself.
+fn apply_skin<'py>(py: Python<'py>, mesh_obj: &'py PyAny, armature_bpy_obj: &'py PyAny, mesh: &Mesh) {
+    let vertex_groups: &PyAny = mesh_obj.getattr(intern!{py, "vertex_groups"}).unwrap();
+    let bpy_groups: Vec<&PyAny> = mesh.vertex_groups.names.iter()
+        .map(|name| vertex_groups.call_method1(intern!{py, "new"}, (name.as_str(),)).unwrap())
+        .collect();
+
+    for (vertex, weights) in mesh.vertex_groups.iter_vertex_weights() {
+        for weight in weights {
+            let Some(&group) = bpy_groups.get(weight.group) else { continue };
+            group.call_method1(intern!{py, "add"}, (vec![vertex], weight.weight, "REPLACE")).unwrap();
+        }
+    }
+
+    let modifiers: &PyAny = mesh_obj.getattr(intern!{py, "modifiers"}).unwrap();
+    let modifier: &PyAny = modifiers.call_method1(intern!{py, "new"}, ("Armature", "ARMATURE")).unwrap();
+    modifier.setattr(intern!{py, "object"}, armature_bpy_obj).unwrap();
+}
\ No newline at end of file
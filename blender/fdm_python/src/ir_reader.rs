@@ -16,6 +16,7 @@ type Rgba = vek::Rgba<u8>;
 use pd2tools_macros::Parse;
 use pd2tools_rust::hashindex::HashIndex;
 use pd2tools_rust::formats::fdm;
+use pd2tools_rust::util::index_slab::IndexSlab;
 use pd2tools_rust::util::parse_helpers::{self, Parse};
 use crate::py_ir as ir;
 
@@ -55,27 +56,37 @@ impl<T> ConvResultExt for ConvResult<T> {
     }
 }
 
-pub fn sections_to_ir<'s, 'hi, 'py>(py: Python<'py>, sections: &'s HashMap<u32, fdm::Section>, hashlist: &'hi HashIndex, units_per_cm: f32, framerate: f32) -> ConvResult<Vec<Py<ir::Object>>> {
+pub fn sections_to_ir<'s, 'hi, 'py>(py: Python<'py>, sections: &'s fdm::DieselContainer, hashlist: &'hi HashIndex, units_per_cm: f32, framerate: f32, weld_tolerance: Option<f32>, weight_tolerance: Option<f32>) -> ConvResult<Vec<Py<ir::Object>>> {
+    let mut slab = IndexSlab::new();
+    for (id, sec) in sections.iter() {
+        slab.insert(id, sec);
+    }
+
     let mut reader = IrReader {
-        py, sections, hashlist, units_per_cm, framerate,
+        py, sections: slab, hashlist, units_per_cm, framerate, weld_tolerance, weight_tolerance,
         objects: HashMap::new()
     };
 
-    let ids = sections.iter().filter_map(|(k, v)| match v {
+    let ids = reader.sections.iter().filter_map(|(k, v)| match v {
         fdm::Section::Object3D(_) |
-        fdm::Section::Model(_) => Some(*k),
+        fdm::Section::Model(_) |
+        fdm::Section::Camera(_) |
+        fdm::Section::Light(_) => Some(k),
         _ => None
     }).collect::<Vec<u32>>();
 
     for i in ids {
         reader.get_object(i).at_object_id(i)?;
     }
+
+    reader.build_armature()?;
+
     Ok(reader.objects.drain().map(|(_,v)| v).collect::<Vec<_>>())
 }
 
 macro_rules! expect_section {
     ($doc:expr, $target:expr, $want:ident) => {
-        match &$doc[&$target] {
+        match $doc[$target] {
             fdm::Section::$want(s) => Ok(s),
             _ => Err(ConversionError::BadSectionType(fdm::SectionType::$want, $target))
         }
@@ -92,16 +103,34 @@ enum AnimItem<'a> {
 
 struct IrReader<'s, 'hi, 'py> {
     py: Python<'py>,
-    sections: &'s HashMap<u32, fdm::Section>,
+    sections: IndexSlab<&'s fdm::Section>,
     hashlist: &'hi HashIndex,
     units_per_cm: f32,
     framerate: f32,
+    /// Distance (in the same `units_per_cm`-scaled space as `merge_vertices`' output) within
+    /// which two vertices weld together despite not being bitwise-identical, or `None` to keep
+    /// the original exact-match behaviour.
+    weld_tolerance: Option<f32>,
+    /// How far apart two candidates' bone weights may be and still weld, when `weld_tolerance`
+    /// is `Some`. Defaults to `weld_tolerance`'s own value if not given separately.
+    weight_tolerance: Option<f32>,
     objects: HashMap<u32, Py<ir::Object>>
 }
 
+/// The `Object3dSection` embedded in any section that has one, so parent/transform
+/// lookups don't need to care which concrete section type they're looking at.
+fn object3d_of(sec: &fdm::Section) -> Option<&fdm::Object3dSection> {
+    match sec {
+        fdm::Section::Object3D(o) => Some(o),
+        fdm::Section::Model(m) => Some(&m.object),
+        fdm::Section::Light(l) => Some(&l.object),
+        _ => None
+    }
+}
+
 impl<'s, 'hi, 'py> IrReader<'s, 'hi, 'py> {
     fn get_section(&self, id: u32) -> ConvResult<&fdm::Section> {
-        self.sections.get(&id).ok_or(ConversionError::MissingSection(id))
+        self.sections.get(id).copied().ok_or(ConversionError::MissingSection(id))
     }
 
     fn get_anim_item(&self, id: u32) -> ConvResult<AnimItem> {
@@ -137,26 +166,40 @@ impl<'s, 'hi, 'py> IrReader<'s, 'hi, 'py> {
         Ok(res)
     }
 
-    fn import_animations(&self, obj: &fdm::Object3dSection, data: Py<ir::Object>) -> ConvResult<()> {
+    /// Wires up an object's animation controllers.
+    ///
+    /// `light` carries the `ir::Light` this object's `data` holds, if any: a
+    /// light's intensity and colour animate the light data itself rather than
+    /// the object, so those combinations are matched against it directly
+    /// instead of being appended to `data`'s own animation list.
+    fn import_animations(&self, obj: &fdm::Object3dSection, data: Py<ir::Object>, light: Option<&mut ir::Light>) -> ConvResult<()> {
         let mut data = data.borrow_mut(self.py);
 
         let ctls = self.resolve_controllers(&obj.animation_controllers)?;
         use AnimItem::*;
-        match ctls {
-            //(Light(li),  (LinearFloat(intensity), Null,                  Null, Null))                  => { },
-            //(Light(li),  (LinearVec3f(color),     Null,                  Null, OOB))                   => { },
-            //(Light(li),  (LinearFloat(intensity), LinearVec3f(color),    Null, LinearVec3f(position))) => { },
-            (LinearVec4f(rotation),  Null,                  OOB, OOB) => {
+        match (light, ctls) {
+            (Some(li), (LinearFloat(intensity), Null,               Null, Null)) => {
+                li.animations.append(&mut intensity.to_animation(self.py, self.framerate, "energy", 1.0)?);
+            },
+            (Some(li), (LinearVec3f(color),     Null,               Null, OOB)) => {
+                li.animations.append(&mut color.to_animation(self.py, self.framerate, "color", 1.0)?);
+            },
+            (Some(li), (LinearFloat(intensity), LinearVec3f(color), Null, LinearVec3f(position))) => {
+                li.animations.append(&mut intensity.to_animation(self.py, self.framerate, "energy", 1.0)?);
+                li.animations.append(&mut color.to_animation(self.py, self.framerate, "color", 1.0)?);
+                data.animations.append(&mut position.to_animation(self.py, self.framerate, "location", self.units_per_cm)?);
+            },
+            (_, (LinearVec4f(rotation),  Null,                  OOB, OOB)) => {
                 data.animations.append(&mut rotation.to_animation(self.py, self.framerate, "rotation_quaternion", 1.0)?);
             },
-            (LinearVec3f(location),  OOB,                   OOB,  OOB) => {
+            (_, (LinearVec3f(location),  OOB,                   OOB,  OOB)) => {
                 data.animations.append(&mut location.to_animation(self.py, self.framerate, "location", self.units_per_cm)?);
             },
-            (LinearVec4f(rotation),  LinearVec3f(location), OOB,  OOB) => {
+            (_, (LinearVec4f(rotation),  LinearVec3f(location), OOB,  OOB)) => {
                 data.animations.append(&mut location.to_animation(self.py, self.framerate, "location", self.units_per_cm)?);
                 data.animations.append(&mut rotation.to_animation(self.py, self.framerate, "rotation_quaternion", 1.0)?);
             },
-            (OOB, OOB, OOB, OOB) => { },
+            (_, (OOB, OOB, OOB, OOB)) => { },
             _ => return Err(ConversionError::WeirdAnimation)
         }
         Ok(())
@@ -193,11 +236,11 @@ impl<'s, 'hi, 'py> IrReader<'s, 'hi, 'py> {
         if id == 0 {
             return Ok(None);
         }
-        match self.sections.get(&id) {
+        match self.sections.get(id) {
             Some(fdm::Section::Object3D(sec)) => {
                 let obj = self.import_object3d(id, sec).at_object_id(id)?;
 
-                self.import_animations(&sec, obj.clone()).at_object_id(id)?;
+                self.import_animations(&sec, obj.clone(), None).at_object_id(id)?;
 
                 self.objects.insert(id, obj.clone());
                 Ok(Some(obj))
@@ -205,13 +248,23 @@ impl<'s, 'hi, 'py> IrReader<'s, 'hi, 'py> {
             Some(fdm::Section::Model(md)) => {
                 let obj = self.import_model(id, md)?;
 
-                self.import_animations(&md.object, obj.clone()).at_object_id(id)?;
+                self.import_animations(&md.object, obj.clone(), None).at_object_id(id)?;
 
                 self.objects.insert(id, obj.clone());
                 Ok(Some(obj))
             }
-            //Some(fdm::Section::Camera(_)) => todo!(),
-            //Some(fdm::Section::Light(_)) => todo!(),
+            Some(fdm::Section::Camera(raw)) => {
+                let obj = self.import_camera(id, raw).at_object_id(id)?;
+
+                self.objects.insert(id, obj.clone());
+                Ok(Some(obj))
+            },
+            Some(fdm::Section::Light(li)) => {
+                let obj = self.import_light(id, li).at_object_id(id)?;
+
+                self.objects.insert(id, obj.clone());
+                Ok(Some(obj))
+            },
             Some(_) =>
                 Err(ConversionError::BadSectionType(fdm::SectionType::Object3D, id)),
             None =>
@@ -240,7 +293,174 @@ impl<'s, 'hi, 'py> IrReader<'s, 'hi, 'py> {
         Ok(())
     }
 
-    fn import_mesh(&mut self, _id: u32, obj: Py<ir::Object>, src: &fdm::MeshModel) -> ConvResult<()> {
+    /// The accumulated parent-to-world transform of `id`, `units_per_cm`-scaled translation included.
+    fn world_transform(&self, id: u32, cache: &mut HashMap<u32, vek::Mat4<f32>>) -> vek::Mat4<f32> {
+        if let Some(m) = cache.get(&id) {
+            return *m;
+        }
+
+        let local = match self.sections.get(id).copied().and_then(object3d_of) {
+            Some(sec) => {
+                let mut tf = sec.transform;
+                tf.cols.w.x *= self.units_per_cm;
+                tf.cols.w.y *= self.units_per_cm;
+                tf.cols.w.z *= self.units_per_cm;
+                tf
+            },
+            None => return vek::Mat4::identity()
+        };
+
+        let parent_id = self.sections.get(id).copied().and_then(object3d_of).map(|s| s.parent).unwrap_or(0);
+        let world = if parent_id == 0 { local } else { self.world_transform(parent_id, cache) * local };
+
+        cache.insert(id, world);
+        world
+    }
+
+    /// Turns every `Object3D` section that has children but isn't itself a mesh,
+    /// bounds, light or camera into a bone of a synthetic `ir::Armature` object,
+    /// mirroring the parent chain those sections already have. Anything that was
+    /// parented to one of these bones gets reparented onto the armature object
+    /// instead, and skinned meshes get `weight_names` so their `vert_weights`
+    /// indices resolve to bone names.
+    fn build_armature(&mut self) -> ConvResult<()> {
+        let mut has_children = HashSet::<u32>::new();
+        for sec in self.sections.values() {
+            if let Some(o) = object3d_of(*sec) {
+                if o.parent != 0 {
+                    has_children.insert(o.parent);
+                }
+            }
+        }
+
+        let bone_ids = self.sections.iter()
+            .filter_map(|(k, v)| match v {
+                fdm::Section::Object3D(_) if has_children.contains(&k) => Some(k),
+                _ => None
+            })
+            .collect::<Vec<u32>>();
+
+        if bone_ids.is_empty() {
+            return Ok(());
+        }
+
+        let bone_index = bone_ids.iter().enumerate().map(|(i, id)| (*id, i)).collect::<HashMap<u32, usize>>();
+
+        let mut cache = HashMap::new();
+        let mut names = Vec::with_capacity(bone_ids.len());
+        let mut parents = Vec::with_capacity(bone_ids.len());
+        let mut heads = Vec::with_capacity(bone_ids.len());
+        let mut axes = Vec::with_capacity(bone_ids.len());
+        for &id in &bone_ids {
+            let sec = object3d_of(self.sections[id]).unwrap();
+            let world = self.world_transform(id, &mut cache);
+            names.push(self.hashlist.get_hash(sec.name.0).to_string());
+            parents.push(bone_index.get(&sec.parent).copied());
+            heads.push(world.cols.w.xyz());
+            axes.push(world.cols.y.xyz());
+        }
+
+        let mut children = vec![Vec::<usize>::new(); bone_ids.len()];
+        for (i, parent) in parents.iter().enumerate() {
+            if let Some(p) = parent {
+                children[*p].push(i);
+            }
+        }
+
+        let mut tails = Vec::with_capacity(bone_ids.len());
+        for i in 0..bone_ids.len() {
+            let tail = match children[i].as_slice() {
+                [only] => heads[*only],
+                [] => heads[i] + axes[i].normalized() * self.units_per_cm,
+                many => {
+                    let sum = many.iter().fold(vek::Vec3::zero(), |acc, &c| acc + heads[c]);
+                    sum / (many.len() as f32)
+                }
+            };
+            tails.push(tail);
+        }
+
+        let armature = ir::Armature {
+            bone_names: names,
+            bone_parents: parents,
+            bone_heads: heads.iter().map(|v| v.into_tuple()).collect(),
+            bone_tails: tails.iter().map(|v| v.into_tuple()).collect()
+        };
+        let armature_data: PyObject = Py::new(self.py, armature)?.into_py(self.py);
+        let armature_obj = Py::new(self.py, ir::Object {
+            name: String::from("Armature"),
+            parent: None,
+            transform: mat_to_row_tuples(vek::Mat4::identity()),
+            animations: Vec::new(),
+            data: Some(armature_data),
+            weight_names: Vec::new()
+        })?;
+
+        for (&id, obj) in self.objects.iter() {
+            if bone_index.contains_key(&id) {
+                continue;
+            }
+
+            let parent_id = self.sections.get(id).copied().and_then(object3d_of).map(|s| s.parent).unwrap_or(0);
+            if bone_index.contains_key(&parent_id) {
+                obj.borrow_mut(self.py).parent = Some(armature_obj.clone());
+            }
+        }
+
+        for id in &bone_ids {
+            self.objects.remove(id);
+        }
+        self.objects.insert(u32::MAX, armature_obj);
+
+        Ok(())
+    }
+
+    fn import_light(&mut self, id: u32, sec: &fdm::LightSection) -> ConvResult<Py<ir::Object>> {
+        let obj = self.import_object3d(id, &sec.object).at_object_id(id)?;
+
+        let light_type = match sec.light_type {
+            fdm::LightType::Omnidirectional => "POINT",
+            fdm::LightType::Spot => "SPOT"
+        };
+        let mut light = ir::Light {
+            light_type: String::from(light_type),
+            color: (sec.color.r, sec.color.g, sec.color.b),
+            intensity: sec.color.a,
+            range: sec.far_range * self.units_per_cm,
+            spot_angle: sec.unknown_6,
+            animations: Vec::new()
+        };
+
+        self.import_animations(&sec.object, obj.clone(), Some(&mut light)).at_object_id(id)?;
+
+        let data: PyObject = Py::new(self.py, light)?.into_py(self.py);
+        obj.borrow_mut(self.py).data = Some(data);
+
+        Ok(obj)
+    }
+
+    /// `fdm::Section::Camera` has no structured reader (it's kept as raw
+    /// bytes), so there's no transform, parent or animation controllers to
+    /// read here - just attach a default `ir::Camera` so the object exists
+    /// in the scene.
+    fn import_camera(&mut self, id: u32, _raw: &[u8]) -> ConvResult<Py<ir::Object>> {
+        let obj = ir::Object {
+            name: format!("camera_{}", id),
+            parent: None,
+            transform: mat_to_row_tuples(vek::Mat4::identity()),
+            animations: Vec::new(),
+            data: None,
+            weight_names: Vec::new()
+        };
+        let obj = Py::new(self.py, obj)?;
+
+        let data: PyObject = Py::new(self.py, ir::Camera::default())?.into_py(self.py);
+        obj.borrow_mut(self.py).data = Some(data);
+
+        Ok(obj)
+    }
+
+    fn import_mesh(&mut self, id: u32, obj: Py<ir::Object>, src: &fdm::MeshModel) -> ConvResult<()> {
         let gp = expect_section!(self.sections, src.geometry_provider, PassthroughGP)?;
         let geo = expect_section!(self.sections, gp.geometry, Geometry)?;
         let topo = expect_section!(self.sections, gp.topology, Topology)?;
@@ -253,7 +473,20 @@ impl<'s, 'hi, 'py> IrReader<'s, 'hi, 'py> {
             material_names.push(hs.to_string());
         }
 
-        let vcache = merge_vertices(geo, self.units_per_cm);
+        // `blend_indices_0`/`_1` already index into the skin's own bone palette (see
+        // `SkinBonesSection`'s docs), so `weight_names` just needs to name that palette in
+        // the same order - nothing in `vert_weights` needs renumbering.
+        let weight_names = match self.sections.get(src.skinbones) {
+            Some(fdm::Section::SkinBones(skin)) => skin.bones.iter()
+                .map(|&bone_id| match self.sections.get(bone_id).copied().and_then(object3d_of) {
+                    Some(bone) => self.hashlist.get_hash(bone.name.0).to_string(),
+                    None => String::new()
+                })
+                .collect(),
+            _ => Vec::new()
+        };
+
+        let vcache = merge_vertices(geo, self.units_per_cm, self.weld_tolerance, self.weight_tolerance);
         let vertex_map = vcache.index_map;
         let mut mesh = ir::Mesh {
             material_names,
@@ -350,6 +583,7 @@ impl<'s, 'hi, 'py> IrReader<'s, 'hi, 'py> {
         let mut objref = obj.borrow_mut(self.py);
         let data = Py::new(self.py, mesh)?;
         objref.data = Some(data.into_py(self.py));
+        objref.weight_names = weight_names;
 
         Ok(())
     }
@@ -368,10 +602,40 @@ struct VertexKey {
     weights: Vec<(u32, f32)>
 }
 
-fn merge_vertices(geo: &fdm::GeometrySection, units_per_cm: f32) -> VertexCache {
-    // For now we only merge bitwise-equivalent vertices.
-    // This should be enough to undo automatic splitting.
+fn merge_vertices(geo: &fdm::GeometrySection, units_per_cm: f32, weld_tolerance: Option<f32>, weight_tolerance: Option<f32>) -> VertexCache {
+    match weld_tolerance {
+        Some(eps) => merge_vertices_epsilon(geo, units_per_cm, eps, weight_tolerance.unwrap_or(eps)),
+        None => merge_vertices_exact(geo, units_per_cm)
+    }
+}
+
+fn vertex_key(geo: &fdm::GeometrySection, units_per_cm: f32, i: usize) -> VertexKey {
+    let mut vtx = VertexKey {
+        co: (geo.position[i] * units_per_cm).into_tuple(),
+        weights: Vec::with_capacity(8)
+    };
+
+    for j in 0..geo.weightcount_0 {
+        vtx.weights.push((
+            geo.blend_indices_0[i][j as usize] as u32,
+            geo.blend_weight_0[i][j as usize]
+        ));
+    }
 
+    for j in 0..geo.weightcount_1 {
+        vtx.weights.push((
+            geo.blend_indices_1[i][j as usize] as u32,
+            geo.blend_weight_1[i][j as usize]
+        ));
+    }
+
+    vtx
+}
+
+/// Merge only bitwise-equivalent vertices (serialized position+weights byte-for-byte). Enough
+/// to undo automatic splitting, but meshes re-quantized or re-exported with tiny epsilon drift
+/// fail to re-merge and import with cracked seams.
+fn merge_vertices_exact(geo: &fdm::GeometrySection, units_per_cm: f32) -> VertexCache {
     let mut positions = Vec::<(f32, f32, f32)>::with_capacity(geo.position.len());
     let mut weights = Vec::<Vec<(u32, f32)>>::with_capacity(geo.position.len());
     let mut index_map = Vec::<usize>::with_capacity(geo.position.len());
@@ -379,25 +643,8 @@ fn merge_vertices(geo: &fdm::GeometrySection, units_per_cm: f32) -> VertexCache
 
     let bufsize = 12 + 4 + 4 + 16 + 4 + 16;
     for i in 0..geo.position.len() {
-        let mut vtx = VertexKey {
-            co: (geo.position[i] * units_per_cm).into_tuple(),
-            weights: Vec::with_capacity(8)
-        };
-        
-        for j in 0..geo.weightcount_0 {
-            vtx.weights.push((
-                geo.blend_indices_0[i][j as usize] as u32,
-                geo.blend_weight_0[i][j as usize]
-            ));
-        }
-        
-        for j in 0..geo.weightcount_1 {
-            vtx.weights.push((
-                geo.blend_indices_1[i][j as usize] as u32,
-                geo.blend_weight_1[i][j as usize]
-            ));
-        }
-        
+        let vtx = vertex_key(geo, units_per_cm, i);
+
         let mut buf = Vec::<u8>::with_capacity(bufsize);
         vtx.serialize(&mut buf).unwrap();
 
@@ -418,6 +665,75 @@ fn merge_vertices(geo: &fdm::GeometrySection, units_per_cm: f32) -> VertexCache
     }
 }
 
+/// Merge vertices within `eps` of each other (and with matching bone weights, within
+/// `weight_eps`) using a spatial hash grid of cell size `eps`, probing the 27 neighbouring
+/// cells (the cell a vertex falls in, plus its 26 neighbours) for a weld candidate instead of
+/// scanning every vertex seen so far.
+///
+/// Position and weights are the only criteria, not normals: a `VertexCache` only carries the
+/// per-vertex attributes (position, bone weights) that `index_map` needs to collapse, and this
+/// runs before `loop_normals` exist at all - those are computed per face-loop, from the already-
+/// welded vertex set, later in [`sections_to_ir`]. Welding on normals as well would mean deriving
+/// them twice and feeding the second copy back into a stage that hasn't built its inputs yet.
+fn merge_vertices_epsilon(geo: &fdm::GeometrySection, units_per_cm: f32, eps: f32, weight_eps: f32) -> VertexCache {
+    let mut positions = Vec::<(f32, f32, f32)>::with_capacity(geo.position.len());
+    let mut weights = Vec::<Vec<(u32, f32)>>::with_capacity(geo.position.len());
+    let mut index_map = Vec::<usize>::with_capacity(geo.position.len());
+    let mut grid = HashMap::<(i64, i64, i64), Vec<usize>>::new();
+
+    let cell_of = |co: (f32, f32, f32)| -> (i64, i64, i64) {
+        ((co.0 / eps).floor() as i64, (co.1 / eps).floor() as i64, (co.2 / eps).floor() as i64)
+    };
+
+    for i in 0..geo.position.len() {
+        let vtx = vertex_key(geo, units_per_cm, i);
+        let cell = cell_of(vtx.co);
+
+        let mut found = None;
+        'neighbours: for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbour = (cell.0 + dx, cell.1 + dy, cell.2 + dz);
+                    if let Some(candidates) = grid.get(&neighbour) {
+                        if let Some(&existing) = candidates.iter().find(|&&c| vertex_welds(&positions[c], &weights[c], &vtx, eps, weight_eps)) {
+                            found = Some(existing);
+                            break 'neighbours;
+                        }
+                    }
+                }
+            }
+        }
+
+        match found {
+            Some(existing) => index_map.push(existing),
+            None => {
+                let idx = positions.len();
+                index_map.push(idx);
+                grid.entry(cell).or_insert_with(Vec::new).push(idx);
+                positions.push(vtx.co);
+                weights.push(vtx.weights);
+            }
+        }
+    }
+
+    VertexCache {
+        positions, index_map, weights
+    }
+}
+
+/// Two vertices weld into one representative if their positions are within `eps` on every
+/// axis and have the same bone-weight set (same indices in the same order, weights within
+/// `weight_eps`).
+fn vertex_welds(co: &(f32, f32, f32), vtx_weights: &[(u32, f32)], candidate: &VertexKey, eps: f32, weight_eps: f32) -> bool {
+    let pos_close = (co.0 - candidate.co.0).abs() <= eps
+        && (co.1 - candidate.co.1).abs() <= eps
+        && (co.2 - candidate.co.2).abs() <= eps;
+
+    pos_close && vtx_weights.len() == candidate.weights.len()
+        && vtx_weights.iter().zip(candidate.weights.iter())
+            .all(|(a, b)| a.0 == b.0 && (a.1 - b.1).abs() <= weight_eps)
+}
+
 fn mat_to_row_tuples(src: vek::Mat4<f32>) ->(
     (f32, f32, f32, f32),
     (f32, f32, f32, f32),
@@ -447,24 +763,81 @@ trait ToAnimation {
     fn to_animation(&self, py: Python, framerate: f32, path: &str, scale: f32) -> pyo3::PyResult<Vec<Py<ir::Animation>>>;
 }
 
+/// Decodes a controller's `flags` into the interpolation/extrapolation pair
+/// `ir::Animation` carries. These bits aren't documented anywhere, this is
+/// inferred from which combinations show up in shipped models: bit 0 selects
+/// stepped (`CONSTANT`) rather than the default `LINEAR` sampling, and bit 1
+/// marks the curve as looping, with bit 2 turning that loop into a ping-pong
+/// rather than a hard restart.
+fn controller_modes(flags: u32) -> (&'static str, &'static str) {
+    let interpolation = if flags & 0x1 != 0 { "CONSTANT" } else { "LINEAR" };
+    let extrapolation = match flags & 0x6 {
+        0x6 => "PING_PONG",
+        0x2 => "LOOP",
+        _ => "PLAY"
+    };
+    (interpolation, extrapolation)
+}
+
+/// `q` and `-q` represent the same rotation, so raw keyframes frequently flip sign between
+/// adjacent frames. [`QuatLinearRotationControllerSection::to_animation`] splits the quaternion
+/// into four independent per-component f-curves, so a sign flip would make the per-channel
+/// linear interpolation cut through the origin instead of along the shorter arc, producing
+/// visible tumbling between keys. Walk the keyframes in order and negate each one (all four
+/// components, so the orientation it represents is unchanged) whenever its dot product with
+/// the previously emitted keyframe is negative, keeping every keyframe on the same hemisphere.
+fn hemisphere_align(keyframes: &[(f32, vek::Vec4<f32>)]) -> Vec<(f32, vek::Vec4<f32>)> {
+    let mut prev: Option<vek::Vec4<f32>> = None;
+    keyframes.iter().map(|(ts, q)| {
+        let q = match prev {
+            Some(p) if p.dot(*q) < 0.0 => -*q,
+            _ => *q
+        };
+        prev = Some(q);
+        (*ts, q)
+    }).collect()
+}
+
+impl ToAnimation for fdm::LinearFloatControllerSection {
+    fn to_animation(&self, py: Python, framerate: f32, path: &str, scale: f32) -> pyo3::PyResult<Vec<Py<ir::Animation>>> {
+        let (interpolation, extrapolation) = controller_modes(self.flags);
+        let a = ir::Animation {
+            target_path: String::from(path),
+            target_index: 0,
+            fcurve: self.keyframes.iter().map(|(ts, v)| (*ts * framerate, v * scale) ).collect(),
+            interpolation: String::from(interpolation),
+            extrapolation: String::from(extrapolation)
+        };
+
+        Ok(vec![Py::new(py, a)?])
+    }
+}
+
 impl ToAnimation for fdm::LinearVector3ControllerSection {
     fn to_animation(&self, py: Python, framerate: f32, path: &str, scale: f32) -> pyo3::PyResult<Vec<Py<ir::Animation>>> {
+        let (interpolation, extrapolation) = controller_modes(self.flags);
         let xa = ir::Animation {
             target_path: String::from(path),
             target_index: 0,
-            fcurve: self.keyframes.iter().map(|(ts, v)| (*ts * framerate, v.x * scale) ).collect()
+            fcurve: self.keyframes.iter().map(|(ts, v)| (*ts * framerate, v.x * scale) ).collect(),
+            interpolation: String::from(interpolation),
+            extrapolation: String::from(extrapolation)
         };
 
         let ya = ir::Animation {
             target_path: String::from(path),
             target_index: 1,
-            fcurve: self.keyframes.iter().map(|(ts, v)| (*ts * framerate, v.y * scale) ).collect()
+            fcurve: self.keyframes.iter().map(|(ts, v)| (*ts * framerate, v.y * scale) ).collect(),
+            interpolation: String::from(interpolation),
+            extrapolation: String::from(extrapolation)
         };
 
         let za = ir::Animation {
             target_path: String::from(path),
             target_index: 2,
-            fcurve: self.keyframes.iter().map(|(ts, v)| (*ts * framerate, v.z * scale) ).collect()
+            fcurve: self.keyframes.iter().map(|(ts, v)| (*ts * framerate, v.z * scale) ).collect(),
+            interpolation: String::from(interpolation),
+            extrapolation: String::from(extrapolation)
         };
 
         Ok(vec![
@@ -477,28 +850,39 @@ impl ToAnimation for fdm::LinearVector3ControllerSection {
 
 impl ToAnimation for fdm::QuatLinearRotationControllerSection {
     fn to_animation(&self, py: Python, framerate: f32, path: &str, _scale: f32) -> pyo3::PyResult<Vec<Py<ir::Animation>>> {
+        let (interpolation, extrapolation) = controller_modes(self.flags);
+        let keyframes = hemisphere_align(&self.keyframes);
+
         let xa = ir::Animation {
             target_path: String::from(path),
             target_index: 1,
-            fcurve: self.keyframes.iter().map(|(ts, v)| (*ts * framerate, v.x) ).collect()
+            fcurve: keyframes.iter().map(|(ts, v)| (*ts * framerate, v.x) ).collect(),
+            interpolation: String::from(interpolation),
+            extrapolation: String::from(extrapolation)
         };
 
         let ya = ir::Animation {
             target_path: String::from(path),
             target_index: 2,
-            fcurve: self.keyframes.iter().map(|(ts, v)| (*ts * framerate, v.y) ).collect()
+            fcurve: keyframes.iter().map(|(ts, v)| (*ts * framerate, v.y) ).collect(),
+            interpolation: String::from(interpolation),
+            extrapolation: String::from(extrapolation)
         };
 
         let za = ir::Animation {
             target_path: String::from(path),
             target_index: 3,
-            fcurve: self.keyframes.iter().map(|(ts, v)| (*ts * framerate, v.z) ).collect()
+            fcurve: keyframes.iter().map(|(ts, v)| (*ts * framerate, v.z) ).collect(),
+            interpolation: String::from(interpolation),
+            extrapolation: String::from(extrapolation)
         };
 
         let wa = ir::Animation {
             target_path: String::from(path),
             target_index: 0,
-            fcurve: self.keyframes.iter().map(|(ts, v)| (*ts * framerate, v.w) ).collect()
+            fcurve: keyframes.iter().map(|(ts, v)| (*ts * framerate, v.w) ).collect(),
+            interpolation: String::from(interpolation),
+            extrapolation: String::from(extrapolation)
         };
 
         Ok(vec![
@@ -1,15 +1,15 @@
 use std::collections::HashMap;
 
+use pd2tools_rust::bundles::database::Database;
 use pd2tools_rust::formats::fdm;
 use pd2tools_rust::formats::fdm::DieselContainer;
 use pd2tools_rust::hashindex::HashIndex;
+use crate::material_registry;
 use crate::model_ir as ir;
 use crate::vek_types::*;
 
-pub fn fdm_to_ir<'s, 'hi>(sections: &'s DieselContainer, hashlist: &'hi mut HashIndex, units_per_cm: f32, framerate: f32) -> ir::Scene {
-    let scene = ir::Scene::default();
-    
-    let mut builder = SceneBuilder::new(sections, hashlist);
+pub fn fdm_to_ir<'s, 'hi>(sections: &'s DieselContainer, hashlist: &'hi mut HashIndex, units_per_cm: f32, framerate: f32, database: Option<&Database>, synthesize_tangents: bool) -> ir::Scene {
+    let mut builder = SceneBuilder::new(sections, hashlist, database, synthesize_tangents);
 
     for (id, sec) in sections.iter() {
         match sec {
@@ -34,24 +34,28 @@ pub fn fdm_to_ir<'s, 'hi>(sections: &'s DieselContainer, hashlist: &'hi mut Hash
         }
     }
 
-    scene
+    builder.into()
 }
 
 
-struct SceneBuilder<'s, 'hi> {
+struct SceneBuilder<'s, 'hi, 'db> {
     fdm: &'s DieselContainer,
     hashlist: &'hi mut HashIndex,
+    database: Option<&'db Database>,
+    synthesize_tangents: bool,
     scene: ir::Scene,
     section_id_to_object: HashMap<u32, ir::ObjectKey>,
     parent_request: Vec<(ir::ObjectKey, u32)>,
     skin_request: Vec<(ir::ObjectKey, u32)>,
     material_mapping: HashMap<u64, ir::MaterialKey>
 }
-impl<'s, 'hi> SceneBuilder<'s, 'hi> {
-    fn new(sections: &'s DieselContainer, hashlist: &'hi mut HashIndex) -> Self {
+impl<'s, 'hi, 'db> SceneBuilder<'s, 'hi, 'db> {
+    fn new(sections: &'s DieselContainer, hashlist: &'hi mut HashIndex, database: Option<&'db Database>, synthesize_tangents: bool) -> Self {
         Self {
             fdm: sections,
             hashlist,
+            database,
+            synthesize_tangents,
             scene: ir::Scene::default(),
             section_id_to_object: HashMap::new(),
             parent_request: Vec::new(),
@@ -67,12 +71,14 @@ impl<'s, 'hi> SceneBuilder<'s, 'hi> {
 
         let ob = ir::Object {
             name: self.hashlist.get_hash(ob_sec.name.0).to_string(),
+            name_hash: ob_sec.name.0,
             parent: None,
             children: Vec::new(),
             transform,
             in_collections: Vec::new(),
             data: ir::ObjectData::None,
             skin_role: ir::SkinRole::None,
+            animation: None,
         };
         let key = self.scene.objects.insert(ob);
         self.section_id_to_object.insert(sec_id, key);
@@ -173,7 +179,11 @@ impl<'s, 'hi> SceneBuilder<'s, 'hi> {
             (l,_,_) => ir::TangentLayer::Tangents(Vec::with_capacity(l))
         };
 
-        for (ra_idx, ra) in mesh.render_atoms.iter().enumerate() {
+        // `blend_indices_0`/`_1` already hold bone-palette indices straight into
+        // `SkinBonesSection::bones`/`inverse_bind_matrices` - see `DieselContainer::skin_joints`
+        // - so there's no per-render-atom remapping to do here, unlike texcoords/tangents which
+        // are only ever populated for the slice of the vertex buffer each render atom covers.
+        for ra in &mesh.render_atoms {
             let idx_start = ra.base_index as usize;
             let idx_end = ra.base_index as usize + (ra.triangle_count as usize) * 3;
 
@@ -194,17 +204,6 @@ impl<'s, 'hi> SceneBuilder<'s, 'hi> {
                     }),
                 }
             }
-
-            if let Some(skinbones) = self.fdm.get_as::<fdm::SkinBones>(mesh.skinbones) {
-                let map = skinbones.bones.mapping[ra_idx].as_slice();
-                for vertex_num in ra.vertex_range() {
-                    let v = &mut me.vertex_groups[vertex_num];
-                    for w in v.iter_mut() {
-                        let g: usize = w.group.try_into().unwrap();
-                        w.group = map[g].try_into().unwrap();
-                    }
-                }
-            }
         }
 
         for (i,uvl) in me_texcoord.into_iter().enumerate() {
@@ -218,7 +217,11 @@ impl<'s, 'hi> SceneBuilder<'s, 'hi> {
                 me.material_ids.push(self.intern_material(*mat_id))
             }
         }
-        
+
+        if self.synthesize_tangents {
+            me.synthesize_tangents();
+        }
+
         me.deduplicate_vertices();
         me
     }
@@ -261,9 +264,32 @@ impl<'s, 'hi> SceneBuilder<'s, 'hi> {
             std::collections::hash_map::Entry::Vacant(v) => {
                 let n = self.hashlist.get_hash(fdm_mat.name);
                 if n.text == Some("Material: Default Material") { return None; }
-                let k = self.scene.materials.insert(ir::Material {
-                    name: n.to_string(),
-                });
+                let name = n.to_string();
+
+                let config = self.database.and_then(|db| material_registry::read_material_config(db, &name));
+                let material = match config {
+                    Some(cfg) => ir::Material {
+                        name,
+                        name_hash: fdm_mat.name.0,
+                        shader: cfg.shader,
+                        textures: cfg.textures.into_iter()
+                            .map(|(slot_name_hash, texture_path)| ir::MaterialTextureSlot { slot_name_hash, texture_path })
+                            .collect(),
+                        parameters: cfg.parameters.into_iter()
+                            .map(|(name, value)| ir::MaterialParameter {
+                                name,
+                                value: match value {
+                                    material_registry::MaterialParamValue::Scalar(s) => ir::MaterialParameterValue::Scalar(s),
+                                    material_registry::MaterialParamValue::Vector(v) => ir::MaterialParameterValue::Vector(v),
+                                }
+                            })
+                            .collect(),
+                        skinned: cfg.skinned,
+                    },
+                    None => ir::Material { name, name_hash: fdm_mat.name.0, ..Default::default() },
+                };
+
+                let k = self.scene.materials.insert(material);
                 v.insert(k);
                 Some(k)
             },
@@ -281,46 +307,82 @@ impl<'s, 'hi> SceneBuilder<'s, 'hi> {
     }
 
     fn build_skins(&mut self) {
-        // I (KT) don't know if SkinBones.root_bone_object always points to something that can
-        // be made into an Armature object in Blender land, so if it *is* weighted to,
-        // the parent gets made into the armature.
+        // I (KT) don't know if SkinBonesSection.root always points to something that can
+        // be made into an Armature object in Blender land, so if it's itself one of its own
+        // skin's bones (or a mesh), its parent gets made into the armature instead.
         //
-        // On top of this, any object which has a bone child is turned to bone, unless it's an
-        // armature or the root (which becomes an armature).
+        // On top of this, any object standing between a bone and its armature gets turned into
+        // a bone too, so the chain from armature to leaf bones stays unbroken.
         //
-        // We're assuming that all the skins in one file have the same bind pose, too.
-
-        struct Skin {
-            armature: ir::ObjectKey,
-            global_transform: Mat4f,
-            joints: Vec<(ir::ObjectKey, Mat4f)>
-        }
-
-        let mut indie_skins = Vec::<(ir::ObjectKey, Skin)>::with_capacity(self.skin_request.len());
+        // We're assuming that all the skins sharing a root_bone_object share one bind pose, too,
+        // so such skins get folded down onto a single armature/BindPose.
+
+        let mut armature_for_root = HashMap::<u32, (ir::ObjectKey, ir::BindPoseKey)>::new();
+
+        let skin_requests = self.skin_request.clone();
+        for (skinned_object_key, skinbones_id) in skin_requests {
+            let Some(skinbones) = self.fdm.get_as::<fdm::SkinBonesSection>(skinbones_id) else { continue };
+
+            let (armature_key, _) = *armature_for_root.entry(skinbones.root).or_insert_with(|| {
+                let root_key = self.section_id_to_object[&skinbones.root];
+                let root_is_weighted_or_mesh = skinbones.bones.contains(&skinbones.root)
+                    || matches!(self.scene.objects[root_key].data, ir::ObjectData::Mesh(_));
+                let armature_key = if root_is_weighted_or_mesh {
+                    self.scene.objects[root_key].parent
+                        .expect("skin's root_bone_object has no parent to promote to an armature")
+                } else {
+                    root_key
+                };
 
-        for (skinned_object_key, skinbones_id) in &self.skin_request {
-            let skinbones = self.fdm.get_as::<fdm::SkinBones>(*skinbones_id).unwrap();
+                let joints = skinbones.bones.iter().zip(skinbones.inverse_bind_matrices.iter())
+                    .map(|(bone_id, inverse_bind)| ir::BindJoint {
+                        bone: self.section_id_to_object[bone_id],
+                        bindspace_to_bonespace: *inverse_bind,
+                    })
+                    .collect();
 
-            let joints = skinbones.joints.iter().map(|(bone_idx, tf)| {
-                let bone_key = self.section_id_to_object[bone_idx];
-                (bone_key, tf.clone())
-            }).collect();
+                let bind_pose_key = self.scene.bind_poses.insert(ir::BindPose {
+                    joints,
+                    mid_to_bind: skinbones.global_transform,
+                });
 
-            let skin = Skin {
-                armature: self.section_id_to_object[&skinbones.root_bone_object],
-                global_transform: skinbones.global_skin_transform,
-                joints
-            };
+                self.scene.objects[armature_key].skin_role = ir::SkinRole::Armature;
+                self.scene.objects[armature_key].data = ir::ObjectData::Armature(bind_pose_key);
+
+                (armature_key, bind_pose_key)
+            });
+
+            for &bone_sec_id in &skinbones.bones {
+                let Some(&bone_key) = self.section_id_to_object.get(&bone_sec_id) else { continue };
+                let mut curr = bone_key;
+                while curr != armature_key {
+                    let obj = &mut self.scene.objects[curr];
+                    if obj.skin_role == ir::SkinRole::Armature { break; }
+                    obj.skin_role = ir::SkinRole::Bone;
+                    match obj.parent {
+                        Some(parent) => curr = parent,
+                        None => break,
+                    }
+                }
+            }
 
-            indie_skins.push((*skinned_object_key, skin));
+            if let ir::ObjectData::Mesh(mesh) = &mut self.scene.objects[skinned_object_key].data {
+                mesh.skin = Some(ir::SkinReference {
+                    armature: armature_key,
+                    // blend_indices_0/_1 are already bone-palette indices, i.e. indices into
+                    // `skinbones.bones` - which is exactly the order `joints` above was built in.
+                    vgroup_to_joint_mapping: (0..skinbones.bones.len()).collect(),
+                    model_to_mid: Mat4f::identity(),
+                });
+            }
         }
-
-        
     }
 }
 impl<'s,'hi> From<SceneBuilder<'s,'hi>> for ir::Scene {
-    fn from(value: SceneBuilder) -> Self {
-        todo!()
+    fn from(mut value: SceneBuilder) -> Self {
+        value.connect_parents();
+        value.build_skins();
+        value.scene
     }
 }
 
@@ -0,0 +1,197 @@
+//! Converts OIL animation-controller chunks into the `Animation` pyclass
+//! objects defined in [`py_ir`] - the import counterpart to
+//! [`crate::ir_writer_oil`]'s OIL export path.
+//!
+//! Every controller (see [`oil`]) decodes as a node reference plus a list of
+//! keyframes; this maps each controller type to the Blender data path it
+//! drives and fans multi-component values (vectors, quaternions, colours)
+//! out into one `Animation` per component via `target_index`, the same way
+//! [`crate::ir_reader`]'s FDM controllers do. `LookatController` and the two
+//! `IkChain*` controllers have no numeric Blender property to drive, so
+//! they're decoded (see `oil.rs`) but not converted here.
+//!
+//! Composite position/rotation controllers reference other controllers by
+//! node id rather than carrying their own keyframes; those are resolved by
+//! resampling each referenced child onto a shared timeline (holding the
+//! child's last known value between its own keys, since there's no curve to
+//! interpolate against before fan-out) and combining them in order -
+//! translations add, rotations compose - before fanning the result out the
+//! same way a plain controller would be.
+
+use std::collections::HashMap;
+
+use pyo3::{Python, Py, PyResult};
+use vek::{Quaternion, Vec3, Vec4};
+
+use pd2tools_rust::formats::oil;
+use crate::py_ir as ir;
+
+fn animation(py: Python, path: &str, index: usize, fcurve: Vec<(f32, f32)>) -> PyResult<Py<ir::Animation>> {
+    Py::new(py, ir::Animation {
+        target_path: String::from(path),
+        target_index: index,
+        fcurve,
+        interpolation: String::from("LINEAR"),
+        extrapolation: String::from("PLAY")
+    })
+}
+
+trait ToAnimation {
+    fn to_animation(&self, py: Python, framerate: f32, path: &str) -> PyResult<Vec<Py<ir::Animation>>>;
+}
+
+impl ToAnimation for [oil::ScalarKey] {
+    fn to_animation(&self, py: Python, framerate: f32, path: &str) -> PyResult<Vec<Py<ir::Animation>>> {
+        let fcurve = self.iter().map(|k| (k.time as f32 * framerate, k.value as f32)).collect();
+        Ok(vec![animation(py, path, 0, fcurve)?])
+    }
+}
+
+impl ToAnimation for [oil::Vec3Key] {
+    fn to_animation(&self, py: Python, framerate: f32, path: &str) -> PyResult<Vec<Py<ir::Animation>>> {
+        let xc = self.iter().map(|k| (k.time as f32 * framerate, k.value.x as f32)).collect();
+        let yc = self.iter().map(|k| (k.time as f32 * framerate, k.value.y as f32)).collect();
+        let zc = self.iter().map(|k| (k.time as f32 * framerate, k.value.z as f32)).collect();
+        Ok(vec![
+            animation(py, path, 0, xc)?,
+            animation(py, path, 1, yc)?,
+            animation(py, path, 2, zc)?
+        ])
+    }
+}
+
+impl ToAnimation for [oil::ColorKey] {
+    fn to_animation(&self, py: Python, framerate: f32, path: &str) -> PyResult<Vec<Py<ir::Animation>>> {
+        let rc = self.iter().map(|k| (k.time as f32 * framerate, k.value.r as f32)).collect();
+        let gc = self.iter().map(|k| (k.time as f32 * framerate, k.value.g as f32)).collect();
+        let bc = self.iter().map(|k| (k.time as f32 * framerate, k.value.b as f32)).collect();
+        Ok(vec![
+            animation(py, path, 0, rc)?,
+            animation(py, path, 1, gc)?,
+            animation(py, path, 2, bc)?
+        ])
+    }
+}
+
+/// `RotationController`s pick one of `rotation_quaternion`/`rotation_euler`
+/// depending on what the exporter wrote; empty keyframe lists default to
+/// quaternion, matching Blender's own default rotation mode. Quaternion
+/// component order (`w, x, y, z`) matches [`crate::ir_reader`]'s FDM
+/// converter.
+fn rotation_animation(py: Python, framerate: f32, keys: &[oil::RotationKey]) -> PyResult<Vec<Py<ir::Animation>>> {
+    match keys.first().map(|k| &k.value) {
+        Some(oil::RotationValue::Euler(_)) => {
+            let xc = keys.iter().filter_map(|k| match k.value { oil::RotationValue::Euler(v) => Some((k.time as f32 * framerate, v.x as f32)), _ => None }).collect();
+            let yc = keys.iter().filter_map(|k| match k.value { oil::RotationValue::Euler(v) => Some((k.time as f32 * framerate, v.y as f32)), _ => None }).collect();
+            let zc = keys.iter().filter_map(|k| match k.value { oil::RotationValue::Euler(v) => Some((k.time as f32 * framerate, v.z as f32)), _ => None }).collect();
+            Ok(vec![
+                animation(py, "rotation_euler", 0, xc)?,
+                animation(py, "rotation_euler", 1, yc)?,
+                animation(py, "rotation_euler", 2, zc)?
+            ])
+        },
+        _ => {
+            let wc = keys.iter().filter_map(|k| match k.value { oil::RotationValue::Quaternion(v) => Some((k.time as f32 * framerate, v.w as f32)), _ => None }).collect();
+            let xc = keys.iter().filter_map(|k| match k.value { oil::RotationValue::Quaternion(v) => Some((k.time as f32 * framerate, v.x as f32)), _ => None }).collect();
+            let yc = keys.iter().filter_map(|k| match k.value { oil::RotationValue::Quaternion(v) => Some((k.time as f32 * framerate, v.y as f32)), _ => None }).collect();
+            let zc = keys.iter().filter_map(|k| match k.value { oil::RotationValue::Quaternion(v) => Some((k.time as f32 * framerate, v.z as f32)), _ => None }).collect();
+            Ok(vec![
+                animation(py, "rotation_quaternion", 0, wc)?,
+                animation(py, "rotation_quaternion", 1, xc)?,
+                animation(py, "rotation_quaternion", 2, yc)?,
+                animation(py, "rotation_quaternion", 3, zc)?
+            ])
+        }
+    }
+}
+
+fn to_quaternion(value: &oil::RotationValue) -> Quaternion<f64> {
+    match value {
+        oil::RotationValue::Quaternion(v) => Quaternion::from_xyzw(v.x, v.y, v.z, v.w),
+        oil::RotationValue::Euler(v) => Quaternion::rotation_x(v.x) * Quaternion::rotation_y(v.y) * Quaternion::rotation_z(v.z)
+    }
+}
+
+/// Samples `track` at `t` by holding the most recent key at or before it (or
+/// the first key, before any keyframes); `track` must be sorted by time.
+fn step_sample<T: Copy>(track: &[(f64, T)], t: f64, default: T) -> T {
+    track.iter().rev().find(|(kt, _)| *kt <= t).map(|(_, v)| *v)
+        .or_else(|| track.first().map(|(_, v)| *v))
+        .unwrap_or(default)
+}
+
+fn merged_times(tracks: &[Vec<(f64, impl Copy)>]) -> Vec<f64> {
+    let mut times: Vec<f64> = tracks.iter().flatten().map(|(t, _)| *t).collect();
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    times.dedup();
+    times
+}
+
+fn merge_position(child_ids: &[u32], positions: &HashMap<u32, &oil::PositionController>) -> Vec<oil::Vec3Key> {
+    let tracks: Vec<Vec<(f64, Vec3<f64>)>> = child_ids.iter()
+        .filter_map(|id| positions.get(id))
+        .map(|c| c.keys.iter().map(|k| (k.time, k.value)).collect())
+        .collect();
+
+    merged_times(&tracks).into_iter().map(|time| {
+        let value = tracks.iter().fold(Vec3::zero(), |acc, track| acc + step_sample(track, time, Vec3::zero()));
+        oil::Vec3Key { time, value }
+    }).collect()
+}
+
+fn merge_rotation(child_ids: &[u32], rotations: &HashMap<u32, &oil::RotationController>) -> Vec<oil::RotationKey> {
+    let tracks: Vec<Vec<(f64, Quaternion<f64>)>> = child_ids.iter()
+        .filter_map(|id| rotations.get(id))
+        .map(|c| c.keys.iter().map(|k| (k.time, to_quaternion(&k.value))).collect())
+        .collect();
+
+    merged_times(&tracks).into_iter().map(|time| {
+        let value = tracks.iter().fold(Quaternion::identity(), |acc, track| {
+            acc * step_sample(track, time, Quaternion::identity())
+        });
+        oil::RotationKey { time, value: oil::RotationValue::Quaternion(Vec4::new(value.x, value.y, value.z, value.w)) }
+    }).collect()
+}
+
+/// Converts every decoded OIL controller chunk in `chunks` into `Animation`
+/// objects, grouped by the node id they animate.
+pub fn animations_from_oil(py: Python, chunks: &[oil::Chunk], framerate: f32) -> PyResult<HashMap<u32, Vec<Py<ir::Animation>>>> {
+    let mut positions = HashMap::new();
+    let mut rotations = HashMap::new();
+    for chunk in chunks {
+        match chunk {
+            oil::Chunk::PositionController(c) => { positions.insert(c.node_id, c); },
+            oil::Chunk::RotationController(c) => { rotations.insert(c.node_id, c); },
+            _ => ()
+        }
+    }
+
+    let mut out: HashMap<u32, Vec<Py<ir::Animation>>> = HashMap::new();
+    for chunk in chunks {
+        let (node_id, anims) = match chunk {
+            oil::Chunk::PositionController(c) => (c.node_id, c.keys.to_animation(py, framerate, "location")?),
+            oil::Chunk::RotationController(c) => (c.node_id, rotation_animation(py, framerate, &c.keys)?),
+            oil::Chunk::ColorController(c) => (c.node_id, c.keys.to_animation(py, framerate, "color")?),
+            oil::Chunk::AttenuationController(c) => (c.node_id, c.keys.to_animation(py, framerate, "attenuation")?),
+            oil::Chunk::MultiplierController(c) => (c.node_id, c.keys.to_animation(py, framerate, "energy")?),
+            oil::Chunk::HotspotController(c) => (c.node_id, c.keys.to_animation(py, framerate, "spot_size")?),
+            oil::Chunk::FalloffController(c) => (c.node_id, c.keys.to_animation(py, framerate, "spot_blend")?),
+            oil::Chunk::FovController(c) => (c.node_id, c.keys.to_animation(py, framerate, "lens")?),
+            oil::Chunk::FarClipController(c) => (c.node_id, c.keys.to_animation(py, framerate, "clip_end")?),
+            oil::Chunk::NearClipController(c) => (c.node_id, c.keys.to_animation(py, framerate, "clip_start")?),
+            oil::Chunk::TargetDistanceController(c) => (c.node_id, c.keys.to_animation(py, framerate, "target_distance")?),
+            oil::Chunk::CompositePositionController(c) => {
+                let merged = merge_position(&c.child_ids, &positions);
+                (c.node_id, merged.to_animation(py, framerate, "location")?)
+            },
+            oil::Chunk::CompositeRotationController(c) => {
+                let merged = merge_rotation(&c.child_ids, &rotations);
+                (c.node_id, rotation_animation(py, framerate, &merged)?)
+            },
+            _ => continue
+        };
+        out.entry(node_id).or_insert_with(Vec::new).extend(anims);
+    }
+
+    Ok(out)
+}
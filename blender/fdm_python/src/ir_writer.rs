@@ -0,0 +1,355 @@
+//! py_ir -> FDM writer, the inverse of [`crate::ir_reader::sections_to_ir`].
+//!
+//! Where `sections_to_ir` flattens `Object3D`/`Model`/`Geometry`/`Topology` sections down into a
+//! `py_ir::Object` tree, this walks that tree back up: one `Object3dSection` per IR object, a
+//! `Model` wrapping a fresh `Geometry`/`Topology`/`PassthroughGP`/`TopologyIP` chain for every
+//! mesh, and a `MaterialGroup`/`Material` per distinct material name. Vertex keys reuse the same
+//! `#[derive(Parse)]` machinery `merge_vertices_exact` dedupes with, so a decode-then-encode of an
+//! untouched mesh reproduces the same vertex buffer.
+
+use std::collections::HashMap;
+
+use pyo3::{Py, Python};
+
+use pd2tools_macros::Parse;
+use pd2tools_rust::hashindex::{Hash as Idstring, HashIndex};
+use pd2tools_rust::formats::fdm;
+use pd2tools_rust::util::parse_helpers::Parse;
+
+use crate::py_ir as ir;
+
+type Vec2f = vek::Vec2<f32>;
+type Vec3f = vek::Vec3<f32>;
+
+/// Walks `objects` and everything they reach through `data`/`parent`, producing the full set of
+/// sections needed to represent them as an FDM model: an `Object3dSection` (or `ModelSection` for
+/// anything carrying an `ir::Mesh`) per object, plus the `Geometry`/`Topology`/`PassthroughGP`/
+/// `TopologyIP`/`MaterialGroup`/`Material` chain each mesh needs. Objects whose `data` isn't a
+/// `Mesh` (armatures, lights, cameras, bounds) round-trip as a bare `Object3dSection` - FDM has
+/// sections for some of those, but nothing here asked for that part of the tree back yet.
+pub fn ir_to_sections(py: Python, objects: &[Py<ir::Object>], hashlist: &mut HashIndex, units_per_cm: f32) -> HashMap<u32, fdm::Section> {
+    let mut writer = IrWriter {
+        py, hashlist, units_per_cm,
+        sections: HashMap::new(),
+        object_ids: HashMap::new(),
+        material_ids: HashMap::new(),
+        next_id: 1
+    };
+    writer.write(objects);
+    writer.sections
+}
+
+struct IrWriter<'hi, 'py> {
+    py: Python<'py>,
+    hashlist: &'hi mut HashIndex,
+    units_per_cm: f32,
+    sections: HashMap<u32, fdm::Section>,
+    /// Keyed by the Python object's identity (`Py::as_ptr`), since a `py_ir::Object` has no id of
+    /// its own until one is allocated here.
+    object_ids: HashMap<usize, u32>,
+    material_ids: HashMap<String, u32>,
+    next_id: u32
+}
+
+impl<'hi, 'py> IrWriter<'hi, 'py> {
+    fn alloc_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn write(&mut self, objects: &[Py<ir::Object>]) {
+        // Every object needs an id before any of them can be written, since parent links point
+        // at other objects that might not have been visited yet. Reserve ids with a placeholder
+        // Object3D now, and overwrite each one with its real content below.
+        for obj in objects {
+            let id = self.alloc_id();
+            self.object_ids.insert(obj.as_ptr() as usize, id);
+            self.sections.insert(id, fdm::Section::Object3D(Box::new(fdm::Object3dSection {
+                name: Idstring(0),
+                animation_controllers: Vec::new(),
+                transform: vek::Mat4::identity(),
+                parent: 0
+            })));
+        }
+
+        for obj in objects {
+            self.write_object(obj);
+        }
+    }
+
+    fn write_object(&mut self, obj: &Py<ir::Object>) {
+        let id = self.object_ids[&(obj.as_ptr() as usize)];
+        let objref = obj.borrow(self.py);
+
+        // An object whose parent isn't part of `objects` is treated as unparented rather than
+        // rejected outright, the same leniency `import_object3d` shows towards a dangling `0`.
+        let parent = objref.parent.as_ref()
+            .and_then(|p| self.object_ids.get(&(p.as_ptr() as usize)))
+            .copied()
+            .unwrap_or(0);
+
+        let mut transform = row_tuples_to_mat(objref.transform);
+        transform.cols.w.x /= self.units_per_cm;
+        transform.cols.w.y /= self.units_per_cm;
+        transform.cols.w.z /= self.units_per_cm;
+
+        let object = fdm::Object3dSection {
+            name: Idstring(self.hashlist.intern(objref.name.clone()).hash),
+            // Sampled keyframes aren't re-emitted as animation controller chunks - only the
+            // bind/rest transform round-trips.
+            animation_controllers: Vec::new(),
+            transform,
+            parent
+        };
+
+        let section = match objref.data.as_ref().and_then(|d| d.extract::<Py<ir::Mesh>>(self.py).ok()) {
+            Some(mesh) => {
+                let data = self.write_mesh(&mesh.borrow(self.py));
+                fdm::Section::Model(Box::new(fdm::ModelSection { object, data }))
+            },
+            None => fdm::Section::Object3D(Box::new(object))
+        };
+
+        self.sections.insert(id, section);
+    }
+
+    fn write_mesh(&mut self, mesh: &ir::Mesh) -> fdm::ModelData {
+        let material_ids: Vec<u32> = mesh.material_names.iter()
+            .map(|name| self.intern_material(name))
+            .collect();
+        let material_group = self.alloc_id();
+        self.sections.insert(material_group, fdm::Section::MaterialGroup(Box::new(
+            fdm::MaterialGroupSection { material_ids }
+        )));
+
+        let GeomBuffers { geometry, topology, atoms, bounds } = build_geometry(mesh, self.units_per_cm);
+
+        let geometry_id = self.alloc_id();
+        self.sections.insert(geometry_id, fdm::Section::Geometry(Box::new(geometry)));
+        let topology_id = self.alloc_id();
+        self.sections.insert(topology_id, fdm::Section::Topology(Box::new(topology)));
+
+        let geometry_provider = self.alloc_id();
+        self.sections.insert(geometry_provider, fdm::Section::PassthroughGP(Box::new(
+            fdm::PassthroughGPSection { geometry: geometry_id, topology: topology_id }
+        )));
+        let topology_ip = self.alloc_id();
+        self.sections.insert(topology_ip, fdm::Section::TopologyIP(Box::new(
+            fdm::TopologyIPSection { topology: topology_id }
+        )));
+
+        fdm::ModelData::Mesh(fdm::MeshModel {
+            geometry_provider,
+            topology_ip,
+            render_atoms: atoms,
+            material_group,
+            lightset: 0xFFFFFFFFu32,
+            bounds,
+            properties: 0,
+            skinbones: 0xFFFFFFFFu32
+        })
+    }
+
+    fn intern_material(&mut self, name: &str) -> u32 {
+        if let Some(&id) = self.material_ids.get(name) {
+            return id;
+        }
+        let hash = self.hashlist.intern(String::from(name)).hash;
+        let id = self.alloc_id();
+        self.sections.insert(id, fdm::Section::Material(Box::new(fdm::MaterialSection { name: hash, items: Vec::new() })));
+        self.material_ids.insert(String::from(name), id);
+        id
+    }
+}
+
+struct GeomBuffers {
+    geometry: fdm::GeometrySection,
+    topology: fdm::TopologySection,
+    atoms: Vec<fdm::RenderAtom>,
+    bounds: fdm::Bounds
+}
+
+/// One fdm-vertex-buffer entry, re-interleaving `ir::Mesh`'s per-vertex attributes (position,
+/// blend weights) with its per-loop ones (normal, UVs, colours). Serialized through the same
+/// `#[derive(Parse)]` machinery `ir_reader`'s `VertexKey` dedupes with on import, so a loop whose
+/// combination of attributes hasn't been seen before splits into a new buffer entry and anything
+/// identical collapses onto the existing one.
+#[derive(Clone, Parse)]
+struct GeomVertexKey {
+    co: (f32, f32, f32),
+    weights: Vec<(u32, f32)>,
+    has_normal: bool,
+    normal: (f32, f32, f32),
+    uvs: Vec<(f32, f32)>,
+    colors: Vec<(f32, f32, f32, f32)>
+}
+
+/// Groups `mesh`'s triangles by material (`TopologySection::faces` addresses a single contiguous
+/// run per atom), re-interleaving loop and vertex attributes into FDM's per-vertex buffer as it
+/// goes, deduplicating identical buffer entries so shared, unsplit vertices stay shared.
+fn build_geometry(mesh: &ir::Mesh, units_per_cm: f32) -> GeomBuffers {
+    let uv_layers = mesh.loop_uv_layers.len();
+    let color_layers = mesh.loop_colour_layers.len();
+
+    let loop_vertex = |loop_idx: usize, vertex: usize| -> GeomVertexKey {
+        let p = mesh.vert_positions[vertex];
+        let (has_normal, normal) = match mesh.has_normals {
+            true => (true, mesh.loop_normals[loop_idx]),
+            false => (false, (0.0, 0.0, 0.0))
+        };
+
+        GeomVertexKey {
+            co: (p.0 / units_per_cm, p.1 / units_per_cm, p.2 / units_per_cm),
+            weights: mesh.vert_weights[vertex].clone(),
+            has_normal,
+            normal,
+            uvs: (0..uv_layers).map(|i| mesh.loop_uv_layers[i].1[loop_idx]).collect(),
+            colors: (0..color_layers).map(|i| mesh.loop_colour_layers[i].1[loop_idx]).collect()
+        }
+    };
+
+    let mut triangles_by_material = HashMap::<usize, Vec<usize>>::new();
+    for (face, &material) in mesh.face_materials.iter().enumerate() {
+        triangles_by_material.entry(material).or_default().push(face);
+    }
+    let mut material_order: Vec<usize> = triangles_by_material.keys().copied().collect();
+    material_order.sort_unstable();
+
+    let mut vertices = Vec::<GeomVertexKey>::new();
+    let mut dedup = HashMap::<Vec<u8>, u32>::new();
+    let mut faces = Vec::<u16>::new();
+    let mut atoms = Vec::with_capacity(material_order.len());
+
+    for material in material_order {
+        let face_indices = &triangles_by_material[&material];
+        let base_index = faces.len() as u32;
+        let mut min_vertex = u32::MAX;
+        let mut max_vertex = 0u32;
+
+        for &face in face_indices {
+            let (v0, v1, v2) = mesh.faces[face];
+            for (corner, vertex) in [(0, v0), (1, v1), (2, v2)] {
+                let gv = loop_vertex(face * 3 + corner, vertex);
+
+                let mut buf = Vec::<u8>::with_capacity(64);
+                gv.serialize(&mut buf).unwrap();
+
+                let idx = *dedup.entry(buf).or_insert_with(|| {
+                    let idx = vertices.len() as u32;
+                    vertices.push(gv);
+                    idx
+                });
+                min_vertex = min_vertex.min(idx);
+                max_vertex = max_vertex.max(idx);
+                faces.push(idx.try_into().expect("fdm meshes can't address more than 65536 vertices"));
+            }
+        }
+
+        atoms.push(fdm::RenderAtom {
+            base_vertex: min_vertex,
+            triangle_count: face_indices.len() as u32,
+            base_index,
+            geometry_slice_length: max_vertex - min_vertex + 1,
+            material: material as u32
+        });
+    }
+
+    let mut vit = vertices.iter().map(|v| Vec3f::new(v.co.0, v.co.1, v.co.2));
+    let aabb = match vit.next() {
+        Some(first) => vit.fold(vek::Aabb::new_empty(first), |a, v| a.expanded_to_contain_point(v)),
+        None => vek::Aabb::default()
+    };
+    let center = (aabb.min + aabb.max) * 0.5;
+    let radius = (aabb.max - center).magnitude();
+    let bounds = fdm::Bounds { min: aabb.min, max: aabb.max, radius, unknown_13: 0 };
+
+    let has_normals = vertices.iter().any(|v| v.has_normal);
+    let has_weights_1 = vertices.iter().any(|v| v.weights.len() > 4);
+
+    let mut geometry = fdm::GeometrySection::default();
+    geometry.name = Idstring(pd2tools_rust::diesel_hash::from_str("geometry"));
+    geometry.position = vertices.iter().map(|v| Vec3f::new(v.co.0, v.co.1, v.co.2)).collect();
+    if has_normals {
+        geometry.normal = vertices.iter().map(|v| Vec3f::new(v.normal.0, v.normal.1, v.normal.2)).collect();
+    }
+    for i in 0..uv_layers.min(8) {
+        let data = vertices.iter().map(|v| Vec2f::new(v.uvs[i].0, v.uvs[i].1)).collect();
+        set_texcoord(&mut geometry, i, data);
+    }
+    if color_layers >= 1 {
+        geometry.color_0 = vertices.iter().map(|v| float_to_rgba(v.colors[0])).collect();
+    }
+    if color_layers >= 2 {
+        geometry.color_1 = vertices.iter().map(|v| float_to_rgba(v.colors[1])).collect();
+    }
+    if vertices.iter().any(|v| !v.weights.is_empty()) {
+        geometry.weightcount_0 = 4;
+        geometry.blend_indices_0 = vertices.iter().map(|v| weight_indices(&v.weights, 0)).collect();
+        geometry.blend_weight_0 = vertices.iter().map(|v| weight_values(&v.weights, 0)).collect();
+        if has_weights_1 {
+            geometry.weightcount_1 = 4;
+            geometry.blend_indices_1 = vertices.iter().map(|v| weight_indices(&v.weights, 4)).collect();
+            geometry.blend_weight_1 = vertices.iter().map(|v| weight_values(&v.weights, 4)).collect();
+        }
+    }
+
+    let topology = fdm::TopologySection {
+        unknown_1: 0,
+        faces,
+        unknown_2: Vec::new(),
+        name: Idstring(pd2tools_rust::diesel_hash::from_str("topology"))
+    };
+
+    GeomBuffers { geometry, topology, atoms, bounds }
+}
+
+/// The four bone indices starting at `skip` into `weights`, or `0` past its end - `skip` is `0`
+/// for `blend_indices_0` and `4` for `blend_indices_1`.
+fn weight_indices(weights: &[(u32, f32)], skip: usize) -> vek::Vec4<u16> {
+    let at = |i: usize| weights.get(skip + i).map_or(0, |&(group, _)| group as u16);
+    vek::Vec4::new(at(0), at(1), at(2), at(3))
+}
+
+fn weight_values(weights: &[(u32, f32)], skip: usize) -> vek::Vec4<f32> {
+    let at = |i: usize| weights.get(skip + i).map_or(0.0, |&(_, weight)| weight);
+    vek::Vec4::new(at(0), at(1), at(2), at(3))
+}
+
+fn float_to_rgba(c: (f32, f32, f32, f32)) -> vek::Rgba<u8> {
+    vek::Rgba::new(
+        (c.0 * 255.0) as u8,
+        (c.1 * 255.0) as u8,
+        (c.2 * 255.0) as u8,
+        (c.3 * 255.0) as u8
+    )
+}
+
+fn set_texcoord(geometry: &mut fdm::GeometrySection, index: usize, data: Vec<Vec2f>) {
+    match index {
+        0 => geometry.tex_coord_0 = data,
+        1 => geometry.tex_coord_1 = data,
+        2 => geometry.tex_coord_2 = data,
+        3 => geometry.tex_coord_3 = data,
+        4 => geometry.tex_coord_4 = data,
+        5 => geometry.tex_coord_5 = data,
+        6 => geometry.tex_coord_6 = data,
+        7 => geometry.tex_coord_7 = data,
+        _ => unreachable!("GeometrySection only has 8 texcoord channels")
+    }
+}
+
+/// Inverse of `ir_reader`'s `mat_to_row_tuples`.
+fn row_tuples_to_mat(src: (
+    (f32, f32, f32, f32),
+    (f32, f32, f32, f32),
+    (f32, f32, f32, f32),
+    (f32, f32, f32, f32)
+)) -> vek::Mat4<f32> {
+    vek::Mat4::new(
+        src.0.0, src.0.1, src.0.2, src.0.3,
+        src.1.0, src.1.1, src.1.2, src.1.3,
+        src.2.0, src.2.1, src.2.2, src.2.3,
+        src.3.0, src.3.1, src.3.2, src.3.3
+    )
+}
@@ -0,0 +1,418 @@
+//! IR -> FDM writer, the inverse of [`crate::ir_reader_fdm::fdm_to_ir`].
+//!
+//! Where `fdm_to_ir` flattens a `DieselContainer`'s Object3D/Model/Geometry/Topology chain down
+//! into a `model_ir::Scene`, this walks that same chain back up: one `Object3dSection` per IR
+//! object, a `Model` wrapping a fresh `Geometry`/`Topology`/`PassthroughGP`/`TopologyIP` chain for
+//! every mesh, and a `SkinBones`/`MaterialGroup` for the skinning and material data hanging off
+//! it. This is what makes the crate a true exporter rather than just a viewer: round-tripping a
+//! `.fdm` file through `fdm_to_ir` and back through [`scene_to_fdm`] should reproduce the same
+//! geometry, modulo whatever vertex-cache reordering `TopologySection::optimize_vertex_cache`
+//! would additionally apply.
+
+use std::collections::HashMap;
+
+use pd2tools_rust::formats::fdm::{self, DieselContainer, Section};
+use slotmap::SecondaryMap;
+
+use crate::model_ir::{self as ir, BindPoseKey, MaterialKey, Mesh, ObjectData, ObjectKey, Scene};
+use crate::vek_types::*;
+
+/// Builds a fresh [`DieselContainer`] holding every section needed to represent `scene`.
+pub fn scene_to_fdm(scene: &Scene) -> DieselContainer {
+    let mut container = DieselContainer::new();
+    let mut writer = SceneWriter::new(scene, &mut container);
+    writer.write();
+    container
+}
+
+fn hash_name(name: &str) -> fdm::Idstring {
+    pd2tools_rust::hashindex::Hash(pd2tools_rust::diesel_hash::from_str(name))
+}
+
+struct SceneWriter<'s, 'c> {
+    scene: &'s Scene,
+    container: &'c mut DieselContainer,
+    object_ids: SecondaryMap<ObjectKey, u32>,
+    material_ids: HashMap<MaterialKey, u32>,
+    skinbones_ids: HashMap<BindPoseKey, u32>,
+}
+
+impl<'s, 'c> SceneWriter<'s, 'c> {
+    fn new(scene: &'s Scene, container: &'c mut DieselContainer) -> Self {
+        Self {
+            scene,
+            container,
+            object_ids: SecondaryMap::new(),
+            material_ids: HashMap::new(),
+            skinbones_ids: HashMap::new(),
+        }
+    }
+
+    fn write(&mut self) {
+        // Every object needs a section id before any of them can be written, since parent
+        // links, skin roots and bone references all point at other objects that might not
+        // have been visited yet. Reserve ids with a placeholder Object3D now, and overwrite
+        // each one with its real content (and possibly a different section type) below.
+        for key in self.scene.objects.keys() {
+            let placeholder = fdm::Object3dSection {
+                name: hash_name(""),
+                animation_controllers: Vec::new(),
+                transform: Mat4f::identity(),
+                parent: 0xFFFFFFFFu32,
+            };
+            let id = self.container.push(Section::Object3D(Box::new(placeholder)));
+            self.object_ids.insert(key, id);
+        }
+
+        for (key, obj) in &self.scene.objects {
+            self.write_object(key, obj);
+        }
+    }
+
+    fn write_object(&mut self, key: ObjectKey, obj: &ir::Object) {
+        let id = self.object_ids[key];
+        let parent = obj.parent.map_or(0xFFFFFFFFu32, |p| self.object_ids[p]);
+
+        let object = fdm::Object3dSection {
+            name: hash_name(&obj.name),
+            // Sampled transform keyframes aren't re-emitted as animation controller
+            // chunks here - only the bind/rest transform round-trips.
+            animation_controllers: Vec::new(),
+            transform: obj.transform.into(),
+            parent,
+        };
+
+        let section = match &obj.data {
+            ObjectData::None | ObjectData::Armature(_) | ObjectData::Light(_) | ObjectData::Camera(_)
+            | ObjectData::Collision(_) => {
+                Section::Object3D(Box::new(object))
+            }
+            ObjectData::Mesh(mesh) => {
+                let data = self.write_mesh(mesh);
+                Section::Model(Box::new(fdm::ModelSection { object, data }))
+            }
+        };
+
+        self.container.insert(id, section);
+    }
+
+    fn write_mesh(&mut self, mesh: &Mesh) -> fdm::ModelData {
+        let aabb = mesh.compute_local_bounds();
+        let center = (aabb.min + aabb.max) * 0.5;
+        let radius = (aabb.max - center).magnitude();
+        let bounds = fdm::Bounds { min: aabb.min, max: aabb.max, radius, unknown_13: 0 };
+
+        if mesh.diesel.bounds_only {
+            return fdm::ModelData::BoundsOnly(bounds);
+        }
+
+        let GeomBuffers { geometry, topology, atoms } = build_geometry(mesh);
+
+        let geometry_id = self.container.push(Section::Geometry(Box::new(geometry)));
+        let topology_id = self.container.push(Section::Topology(Box::new(topology)));
+
+        let geometry_provider = self.container.push(Section::PassthroughGP(Box::new(
+            fdm::PassthroughGPSection { geometry: geometry_id, topology: topology_id },
+        )));
+        let topology_ip = self.container.push(Section::TopologyIP(Box::new(
+            fdm::TopologyIPSection { topology: topology_id },
+        )));
+
+        let material_group_ids: Vec<u32> = mesh.material_ids.iter()
+            .map(|m| self.intern_material(*m))
+            .collect();
+        let material_group = self.container.push(Section::MaterialGroup(Box::new(
+            fdm::MaterialGroupSection { material_ids: material_group_ids },
+        )));
+
+        let skinbones = match &mesh.skin {
+            Some(skin) => self.intern_skin(skin),
+            None => 0xFFFFFFFFu32,
+        };
+
+        fdm::ModelData::Mesh(fdm::MeshModel {
+            geometry_provider,
+            topology_ip,
+            render_atoms: atoms,
+            material_group,
+            lightset: 0xFFFFFFFFu32,
+            bounds,
+            properties: if mesh.diesel.cast_shadows { 1 } else { 0 },
+            skinbones,
+        })
+    }
+
+    fn intern_material(&mut self, mat: Option<MaterialKey>) -> u32 {
+        let Some(mat) = mat else { return 0xFFFFFFFFu32 };
+        if let Some(&id) = self.material_ids.get(&mat) {
+            return id;
+        }
+        let section = fdm::MaterialSection {
+            name: pd2tools_rust::diesel_hash::from_str(&self.scene.materials[mat].name),
+            items: Vec::new(),
+        };
+        let id = self.container.push(Section::Material(Box::new(section)));
+        self.material_ids.insert(mat, id);
+        id
+    }
+
+    fn intern_skin(&mut self, skin: &ir::SkinReference) -> u32 {
+        let bind_pose_key = match self.scene.objects[skin.armature].data {
+            ObjectData::Armature(bp) => bp,
+            _ => panic!("SkinReference::armature doesn't point at an armature object"),
+        };
+
+        if let Some(&id) = self.skinbones_ids.get(&bind_pose_key) {
+            return id;
+        }
+
+        let bind_pose = &self.scene.bind_poses[bind_pose_key];
+        let bones: Vec<u32> = bind_pose.joints.iter()
+            .map(|j| self.object_ids[j.bone])
+            .collect();
+        let inverse_bind_matrices: Vec<Mat4f> = bind_pose.joints.iter()
+            .map(|j| j.bindspace_to_bonespace)
+            .collect();
+
+        let section = fdm::SkinBonesSection {
+            root: self.object_ids[skin.armature],
+            global_transform: bind_pose.mid_to_bind,
+            inverse_bind_matrices,
+            bones,
+        };
+        let id = self.container.push(Section::SkinBones(Box::new(section)));
+        self.skinbones_ids.insert(bind_pose_key, id);
+        id
+    }
+}
+
+struct GeomBuffers {
+    geometry: fdm::GeometrySection,
+    topology: fdm::TopologySection,
+    atoms: Vec<fdm::RenderAtom>,
+}
+
+/// One fdm-vertex-buffer entry: everything FDM indexes per-vertex rather than per-faceloop
+/// (position, blend weights, normal/tangent/binormal and every texcoord/colour channel),
+/// bit-exact so that two loops that genuinely share every attribute collapse onto the same
+/// buffer entry instead of needlessly splitting the vertex.
+#[derive(Clone, PartialEq)]
+struct GeomVertex {
+    position: Vec3f,
+    weights: [ir::Weight; 8],
+    normal: Option<Vec3f>,
+    tangent: Option<Vec3f>,
+    binormal: Option<Vec3f>,
+    texcoords: [Option<Vec2f>; 8],
+    color_0: Option<Rgbaf>,
+    color_1: Option<Rgbaf>,
+}
+
+/// Re-interleaves `mesh`'s per-faceloop attributes (UVs, tangents, colours) back together with
+/// its per-vertex attributes (position, blend weights) into FDM's single per-vertex buffer,
+/// splitting any loop whose combination of attributes hasn't been seen before into a new buffer
+/// entry, and groups the resulting triangles into one [`fdm::RenderAtom`] per material so each
+/// atom's `base_index`/`triangle_count` describe a contiguous run of `topology.faces`.
+fn build_geometry(mesh: &Mesh) -> GeomBuffers {
+    // Sorted by weight (the same order `VertexGroups::sort_weights` puts them in) so that two
+    // loops with the same skinning but whose vertex groups were authored in a different order
+    // hash identically and still weld onto the same buffer entry, instead of needlessly
+    // splitting the vertex.
+    let weights_of = |vertex: usize| -> [ir::Weight; 8] {
+        let mut sorted: Vec<ir::Weight> = mesh.vertex_groups[vertex].to_vec();
+        sorted.sort_by(|a, b| a.weight.partial_cmp(&b.weight).unwrap());
+
+        let mut out = [ir::Weight::default(); 8];
+        for (i, w) in sorted.iter().take(8).enumerate() {
+            out[i] = *w;
+        }
+        out
+    };
+
+    let vgroup_to_joint = mesh.skin.as_ref().map(|s| s.vgroup_to_joint_mapping.as_slice());
+
+    let loop_vertex = |loop_idx: usize| -> GeomVertex {
+        let fl = &mesh.faceloops[loop_idx];
+        let (normal, tangent, binormal) = match &mesh.faceloop_tangents {
+            ir::TangentLayer::None => (None, None, None),
+            ir::TangentLayer::Normals(ns) => (Some(ns[loop_idx]), None, None),
+            ir::TangentLayer::Tangents(ts) => {
+                let t = &ts[loop_idx];
+                (Some(t.normal), Some(t.tangent), Some(t.bitangent))
+            }
+        };
+
+        let mut texcoords: [Option<Vec2f>; 8] = Default::default();
+        for (i, (_name, data)) in mesh.faceloop_uvs.iter().enumerate().take(8) {
+            texcoords[i] = Some(data[loop_idx]);
+        }
+
+        let colors: Vec<&Vec<Rgbaf>> = mesh.faceloop_colors.values().collect();
+        let color_0 = colors.get(0).map(|c| c[loop_idx]);
+        let color_1 = colors.get(1).map(|c| c[loop_idx]);
+
+        let mut weights = weights_of(fl.vertex);
+        if let Some(mapping) = vgroup_to_joint {
+            for w in weights.iter_mut() {
+                if w.weight > 0.0 {
+                    w.group = mapping[w.group];
+                }
+            }
+        }
+
+        GeomVertex {
+            position: mesh.vertices[fl.vertex],
+            weights,
+            normal,
+            tangent,
+            binormal,
+            texcoords,
+            color_0,
+            color_1,
+        }
+    };
+
+    // Group triangles by material id, since `RenderAtom::base_index`/`triangle_count`
+    // only describe a single contiguous run of `topology.faces`.
+    let mut triangles_by_material: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (ti, tri) in mesh.triangles.iter().enumerate() {
+        let material = mesh.polygons[tri.polygon].material;
+        triangles_by_material.entry(material).or_default().push(ti);
+    }
+    let mut material_order: Vec<usize> = triangles_by_material.keys().copied().collect();
+    material_order.sort_unstable();
+
+    let mut vertices: Vec<GeomVertex> = Vec::new();
+    let mut dedup: HashMap<Vec<u8>, u32> = HashMap::new();
+    let mut faces: Vec<u16> = Vec::new();
+    let mut atoms = Vec::with_capacity(material_order.len());
+
+    let key_of = |v: &GeomVertex| -> Vec<u8> {
+        let mut k = Vec::with_capacity(64);
+        k.extend_from_slice(bytemuck::bytes_of(&v.position));
+        k.extend_from_slice(bytemuck::bytes_of(&v.weights));
+        for opt in [v.normal, v.tangent, v.binormal] {
+            match opt {
+                Some(v) => { k.push(1); k.extend_from_slice(bytemuck::bytes_of(&v)); }
+                None => k.push(0),
+            }
+        }
+        for opt in v.texcoords {
+            match opt {
+                Some(v) => { k.push(1); k.extend_from_slice(bytemuck::bytes_of(&v)); }
+                None => k.push(0),
+            }
+        }
+        for opt in [v.color_0, v.color_1] {
+            match opt {
+                Some(v) => { k.push(1); k.extend_from_slice(bytemuck::bytes_of(&v)); }
+                None => k.push(0),
+            }
+        }
+        k
+    };
+
+    for material in material_order {
+        let tri_indices = &triangles_by_material[&material];
+        let base_index = faces.len() as u32;
+        let mut min_vertex = u32::MAX;
+        let mut max_vertex = 0u32;
+
+        for &ti in tri_indices {
+            let tri = &mesh.triangles[ti];
+            for &loop_idx in &tri.loops {
+                let gv = loop_vertex(loop_idx);
+                let key = key_of(&gv);
+                let idx = *dedup.entry(key).or_insert_with(|| {
+                    let idx = vertices.len() as u32;
+                    vertices.push(gv);
+                    idx
+                });
+                min_vertex = min_vertex.min(idx);
+                max_vertex = max_vertex.max(idx);
+                faces.push(idx.try_into().expect("fdm meshes can't address more than 65536 vertices"));
+            }
+        }
+
+        atoms.push(fdm::RenderAtom {
+            base_vertex: min_vertex,
+            triangle_count: tri_indices.len() as u32,
+            base_index,
+            geometry_slice_length: max_vertex - min_vertex + 1,
+            material: material as u32,
+        });
+    }
+
+    let has_normals = vertices.iter().any(|v| v.normal.is_some());
+    let has_tangents = vertices.iter().any(|v| v.tangent.is_some());
+    let has_weights_1 = vertices.iter().any(|v| v.weights[4].weight > 0.0);
+
+    let mut geometry = fdm::GeometrySection::default();
+    geometry.name = hash_name("geometry");
+    geometry.position = vertices.iter().map(|v| v.position).collect();
+    if has_normals {
+        geometry.normal = vertices.iter().map(|v| v.normal.unwrap_or(Vec3f::zero())).collect();
+    }
+    if has_tangents {
+        geometry.tangent = vertices.iter().map(|v| v.tangent.unwrap_or(Vec3f::zero())).collect();
+        geometry.binormal = vertices.iter().map(|v| v.binormal.unwrap_or(Vec3f::zero())).collect();
+    }
+    for i in 0..8 {
+        if vertices.iter().any(|v| v.texcoords[i].is_some()) {
+            let data = vertices.iter().map(|v| v.texcoords[i].unwrap_or(Vec2f::zero())).collect();
+            set_texcoord(&mut geometry, i, data);
+        }
+    }
+    if vertices.iter().any(|v| v.color_0.is_some()) {
+        geometry.color_0 = vertices.iter().map(|v| v.color_0.unwrap_or(Rgbaf::zero()).map(|c| (c * 255.0) as u8)).collect();
+    }
+    if vertices.iter().any(|v| v.color_1.is_some()) {
+        geometry.color_1 = vertices.iter().map(|v| v.color_1.unwrap_or(Rgbaf::zero()).map(|c| (c * 255.0) as u8)).collect();
+    }
+    if mesh.skin.is_some() {
+        geometry.weightcount_0 = 4;
+        geometry.blend_indices_0 = vertices.iter().map(|v| weight_indices(&v.weights[0..4])).collect();
+        geometry.blend_weight_0 = vertices.iter().map(|v| weight_values(&v.weights[0..4])).collect();
+        if has_weights_1 {
+            geometry.weightcount_1 = 4;
+            geometry.blend_indices_1 = vertices.iter().map(|v| weight_indices(&v.weights[4..8])).collect();
+            geometry.blend_weight_1 = vertices.iter().map(|v| weight_values(&v.weights[4..8])).collect();
+        }
+    }
+
+    let topology = fdm::TopologySection {
+        unknown_1: 0,
+        faces,
+        unknown_2: Vec::new(),
+        name: hash_name("topology"),
+    };
+
+    GeomBuffers { geometry, topology, atoms }
+}
+
+fn weight_indices(weights: &[ir::Weight]) -> vek::Vec4<u16> {
+    vek::Vec4::new(
+        weights[0].group as u16,
+        weights[1].group as u16,
+        weights[2].group as u16,
+        weights[3].group as u16,
+    )
+}
+
+fn weight_values(weights: &[ir::Weight]) -> Vec4f {
+    Vec4f::new(weights[0].weight, weights[1].weight, weights[2].weight, weights[3].weight)
+}
+
+fn set_texcoord(geometry: &mut fdm::GeometrySection, index: usize, data: Vec<Vec2f>) {
+    match index {
+        0 => geometry.tex_coord_0 = data,
+        1 => geometry.tex_coord_1 = data,
+        2 => geometry.tex_coord_2 = data,
+        3 => geometry.tex_coord_3 = data,
+        4 => geometry.tex_coord_4 = data,
+        5 => geometry.tex_coord_5 = data,
+        6 => geometry.tex_coord_6 = data,
+        7 => geometry.tex_coord_7 = data,
+        _ => unreachable!("GeometrySection only has 8 texcoord channels"),
+    }
+}
@@ -0,0 +1,651 @@
+//! glTF 2.0 / GLB export for [`model_ir::Scene`] - lets a Payday model reach
+//! any modern DCC tool straight from the IR, without going through Blender's
+//! own glTF plugin. Unlike [`crate::ir_writer_oil`], which keeps one shared
+//! vertex buffer per mesh and indexes into it per-channel the way OIL itself
+//! does, glTF wants every attribute set co-indexed - so this writer expands
+//! each of [`Mesh`]'s faceloops into its own glTF vertex (mirroring
+//! [`pd2tools_rust::formats::fdm::export_gltf`]'s per-render-atom approach,
+//! just at faceloop granularity instead of the raw vertex buffer's) and
+//! groups the resulting triangles into one primitive per material.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use pyo3::{PyAny, PyResult};
+use serde::Serialize;
+use vek::{Vec3, Vec4};
+
+use pd2tools_rust::bundles::database::Database;
+use crate::PyEnv;
+use crate::material_registry::MaterialRegistry;
+use crate::model_ir::{Mesh, MaterialKey, ObjectData, ObjectKey, Scene, SkinReference, TangentLayer};
+use crate::vek_types::*;
+
+#[derive(Debug)]
+pub enum ExportGltfError {
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+impl From<io::Error> for ExportGltfError {
+    fn from(e: io::Error) -> Self { ExportGltfError::Io(e) }
+}
+impl From<serde_json::Error> for ExportGltfError {
+    fn from(e: serde_json::Error) -> Self { ExportGltfError::Json(e) }
+}
+
+const COMPONENT_UNSIGNED_SHORT: u32 = 5123;
+const COMPONENT_FLOAT: u32 = 5126;
+
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Serializes `scene` to `gltf_path`, alongside a sibling `.bin` holding
+/// every accessor's data.
+pub fn write_to_files(scene: &Scene, gltf_path: &Path) -> Result<(), ExportGltfError> {
+    let bin_path = gltf_path.with_extension("bin");
+    let bin_name = bin_path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "scene.bin".to_owned());
+
+    let mut exporter = Exporter::new(scene);
+    exporter.run();
+    let (document, bin_bytes) = exporter.finish(bin_name);
+
+    fs::write(&bin_path, &bin_bytes)?;
+    fs::write(gltf_path, serde_json::to_vec_pretty(&document)?)?;
+    Ok(())
+}
+
+struct Exporter<'s> {
+    scene: &'s Scene,
+    buffer: BufferBuilder,
+    nodes: Vec<Node>,
+    meshes: Vec<GltfMesh>,
+    materials: Vec<Material>,
+    material_index: std::collections::HashMap<MaterialKey, usize>,
+    skins: Vec<Skin>,
+    skin_index: std::collections::HashMap<ObjectKey, usize>,
+    lights: Vec<Light>,
+    object_to_node: slotmap::SecondaryMap<ObjectKey, usize>,
+    root_nodes: Vec<usize>,
+}
+
+impl<'s> Exporter<'s> {
+    fn new(scene: &'s Scene) -> Self {
+        Exporter {
+            scene,
+            buffer: BufferBuilder::new(),
+            nodes: Vec::with_capacity(scene.objects.len()),
+            meshes: Vec::new(),
+            materials: Vec::new(),
+            material_index: std::collections::HashMap::new(),
+            skins: Vec::new(),
+            skin_index: std::collections::HashMap::new(),
+            lights: Vec::new(),
+            object_to_node: slotmap::SecondaryMap::new(),
+            root_nodes: Vec::new(),
+        }
+    }
+
+    fn run(&mut self) {
+        for (key, obj) in &self.scene.objects {
+            let idx = self.nodes.len();
+            self.nodes.push(Node {
+                name: Some(obj.name.clone()),
+                children: Vec::new(),
+                mesh: None,
+                skin: None,
+                translation: obj.transform.position.into_array(),
+                rotation: [obj.transform.orientation.x, obj.transform.orientation.y, obj.transform.orientation.z, obj.transform.orientation.w],
+                scale: obj.transform.scale.into_array(),
+                extensions: None,
+            });
+            self.object_to_node.insert(key, idx);
+        }
+
+        // Meshes/skins reference other nodes by index, so those links can
+        // only be resolved once every node above has one - hence the second
+        // pass here instead of filling them in during the loop above.
+        for (key, obj) in &self.scene.objects {
+            let node_idx = self.object_to_node[key];
+            match &obj.data {
+                ObjectData::Mesh(me) => {
+                    let mesh_idx = self.add_mesh(me);
+                    self.nodes[node_idx].mesh = Some(mesh_idx);
+                    if let Some(skin) = &me.skin {
+                        self.nodes[node_idx].skin = Some(self.add_skin(skin));
+                    }
+                },
+                ObjectData::Light(l) => self.add_light(node_idx, l),
+                _ => {}
+            }
+        }
+
+        for (key, obj) in &self.scene.objects {
+            let idx = self.object_to_node[key];
+            match obj.parent {
+                Some(parent) => self.nodes[self.object_to_node[parent]].children.push(idx),
+                None => self.root_nodes.push(idx),
+            }
+        }
+    }
+
+    fn add_mesh(&mut self, me: &Mesh) -> usize {
+        let faceloop_count = me.faceloops.len();
+
+        let positions: Vec<Vec3f> = me.faceloops.iter().map(|fl| me.vertices[fl.vertex]).collect();
+        let mut attributes = BTreeMap::new();
+        attributes.insert("POSITION".to_owned(), self.buffer.push_vec3_accessor(&positions, true));
+
+        match &me.faceloop_tangents {
+            TangentLayer::None => {},
+            TangentLayer::Normals(normals) => {
+                attributes.insert("NORMAL".to_owned(), self.buffer.push_vec3_accessor(normals, false));
+            },
+            TangentLayer::Tangents(tangents) => {
+                let normals: Vec<Vec3f> = tangents.iter().map(|t| t.normal).collect();
+                attributes.insert("NORMAL".to_owned(), self.buffer.push_vec3_accessor(&normals, false));
+                attributes.insert("TANGENT".to_owned(), self.buffer.push_tangent_accessor(tangents));
+            },
+        }
+
+        for (name, uvs) in &me.faceloop_uvs {
+            attributes.insert(name.clone(), self.buffer.push_vec2_accessor(uvs));
+        }
+        for (name, cols) in &me.faceloop_colors {
+            attributes.insert(name.clone(), self.buffer.push_color_accessor(cols));
+        }
+
+        if let Some(skin) = &me.skin {
+            let (joints_0, weights_0, joints_1, weights_1) = self.build_skin_streams(me, skin, faceloop_count);
+            attributes.insert("JOINTS_0".to_owned(), self.buffer.push_joints_accessor(&joints_0));
+            attributes.insert("WEIGHTS_0".to_owned(), self.buffer.push_weights_accessor(&weights_0));
+            if me.vertex_groups.vertices.iter().any(|bc| bc.count > 4) {
+                attributes.insert("JOINTS_1".to_owned(), self.buffer.push_joints_accessor(&joints_1));
+                attributes.insert("WEIGHTS_1".to_owned(), self.buffer.push_weights_accessor(&weights_1));
+            }
+        }
+
+        // Every polygon this reader ever produces is a triangle (see
+        // `ir_reader_fdm::add_mesh`), so each one contributes exactly the
+        // three faceloop indices making up that triangle - there's no
+        // arbitrary-gon fan-triangulation to do here.
+        let mut by_material: BTreeMap<usize, Vec<u16>> = BTreeMap::new();
+        for poly in &me.polygons {
+            let tri = by_material.entry(poly.material).or_default();
+            for i in 0..poly.count {
+                tri.push((poly.base + i) as u16);
+            }
+        }
+
+        let mut primitives = Vec::with_capacity(by_material.len());
+        for (local_mat, indices) in by_material {
+            let material = me.material_ids.get(local_mat).copied().flatten()
+                .map(|mk| self.material_for(mk));
+            primitives.push(Primitive {
+                attributes: attributes.clone(),
+                indices: self.buffer.push_indices(&indices),
+                material,
+            });
+        }
+
+        self.meshes.push(GltfMesh { primitives });
+        self.meshes.len() - 1
+    }
+
+    /// Splits each faceloop's up-to-8 [`crate::model_ir::Weight`] entries
+    /// (looked up through the faceloop's source vertex, since weights are
+    /// per-vertex data) into glTF's fixed 4-wide JOINTS_0/WEIGHTS_0 and
+    /// JOINTS_1/WEIGHTS_1, renormalizing the surviving weights of each
+    /// vertex to sum to 1.0.
+    fn build_skin_streams(&self, me: &Mesh, skin: &SkinReference, faceloop_count: usize) -> (Vec<Vec4<u16>>, Vec<Vec4f>, Vec<Vec4<u16>>, Vec<Vec4f>) {
+        let mut joints_0 = Vec::with_capacity(faceloop_count);
+        let mut weights_0 = Vec::with_capacity(faceloop_count);
+        let mut joints_1 = Vec::with_capacity(faceloop_count);
+        let mut weights_1 = Vec::with_capacity(faceloop_count);
+
+        for fl in &me.faceloops {
+            let weights = &me.vertex_groups[fl.vertex];
+            let sum: f32 = weights.iter().map(|w| w.weight).sum();
+            let norm = if sum > 0.0 { 1.0 / sum } else { 0.0 };
+
+            let mut j = [0u16; 8];
+            let mut w = [0.0f32; 8];
+            for (i, weight) in weights.iter().take(8).enumerate() {
+                j[i] = skin.vgroup_to_joint_mapping[weight.group] as u16;
+                w[i] = weight.weight * norm;
+            }
+
+            joints_0.push(Vec4::new(j[0], j[1], j[2], j[3]));
+            weights_0.push(Vec4::new(w[0], w[1], w[2], w[3]));
+            joints_1.push(Vec4::new(j[4], j[5], j[6], j[7]));
+            weights_1.push(Vec4::new(w[4], w[5], w[6], w[7]));
+        }
+
+        (joints_0, weights_0, joints_1, weights_1)
+    }
+
+    fn add_skin(&mut self, skin: &SkinReference) -> usize {
+        if let Some(&idx) = self.skin_index.get(&skin.armature) {
+            return idx;
+        }
+
+        let bind_pose = match &self.scene.objects[skin.armature].data {
+            ObjectData::Armature(bpk) => &self.scene.bind_poses[*bpk],
+            _ => panic!("skin references an armature object with no bind pose"),
+        };
+        let postmul_transform = skin.model_to_mid * bind_pose.mid_to_bind;
+
+        let joints: Vec<usize> = bind_pose.joints.iter().map(|j| self.object_to_node[j.bone]).collect();
+        let inverse_binds: Vec<Mat4f> = bind_pose.joints.iter()
+            .map(|j| j.bindspace_to_bonespace * postmul_transform)
+            .collect();
+        let ibm_accessor = self.buffer.push_mat4_accessor(&inverse_binds);
+
+        let idx = self.skins.len();
+        self.skins.push(Skin {
+            inverse_bind_matrices: ibm_accessor,
+            skeleton: self.object_to_node[skin.armature],
+            joints,
+        });
+        self.skin_index.insert(skin.armature, idx);
+        idx
+    }
+
+    fn material_for(&mut self, mat_id: MaterialKey) -> usize {
+        if let Some(&idx) = self.material_index.get(&mat_id) {
+            return idx;
+        }
+        let idx = self.materials.len();
+        self.materials.push(Material {
+            name: self.scene.materials[mat_id].name.clone(),
+            pbr_metallic_roughness: PbrMetallicRoughness::default(),
+        });
+        self.material_index.insert(mat_id, idx);
+        idx
+    }
+
+    fn add_light(&mut self, node_idx: usize, light: &crate::model_ir::Light) {
+        use crate::model_ir::LightKind;
+        let light_idx = self.lights.len();
+        self.lights.push(Light {
+            type_: match light.kind {
+                LightKind::Point | LightKind::Area => "point",
+                LightKind::Sun => "directional",
+                LightKind::Spot => "spot",
+            },
+            color: [light.color.r, light.color.g, light.color.b],
+            intensity: light.energy,
+            range: if light.range > 0.0 { Some(light.range) } else { None },
+        });
+        self.nodes[node_idx].extensions = Some(NodeExtensions {
+            khr_lights_punctual: NodeLightRef { light: light_idx },
+        });
+    }
+
+    fn finish(self, bin_name: String) -> (Document, Vec<u8>) {
+        let extensions_used = if self.lights.is_empty() { Vec::new() } else { vec!["KHR_lights_punctual"] };
+        let extensions = if self.lights.is_empty() {
+            None
+        } else {
+            Some(DocumentExtensions { khr_lights_punctual: KhrLightsPunctual { lights: self.lights } })
+        };
+
+        let buffer_bytes = self.buffer.bytes;
+        let document = Document {
+            asset: Asset { version: "2.0" },
+            extensions_used,
+            scene: 0,
+            scenes: vec![GltfScene { nodes: self.root_nodes }],
+            nodes: self.nodes,
+            meshes: self.meshes,
+            materials: self.materials,
+            skins: self.skins,
+            accessors: self.buffer.accessors,
+            buffer_views: self.buffer.buffer_views,
+            buffers: vec![Buffer { uri: bin_name, byte_length: buffer_bytes.len() }],
+            extensions,
+        };
+        (document, buffer_bytes)
+    }
+}
+
+/// Accumulates every accessor's raw bytes into one flat buffer, padding each
+/// new bufferView onto a 4-byte boundary as glTF requires.
+struct BufferBuilder {
+    bytes: Vec<u8>,
+    buffer_views: Vec<BufferView>,
+    accessors: Vec<Accessor>,
+}
+
+impl BufferBuilder {
+    fn new() -> Self {
+        BufferBuilder { bytes: Vec::new(), buffer_views: Vec::new(), accessors: Vec::new() }
+    }
+
+    fn push_view(&mut self, data: &[u8], target: Option<u32>) -> usize {
+        while self.bytes.len() % 4 != 0 { self.bytes.push(0); }
+        let byte_offset = self.bytes.len();
+        self.bytes.extend_from_slice(data);
+        self.buffer_views.push(BufferView { byte_offset, byte_length: data.len(), target });
+        self.buffer_views.len() - 1
+    }
+
+    fn push_vec3_accessor(&mut self, data: &[Vec3f], bounded: bool) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 12);
+        for v in data {
+            raw.extend_from_slice(&v.x.to_le_bytes());
+            raw.extend_from_slice(&v.y.to_le_bytes());
+            raw.extend_from_slice(&v.z.to_le_bytes());
+        }
+        let view = self.push_view(&raw, Some(TARGET_ARRAY_BUFFER));
+        let (min, max) = if bounded { vec3_bounds(data) } else { (None, None) };
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: data.len(), type_: "VEC3",
+            normalized: None, min, max
+        });
+        self.accessors.len() - 1
+    }
+
+    fn push_vec2_accessor(&mut self, data: &[Vec2f]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 8);
+        for v in data {
+            raw.extend_from_slice(&v.x.to_le_bytes());
+            raw.extend_from_slice(&v.y.to_le_bytes());
+        }
+        let view = self.push_view(&raw, Some(TARGET_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: data.len(), type_: "VEC2",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    /// glTF's TANGENT is `vec4(tangent.xyz, handedness)`, with the handedness
+    /// sign recovered from `bitangent . (normal x tangent)` - see this
+    /// module's docs for why that's what's stored instead of a raw binormal.
+    fn push_tangent_accessor(&mut self, tangents: &[crate::model_ir::Tangent]) -> usize {
+        let mut raw = Vec::with_capacity(tangents.len() * 16);
+        for t in tangents {
+            let w = if t.normal.cross(t.tangent).dot(t.bitangent) < 0.0 { -1.0f32 } else { 1.0f32 };
+            raw.extend_from_slice(&t.tangent.x.to_le_bytes());
+            raw.extend_from_slice(&t.tangent.y.to_le_bytes());
+            raw.extend_from_slice(&t.tangent.z.to_le_bytes());
+            raw.extend_from_slice(&w.to_le_bytes());
+        }
+        let view = self.push_view(&raw, Some(TARGET_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: tangents.len(), type_: "VEC4",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    fn push_color_accessor(&mut self, data: &[Rgbaf]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 16);
+        for c in data {
+            raw.extend_from_slice(&c.r.to_le_bytes());
+            raw.extend_from_slice(&c.g.to_le_bytes());
+            raw.extend_from_slice(&c.b.to_le_bytes());
+            raw.extend_from_slice(&c.a.to_le_bytes());
+        }
+        let view = self.push_view(&raw, Some(TARGET_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: data.len(), type_: "VEC4",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    fn push_joints_accessor(&mut self, data: &[Vec4<u16>]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 8);
+        for v in data {
+            raw.extend_from_slice(&v.x.to_le_bytes());
+            raw.extend_from_slice(&v.y.to_le_bytes());
+            raw.extend_from_slice(&v.z.to_le_bytes());
+            raw.extend_from_slice(&v.w.to_le_bytes());
+        }
+        let view = self.push_view(&raw, Some(TARGET_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_UNSIGNED_SHORT, count: data.len(), type_: "VEC4",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    fn push_weights_accessor(&mut self, data: &[Vec4f]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 16);
+        for v in data {
+            raw.extend_from_slice(&v.x.to_le_bytes());
+            raw.extend_from_slice(&v.y.to_le_bytes());
+            raw.extend_from_slice(&v.z.to_le_bytes());
+            raw.extend_from_slice(&v.w.to_le_bytes());
+        }
+        let view = self.push_view(&raw, Some(TARGET_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: data.len(), type_: "VEC4",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    fn push_indices(&mut self, data: &[u16]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 2);
+        for i in data { raw.extend_from_slice(&i.to_le_bytes()); }
+        let view = self.push_view(&raw, Some(TARGET_ELEMENT_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_UNSIGNED_SHORT, count: data.len(), type_: "SCALAR",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    fn push_mat4_accessor(&mut self, data: &[Mat4f]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 64);
+        for m in data {
+            for c in &m.cols {
+                raw.extend_from_slice(&c.x.to_le_bytes());
+                raw.extend_from_slice(&c.y.to_le_bytes());
+                raw.extend_from_slice(&c.z.to_le_bytes());
+                raw.extend_from_slice(&c.w.to_le_bytes());
+            }
+        }
+        let view = self.push_view(&raw, None);
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: data.len(), type_: "MAT4",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+}
+
+fn vec3_bounds(data: &[Vec3f]) -> (Option<Vec<f32>>, Option<Vec<f32>>) {
+    let mut min = data[0];
+    let mut max = data[0];
+    for v in data {
+        min = Vec3::partial_min(min, *v);
+        max = Vec3::partial_max(max, *v);
+    }
+    (Some(vec![min.x, min.y, min.z]), Some(vec![max.x, max.y, max.z]))
+}
+
+#[derive(Serialize)]
+struct Document {
+    asset: Asset,
+    #[serde(rename = "extensionsUsed", skip_serializing_if = "Vec::is_empty")]
+    extensions_used: Vec<&'static str>,
+    scene: usize,
+    scenes: Vec<GltfScene>,
+    nodes: Vec<Node>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    meshes: Vec<GltfMesh>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    materials: Vec<Material>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    skins: Vec<Skin>,
+    accessors: Vec<Accessor>,
+    #[serde(rename = "bufferViews")]
+    buffer_views: Vec<BufferView>,
+    buffers: Vec<Buffer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extensions: Option<DocumentExtensions>,
+}
+
+#[derive(Serialize)]
+struct Asset { version: &'static str }
+
+#[derive(Serialize)]
+struct GltfScene { nodes: Vec<usize> }
+
+#[derive(Serialize)]
+struct Node {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mesh: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skin: Option<usize>,
+    translation: [f32; 3],
+    rotation: [f32; 4],
+    scale: [f32; 3],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extensions: Option<NodeExtensions>,
+}
+
+#[derive(Serialize)]
+struct NodeExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    khr_lights_punctual: NodeLightRef,
+}
+
+#[derive(Serialize)]
+struct NodeLightRef { light: usize }
+
+#[derive(Serialize)]
+struct GltfMesh { primitives: Vec<Primitive> }
+
+#[derive(Serialize, Clone)]
+struct Primitive {
+    attributes: BTreeMap<String, usize>,
+    indices: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    material: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Material {
+    name: String,
+    pbr_metallic_roughness: PbrMetallicRoughness,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PbrMetallicRoughness {
+    base_color_factor: [f32; 4],
+    metallic_factor: f32,
+    roughness_factor: f32,
+}
+impl Default for PbrMetallicRoughness {
+    fn default() -> Self {
+        PbrMetallicRoughness { base_color_factor: [1.0, 1.0, 1.0, 1.0], metallic_factor: 1.0, roughness_factor: 1.0 }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Skin {
+    inverse_bind_matrices: usize,
+    skeleton: usize,
+    joints: Vec<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: usize,
+    component_type: u32,
+    count: usize,
+    #[serde(rename = "type")]
+    type_: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normalized: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<Vec<f32>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    byte_offset: usize,
+    byte_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Buffer {
+    uri: String,
+    byte_length: usize,
+}
+
+#[derive(Serialize)]
+struct DocumentExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    khr_lights_punctual: KhrLightsPunctual,
+}
+
+#[derive(Serialize)]
+struct KhrLightsPunctual { lights: Vec<Light> }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Light {
+    #[serde(rename = "type")]
+    type_: &'static str,
+    color: [f32; 3],
+    intensity: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    range: Option<f32>,
+}
+
+/// Gathers `object` (and its selected descendants) from bpy into a
+/// [`model_ir::Scene`](crate::model_ir::Scene), then writes it to
+/// `output_path` as glTF/GLB - the same bpy-gather/release-the-GIL split
+/// [`crate::ir_writer_oil::export`] uses, since the conversion to glTF is
+/// just as GIL-free once the scene is in IR form.
+pub fn export(env: PyEnv, output_path: &str, meters_per_unit: f32, default_author_tag: &str, object: &PyAny, database: Option<&Database>) -> PyResult<()> {
+    let scene = crate::ir_blender::scene_from_bpy_selected(&env, object, meters_per_unit, default_author_tag)?;
+
+    let python = env.python;
+    python.allow_threads(|| -> PyResult<()> {
+        let mut scene = scene;
+
+        // Unlike Diesel/OIL, which are fixed to centimetres, glTF's own spec
+        // recommends metres - so unlike `ir_writer_oil::export`, this rescales
+        // towards 1.0 rather than 0.01.
+        if f32::abs(1.0 - meters_per_unit) > 0.000244140625f32 { // arbitrary threshold, matches ir_writer_oil::export
+            scene.change_scale(1.0);
+        }
+
+        let mut materials = MaterialRegistry::new();
+        for (_, obj) in scene.objects.iter_mut() {
+            if let ObjectData::Mesh(me) = &mut obj.data {
+                me.vcols_to_faceloop_cols();
+                if let Some(db) = database {
+                    me.resolve_materials(&mut materials, db);
+                }
+            }
+        }
+
+        write_to_files(&scene, Path::new(output_path))
+            .map_err(|e| pyo3::exceptions::PyException::new_err(format!("Failed to write glTF: {:?}", e)))
+    })
+}
@@ -1,17 +1,116 @@
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
 use std::convert::TryInto;
+use std::hash::{Hash, Hasher};
 
 use pyo3::{PyAny, PyResult};
 
+use pd2tools_rust::bundles::database::Database;
 use pd2tools_rust::formats::oil;
 use slotmap::SecondaryMap;
 use crate::PyEnv;
-use crate::model_ir::{Mesh, MaterialKey, Scene, ObjectData};
+use crate::material_registry::MaterialRegistry;
+use crate::model_ir::{Mesh, MaterialKey, Polygon, Scene, ObjectData};
+
+/// Components are quantized to this many fractional bits before hashing in
+/// [`content_hash128`], so that two channels which really are the same data
+/// - just produced through slightly different floating-point paths - still
+/// hash identically instead of being defeated by the last couple of bits of
+/// noise. `2^20` is about a micrometer at Diesel's 1cm-per-unit scale.
+const CHANNEL_QUANT_SHIFT: f64 = (1u64 << 20) as f64;
+
+fn hash_component<H: Hasher>(h: &mut H, v: f64) {
+    if v.is_finite() {
+        h.write_u8(0);
+        h.write_i64((v * CHANNEL_QUANT_SHIFT).round() as i64);
+    } else {
+        // NaN and the infinities have no sensible quantization, and NaN
+        // doesn't even equal itself, so every non-finite component hashes
+        // to this one reserved sentinel instead. The finite branch above
+        // always starts with discriminant byte 0, so this can never
+        // collide with a real quantized value.
+        h.write_u8(1);
+    }
+}
+
+/// A 128-bit content hash for one geometry channel: its kind tag and index
+/// (so e.g. UV channel 1 and UV channel 2 never compare equal even if their
+/// data does), plus every float component of its data, quantized through
+/// [`hash_component`]. Used as a `HashMap` key in [`ChannelCollector`], so
+/// this is trusting a 128-bit hash not to collide by chance rather than
+/// doing a real equality check.
+fn content_hash128(kind: u8, index: u32, components: impl IntoIterator<Item = f64>) -> u128 {
+    let mut h1 = DefaultHasher::new();
+    let mut h2 = DefaultHasher::new();
+    kind.hash(&mut h1);
+    index.hash(&mut h1);
+    kind.hash(&mut h2);
+    index.hash(&mut h2);
+    0xA5u8.hash(&mut h2); // perturb h2's state so it diverges from h1
+    for c in components {
+        hash_component(&mut h1, c);
+        hash_component(&mut h2, c);
+    }
+    ((h1.finish() as u128) << 64) | (h2.finish() as u128)
+}
+
+/// Collects the channels of a single [`oil::Geometry`], folding away any
+/// channel whose content hash exactly matches one already collected -
+/// e.g. a second UV or vertex-colour layer an artist duplicated without
+/// editing - and returning the `og.channels` index to use in
+/// [`oil::GeometryFaceloop::channel`] either way. Scoped to one mesh: a
+/// channel's tag only has meaning within its own `Geometry` chunk, so
+/// there's no cross-mesh sharing to be had here the way there is for
+/// materials below.
+struct ChannelCollector {
+    channels: Vec<oil::GeometryChannel>,
+    by_hash: HashMap<u128, u32>
+}
+impl ChannelCollector {
+    fn new() -> Self {
+        ChannelCollector { channels: Vec::with_capacity(5), by_hash: HashMap::new() }
+    }
+
+    fn push(&mut self, hash: u128, channel: oil::GeometryChannel) -> u32 {
+        if let Some(&id) = self.by_hash.get(&hash) {
+            return id;
+        }
+        let id = self.channels.len() as u32;
+        self.channels.push(channel);
+        self.by_hash.insert(hash, id);
+        id
+    }
+
+    fn finish(self) -> Vec<oil::GeometryChannel> {
+        self.channels
+    }
+}
+
+/// A content hash for a material record, used by [`MaterialCollector`] to
+/// fold together materials that were loaded as distinct `MaterialKey`s but
+/// are otherwise identical - e.g. the same material name reused across two
+/// mesh objects' material slots. `parent_id` is part of the hash so this
+/// can never reparent a multi-material child under the wrong parent: two
+/// solo materials always share the sentinel parent and so are free to
+/// dedup, but two children of different `MultiMaterial`s never share a
+/// parent id and so never collapse into each other.
+fn material_content_hash(name: &str, parent_id: u32) -> u128 {
+    let mut h1 = DefaultHasher::new();
+    let mut h2 = DefaultHasher::new();
+    name.hash(&mut h1);
+    parent_id.hash(&mut h1);
+    name.hash(&mut h2);
+    parent_id.hash(&mut h2);
+    0xA5u8.hash(&mut h2);
+    ((h1.finish() as u128) << 64) | (h2.finish() as u128)
+}
 
 struct MaterialCollector<'s> {
     scene: &'s Scene,
     next_id: u32,
     collected: Vec<oil::Material>,
-    solo_mats: SecondaryMap<MaterialKey, u32> 
+    solo_mats: SecondaryMap<MaterialKey, u32>,
+    by_content: HashMap<u128, u32>
 }
 impl<'s> MaterialCollector<'s> {
     fn new(scene: &'s Scene, next_id: u32) -> Self {
@@ -19,13 +118,19 @@ impl<'s> MaterialCollector<'s> {
             scene,
             next_id,
             collected: Vec::new(),
-            solo_mats: SecondaryMap::new()
+            solo_mats: SecondaryMap::new(),
+            by_content: HashMap::new()
         }
     }
 
     fn append_material(&mut self, name: String, parent_id: u32) -> u32 {
+        let hash = material_content_hash(&name, parent_id);
+        if let Some(&id) = self.by_content.get(&hash) {
+            return id;
+        }
         let id = self.next_id;
         self.next_id += 1;
+        self.by_content.insert(hash, id);
         self.collected.push(oil::Material { id, name, parent_id });
         id
     }
@@ -77,7 +182,183 @@ impl<'s> MaterialCollector<'s> {
     }
 }
 
-fn mesh_to_oil_geometry(node_id: u32, me: &Mesh, materials: &mut MaterialCollector) -> oil::Geometry {
+fn light_to_oil(node_id: u32, light: &crate::model_ir::Light) -> oil::Light {
+    use crate::model_ir::LightKind;
+
+    let (lamp_type, shape) = match light.kind {
+        LightKind::Spot => (oil::LightType::Spot, oil::SpotlightShape::Circular),
+        LightKind::Sun => (oil::LightType::Directional, oil::SpotlightShape::Circular),
+        LightKind::Point | LightKind::Area => (oil::LightType::Omni, oil::SpotlightShape::Circular),
+    };
+
+    // No target object in the IR: Blender lights aim along their own local
+    // -Z axis rather than pointing at a separate target node the way 3ds
+    // Max ones can, so there's nothing sensible to put here.
+    let target_id = 0xFFFFFFFFu32;
+
+    oil::Light {
+        node_id,
+        lamp_type,
+        color: light.color.map(From::<f32>::from),
+        multiplier: light.energy.into(),
+        far_attenuation_end: if light.range > 0.0 { light.range.into() } else { f64::MAX },
+        far_attenuation_start: 0.0,
+        near_attenuation_end: 0.0,
+        near_attenuation_start: 0.0,
+        falloff: light.spot_angle.into(),
+        hotspot: (light.spot_angle * (1.0 - light.spot_blend)).into(),
+        aspect_ratio: 1.0,
+        overshoot: false,
+        shape,
+        target_id,
+        on: true,
+    }
+}
+
+/// Emits a node's sampled transform keyframes as OIL controller chunks, one each for position
+/// and rotation if that channel actually carries more than one distinct key - a single-key
+/// channel never animates, so nothing needs writing for it at all.
+fn animation_to_oil(node_id: u32, anim: &crate::model_ir::ObjectAnimation, chunks: &mut Vec<oil::Chunk>) {
+    if anim.position.len() > 1 {
+        let keys = anim.position.iter()
+            .map(|(time, value)| oil::Vec3Key { time: (*time).into(), value: value.map(From::<f32>::from) })
+            .collect();
+        chunks.push(oil::PositionController { node_id, keys }.into());
+    }
+
+    if anim.rotation.len() > 1 {
+        let keys = anim.rotation.iter()
+            .map(|(time, value)| oil::RotationKey {
+                time: (*time).into(),
+                value: oil::RotationValue::Quaternion(vek::Vec4::new(
+                    value.x.into(), value.y.into(), value.z.into(), value.w.into()
+                ))
+            })
+            .collect();
+        chunks.push(oil::RotationController { node_id, keys }.into());
+    }
+}
+
+fn camera_to_oil(node_id: u32, camera: &crate::model_ir::Camera) -> oil::Camera {
+    oil::Camera {
+        node_id,
+        fov: camera.fov.into(),
+        far_clip: camera.far_clip.into(),
+        near_clip: camera.near_clip.into(),
+        target_id: 0xFFFFFFFFu32,
+        target_distance: 0.0,
+        aspect_ratio: camera.aspect_ratio.into(),
+    }
+}
+
+/// Partitions `me`'s polygons into smoothing groups, then returns the
+/// resulting bitmask for every triangle in `me.triangles`, in order.
+///
+/// Two polygons sharing an edge flood-fill into the same group unless that
+/// edge is marked sharp, or the polygons' face normals diverge past
+/// `crease_angle` (radians) - this is what `GeometryFace.smoothing_group`
+/// actually drives downstream: whether Diesel treats the edge as a normal
+/// seam or smooths across it. Triangles from the same source polygon are
+/// always in the same group, since the diagonals OIL's triangulation adds
+/// aren't real mesh edges.
+///
+/// Diesel's bitmask can only tell 32 groups apart, so components that never
+/// touch the same vertex - and so can never be mistaken for one another -
+/// are greedily allowed to reuse a bit. Components that might plausibly
+/// collide but can't fit in 32 bits anyway fall back to reusing the last
+/// bit, which risks an occasional incorrect normal seam rather than
+/// panicking on pathologically fragmented meshes.
+fn compute_smoothing_groups(me: &Mesh, crease_angle: f32) -> Vec<u32> {
+    fn polygon_normal(me: &Mesh, poly: &Polygon) -> vek::Vec3<f32> {
+        let loops = &me.faceloops[poly.base..(poly.base + poly.count)];
+        let mut normal = vek::Vec3::zero();
+        for i in 0..loops.len() {
+            let a = me.vertices[loops[i].vertex];
+            let b = me.vertices[loops[(i + 1) % loops.len()].vertex];
+            normal.x += (a.y - b.y) * (a.z + b.z);
+            normal.y += (a.z - b.z) * (a.x + b.x);
+            normal.z += (a.x - b.x) * (a.y + b.y);
+        }
+        if normal.magnitude_squared() > 1e-12 { normal.normalized() } else { vek::Vec3::unit_z() }
+    }
+
+    fn find(parent: &mut [usize], i: usize) -> usize {
+        if parent[i] != i {
+            parent[i] = find(parent, parent[i]);
+        }
+        parent[i]
+    }
+    fn union(parent: &mut [usize], a: usize, b: usize) {
+        let ra = find(parent, a);
+        let rb = find(parent, b);
+        if ra != rb { parent[ra] = rb; }
+    }
+
+    let poly_count = me.polygons.len();
+    let normals: Vec<_> = me.polygons.iter().map(|p| polygon_normal(me, p)).collect();
+
+    let mut edge_to_polys: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (poly_idx, poly) in me.polygons.iter().enumerate() {
+        for lp in &me.faceloops[poly.base..(poly.base + poly.count)] {
+            edge_to_polys.entry(lp.edge).or_default().push(poly_idx);
+        }
+    }
+
+    let mut parent: Vec<usize> = (0..poly_count).collect();
+    for (edge_idx, polys) in &edge_to_polys {
+        if me.edges[*edge_idx].sharp { continue; }
+        for i in 0..polys.len() {
+            for j in (i + 1)..polys.len() {
+                let angle = normals[polys[i]].dot(normals[polys[j]]).clamp(-1.0, 1.0).acos();
+                if angle <= crease_angle {
+                    union(&mut parent, polys[i], polys[j]);
+                }
+            }
+        }
+    }
+
+    let component_of: Vec<usize> = (0..poly_count).map(|i| find(&mut parent, i)).collect();
+
+    // Two components "conflict" - can't share a bit - if any vertex touches
+    // polygons from both, since that's the shared state a consuming engine
+    // would use to average normals across.
+    let mut conflicts: HashMap<usize, HashSet<usize>> = HashMap::new();
+    let mut components_at_vertex: HashMap<usize, HashSet<usize>> = HashMap::new();
+    for (poly_idx, poly) in me.polygons.iter().enumerate() {
+        for lp in &me.faceloops[poly.base..(poly.base + poly.count)] {
+            components_at_vertex.entry(lp.vertex).or_default().insert(component_of[poly_idx]);
+        }
+    }
+    for comps in components_at_vertex.values() {
+        if comps.len() < 2 { continue; }
+        for &a in comps {
+            for &b in comps {
+                if a != b {
+                    conflicts.entry(a).or_default().insert(b);
+                }
+            }
+        }
+    }
+
+    let mut color_of: HashMap<usize, u32> = HashMap::new();
+    let mut distinct_components: Vec<usize> = component_of.iter().copied().collect::<HashSet<_>>().into_iter().collect();
+    distinct_components.sort_unstable();
+    for comp in distinct_components {
+        let used: HashSet<u32> = conflicts.get(&comp)
+            .into_iter()
+            .flatten()
+            .filter_map(|n| color_of.get(n).copied())
+            .collect();
+        let color = (0u32..32).find(|c| !used.contains(c)).unwrap_or(31);
+        color_of.insert(comp, color);
+    }
+
+    me.triangles.iter()
+        .map(|tri| 1u32 << color_of[&component_of[tri.polygon]])
+        .collect()
+}
+
+fn mesh_to_oil_geometry(node_id: u32, me: &Mesh, materials: &mut MaterialCollector, crease_angle: f32) -> oil::Geometry {
     let mut og = oil::Geometry {
         node_id,
         material_id: 0xFFFFFFFFu32,
@@ -98,36 +379,44 @@ fn mesh_to_oil_geometry(node_id: u32, me: &Mesh, materials: &mut MaterialCollect
         return og;
     }
 
-    // TODO: Do we care about duplication? Is this horrifyingly slow?
     // TODO: Does the OIL->FDM step *care* about if there are unused things?
 
-    og.channels.push(oil::GeometryChannel::Position(0, me.vertices.iter().map(|i|{
-        i.map(|c| c.into())
-    }).collect()));
+    let mut channels = ChannelCollector::new();
+
+    let position_data: Vec<vek::Vec3<f64>> = me.vertices.iter().map(|i| i.map(|c| c.into())).collect();
+    let position_hash = content_hash128(0, 0, position_data.iter().flat_map(|v| [v.x, v.y, v.z]));
+    let position_channel = channels.push(position_hash, oil::GeometryChannel::Position(0, position_data));
 
+    let mut uv_channels = Vec::with_capacity(me.faceloop_uvs.len());
     for (idx, (_name, tc)) in me.faceloop_uvs.iter().enumerate() {
-        let data = tc.iter().map(|i| i.map(|j| j.into())).collect();
-        og.channels.push(oil::GeometryChannel::TexCoord(idx as u32 + 1, data))
+        let data: Vec<vek::Vec2<f64>> = tc.iter().map(|i| i.map(|j| j.into())).collect();
+        let hash = content_hash128(1, idx as u32 + 1, data.iter().flat_map(|v| [v.x, v.y]));
+        uv_channels.push(channels.push(hash, oil::GeometryChannel::TexCoord(idx as u32 + 1, data)));
     }
 
+    let mut color_channels = Vec::with_capacity(me.faceloop_colors.len());
     for (idx, (_name, vc)) in me.faceloop_colors.iter().enumerate() {
-        let data_rgb = vc.iter().map(|i| {
+        let data_rgb: Vec<vek::Rgb<f64>> = vc.iter().map(|i| {
             let v: vek::Rgba<f64> = i.map(|j| j.into());
             v.rgb()
         }).collect();
-        let data_a = vc.iter().map(|i| {
+        let data_a: Vec<f64> = vc.iter().map(|i| {
             i.a.into()
         }).collect();
-        og.channels.push(oil::GeometryChannel::Colour(idx as u32 + 1, data_rgb));
-        og.channels.push(oil::GeometryChannel::Alpha(idx as u32 + 1, data_a))
+        let rgb_hash = content_hash128(5, idx as u32 + 1, data_rgb.iter().flat_map(|v| [v.r, v.g, v.b]));
+        let a_hash = content_hash128(6, idx as u32 + 1, data_a.iter().copied());
+        let rgb_channel = channels.push(rgb_hash, oil::GeometryChannel::Colour(idx as u32 + 1, data_rgb));
+        let a_channel = channels.push(a_hash, oil::GeometryChannel::Alpha(idx as u32 + 1, data_a));
+        color_channels.push((rgb_channel, a_channel));
     }
 
-    let (has_norm, has_tangent) = match &me.faceloop_tangents {
-        crate::model_ir::TangentLayer::None => (false, false),
+    let (normal_channel, tangent_channels) = match &me.faceloop_tangents {
+        crate::model_ir::TangentLayer::None => (None, None),
         crate::model_ir::TangentLayer::Normals(norms) => {
-            let norms = norms.iter().map(|i| i.map(|j| <f32 as Into<f64>>::into(j))).collect();
-            og.channels.push(oil::GeometryChannel::Normal(0, norms));
-            (true, false)
+            let data: Vec<vek::Vec3<f64>> = norms.iter().map(|i| i.map(|j| <f32 as Into<f64>>::into(j))).collect();
+            let hash = content_hash128(2, 0, data.iter().flat_map(|v| [v.x, v.y, v.z]));
+            let idx = channels.push(hash, oil::GeometryChannel::Normal(0, data));
+            (Some(idx), None)
         },
         crate::model_ir::TangentLayer::Tangents(t) => {
             let norms = t.iter().map(|i| i.normal)
@@ -139,49 +428,50 @@ fn mesh_to_oil_geometry(node_id: u32, me: &Mesh, materials: &mut MaterialCollect
             let binorms = t.iter().map(|i| i.bitangent)
                 .map(|i| i.map(|j| <f32 as Into<f64>>::into(j)))
                 .collect::<Vec<_>>();
-            og.channels.push(oil::GeometryChannel::Normal(0, norms));
-            og.channels.push(oil::GeometryChannel::Tangent(0, tangs));
-            og.channels.push(oil::GeometryChannel::Binormal(0, binorms));
-            (true, true)
+            let norm_hash = content_hash128(2, 0, norms.iter().flat_map(|v| [v.x, v.y, v.z]));
+            let tang_hash = content_hash128(4, 0, tangs.iter().flat_map(|v| [v.x, v.y, v.z]));
+            let binorm_hash = content_hash128(3, 0, binorms.iter().flat_map(|v| [v.x, v.y, v.z]));
+            let norm_idx = channels.push(norm_hash, oil::GeometryChannel::Normal(0, norms));
+            let tang_idx = channels.push(tang_hash, oil::GeometryChannel::Tangent(0, tangs));
+            let binorm_idx = channels.push(binorm_hash, oil::GeometryChannel::Binormal(0, binorms));
+            (Some(norm_idx), Some((tang_idx, binorm_idx)))
         },
     };
 
     let (root_material, material_mapping) = materials.collect_and_map(&me.material_ids);
     og.material_id = root_material;
 
-    for tri in &me.triangles {
+    let smoothing_groups = compute_smoothing_groups(me, crease_angle);
+
+    for (tri, &smoothing_group) in me.triangles.iter().zip(&smoothing_groups) {
         let local_mat_id = me.polygons[tri.polygon].material;
         let mut loops = Vec::with_capacity(5);
-        let mut channel = 0;
 
         loops.push(oil::GeometryFaceloop {
-            channel,
+            channel: position_channel,
             a: me.faceloops[tri.loops[0]].vertex as u32,
             b: me.faceloops[tri.loops[1]].vertex as u32,
             c: me.faceloops[tri.loops[2]].vertex as u32
         });
 
-        for _ in 0..me.faceloop_uvs.len() {
-            channel += 1;
+        for &uv_channel in &uv_channels {
             loops.push(oil::GeometryFaceloop {
-                channel,
+                channel: uv_channel,
                 a: tri.loops[0] as u32,
                 b: tri.loops[1] as u32,
                 c: tri.loops[2] as u32
             })
         }
 
-        for _ in 0..me.faceloop_colors.len() {
-            channel += 1;
+        for &(rgb_channel, a_channel) in &color_channels {
             loops.push(oil::GeometryFaceloop {
-                channel,
+                channel: rgb_channel,
                 a: tri.loops[0] as u32,
                 b: tri.loops[1] as u32,
                 c: tri.loops[2] as u32
             });
-            channel += 1;
             loops.push(oil::GeometryFaceloop {
-                channel,
+                channel: a_channel,
                 a: tri.loops[0] as u32,
                 b: tri.loops[1] as u32,
                 c: tri.loops[2] as u32
@@ -189,26 +479,23 @@ fn mesh_to_oil_geometry(node_id: u32, me: &Mesh, materials: &mut MaterialCollect
         }
 
         // normal/tangent/binormal
-        if has_norm {
-            channel += 1;
+        if let Some(normal_channel) = normal_channel {
             loops.push(oil::GeometryFaceloop {
-                channel,
+                channel: normal_channel,
                 a: tri.loops[0] as u32,
                 b: tri.loops[1] as u32,
                 c: tri.loops[2] as u32
             });
         }
-        if has_tangent {
-            channel += 1;
+        if let Some((tangent_channel, binormal_channel)) = tangent_channels {
             loops.push(oil::GeometryFaceloop {
-                channel,
+                channel: tangent_channel,
                 a: tri.loops[0] as u32,
                 b: tri.loops[1] as u32,
                 c: tri.loops[2] as u32
             });
-            channel += 1;
             loops.push(oil::GeometryFaceloop {
-                channel,
+                channel: binormal_channel,
                 a: tri.loops[0] as u32,
                 b: tri.loops[1] as u32,
                 c: tri.loops[2] as u32
@@ -217,15 +504,16 @@ fn mesh_to_oil_geometry(node_id: u32, me: &Mesh, materials: &mut MaterialCollect
 
         og.faces.push(oil::GeometryFace {
             material_id: material_mapping[local_mat_id],
-            smoothing_group: 0, // TODO: Does Blender *have* smoothing groups and do we care?
+            smoothing_group,
             loops,
         });
     }
 
+    og.channels = channels.finish();
     og
 }
 
-fn scene_to_oilchunks(scene: &crate::model_ir::Scene, chunks: &mut Vec<oil::Chunk>) {
+fn scene_to_oilchunks(scene: &crate::model_ir::Scene, chunks: &mut Vec<oil::Chunk>, crease_angle: f32) {
     let base_chunkid = 1u32;
     let base_mat_chunkid = (base_chunkid as usize + scene.objects.len()).try_into().unwrap();
     let mut mat_collector = MaterialCollector::new(scene, base_mat_chunkid);
@@ -247,13 +535,17 @@ fn scene_to_oilchunks(scene: &crate::model_ir::Scene, chunks: &mut Vec<oil::Chun
             transform: transform.map(From::<f32>::from),
             pivot_transform: vek::Mat4::identity(),
             parent_id,
-            
+
         }.into());
 
+        if let Some(anim) = &obj.animation {
+            animation_to_oil(chunk_id, anim, chunks);
+        }
+
         match &obj.data {
             ObjectData::None => (),
             ObjectData::Mesh(md) => {
-                let mut ch = mesh_to_oil_geometry(chunk_id, md, &mut mat_collector);
+                let mut ch = mesh_to_oil_geometry(chunk_id, md, &mut mat_collector, crease_angle);
 
                 match &md.skin {
                     None => (),
@@ -306,41 +598,58 @@ fn scene_to_oilchunks(scene: &crate::model_ir::Scene, chunks: &mut Vec<oil::Chun
 
                 chunks.push(ch.into())
             },
-            ObjectData::Light(_) => todo!(),
-            ObjectData::Camera(_) => todo!(),
-            ObjectData::Armature(_) => ()
+            ObjectData::Light(light) => chunks.push(light_to_oil(chunk_id, light).into()),
+            ObjectData::Camera(camera) => chunks.push(camera_to_oil(chunk_id, camera).into()),
+            // OIL has no collision-primitive chunk of its own, same as for armatures: the node
+            // itself still round-trips, just without anything hanging off it.
+            ObjectData::Armature(_) | ObjectData::Collision(_) => ()
         }
     }
 
     chunks.extend(mat_collector.collected.drain(..).map(|i| i.into()))
 }
 
-pub fn export(env: PyEnv, output_path: &str, meters_per_unit: f32, default_author_tag: &str, object: &PyAny) -> PyResult<()> {
-    let mut scene = crate::ir_blender::scene_from_bpy_selected(&env, object, meters_per_unit, default_author_tag);
+pub fn export(env: PyEnv, output_path: &str, meters_per_unit: f32, default_author_tag: &str, object: &PyAny, database: Option<&Database>, crease_angle: f32) -> PyResult<()> {
+    // Everything up to here has to touch bpy, so it needs the GIL. Once the
+    // scene is gathered into `model_ir::Scene` it's plain Rust data with no
+    // PyAny left in it, so the rest of the conversion - the CPU-bound part,
+    // for a big scene - can run with the GIL released.
+    let scene = crate::ir_blender::scene_from_bpy_selected(&env, object, meters_per_unit, default_author_tag)?;
 
-    if f32::abs(0.01 - meters_per_unit) > 0.000244140625f32 { // arbitrary threshold
-        scene.change_scale(0.01);
-    }
+    let python = env.python;
+    let bytes = python.allow_threads(|| -> PyResult<Vec<u8>> {
+        let mut scene = scene;
 
-    for (_, obj) in scene.objects.iter_mut() {
-        match &mut obj.data {
-            ObjectData::Mesh(me) => me.vcols_to_faceloop_cols(),
-            _ => ()
+        if f32::abs(0.01 - meters_per_unit) > 0.000244140625f32 { // arbitrary threshold
+            scene.change_scale(0.01);
+        }
+
+        let mut materials = MaterialRegistry::new();
+        for (_, obj) in scene.objects.iter_mut() {
+            match &mut obj.data {
+                ObjectData::Mesh(me) => {
+                    me.vcols_to_faceloop_cols();
+                    if let Some(db) = database {
+                        me.resolve_materials(&mut materials, db);
+                    }
+                },
+                _ => ()
+            }
         }
-    }
 
-    let mut chunks = vec! [
-        oil::SceneInfo3 {
-            start_time: 0.0,
-            end_time: 1.0,
-            author_tag: scene.diesel.author_tag.clone(),
-            source_filename: scene.diesel.source_file.clone(),
-            scene_type: scene.diesel.scene_type.clone()
-        }.into(),
-        oil::MaterialsXml { xml: String::new() }.into()
-    ];
-    scene_to_oilchunks(&scene, &mut chunks);
-    let bytes = oil::chunks_to_bytes(&chunks)?;
+        let mut chunks = vec! [
+            oil::SceneInfo3 {
+                start_time: scene.start_time.into(),
+                end_time: scene.end_time.into(),
+                author_tag: scene.diesel.author_tag.clone(),
+                source_filename: scene.diesel.source_file.clone(),
+                scene_type: scene.diesel.scene_type.clone()
+            }.into(),
+            oil::MaterialsXml { xml: String::new() }.into()
+        ];
+        scene_to_oilchunks(&scene, &mut chunks, crease_angle);
+        Ok(oil::chunks_to_bytes(&chunks)?)
+    })?;
     std::fs::write(output_path, &bytes)?;
     Ok(())
 }
\ No newline at end of file
@@ -1,14 +1,20 @@
 mod py_ir;
-mod py_ir_reader_fdm;
+mod ir_reader;
+mod ir_writer;
 mod ir_writer_oil;
+mod ir_writer_gltf;
+mod ir_writer_fdm;
 mod model_ir;
+mod model_reader_oil;
 mod ir_blender;
 mod bpy;
 mod ir_reader_fdm;
+mod ir_reader_oil;
+mod material_registry;
 
 use pyo3::prelude::*;
 
-use pd2tools_rust::formats::fdm;
+use pd2tools_rust::formats::{fdm, oil};
 use pd2tools_rust::util::LIB_VERSION;
 
 #[pymodule]
@@ -21,7 +27,15 @@ fn pd2tools_fdm(_py: Python, m: &PyModule) -> PyResult<()> {
     }
 
     #[pyfunction]
-    fn import_ir_from_file(py: Python, hashlist_path: &str, model_path: &str, units_per_cm: f32, framerate: f32) -> PyResult<Vec<Py<py_ir::Object>>> {
+    /// `asset_dir`/`synthesize_tangents` aren't params here because [`ir_reader`] predates the
+    /// asset-database-backed material resolution that
+    /// [`ir_reader_fdm::fdm_to_ir`](crate::ir_reader_fdm::fdm_to_ir) (the reader behind
+    /// `export_gltf`/`export_oil`'s own import paths) has - there's nothing in this pipeline for
+    /// them to feed yet. `weld_tolerance`/`weight_tolerance` forward straight to
+    /// [`ir_reader::sections_to_ir`]; see its own docs for what they do.
+    fn import_ir_from_file(py: Python, hashlist_path: &str, model_path: &str, units_per_cm: f32, framerate: f32,
+        weld_tolerance: Option<f32>, weight_tolerance: Option<f32>) -> PyResult<Vec<Py<py_ir::Object>>>
+    {
         let hlp = Some(String::from(hashlist_path));
         let hashlist = pd2tools_rust::get_hashlist(&hlp);
         let hashlist = match hashlist {
@@ -44,7 +58,7 @@ fn pd2tools_fdm(_py: Python, m: &PyModule) -> PyResult<()> {
             Ok(s) => s
         };
 
-        let r = py_ir_reader_fdm::sections_to_ir(py, &sections, &hashlist, units_per_cm, framerate);
+        let r = ir_reader::sections_to_ir(py, &sections, &hashlist, units_per_cm, framerate, weld_tolerance, weight_tolerance);
         r.map_err(|e| {
             let mut es = String::new();
             pd2tools_rust::util::write_error_chain(&mut es, e).unwrap();
@@ -53,27 +67,123 @@ fn pd2tools_fdm(_py: Python, m: &PyModule) -> PyResult<()> {
     }
 
     #[pyfunction]
-    fn export_oil(py: Python, output_path: &str, meters_per_unit: f32, author_tag: &str, object: &PyAny) -> PyResult<()> {
+    fn export_oil(py: Python, output_path: &str, meters_per_unit: f32, author_tag: &str, object: &PyAny,
+        hashlist_path: Option<String>, asset_dir: Option<String>, crease_angle: f32) -> PyResult<()>
+    {
         let env = PyEnv::new(py);
-        ir_writer_oil::export(env, output_path, meters_per_unit, author_tag, object)
+
+        let database = match asset_dir {
+            None => None,
+            Some(dir) => {
+                let hashlist = pd2tools_rust::get_hashlist(&hashlist_path)
+                    .ok_or_else(|| pyo3::exceptions::PyException::new_err("Failed to load hashlist"))?;
+                let coll = pd2tools_rust::bundles::loader::load_bundle_dir(&std::path::PathBuf::from(dir), true)
+                    .map_err(|e| pyo3::exceptions::PyException::new_err(format!("Failed to load asset database: {:?}", e)))?;
+                Some(pd2tools_rust::bundles::database::from_bdb(hashlist, &coll.0, &coll.1))
+            }
+        };
+
+        ir_writer_oil::export(env, output_path, meters_per_unit, author_tag, object, database.as_ref(), crease_angle)
+    }
+
+    #[pyfunction]
+    fn export_gltf(py: Python, output_path: &str, meters_per_unit: f32, default_author_tag: &str, object: &PyAny,
+        hashlist_path: Option<String>, asset_dir: Option<String>) -> PyResult<()>
+    {
+        let env = PyEnv::new(py);
+
+        let database = match asset_dir {
+            None => None,
+            Some(dir) => {
+                let hashlist = pd2tools_rust::get_hashlist(&hashlist_path)
+                    .ok_or_else(|| pyo3::exceptions::PyException::new_err("Failed to load hashlist"))?;
+                let coll = pd2tools_rust::bundles::loader::load_bundle_dir(&std::path::PathBuf::from(dir), true)
+                    .map_err(|e| pyo3::exceptions::PyException::new_err(format!("Failed to load asset database: {:?}", e)))?;
+                Some(pd2tools_rust::bundles::database::from_bdb(hashlist, &coll.0, &coll.1))
+            }
+        };
+
+        ir_writer_gltf::export(env, output_path, meters_per_unit, default_author_tag, object, database.as_ref())
+    }
+
+    #[pyfunction]
+    fn export_fdm(py: Python, output_path: &str, meters_per_unit: f32, object: &PyAny) -> PyResult<()> {
+        let env = PyEnv::new(py);
+
+        // As with export_oil/export_gltf: the bpy walk needs the GIL, the IR-to-FDM
+        // conversion doesn't, so it's the only part that gets to release it.
+        let scene = crate::ir_blender::scene_from_bpy_selected(&env, object, meters_per_unit, "")?;
+
+        let python = env.python;
+        let bytes = python.allow_threads(|| -> PyResult<Vec<u8>> {
+            let mut scene = scene;
+            if f32::abs(0.01 - meters_per_unit) > 0.000244140625f32 {
+                scene.change_scale(0.01);
+            }
+            for (_, obj) in scene.objects.iter_mut() {
+                if let crate::model_ir::ObjectData::Mesh(me) = &mut obj.data {
+                    me.vcols_to_faceloop_cols();
+                }
+            }
+
+            let container = ir_writer_fdm::scene_to_fdm(&scene);
+            let mut bytes = Vec::new();
+            fdm::write_stream(&container, &mut bytes)
+                .map_err(|e| pyo3::exceptions::PyException::new_err(format!("Failed writing FDM: {}", e)))?;
+            Ok(bytes)
+        })?;
+        std::fs::write(output_path, &bytes)?;
+        Ok(())
+    }
+
+    /// Reads an `.oil` file into a new set of bpy objects in the current scene's active
+    /// collection, the round trip [`ir_writer_oil::export`] has no inverse for on its own.
+    /// Armatures/skinning and animation aren't built into bpy by this pass - see
+    /// [`ir_blender::scene_to_bpy`]'s doc comment for why.
+    #[pyfunction]
+    fn import_oil(py: Python, input_path: &str) -> PyResult<()> {
+        let env = PyEnv::new(py);
+
+        let bytes = std::fs::read(input_path)?;
+        let chunks = oil::parse_chunks(&bytes)
+            .map_err(|e| pyo3::exceptions::PyException::new_err(format!("Failed parsing OIL: {}", e)))?;
+
+        let scene = model_reader_oil::scene_from_oil(&chunks);
+        ir_blender::scene_to_bpy(&env, &scene);
+        Ok(())
     }
 
     m.add_function(wrap_pyfunction!(diesel_hash, m)?)?;
     m.add_function(wrap_pyfunction!(import_ir_from_file, m)?)?;
     m.add_function(wrap_pyfunction!(export_oil, m)?)?;
+    m.add_function(wrap_pyfunction!(export_gltf, m)?)?;
+    m.add_function(wrap_pyfunction!(export_fdm, m)?)?;
+    m.add_function(wrap_pyfunction!(import_oil, m)?)?;
 
     Ok(())
     
 }
 
-#[derive(Clone, Copy)]
+/// Blender's own bpy/bmesh handles, kept as owned [`Py`] references rather
+/// than the `&'py PyAny` gil-refs this used to cache. That's what lets
+/// [`ir_writer_oil::export`](crate::ir_writer_oil::export) release the GIL
+/// with `Python::allow_threads` around the pure-Rust IR-to-OIL conversion
+/// once the bpy scene has been gathered into a [`model_ir::Scene`]: a
+/// `PyEnv` holding `&'py PyAny`s can't be let go of for that long, because
+/// every one of those gil-refs is only valid while the GIL is held.
+///
+/// `python` itself is still a plain [`Python<'py>`] token rather than
+/// anything cached *from* Python, so it doesn't need the same treatment -
+/// it just isn't usable once the GIL has actually been released, same as
+/// for any other pyo3 code.
+#[derive(Clone)]
 pub struct PyEnv<'py> {
     pub python: Python<'py>,
-    pub bpy_context: &'py PyAny,
-    pub bpy_data: &'py PyAny,
-    pub bmesh: &'py PyModule,
+    bpy_context: Py<PyAny>,
+    bpy_data: Py<PyAny>,
+    pub bmesh: Py<PyModule>,
     pub bmesh_ops: bpy::bmesh::Ops<'py>,
-    id_fn: &'py PyAny,
+    id_fn: Py<PyAny>,
 }
 
 impl<'py> PyEnv<'py> {
@@ -82,18 +192,27 @@ impl<'py> PyEnv<'py> {
         let bpy = python.import("bpy").unwrap();
         PyEnv {
             python,
-            id_fn: builtins.getattr("id").unwrap(),
-            bpy_context: bpy.getattr("context").unwrap(),
-            bpy_data: bpy.getattr("data").unwrap(),
-            bmesh: python.import("bmesh").unwrap(),
+            id_fn: builtins.getattr("id").unwrap().into(),
+            bpy_context: bpy.getattr("context").unwrap().into(),
+            bpy_data: bpy.getattr("data").unwrap().into(),
+            bmesh: python.import("bmesh").unwrap().into(),
             bmesh_ops: bpy::bmesh::Ops::import(python)
         }
     }
+
+    pub fn bpy_context(&self) -> &'py PyAny {
+        self.bpy_context.as_ref(self.python)
+    }
+
+    pub fn bpy_data(&self) -> &'py PyAny {
+        self.bpy_data.as_ref(self.python)
+    }
+
     pub fn id(&self, pyobj: &'py PyAny) -> u64 {
-        self.id_fn.call1( (pyobj,) ).unwrap().extract::<u64>().unwrap()
+        self.id_fn.as_ref(self.python).call1( (pyobj,) ).unwrap().extract::<u64>().unwrap()
     }
 
     pub fn b_c_evaluated_depsgraph_get(&self) -> PyResult<&PyAny> {
-        self.bpy_context.call_method0(pyo3::intern!{self.python, "evaluated_depsgraph_get"})
+        self.bpy_context().call_method0(pyo3::intern!{self.python, "evaluated_depsgraph_get"})
     }
 }
\ No newline at end of file
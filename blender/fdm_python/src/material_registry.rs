@@ -0,0 +1,157 @@
+//! Resolves a [`Mesh`](crate::model_ir::Mesh)'s material names against the
+//! packed asset [`Database`] during export: for each material name, looks
+//! up its `.material_config`, reads which `.texture` files it references,
+//! and keeps only the ones that actually exist. Exported objects tend to
+//! reuse the same handful of materials across many mesh slots, so
+//! [`MaterialRegistry`] memoizes the resolution by material name instead of
+//! re-querying the database every time a name comes up again.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::rc::Rc;
+
+use fnv::FnvHashMap;
+
+use pd2tools_rust::bundles::database::Database;
+use pd2tools_rust::diesel_hash;
+
+/// A material, resolved to the textures its `.material_config` references
+/// and that were confirmed present in the database.
+pub struct ResolvedMaterial {
+    pub name: Rc<str>,
+    pub textures: Vec<Rc<str>>
+}
+
+/// Memoizes [`MaterialRegistry::resolve`] by material name.
+#[derive(Default)]
+pub struct MaterialRegistry {
+    resolved: FnvHashMap<Rc<str>, Rc<ResolvedMaterial>>
+}
+
+impl MaterialRegistry {
+    pub fn new() -> MaterialRegistry {
+        MaterialRegistry::default()
+    }
+
+    /// Resolves `name` against `database`, reusing the cached result if this
+    /// name was already looked up through this registry.
+    pub fn resolve(&mut self, database: &Database, name: &Rc<str>) -> Rc<ResolvedMaterial> {
+        if let Some(existing) = self.resolved.get(name) {
+            return existing.clone();
+        }
+
+        let resolved = Rc::new(resolve_material(database, name));
+        self.resolved.insert(name.clone(), resolved.clone());
+        resolved
+    }
+}
+
+fn resolve_material(database: &Database, name: &Rc<str>) -> ResolvedMaterial {
+    let mut textures = Vec::new();
+
+    if let Some(xml) = read_file(database, name, "material_config") {
+        for file in material_config_textures(&xml) {
+            let hash = diesel_hash::hash_str(&file);
+            if database.get_by_hashes(hash, diesel_hash::EMPTY, diesel_hash::hash_str("texture")).is_some() {
+                textures.push(Rc::from(file.as_str()));
+            }
+        }
+    }
+
+    ResolvedMaterial { name: name.clone(), textures }
+}
+
+/// Every distinct value of a `file` attribute anywhere in the document -
+/// material_config's own textures, normal maps, etc. are all just
+/// `<material ... file="...">`-shaped elements.
+fn material_config_textures(xml: &[u8]) -> Vec<String> {
+    let text = match std::str::from_utf8(xml) {
+        Ok(t) => t,
+        Err(_) => return Vec::new()
+    };
+    let doc = match roxmltree::Document::parse(text) {
+        Ok(d) => d,
+        Err(_) => return Vec::new()
+    };
+
+    doc.descendants()
+        .filter_map(|node| node.attribute("file"))
+        .map(String::from)
+        .collect()
+}
+
+/// A shader binding resolved from a `.material_config` document while importing a model, as
+/// opposed to [`resolve_material`]'s existence-check scan for exporting one: the shader variant
+/// name, its texture slots (by slot element name, e.g. `diffuse`/`normal`/`bump`/`reflection`),
+/// and any scalar/vector shader parameters.
+pub struct MaterialConfig {
+    pub shader: Option<String>,
+    pub textures: Vec<(u64, String)>,
+    pub parameters: Vec<(String, MaterialParamValue)>,
+    pub skinned: bool,
+}
+
+pub enum MaterialParamValue {
+    Scalar(f32),
+    Vector(vek::Vec4<f32>),
+}
+
+/// Resolves `name`'s `.material_config` against `database` into a [`MaterialConfig`], or `None`
+/// if the document doesn't exist or isn't valid XML.
+pub fn read_material_config(database: &Database, name: &str) -> Option<MaterialConfig> {
+    let xml = read_file(database, name, "material_config")?;
+    Some(parse_material_config(&xml))
+}
+
+fn parse_material_config(xml: &[u8]) -> MaterialConfig {
+    let empty = || MaterialConfig { shader: None, textures: Vec::new(), parameters: Vec::new(), skinned: false };
+
+    let Ok(text) = std::str::from_utf8(xml) else { return empty() };
+    let Ok(doc) = roxmltree::Document::parse(text) else { return empty() };
+    let root = doc.root_element();
+
+    let shader = root.attribute("shader")
+        .or_else(|| doc.descendants().find(|n| n.has_tag_name("shader")).and_then(|n| n.attribute("name")))
+        .map(String::from);
+
+    let textures = doc.descendants()
+        .filter_map(|node| node.attribute("file").map(|file| (diesel_hash::hash_str(node.tag_name().name()), file.to_string())))
+        .collect();
+
+    let parameters = doc.descendants()
+        .filter(|node| node.has_tag_name("param") || node.has_tag_name("variable"))
+        .filter_map(|node| {
+            let name = node.attribute("name")?;
+            let value = node.attribute("value")?;
+            let components: Vec<f32> = value.split(',').filter_map(|c| c.trim().parse().ok()).collect();
+            let parsed = match components.as_slice() {
+                &[x] => MaterialParamValue::Scalar(x),
+                &[x, y, z] => MaterialParamValue::Vector(vek::Vec4::new(x, y, z, 0.0)),
+                &[x, y, z, w] => MaterialParamValue::Vector(vek::Vec4::new(x, y, z, w)),
+                _ => return None,
+            };
+            Some((name.to_string(), parsed))
+        })
+        .collect();
+
+    // There's no documented flag for this - going by whether the shader name itself or any
+    // `<skin .../>`-shaped element advertises it, same way `material_config_textures` above
+    // just scans for `file` attributes rather than relying on a known schema.
+    let skinned = shader.as_deref().is_some_and(|s| s.to_lowercase().contains("skin"))
+        || doc.descendants().any(|n| n.has_tag_name("skin"));
+
+    MaterialConfig { shader, textures, parameters, skinned }
+}
+
+/// Reads the whole contents of the `language`-less file named `name` with
+/// extension `extension`, by locating its package and seeking to its
+/// packed offset.
+fn read_file(database: &Database, name: &str, extension: &str) -> Option<Vec<u8>> {
+    let item = database.get_by_str(name, "", extension)?;
+    let (path, offset, length) = item.get_backing_details()?;
+
+    let mut file = std::fs::File::open(path).ok()?;
+    file.seek(SeekFrom::Start(offset as u64)).ok()?;
+    let mut bytes = vec![0u8; length];
+    file.read_exact(&mut bytes).ok()?;
+    Some(bytes)
+}
@@ -3,7 +3,9 @@ use std::rc::Rc;
 
 use bytemuck_derive::Zeroable;
 use slotmap::SlotMap;
+use thiserror::Error;
 
+use crate::material_registry::ResolvedMaterial;
 use crate::vek_types::*;
 
 slotmap::new_key_type! {
@@ -25,7 +27,22 @@ pub struct Scene {
 
     pub active_object: Option<ObjectKey>,
     pub meters_per_unit: f32,
-    pub diesel: DieselSceneSettings
+    pub diesel: DieselSceneSettings,
+
+    /// Blender's scene frame range, converted to seconds at [`Scene::framerate`]. Both zero if
+    /// the source scene turned out to hold only a single frame of animation.
+    pub start_time: f32,
+    pub end_time: f32,
+    /// Frames per second of the source scene, used to turn sampled frame numbers into the
+    /// seconds-based timestamps OIL keyframes are stored as.
+    pub framerate: f32,
+
+    /// Baked armature-action tracks, keyed by the bone's own [`ObjectKey`] (the one its
+    /// [`BindJoint`] is addressed by). Separate from `objects[key].animation` because a bone
+    /// has no `bpy.types.Object` of its own to sample a depsgraph-evaluated transform from - its
+    /// pose comes off the armature's action instead - and because a bone's track needs a scale
+    /// channel, which [`ObjectAnimation`] deliberately doesn't carry.
+    pub bone_animations: HashMap<ObjectKey, BoneAnimation>,
 }
 
 #[derive(Default)]
@@ -35,8 +52,48 @@ pub struct DieselSceneSettings {
     pub scene_type: String
 }
 
+#[derive(Default)]
 pub struct Material {
     pub name: String,
+    /// `name` hashed with `pd2tools_rust::diesel_hash`, the same way [`Object::name_hash`] is -
+    /// materials share one flat namespace scene-wide, so [`Scene::check_name_hash_collisions`]
+    /// compares every material's hash against every other's rather than just siblings' own.
+    pub name_hash: u64,
+
+    /// Shader variant name from the material's `.material_config`, if it was resolved against
+    /// an asset database while importing. `None` for materials gathered from Blender, or
+    /// imported without a database to resolve against.
+    pub shader: Option<String>,
+
+    /// The material's texture slot bindings, as name-hash/texture-path pairs rather than free
+    /// text, so consumers (the Blender bridge, the glTF exporter) can match a slot against a
+    /// PBR input without re-parsing `.material_config` themselves.
+    pub textures: Vec<MaterialTextureSlot>,
+
+    /// Scalar/vector shader parameters read from the same document.
+    pub parameters: Vec<MaterialParameter>,
+
+    /// Set when the `.material_config` marks this shader as skinned/weighted, so downstream
+    /// code knows a mesh using it is expected to carry JOINTS/WEIGHTS streams.
+    pub skinned: bool,
+}
+
+/// One texture slot of a [`Material`], keyed by the diesel-hash of its slot name (e.g.
+/// `"diffuse"`, `"normal"`, `"bump"`, `"reflection"`) rather than the name itself, matching how
+/// every other named reference in these formats is addressed.
+pub struct MaterialTextureSlot {
+    pub slot_name_hash: u64,
+    pub texture_path: String,
+}
+
+pub struct MaterialParameter {
+    pub name: String,
+    pub value: MaterialParameterValue,
+}
+
+pub enum MaterialParameterValue {
+    Scalar(f32),
+    Vector(Vec4f),
 }
 
 pub struct Collection {
@@ -48,12 +105,44 @@ pub struct Collection {
 
 pub struct Object {
     pub name: String,
+    /// `name` hashed with `pd2tools_rust::diesel_hash` - the form diesel actually addresses it
+    /// by, and what [`Scene::check_name_hash_collisions`] compares sibling objects and bones by,
+    /// since the engine can't tell two same-hashed names apart at all.
+    pub name_hash: u64,
     pub parent: Option<ObjectKey>,
     pub children: Vec<ObjectKey>,
     pub transform: Transform,
     pub in_collections: Vec<CollectionKey>,
     pub data: ObjectData,
     pub skin_role: SkinRole,
+    /// Sampled local-space transform keyframes, or `None` for a node whose transform never
+    /// moves - which costs nothing to export, since no controller chunks get written for it.
+    pub animation: Option<ObjectAnimation>,
+}
+
+/// A node's animated position/rotation, sampled once per Blender frame and then decimated down
+/// to the keys that actually matter (see [`crate::ir_blender::decimate_keys`]). Each is kept
+/// in its own list - and so can decimate independently - because it's common for only one of a
+/// node's position or rotation to actually be animated.
+///
+/// There's no scale track: OIL has no scale controller chunk, so an animated scale has nowhere
+/// to go and is simply not captured here.
+#[derive(Default)]
+pub struct ObjectAnimation {
+    pub position: Vec<(f32, Vec3f)>,
+    pub rotation: Vec<(f32, Quaternion)>,
+}
+
+/// A bone's animated local TRS, relative to its parent bone, sampled once per frame of the
+/// action driving its armature and then decimated the same way [`ObjectAnimation`] is (see
+/// [`crate::ir_blender::decimate_keys`]). Unlike a node's animation, scale is captured here too:
+/// pose bones are routinely scaled by constraints or by hand, and the skin controller chunks
+/// these end up written as have room for it even though the plain node controller doesn't.
+#[derive(Default)]
+pub struct BoneAnimation {
+    pub position: Vec<(f32, Vec3f)>,
+    pub rotation: Vec<(f32, Quaternion)>,
+    pub scale: Vec<(f32, Vec3f)>,
 }
 
 #[derive(PartialEq, Eq)]
@@ -68,29 +157,114 @@ pub enum ObjectData {
     Mesh(Mesh),
     Light(Light),
     Camera(Camera),
-    Armature(BindPoseKey)
+    Armature(BindPoseKey),
+    Collision(Collision)
+}
+
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Rgbf,
+    /// Blender's "Power", in Watts.
+    pub energy: f32,
+    /// Cone angle in radians. Only meaningful for [`LightKind::Spot`].
+    pub spot_angle: f32,
+    /// Fraction of the cone that's a soft falloff rather than fully lit. Only meaningful for [`LightKind::Spot`].
+    pub spot_blend: f32,
+    /// Custom attenuation distance. Zero means "use the engine default".
+    pub range: f32,
+    pub cast_shadows: bool,
+}
+
+pub enum LightKind {
+    Point,
+    Sun,
+    Spot,
+    Area
+}
+
+pub struct Camera {
+    pub kind: CameraKind,
+    /// Horizontal field of view, in radians. For [`CameraKind::Orthographic`], OIL has no
+    /// orthographic projection chunk, so this is the perspective-equivalent FOV used as a
+    /// best-effort approximation when exporting.
+    pub fov: f32,
+    pub near_clip: f32,
+    pub far_clip: f32,
+    pub aspect_ratio: f32,
+}
+
+pub enum CameraKind {
+    Perspective,
+    Orthographic,
+    Panoramic
+}
+
+/// A collision bounds primitive, gathered from an Empty carrying a `diesel_collision`
+/// PropertyGroup - this crate's equivalent of the SWBF addon's `msh_collision_prim_properties` -
+/// rather than from any real geometry.
+pub struct Collision {
+    pub shape: CollisionShape,
+    /// Sphere/cylinder radius. Unused for [`CollisionShape::Box`].
+    pub radius: f32,
+    /// Box half-extents, or a cylinder's half-height in `z`. Unused for [`CollisionShape::Sphere`].
+    pub extent: Vec3f,
+}
+
+pub enum CollisionShape {
+    Sphere,
+    Box,
+    Cylinder
+}
+
+/// Which element a [`CustomAttribute`] has one entry per, mirroring Blender's own attribute
+/// domains closely enough that translating `bpy::AttributeDomain` to this is a straight match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeDomain {
+    Point,
+    Edge,
+    Face,
+    Faceloop,
 }
 
-pub struct Light;
-pub struct Camera;
+/// A generic per-element data stream gathered from one of Blender's named attributes, kept
+/// around uninterpreted so round-tripping geometry-nodes output doesn't silently drop it.
+/// `data` has one entry per element of `domain` - e.g. one per [`Mesh::vertices`] for
+/// [`AttributeDomain::Point`], one per [`Mesh::faceloops`] for [`AttributeDomain::Faceloop`].
+pub struct CustomAttribute<T> {
+    pub domain: AttributeDomain,
+    pub data: Vec<T>,
+}
 
 #[derive(Default)]
 pub struct Mesh {
     pub vertices: Vec<Vec3f>,
-    pub edges: Vec<(usize, usize)>,
+    pub edges: Vec<Edge>,
     pub faceloops: Vec<Faceloop>,
     pub polygons: Vec<Polygon>,
     pub triangles: Vec<Triangle>,
 
     pub vertex_groups: VertexGroups,
     pub vertex_colors: BTreeMap<String, Vec<Rgbaf>>,
-    
+
     pub faceloop_tangents: TangentLayer,
     pub faceloop_colors: BTreeMap<String, Vec<Rgbaf>>,
     pub faceloop_uvs: BTreeMap<String, Vec<Vec2f>>,
 
+    /// Generic `FLOAT`/`INT`/`BOOLEAN`/`FLOAT2`/`FLOAT_VECTOR` attributes that aren't one of
+    /// the above recognized streams, kept by name so arbitrary geometry-nodes output survives
+    /// export instead of being silently discarded.
+    pub custom_float_attributes: BTreeMap<String, CustomAttribute<f32>>,
+    pub custom_int_attributes: BTreeMap<String, CustomAttribute<i32>>,
+    pub custom_bool_attributes: BTreeMap<String, CustomAttribute<bool>>,
+    pub custom_vec2_attributes: BTreeMap<String, CustomAttribute<Vec2f>>,
+    pub custom_vec3_attributes: BTreeMap<String, CustomAttribute<Vec3f>>,
+
     pub material_names: Vec<Option<Rc<str>>>,
     pub material_ids: Vec<Option<MaterialKey>>,
+    /// Asset-database resolution of each entry in `material_names`, filled
+    /// in by [`crate::material_registry`] when exporting against a known
+    /// `Database` rather than left empty.
+    pub resolved_materials: Vec<Option<Rc<ResolvedMaterial>>>,
 
     pub skin: Option<SkinReference>,
     pub diesel: DieselMeshSettings
@@ -106,6 +280,14 @@ impl Mesh {
         vit.fold(init_aabb, |c,v| { c.expanded_to_contain_point(*v)} )
     }
 
+    /// Resolves every entry in `material_names` against `database` through
+    /// `registry`, filling in `resolved_materials` in lockstep.
+    pub fn resolve_materials(&mut self, registry: &mut crate::material_registry::MaterialRegistry, database: &pd2tools_rust::bundles::database::Database) {
+        self.resolved_materials = self.material_names.iter()
+            .map(|name| name.as_ref().map(|n| registry.resolve(database, n)))
+            .collect();
+    }
+
     pub fn vcols_to_faceloop_cols(&mut self) {
         let vertex_color_attrs = std::mem::take(&mut self.vertex_colors);
         
@@ -117,6 +299,406 @@ impl Mesh {
         }
     }
 
+    /// Replaces a `TangentLayer::Normals` layer with a full `TangentLayer::Tangents` one,
+    /// synthesized from this mesh's normals and its first UV channel using Lengyel's
+    /// per-triangle method. No-op if there's no UV channel to synthesize from, or if
+    /// `faceloop_tangents` isn't currently `Normals` (i.e. it's already `Tangents`, or `None`
+    /// because the source had no normals either).
+    pub fn synthesize_tangents(&mut self) {
+        let TangentLayer::Normals(normals) = &self.faceloop_tangents else { return };
+        let Some(uvs) = self.faceloop_uvs.values().next() else { return };
+
+        let mut tangents = vec![Vec3f::zero(); normals.len()];
+        let mut bitangents = vec![Vec3f::zero(); normals.len()];
+
+        for poly in &self.polygons {
+            if poly.count != 3 { continue; }
+            let l0 = self.faceloops[poly.base].vertex;
+            let l1 = self.faceloops[poly.base + 1].vertex;
+            let l2 = self.faceloops[poly.base + 2].vertex;
+
+            let e1 = self.vertices[l1] - self.vertices[l0];
+            let e2 = self.vertices[l2] - self.vertices[l0];
+            let w0 = uvs[poly.base];
+            let w1 = uvs[poly.base + 1];
+            let w2 = uvs[poly.base + 2];
+            let du1 = w1.x - w0.x;
+            let dv1 = w1.y - w0.y;
+            let du2 = w2.x - w0.x;
+            let dv2 = w2.y - w0.y;
+
+            let r = 1.0 / (du1 * dv2 - du2 * dv1);
+            if !r.is_finite() { continue; }
+
+            let tangent = (e1 * dv2 - e2 * dv1) * r;
+            let bitangent = (e2 * du1 - e1 * du2) * r;
+
+            for i in poly.base..poly.base + poly.count {
+                tangents[i] += tangent;
+                bitangents[i] += bitangent;
+            }
+        }
+
+        let synthesized = normals.iter().zip(tangents).zip(bitangents)
+            .map(|((&normal, t), b)| {
+                let tangent = (t - normal * normal.dot(t)).normalized();
+                let w = if normal.cross(tangent).dot(b) < 0.0 { -1.0 } else { 1.0 };
+                Tangent { normal, tangent, bitangent: normal.cross(tangent) * w }
+            })
+            .collect();
+
+        self.faceloop_tangents = TangentLayer::Tangents(synthesized);
+    }
+
+    /// Like [`Self::synthesize_tangents`], but works from `triangles` and a named UV channel
+    /// instead of assuming every polygon is already a triangle and using whichever UV channel
+    /// comes first - the form exporters want once [`Self::triangulate`] has run. A triangle
+    /// whose UVs are degenerate (collapsed to a line or point, so Lengyel's method divides by
+    /// zero) falls back to an arbitrary frame orthogonal to its normal rather than contributing
+    /// a NaN.
+    pub fn compute_tangents(&mut self, uv_layer: &str) {
+        let TangentLayer::Normals(normals) = &self.faceloop_tangents else { return };
+        let Some(uvs) = self.faceloop_uvs.get(uv_layer) else { return };
+
+        let mut tangents = vec![Vec3f::zero(); normals.len()];
+        let mut bitangents = vec![Vec3f::zero(); normals.len()];
+
+        for tri in &self.triangles {
+            let [l0, l1, l2] = tri.loops;
+            let p0 = self.vertices[self.faceloops[l0].vertex];
+            let p1 = self.vertices[self.faceloops[l1].vertex];
+            let p2 = self.vertices[self.faceloops[l2].vertex];
+
+            let e1 = p1 - p0;
+            let e2 = p2 - p0;
+            let duv1 = uvs[l1] - uvs[l0];
+            let duv2 = uvs[l2] - uvs[l0];
+
+            let det = duv1.x * duv2.y - duv2.x * duv1.y;
+            let r = 1.0 / det;
+
+            let (tangent, bitangent) = if r.is_finite() {
+                ((e1 * duv2.y - e2 * duv1.y) * r, (e2 * duv1.x - e1 * duv2.x) * r)
+            }
+            else {
+                let n = (normals[l0] + normals[l1] + normals[l2]).normalized();
+                let arbitrary = if n.x.abs() < 0.9 { Vec3f::unit_x() } else { Vec3f::unit_y() };
+                let t = n.cross(arbitrary).normalized();
+                (t, n.cross(t))
+            };
+
+            for &l in &[l0, l1, l2] {
+                tangents[l] += tangent;
+                bitangents[l] += bitangent;
+            }
+        }
+
+        let synthesized = normals.iter().zip(tangents).zip(bitangents)
+            .map(|((&normal, t), b)| {
+                let tangent = (t - normal * normal.dot(t)).normalized();
+                let w = if normal.cross(tangent).dot(b) < 0.0 { -1.0 } else { 1.0 };
+                Tangent { normal, tangent, bitangent: normal.cross(tangent) * w }
+            })
+            .collect();
+
+        self.faceloop_tangents = TangentLayer::Tangents(synthesized);
+    }
+
+    /// Fills `triangles` by triangulating every polygon: a straight fan from the polygon's
+    /// first faceloop for convex polygons, ear clipping (projected onto the polygon's own
+    /// plane) for concave ones. Polygons with fewer than 3 faceloops are skipped rather than
+    /// producing degenerate triangles.
+    pub fn triangulate(&mut self) {
+        let mut triangles = Vec::with_capacity(self.polygons.len());
+
+        for (poly_idx, poly) in self.polygons.iter().enumerate() {
+            if poly.count < 3 { continue; }
+
+            if poly.count == 3 {
+                triangles.push(Triangle { loops: [poly.base, poly.base + 1, poly.base + 2], polygon: poly_idx });
+                continue;
+            }
+
+            let ring: Vec<usize> = (poly.base..poly.base + poly.count).collect();
+            let positions: Vec<Vec3f> = ring.iter().map(|&l| self.vertices[self.faceloops[l].vertex]).collect();
+            let normal = newell_normal(&positions);
+
+            if polygon_is_convex(normal, &positions) {
+                for i in 1..poly.count - 1 {
+                    triangles.push(Triangle { loops: [poly.base, poly.base + i, poly.base + i + 1], polygon: poly_idx });
+                }
+            }
+            else {
+                for [a, b, c] in ear_clip(&ring, &positions, normal) {
+                    triangles.push(Triangle { loops: [a, b, c], polygon: poly_idx });
+                }
+            }
+        }
+
+        self.triangles = triangles;
+    }
+
+    /// Splits this mesh into one [`Mesh`] per connected component ("island"), built from
+    /// adjacency over `edges` plus each polygon's own faceloop ring (so a polygon sharing only
+    /// a corner vertex with its neighbours, with no explicit boundary edge between them, still
+    /// links up). Adjacency is kept as one bitset row per vertex rather than a `HashSet` of
+    /// pairs, since a mesh with tens of thousands of vertices would otherwise spend more time
+    /// hashing index pairs than visiting them.
+    pub fn connected_components(&self) -> Vec<Mesh> {
+        let n = self.vertices.len();
+        if n == 0 { return Vec::new(); }
+
+        let words_per_row = (n + 63) / 64;
+        let mut adjacency = vec![vec![0u64; words_per_row]; n];
+        let mut connect = |a: usize, b: usize, adjacency: &mut [Vec<u64>]| {
+            adjacency[a][b / 64] |= 1 << (b % 64);
+            adjacency[b][a / 64] |= 1 << (a % 64);
+        };
+
+        for edge in &self.edges {
+            connect(edge.a, edge.b, &mut adjacency);
+        }
+        for poly in &self.polygons {
+            for i in 0..poly.count {
+                let a = self.faceloops[poly.base + i].vertex;
+                let b = self.faceloops[poly.base + (i + 1) % poly.count].vertex;
+                connect(a, b, &mut adjacency);
+            }
+        }
+
+        let mut vert_component = vec![usize::MAX; n];
+        let mut component_count = 0;
+        let mut stack = Vec::new();
+        for start in 0..n {
+            if vert_component[start] != usize::MAX { continue; }
+
+            vert_component[start] = component_count;
+            stack.push(start);
+            while let Some(v) = stack.pop() {
+                for (word_idx, &word) in adjacency[v].iter().enumerate() {
+                    let mut bits = word;
+                    while bits != 0 {
+                        let bit = bits.trailing_zeros() as usize;
+                        bits &= bits - 1;
+                        let neighbor = word_idx * 64 + bit;
+                        if vert_component[neighbor] == usize::MAX {
+                            vert_component[neighbor] = component_count;
+                            stack.push(neighbor);
+                        }
+                    }
+                }
+            }
+            component_count += 1;
+        }
+
+        (0..component_count).map(|c| self.extract_component(c, &vert_component)).collect()
+    }
+
+    /// Builds the sub-[`Mesh`] holding only what `vert_component` marks as belonging to
+    /// `component`, remapping every index densely the same way [`Self::deduplicate_vertices`]
+    /// does. Vertex groups are trimmed to the ones actually referenced by a retained vertex and
+    /// renumbered to match, and `skin.vgroup_to_joint_mapping` follows the same renumbering so
+    /// it still lines up.
+    fn extract_component(&self, component: usize, vert_component: &[usize]) -> Mesh {
+        let mut old_to_new_vertex = vec![usize::MAX; self.vertices.len()];
+        let mut new_vertices = Vec::new();
+        for (old, &c) in vert_component.iter().enumerate() {
+            if c == component {
+                old_to_new_vertex[old] = new_vertices.len();
+                new_vertices.push(self.vertices[old]);
+            }
+        }
+
+        let new_vertex_colors = self.vertex_colors.iter()
+            .map(|(name, cols)| {
+                let filtered = vert_component.iter().zip(cols)
+                    .filter(|(&c, _)| c == component)
+                    .map(|(_, &col)| col)
+                    .collect();
+                (name.clone(), filtered)
+            })
+            .collect();
+
+        let mut used_groups = Vec::new();
+        let mut group_old_to_new = HashMap::new();
+        for (old, &c) in vert_component.iter().enumerate() {
+            if c != component { continue; }
+            for w in &self.vertex_groups[old] {
+                group_old_to_new.entry(w.group).or_insert_with(|| {
+                    used_groups.push(w.group);
+                    used_groups.len() - 1
+                });
+            }
+        }
+
+        let mut new_vertex_groups = VertexGroups::default();
+        new_vertex_groups.names = used_groups.iter().map(|&g| self.vertex_groups.names[g].clone()).collect();
+        for (old, &c) in vert_component.iter().enumerate() {
+            if c != component { continue; }
+            new_vertex_groups.push(self.vertex_groups[old].iter().map(|w| Weight {
+                group: group_old_to_new[&w.group],
+                weight: w.weight
+            }));
+        }
+
+        let mut old_to_new_edge = vec![usize::MAX; self.edges.len()];
+        let mut new_edges = Vec::new();
+        for (old, edge) in self.edges.iter().enumerate() {
+            if vert_component[edge.a] != component { continue; }
+            old_to_new_edge[old] = new_edges.len();
+            new_edges.push(Edge {
+                a: old_to_new_vertex[edge.a],
+                b: old_to_new_vertex[edge.b],
+                sharp: edge.sharp,
+                seam: edge.seam,
+                crease: edge.crease
+            });
+        }
+
+        let mut old_to_new_faceloop = vec![usize::MAX; self.faceloops.len()];
+        let mut old_to_new_polygon = vec![usize::MAX; self.polygons.len()];
+        let mut new_faceloops = Vec::new();
+        let mut new_polygons = Vec::new();
+        let mut new_faceloop_colors: BTreeMap<String, Vec<Rgbaf>> =
+            self.faceloop_colors.keys().map(|k| (k.clone(), Vec::new())).collect();
+        let mut new_faceloop_uvs: BTreeMap<String, Vec<Vec2f>> =
+            self.faceloop_uvs.keys().map(|k| (k.clone(), Vec::new())).collect();
+        let mut new_normals = Vec::new();
+        let mut new_tangents = Vec::new();
+
+        for (poly_idx, poly) in self.polygons.iter().enumerate() {
+            if poly.count == 0 || vert_component[self.faceloops[poly.base].vertex] != component { continue; }
+
+            old_to_new_polygon[poly_idx] = new_polygons.len();
+            let new_base = new_faceloops.len();
+
+            for i in 0..poly.count {
+                let old_fl = poly.base + i;
+                old_to_new_faceloop[old_fl] = new_faceloops.len();
+                let fl = &self.faceloops[old_fl];
+                new_faceloops.push(Faceloop {
+                    vertex: old_to_new_vertex[fl.vertex],
+                    edge: old_to_new_edge[fl.edge]
+                });
+
+                for (name, cols) in &self.faceloop_colors {
+                    new_faceloop_colors.get_mut(name).unwrap().push(cols[old_fl]);
+                }
+                for (name, uvs) in &self.faceloop_uvs {
+                    new_faceloop_uvs.get_mut(name).unwrap().push(uvs[old_fl]);
+                }
+                match &self.faceloop_tangents {
+                    TangentLayer::Normals(normals) => new_normals.push(normals[old_fl]),
+                    TangentLayer::Tangents(tangents) => new_tangents.push(Tangent {
+                        normal: tangents[old_fl].normal,
+                        tangent: tangents[old_fl].tangent,
+                        bitangent: tangents[old_fl].bitangent
+                    }),
+                    TangentLayer::None => ()
+                }
+            }
+
+            new_polygons.push(Polygon { base: new_base, count: poly.count, material: poly.material });
+        }
+
+        let new_faceloop_tangents = match &self.faceloop_tangents {
+            TangentLayer::None => TangentLayer::None,
+            TangentLayer::Normals(_) => TangentLayer::Normals(new_normals),
+            TangentLayer::Tangents(_) => TangentLayer::Tangents(new_tangents)
+        };
+
+        let new_triangles = self.triangles.iter()
+            .filter(|t| old_to_new_polygon[t.polygon] != usize::MAX)
+            .map(|t| Triangle {
+                loops: t.loops.map(|l| old_to_new_faceloop[l]),
+                polygon: old_to_new_polygon[t.polygon]
+            })
+            .collect();
+
+        let new_skin = self.skin.as_ref().map(|skin| {
+            let mut vgroup_to_joint_mapping = vec![0usize; used_groups.len()];
+            for (&old_group, &new_group) in &group_old_to_new {
+                if let Some(&joint) = skin.vgroup_to_joint_mapping.get(old_group) {
+                    vgroup_to_joint_mapping[new_group] = joint;
+                }
+            }
+            SkinReference {
+                armature: skin.armature,
+                vgroup_to_joint_mapping,
+                model_to_mid: skin.model_to_mid
+            }
+        });
+
+        // A `CustomAttribute` can be tagged with any of the four domains, so which old/new
+        // index map filters it depends on its own `domain`, not on which of the five typed
+        // maps it happens to live in.
+        fn remap_custom_attribute<T: Copy>(
+            attr: &CustomAttribute<T>,
+            component: usize,
+            vert_component: &[usize],
+            old_to_new_edge: &[usize],
+            old_to_new_polygon: &[usize],
+            old_to_new_faceloop: &[usize],
+        ) -> CustomAttribute<T> {
+            let data = match attr.domain {
+                AttributeDomain::Point => vert_component.iter().zip(&attr.data)
+                    .filter(|(&c, _)| c == component)
+                    .map(|(_, &v)| v)
+                    .collect(),
+                AttributeDomain::Edge => old_to_new_edge.iter().zip(&attr.data)
+                    .filter(|(&new, _)| new != usize::MAX)
+                    .map(|(_, &v)| v)
+                    .collect(),
+                AttributeDomain::Face => old_to_new_polygon.iter().zip(&attr.data)
+                    .filter(|(&new, _)| new != usize::MAX)
+                    .map(|(_, &v)| v)
+                    .collect(),
+                AttributeDomain::Faceloop => old_to_new_faceloop.iter().zip(&attr.data)
+                    .filter(|(&new, _)| new != usize::MAX)
+                    .map(|(_, &v)| v)
+                    .collect(),
+            };
+            CustomAttribute { domain: attr.domain, data }
+        }
+        macro_rules! remap_custom_attributes {
+            ($field:ident) => {
+                self.$field.iter()
+                    .map(|(name, attr)| (name.clone(), remap_custom_attribute(
+                        attr, component, vert_component,
+                        &old_to_new_edge, &old_to_new_polygon, &old_to_new_faceloop
+                    )))
+                    .collect()
+            };
+        }
+
+        Mesh {
+            vertices: new_vertices,
+            edges: new_edges,
+            faceloops: new_faceloops,
+            polygons: new_polygons,
+            triangles: new_triangles,
+            vertex_groups: new_vertex_groups,
+            vertex_colors: new_vertex_colors,
+            faceloop_tangents: new_faceloop_tangents,
+            faceloop_colors: new_faceloop_colors,
+            faceloop_uvs: new_faceloop_uvs,
+            custom_float_attributes: remap_custom_attributes!(custom_float_attributes),
+            custom_int_attributes: remap_custom_attributes!(custom_int_attributes),
+            custom_bool_attributes: remap_custom_attributes!(custom_bool_attributes),
+            custom_vec2_attributes: remap_custom_attributes!(custom_vec2_attributes),
+            custom_vec3_attributes: remap_custom_attributes!(custom_vec3_attributes),
+            material_names: self.material_names.clone(),
+            material_ids: self.material_ids.clone(),
+            resolved_materials: self.resolved_materials.clone(),
+            skin: new_skin,
+            diesel: DieselMeshSettings {
+                cast_shadows: self.diesel.cast_shadows,
+                receive_shadows: self.diesel.receive_shadows,
+                bounds_only: self.diesel.bounds_only
+            }
+        }
+    }
+
     pub fn deduplicate_vertices(&mut self) {
         self.vertex_groups.sort_weights();
         
@@ -157,27 +739,22 @@ impl Mesh {
         self.vertex_groups = new_vgroups;
 
         for i in self.edges.iter_mut() {
-            i.0 = old_to_new[i.0];
-            i.1 = old_to_new[i.1];
+            i.a = old_to_new[i.a];
+            i.b = old_to_new[i.b];
         }
 
         for i in self.faceloops.iter_mut() {
             i.vertex = old_to_new[i.vertex];
         }
 
-        for i in self.edges.iter_mut() {
-            i.0 = old_to_new[i.0];
-            i.1 = old_to_new[i.1];
-        }
-        
         if self.edges.len() > 0 {
             let mut seen_edges = HashMap::with_capacity(self.edges.len());
             let mut old_to_new = Vec::<usize>::with_capacity(self.edges.len());
             let mut new_to_old = Vec::<usize>::with_capacity(self.edges.len());
 
             for i in 0..(self.edges.len()) {
-                let cand = self.edges[i];
-                let cand = if cand.1 < cand.0 { (cand.1, cand.0) } else { (cand.0, cand.1) };
+                let cand = &self.edges[i];
+                let cand = if cand.b < cand.a { (cand.b, cand.a) } else { (cand.a, cand.b) };
 
                 match seen_edges.entry(cand) {
                     std::collections::hash_map::Entry::Occupied(o) => old_to_new.push(*o.get()),
@@ -193,6 +770,92 @@ impl Mesh {
     }
 }
 
+/// Newell's method: sums the cross-products of each edge against the next, which is robust to
+/// the polygon's vertices not being exactly coplanar (the usual case for a hand-modelled n-gon)
+/// unlike taking the cross product of just two edges would be.
+fn newell_normal(positions: &[Vec3f]) -> Vec3f {
+    let mut n = Vec3f::zero();
+    let len = positions.len();
+    for i in 0..len {
+        let curr = positions[i];
+        let next = positions[(i + 1) % len];
+        n.x += (curr.y - next.y) * (curr.z + next.z);
+        n.y += (curr.z - next.z) * (curr.x + next.x);
+        n.z += (curr.x - next.x) * (curr.y + next.y);
+    }
+    n.normalized()
+}
+
+/// Whether every corner of `positions` (in polygon winding order, with plane normal `normal`)
+/// turns the same way - i.e. the polygon can be fanned from its first vertex without the fan
+/// poking outside the polygon.
+fn polygon_is_convex(normal: Vec3f, positions: &[Vec3f]) -> bool {
+    let n = positions.len();
+    (0..n).all(|i| {
+        let prev = positions[(i + n - 1) % n];
+        let curr = positions[i];
+        let next = positions[(i + 1) % n];
+        (curr - prev).cross(next - curr).dot(normal) >= 0.0
+    })
+}
+
+/// Whether `p` lies inside (or on the boundary of) the triangle `a, b, c`, tested in the plane
+/// with normal `normal` rather than by dropping to 2D, since these polygons aren't guaranteed
+/// exactly planar.
+fn point_in_triangle(p: Vec3f, a: Vec3f, b: Vec3f, c: Vec3f, normal: Vec3f) -> bool {
+    let ab = (b - a).cross(p - a).dot(normal);
+    let bc = (c - b).cross(p - b).dot(normal);
+    let ca = (a - c).cross(p - c).dot(normal);
+    (ab >= 0.0 && bc >= 0.0 && ca >= 0.0) || (ab <= 0.0 && bc <= 0.0 && ca <= 0.0)
+}
+
+/// Ear-clips the ring of faceloop indices `loops` (whose positions, in the same order, are
+/// `positions`) into triangles, returning each as a triple of entries from `loops`. Walks the
+/// remaining vertices looking for a convex one that doesn't contain any other remaining vertex
+/// - an "ear" - and clips it off; if a full pass finds none (possible with duplicate/collinear
+/// vertices), the first remaining vertex is clipped anyway so this always terminates.
+fn ear_clip(loops: &[usize], positions: &[Vec3f], normal: Vec3f) -> Vec<[usize; 3]> {
+    let mut remaining: Vec<usize> = (0..loops.len()).collect();
+    let mut tris = Vec::with_capacity(loops.len().saturating_sub(2));
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let mut clipped = false;
+
+        for i in 0..n {
+            let ia = remaining[(i + n - 1) % n];
+            let ib = remaining[i];
+            let ic = remaining[(i + 1) % n];
+            let (a, b, c) = (positions[ia], positions[ib], positions[ic]);
+
+            if (b - a).cross(c - b).dot(normal) < 0.0 { continue; }
+
+            let is_ear = !remaining.iter().any(|&iv| {
+                iv != ia && iv != ib && iv != ic && point_in_triangle(positions[iv], a, b, c, normal)
+            });
+
+            if is_ear {
+                tris.push([ia, ib, ic]);
+                remaining.remove(i);
+                clipped = true;
+                break;
+            }
+        }
+
+        if !clipped {
+            let n = remaining.len();
+            tris.push([remaining[n - 1], remaining[0], remaining[1]]);
+            remaining.remove(0);
+        }
+    }
+
+    if remaining.len() == 3 {
+        tris.push([remaining[0], remaining[1], remaining[2]]);
+    }
+
+    tris.into_iter().map(|[a, b, c]| [loops[a], loops[b], loops[c]]).collect()
+}
+
 struct VertexRef<'m> {
     mesh: &'m Mesh,
     vtx: usize,
@@ -239,6 +902,14 @@ pub struct Faceloop {
     pub edge: usize
 }
 
+pub struct Edge {
+    pub a: usize,
+    pub b: usize,
+    pub sharp: bool,
+    pub seam: bool,
+    pub crease: f32,
+}
+
 #[derive(Default, Zeroable, Clone, Copy, PartialEq)]
 pub struct Weight {
     pub group: usize,
@@ -369,6 +1040,18 @@ pub struct BindJoint {
     pub bindspace_to_bonespace: Mat4f
 }
 
+/// Two sibling objects/bones, or two materials, whose names hash to the same diesel idstring -
+/// the engine has no way left to tell them apart, so [`Scene::check_name_hash_collisions`] refuses
+/// to guess which one a later by-hash lookup was supposed to mean.
+#[derive(Debug, Error)]
+pub enum NameHashCollisionError {
+    #[error("objects {1:?} and {2:?} both hash to {0:#018x} - rename one of them")]
+    Object(u64, ObjectKey, ObjectKey),
+
+    #[error("materials {1:?} and {2:?} both hash to {0:#018x} - rename one of them")]
+    Material(u64, MaterialKey, MaterialKey),
+}
+
 impl Scene {
     /// Actually resize everything in the scene to match `new_scale`, then set that as the scale.
     /// 
@@ -384,6 +1067,11 @@ impl Scene {
         
         for obj in self.objects.values_mut() {
             obj.transform.position *= scale_factor;
+            if let Some(anim) = &mut obj.animation {
+                for (_, p) in anim.position.iter_mut() {
+                    *p *= scale_factor;
+                }
+            }
             match &mut obj.data {
                 ObjectData::None => (),
                 ObjectData::Mesh(m) => {
@@ -400,8 +1088,11 @@ impl Scene {
                         _ => ()
                     }
                 },
-                ObjectData::Light(_) => todo!(),
-                ObjectData::Camera(_) => todo!(),
+                ObjectData::Light(l) => l.range *= scale_factor,
+                ObjectData::Camera(c) => {
+                    c.near_clip *= scale_factor;
+                    c.far_clip *= scale_factor;
+                },
                 ObjectData::Armature(bpk) => {
                     let bind_pose = &mut self.bind_poses[*bpk];
                     for j in bind_pose.joints.iter_mut() {
@@ -413,10 +1104,79 @@ impl Scene {
                     bind_pose.mid_to_bind.cols[3].y *= scale_factor;
                     bind_pose.mid_to_bind.cols[3].z *= scale_factor;
                 },
+                ObjectData::Collision(c) => {
+                    c.radius *= scale_factor;
+                    c.extent *= scale_factor;
+                },
             }
         }
         self.meters_per_unit = new_scale
     }
+
+    /// Replaces `object`'s mesh with just its first connected-component island, and adds the
+    /// rest as new child objects of it (so users can break a merged model back into discrete
+    /// parts for editing). No-op if `object` doesn't hold mesh data, or if the mesh is already
+    /// a single island.
+    pub fn split_mesh_into_islands(&mut self, object: ObjectKey) {
+        let mut islands = match &self.objects[object].data {
+            ObjectData::Mesh(mesh) => mesh.connected_components(),
+            _ => return
+        };
+        if islands.len() <= 1 { return; }
+
+        let name = self.objects[object].name.clone();
+        let transform = self.objects[object].transform.clone();
+        let in_collections = self.objects[object].in_collections.clone();
+
+        self.objects[object].data = ObjectData::Mesh(islands.remove(0));
+
+        for (i, island) in islands.into_iter().enumerate() {
+            let island_name = format!("{} island {}", name, i + 2);
+            let child = self.objects.insert(Object {
+                name_hash: pd2tools_rust::diesel_hash::from_str(&island_name),
+                name: island_name,
+                parent: Some(object),
+                children: Vec::new(),
+                transform: transform.clone(),
+                in_collections: in_collections.clone(),
+                data: ObjectData::Mesh(island),
+                skin_role: SkinRole::None,
+                animation: None
+            });
+            self.objects[object].children.push(child);
+        }
+    }
+
+    /// Checks that no two sibling objects (including bones, which are just objects parented into
+    /// their armature) share a hashed name, and that no two materials do either - since diesel
+    /// addresses both purely by hash, two colliding names would be indistinguishable to it even
+    /// though they're still two different strings to us.
+    pub fn check_name_hash_collisions(&self) -> Result<(), NameHashCollisionError> {
+        let mut by_parent: HashMap<Option<ObjectKey>, Vec<ObjectKey>> = HashMap::new();
+        for (key, obj) in self.objects.iter() {
+            by_parent.entry(obj.parent).or_default().push(key);
+        }
+        for siblings in by_parent.values() {
+            for (i, &a) in siblings.iter().enumerate() {
+                for &b in &siblings[i + 1..] {
+                    if self.objects[a].name_hash == self.objects[b].name_hash {
+                        return Err(NameHashCollisionError::Object(self.objects[a].name_hash, a, b));
+                    }
+                }
+            }
+        }
+
+        let materials = self.materials.keys().collect::<Vec<_>>();
+        for (i, &a) in materials.iter().enumerate() {
+            for &b in &materials[i + 1..] {
+                if self.materials[a].name_hash == self.materials[b].name_hash {
+                    return Err(NameHashCollisionError::Material(self.materials[a].name_hash, a, b));
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 pub struct SkinRequest<I> {
@@ -446,6 +1206,20 @@ impl<OID, MID> std::ops::DerefMut for CoreBuilder<OID, MID> {
     }
 }
 
+/// An overlapping, contradictory, or otherwise malformed rig that [`CoreBuilder::build`]
+/// refuses to guess its way through.
+#[derive(Debug, Error)]
+pub enum SkinBuildError {
+    #[error("object {0:?} is used as an armature, but is also a bone of another skin")]
+    ArmatureIsAlsoBone(ObjectKey),
+
+    #[error("object {0:?} is used as a bone here, but is an armature elsewhere")]
+    ObjectIsBothArmatureAndBone(ObjectKey),
+
+    #[error("bone {0:?} was bound with two different bind-pose matrices")]
+    ConflictingBindMatrix(ObjectKey)
+}
+
 impl<OID, MID> CoreBuilder<OID, MID>
 where
     OID: PartialEq + Eq + std::hash::Hash
@@ -461,63 +1235,112 @@ where
     pub fn add_skin_request(&mut self, object: OID, skin_request: SkinRequest<OID>) {
         self.skin_request.push((object, skin_request))
     }
-    
-    pub fn build(mut self) -> Scene {
+
+    pub fn build(mut self) -> Result<Scene, SkinBuildError> {
         for (child, parent) in self.parent_request {
             let parent = self.id_to_object[&parent];
             self.scene.objects[child].parent = Some(parent);
             self.scene.objects[parent].children.push(child);
         }
 
-        let skin_requests = self.skin_request.iter().map(|(oid, sr)| SkinRequest::<ObjectKey> {
-            armature: self.id_to_object[&oid],
-            global_transform: sr.global_transform,
-            joints: sr.joints.iter().map(|(ji,jt)| (self.id_to_object[&ji], *jt)).collect()
-        }).collect::<Vec<_>>();
+        let skin_requests: Vec<(ObjectKey, SkinRequest<ObjectKey>)> = self.skin_request.iter()
+            .map(|(mesh_oid, sr)| (self.id_to_object[mesh_oid], SkinRequest::<ObjectKey> {
+                armature: self.id_to_object[&sr.armature],
+                global_transform: sr.global_transform,
+                joints: sr.joints.iter().map(|(ji, jt)| (self.id_to_object[ji], *jt)).collect()
+            }))
+            .collect();
 
-        /* 
-        Currently we assume that skinnings don't overlap in a way that either
+        /*
+        We assume that skinnings don't overlap in a way that either
         - causes the SkinRequest.armature to be a bone
         - uses an object as an armature in one skin and a bone in another
         - requires two different bind poses for the same bone.
-        We also assume that the armature is actually specified.
+        We also assume that the armature is actually specified. All three are checked for below
+        and reported as a SkinBuildError rather than silently guessed at or panicking.
 
-        For now we just:
+        We:
         - Mark as an armature everything requested as such
-        - Mark as a bone everything requested as such
-        - And the ancestors of anything requested as such, up to the armature.
-        - Generate a bind pose by taking the last joint matrix seen for each bone
-        - Generate vgroup->joint mappings
-         */ 
-
-        let mut bone_poses: HashMap<ObjectKey, (Mat4f, Mat4f)> = Default::default();
-
-        for sr in &skin_requests {
-            let arma_obj = &mut self.scene.objects[sr.armature];
-            if arma_obj.skin_role == SkinRole::Bone {
-                todo!("Deal with overlapping armatures")
-            }
-            else {
-                arma_obj.skin_role = SkinRole::Armature
+        - Mark as a bone everything requested as such, and the ancestors of anything requested
+          as such, up to (but not including) the armature
+        - Generate a bind pose per armature by taking the last joint matrix seen for each bone
+        - Generate vgroup->joint mappings by matching vertex group names against bone names,
+          the same convention ir_blender's own skin resolution uses
+         */
+
+        for (_, sr) in &skin_requests {
+            if self.scene.objects[sr.armature].skin_role == SkinRole::Bone {
+                return Err(SkinBuildError::ArmatureIsAlsoBone(sr.armature));
             }
+            self.scene.objects[sr.armature].skin_role = SkinRole::Armature;
 
-            for (bone_key, bone_tf) in &sr.joints {
+            for (bone_key, _) in &sr.joints {
                 let mut curr_ancestor = *bone_key;
-                loop {
-                    let ancestor_obj = &mut self.scene.objects[curr_ancestor];
-                    if ancestor_obj.skin_role != SkinRole::Armature {
-                        ancestor_obj.skin_role = SkinRole::Bone
+                while curr_ancestor != sr.armature {
+                    if self.scene.objects[curr_ancestor].skin_role == SkinRole::Armature {
+                        return Err(SkinBuildError::ObjectIsBothArmatureAndBone(curr_ancestor));
                     }
-                    if let Some(a) = ancestor_obj.parent {
-                        curr_ancestor = a;
+                    self.scene.objects[curr_ancestor].skin_role = SkinRole::Bone;
+
+                    match self.scene.objects[curr_ancestor].parent {
+                        Some(a) => curr_ancestor = a,
+                        None => break
                     }
-                    else {
-                        break
+                }
+            }
+        }
+
+        let mut bone_matrices: HashMap<ObjectKey, Mat4f> = HashMap::new();
+        let mut armature_joints: HashMap<ObjectKey, Vec<ObjectKey>> = HashMap::new();
+
+        for (_, sr) in &skin_requests {
+            let joints = armature_joints.entry(sr.armature).or_default();
+            for (bone_key, bone_tf) in &sr.joints {
+                match bone_matrices.get(bone_key) {
+                    Some(existing) if existing != bone_tf => return Err(SkinBuildError::ConflictingBindMatrix(*bone_key)),
+                    Some(_) => (),
+                    None => {
+                        bone_matrices.insert(*bone_key, *bone_tf);
+                        joints.push(*bone_key);
                     }
                 }
             }
         }
 
-        self.scene
+        let mut bind_pose_of: HashMap<ObjectKey, BindPoseKey> = HashMap::new();
+        for (armature, bones) in armature_joints {
+            let joints = bones.iter()
+                .map(|bone| BindJoint { bone: *bone, bindspace_to_bonespace: bone_matrices[bone] })
+                .collect();
+
+            let mid_to_bind = skin_requests.iter()
+                .find(|(_, sr)| sr.armature == armature)
+                .map(|(_, sr)| sr.global_transform)
+                .unwrap_or_else(Mat4f::identity);
+
+            let bind_pose_key = self.scene.bind_poses.insert(BindPose { joints, mid_to_bind });
+            self.scene.objects[armature].data = ObjectData::Armature(bind_pose_key);
+            bind_pose_of.insert(armature, bind_pose_key);
+        }
+
+        for (mesh_key, sr) in &skin_requests {
+            let bind_pose = &self.scene.bind_poses[bind_pose_of[&sr.armature]];
+            let joint_names: Vec<&str> = bind_pose.joints.iter()
+                .map(|bj| self.scene.objects[bj.bone].name.as_str())
+                .collect();
+
+            let ObjectData::Mesh(mesh) = &mut self.scene.objects[*mesh_key].data else { continue };
+            let vgroup_to_joint_mapping = mesh.vertex_groups.names.iter()
+                .map(|name| joint_names.iter().position(|jn| *jn == name.as_str()).unwrap())
+                .collect();
+
+            mesh.skin = Some(SkinReference {
+                armature: sr.armature,
+                vgroup_to_joint_mapping,
+                model_to_mid: sr.global_transform
+            });
+        }
+
+        Ok(self.scene)
     }
 }
\ No newline at end of file
@@ -0,0 +1,478 @@
+//! Parses OIL chunks back into a [`model_ir::Scene`] - the import counterpart to
+//! [`crate::ir_writer_oil`]'s OIL export path. (The similarly-named
+//! [`crate::ir_reader_oil`] goes the other way for animation controllers alone,
+//! turning them into [`crate::py_ir::Animation`] objects for the FDM importer;
+//! this module instead rebuilds a whole scene, the way [`crate::ir_reader_fdm`]
+//! does for FDM.)
+//!
+//! OIL only ever represents triangles - each [`oil::GeometryFace`] is one
+//! triangle, not an arbitrary polygon - so every [`crate::model_ir::Polygon`]
+//! reconstructed here has exactly three loops. That, and the collapse of
+//! per-edge sharpness into `crate::ir_writer_oil`'s smoothing-group bitmask on
+//! export, mean a round trip through OIL can't reproduce the exact mesh an
+//! artist authored - only one that triangulates and shades the same.
+
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use pd2tools_rust::formats::oil;
+
+use crate::model_ir::{
+    BindJoint, BindPose, Camera, CameraKind, Edge, Faceloop, Light, LightKind, Material,
+    MaterialKey, Mesh, ObjectAnimation, ObjectData, ObjectKey, Polygon, Scene, SkinReference,
+    SkinRole, Tangent, TangentLayer, Triangle, VertexGroups, Weight,
+};
+
+type Vec2f = vek::Vec2<f32>;
+type Vec3f = vek::Vec3<f32>;
+type Rgbf = vek::Rgb<f32>;
+type Rgbaf = vek::Rgba<f32>;
+type Mat4f = vek::Mat4<f32>;
+type Transform = vek::Transform<f32, f32, f32>;
+type Quaternion = vek::Quaternion<f32>;
+
+/// Decomposes a node's transform matrix into the position/orientation/scale form
+/// [`model_ir::Object::transform`](crate::model_ir::Object::transform) expects, the inverse of
+/// the `Mat4: From<Transform>` conversion [`crate::ir_writer_oil`] uses on the way out. Columns
+/// that collapse to zero length (a degenerate scale) fall back to the corresponding basis vector
+/// rather than dividing by zero.
+fn mat4_to_transform(m: Mat4f) -> Transform {
+    let c0 = Vec3f::new(m.cols[0].x, m.cols[0].y, m.cols[0].z);
+    let c1 = Vec3f::new(m.cols[1].x, m.cols[1].y, m.cols[1].z);
+    let c2 = Vec3f::new(m.cols[2].x, m.cols[2].y, m.cols[2].z);
+    let position = Vec3f::new(m.cols[3].x, m.cols[3].y, m.cols[3].z);
+
+    let sx = c0.magnitude();
+    let sy = c1.magnitude();
+    let sz = c2.magnitude();
+    let r0 = if sx > 1e-8 { c0 / sx } else { Vec3f::unit_x() };
+    let r1 = if sy > 1e-8 { c1 / sy } else { Vec3f::unit_y() };
+    let r2 = if sz > 1e-8 { c2 / sz } else { Vec3f::unit_z() };
+
+    Transform {
+        position,
+        orientation: mat3_columns_to_quaternion(r0, r1, r2),
+        scale: Vec3f::new(sx, sy, sz),
+    }
+}
+
+/// Standard trace-based rotation-matrix-to-quaternion conversion, branching on whichever
+/// diagonal term is largest to avoid dividing by a near-zero square root.
+fn mat3_columns_to_quaternion(r0: Vec3f, r1: Vec3f, r2: Vec3f) -> Quaternion {
+    let trace = r0.x + r1.y + r2.z;
+    let q = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        Quaternion { w: 0.25 * s, x: (r1.z - r2.y) / s, y: (r2.x - r0.z) / s, z: (r0.y - r1.x) / s }
+    } else if r0.x > r1.y && r0.x > r2.z {
+        let s = (1.0 + r0.x - r1.y - r2.z).sqrt() * 2.0;
+        Quaternion { w: (r1.z - r2.y) / s, x: 0.25 * s, y: (r1.x + r0.y) / s, z: (r2.x + r0.z) / s }
+    } else if r1.y > r2.z {
+        let s = (1.0 + r1.y - r0.x - r2.z).sqrt() * 2.0;
+        Quaternion { w: (r2.x - r0.z) / s, x: (r1.x + r0.y) / s, y: 0.25 * s, z: (r2.y + r1.z) / s }
+    } else {
+        let s = (1.0 + r2.z - r0.x - r1.y).sqrt() * 2.0;
+        Quaternion { w: (r0.y - r1.x) / s, x: (r2.x + r0.z) / s, y: (r2.y + r1.z) / s, z: 0.25 * s }
+    };
+    q.normalized()
+}
+
+fn rotation_value_to_quaternion(v: &oil::RotationValue) -> Quaternion {
+    match v {
+        oil::RotationValue::Quaternion(q) => {
+            Quaternion { x: q.x as f32, y: q.y as f32, z: q.z as f32, w: q.w as f32 }
+        },
+        // Matches the order `crate::ir_reader_oil`'s own Euler handling implies: X, then Y, then Z.
+        oil::RotationValue::Euler(e) => {
+            Quaternion::rotation_x(e.x as f32) * Quaternion::rotation_y(e.y as f32) * Quaternion::rotation_z(e.z as f32)
+        },
+    }
+}
+
+fn oil_light_to_light(light: &oil::Light) -> Light {
+    let kind = match light.lamp_type {
+        oil::LightType::Spot => LightKind::Spot,
+        oil::LightType::Directional => LightKind::Sun,
+        // Point and Area both export as Omni, so there's no way to tell them back apart; this
+        // always reconstructs the more common of the two.
+        oil::LightType::Omni => LightKind::Point,
+    };
+
+    Light {
+        kind,
+        color: Rgbf::new(light.color.r as f32, light.color.g as f32, light.color.b as f32),
+        energy: light.multiplier as f32,
+        spot_angle: light.falloff as f32,
+        spot_blend: if light.falloff > 0.0 { (1.0 - light.hotspot / light.falloff) as f32 } else { 0.0 },
+        range: if light.far_attenuation_end.is_finite() { light.far_attenuation_end as f32 } else { 0.0 },
+        cast_shadows: true,
+    }
+}
+
+fn oil_camera_to_camera(camera: &oil::Camera) -> Camera {
+    Camera {
+        // OIL has no chunk distinguishing ortho/panoramic from perspective, so every camera
+        // reconstructs as perspective - the FOV read back is still a good approximation either way.
+        kind: CameraKind::Perspective,
+        fov: camera.fov as f32,
+        near_clip: camera.near_clip as f32,
+        far_clip: camera.far_clip as f32,
+        aspect_ratio: camera.aspect_ratio as f32,
+    }
+}
+
+/// One [`oil::Geometry`] channel, resolved to the typed slice callers actually want instead of
+/// the raw `(u32, Vec<_>)` shape [`oil::GeometryChannel`] stores it in.
+enum ChannelData<'a> {
+    Position(&'a [vek::Vec3<f64>]),
+    Uv(u32, &'a [vek::Vec2<f64>]),
+    Normal(&'a [vek::Vec3<f64>]),
+    Tangent(&'a [vek::Vec3<f64>]),
+    Binormal(&'a [vek::Vec3<f64>]),
+    ColourRgb(u32, &'a [vek::Rgb<f64>]),
+    ColourAlpha(u32, &'a [f64]),
+}
+
+/// Inverts [`crate::ir_writer_oil`]'s `mesh_to_oil_geometry`: regroups `og`'s channels and
+/// per-triangle faceloops back into a [`Mesh`]'s vertices/faceloops/polygons. Material references
+/// are resolved through `material_key_for_id`/`material_name_for_id`, which the caller builds once
+/// per scene rather than per mesh. Skin weights aren't filled in here - they need the node-id to
+/// `ObjectKey` mapping, which only exists once every node has been read - so [`scene_from_oil`]
+/// wires `mesh.skin`/`mesh.vertex_groups` in afterwards.
+fn geometry_to_mesh(og: &oil::Geometry, material_key_for_id: &HashMap<u32, MaterialKey>, material_name_for_id: &HashMap<u32, String>) -> Mesh {
+    let mut mesh = Mesh::default();
+    mesh.diesel.cast_shadows = og.casts_shadows;
+    mesh.diesel.receive_shadows = og.receives_shadows;
+
+    if og.faces.is_empty() {
+        // A mesh written with `diesel.bounds_only` set carries no geometry at all, just the
+        // bounding box `Mesh::compute_local_bounds` would otherwise have derived from its
+        // vertices - so two vertices at the box corners reproduce exactly that.
+        mesh.diesel.bounds_only = true;
+        if let Some(bb) = &og.override_bounding_box {
+            mesh.vertices.push(Vec3f::new(bb.min.x as f32, bb.min.y as f32, bb.min.z as f32));
+            mesh.vertices.push(Vec3f::new(bb.max.x as f32, bb.max.y as f32, bb.max.z as f32));
+        }
+        return mesh;
+    }
+
+    let mut channels: Vec<ChannelData> = Vec::with_capacity(og.channels.len());
+    let mut position_data: Option<&[vek::Vec3<f64>]> = None;
+    for ch in &og.channels {
+        match ch {
+            oil::GeometryChannel::Position(_, data) => { position_data = Some(data); channels.push(ChannelData::Position(data)); },
+            oil::GeometryChannel::TexCoord(slot, data) => channels.push(ChannelData::Uv(*slot, data)),
+            oil::GeometryChannel::Normal(_, data) => channels.push(ChannelData::Normal(data)),
+            oil::GeometryChannel::Tangent(_, data) => channels.push(ChannelData::Tangent(data)),
+            oil::GeometryChannel::Binormal(_, data) => channels.push(ChannelData::Binormal(data)),
+            oil::GeometryChannel::Colour(slot, data) => channels.push(ChannelData::ColourRgb(*slot, data)),
+            oil::GeometryChannel::Alpha(slot, data) => channels.push(ChannelData::ColourAlpha(*slot, data)),
+        }
+    }
+    let position_data = match position_data {
+        Some(d) => d,
+        None => return mesh, // No position channel at all: nothing sensible to rebuild.
+    };
+    mesh.vertices = position_data.iter().map(|v| Vec3f::new(v.x as f32, v.y as f32, v.z as f32)).collect();
+
+    let mut normals: Vec<Vec3f> = Vec::new();
+    let mut tangents: Vec<Vec3f> = Vec::new();
+    let mut binormals: Vec<Vec3f> = Vec::new();
+
+    let mut material_index_of: HashMap<u32, usize> = HashMap::new();
+    let mut edge_index: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut edge_faces: HashMap<usize, Vec<usize>> = HashMap::new();
+
+    for face in &og.faces {
+        let mut corner_vertex = [0usize; 3];
+        let mut corner_uv: BTreeMap<u32, [Vec2f; 3]> = BTreeMap::new();
+        let mut corner_rgb: BTreeMap<u32, [Rgbf; 3]> = BTreeMap::new();
+        let mut corner_a: BTreeMap<u32, [f32; 3]> = BTreeMap::new();
+        let mut corner_normal: Option<[Vec3f; 3]> = None;
+        let mut corner_tangent: Option<[Vec3f; 3]> = None;
+        let mut corner_binormal: Option<[Vec3f; 3]> = None;
+
+        for fl in &face.loops {
+            match channels.get(fl.channel as usize) {
+                Some(ChannelData::Position(_)) => {
+                    corner_vertex = [fl.a as usize, fl.b as usize, fl.c as usize];
+                },
+                Some(ChannelData::Uv(slot, data)) => {
+                    let get = |i: u32| data.get(i as usize).map(|v| Vec2f::new(v.x as f32, v.y as f32)).unwrap_or(Vec2f::zero());
+                    corner_uv.insert(*slot, [get(fl.a), get(fl.b), get(fl.c)]);
+                },
+                Some(ChannelData::ColourRgb(slot, data)) => {
+                    let get = |i: u32| data.get(i as usize).map(|v| Rgbf::new(v.r as f32, v.g as f32, v.b as f32)).unwrap_or(Rgbf::one());
+                    corner_rgb.insert(*slot, [get(fl.a), get(fl.b), get(fl.c)]);
+                },
+                Some(ChannelData::ColourAlpha(slot, data)) => {
+                    let get = |i: u32| data.get(i as usize).copied().unwrap_or(1.0) as f32;
+                    corner_a.insert(*slot, [get(fl.a), get(fl.b), get(fl.c)]);
+                },
+                Some(ChannelData::Normal(data)) => {
+                    let get = |i: u32| data.get(i as usize).map(|v| Vec3f::new(v.x as f32, v.y as f32, v.z as f32)).unwrap_or(Vec3f::unit_z());
+                    corner_normal = Some([get(fl.a), get(fl.b), get(fl.c)]);
+                },
+                Some(ChannelData::Tangent(data)) => {
+                    let get = |i: u32| data.get(i as usize).map(|v| Vec3f::new(v.x as f32, v.y as f32, v.z as f32)).unwrap_or(Vec3f::unit_x());
+                    corner_tangent = Some([get(fl.a), get(fl.b), get(fl.c)]);
+                },
+                Some(ChannelData::Binormal(data)) => {
+                    let get = |i: u32| data.get(i as usize).map(|v| Vec3f::new(v.x as f32, v.y as f32, v.z as f32)).unwrap_or(Vec3f::unit_y());
+                    corner_binormal = Some([get(fl.a), get(fl.b), get(fl.c)]);
+                },
+                None => (),
+            }
+        }
+
+        let poly_base = mesh.faceloops.len();
+        for corner in 0..3 {
+            let vertex = corner_vertex[corner];
+            let next_vertex = corner_vertex[(corner + 1) % 3];
+            let edge_key = if vertex < next_vertex { (vertex, next_vertex) } else { (next_vertex, vertex) };
+            let edge = *edge_index.entry(edge_key).or_insert_with(|| {
+                let idx = mesh.edges.len();
+                mesh.edges.push(Edge { a: vertex, b: next_vertex, sharp: false, seam: false, crease: 0.0 });
+                idx
+            });
+            mesh.faceloops.push(Faceloop { vertex, edge });
+
+            for (&slot, values) in &corner_uv {
+                mesh.faceloop_uvs.entry(format!("UV{}", slot)).or_insert_with(Vec::new).push(values[corner]);
+            }
+            for (&slot, values) in &corner_rgb {
+                let a = corner_a.get(&slot).map(|v| v[corner]).unwrap_or(1.0);
+                let rgb = values[corner];
+                mesh.faceloop_colors.entry(format!("Color{}", slot)).or_insert_with(Vec::new)
+                    .push(Rgbaf::new(rgb.r, rgb.g, rgb.b, a));
+            }
+            if let Some(n) = corner_normal { normals.push(n[corner]); }
+            if let Some(t) = corner_tangent { tangents.push(t[corner]); }
+            if let Some(b) = corner_binormal { binormals.push(b[corner]); }
+        }
+
+        let polygon = mesh.polygons.len();
+        for i in poly_base..(poly_base + 3) {
+            edge_faces.entry(mesh.faceloops[i].edge).or_default().push(polygon);
+        }
+
+        let material = *material_index_of.entry(face.material_id).or_insert_with(|| {
+            let idx = mesh.material_ids.len();
+            if face.material_id == 0xFFFFFFFFu32 {
+                mesh.material_ids.push(None);
+                mesh.material_names.push(None);
+            }
+            else {
+                mesh.material_ids.push(material_key_for_id.get(&face.material_id).copied());
+                mesh.material_names.push(material_name_for_id.get(&face.material_id).map(|n| Rc::from(n.as_str())));
+            }
+            idx
+        });
+
+        mesh.polygons.push(Polygon { base: poly_base, count: 3, material });
+        mesh.triangles.push(Triangle { loops: [poly_base, poly_base + 1, poly_base + 2], polygon });
+    }
+
+    // The inverse of `compute_smoothing_groups`: two triangles sharing an edge but with no
+    // smoothing-group bit in common were split apart on export specifically because that edge is
+    // a normal seam, so mark it sharp. A boundary edge with only one touching triangle has nothing
+    // to compare against and stays smooth.
+    for (&edge, faces) in &edge_faces {
+        let sharp = (0..faces.len()).any(|i| {
+            (i + 1..faces.len()).any(|j| og.faces[faces[i]].smoothing_group & og.faces[faces[j]].smoothing_group == 0)
+        });
+        mesh.edges[edge].sharp = sharp;
+    }
+
+    mesh.faceloop_tangents = if !tangents.is_empty() && !binormals.is_empty() {
+        TangentLayer::Tangents(normals.into_iter().zip(tangents).zip(binormals)
+            .map(|((normal, tangent), bitangent)| Tangent { normal, tangent, bitangent })
+            .collect())
+    }
+    else if !normals.is_empty() {
+        TangentLayer::Normals(normals)
+    }
+    else {
+        TangentLayer::None
+    };
+
+    mesh
+}
+
+/// Parses `chunks` - as produced by [`oil::parse_chunks`] - into a [`Scene`]: the node tree comes
+/// from each [`oil::Node`]'s `parent_id`, meshes from [`geometry_to_mesh`], materials from the
+/// flattened `oil::Material` list (skipping `MultiMaterial` grouping entries, since faces always
+/// reference a real per-slot or solo material id directly, never the group), and skins from each
+/// mesh's [`oil::GeometrySkin`] once every node has a home.
+pub fn scene_from_oil(chunks: &[oil::Chunk]) -> Scene {
+    let mut scene = Scene::default();
+    // Diesel scenes are always centimetres; OIL has no chunk that records units explicitly.
+    scene.meters_per_unit = 0.01;
+
+    let mut materials_by_id: HashMap<u32, String> = HashMap::new();
+    let mut nodes: Vec<&oil::Node> = Vec::new();
+    let mut geometries: HashMap<u32, &oil::Geometry> = HashMap::new();
+    let mut lights: HashMap<u32, &oil::Light> = HashMap::new();
+    let mut cameras: HashMap<u32, &oil::Camera> = HashMap::new();
+    let mut positions: HashMap<u32, &oil::PositionController> = HashMap::new();
+    let mut rotations: HashMap<u32, &oil::RotationController> = HashMap::new();
+
+    for chunk in chunks {
+        match chunk {
+            oil::Chunk::SceneInfo3(si) => {
+                scene.start_time = si.start_time as f32;
+                scene.end_time = si.end_time as f32;
+                scene.diesel.author_tag = si.author_tag.clone();
+                scene.diesel.source_file = si.source_filename.clone();
+                scene.diesel.scene_type = si.scene_type.clone();
+            },
+            oil::Chunk::SceneInfo2(si) => {
+                scene.start_time = si.start_time as f32;
+                scene.end_time = si.end_time as f32;
+                scene.diesel.author_tag = si.author_tag.clone();
+                scene.diesel.source_file = si.source_filename.clone();
+            },
+            oil::Chunk::SceneInfo1(si) => {
+                scene.start_time = si.start_time as f32;
+                scene.end_time = si.end_time as f32;
+            },
+            oil::Chunk::Material(m) => { materials_by_id.insert(m.id, m.name.clone()); },
+            oil::Chunk::Node(n) => nodes.push(n),
+            oil::Chunk::Geometry(g) => { geometries.insert(g.node_id, g); },
+            oil::Chunk::Light(l) => { lights.insert(l.node_id, l); },
+            oil::Chunk::Camera(c) => { cameras.insert(c.node_id, c); },
+            oil::Chunk::PositionController(c) => { positions.insert(c.node_id, c); },
+            oil::Chunk::RotationController(c) => { rotations.insert(c.node_id, c); },
+            _ => (),
+        }
+    }
+
+    let mut material_key_for_id: HashMap<u32, MaterialKey> = HashMap::new();
+    let mut material_name_for_id: HashMap<u32, String> = HashMap::new();
+    for (&id, name) in &materials_by_id {
+        if name == "MultiMaterial" { continue; }
+        let key = scene.materials.insert(Material {
+            name: name.clone(),
+            name_hash: pd2tools_rust::diesel_hash::from_str(name),
+            ..Default::default()
+        });
+        material_key_for_id.insert(id, key);
+        material_name_for_id.insert(id, name.clone());
+    }
+
+    let mut object_for_node: HashMap<u32, ObjectKey> = HashMap::new();
+    for node in &nodes {
+        let obj = crate::model_ir::Object {
+            name: node.name.clone(),
+            name_hash: pd2tools_rust::diesel_hash::from_str(&node.name),
+            parent: None,
+            children: Vec::new(),
+            transform: mat4_to_transform(node.transform.map(|c| c as f32)),
+            in_collections: Vec::new(),
+            data: ObjectData::None,
+            skin_role: SkinRole::None,
+            animation: None,
+        };
+        let key = scene.objects.insert(obj);
+        object_for_node.insert(node.id, key);
+    }
+    for node in &nodes {
+        if node.parent_id == 0xFFFFFFFFu32 { continue; }
+        if let (Some(&child), Some(&parent)) = (object_for_node.get(&node.id), object_for_node.get(&node.parent_id)) {
+            scene.objects[child].parent = Some(parent);
+            scene.objects[parent].children.push(child);
+        }
+    }
+
+    for (node_id, geom) in &geometries {
+        if let Some(&key) = object_for_node.get(node_id) {
+            scene.objects[key].data = ObjectData::Mesh(geometry_to_mesh(geom, &material_key_for_id, &material_name_for_id));
+        }
+    }
+    for (node_id, light) in &lights {
+        if let Some(&key) = object_for_node.get(node_id) {
+            scene.objects[key].data = ObjectData::Light(oil_light_to_light(light));
+        }
+    }
+    for (node_id, camera) in &cameras {
+        if let Some(&key) = object_for_node.get(node_id) {
+            scene.objects[key].data = ObjectData::Camera(oil_camera_to_camera(camera));
+        }
+    }
+
+    for (node_id, geom) in &geometries {
+        let (Some(skin), Some(&mesh_key)) = (&geom.skin, object_for_node.get(node_id)) else { continue };
+        let Some(&armature_key) = object_for_node.get(&skin.root_node_id) else { continue };
+
+        let bone_keys: Vec<Option<ObjectKey>> = skin.bones.iter()
+            .map(|b| object_for_node.get(&b.bone_node_id).copied())
+            .collect();
+        let bone_names: Vec<String> = bone_keys.iter()
+            .map(|k| k.map(|k| scene.objects[k].name.clone()).unwrap_or_default())
+            .collect();
+
+        let bind_pose_key = match &scene.objects[armature_key].data {
+            ObjectData::Armature(bp) => *bp,
+            _ => {
+                // There's no way to recover the original split between a mesh's `model_to_mid`
+                // and the armature's `mid_to_bind` from their product alone, so the whole thing
+                // is folded into `mid_to_bind` and `model_to_mid` left as identity.
+                let bp = scene.bind_poses.insert(BindPose { joints: Vec::new(), mid_to_bind: Mat4f::identity() });
+                scene.objects[armature_key].data = ObjectData::Armature(bp);
+                scene.objects[armature_key].skin_role = SkinRole::Armature;
+                bp
+            }
+        };
+        scene.bind_poses[bind_pose_key].mid_to_bind = skin.postmul_transform.map(|c| c as f32);
+
+        let joints: Vec<BindJoint> = bone_keys.iter().zip(&skin.bones)
+            .filter_map(|(key, b)| key.map(|bone| {
+                scene.objects[bone].skin_role = SkinRole::Bone;
+                BindJoint { bone, bindspace_to_bonespace: b.premul_transform.map(|c| c as f32) }
+            }))
+            .collect();
+        scene.bind_poses[bind_pose_key].joints = joints;
+
+        let vertex_count = match &scene.objects[mesh_key].data { ObjectData::Mesh(m) => m.vertices.len(), _ => 0 };
+        let weights_per_vertex = skin.weights_per_vertex as usize;
+        let mut vertex_groups = VertexGroups::with_capacity(vertex_count, weights_per_vertex);
+        vertex_groups.names = bone_names;
+        if weights_per_vertex > 0 {
+            for v in 0..vertex_count {
+                let row = &skin.weights[(v * weights_per_vertex)..((v + 1) * weights_per_vertex)];
+                let weights = row.iter()
+                    .enumerate()
+                    .filter(|(_, w)| w.weight > 0.0)
+                    .map(|(group, w)| Weight { group, weight: w.weight as f32 });
+                vertex_groups.push(weights);
+            }
+        }
+
+        if let ObjectData::Mesh(mesh) = &mut scene.objects[mesh_key].data {
+            mesh.vertex_groups = vertex_groups;
+            mesh.skin = Some(SkinReference {
+                armature: armature_key,
+                vgroup_to_joint_mapping: (0..skin.bones.len()).collect(),
+                model_to_mid: Mat4f::identity(),
+            });
+        }
+    }
+
+    for node in &nodes {
+        let Some(&key) = object_for_node.get(&node.id) else { continue };
+        let position = positions.get(&node.id).map(|c| c.keys.iter()
+            .map(|k| (k.time as f32, Vec3f::new(k.value.x as f32, k.value.y as f32, k.value.z as f32)))
+            .collect::<Vec<_>>());
+        let rotation = rotations.get(&node.id).map(|c| c.keys.iter()
+            .map(|k| (k.time as f32, rotation_value_to_quaternion(&k.value)))
+            .collect::<Vec<_>>());
+
+        if position.is_some() || rotation.is_some() {
+            scene.objects[key].animation = Some(ObjectAnimation {
+                position: position.unwrap_or_default(),
+                rotation: rotation.unwrap_or_default(),
+            });
+        }
+    }
+
+    scene
+}
@@ -9,17 +9,46 @@
 //! Pyo3 will actually make the conversion routines for us, if we ask for getters
 //! and setters, but then insist on executing them on every single get, which is a
 //! rather substantial performance issue for meshes.
+//!
+//! `Mesh`'s biggest arrays (`vert_positions`, `faces`, `loop_normals`,
+//! `loop_uv_layers`, `vert_weights`) additionally pay for one Python object
+//! per tuple on every access. For large meshes that dominates import time, so
+//! each of those also has a `*_flat` getter returning the same data as flat
+//! `f32`/`u32` arrays - the layout Blender's own `foreach_set` wants - so a
+//! caller that doesn't need the tuple view can bulk-load the mesh in one call
+//! instead of per-vertex/per-loop. The tuple getters are unchanged and remain
+//! the default way to read this data.
 
 use pyo3::prelude::*;
 use pyo3::{PyTraverseError, PyVisit};
 
 #[pyclass]
-pub struct Armature { }
+#[derive(Default)]
+pub struct Armature {
+    #[pyo3(get, set)] pub bone_names: Vec<String>,
+    /// Index of each bone's parent in these same parallel arrays, if any.
+    #[pyo3(get, set)] pub bone_parents: Vec<Option<usize>>,
+    #[pyo3(get, set)] pub bone_heads: Vec<(f32, f32, f32)>,
+    #[pyo3(get, set)] pub bone_tails: Vec<(f32, f32, f32)>
+}
+#[pymethods]
+impl Armature {
+    #[new]
+    fn new() -> Self { Self::default() }
+
+    #[getter]
+    pub fn get_data_type(&self) -> &str { "ARMATURE" }
+}
 #[pyclass]
 pub struct Animation {
     #[pyo3(get, set)] pub target_path: String,
     #[pyo3(get, set)] pub target_index: usize,
-    #[pyo3(get, set)] pub fcurve: Vec<(f32, f32)>
+    #[pyo3(get, set)] pub fcurve: Vec<(f32, f32)>,
+
+    /// One of `"CONSTANT"`, `"LINEAR"`, `"BEZIER"` - sampling between keyframes.
+    #[pyo3(get, set)] pub interpolation: String,
+    /// One of `"PLAY"`, `"LOOP"`, `"PING_PONG"` - behaviour past the last keyframe.
+    #[pyo3(get, set)] pub extrapolation: String
 }
 
 #[pyclass]
@@ -68,6 +97,13 @@ impl Object {
 
 #[pyclass]
 pub struct Light {
+    /// Blender light type, one of `"POINT"` or `"SPOT"`.
+    #[pyo3(get, set)] pub light_type: String,
+    #[pyo3(get, set)] pub color: (f32, f32, f32),
+    #[pyo3(get, set)] pub intensity: f32,
+    #[pyo3(get, set)] pub range: f32,
+    /// Cone angle in radians. Only meaningful when `light_type` is `"SPOT"`.
+    #[pyo3(get, set)] pub spot_angle: f32,
     #[pyo3(get, set)] pub animations: Vec<Py<Animation>>,
 }
 #[pymethods]
@@ -87,7 +123,23 @@ impl Light {
 }
 
 #[pyclass]
-pub struct Camera { }
+#[derive(Default)]
+pub struct Camera {
+    #[pyo3(get, set)] pub fov: f32,
+    #[pyo3(get, set)] pub clip_start: f32,
+    #[pyo3(get, set)] pub clip_end: f32
+}
+#[pymethods]
+impl Camera {
+    #[new]
+    fn new() -> Self { Self::default() }
+
+    #[getter]
+    pub fn get_data_type(&self) -> &str { "CAMERA" }
+
+    #[getter]
+    pub fn get_animations(&self) -> Vec<Py<Animation>> { Vec::new() }
+}
 
 #[pyclass]
 #[derive(Default)]
@@ -119,6 +171,60 @@ impl Mesh {
 
     #[getter]
     pub fn get_animations(&self) -> Vec<Py<Animation>> { Vec::new() }
+
+    /// `vert_positions` as a flat `(x, y, z, x, y, z, ...)` array, for
+    /// `mesh.vertices.foreach_set("co", ...)`.
+    #[getter]
+    pub fn get_vert_positions_flat(&self) -> Vec<f32> {
+        self.vert_positions.iter().flat_map(|&(x, y, z)| [x, y, z]).collect()
+    }
+
+    /// `faces` as a flat `(a, b, c, a, b, c, ...)` array of vertex indices,
+    /// for `mesh.loop_triangles`/`mesh.polygons`-style bulk assignment.
+    #[getter]
+    pub fn get_faces_flat(&self) -> Vec<u32> {
+        self.faces.iter().flat_map(|&(a, b, c)| [a as u32, b as u32, c as u32]).collect()
+    }
+
+    /// `loop_normals` as a flat `(x, y, z, x, y, z, ...)` array, for
+    /// `mesh.loops.foreach_set("normal", ...)`.
+    #[getter]
+    pub fn get_loop_normals_flat(&self) -> Vec<f32> {
+        self.loop_normals.iter().flat_map(|&(x, y, z)| [x, y, z]).collect()
+    }
+
+    /// `loop_uv_layers`, but each layer's coordinates are a flat
+    /// `(u, v, u, v, ...)` array instead of a `Vec` of tuples, for
+    /// `uv_layer.data.foreach_set("uv", ...)`.
+    #[getter]
+    pub fn get_loop_uv_layers_flat(&self) -> Vec<(String, Vec<f32>)> {
+        self.loop_uv_layers.iter()
+            .map(|(name, uvs)| (name.clone(), uvs.iter().flat_map(|&(u, v)| [u, v]).collect()))
+            .collect()
+    }
+
+    /// `vert_weights` flattened to the CSR layout a ragged per-vertex array
+    /// needs for bulk loading: `offsets` has one entry per vertex plus a
+    /// final total, so vertex `i`'s weights are
+    /// `bone_indices[offsets[i]..offsets[i+1]]` paired with `weights` at the
+    /// same range.
+    #[getter]
+    pub fn get_vert_weights_flat(&self) -> (Vec<u32>, Vec<u32>, Vec<f32>) {
+        let mut offsets = Vec::with_capacity(self.vert_weights.len() + 1);
+        let mut bone_indices = Vec::new();
+        let mut weights = Vec::new();
+
+        offsets.push(0u32);
+        for vw in &self.vert_weights {
+            for &(bone_index, weight) in vw {
+                bone_indices.push(bone_index);
+                weights.push(weight);
+            }
+            offsets.push(bone_indices.len() as u32);
+        }
+
+        (offsets, bone_indices, weights)
+    }
 }
 
 #[pyclass]
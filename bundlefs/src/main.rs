@@ -8,7 +8,7 @@ use winapi::shared::ntstatus;
 use winapi::um::winnt;
 
 use pd2tools_rust::bundles::database::Database;
-use pd2tools_rust::filesystem::{raw_bundledb, transcoder, teststub};
+use pd2tools_rust::filesystem::{raw_bundledb, transcoder, unhash_fs, teststub};
 use pd2tools_rust::filesystem::{FsError, FsReadHandle, ReadOnlyFs};
 use pd2tools_rust::util::LIB_VERSION;
 
@@ -30,18 +30,35 @@ fn main() {
 
     let hashlist = pd2tools_rust::get_hashlist(&opt.hashlist).unwrap();
     let db = pd2tools_rust::get_packagedb(hashlist, &opt.asset_dir).unwrap();
-    mount_cooked_database(&opt.mountpoint, db.hashes.clone(), Arc::new(db));
+    mount_cooked_database(&opt.mountpoint, &opt.asset_dir, db.hashes.clone(), Arc::new(db));
 }
 
-pub fn mount_cooked_database(mountpoint: &str, hashlist: Arc<pd2tools_rust::hashindex::HashIndex>, db: Arc<Database>) {
+/// Loads the unhashing dictionary cached under `asset_dir` from a previous scan, or
+/// builds and caches a fresh one by scanning `db` if there isn't one yet.
+fn load_or_build_dictionary(asset_dir: &str, db: &Database) -> fnv::FnvHashMap<u64, std::rc::Rc<str>> {
+    let cache_path = std::path::Path::new(asset_dir).join("unhash_cache.txt");
+    if let Ok(dictionary) = pd2tools_rust::hashlist_scan::load_dictionary(&cache_path) {
+        return dictionary;
+    }
+
+    let dictionary = pd2tools_rust::hashlist_scan::build_dictionary(db, &[]);
+    if let Err(e) = pd2tools_rust::hashlist_scan::save_dictionary(&dictionary, &cache_path) {
+        eprintln!("Couldn't save unhash cache to {:?}: {}", cache_path, e);
+    }
+    dictionary
+}
+
+pub fn mount_cooked_database(mountpoint: &str, asset_dir: &str, hashlist: Arc<pd2tools_rust::hashindex::HashIndex>, db: Arc<Database>) {
     let mp = U16CString::from_str(mountpoint).unwrap();
+    let dictionary = load_or_build_dictionary(asset_dir, &db);
     let rawdb : Arc<dyn ReadOnlyFs> = Arc::new(raw_bundledb::BundleFs::new(db));
+    let transcoded : Arc<dyn ReadOnlyFs> = Arc::new(transcoder::TranscoderFs::new(hashlist, rawdb));
     let handler = DokanAdapter {
-        fs: transcoder::TranscoderFs::new(hashlist, rawdb),
+        fs: unhash_fs::UnhashFs::new(transcoded, dictionary),
         name: U16CString::from_str("Diesel Assets").unwrap(),
         serial: 0xf8be397b
     };
-    
+
     {
         let mut drive = Drive::new();
         drive
@@ -245,7 +262,8 @@ impl IntoExt<OperationError> for FsError {
             FsError::IsDirectory => OperationError::NtStatus(ntstatus::STATUS_FILE_IS_A_DIRECTORY),
             FsError::NotFound => OperationError::NtStatus(ntstatus::STATUS_NOT_FOUND),
             FsError::ReadError => OperationError::Win32(winapi::shared::winerror::ERROR_READ_FAULT),
-            FsError::OsError(oe) => OperationError::Win32(oe.try_into().unwrap())
+            FsError::OsError(oe) => OperationError::Win32(oe.try_into().unwrap()),
+            FsError::Unsupported => OperationError::NtStatus(ntstatus::STATUS_NOT_SUPPORTED)
         }
     }
 }
@@ -188,29 +188,60 @@ fn fields_rw(stream: TokenStream, item: TokenStream, fields: &syn::Fields) -> Re
 
     struct FieldInfo {
         skip_before: Option<LitInt>,
-        wire_type: syn::Type,
+        wire_type: TokenStream,
         name: syn::Member,
-        local_name: Ident
+        local_name: Ident,
+        present_if: Option<Expr>
     }
 
     let mut field_infos = Vec::<FieldInfo>::with_capacity(field_list.len());
     for (idx, field) in field_list.iter().enumerate() {
-        let wire_type = match parse_attribute(&field.attrs, "read_as") {
-            Ok(Some(s)) => s,
-            Ok(None) => field.ty.clone(),
+        let present_if = match parse_attribute::<Expr>(&field.attrs, "present_if") {
+            Ok(s) => s,
+            Err(e) => return Err(e.into_compile_error())
+        };
+        // A `#[present_if(...)]` field is only read/written when the
+        // condition holds, so its declared type is the `Option<T>` the
+        // caller sees, not the `T` that's actually on the wire - resolve
+        // `read_as`/`pd2` against the inner `T` instead.
+        let underlying_ty = if present_if.is_some() {
+            match option_elem_type(&field.ty) {
+                Some(t) => t,
+                None => return Err(syn::Error::new_spanned(&field.ty, "#[present_if(...)] fields must be declared as Option<T>").into_compile_error())
+            }
+        }
+        else {
+            field.ty.clone()
+        };
+        let explicit_wire_type = match parse_attribute::<syn::Type>(&field.attrs, "read_as") {
+            Ok(s) => s,
             Err(e) => return Err(e.into_compile_error())
         };
+        let wire_type = match (explicit_wire_type, parse_pd2(&field.attrs)) {
+            (Some(t), _) => quote!{ #t },
+            (None, Err(e)) => return Err(e.into_compile_error()),
+            (None, Ok(pd2)) => match pd2_wire_type(&underlying_ty, &pd2) {
+                Ok(t) => t,
+                Err(e) => return Err(e.into_compile_error())
+            }
+        };
         let skip_before = match parse_attribute::<LitInt>(&field.attrs, "skip_before") {
             Ok(s) => s,
             Err(e) => return Err(e.into_compile_error())
         };
-        let local_name = Ident::new(&format!("v_{}", idx), Span::call_site());
+        // Named fields bind under their own name rather than `v_N`, so a
+        // later field's `#[present_if(...)]` expression can refer to them
+        // the way it'd refer to any other local - e.g. `#[present_if(version >= 2)]`.
+        let local_name = match &field.ident {
+            Some(n) => n.clone(),
+            None => Ident::new(&format!("v_{}", idx), Span::call_site())
+        };
         let name = match &field.ident {
             Some(n) => syn::Member::Named(n.clone()),
             None => syn::Member::Unnamed(syn::Index{ index:idx as u32, span: Span::call_site() })
         };
         field_infos.push(FieldInfo{
-            skip_before, wire_type, local_name, name
+            skip_before, wire_type, local_name, name, present_if
         });
     }
 
@@ -219,15 +250,27 @@ fn fields_rw(stream: TokenStream, item: TokenStream, fields: &syn::Fields) -> Re
     let mut structor_parts = Vec::<TokenStream>::with_capacity(field_list.len());
 
     for field in field_infos {
-        let FieldInfo { wire_type, local_name, name, skip_before } = field;
-        
+        let FieldInfo { wire_type, local_name, name, skip_before, present_if } = field;
+
         if let Some(s) = skip_before {
             reader_statements.push(quote!{ let mut p = [0u8; #s]; #stream.read_exact(&mut p)? });
             writer_statements.push(quote!{ let p = [0u8; #s]; #stream.write_all(&p)? });
         }
 
-        reader_statements.push(quote!{ let #local_name = #stream.read_item_as::<#wire_type>()? });
-        writer_statements.push(quote!{ #stream.write_item_as::<#wire_type>(&#local_name)? });
+        match present_if {
+            Some(cond) => {
+                reader_statements.push(quote!{
+                    let #local_name = if #cond { Some(#stream.read_item_as::<#wire_type>()?) } else { None }
+                });
+                writer_statements.push(quote!{
+                    if let Some(v) = #local_name { #stream.write_item_as::<#wire_type>(v)?; }
+                });
+            },
+            None => {
+                reader_statements.push(quote!{ let #local_name = #stream.read_item_as::<#wire_type>()? });
+                writer_statements.push(quote!{ #stream.write_item_as::<#wire_type>(&#local_name)? });
+            }
+        }
 
         match fields {
             syn::Fields::Named(_) => structor_parts.push(quote!{ #name: #local_name }),
@@ -245,6 +288,126 @@ fn fields_rw(stream: TokenStream, item: TokenStream, fields: &syn::Fields) -> Re
     Ok(FieldRw { reader_statements, writer_statements, structor_body })
 }
 
+/// Field-level `#[pd2(...)]` options mirroring the generic helpers in
+/// `util::binaryreader` - an alternative to spelling the wire type out by hand
+/// with `#[read_as(...)]`.
+#[derive(Default)]
+struct Pd2Field {
+    /// `#[pd2(count = "u16")]` - read a `Vec`/`String`'s length as this integer
+    /// type instead of the default `u32`.
+    count: Option<syn::Type>,
+    /// `#[pd2(null_terminated)]` - a `String` with no length prefix at all,
+    /// ending at the first NUL byte.
+    null_terminated: bool,
+    /// `#[pd2(encoding = "w1252")]` - a `String` in some encoding other than
+    /// UTF-8. Only `"w1252"` (Windows-1252) is recognised so far.
+    encoding: Option<syn::LitStr>
+}
+
+fn parse_pd2(attrs: &Vec<Attribute>) -> syn::Result<Pd2Field> {
+    let mut out = Pd2Field::default();
+    let attr = match get_attribute(attrs, "pd2") {
+        Some(a) => a,
+        None => return Ok(out)
+    };
+
+    let metas = attr.parse_args_with(syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated)?;
+    for meta in metas {
+        match &meta {
+            syn::Meta::NameValue(nv) if nv.path.is_ident("count") => {
+                let lit = match &nv.lit {
+                    syn::Lit::Str(s) => s,
+                    _ => return Err(syn::Error::new_spanned(nv, "pd2(count) must be a string naming the count type, e.g. \"u16\""))
+                };
+                out.count = Some(lit.parse()?);
+            },
+            syn::Meta::NameValue(nv) if nv.path.is_ident("encoding") => {
+                let lit = match &nv.lit {
+                    syn::Lit::Str(s) => s,
+                    _ => return Err(syn::Error::new_spanned(nv, "pd2(encoding) must be a string"))
+                };
+                out.encoding = Some(lit.clone());
+            },
+            syn::Meta::Path(p) if p.is_ident("null_terminated") => out.null_terminated = true,
+            other => return Err(syn::Error::new_spanned(other, "unrecognised pd2 field option"))
+        }
+    }
+    Ok(out)
+}
+
+/// Resolves a field's `pd2(...)` options (if any) against its declared type
+/// into the wire type its `ItemReader` should actually be read/written as.
+fn pd2_wire_type(ty: &syn::Type, pd2: &Pd2Field) -> syn::Result<TokenStream> {
+    if pd2.null_terminated {
+        return Ok(quote!{ binaryreader::NullTerminatedUtf8String });
+    }
+    if let Some(enc) = &pd2.encoding {
+        return match enc.value().as_str() {
+            "w1252" => Ok(quote!{ binaryreader::NullTerminated1252String }),
+            other => Err(syn::Error::new_spanned(enc, format!("unrecognised pd2 encoding {:?}", other)))
+        };
+    }
+    if let Some(count_ty) = &pd2.count {
+        return match vec_elem_type(ty)? {
+            // `Vec<u8>` is the one element type `PodVec` can bulk-read with a
+            // single `read_exact`/`write_all` instead of one `read_item` call
+            // per byte, so prefer it over the generic element-at-a-time path.
+            Some(elem) if is_u8(&elem) => Ok(quote!{ binaryreader::PodVec<u8, #count_ty> }),
+            Some(elem) => Ok(quote!{ binaryreader::CountedVec<#elem, #count_ty> }),
+            None => Ok(quote!{ binaryreader::CountedString<#count_ty> })
+        };
+    }
+    Ok(quote!{ #ty })
+}
+
+fn is_u8(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Path(p) if p.path.is_ident("u8"))
+}
+
+/// `Some(T)` if `ty` is `Vec<T>`, `None` if it's `String` - the only two types
+/// `#[pd2(count = ...)]` makes sense on - or an error otherwise.
+fn vec_elem_type(ty: &syn::Type) -> syn::Result<Option<syn::Type>> {
+    let path = match ty {
+        syn::Type::Path(p) => &p.path,
+        _ => return Err(syn::Error::new_spanned(ty, "pd2(count) only applies to Vec<_> or String fields"))
+    };
+    let segment = match path.segments.last() {
+        Some(s) => s,
+        None => return Err(syn::Error::new_spanned(ty, "pd2(count) only applies to Vec<_> or String fields"))
+    };
+
+    if segment.ident == "String" {
+        return Ok(None);
+    }
+    if segment.ident == "Vec" {
+        if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
+            if let Some(syn::GenericArgument::Type(elem)) = args.args.first() {
+                return Ok(Some(elem.clone()));
+            }
+        }
+    }
+    Err(syn::Error::new_spanned(ty, "pd2(count) only applies to Vec<_> or String fields"))
+}
+
+/// `Some(T)` if `ty` is `Option<T>`, used to recover the on-wire type of a
+/// `#[present_if(...)]` field from its declared `Option<T>` Rust type.
+fn option_elem_type(ty: &syn::Type) -> Option<syn::Type> {
+    let path = match ty {
+        syn::Type::Path(p) => &p.path,
+        _ => return None
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
+        if let Some(syn::GenericArgument::Type(elem)) = args.args.first() {
+            return Some(elem.clone());
+        }
+    }
+    None
+}
+
 fn get_attribute<'a>(attrs: &'a Vec<Attribute>, name: &str) -> Option<&'a Attribute> {
     attrs.iter().filter(|i| i.path.segments[0].ident == name).next()
 }
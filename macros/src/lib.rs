@@ -1,4 +1,5 @@
 mod binaryreader;
+mod parse_derive;
 
 use proc_macro2::{Ident, Span, TokenStream};
 use quote::{quote, quote_spanned};
@@ -103,12 +104,18 @@ pub fn derive_enum_from_data(item: proc_macro::TokenStream) -> proc_macro::Token
     TokenStream::from(quote!{ #(#trees)* }).into()
 }
 
-#[proc_macro_derive(ItemReader, attributes(read_as, skip_before, tag))]
+#[proc_macro_derive(ItemReader, attributes(read_as, skip_before, tag, pd2, present_if))]
 pub fn derive_itemreader(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let item = syn::parse_macro_input!(item as syn::DeriveInput);
     binaryreader::derive_itemreader(item).into()
 }
 
+#[proc_macro_derive(Parse, attributes(skip_before))]
+pub fn derive_parse(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = syn::parse_macro_input!(item as syn::DeriveInput);
+    parse_derive::derive_parse(item)
+}
+
 #[proc_macro]
 pub fn tuple_itemreaders(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = syn::parse_macro_input!(item as syn::LitInt);
@@ -178,14 +185,14 @@ pub fn derive_wraps_pyany(item: proc_macro::TokenStream) -> proc_macro::TokenStr
 
     quote!{
         impl #impl_generics #name #ty_generics #where_cl {
-            #vis fn wrap(ob: & #lt PyAny) -> Self {
+            #vis fn wrap(ob: pyo3::Bound<#lt, PyAny>) -> Self {
                 Self(ob #phantom)
             }
         }
         impl #impl_generics WrapsPyAny<#lt> for #name #ty_generics #where_cl {
             fn py(&self) -> Python<#lt> { self.0.py() }
             fn as_ptr(&self) -> *mut pyo3::ffi::PyObject { self.0.as_ptr() }
-            fn as_pyany(&self) -> & #lt PyAny { self.0 }
+            fn as_pyany(&self) -> &pyo3::Bound<#lt, PyAny> { &self.0 }
         }
         impl #impl_generics pyo3::conversion::IntoPy<PyObject> for #name #ty_generics #where_cl{
             fn into_py(self, py: Python<'_>) -> PyObject {
@@ -194,12 +201,12 @@ pub fn derive_wraps_pyany(item: proc_macro::TokenStream) -> proc_macro::TokenStr
         }
         impl #impl_generics pyo3::conversion::ToPyObject for #name #ty_generics #where_cl {
             fn to_object(&self, py: Python<'_>) -> PyObject {
-                self.0.into_py(py)
+                self.0.to_object(py)
             }
         }
         impl #impl_generics pyo3::conversion::FromPyObject<#lt> for #name #ty_generics #where_cl {
-            fn extract(ob: & #lt PyAny) -> PyResult<Self> {
-                Ok(Self::wrap(ob))
+            fn extract(ob: &pyo3::Bound<#lt, PyAny>) -> PyResult<Self> {
+                Ok(Self::wrap(ob.clone()))
             }
         }
     }.into()
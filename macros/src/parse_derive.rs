@@ -0,0 +1,134 @@
+//use proc_macro::{TokenStream};
+use proc_macro2::{Span, Ident, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::{Attribute, LitInt};
+
+/* Mirrors binaryreader.rs's ItemReader derive, but for the nom-based `Parse`
+trait instead of the stream-based `ItemReader` one: per field, parse then
+tag any error with a ContextFrame::Field frame (so a failure deep inside a
+struct can be traced back to the field that was being read), then tag the
+whole thing with a ContextFrame::Struct frame so the struct's name shows up
+in the breadcrumb too. See parse_helpers::ContextFrame/ParseError. */
+
+pub fn derive_parse(item: syn::DeriveInput) -> proc_macro::TokenStream {
+    match &item.data {
+        syn::Data::Struct(s) => rw_struct(&item.ident, s),
+        _ => quote_spanned!{ item.ident.span()=> compile_error!("Parse can only be derived for structs") },
+    }.into()
+}
+
+fn rw_struct(name: &Ident, struc: &syn::DataStruct) -> TokenStream {
+    let frw = match fields_parse(&struc.fields) {
+        Err(e) => return e,
+        Ok(o) => o
+    };
+
+    let FieldParse { reader_statements, writer_statements, structor_body } = frw;
+    let struct_name = name.to_string();
+
+    quote! {
+        impl parse_helpers::Parse for #name {
+            fn parse<'a>(input: &'a [u8]) -> nom::IResult<&'a [u8], Self, parse_helpers::ParseError> {
+                (|| {
+                    let rest = input;
+                    #(#reader_statements;)*
+                    Ok((rest, Self#structor_body))
+                })().map_err(|e| parse_helpers::push_frame(e, parse_helpers::ContextFrame::Struct(#struct_name)))
+            }
+
+            fn serialize<O: std::io::Write>(&self, output: &mut O) -> std::io::Result<()> {
+                let Self#structor_body = self;
+                #(#writer_statements;)*
+                Ok(())
+            }
+        }
+    }
+}
+
+struct FieldParse {
+    reader_statements: Vec<TokenStream>,
+    writer_statements: Vec<TokenStream>,
+    structor_body: TokenStream
+}
+
+fn fields_parse(fields: &syn::Fields) -> Result<FieldParse, TokenStream> {
+    let empty = Default::default();
+    let field_list = match fields {
+        syn::Fields::Named(na) => &na.named,
+        syn::Fields::Unnamed(un) => &un.unnamed,
+        syn::Fields::Unit => &empty,
+    };
+
+    struct FieldInfo {
+        skip_before: Option<LitInt>,
+        ty: syn::Type,
+        name: syn::Member,
+        local_name: Ident,
+        field_label: String
+    }
+
+    let mut field_infos = Vec::<FieldInfo>::with_capacity(field_list.len());
+    for (idx, field) in field_list.iter().enumerate() {
+        let skip_before = match parse_attribute::<LitInt>(&field.attrs, "skip_before") {
+            Ok(s) => s,
+            Err(e) => return Err(e.into_compile_error())
+        };
+        let local_name = Ident::new(&format!("v_{}", idx), Span::call_site());
+        let (name, field_label) = match &field.ident {
+            Some(n) => (syn::Member::Named(n.clone()), n.to_string()),
+            None => (syn::Member::Unnamed(syn::Index{ index:idx as u32, span: Span::call_site() }), idx.to_string())
+        };
+        field_infos.push(FieldInfo{
+            skip_before, ty: field.ty.clone(), local_name, name, field_label
+        });
+    }
+
+    let mut reader_statements = Vec::<TokenStream>::with_capacity(field_list.len());
+    let mut writer_statements = Vec::<TokenStream>::with_capacity(field_list.len());
+    let mut structor_parts = Vec::<TokenStream>::with_capacity(field_list.len());
+
+    for field in field_infos {
+        let FieldInfo { ty, local_name, name, skip_before, field_label } = field;
+
+        if let Some(s) = skip_before {
+            reader_statements.push(quote!{
+                let (rest, _) = nom::bytes::complete::take(#s as usize)(rest)
+                    .map_err(|e| parse_helpers::push_frame(e, parse_helpers::ContextFrame::Field(#field_label)))?
+            });
+            writer_statements.push(quote!{ output.write_all(&[0u8; #s])? });
+        }
+
+        reader_statements.push(quote!{
+            let (rest, #local_name) = <#ty as parse_helpers::Parse>::parse(rest)
+                .map_err(|e| parse_helpers::push_frame(e, parse_helpers::ContextFrame::Field(#field_label)))?
+        });
+        writer_statements.push(quote!{ <#ty as parse_helpers::Parse>::serialize(#local_name, output)? });
+
+        match fields {
+            syn::Fields::Named(_) => structor_parts.push(quote!{ #name: #local_name }),
+            syn::Fields::Unnamed(_) => structor_parts.push(quote!{ #local_name }),
+            syn::Fields::Unit => (),
+        }
+    }
+
+    let structor_body = match fields {
+        syn::Fields::Named(_) => quote!{{ #(#structor_parts),* }},
+        syn::Fields::Unnamed(_) => quote!{( #(#structor_parts),* )},
+        syn::Fields::Unit => quote!{},
+    };
+
+    Ok(FieldParse { reader_statements, writer_statements, structor_body })
+}
+
+fn get_attribute<'a>(attrs: &'a Vec<Attribute>, name: &str) -> Option<&'a Attribute> {
+    attrs.iter().filter(|i| i.path.segments[0].ident == name).next()
+}
+
+fn parse_attribute<'a, T: syn::parse::Parse>(attrs: &'a Vec<Attribute>, name: &str) -> syn::Result<Option<T>> {
+    if let Some(attr) = get_attribute(attrs, name) {
+        attr.parse_args::<T>().map(Some)
+    }
+    else {
+        Ok(None)
+    }
+}
@@ -0,0 +1,109 @@
+//! Renders a resolved [`Schema`] as a Rust module: one `#[derive(Parse)]`
+//! struct per record, one `#[derive(EnumTryFrom)]` enum (plus a hand-written
+//! `Parse` impl wrapping it) per enum, and a small hand-written tagged-union
+//! type per union, since a union's tag lives on a sibling field rather than
+//! in its own bytes and so isn't self-describing enough for a derive.
+
+use crate::model::{Definition, DefinitionKind, Schema, TypeRef};
+
+pub fn render(schema: &Schema) -> String {
+    let mut out = String::new();
+    out.push_str("//! Generated by schema_compiler. Do not edit by hand - edit the .schema\n");
+    out.push_str("//! source and regenerate instead.\n\n");
+    out.push_str("use pd2tools_rust::util::parse_helpers;\n");
+    out.push_str("use pd2tools_rust::util::parse_helpers::*;\n\n");
+
+    for def in &schema.definitions {
+        render_definition(def, &mut out);
+        out.push('\n');
+    }
+
+    out
+}
+
+fn render_definition(def: &Definition, out: &mut String) {
+    match &def.kind {
+        DefinitionKind::Record(fields) => render_record(&def.name, fields, out),
+        DefinitionKind::Enum { repr, variants } => render_enum(&def.name, repr, variants, out),
+        DefinitionKind::Union { tag_type, variants } => render_union(&def.name, tag_type, variants, out)
+    }
+}
+
+fn render_record(name: &str, fields: &[crate::model::Field], out: &mut String) {
+    out.push_str("#[derive(pd2tools_macros::Parse)]\n");
+    out.push_str(&format!("pub struct {} {{\n", name));
+    for field in fields {
+        if let Some(n) = field.skip_before {
+            out.push_str(&format!("    #[skip_before({})]\n", n));
+        }
+        out.push_str(&format!("    pub {}: {},\n", field.name, render_type(&field.ty, field.boxed)));
+    }
+    out.push_str("}\n");
+}
+
+fn render_enum(name: &str, repr: &TypeRef, variants: &[(String, i64)], out: &mut String) {
+    let repr_name = render_type(repr, false);
+    out.push_str("#[derive(Copy, Clone, PartialEq, Eq, Debug, pd2tools_macros::EnumTryFrom)]\n");
+    out.push_str(&format!("pub enum {} {{\n", name));
+    for (variant, value) in variants {
+        out.push_str(&format!("    {} = {},\n", variant, value));
+    }
+    out.push_str("}\n");
+
+    out.push_str(&format!("impl Parse for {} {{\n", name));
+    out.push_str("    fn parse<'a>(input: &'a [u8]) -> nom::IResult<&'a [u8], Self, ParseError> {\n");
+    out.push_str(&format!(
+        "        nom::combinator::map_res(<{} as Parse>::parse, {}::try_from)(input)\n",
+        repr_name, name
+    ));
+    out.push_str("    }\n");
+    out.push_str("    fn serialize<O: std::io::Write>(&self, output: &mut O) -> std::io::Result<()> {\n");
+    out.push_str(&format!("        let wire: {} = (*self).into();\n", repr_name));
+    out.push_str("        wire.serialize(output)\n");
+    out.push_str("    }\n");
+    out.push_str("}\n");
+}
+
+fn render_union(name: &str, tag_type: &TypeRef, variants: &[(i64, String, TypeRef)], out: &mut String) {
+    let tag_name = render_type(tag_type, false);
+    out.push_str(&format!("pub enum {} {{\n", name));
+    for (_, variant, ty) in variants {
+        out.push_str(&format!("    {}({}),\n", variant, render_type(ty, false)));
+    }
+    out.push_str("}\n");
+
+    out.push_str(&format!("impl {} {{\n", name));
+    out.push_str(&format!(
+        "    pub fn parse_with_tag<'a>(tag: {}, input: &'a [u8]) -> nom::IResult<&'a [u8], Self, ParseError> {{\n",
+        tag_name
+    ));
+    out.push_str("        match tag {\n");
+    for (tag_value, variant, ty) in variants {
+        out.push_str(&format!(
+            "            {} => nom::combinator::map(<{} as Parse>::parse, {}::{})(input),\n",
+            tag_value, render_type(ty, false), name, variant
+        ));
+    }
+    out.push_str("            _ => Err(nom::Err::Failure(<ParseError as nom::error::ParseError<&[u8]>>::from_error_kind(input, nom::error::ErrorKind::Alt)))\n");
+    out.push_str("        }\n");
+    out.push_str("    }\n\n");
+    out.push_str("    pub fn serialize<O: std::io::Write>(&self, output: &mut O) -> std::io::Result<()> {\n");
+    out.push_str("        match self {\n");
+    for (_, variant, _) in variants {
+        out.push_str(&format!("            {}::{}(v) => v.serialize(output),\n", name, variant));
+    }
+    out.push_str("        }\n");
+    out.push_str("    }\n");
+    out.push_str("}\n");
+}
+
+fn render_type(ty: &TypeRef, boxed: bool) -> String {
+    let inner = match ty {
+        TypeRef::Primitive(p) if p == "Hash" => "crate::hashindex::Hash".to_owned(),
+        TypeRef::Primitive(p) => p.clone(),
+        TypeRef::Named(n) => n.clone(),
+        TypeRef::Vec(inner) => format!("Vec<{}>", render_type(inner, false)),
+        TypeRef::Array(inner, n) => format!("[{}; {}]", render_type(inner, false), n)
+    };
+    if boxed { format!("Box<{}>", inner) } else { inner }
+}
@@ -0,0 +1,61 @@
+//! Schema compiler: turns a declarative description of an on-disk binary
+//! format into the Rust types (`#[derive(Parse)]` structs, discriminanted
+//! enums, tagged unions) that would otherwise be hand-written the way
+//! `src/bundles/bundledb_reader.rs` does it today.
+//!
+//! Pipeline: [`parser::parse`] a schema file into a [`model::Schema`],
+//! [`resolve::resolve`] named references across it (and box the field that
+//! closes any reference cycle, since a record can't contain itself
+//! inline), then [`codegen::render`] the result as one Rust module.
+//!
+//! The payoff this is aimed at: the dozens of Diesel/model structs this
+//! crate already hand-writes become schema *data*, and a format variant
+//! across game versions is a different schema file rather than an edited
+//! Rust module.
+
+mod model;
+mod parser;
+mod resolve;
+mod codegen;
+
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("usage: {} <schema-file> <output-rs-file>", args.get(0).map(String::as_str).unwrap_or("schema_compiler"));
+        return ExitCode::FAILURE;
+    }
+
+    let src = match fs::read_to_string(&args[1]) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("error reading '{}': {}", args[1], e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut schema = match parser::parse(&src) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("error parsing '{}': {}", args[1], e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(e) = resolve::resolve(&mut schema) {
+        eprintln!("error resolving '{}': {}", args[1], e);
+        return ExitCode::FAILURE;
+    }
+
+    let rendered = codegen::render(&schema);
+
+    if let Err(e) = fs::write(&args[2], rendered) {
+        eprintln!("error writing '{}': {}", args[2], e);
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
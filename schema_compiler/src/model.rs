@@ -0,0 +1,74 @@
+//! Intermediate representation for a schema file: one [`Definition`] per
+//! named record/enum/union, each built out of the same handful of shapes
+//! the hand-written `#[derive(Parse)]`/`#[derive(ItemReader)]` structs in
+//! `src/bundles/bundledb_reader.rs` and friends already use. The schema
+//! compiler's job is turning this into Rust, not inventing new shapes, so
+//! this mirrors that existing vocabulary rather than a generic IDL's.
+
+#[derive(Debug, Clone)]
+pub struct Schema {
+    pub definitions: Vec<Definition>
+}
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: String,
+    pub kind: DefinitionKind
+}
+
+#[derive(Debug, Clone)]
+pub enum DefinitionKind {
+    /// A plain struct: fields read in order, same as `#[derive(Parse)]` today.
+    Record(Vec<Field>),
+    /// A C-like enum with an explicit discriminant per variant, same shape
+    /// as `#[derive(EnumTryFrom)]` expects.
+    Enum {
+        repr: TypeRef,
+        variants: Vec<(String, i64)>
+    },
+    /// A tagged union: one external field elsewhere in the containing
+    /// record gives the variant tag, and the payload shape depends on it.
+    Union {
+        tag_type: TypeRef,
+        variants: Vec<(i64, String, TypeRef)>
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: String,
+    pub ty: TypeRef,
+    /// `#[skip_before(n)]` in the hand-written structs: padding bytes to
+    /// discard immediately before this field.
+    pub skip_before: Option<u32>,
+    /// Filled in by [`crate::resolve`]: true if, left alone, this field
+    /// would make `Definition` infinitely sized by way of a reference cycle
+    /// through named types, so codegen needs to wrap it in `Box`.
+    pub boxed: bool
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeRef {
+    /// A type `parse_helpers`/the prelude already knows how to `Parse`:
+    /// `u8`, `u32`, `f32`, `bool`, `String`, `Hash`, etc.
+    Primitive(String),
+    /// A reference to another definition in this schema.
+    Named(String),
+    /// `T[]`: a `u32`-counted `Vec<T>`.
+    Vec(Box<TypeRef>),
+    /// `T[n]`: a fixed-size `[T; n]`.
+    Array(Box<TypeRef>, u32)
+}
+
+impl TypeRef {
+    /// The named definition this type directly depends on, if any -
+    /// `Vec<T>`/`[T; n]` count as depending on `T`'s size only through one
+    /// level of indirection (a `Vec`/array already "boxes" its contents on
+    /// the heap), so only a bare `Named` reference can close a cycle.
+    pub fn direct_dependency(&self) -> Option<&str> {
+        match self {
+            TypeRef::Named(n) => Some(n),
+            _ => None
+        }
+    }
+}
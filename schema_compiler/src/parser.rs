@@ -0,0 +1,200 @@
+//! Hand-rolled recursive-descent parser for schema files, in the same
+//! style as [`crate::formats::scriptdata::lua_like`]'s `Parser`: a cursor
+//! over the source text plus one method per grammar production, returning
+//! `anyhow::Result` rather than threading a dedicated error type through.
+//!
+//! Grammar, informally:
+//!
+//! ```text
+//! schema     := definition*
+//! definition := record | enum_def | union_def
+//! record     := "record" ident "{" field* "}"
+//! field      := ("skip_before" "(" int ")")? ident ":" type ","
+//! enum_def   := "enum" ident ":" type "{" (ident "=" int ",")* "}"
+//! union_def  := "union" ident "(" type ")" "{" (int "=>" ident "(" type ")" ",")* "}"
+//! type       := ident ("[" int? "]")?
+//! ```
+
+use anyhow::{bail, Result};
+
+use crate::model::{Definition, DefinitionKind, Field, Schema, TypeRef};
+
+pub fn parse(src: &str) -> Result<Schema> {
+    let mut p = Parser { input: src, pos: 0 };
+    let mut definitions = Vec::new();
+    p.skip_trivia();
+    while !p.at_end() {
+        definitions.push(p.parse_definition()?);
+        p.skip_trivia();
+    }
+    Ok(Schema { definitions })
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str { &self.input[self.pos..] }
+    fn at_end(&self) -> bool { self.rest().is_empty() }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = self.rest();
+            let ws_len = rest.len() - rest.trim_start().len();
+            self.pos += ws_len;
+            let rest = self.rest();
+            if rest.starts_with("//") {
+                let line_len = rest.find('\n').unwrap_or(rest.len());
+                self.pos += line_len;
+            }
+            else {
+                break;
+            }
+        }
+    }
+
+    fn peek_ident(&self) -> Option<&'a str> {
+        let rest = self.rest();
+        let end = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+        if end == 0 { None } else { Some(&rest[..end]) }
+    }
+
+    fn expect_ident(&mut self) -> Result<&'a str> {
+        let ident = match self.peek_ident() {
+            Some(i) => i,
+            None => bail!("expected identifier at byte {}", self.pos)
+        };
+        self.pos += ident.len();
+        self.skip_trivia();
+        Ok(ident)
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<()> {
+        if !self.rest().starts_with(punct) {
+            bail!("expected '{}' at byte {}, found: {:.20}", punct, self.pos, self.rest());
+        }
+        self.pos += punct.len();
+        self.skip_trivia();
+        Ok(())
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        if self.rest().starts_with(punct) {
+            self.pos += punct.len();
+            self.skip_trivia();
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    fn expect_int(&mut self) -> Result<i64> {
+        let rest = self.rest();
+        let neg = rest.starts_with('-');
+        let digits_start = if neg { 1 } else { 0 };
+        let end = rest[digits_start..].find(|c: char| !c.is_ascii_digit()).map(|i| i + digits_start).unwrap_or(rest.len());
+        if end == digits_start {
+            bail!("expected integer at byte {}", self.pos);
+        }
+        let text = &rest[..end];
+        let value: i64 = text.parse()?;
+        self.pos += end;
+        self.skip_trivia();
+        Ok(value)
+    }
+
+    fn parse_definition(&mut self) -> Result<Definition> {
+        let keyword = self.expect_ident()?;
+        match keyword {
+            "record" => self.parse_record(),
+            "enum" => self.parse_enum(),
+            "union" => self.parse_union(),
+            other => bail!("unknown definition kind '{}'", other)
+        }
+    }
+
+    fn parse_record(&mut self) -> Result<Definition> {
+        let name = self.expect_ident()?.to_owned();
+        self.expect_punct("{")?;
+        let mut fields = Vec::new();
+        while !self.eat_punct("}") {
+            fields.push(self.parse_field()?);
+        }
+        Ok(Definition { name, kind: DefinitionKind::Record(fields) })
+    }
+
+    fn parse_field(&mut self) -> Result<Field> {
+        let mut skip_before = None;
+        if self.peek_ident() == Some("skip_before") {
+            self.expect_ident()?;
+            self.expect_punct("(")?;
+            let n = self.expect_int()?;
+            self.expect_punct(")")?;
+            skip_before = Some(n as u32);
+        }
+        let name = self.expect_ident()?.to_owned();
+        self.expect_punct(":")?;
+        let ty = self.parse_type()?;
+        self.expect_punct(",")?;
+        Ok(Field { name, ty, skip_before, boxed: false })
+    }
+
+    fn parse_type(&mut self) -> Result<TypeRef> {
+        let ident = self.expect_ident()?.to_owned();
+        let mut ty = if is_primitive(&ident) { TypeRef::Primitive(ident) } else { TypeRef::Named(ident) };
+        if self.eat_punct("[") {
+            if self.eat_punct("]") {
+                ty = TypeRef::Vec(Box::new(ty));
+            }
+            else {
+                let n = self.expect_int()?;
+                self.expect_punct("]")?;
+                ty = TypeRef::Array(Box::new(ty), n as u32);
+            }
+        }
+        Ok(ty)
+    }
+
+    fn parse_enum(&mut self) -> Result<Definition> {
+        let name = self.expect_ident()?.to_owned();
+        self.expect_punct(":")?;
+        let repr = self.parse_type()?;
+        self.expect_punct("{")?;
+        let mut variants = Vec::new();
+        while !self.eat_punct("}") {
+            let variant_name = self.expect_ident()?.to_owned();
+            self.expect_punct("=")?;
+            let value = self.expect_int()?;
+            self.expect_punct(",")?;
+            variants.push((variant_name, value));
+        }
+        Ok(Definition { name, kind: DefinitionKind::Enum { repr, variants } })
+    }
+
+    fn parse_union(&mut self) -> Result<Definition> {
+        let name = self.expect_ident()?.to_owned();
+        self.expect_punct("(")?;
+        let tag_type = self.parse_type()?;
+        self.expect_punct(")")?;
+        self.expect_punct("{")?;
+        let mut variants = Vec::new();
+        while !self.eat_punct("}") {
+            let tag = self.expect_int()?;
+            self.expect_punct("=>")?;
+            let variant_name = self.expect_ident()?.to_owned();
+            self.expect_punct("(")?;
+            let payload = self.parse_type()?;
+            self.expect_punct(")")?;
+            self.expect_punct(",")?;
+            variants.push((tag, variant_name, payload));
+        }
+        Ok(Definition { name, kind: DefinitionKind::Union { tag_type, variants } })
+    }
+}
+
+fn is_primitive(name: &str) -> bool {
+    matches!(name, "u8" | "u16" | "u32" | "u64" | "i8" | "i16" | "i32" | "i64" | "f32" | "f64" | "bool" | "String" | "Hash")
+}
@@ -0,0 +1,101 @@
+//! Cross-references named types within a [`Schema`], then walks the
+//! resulting dependency graph to find cycles and mark the field that
+//! closes each one as needing `Box` indirection - a record can't embed
+//! itself inline (infinite size), so somewhere along any cycle one field
+//! has to go on the heap instead.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+
+use crate::model::{Definition, DefinitionKind, Schema, TypeRef};
+
+pub fn resolve(schema: &mut Schema) -> Result<()> {
+    let names: HashMap<String, usize> = schema.definitions.iter()
+        .enumerate()
+        .map(|(i, d)| (d.name.clone(), i))
+        .collect();
+
+    for field_ty in all_named_field_types(schema) {
+        if !names.contains_key(field_ty) {
+            bail!("undefined type '{}' referenced in schema", field_ty);
+        }
+    }
+
+    let mut state = vec![VisitState::Unvisited; schema.definitions.len()];
+    let mut stack: Vec<usize> = Vec::new();
+    for i in 0..schema.definitions.len() {
+        visit(schema, &names, i, &mut state, &mut stack);
+    }
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VisitState { Unvisited, InProgress, Done }
+
+/// Depth-first walk of `def`'s named-type fields, boxing the field of any
+/// edge that points back at a definition still `InProgress` (i.e. an
+/// ancestor in the current path - a cycle), rather than recursing into it.
+fn visit(schema: &mut Schema, names: &HashMap<String, usize>, idx: usize, state: &mut Vec<VisitState>, stack: &mut Vec<usize>) {
+    if state[idx] != VisitState::Unvisited {
+        return;
+    }
+    state[idx] = VisitState::InProgress;
+    stack.push(idx);
+
+    let dependency_field_indices: Vec<usize> = match &schema.definitions[idx].kind {
+        DefinitionKind::Record(fields) => (0..fields.len())
+            .filter(|&i| fields[i].ty.direct_dependency().is_some())
+            .collect(),
+        _ => Vec::new()
+    };
+
+    for field_idx in dependency_field_indices {
+        let target_name = match &schema.definitions[idx].kind {
+            DefinitionKind::Record(fields) => fields[field_idx].ty.direct_dependency().unwrap().to_owned(),
+            _ => unreachable!()
+        };
+        let target = names[&target_name];
+
+        if state[target] == VisitState::InProgress {
+            if let DefinitionKind::Record(fields) = &mut schema.definitions[idx].kind {
+                fields[field_idx].boxed = true;
+            }
+        }
+        else {
+            visit(schema, names, target, state, stack);
+        }
+    }
+
+    stack.pop();
+    state[idx] = VisitState::Done;
+}
+
+fn all_named_field_types(schema: &Schema) -> Vec<&str> {
+    let mut out = Vec::new();
+    for def in &schema.definitions {
+        match &def.kind {
+            DefinitionKind::Record(fields) => {
+                for f in fields {
+                    collect_named(&f.ty, &mut out);
+                }
+            }
+            DefinitionKind::Union { variants, .. } => {
+                for (_, _, ty) in variants {
+                    collect_named(ty, &mut out);
+                }
+            }
+            DefinitionKind::Enum { .. } => ()
+        }
+    }
+    out
+}
+
+fn collect_named<'a>(ty: &'a TypeRef, out: &mut Vec<&'a str>) {
+    match ty {
+        TypeRef::Named(n) => out.push(n),
+        TypeRef::Vec(inner) | TypeRef::Array(inner, _) => collect_named(inner, out),
+        TypeRef::Primitive(_) => ()
+    }
+}
@@ -50,7 +50,7 @@ use roxmltree::{Document as RoxDocument, Node as RoxNode};
 use xmlwriter::XmlWriter;
 use crate::document::DocumentRef;
 use crate::reference_tree::{self as rt, TableHeader};
-use crate::{Key, RoxmlNodeExt, Scalar, SchemaError};
+use crate::{Key, RoxmlNodeExt, Scalar, SchemaError, Span};
 
 fn parse_scalar(input: &str) -> Result<Scalar<Rc<str>>, SchemaError> {
     if input == "true" { return Ok(Scalar::Bool(true)) }
@@ -131,7 +131,8 @@ fn load_node<'t>(elem: RoxNode, parent: &mut rt::NodeMut<'t>, key: Key<Rc<str>>,
         let val = parse_scalar(valstr)?;
         let node = parent.append(rt::Data {
             key,
-            value: rt::Value::Scalar(val)
+            value: rt::Value::Scalar(val),
+            span: Span::default()
         });
         return Ok(node.id());
     }
@@ -143,7 +144,8 @@ fn load_node<'t>(elem: RoxNode, parent: &mut rt::NodeMut<'t>, key: Key<Rc<str>>,
 
         let node = parent.append(rt::Data {
             key,
-            value: rt::Value::Ref(refid.into())
+            value: rt::Value::Ref(refid.into()),
+            span: Span::default()
         });
         return Ok(node.id());
     }
@@ -159,7 +161,8 @@ fn load_node<'t>(elem: RoxNode, parent: &mut rt::NodeMut<'t>, key: Key<Rc<str>>,
         key,
         value: rt::Value::Table(rt::TableHeader {
             id, meta
-        })
+        }),
+        span: Span::default()
     });
 
     for attr in elem.attributes() {
@@ -169,7 +172,8 @@ fn load_node<'t>(elem: RoxNode, parent: &mut rt::NodeMut<'t>, key: Key<Rc<str>>,
                 let val = parse_scalar(attr.value())?;
                 node.append(rt::Data {
                     key: Key::String(name.into()),
-                    value: rt::Value::Scalar(val)
+                    value: rt::Value::Scalar(val),
+                    span: Span::default()
                 });
             }
         }
@@ -193,7 +197,8 @@ fn load_node<'t>(elem: RoxNode, parent: &mut rt::NodeMut<'t>, key: Key<Rc<str>>,
     for (key, target) in keyed_nodes {
         let n = node.append(rt::Data {
             key: key.into(),
-            value: rt::Value::Ref("".into())
+            value: rt::Value::Ref("".into()),
+            span: Span::default()
         });
         fixups.push((n.id(), target));
     }
@@ -232,6 +237,7 @@ impl DumpTable {
             match v {
                 Scalar::Bool(v) => wa!(k, "{}", v),
                 Scalar::Number(v) => wa!(k, "{}", v),
+                Scalar::Integer(v) => wa!(k, "{}", v),
                 Scalar::IdString(v) => wa!(k, "@ID{:>016x}@", v),
                 Scalar::String(v) => wa!(k, "{}", v),
                 Scalar::Vector(v) => wa!(k, "{} {} {}", v.x, v.y, v.z),
@@ -249,6 +255,9 @@ impl DumpTable {
 
 fn rt_node_to_dumpnode(node: rt::Node, xw: &mut XmlWriter) -> DumpTable {
     match &node.value().value {
+        rt::Value::Unset(_) | rt::Value::Include(_) => {
+            unreachable!("directives never appear in a resolved Document, only while loading one")
+        },
         rt::Value::Scalar(s) => DumpTable {
             name: "value_node".into(),
             attributes: vec![("value".into(), s.clone())],
@@ -318,6 +327,9 @@ fn collect_ids(tree: rt::Node) -> HashSet<Rc<str>> {
             rt::Value::Ref(rs) => {
                 seen_refs.insert(rs.clone());
             },
+            rt::Value::Unset(_) | rt::Value::Include(_) => {
+                unreachable!("directives never appear in a resolved Document, only while loading one")
+            }
         }
     }
     seen_refs
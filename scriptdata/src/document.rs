@@ -220,6 +220,16 @@ impl<'t> InteriorTableWriter<'t> {
             BorrowedKey::String(st) => self.string_keyed(&st),
         }
     }
+
+    /// Drop the most recently bound entry with this key, if any. Used to implement
+    /// `%unset`, which needs to un-bind a key an earlier sibling (or an included
+    /// document) already bound, so a later plain entry can bind it again.
+    pub fn unset(&mut self, key: BorrowedKey) -> bool {
+        match key {
+            BorrowedKey::Index(idx) => self.root.tables[self.table].numeric.remove(&idx).is_some(),
+            BorrowedKey::String(s) => self.root.tables[self.table].stringed.remove(s).is_some(),
+        }
+    }
 }
 
 pub struct EntryWriter<'t> {
@@ -19,34 +19,52 @@ use xmlwriter::XmlWriter;
 
 use crate::document::DocumentRef;
 use crate::reference_tree as rt;
-use crate::{Key, OwnedKey, RoxmlNodeExt, Scalar, SchemaError};
+use crate::{Key, LineIndex, OwnedKey, RoxmlNodeExt, Scalar, SchemaError, Span};
+
+fn span_of(node: &RoxNode) -> Span {
+    let r = node.range();
+    Span::new(r.start, r.end)
+}
+
+/// Wraps `err` with the source position of `node`, so a failure deep in
+/// `load_value`/`load_key`/`load_table` says where in the XML it occurred.
+fn at(li: &LineIndex, node: &RoxNode, err: SchemaError) -> SchemaError {
+    SchemaError::At { pos: li.locate(node.range().start), kind: Box::new(err) }
+}
 
 pub fn load<'a>(doc: &'a RoxDocument<'a>) -> Result<DocumentRef, SchemaError> {
+    let li = LineIndex::new(doc.input_text());
     let rn = doc.root_element();
-    rn.assert_name("generic_scriptdata")?;
-    
-    let root_data = load_value(&rn)?;
+    rn.assert_name("generic_scriptdata").map_err(|e| at(&li, &rn, e))?;
+
+    let root_data = load_value(&li, &rn)?;
     let reftree = match root_data {
         rt::Value::Ref(r) => return Err(SchemaError::DanglingReference(r.into())),
         rt::Value::Scalar(_) => {
             rt::Tree::new(rt::Data {
                 key: OwnedKey::Index(0),
-                value: root_data
+                value: root_data,
+                span: span_of(&rn)
             })
         },
         rt::Value::Table(_) => {
             let mut tree = rt::Tree::new(rt::Data {
                 key: OwnedKey::Index(0),
-                value: root_data
+                value: root_data,
+                span: span_of(&rn)
             });
-            load_table(&rn, tree.root_mut())?;
+            load_table(&li, &rn, tree.root_mut())?;
             tree
         }
     };
     rt::to_document(reftree)
 }
 
-fn load_value<'a, 'input>(node: &RoxNode<'a, 'input>) -> Result<rt::Value, SchemaError> {
+fn load_value<'a, 'input>(li: &LineIndex, node: &RoxNode<'a, 'input>) -> Result<rt::Value, SchemaError> {
+    load_value_inner(node).map_err(|e| at(li, node, e))
+}
+
+fn load_value_inner<'a, 'input>(node: &RoxNode<'a, 'input>) -> Result<rt::Value, SchemaError> {
     use rt::Value::Scalar as VS;
     match (node.required_attribute("type")?, node.attribute("value")) {
         ("boolean", Some("true")) => Ok(VS(true.into())),
@@ -58,6 +76,11 @@ fn load_value<'a, 'input>(node: &RoxNode<'a, 'input>) -> Result<rt::Value, Schem
             Err(_) => Err(SchemaError::InvalidFloat)
         },
 
+        ("integer", Some(ns)) => match i64::from_str(ns) {
+            Ok(n) => Ok(VS(n.into())),
+            Err(_) => Err(SchemaError::BadIndex(ns.into()))
+        },
+
         ("idstring", Some(ids)) => match u64::from_str_radix(ids, 16) {
             Ok(val) => Ok(VS(val.swap_bytes().into())),
             Err(_) => Err(SchemaError::InvalidIdString)
@@ -102,7 +125,11 @@ fn load_value<'a, 'input>(node: &RoxNode<'a, 'input>) -> Result<rt::Value, Schem
     }
 }
 
-fn load_key<'a, 'input>(node: &RoxNode<'a, 'input>) -> Result<OwnedKey, SchemaError> {
+fn load_key<'a, 'input>(li: &LineIndex, node: &RoxNode<'a, 'input>) -> Result<OwnedKey, SchemaError> {
+    load_key_inner(node).map_err(|e| at(li, node, e))
+}
+
+fn load_key_inner<'a, 'input>(node: &RoxNode<'a, 'input>) -> Result<OwnedKey, SchemaError> {
     match (node.attribute("index"), node.attribute("key")) {
         (Some(i), Some(k)) => Err(SchemaError::KeyAndIndex(i.into(), k.into())),
         (Some(i), None) => match usize::from_str_radix(i, 10) {
@@ -114,18 +141,34 @@ fn load_key<'a, 'input>(node: &RoxNode<'a, 'input>) -> Result<OwnedKey, SchemaEr
     }
 }
 
-fn load_table<'t, 'a, 'input>(xml: &RoxNode<'a, 'input>, mut reftree: rt::NodeMut) -> Result<(), SchemaError> {
+fn load_table<'t, 'a, 'input>(li: &LineIndex, xml: &RoxNode<'a, 'input>, mut reftree: rt::NodeMut) -> Result<(), SchemaError> {
     for n in xml.children() {
-        n.assert_name("entry")?;
-        let key = load_key(&n)?;
-        let datum = load_value(&n)?;
+        n.assert_name("entry").map_err(|e| at(li, &n, e))?;
+
+        // `%include` and `%unset` are directives rather than ordinary entries: they
+        // don't bind a key of their own, so they're recognised before `load_key`/
+        // `load_value` (which require exactly that).
+        if n.attribute("type") == Some("%include") {
+            let path = n.required_attribute("path").map_err(|e| at(li, &n, e))?;
+            reftree.append(rt::Data { key: OwnedKey::Index(0), value: rt::Value::Include(path.into()), span: span_of(&n) });
+            continue;
+        }
+        if n.attribute("type") == Some("%unset") {
+            let key = load_key(li, &n)?;
+            reftree.append(rt::Data { key: key.clone(), value: rt::Value::Unset(key), span: span_of(&n) });
+            continue;
+        }
+
+        let key = load_key(li, &n)?;
+        let datum = load_value(li, &n)?;
         match datum {
-            rt::Value::Scalar(_) => { reftree.append(rt::Data { key, value: datum }); },
-            rt::Value::Ref(_) => { reftree.append(rt::Data {key, value: datum}); },
+            rt::Value::Scalar(_) => { reftree.append(rt::Data { key, value: datum, span: span_of(&n) }); },
+            rt::Value::Ref(_) => { reftree.append(rt::Data {key, value: datum, span: span_of(&n) }); },
             rt::Value::Table(_) => {
-                let child = reftree.append(rt::Data {key, value: datum});
-                load_table(&n, child)?
-            }
+                let child = reftree.append(rt::Data {key, value: datum, span: span_of(&n) });
+                load_table(li, &n, child)?
+            },
+            rt::Value::Unset(_) | rt::Value::Include(_) => unreachable!("handled above")
         };
     }
     Ok(())
@@ -173,6 +216,9 @@ fn dump_entry<'t>(xw: &mut XmlWriter, node: rt::Node<'t>) {
             xw.write_attribute("type", "table");
             xw.write_attribute("_ref", &r);
         },
+        rt::Value::Unset(_) | rt::Value::Include(_) => {
+            unreachable!("directives never appear in a resolved Document, only while loading one")
+        }
     }
 }
 
@@ -186,6 +232,7 @@ fn dump_scalar(xw: &mut XmlWriter, val: &Scalar<Rc<str>>) {
     match val {
         Scalar::Bool(v) => wa!("boolean", "{}", v),
         Scalar::Number(v) => wa!("number", "{}", v),
+        Scalar::Integer(v) => wa!("integer", "{}", v),
         Scalar::IdString(v) => wa!("idstring", "{:>016x}", v),
         Scalar::String(v) => wa!("string", "{}", v),
         Scalar::Vector(v) => wa!("vector", "{} {} {}", v.x, v.y, v.z),
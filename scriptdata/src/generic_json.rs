@@ -0,0 +1,208 @@
+//! `generic_json` scriptdata support
+//!
+//! A JSON-native sibling of [`crate::generic`], over the same `reference_tree`
+//! model so it stays lossless and interchangeable with `generic_xml`.
+//!
+//! - A table with only contiguous integer keys starting at 0, no `_id` and
+//!   no metatable, is written as a JSON array.
+//! - Every other table is a JSON object: string keys are used as-is, integer
+//!   keys are stringified (`"3"`), an `_id` becomes `"$id"` and a metatable
+//!   becomes `"$meta"`.
+//! - A `_ref` is `{"$ref": "<id>"}`.
+//! - Scalars that have a native JSON representation (`bool`, number, string)
+//!   are written directly; the others carry an explicit `$type` tag:
+//!   `{"$type":"idstring","value":"<hex>"}`, `{"$type":"vector","value":[x,y,z]}`,
+//!   `{"$type":"quaternion","value":[x,y,z,w]}`.
+
+use std::rc::Rc;
+
+use serde_json::{Map, Value as Json};
+
+use crate::document::DocumentRef;
+use crate::reference_tree as rt;
+use crate::{Key, OwnedKey, Scalar, SchemaError, Span};
+
+pub fn load(input: &str) -> Result<DocumentRef, SchemaError> {
+    let json: Json = serde_json::from_str(input)
+        .map_err(|e| SchemaError::SyntaxError(Box::new(e)))?;
+
+    if matches!(json, Json::Null) {
+        return Ok(crate::document::DocumentBuilder::new().empty_document())
+    }
+
+    let root_data = load_value(&json)?;
+    let reftree = match root_data {
+        rt::Value::Ref(r) => return Err(SchemaError::DanglingReference(r)),
+        rt::Value::Scalar(_) => rt::Tree::new(rt::Data { key: OwnedKey::Index(0), value: root_data, span: Span::default() }),
+        rt::Value::Table(_) => {
+            let mut tree = rt::Tree::new(rt::Data { key: OwnedKey::Index(0), value: root_data, span: Span::default() });
+            load_table(&json, tree.root_mut())?;
+            tree
+        },
+        rt::Value::Unset(_) | rt::Value::Include(_) => unreachable!("load_value never returns a directive")
+    };
+    rt::to_document(reftree)
+}
+
+fn load_value(json: &Json) -> Result<rt::Value, SchemaError> {
+    use rt::Value::Scalar as VS;
+    match json {
+        Json::Bool(b) => Ok(VS((*b).into())),
+        Json::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                return Ok(VS(i.into()))
+            }
+            let f = n.as_f64().ok_or_else(|| SchemaError::BadValue(Rc::from(n.to_string())))?;
+            Ok(VS((f as f32).into()))
+        },
+        Json::String(s) => Ok(VS(Scalar::String(s.as_str().into()))),
+        Json::Array(_) => Ok(rt::Value::Table(rt::TableHeader { id: None, meta: None })),
+        Json::Object(o) => load_object_header(o),
+        Json::Null => Err(SchemaError::BadValue(Rc::from("null")))
+    }
+}
+
+fn load_object_header(o: &Map<String, Json>) -> Result<rt::Value, SchemaError> {
+    if let Some(Json::String(r)) = o.get("$ref") {
+        return Ok(rt::Value::Ref(r.as_str().into()))
+    }
+
+    match o.get("$type").and_then(Json::as_str) {
+        Some("idstring") => {
+            let hex = o.get("value").and_then(Json::as_str).ok_or(SchemaError::InvalidIdString)?;
+            let val = u64::from_str_radix(hex, 16).map_err(|_| SchemaError::InvalidIdString)?;
+            return Ok(rt::Value::Scalar(val.into()))
+        },
+        Some("vector") => {
+            let v = read_number_array(o).ok_or(SchemaError::InvalidVector)?;
+            if v.len() != 3 { return Err(SchemaError::InvalidVector) }
+            return Ok(rt::Value::Scalar(vek::Vec3::new(v[0], v[1], v[2]).into()))
+        },
+        Some("quaternion") => {
+            let v = read_number_array(o).ok_or(SchemaError::InvalidQuaternion)?;
+            if v.len() != 4 { return Err(SchemaError::InvalidQuaternion) }
+            return Ok(rt::Value::Scalar(vek::Quaternion::from_xyzw(v[0], v[1], v[2], v[3]).into()))
+        },
+        Some(ty) => return Err(SchemaError::BadType(Rc::from(ty))),
+        None => {}
+    }
+
+    let id = match o.get("$id") {
+        Some(Json::String(s)) => Some(Rc::from(s.as_str())),
+        _ => None
+    };
+    let meta = match o.get("$meta") {
+        Some(Json::String(s)) => Some(Rc::from(s.as_str())),
+        _ => None
+    };
+    Ok(rt::Value::Table(rt::TableHeader { id, meta }))
+}
+
+fn read_number_array(o: &Map<String, Json>) -> Option<Vec<f32>> {
+    o.get("value")?.as_array()?.iter().map(|v| v.as_f64().map(|f| f as f32)).collect()
+}
+
+fn load_table(json: &Json, mut reftree: rt::NodeMut) -> Result<(), SchemaError> {
+    match json {
+        Json::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                append_entry(&mut reftree, Key::Index(i), item)?;
+            }
+        },
+        Json::Object(o) => {
+            for (k, v) in o.iter() {
+                if k.starts_with('$') { continue }
+                let key = match k.parse::<usize>() {
+                    Ok(i) => Key::Index(i),
+                    Err(_) => Key::String(Rc::from(k.as_str()))
+                };
+                append_entry(&mut reftree, key, v)?;
+            }
+        },
+        _ => unreachable!("load_table only called for a table-valued node")
+    }
+    Ok(())
+}
+
+fn append_entry(reftree: &mut rt::NodeMut, key: OwnedKey, json: &Json) -> Result<(), SchemaError> {
+    let datum = load_value(json)?;
+    match datum {
+        rt::Value::Scalar(_) | rt::Value::Ref(_) => { reftree.append(rt::Data { key, value: datum, span: Span::default() }); },
+        rt::Value::Table(_) => {
+            let child = reftree.append(rt::Data { key, value: datum, span: Span::default() });
+            load_table(json, child)?
+        },
+        rt::Value::Unset(_) | rt::Value::Include(_) => unreachable!("load_value never returns a directive")
+    }
+    Ok(())
+}
+
+pub fn dump(doc: DocumentRef) -> String {
+    match rt::from_document(doc) {
+        None => String::from("null"),
+        Some(tree) => dump_entry(tree.root()).to_string()
+    }
+}
+
+fn dump_entry(node: rt::Node) -> Json {
+    match &node.value().value {
+        rt::Value::Scalar(s) => dump_scalar(s),
+        rt::Value::Ref(r) => {
+            let mut o = Map::new();
+            o.insert("$ref".into(), Json::String(r.to_string()));
+            Json::Object(o)
+        },
+        rt::Value::Table(t) => {
+            let is_array = t.id.is_none() && t.meta.is_none() && node.children().enumerate().all(|(i, c)| {
+                matches!(c.value().key, Key::Index(idx) if idx == i)
+            });
+
+            if is_array {
+                Json::Array(node.children().map(dump_entry).collect())
+            }
+            else {
+                let mut o = Map::new();
+                if let Some(id) = &t.id { o.insert("$id".into(), Json::String(id.to_string())); }
+                if let Some(meta) = &t.meta { o.insert("$meta".into(), Json::String(meta.to_string())); }
+                for c in node.children() {
+                    let key = match &c.value().key {
+                        Key::Index(i) => i.to_string(),
+                        Key::String(s) => s.to_string()
+                    };
+                    o.insert(key, dump_entry(c));
+                }
+                Json::Object(o)
+            }
+        },
+        rt::Value::Unset(_) | rt::Value::Include(_) => {
+            unreachable!("directives never appear in a resolved Document, only while loading one")
+        }
+    }
+}
+
+fn dump_scalar(val: &Scalar<Rc<str>>) -> Json {
+    match val {
+        Scalar::Bool(v) => Json::Bool(*v),
+        Scalar::Number(v) => serde_json::Number::from_f64(*v as f64).map(Json::Number).unwrap_or(Json::Null),
+        Scalar::Integer(v) => Json::Number((*v).into()),
+        Scalar::String(v) => Json::String(v.to_string()),
+        Scalar::IdString(v) => {
+            let mut o = Map::new();
+            o.insert("$type".into(), Json::String("idstring".into()));
+            o.insert("value".into(), Json::String(format!("{:016x}", v)));
+            Json::Object(o)
+        },
+        Scalar::Vector(v) => {
+            let mut o = Map::new();
+            o.insert("$type".into(), Json::String("vector".into()));
+            o.insert("value".into(), Json::Array(vec![(v.x as f64).into(), (v.y as f64).into(), (v.z as f64).into()]));
+            Json::Object(o)
+        },
+        Scalar::Quaternion(v) => {
+            let mut o = Map::new();
+            o.insert("$type".into(), Json::String("quaternion".into()));
+            o.insert("value".into(), Json::Array(vec![(v.x as f64).into(), (v.y as f64).into(), (v.z as f64).into(), (v.w as f64).into()]));
+            Json::Object(o)
+        }
+    }
+}
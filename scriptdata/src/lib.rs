@@ -1,5 +1,6 @@
 pub mod document;
 pub mod generic;
+pub mod generic_json;
 mod reference_tree;
 pub mod custom;
 pub mod lua_like;
@@ -34,11 +35,20 @@ impl From<&str> for Key<Rc<str>> {
         Key::String(src.into())
     }
 }
+impl<T: Borrow<str>> Key<T> {
+    pub fn as_borrowed(&self) -> Key<&str> {
+        match self {
+            Key::Index(i) => Key::Index(*i),
+            Key::String(s) => Key::String(s.borrow())
+        }
+    }
+}
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Scalar<S> {
     Bool(bool),
     Number(f32),
+    Integer(i64),
     IdString(u64),
     String(S),
     Vector(vek::Vec3<f32>),
@@ -46,6 +56,7 @@ pub enum Scalar<S> {
 }
 impl<S> From<bool> for Scalar<S> { fn from(s: bool) -> Scalar<S> { Scalar::Bool(s) } }
 impl<S> From<f32> for Scalar<S> { fn from(s: f32) -> Scalar<S> { Scalar::Number(s) } }
+impl<S> From<i64> for Scalar<S> { fn from(s: i64) -> Scalar<S> { Scalar::Integer(s) } }
 impl<S> From<u64> for Scalar<S> { fn from(s:u64) -> Scalar<S> { Scalar::IdString(s) } }
 impl<S> From<vek::Vec3<f32>> for Scalar<S> { fn from(s: vek::Vec3<f32>) -> Scalar<S> { Scalar::Vector(s) } }
 impl<S> From<vek::Quaternion<f32>> for Scalar<S> { fn from(s: vek::Quaternion<f32>) -> Scalar<S> { Scalar::Quaternion(s) } }
@@ -57,10 +68,11 @@ impl<S> Scalar<S> {
             String(s) => String(func(s)),
             Bool(i) => Bool(i),
             Number(i) => Number(i),
+            Integer(i) => Integer(i),
             IdString(i) => IdString(i),
             Vector(i) => Vector(i),
             Quaternion(i) => Quaternion(i),
-        } 
+        }
     }
 }
 impl<S: Borrow<str>> Scalar<S>{
@@ -70,10 +82,11 @@ impl<S: Borrow<str>> Scalar<S>{
             String(s) => String(s.borrow()),
             Bool(i) => Bool(*i),
             Number(i) => Number(*i),
+            Integer(i) => Integer(*i),
             IdString(i) => IdString(*i),
             Vector(i) => Vector(*i),
             Quaternion(i) => Quaternion(*i),
-        } 
+        }
     }
 }
 
@@ -155,8 +168,14 @@ pub enum SchemaError {
     #[error("Reference to {0:?} has children")]
     RefHasChildren(Rc<str>),
 
+    #[error("%include of {0:?} forms a cycle")]
+    IncludeCycle(Rc<str>),
+
     #[error("Syntax error: {0}")]
-    SyntaxError(Box<dyn std::error::Error>)
+    SyntaxError(Box<dyn std::error::Error>),
+
+    #[error("{pos}: {kind}")]
+    At { pos: SourcePos, kind: Box<SchemaError> }
 }
 impl<T> From<SchemaError> for Result<T, SchemaError> {
     fn from(src: SchemaError) -> Self {
@@ -169,6 +188,53 @@ impl From<DuplicateKey> for SchemaError {
     }
 }
 
+/// A 1-based line/column in some source text, attached to a [`SchemaError`]
+/// via [`SchemaError::At`] so a rejected document says *where* it's wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourcePos {
+    pub line: usize,
+    pub col: usize
+}
+impl std::fmt::Display for SourcePos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// Converts byte offsets into a source string into [`SourcePos`]es. Built
+/// once per document (it scans the whole input) so each lookup afterwards
+/// is an O(log n) binary search rather than a rescan.
+pub struct LineIndex {
+    newline_offsets: Vec<usize>
+}
+impl LineIndex {
+    pub fn new(src: &str) -> LineIndex {
+        let newline_offsets = src.char_indices().filter(|&(_, c)| c == '\n').map(|(i, _)| i).collect();
+        LineIndex { newline_offsets }
+    }
+
+    pub fn locate(&self, offset: usize) -> SourcePos {
+        let line = self.newline_offsets.partition_point(|&nl| nl < offset);
+        let col_start = if line == 0 { 0 } else { self.newline_offsets[line - 1] + 1 };
+        SourcePos { line: line + 1, col: offset - col_start + 1 }
+    }
+}
+
+/// A byte range into some source text, e.g. as produced while scanning a [`lua_like`] document.
+/// Kept as raw offsets everywhere a tree is built or walked, since most nodes are never involved
+/// in an error - only [`LineIndex::locate`] a [`Span`]'s `start` into a human-facing [`SourcePos`]
+/// once something actually needs to report where it went wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: u32,
+    pub end: u32
+}
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start: start as u32, end: end as u32 }
+    }
+}
+
 trait RoxmlNodeExt<'a> {
     fn assert_name(&self, name: &'static str) -> Result<(), SchemaError>;
     fn required_attribute(&self, name: &'static str)-> Result<&'a str, SchemaError>;
@@ -1,17 +1,15 @@
+use std::collections::HashMap;
 use std::rc::Rc;
 
-use pest::iterators::Pair;
-use pest::{Parser, iterators::Pairs, error::Error as PestError};
-use pest_derive::Parser;
 use thiserror::Error as ThisError;
 
-use crate::document::DocumentRef;
-use crate::{Key, Scalar, reference_tree as rt};
+use crate::document::{DocumentBuilder, DocumentRef};
+use crate::{Key, LineIndex, Scalar, SourcePos, Span, reference_tree as rt};
 
 #[derive(ThisError, Debug)]
 pub enum LuaLikeError {
     #[error("Error parsing Lua-like syntax: {0}")]
-    ParseError(#[from] PestError<Rule>),
+    ParseError(#[from] Reject),
 
     #[error("Malformed number: {0}")]
     BadNumber(#[from] std::num::ParseFloatError),
@@ -26,369 +24,744 @@ pub enum LuaLikeError {
     BadStringEncoding,
 
     #[error("Unknown function {0}")]
-    UnknownFunction(Rc<str>)
+    UnknownFunction(Rc<str>),
+
+    #[error("{0}")]
+    Malformed(Diagnostic)
 }
 impl Into<crate::SchemaError> for LuaLikeError {
     fn into(self) -> crate::SchemaError {
         use crate::SchemaError::*;
         match self {
             LuaLikeError::ParseError(e) => SyntaxError(Box::new(e)),
-            LuaLikeError::BadNumber(_) => todo!(),
-            LuaLikeError::BadInt(_) => todo!(),
-            LuaLikeError::BadEscape => todo!(),
-            LuaLikeError::BadStringEncoding => todo!(),
-            LuaLikeError::UnknownFunction(_) => todo!(),
+            LuaLikeError::BadNumber(e) => InvalidFloat(e),
+            LuaLikeError::BadInt(e) => InvalidInt(e),
+            LuaLikeError::BadEscape => BadValue(Rc::from("bad string escape")),
+            LuaLikeError::BadStringEncoding => BadValue(Rc::from("string escape produced invalid UTF-8")),
+            LuaLikeError::UnknownFunction(name) => BadType(name),
+            LuaLikeError::Malformed(d) => At { pos: d.pos, kind: Box::new(BadValue(Rc::from(d.message.as_str()))) },
         }
     }
 }
 
+/// One recoverable problem found while walking the parse tree: unlike the hard failures
+/// [`LuaLikeError`] otherwise reports, a [`Diagnostic`] doesn't stop [`load_resilient`] from
+/// finishing the rest of the document - the offending value becomes an [`rt::Value::Error`]
+/// placeholder instead, so one malformed entry doesn't cost you the whole file.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub pos: SourcePos,
+    pub message: String
+}
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.pos, self.message)
+    }
+}
 
-#[derive(Parser)]
-#[grammar = "lua_like.pest"]
-struct LualikeParser();
+/// A handler registered under some function name in a [`FunctionRegistry`], given read access to
+/// the already-built argument table (its children are the call's positional/keyed arguments, same
+/// as any other table) so it can validate arity and coerce them into a first-class value.
+pub type ConstructorFn = dyn Fn(rt::Node) -> Result<rt::Value, String>;
 
-pub fn get_parse(input: &str) -> Result<Pairs<Rule>, PestError<Rule>> {
-    LualikeParser::parse(Rule::document, input)
+/// Maps the name in a `Name{...}` or `meta("Name") {...}` construct to a handler that lowers its
+/// argument table straight to a value - e.g. so a caller can register `Vector3`/`Quaternion`/
+/// `Idstring` and have `Vector3{1,2,3}` parse to a `Scalar::Vector` instead of a generic
+/// `Value::Table` named `"Vector3"`. A name with no registered handler still becomes that plain
+/// table, unless [`FunctionRegistry::set_strict`] is on, in which case it's reported the same way
+/// [`LuaLikeError::UnknownFunction`] would be.
+#[derive(Default)]
+pub struct FunctionRegistry {
+    handlers: HashMap<Rc<str>, Box<ConstructorFn>>,
+    strict: bool
+}
+impl FunctionRegistry {
+    pub fn new() -> FunctionRegistry {
+        FunctionRegistry::default()
+    }
+
+    /// Registers `handler` under `name`. Registering the same name twice replaces the earlier handler.
+    pub fn register(&mut self, name: impl Into<Rc<str>>, handler: impl Fn(rt::Node) -> Result<rt::Value, String> + 'static) {
+        self.handlers.insert(name.into(), Box::new(handler));
+    }
+
+    /// When set, a `Name{...}`/`meta("Name") {...}` whose name has no registered handler is rejected
+    /// instead of falling back to a plain table named after it.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    fn get(&self, name: &str) -> Option<&ConstructorFn> {
+        self.handlers.get(name).map(|h| h.as_ref())
+    }
+}
+
+/// A syntax error from the hand-written lexer/parser below, anchored to the byte offset where the
+/// expectation that failed started looking. Recoverable per-entry failures (inside a table body)
+/// never escape as one of these - they're folded into a [`Diagnostic`] and the scan resumes after
+/// the next `,`/`;`/`}`. A [`Reject`] only surfaces for a document that can't be parsed at all.
+#[derive(Debug, Clone)]
+pub struct Reject {
+    pub pos: u32,
+    pub message: String
+}
+impl Reject {
+    fn new(pos: u32, message: impl Into<String>) -> Reject {
+        Reject { pos, message: message.into() }
+    }
+}
+impl std::fmt::Display for Reject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "byte {}: {}", self.pos, self.message)
+    }
+}
+impl std::error::Error for Reject {}
+
+/// A position in some source text, carrying only what the scanners below need: the remaining
+/// text and how far into the document it starts. Cheap to copy, so every scanner takes one by
+/// value and returns the cursor past whatever it consumed, the same shape as `proc-macro2`'s
+/// `parse::Cursor`.
+#[derive(Debug, Clone, Copy)]
+struct Cursor<'a> {
+    rest: &'a str,
+    off: u32
+}
+impl<'a> Cursor<'a> {
+    fn new(src: &'a str) -> Cursor<'a> {
+        Cursor { rest: src, off: 0 }
+    }
+
+    fn advance(self, bytes: usize) -> Cursor<'a> {
+        Cursor { rest: &self.rest[bytes..], off: self.off + bytes as u32 }
+    }
+
+    fn first(self) -> Option<char> {
+        self.rest.chars().next()
+    }
+
+    fn starts_with(self, s: &str) -> bool {
+        self.rest.starts_with(s)
+    }
+
+    /// Parses a fixed token, failing with a [`Reject`] naming it if it isn't next.
+    fn parse(self, tag: &str) -> PResult<'a, ()> {
+        if self.rest.starts_with(tag) { Ok((self.advance(tag.len()), ())) }
+        else { Err(Reject::new(self.off, format!("expected {:?}", tag))) }
+    }
+
+    fn take_while(self, mut pred: impl FnMut(char) -> bool) -> (Cursor<'a>, &'a str) {
+        let end = self.rest.find(|c| !pred(c)).unwrap_or(self.rest.len());
+        (self.advance(end), &self.rest[..end])
+    }
+
+    /// The span from `start` (which must be an earlier cursor over the same source) to here.
+    fn span_since(self, start: Cursor<'a>) -> Span {
+        Span::new(start.off as usize, self.off as usize)
+    }
+}
+
+type PResult<'a, O> = Result<(Cursor<'a>, O), Reject>;
+
+fn is_ident_start(c: char) -> bool { c.is_ascii_alphabetic() || c == '_' }
+fn is_ident_continue(c: char) -> bool { c.is_ascii_alphanumeric() || c == '_' }
+
+fn skip_trivia(mut c: Cursor) -> Cursor {
+    loop {
+        let (nc, _) = c.take_while(|ch| ch.is_ascii_whitespace());
+        c = nc;
+        if c.starts_with("--") {
+            let (nc, _) = c.take_while(|ch| ch != '\n');
+            c = nc;
+            continue;
+        }
+        break;
+    }
+    c
+}
+
+fn lex_ident(c: Cursor) -> PResult<'_, &str> {
+    match c.first() {
+        Some(ch) if is_ident_start(ch) => Ok(c.take_while(is_ident_continue)),
+        _ => Err(Reject::new(c.off, "expected an identifier"))
+    }
+}
+
+/// Scans a Lua numeral: an optional sign, then either a `0x`/`0b`/`0o` non-decimal integer (hex
+/// additionally allowing a `.frac` and `pP exp`) or a decimal integer/float with an optional
+/// `.frac`/`eE exp`. Returns the raw token text for [`classify_number`] to interpret.
+fn lex_number(c: Cursor) -> PResult<'_, &str> {
+    let start = c;
+    let mut cur = c;
+    if matches!(cur.first(), Some('+') | Some('-')) {
+        cur = cur.advance(1);
+    }
+    if !matches!(cur.first(), Some(ch) if ch.is_ascii_digit()) {
+        return Err(Reject::new(start.off, "expected a number"));
+    }
+
+    if cur.starts_with("0x") || cur.starts_with("0X") {
+        cur = cur.advance(2);
+        cur = cur.take_while(|c| c.is_ascii_hexdigit()).0;
+        if cur.first() == Some('.') {
+            cur = cur.advance(1);
+            cur = cur.take_while(|c| c.is_ascii_hexdigit()).0;
+        }
+        if matches!(cur.first(), Some('p') | Some('P')) {
+            cur = cur.advance(1);
+            if matches!(cur.first(), Some('+') | Some('-')) { cur = cur.advance(1); }
+            cur = cur.take_while(|c| c.is_ascii_digit()).0;
+        }
+    }
+    else if cur.starts_with("0b") || cur.starts_with("0B") || cur.starts_with("0o") || cur.starts_with("0O") {
+        cur = cur.advance(2);
+        cur = cur.take_while(|c| c.is_ascii_alphanumeric()).0;
+    }
+    else {
+        cur = cur.take_while(|c| c.is_ascii_digit()).0;
+        if cur.first() == Some('.') {
+            cur = cur.advance(1);
+            cur = cur.take_while(|c| c.is_ascii_digit()).0;
+        }
+        if matches!(cur.first(), Some('e') | Some('E')) {
+            cur = cur.advance(1);
+            if matches!(cur.first(), Some('+') | Some('-')) { cur = cur.advance(1); }
+            cur = cur.take_while(|c| c.is_ascii_digit()).0;
+        }
+    }
+
+    let len = (cur.off - start.off) as usize;
+    Ok((cur, &start.rest[..len]))
+}
+
+/// Scans a `"..."`/`'...'` literal up to and including its closing quote, without decoding
+/// escapes - just enough to find the extent, treating any `\` as escaping the one char after it
+/// so an escaped quote doesn't end the literal early. [`decode_short_string`] does the rest.
+fn scan_short_string(c: Cursor) -> PResult<'_, &str> {
+    let start = c;
+    let quote = match c.first() {
+        Some(q @ ('"' | '\'')) => q,
+        _ => return Err(Reject::new(c.off, "expected a string"))
+    };
+    let mut cur = c.advance(quote.len_utf8());
+    loop {
+        match cur.first() {
+            None => return Err(Reject::new(start.off, "unterminated string")),
+            Some(ch) if ch == quote => {
+                cur = cur.advance(ch.len_utf8());
+                break;
+            },
+            Some('\\') => {
+                cur = cur.advance(1);
+                match cur.first() {
+                    None => return Err(Reject::new(start.off, "unterminated string")),
+                    Some(e) => cur = cur.advance(e.len_utf8())
+                }
+            },
+            Some(ch) => cur = cur.advance(ch.len_utf8())
+        }
+    }
+    let len = (cur.off - start.off) as usize;
+    Ok((cur, &start.rest[..len]))
+}
+
+/// Decodes the body of a literal [`scan_short_string`] found: `\a\b\f\n\r\t\v\\\"\'`, `\xHH`,
+/// `\ddd` (1-3 decimal digits), and `\u{HHHH}`.
+fn decode_short_string(raw: &str) -> Result<Rc<str>, LuaLikeError> {
+    let body = &raw[1..raw.len() - 1];
+    let mut buf = Vec::<u8>::new();
+    let mut chars = body.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            let mut b = [0u8; 4];
+            buf.extend_from_slice(ch.encode_utf8(&mut b).as_bytes());
+            continue;
+        }
+        let esc = chars.next().expect("scan_short_string guarantees an escape char follows a backslash");
+        match esc {
+            'a' => buf.push(0x07),
+            'b' => buf.push(0x08),
+            'f' => buf.push(0x0C),
+            'n' => buf.push(0x0A),
+            'r' => buf.push(0x0D),
+            't' => buf.push(0x09),
+            'v' => buf.push(0x0B),
+            '\\' => buf.push(0x5C),
+            '"' => buf.push(0x22),
+            '\'' => buf.push(0x27),
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let hv = u8::from_str_radix(&hex, 16).map_err(|_| LuaLikeError::BadEscape)?;
+                buf.push(hv);
+            },
+            'u' => {
+                if chars.next() != Some('{') { return Err(LuaLikeError::BadEscape); }
+                let mut hex = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => hex.push(c),
+                        None => return Err(LuaLikeError::BadEscape)
+                    }
+                }
+                let cv = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32).ok_or(LuaLikeError::BadEscape)?;
+                let mut b = [0u8; 4];
+                buf.extend_from_slice(cv.encode_utf8(&mut b).as_bytes());
+            },
+            d if d.is_ascii_digit() => {
+                let mut dec = String::new();
+                dec.push(d);
+                while dec.len() < 3 {
+                    match chars.peek() {
+                        Some(c) if c.is_ascii_digit() => { dec.push(*c); chars.next(); },
+                        _ => break
+                    }
+                }
+                let dv: u16 = dec.parse().map_err(|_| LuaLikeError::BadEscape)?;
+                if dv > 255 { return Err(LuaLikeError::BadEscape); }
+                buf.push(dv as u8);
+            },
+            _ => return Err(LuaLikeError::BadEscape)
+        }
+    }
+    String::from_utf8(buf).map(Rc::from).map_err(|_| LuaLikeError::BadStringEncoding)
+}
+
+/// Scans a long bracket string `[=*[ ... ]=*]`, returning just its content (a leading newline
+/// right after the opening bracket is dropped, same as Lua itself does).
+fn scan_long_string(c: Cursor) -> PResult<'_, &str> {
+    let start = c;
+    if c.first() != Some('[') {
+        return Err(Reject::new(c.off, "expected a long string"));
+    }
+    let (after_eqs, eqs) = c.advance(1).take_while(|ch| ch == '=');
+    if after_eqs.first() != Some('[') {
+        return Err(Reject::new(start.off, "malformed long string opening"));
+    }
+    let mut content_start = after_eqs.advance(1);
+    if content_start.first() == Some('\n') {
+        content_start = content_start.advance(1);
+    }
+    let closing = format!("]{}]", "=".repeat(eqs.len()));
+    match content_start.rest.find(&closing) {
+        Some(idx) => Ok((content_start.advance(idx + closing.len()), &content_start.rest[..idx])),
+        None => Err(Reject::new(start.off, "unterminated long string"))
+    }
+}
+
+/// Parses a quoted or long-bracket string used as a function argument (`id(...)`/`meta(...)`/
+/// `ref(...)`). A bad escape doesn't abort the parse - like any other malformed value it becomes
+/// a diagnostic, and the caller treats the argument as absent.
+fn parse_call_string_arg<'a>(c: Cursor<'a>, lines: &LineIndex, diagnostics: &mut Vec<Diagnostic>) -> PResult<'a, Option<Rc<str>>> {
+    let start = c;
+    match c.first() {
+        Some('"') | Some('\'') => {
+            let (nc, raw) = scan_short_string(c)?;
+            match decode_short_string(raw) {
+                Ok(s) => Ok((nc, Some(s))),
+                Err(e) => { malformed_at(nc.span_since(start), raw, lines, diagnostics, e); Ok((nc, None)) }
+            }
+        },
+        Some('[') => {
+            let (nc, text) = scan_long_string(c)?;
+            Ok((nc, Some(Rc::from(text))))
+        },
+        _ => Err(Reject::new(c.off, "expected a string"))
+    }
+}
+
+/// Recognises the `0x`/`0X`, `0b`/`0B`, `0o`/`0O` prefixes Lua numerals use for non-decimal
+/// integers, returning the matching radix and the digits (mantissa, for hex) after it.
+fn radix_prefix(text: &str) -> Option<(u32, &str)> {
+    if let Some(h) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) { return Some((16, h)); }
+    if let Some(b) = text.strip_prefix("0b").or_else(|| text.strip_prefix("0B")) { return Some((2, b)); }
+    if let Some(o) = text.strip_prefix("0o").or_else(|| text.strip_prefix("0O")) { return Some((8, o)); }
+    None
+}
+
+/// Classifies a numeral token the way Lua itself does: plain decimal is an integer unless it has
+/// a `.` or exponent, `0x`/`0b`/`0o` are non-decimal integers, and hex additionally allows a
+/// `[.frac][pP exp]` float form computed as `mantissa * 2^exp`. A decimal integer that overflows
+/// `i64` falls back to `f32`, same as the plain float path.
+fn classify_number(text: &str) -> Result<Scalar<Rc<str>>, LuaLikeError> {
+    let (sign, rest) = match text.as_bytes().first() {
+        Some(b'-') => ("-", &text[1..]),
+        Some(b'+') => ("+", &text[1..]),
+        _ => ("", text)
+    };
+
+    if let Some((radix, digits)) = radix_prefix(rest) {
+        if radix == 16 && (digits.contains('.') || digits.contains('p') || digits.contains('P')) {
+            return parse_hex_float(sign, digits);
+        }
+        let combined = format!("{}{}", sign, digits);
+        return Ok(Scalar::Integer(i64::from_str_radix(&combined, radix)?));
+    }
+
+    if rest.contains('.') || rest.contains('e') || rest.contains('E') {
+        return Ok(Scalar::Number(text.parse::<f32>()?));
+    }
+
+    match text.parse::<i64>() {
+        Ok(i) => Ok(Scalar::Integer(i)),
+        Err(_) => Ok(Scalar::Number(text.parse::<f32>()?))
+    }
+}
+
+fn parse_hex_float(sign: &str, hex: &str) -> Result<Scalar<Rc<str>>, LuaLikeError> {
+    let (mantissa, exp) = match hex.find(|c| c == 'p' || c == 'P') {
+        Some(idx) => (&hex[..idx], hex[(idx + 1)..].parse::<i32>()?),
+        None => (hex, 0)
+    };
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let int_val = if int_part.is_empty() { 0 } else { u64::from_str_radix(int_part, 16)? };
+    let frac_val = if frac_part.is_empty() { 0.0 } else {
+        u64::from_str_radix(frac_part, 16)? as f64 / 16f64.powi(frac_part.len() as i32)
+    };
+    let magnitude = (int_val as f64 + frac_val) * 2f64.powi(exp);
+    Ok(Scalar::Number((if sign == "-" { -magnitude } else { magnitude }) as f32))
+}
+
+/// Records `message` against `span` and returns an [`rt::Value::Error`] placeholder (carrying
+/// `text`, the source snippet the problem came from) to use in its place, so the caller can carry
+/// on with the rest of the document instead of aborting.
+fn malformed_at(span: Span, text: &str, lines: &LineIndex, diagnostics: &mut Vec<Diagnostic>, message: impl std::fmt::Display) -> rt::Value {
+    diagnostics.push(Diagnostic {
+        pos: lines.locate(span.start as usize),
+        message: message.to_string()
+    });
+    rt::Value::Error(Rc::from(text))
 }
 
 pub fn load(input: &str) -> Result<DocumentRef, LuaLikeError> {
-    let mut tree = get_parse(input)?;
-    
-    let p_doc = tree.next().unwrap();
-    let v_doc = p_doc.into_inner().next().unwrap();
+    load_with(input, &FunctionRegistry::default())
+}
 
+/// As [`load`], but dispatches `Name{...}`/`meta("Name") {...}` constructs through `registry` first.
+pub fn load_with(input: &str, registry: &FunctionRegistry) -> Result<DocumentRef, LuaLikeError> {
+    let lines = LineIndex::new(input);
+    let mut diagnostics = Vec::new();
     let mut tree = rt::empty_tree();
-    parse_value_data(v_doc, Key::Index(0), &mut tree.root_mut())?;
-    
-    rt::to_document(tree.root().first_child().unwrap()); todo!()
-}
-
-fn parse_value_data(pair: Pair<Rule>, key: Key<Rc<str>>, parent: &mut rt::NodeMut) -> Result<(), LuaLikeError> {
-    match pair.as_rule() {
-        Rule::number => {
-            let num: f32 = pair.as_str().parse()?;
-            parent.append(rt::Data {
-                key,
-                value: rt::Value::Scalar(num.into())
-            });
-        },
-        Rule::bool => {
-            let b = match pair.as_str() {
-                "true" => true,
-                "false" => false,
-                _ => panic!("Unaccounted for boolean literal")
+
+    let c = skip_trivia(Cursor::new(input));
+    let (c, ()) = parse_value(c, Key::Index(0), &mut tree.root_mut(), &lines, &mut diagnostics, registry)?;
+    let c = skip_trivia(c);
+    if !c.rest.is_empty() {
+        return Err(LuaLikeError::ParseError(Reject::new(c.off, "trailing garbage after the document's value")));
+    }
+
+    if let Some(d) = diagnostics.into_iter().next() {
+        return Err(LuaLikeError::Malformed(d));
+    }
+
+    rt::to_document(tree.root().first_child().unwrap())
+        .map_err(|e| LuaLikeError::Malformed(Diagnostic { pos: SourcePos { line: 1, col: 1 }, message: e.to_string() }))
+}
+
+/// As [`load`], but doesn't give up at the first malformed value: every problem [`parse_value`]/
+/// [`fill_table`] hits inside a table is recorded as a [`Diagnostic`] and replaced with an
+/// [`rt::Value::Error`] placeholder instead of aborting, so a document with one bad entry still
+/// loads everything else. A syntax error the parser can't recover from at all (there's no tree to
+/// walk) still yields just that one diagnostic, alongside an empty document.
+pub fn load_resilient(input: &str) -> (DocumentRef, Vec<Diagnostic>) {
+    load_resilient_with(input, &FunctionRegistry::default())
+}
+
+/// As [`load_resilient`], but dispatches `Name{...}`/`meta("Name") {...}` constructs through `registry` first.
+pub fn load_resilient_with(input: &str, registry: &FunctionRegistry) -> (DocumentRef, Vec<Diagnostic>) {
+    let lines = LineIndex::new(input);
+    let mut diagnostics = Vec::new();
+    let mut tree = rt::empty_tree();
+
+    let c = skip_trivia(Cursor::new(input));
+    if let Err(e) = parse_value(c, Key::Index(0), &mut tree.root_mut(), &lines, &mut diagnostics, registry) {
+        diagnostics.push(Diagnostic { pos: lines.locate(e.pos as usize), message: e.message });
+    }
+
+    let doc = tree.root().first_child()
+        .and_then(|root| match rt::to_document(root) {
+            Ok(doc) => Some(doc),
+            Err(e) => {
+                diagnostics.push(Diagnostic { pos: SourcePos { line: 1, col: 1 }, message: e.to_string() });
+                None
+            }
+        })
+        .unwrap_or_else(|| DocumentBuilder::new().empty_document());
+
+    (doc, diagnostics)
+}
+
+/// Parses one value - a number, bool, string, table, or `id`/`meta`/`ref` call - appending it to
+/// `parent` under `key`. A structural problem (nothing recognisable at all) is a hard [`Reject`];
+/// everything recoverable (a bad number, a bad escape, an unknown `meta` constructor) is folded
+/// into a [`Diagnostic`] and replaced with an [`rt::Value::Error`] placeholder instead.
+fn parse_value<'a>(c: Cursor<'a>, key: Key<Rc<str>>, parent: &mut rt::NodeMut, lines: &LineIndex, diagnostics: &mut Vec<Diagnostic>, registry: &FunctionRegistry) -> PResult<'a, ()> {
+    let c = skip_trivia(c);
+    let start = c;
+
+    match c.first() {
+        None => Err(Reject::new(c.off, "unexpected end of input, expected a value")),
+
+        Some('"') | Some('\'') => {
+            let (c, raw) = scan_short_string(c)?;
+            let span = c.span_since(start);
+            let value = match decode_short_string(raw) {
+                Ok(s) => rt::Value::Scalar(Scalar::String(s)),
+                Err(e) => malformed_at(span, raw, lines, diagnostics, e)
             };
-            parent.append(rt::Data {
-                key,
-                value: rt::Value::Scalar(b.into())
-            });
-        }
-        Rule::long_string => {
-            let st = Rc::from(pair.as_str());
-            parent.append(rt::Data {
-                key,
-                value: Scalar::String(st).into()
-            });
+            parent.append(rt::Data { key, value, span });
+            Ok((c, ()))
         },
-        Rule::short_string => {
-            let st = parse_short_string(pair)?;
-            parent.append(rt::Data {
-                key,
-                value: Scalar::String(st).into()
-            });
+
+        Some('[') => {
+            let (c, text) = scan_long_string(c)?;
+            let span = c.span_since(start);
+            parent.append(rt::Data { key, value: rt::Value::Scalar(Scalar::String(Rc::from(text))), span });
+            Ok((c, ()))
         },
-        Rule::table => {
-            fill_table(pair, parent, key, None, None)?;
+
+        Some('{') => fill_table(c, parent, key, None, None, start, lines, diagnostics, registry),
+
+        Some(ch) if ch.is_ascii_digit() || ch == '-' || ch == '+' => {
+            let (c, text) = lex_number(c)?;
+            let span = c.span_since(start);
+            let value = match classify_number(text) {
+                Ok(num) => rt::Value::Scalar(num),
+                Err(e) => malformed_at(span, text, lines, diagnostics, e)
+            };
+            parent.append(rt::Data { key, value, span });
+            Ok((c, ()))
         },
-        Rule::meta_table => {
-            let mut items = pair.into_inner();
-            let meta = items.next().unwrap().as_str();
-            let table = items.next().unwrap();
-            
-            fill_table(table, parent, key, None, Some(Rc::from(meta)))?;
+
+        Some(ch) if is_ident_start(ch) => {
+            let (c, ident) = lex_ident(c)?;
+            match ident {
+                "true" | "false" => {
+                    let span = c.span_since(start);
+                    parent.append(rt::Data { key, value: rt::Value::Scalar((ident == "true").into()), span });
+                    Ok((c, ()))
+                },
+                "id" => parse_call_id(skip_trivia(c), key, parent, start, lines, diagnostics, registry),
+                "meta" => parse_call_meta(skip_trivia(c), key, parent, start, lines, diagnostics, registry),
+                "ref" => parse_call_ref(skip_trivia(c), key, parent, start, lines, diagnostics),
+                _ => fill_table(skip_trivia(c), parent, key, None, Some(Rc::from(ident)), start, lines, diagnostics, registry)
+            }
         },
-        Rule::call_meta => {
-            let mut items = pair.into_inner();
-            let meta = parse_string(items.next().unwrap())?;
-            let table = items.next().unwrap();
-            
-            fill_table(table, parent, key, None, Some(meta))?;
+
+        Some(ch) => Err(Reject::new(c.off, format!("unexpected character {:?}", ch)))
+    }
+}
+
+fn parse_call_id<'a>(c: Cursor<'a>, key: Key<Rc<str>>, parent: &mut rt::NodeMut, construct_start: Cursor<'a>, lines: &LineIndex, diagnostics: &mut Vec<Diagnostic>, registry: &FunctionRegistry) -> PResult<'a, ()> {
+    let (c, _) = c.parse("(")?;
+    let c = skip_trivia(c);
+    let (c, id) = parse_call_string_arg(c, lines, diagnostics)?;
+    let c = skip_trivia(c);
+    let (c, _) = c.parse(")")?;
+    let c = skip_trivia(c);
+
+    match c.first() {
+        Some(ch) if is_ident_start(ch) => {
+            let (after_ident, ident) = lex_ident(c)?;
+            if ident == "meta" {
+                let (c, _) = skip_trivia(after_ident).parse("(")?;
+                let c = skip_trivia(c);
+                let (c, meta) = parse_call_string_arg(c, lines, diagnostics)?;
+                let c = skip_trivia(c);
+                let (c, _) = c.parse(")")?;
+                fill_table(skip_trivia(c), parent, key, id, meta, construct_start, lines, diagnostics, registry)
+            }
+            else {
+                fill_table(skip_trivia(after_ident), parent, key, id, Some(Rc::from(ident)), construct_start, lines, diagnostics, registry)
+            }
         },
-        Rule::call_id => {
-            let mut items = pair.into_inner();
-            let id = Some(parse_string(items.next().unwrap())?);
-            let table = items.next().unwrap();
-
-            let (meta, table_body) = match table.as_rule() {
-                Rule::table => (None, table),
-                Rule::meta_table => {
-                    let mut ii = table.into_inner();
-                    let m = Rc::from(ii.next().unwrap().as_str());
-                    let t = ii.next().unwrap();
-                    (Some(m), t)
+        Some('{') => fill_table(c, parent, key, id, None, construct_start, lines, diagnostics, registry),
+        _ => Err(Reject::new(c.off, "expected a table after id(...)"))
+    }
+}
+
+fn parse_call_meta<'a>(c: Cursor<'a>, key: Key<Rc<str>>, parent: &mut rt::NodeMut, construct_start: Cursor<'a>, lines: &LineIndex, diagnostics: &mut Vec<Diagnostic>, registry: &FunctionRegistry) -> PResult<'a, ()> {
+    let (c, _) = c.parse("(")?;
+    let c = skip_trivia(c);
+    let (c, meta) = parse_call_string_arg(c, lines, diagnostics)?;
+    let c = skip_trivia(c);
+    let (c, _) = c.parse(")")?;
+    fill_table(skip_trivia(c), parent, key, None, meta, construct_start, lines, diagnostics, registry)
+}
+
+fn parse_call_ref<'a>(c: Cursor<'a>, key: Key<Rc<str>>, parent: &mut rt::NodeMut, construct_start: Cursor<'a>, lines: &LineIndex, diagnostics: &mut Vec<Diagnostic>) -> PResult<'a, ()> {
+    let (c, _) = c.parse("(")?;
+    let c = skip_trivia(c);
+    let (c, r) = parse_call_string_arg(c, lines, diagnostics)?;
+    let c = skip_trivia(c);
+    let (c, _) = c.parse(")")?;
+    let span = c.span_since(construct_start);
+    let value = match r {
+        Some(ident) => rt::Value::Ref(ident),
+        None => rt::Value::Error(Rc::from("ref(...)"))
+    };
+    parent.append(rt::Data { key, value, span });
+    Ok((c, ()))
+}
+
+/// Builds the table `{...}` a `Name{...}`/`id(...)`/`meta(...)` construct was given. When it's a
+/// bare `Name{...}`/`meta(...) {...}` (no `id(...)` of its own - one can't collapse an identified
+/// table into a scalar and still have `ref(...)`s resolve to it) and `Name` is registered in
+/// `registry`, the argument table is built into a scratch tree instead of `parent_node` and
+/// handed to the constructor, which replaces the whole thing with whatever value it returns.
+/// Anything else - an unregistered name, or `registry`'s [`FunctionRegistry::set_strict`]
+/// rejecting it - keeps the existing behaviour of a plain table named after it.
+fn fill_table<'a>(c: Cursor<'a>, parent_node: &mut rt::NodeMut, key: Key<Rc<str>>, id: Option<Rc<str>>, meta: Option<Rc<str>>, construct_start: Cursor<'a>, lines: &LineIndex, diagnostics: &mut Vec<Diagnostic>, registry: &FunctionRegistry) -> PResult<'a, ()> {
+    let (c, _) = c.parse("{")?;
+
+    if id.is_none() {
+        if let Some(name) = &meta {
+            match registry.get(name) {
+                Some(handler) => {
+                    let mut scratch = rt::empty_tree();
+                    let (c, _) = fill_table_entries(c, &mut scratch.root_mut(), lines, diagnostics, registry)?;
+                    let span = c.span_since(construct_start);
+                    let value = match handler(scratch.root()) {
+                        Ok(v) => v,
+                        Err(msg) => malformed_at(span, name, lines, diagnostics, msg)
+                    };
+                    parent_node.append(rt::Data { key, value, span });
+                    return Ok((c, ()));
                 },
-                Rule::call_meta => {
-                    let mut ii = table.into_inner();
-                    let m = parse_string(ii.next().unwrap())?;
-                    let t = ii.next().unwrap();
-                    (Some(m), t)
+                None if registry.strict => {
+                    let mut scratch = rt::empty_tree();
+                    let (c, _) = fill_table_entries(c, &mut scratch.root_mut(), lines, diagnostics, registry)?;
+                    let span = c.span_since(construct_start);
+                    let value = malformed_at(span, name, lines, diagnostics, format!("Unknown function {:?}", name));
+                    parent_node.append(rt::Data { key, value, span });
+                    return Ok((c, ()));
                 },
-                _ => unreachable!("Unexpected variation between the grammmar of `call_id` and its handling")
-            };
-            
-            fill_table(table_body, parent, key, id, meta)?;
-        },
-        Rule::call_ref => {
-            let mut items = pair.into_inner();
-            let ident = parse_string(items.next().unwrap())?;
-            parent.append(rt::Data {
-                key,
-                value: rt::Value::Ref(ident.into())
-            });
+                None => {}
+            }
         }
-        _ => panic!("Unexpected variation between the grammmar of `value` and its handling")
     }
-    Ok(())
-}
 
-fn fill_table(table_body: Pair<Rule>, parent_node: &mut rt::NodeMut, key: Key<Rc<str>>, id: Option<Rc<str>>, meta: Option<Rc<str>>) -> Result<(), LuaLikeError> {
     let mut table_node = parent_node.append(rt::Data {
         key,
-        value: rt::Value::Table(rt::TableHeader {
-            id, meta
-        })
+        value: rt::Value::Table(rt::TableHeader { id, meta }),
+        span: Span::default()
     });
-    
-    let mut implicit_index = 0;
-    for p in table_body.into_inner() {
-        let rule = p.as_rule();
-        let mut k = p.into_inner();
-        let key = match rule {
-            Rule::ident_keyed => {
-                let id = k.next().unwrap();
-                Key::String(Rc::from(id.as_str()))
-            },
-            Rule::value_keyed => {
-                let id = k.next().unwrap();
-                value_key(id)?
-            },
-            Rule::value => {
-                implicit_index += 1;
-                Key::Index(implicit_index)
-            },
-            _ => panic!("Grammar of `table` changed without updating tree builder!")
-        };
-        let val = k.next().unwrap();
-        let val_data = val.into_inner().next().unwrap();
-        parse_value_data(val_data, key, &mut table_node)?;
-    }
-    Ok(())
-}
-
-fn value_key(pair: Pair<Rule>) -> Result<Key<Rc<str>>, LuaLikeError> {
-    let r = match pair.as_rule() {
-        Rule::long_string => Key::String(Rc::from(pair.as_str())),
-        Rule::short_string => Key::String(parse_short_string(pair)?),
-        Rule::integer => {
-            let num: usize = pair.as_str().parse()?;
-            Key::Index(num)
-        }
-        _ => panic!("Grammar of `value_keyed` changed without updating tree builder!")
-    };
-    Ok(r)
+    let (c, _) = fill_table_entries(c, &mut table_node, lines, diagnostics, registry)?;
+    table_node.value().span = c.span_since(construct_start);
+    Ok((c, ()))
 }
 
-fn parse_string(pair: Pair<Rule>) -> Result<Rc<str>, LuaLikeError> {
-    match pair.as_rule() {
-        Rule::long_string => Ok(Rc::from(pair.as_str())),
-        Rule::short_string => parse_short_string(pair),
-        _ => unreachable!("Grammar changed to allow a non-string where previously only strings existed")
-    }
-}
+/// Parses entries up to and including the closing `}`. A single malformed entry doesn't abort the
+/// table: [`recover_to_next_entry`] skips forward to the next `,`/`;`/`}` at the same nesting
+/// depth and parsing continues from there, same as `formats::scriptdata::lua_like`'s recovery.
+fn fill_table_entries<'a>(mut c: Cursor<'a>, table_node: &mut rt::NodeMut, lines: &LineIndex, diagnostics: &mut Vec<Diagnostic>, registry: &FunctionRegistry) -> PResult<'a, ()> {
+    let mut implicit_index = 0;
+    loop {
+        c = skip_trivia(c);
+        match c.first() {
+            Some('}') => return Ok((c.advance(1), ())),
+            None => return Err(Reject::new(c.off, "unterminated table: ran out of input before a closing '}'")),
+            _ => {}
+        }
 
-fn parse_short_string(pair: Pair<Rule>)-> Result<Rc<str>, LuaLikeError> {
-    let mut buf = Vec::<u8>::new();
-    for chunk in pair.into_inner() {
-        match chunk.as_rule() {
-            Rule::short_string_plain => buf.extend_from_slice(chunk.as_str().as_bytes()),
-            Rule::string_esc_c => buf.push(match chunk.as_str() {
-                "a" => 0x07,
-                "b" => 0x08,
-                "f" => 0x0C,
-                "n" => 0x0A,
-                "r" => 0x0D,
-                "t" => 0x09,
-                "v" => 0x0B,
-                "\\" => 0x5C,
-                "\"" => 0x22,
-                "\'" => 0x27,
-                _ => panic!("Somehow missed a C-like escape!")
-            }),
-            Rule::string_esc_hex => {
-                let hex = &chunk.as_str()[1..];
-                let hv = u8::from_str_radix(hex, 16).unwrap();
-                buf.push(hv);
-            },
-            Rule::string_esc_dec => {
-                let dec = chunk.as_str();
-                let dv = u16::from_str_radix(dec, 10).unwrap();
-                if dv > 255 { return Err(LuaLikeError::BadEscape) }
-                buf.push(dv as u8);
-            },
-            Rule::string_esc_unicode => {
-                let us = &chunk.as_str()[2..];
-                let us = &us[..(us.len() - 1)];
-                let cv = u32::from_str_radix(us, 16)
-                    .ok()
-                    .and_then(char::from_u32);
-                match cv {
-                    Some(c) => {
-                        let mut b = [0u8; 4];
-                        c.encode_utf8(&mut b);
-                        buf.extend_from_slice(&b);
-                    },
-                    None => return Err(LuaLikeError::BadEscape)
+        let entry_start = c;
+        match parse_table_entry(c, &mut implicit_index, table_node, lines, diagnostics, registry) {
+            Ok(nc) => {
+                c = skip_trivia(nc);
+                if matches!(c.first(), Some(',') | Some(';')) {
+                    c = c.advance(1);
                 }
             },
-            _ => panic!("Unexpected variation between the grammmar of `short_string` and its handling")
+            Err(e) => {
+                diagnostics.push(Diagnostic { pos: lines.locate(e.pos as usize), message: e.message });
+                c = recover_to_next_entry(entry_start);
+            }
         }
     }
-    match String::from_utf8(buf) {
-        Ok(st) => Ok(st.into()),
-        Err(_) => Err(LuaLikeError::BadStringEncoding),
-    }
-}
-
-/*use logos::{Lexer, Logos};
-
-#[derive(Logos, Debug, PartialEq)]
-//#[logos(extras = LexExtras)]
-enum Token {
-    #[regex("[_[:alpha:]][_[:alpha:][:digit:]]*", lex_ident)]
-    Ident(Rc<str>),
-
-    #[regex(r#"["']"#, lex_short_string)]
-    #[regex(r#"\[=*\["#, lex_long_string)]
-    String(String),
-
-    //#[regex(r"-?0[xX][0-9A-Fa-f]+(\.[0-9A-Fa-f]*)?([pP]-?[0-9]+)?", parse_hex_num)]
-    #[regex(r"[-+]?[0-9]+(\.[0-9]*)?([eE][-+]?[0-9]+)?", parse_dec_num)]
-    Number(f32),
-
-    #[token("(")] LeftParen,
-    #[token(")")] RightParen,
-    #[token("{")] LeftBrace,
-    #[token("}")] RightBrace,
-    #[token("[")] LeftBracket,
-    #[token("]")] RightBracket,
-    #[token(",")] Comma,
-    #[token("=")] Equals,
-
-    #[regex("--.*[\r\n]", logos::skip)]
-    #[regex(r"[ \r\n]+", logos::skip)]
-    #[error]
-    Error
-}
-
-fn lex_ident(lex: &mut Lexer<Token>) -> Rc<str> {
-    Rc::from(lex.slice())
-}
-
-fn lex_short_string(lex: &mut Lexer<Token>) -> Result<Rc<str>, ()> {
-    #[derive(Logos, Debug, PartialEq)]
-    enum StringPart {
-        #[token("[\"']")] Quote,
-        #[regex(r#"\\[abfnrtv\\"']"#)] CEscape,
-        #[regex(r#"\\x[0-9A-Fa-f][0-9A-Fa-f]"#)] HexByte,
-        #[regex(r#"\\[0-9]([0-9][0-9]?)?"#)] DecByte,
-        #[regex(r#"\\u\{[0-9A-Fa-f]+\}"#)] Unicode,
-        #[regex(r#"[^"'\\]+"#)] Plain,
-        #[error] Error
-    }
-    let buf = Vec::<u8>::new();
-    let eos = lex.slice();
-    let strlex = StringPart::lexer(lex.remainder());
-    let success = loop {
-        let sp = match strlex.next() {
-            Some(sp) => sp,
-            None => break Err(())
-        };
-        match sp {
-            StringPart::Quote => {
-                if strlex.slice() == eos {
-                    break Ok(());
-                }
-                else {
-                    buf.push(strlex.slice().as_bytes()[0])
-                }
-            },
-            StringPart::CEscape => buf.push(match strlex.slice() {
-                "\\a" => 0x07,
-                "\\b" => 0x08,
-                "\\f" => 0x0C,
-                "\\n" => 0x0A,
-                "\\r" => 0x0D,
-                "\\t" => 0x09,
-                "\\v" => 0x0B,
-                "\\\\" => 0x5C,
-                "\\\"" => 0x22,
-                "\\\'" => 0x27,
-                _ => panic!("Somehow missed a C-like escape!")
-            }),
-            StringPart::HexByte => {
-                let hex = &strlex.slice()[2..];
-                let hv = u8::from_str_radix(hex, 16).unwrap();
-                buf.push(hv);
-            },
-            StringPart::DecByte => {
-                let dec = &strlex.slice()[1..];
-                let dv = u16::from_str_radix(dec, 10).unwrap();
-                if dv > 255 { break Err(()); }
-                buf.push(dv as u8);
-            },
-            StringPart::Unicode => {
-                let st = &strlex.slice()[3..];
-                let st = &st[..(st.len()-1)];
-                if st.len() > 6 { break Err(()); }
-                let cv = u32::from_str_radix(st, 16)
-                    .ok()
-                    .and_then(char::from_u32);
-                if let Some(c) = cv {
-                    let mut b = [0; 4];
-                    c.encode_utf8(&mut b);
-                    buf.extend(b);
-                }
-                else {
-                    break Err(())
-                }
-            },
-            StringPart::Plain => buf.extend_from_slice(strlex.slice().as_bytes()),
-            StringPart::Error => break Err(()),
-        }
-    };
-    lex.bump(strlex.span().len());
-    if success.is_err() { return Err(()); }
-    
-    match String::from_utf8(buf) {
-        Ok(st) => Ok(st.into()),
-        Err(_) => Err(()),
-    }
-}
-
-fn lex_long_string(lex: &mut Lexer<Token>) -> Result<Rc<str>, ()> {
-    let end = lex.slice().replace("[", "]");
-    match lex.remainder().find(&end) {
-        Some(idx) => {
-            let data = &lex.remainder()[..idx];
-            lex.bump(idx + end.len());
-            Ok(data.into())
+}
+
+fn parse_table_entry<'a>(c: Cursor<'a>, implicit_index: &mut usize, table_node: &mut rt::NodeMut, lines: &LineIndex, diagnostics: &mut Vec<Diagnostic>, registry: &FunctionRegistry) -> Result<Cursor<'a>, Reject> {
+    match c.first() {
+        Some(ch) if is_ident_start(ch) => {
+            let (after_ident, ident) = lex_ident(c)?;
+            let after_ws = skip_trivia(after_ident);
+            if after_ws.first() == Some('=') {
+                let (c, _) = after_ws.parse("=")?;
+                let (c, ()) = parse_value(c, Key::String(Rc::from(ident)), table_node, lines, diagnostics, registry)?;
+                Ok(c)
+            }
+            else {
+                *implicit_index += 1;
+                let (c, ()) = parse_value(c, Key::Index(*implicit_index), table_node, lines, diagnostics, registry)?;
+                Ok(c)
+            }
+        },
+        Some('[') if !matches!(c.rest.as_bytes().get(1), Some(b'[') | Some(b'=')) => {
+            let (c, _) = c.parse("[")?;
+            let c = skip_trivia(c);
+            let (c, key) = parse_value_key(c)?;
+            let c = skip_trivia(c);
+            let (c, _) = c.parse("]")?;
+            let c = skip_trivia(c);
+            let (c, _) = c.parse("=")?;
+            let (c, ()) = parse_value(c, key, table_node, lines, diagnostics, registry)?;
+            Ok(c)
         },
-        None => Err(())
+        _ => {
+            *implicit_index += 1;
+            let (c, ()) = parse_value(c, Key::Index(*implicit_index), table_node, lines, diagnostics, registry)?;
+            Ok(c)
+        }
     }
 }
 
-fn parse_dec_num(lex: &mut Lexer<Token>) -> Result<f32, ()> {
-    <f32 as std::str::FromStr>::from_str(lex.slice()).map_err(|_|())
+/// Parses the key inside a `[key] = value` table entry: a string (short or long) or an integer.
+fn parse_value_key(c: Cursor) -> PResult<'_, Key<Rc<str>>> {
+    match c.first() {
+        Some('"') | Some('\'') => {
+            let (c, raw) = scan_short_string(c)?;
+            match decode_short_string(raw) {
+                Ok(s) => Ok((c, Key::String(s))),
+                Err(_) => Ok((c, Key::String(Rc::from(raw))))
+            }
+        },
+        Some('[') => {
+            let (c, text) = scan_long_string(c)?;
+            Ok((c, Key::String(Rc::from(text))))
+        },
+        Some(ch) if ch.is_ascii_digit() || ch == '-' || ch == '+' => {
+            let (c, text) = lex_number(c)?;
+            let index = match radix_prefix(text) {
+                Some((radix, digits)) => usize::from_str_radix(digits, radix),
+                None => text.parse()
+            };
+            match index {
+                Ok(i) => Ok((c, Key::Index(i))),
+                Err(_) => Err(Reject::new(c.off, "malformed table index"))
+            }
+        },
+        _ => Err(Reject::new(c.off, "expected a string or integer key"))
+    }
 }
-*/
 
+/// Skips forward from a failed table entry to the start of the next one, tracking bracket/brace
+/// nesting (and skipping over string literals whole) so a `}`/`,` inside a quoted value doesn't
+/// confuse the scan. Mirrors `formats::scriptdata::lua_like`'s recovery of the same name.
+fn recover_to_next_entry(mut c: Cursor) -> Cursor {
+    let mut depth: i32 = 0;
+    loop {
+        match c.first() {
+            None => return c,
+            Some('"') | Some('\'') => {
+                c = match scan_short_string(c) {
+                    Ok((nc, _)) => nc,
+                    Err(_) => return c
+                };
+            },
+            Some('{') | Some('(') => { depth += 1; c = c.advance(1); },
+            Some('}') if depth == 0 => return c,
+            Some(')') | Some('}') => { depth -= 1; c = c.advance(1); },
+            Some(',') | Some(';') if depth == 0 => return c.advance(1),
+            Some(ch) => c = c.advance(ch.len_utf8())
+        }
+    }
+}
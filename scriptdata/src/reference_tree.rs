@@ -2,7 +2,7 @@ use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 use pd2tools_macros::EnumFromData;
-use crate::{Key, Item, Scalar, SchemaError, TableId};
+use crate::{Key, Item, OwnedKey, Scalar, SchemaError, Span, TableId};
 use crate::document::{DocumentBuilder, DocumentRef, InteriorTableWriter, TableRef};
 
 #[derive(EnumFromData, Debug, Clone)]
@@ -14,7 +14,22 @@ pub enum Value {
     Table(TableHeader),
 
     /// Diamond reference created by `_ref` attributes and the like.
-    Ref(Rc<str>)
+    Ref(Rc<str>),
+
+    /// `%unset` directive: drop the most recently bound entry with this key
+    /// from the enclosing table instead of binding anything itself.
+    Unset(OwnedKey),
+
+    /// `%include` directive: splice another document's root table into the
+    /// enclosing table at this point, so later entries can still override it.
+    #[no_auto_from]
+    Include(Rc<str>),
+
+    /// Placeholder left by a resilient parse (see `lua_like::load_resilient`) where a value
+    /// failed to convert - keeps the key bound to *something* so the rest of the table still
+    /// loads, rather than losing the entry or aborting the whole document.
+    #[no_auto_from]
+    Error(Rc<str>)
 }
 
 #[derive(Debug, Clone)]
@@ -26,7 +41,11 @@ pub struct TableHeader {
 #[derive(Debug, Clone)]
 pub struct Data<S> {
     pub key: Key<S>,
-    pub value: Value
+    pub value: Value,
+
+    /// Byte range this node came from, or `Span::default()` for nodes with no source text to
+    /// point at (e.g. ones synthesized by [`from_document`] or the other frontends).
+    pub span: Span
 }
 
 pub type Tree = ego_tree::Tree<Data<Rc<str>>>;
@@ -44,28 +63,65 @@ pub fn empty_tree() -> Tree {
         value: Value::Table(TableHeader {
             id: None,
             meta: None
-        })
+        }),
+        span: Span::default()
     })
 }
 
+/// Resolves the path in an `%include` directive to the reference tree of
+/// another document, so it can be spliced into the one being built.
+pub trait IncludeResolver {
+    fn resolve(&mut self, path: &str) -> Result<Tree, SchemaError>;
+}
+
+/// An [`IncludeResolver`] for callers that don't support `%include` at all.
+/// `to_document` uses this, so existing callers are unaffected.
+struct NoIncludes;
+impl IncludeResolver for NoIncludes {
+    fn resolve(&mut self, path: &str) -> Result<Tree, SchemaError> {
+        Err(SchemaError::DanglingReference(path.into()))
+    }
+}
+
 pub fn to_document(root: Node) -> Result<DocumentRef, SchemaError> {
+    to_document_with(root, &mut NoIncludes)
+}
+
+/// As [`to_document`], but resolves any `%include` directives via `resolver`.
+pub fn to_document_with(root: Node, resolver: &mut dyn IncludeResolver) -> Result<DocumentRef, SchemaError> {
+    let mut visited = HashSet::<Rc<str>>::new();
+    to_document_resolved(root, resolver, &mut visited)
+}
+
+fn to_document_resolved(root: Node, resolver: &mut dyn IncludeResolver, visited: &mut HashSet<Rc<str>>) -> Result<DocumentRef, SchemaError> {
     match &root.value().value {
         Value::Scalar(item) => Ok(DocumentBuilder::new().scalar_document(item.clone())),
+        Value::Error(msg) => Ok(DocumentBuilder::new().string_document(msg)),
         Value::Ref(_) => panic!("RefTree construction didn't reject a root Ref before it got here."),
+        Value::Unset(_) => panic!("RefTree construction didn't reject a root %unset before it got here."),
+        Value::Include(path) => {
+            if !visited.insert(path.clone()) {
+                return Err(SchemaError::IncludeCycle(path.clone()))
+            }
+            let included = resolver.resolve(path)?;
+            let result = to_document_resolved(included.root(), resolver, visited);
+            visited.remove(path);
+            result
+        },
         Value::Table(head) => {
             let mut ids = HashMap::<Rc<str>, TableId>::new();
             let mut found_ids = HashSet::<Rc<str>>::new();
             let mut doc_builder = DocumentBuilder::new();
             let (builder, _) = doc_builder.table_document();
 
-            load_table(root, head.clone(), &mut ids, &mut found_ids, builder)?;
+            load_table(root, head.clone(), &mut ids, &mut found_ids, builder, resolver, visited)?;
 
             Ok(doc_builder.finish())
         }
     }
 }
 
-fn load_table<'s, 't: 's>(node: Node<'t>, table_header: TableHeader, ids: &mut HashMap<Rc<str>, TableId>, found_ids: &mut HashSet<Rc<str>>, mut table: InteriorTableWriter<'_>) -> Result<(), SchemaError> {
+fn load_table<'s, 't: 's>(node: Node<'t>, table_header: TableHeader, ids: &mut HashMap<Rc<str>, TableId>, found_ids: &mut HashSet<Rc<str>>, mut table: InteriorTableWriter<'_>, resolver: &mut dyn IncludeResolver, visited: &mut HashSet<Rc<str>>) -> Result<(), SchemaError> {
     if let Some(id) = table_header.id {
         if !found_ids.insert(id.clone()) {
             return Err(SchemaError::DuplicateId(id))
@@ -76,29 +132,71 @@ fn load_table<'s, 't: 's>(node: Node<'t>, table_header: TableHeader, ids: &mut H
     table.set_meta(table_header.meta);
 
     for cn in node.children() {
-        let ew = table.key(cn.value().key.clone())?;
-        match &cn.value().value {
-            Value::Scalar(it) => ew.scalar(it.clone()),
-            Value::Table(tab) => {
-                let id = tab.id.as_ref().and_then(|i| ids.get(i));
-                let tb = match id {
-                    None => ew.new_table(),
-                    Some(tid) => ew.resume_table(*tid).unwrap()
-                };
-                load_table(cn, tab.clone(), ids, found_ids, tb.1)?
-            },
-            Value::Ref(r) => {
-                match ids.get(r) {
-                    Some(tid) => { ew.resume_table(*tid).unwrap(); },
-                    None => {
-                        let (tid, _) = ew.new_table();
-                        ids.insert(r.clone(), tid);
-                    }
+        load_entry(cn, ids, found_ids, &mut table, resolver, visited)?;
+    }
+    Ok(())
+}
+
+/// Processes one child of a table being loaded: a plain entry binds its key,
+/// while `%unset`/`%include` directives mutate `table` without binding a key
+/// of their own.
+fn load_entry<'s, 't: 's>(cn: Node<'t>, ids: &mut HashMap<Rc<str>, TableId>, found_ids: &mut HashSet<Rc<str>>, table: &mut InteriorTableWriter<'_>, resolver: &mut dyn IncludeResolver, visited: &mut HashSet<Rc<str>>) -> Result<(), SchemaError> {
+    match &cn.value().value {
+        Value::Unset(key) => {
+            table.unset(key.as_borrowed());
+            return Ok(())
+        },
+        Value::Include(path) => {
+            if !visited.insert(path.clone()) {
+                return Err(SchemaError::IncludeCycle(path.clone()))
+            }
+            let included = resolver.resolve(path)?;
+            let result = splice_include(included.root(), ids, found_ids, table, resolver, visited);
+            visited.remove(path);
+            return result
+        },
+        _ => {}
+    }
+
+    let ew = table.key(cn.value().key.clone())?;
+    match &cn.value().value {
+        Value::Scalar(it) => { ew.scalar(it.clone()); Ok(()) },
+        Value::Error(msg) => { ew.scalar(Scalar::String(msg.clone())); Ok(()) },
+        Value::Table(tab) => {
+            let id = tab.id.as_ref().and_then(|i| ids.get(i));
+            let tb = match id {
+                None => ew.new_table(),
+                Some(tid) => ew.resume_table(*tid).unwrap()
+            };
+            load_table(cn, tab.clone(), ids, found_ids, tb.1, resolver, visited)
+        },
+        Value::Ref(r) => {
+            match ids.get(r) {
+                Some(tid) => { ew.resume_table(*tid).unwrap(); },
+                None => {
+                    let (tid, _) = ew.new_table();
+                    ids.insert(r.clone(), tid);
                 }
             }
-        }
+            Ok(())
+        },
+        Value::Unset(_) | Value::Include(_) => unreachable!("handled before the key was bound")
+    }
+}
+
+/// Splices the root table of an included document's tree directly into
+/// `table`, so keys appearing after the `%include` in the including
+/// document still win (last-writer-wins, same as any other duplicate key).
+fn splice_include<'s, 't: 's>(included_root: Node<'t>, ids: &mut HashMap<Rc<str>, TableId>, found_ids: &mut HashSet<Rc<str>>, table: &mut InteriorTableWriter<'_>, resolver: &mut dyn IncludeResolver, visited: &mut HashSet<Rc<str>>) -> Result<(), SchemaError> {
+    match &included_root.value().value {
+        Value::Table(_) => {
+            for cn in included_root.children() {
+                load_entry(cn, ids, found_ids, table, resolver, visited)?;
+            }
+            Ok(())
+        },
+        _ => Err(SchemaError::WrongElement("table"))
     }
-    Ok(())
 }
 
 pub fn from_document(doc: DocumentRef) -> Option<ego_tree::Tree<Data<Rc<str>>>> {
@@ -106,7 +204,7 @@ pub fn from_document(doc: DocumentRef) -> Option<ego_tree::Tree<Data<Rc<str>>>>
         None => None,
         Some(Item::Scalar(s)) => { 
             let data = Data {
-                key: Key::Index(0), value: Value::Scalar(s)
+                key: Key::Index(0), value: Value::Scalar(s), span: Span::default()
             };
             Some(ego_tree::Tree::new(data))
         },
@@ -120,7 +218,8 @@ pub fn from_document(doc: DocumentRef) -> Option<ego_tree::Tree<Data<Rc<str>>>>
 
             let mut tree = ego_tree::Tree::<Data<Rc<str>>>::new(Data {
                 key: Key::Index(0),
-                value: thead.into()
+                value: thead.into(),
+                span: Span::default()
             });
 
             state.tree_from_tableref(tref, tree.root_mut());
@@ -153,14 +252,16 @@ impl DocToTreeState {
                 Item::Scalar(s) => {
                     node.append(Data {
                         key: k,
-                        value: Value::Scalar(s)
+                        value: Value::Scalar(s),
+                        span: Span::default()
                     });
                 },
                 Item::Table(t) => {
                     if let Some(target) = self.tree_nid_by_doc_tid.get(&t.id()) {
                         let rn = node.append(Data {
                             key: k,
-                            value: Value::Ref(Rc::from(""))
+                            value: Value::Ref(Rc::from("")),
+                            span: Span::default()
                         });
                         self.pending_refs.push((rn.id(), *target));
                     }
@@ -170,7 +271,8 @@ impl DocToTreeState {
                             value: Value::Table(TableHeader {
                                 id: None,
                                 meta: t.meta()
-                            })
+                            }),
+                            span: Span::default()
                         });
                         self.tree_from_tableref(t, tn);
                     }
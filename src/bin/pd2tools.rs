@@ -13,7 +13,8 @@ arg_enum! {
         Binary,
         Lua,
         Generic,
-        Custom
+        Custom,
+        Cbor
     }
 }
 
@@ -84,6 +85,13 @@ enum Command {
         #[structopt(short, long)]
         events: bool,
 
+        /// Merge structurally identical tables before writing the output,
+        /// so repeated subtrees become `_id`/`_ref` pairs instead of being
+        /// duplicated. Costs extra CPU; only worth it for formats (like
+        /// custom_xml) that can actually represent the sharing.
+        #[structopt(long)]
+        dedup: bool,
+
         /// File to read
         input: String,
         /// File to write
@@ -121,8 +129,8 @@ fn main() {
         Command::Scan{ asset_dir, output } => {
             do_scan(&opt.hashlist, &asset_dir, &output)
         },
-        Command::Convert{ input, output, input_format, output_format, events } => {
-            do_convert(&input, input_format, &output, output_format, events)
+        Command::Convert{ input, output, input_format, output_format, events, dedup } => {
+            do_convert(&input, input_format, &output, output_format, events, dedup)
         }
         Command::Oil{ input } => {
             let path: std::path::PathBuf = input.into();
@@ -166,7 +174,7 @@ fn do_scan(hashlist_filename: &Option<String>, asset_dir: &str, outname: &str) {
     hashlist_scan::do_scan(&db, &mut outfile).unwrap();
 }
 
-fn do_convert(input_filename: &str, input_type: Option<ConvertType>, output_filename: &str, output_type: ConvertType, events: bool) {
+fn do_convert(input_filename: &str, input_type: Option<ConvertType>, output_filename: &str, output_type: ConvertType, events: bool, dedup: bool) {
     let in_data: Vec<u8> = match input_filename {
         "-" => {
             let mut id = Vec::<u8>::new();
@@ -193,22 +201,25 @@ fn do_convert(input_filename: &str, input_type: Option<ConvertType>, output_file
     let input_func = match input_type {
         Some(ConvertType::Binary) => formats::scriptdata::binary::load,
         Some(ConvertType::Custom) => formats::scriptdata::custom_xml::load,
-        _ => unimplemented!("Only custom and binary are currently implemented")
+        Some(ConvertType::Cbor) => formats::scriptdata::cbor::read_cbor,
+        _ => unimplemented!("Only custom, binary and cbor are currently implemented")
     };
 
-    let doc = input_func(&in_data).with_context(||{
+    let mut doc = input_func(&in_data).with_context(||{
         format!("Decoding \"{}\" as {:?}", input_filename, input_type)
     }).unwrap();
 
-    
+    if dedup {
+        doc.dedup_tables();
+    }
 
-    let output_func = match output_type {
-        ConvertType::Lua => formats::scriptdata::lua_like::dump,
-        ConvertType::Generic => formats::scriptdata::generic_xml::dump,
-        ConvertType::Custom => formats::scriptdata::custom_xml::dump,
+    let output: Vec<u8> = match output_type {
+        ConvertType::Lua => formats::scriptdata::lua_like::dump(&doc).into_bytes(),
+        ConvertType::Generic => formats::scriptdata::generic_xml::dump(&doc).into_bytes(),
+        ConvertType::Custom => formats::scriptdata::custom_xml::dump(&doc).into_bytes(),
+        ConvertType::Cbor => formats::scriptdata::cbor::write_cbor(&doc),
         ConvertType::Binary => unimplemented!()
     };
-    let output = output_func(&doc).into_bytes();
 
     match output_filename {
         "-" => {
@@ -1,6 +1,8 @@
 use std::convert::TryInto;
 
 use pd2tools_macros::Parse;
+use crate::diesel_hash;
+use crate::hashindex::{HashIndex, HashedStr};
 use crate::util::read_helpers::*;
 use crate::util::parse_helpers;
 use crate::util::parse_helpers::Parse;
@@ -25,6 +27,18 @@ pub struct BundleDbFile {
     pub files: Vec<FileEntry>
 }
 
+/// Which of the three header layouts documented below a [`BundleDbFile`]
+/// was read from, or should be written back out as - [`read_bundle_db`]
+/// tells these apart by probing for a zero/nonzero discriminant field
+/// rather than a magic number, so there's nothing to recover this from
+/// except remembering it at read time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BdbFormat {
+    Pd2,
+    X64,
+    Raid
+}
+
 /* there's three possible layouts for the bdb header
 
 PD2 form {
@@ -65,7 +79,7 @@ raid form {
 
 */
 
-pub fn read_bundle_db(blb: &[u8]) -> BundleDbFile {
+pub fn read_bundle_db(blb: &[u8]) -> (BundleDbFile, BdbFormat) {
     let mut res = BundleDbFile {
         tag: 0,
         languages: std::vec::Vec::new(),
@@ -78,7 +92,9 @@ pub fn read_bundle_db(blb: &[u8]) -> BundleDbFile {
     let lang_offset : u64;
     let file_entries_count : u32;
     let file_entries_offset: u64;
+    let format: BdbFormat;
     if maybe_lang_count != 0 { // PD2
+        format = BdbFormat::Pd2;
         lang_count = maybe_lang_count;
         lang_offset = read_u32_le(blb, 12).into();
         file_entries_count = read_u32_le(blb, 28);
@@ -88,11 +104,13 @@ pub fn read_bundle_db(blb: &[u8]) -> BundleDbFile {
         lang_count = read_u32_le(blb, 8);
         let discriminator = read_u32_le(blb, 12);
         if discriminator != 0 { //x64
+            format = BdbFormat::X64;
             lang_offset = read_u64_le(blb, 16);
             file_entries_count = read_u32_le(blb, 48);
             file_entries_offset = read_u64_le(blb, 56);
         }
         else { //raid
+            format = BdbFormat::Raid;
             lang_offset = read_u64_le(blb,24);
             file_entries_count = read_u32_le(blb, 56);
             file_entries_offset = read_u64_le(blb, 72);
@@ -102,7 +120,68 @@ pub fn read_bundle_db(blb: &[u8]) -> BundleDbFile {
     res.languages = parse_array_strided_unwrap(&blb[(lang_offset as usize)..], lang_count as usize, 16);
     res.files = parse_array_strided_unwrap(&blb[(file_entries_offset as usize)..], file_entries_count as usize, 32);
 
-    return res;
+    return (res, format);
+}
+
+/// The inverse of [`read_bundle_db`]: lays out `db` in `fmt`'s header shape
+/// (see the layout comment above) with the language and file-entry arrays
+/// immediately following the header, each entry padded out to the stride
+/// [`read_bundle_db`] expects (16 bytes per [`LanguageEntry`], 32 per
+/// [`FileEntry`] - both wider than the fields actually stored, same as on
+/// read). `read_bundle_db(&write_bundle_db(db, fmt))` round-trips for any
+/// `fmt`, since the header only records which layout was used, not which
+/// one a given `BundleDbFile` "really" came from.
+pub fn write_bundle_db(db: &BundleDbFile, fmt: BdbFormat) -> Vec<u8> {
+    const LANG_STRIDE: usize = 16;
+    const FILE_STRIDE: usize = 32;
+
+    let header_len = match fmt {
+        BdbFormat::Pd2 => 40,
+        BdbFormat::X64 => 64,
+        BdbFormat::Raid => 80,
+    };
+    let lang_offset = header_len as u64;
+    let file_entries_offset = lang_offset + (db.languages.len() * LANG_STRIDE) as u64;
+
+    let mut out = vec![0u8; header_len];
+    out[0..4].copy_from_slice(&db.tag.to_le_bytes());
+
+    match fmt {
+        BdbFormat::Pd2 => {
+            out[4..8].copy_from_slice(&(db.languages.len() as u32).to_le_bytes());
+            out[12..16].copy_from_slice(&(lang_offset as u32).to_le_bytes());
+            out[28..32].copy_from_slice(&(db.files.len() as u32).to_le_bytes());
+            out[36..40].copy_from_slice(&(file_entries_offset as u32).to_le_bytes());
+        },
+        BdbFormat::X64 => {
+            out[8..12].copy_from_slice(&(db.languages.len() as u32).to_le_bytes());
+            out[12..16].copy_from_slice(&1u32.to_le_bytes());
+            out[16..24].copy_from_slice(&lang_offset.to_le_bytes());
+            out[48..52].copy_from_slice(&(db.files.len() as u32).to_le_bytes());
+            out[56..64].copy_from_slice(&file_entries_offset.to_le_bytes());
+        },
+        BdbFormat::Raid => {
+            out[8..12].copy_from_slice(&(db.languages.len() as u32).to_le_bytes());
+            out[24..32].copy_from_slice(&lang_offset.to_le_bytes());
+            out[56..60].copy_from_slice(&(db.files.len() as u32).to_le_bytes());
+            out[72..80].copy_from_slice(&file_entries_offset.to_le_bytes());
+        },
+    }
+
+    for lang in &db.languages {
+        write_strided(&mut out, lang, LANG_STRIDE);
+    }
+    for file in &db.files {
+        write_strided(&mut out, file, FILE_STRIDE);
+    }
+
+    out
+}
+
+fn write_strided<T: Parse>(out: &mut Vec<u8>, item: &T, stride: usize) {
+    let start = out.len();
+    item.serialize(out).unwrap();
+    out.resize(start + stride, 0);
 }
 
 fn parse_array_strided_unwrap<T: Parse>(data: &[u8], count: usize, stride: usize) -> Vec<T> {
@@ -114,4 +193,42 @@ fn parse_array_strided_unwrap<T: Parse>(data: &[u8], count: usize, stride: usize
         dest.push(entry);
     }
     return dest;
+}
+
+/// A [`FileEntry`] with its `path`/`extension` hashes resolved against a
+/// hashlist instead of left as raw `u64`s - see [`resolve_files`]. Unlike
+/// [`super::database::DatabaseItem`] this doesn't need the package data
+/// `from_bdb` does, just `bdb` and a hashlist, which is enough for printing
+/// or diffing a bundle DB's contents by name.
+pub struct ResolvedFileEntry<'a> {
+    pub path: HashedStr<'a>,
+    pub extension: Option<HashedStr<'a>>,
+    pub lang_id: u32,
+    pub file_id: u32,
+}
+
+impl<'a> ResolvedFileEntry<'a> {
+    /// The filename [`FileEntry`] doesn't store directly - `path` with
+    /// `extension` appended if there is one, e.g. `units/player.unit`.
+    /// Either half falls back to hex if the hashlist doesn't know it.
+    pub fn filename(&self) -> String {
+        match &self.extension {
+            Some(ext) => format!("{}.{}", self.path, ext),
+            None => self.path.to_string()
+        }
+    }
+}
+
+/// Resolves every [`FileEntry`] in `bdb` against `hashlist`, leaving any
+/// hash it doesn't recognise as hex - see [`ResolvedFileEntry`].
+pub fn resolve_files<'a>(bdb: &BundleDbFile, hashlist: &'a HashIndex) -> Vec<ResolvedFileEntry<'a>> {
+    bdb.files.iter().map(|fe| {
+        let extension = hashlist.get_hash(fe.extension);
+        ResolvedFileEntry {
+            path: hashlist.get_hash(fe.path),
+            extension: if extension.hash == diesel_hash::EMPTY { None } else { Some(extension) },
+            lang_id: fe.lang_id,
+            file_id: fe.file_id,
+        }
+    }).collect()
 }
\ No newline at end of file
@@ -1,13 +1,17 @@
 use std::cmp::Ord;
 use std::cmp::Ordering;
 use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, Write};
 use std::path::PathBuf;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::SystemTime;
 
+use bytemuck::{Pod, Zeroable};
 use fnv::FnvHashMap;
 use fnv::FnvHashSet;
+use memmap2::Mmap;
 
 use crate::hashindex::HashIndex;
 use crate::hashindex::HashedStr;
@@ -21,7 +25,7 @@ Because we're obsessing with memory usage, we want to be compact.
 We also know the main operations are:
  - get a *specific* item, but we don't know whether we want a file or folder
    (eg, because stat(2).)
- 
+
  - enumerate the children of an item, which we assume is a folder, if only
    because the client checked first.
 
@@ -33,6 +37,12 @@ some order where all the direct children of an item are together. Breadth
 first traversal of the overall folder tree, for instance. Then we have an
 index of path/lang/ext to where in that array the item is.
 
+`items` and `file_packages` below are plain fixed-width records rather than
+an enum-with-a-heap-Vec-inside, specifically so that [`load_cache`] can read
+them straight out of a memory map instead of re-running [`from_bdb`]'s
+hashing/sorting/path-walking to rebuild them. [`ItemStorage::Owned`] and
+[`ItemStorage::Mapped`] hold the exact same record shapes; only where the
+bytes live differs.
 */
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
@@ -63,50 +73,96 @@ impl<'a> HashStrKey<'a> {
 
 pub struct Database {
     pub hashes: Arc<HashIndex>,
-    
-    // Items by their index in self.items
-    item_index: FnvHashMap<(u64, u64, u64), u32>,
-    items: Vec<ItemRecord>,
-    
-    // Packages by their index in self.pacakges
+
+    storage: ItemStorage,
+
+    // Packages by their index in self.packages. Always owned: there are at
+    // most a few hundred of these, so rebuilding them from the cache's
+    // section costs nothing next to the items themselves.
     package_index: FnvHashMap<u64, u32>,
     packages: Vec<PackageRecord>
 }
 
+/// Where [`ItemRecord`]s and [`FileToPackage`]s actually live: either built
+/// up in memory by [`from_bdb`], or borrowed straight out of a [`load_cache`]
+/// memory map.
+enum ItemStorage {
+    Owned {
+        // A sorted table rather than a hashmap: `items` is already sorted
+        // breadth-first, so paying for a hashmap's load-factor slack and
+        // probe metadata on top of it - one entry per file, potentially
+        // hundreds of thousands of them - isn't worth it next to a binary
+        // search. Mirrors the sorted [`IndexEntry`] table [`MappedItems`]
+        // already binary-searches out of a cache file.
+        item_index: Vec<IndexEntry>,
+        items: Vec<ItemRecord>,
+        file_packages: Vec<FileToPackage>,
+        // The union of package numbers backing every file under a folder,
+        // flattened the same way `file_packages` is - each folder's
+        // `ItemRecord` points at its own range. See `compute_folder_aggregates`.
+        folder_packages: Vec<u32>
+    },
+    Mapped(MappedItems)
+}
+
+/// One entry in `Database`'s item list: a plain, fixed-width record so it
+/// can be reinterpreted directly out of a cache file's memory map.
+///
+/// `tag` discriminates `File`/`Folder`; `a`/`b` mean different things
+/// depending on it - the offset/count of this file's entries in
+/// `file_packages`, or a folder's `first_child`/`child_count` into the
+/// overall item list.
+///
+/// `folder_packages_offset`/`folder_packages_count` and
+/// `folder_last_modified_*` are only meaningful for folders: a range into
+/// `Database`'s `folder_packages` array (the union of every package backing
+/// any file in this folder's subtree, deduplicated and sorted) and the
+/// recursive max `last_modified` across that same subtree, both folded
+/// bottom-up by [`compute_folder_aggregates`]. Left zeroed for files, which
+/// answer the same questions directly off their own `file_packages` entries.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
 struct ItemRecord {
     path: u64,
     language: u64,
     extension: u64,
-    specifics: ItemRecordSpecifics
+    folder_last_modified_secs: u64,
+    tag: u32,
+    a: u32,
+    b: u32,
+    folder_last_modified_nanos: u32,
+    folder_packages_offset: u32,
+    folder_packages_count: u32
 }
 
-enum ItemRecordSpecifics {
-    File(FileRecord),
-    Folder(FolderRecord)
-}
-
-struct FileRecord {
-    packages: Vec<FileToPackage>
-}
+const TAG_FILE: u32 = 0;
+const TAG_FOLDER: u32 = 1;
 
+/// Which package (and which entry within it) a file item's bytes live in.
+/// A file can be duplicated across several packages, hence the indirection
+/// through a separate, variable-length array rather than a fixed slot per item.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
 struct FileToPackage {
     package_number: u32,
     file_number: u32
 }
 
-struct FolderRecord {
-    //packages: Vec<u32>,
-    first_child: u32,
-    child_count: u32
-}
-
 struct PackageRecord {
     package_id: u64,
     data_path: PathBuf,
     last_modified: SystemTime,
+    // Identity of the data file this was last read from, so a rescan can
+    // tell "unchanged" from "same name, different file" - see
+    // `Database::incremental_update`.
+    device: u64,
+    inode: u64,
+    data_len: u64,
     files: Vec<PackageEntryRecord>
 }
 
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
 struct PackageEntryRecord {
     item_number: u32,
     offset: u32,
@@ -119,9 +175,8 @@ impl<'a> Database {
     }
 
     pub fn get_by_hashes(&self, path: u64, language: u64, extension: u64) -> Option<DatabaseItem> {
-        let query = (path, language, extension);
-        let idx = self.item_index.get(&query)?;
-        return Some(self.get_by_inode(*idx));
+        let idx = self.lookup_item_index((path, language, extension))?;
+        return Some(self.get_by_inode(idx));
     }
 
     pub fn get_by_inode(&'a self, inode_number: u32) -> DatabaseItem {
@@ -131,40 +186,131 @@ impl<'a> Database {
         }
     }
 
+    fn lookup_item_index(&self, key: (u64, u64, u64)) -> Option<u32> {
+        match &self.storage {
+            ItemStorage::Owned { item_index, .. } => binary_search_index(item_index.len(), key, |i| item_index[i]),
+            ItemStorage::Mapped(m) => m.find_index(key)
+        }
+    }
+
+    fn item_count(&self) -> usize {
+        match &self.storage {
+            ItemStorage::Owned { items, .. } => items.len(),
+            ItemStorage::Mapped(m) => m.item_count
+        }
+    }
+
+    fn item_at(&self, idx: u32) -> ItemRecord {
+        match &self.storage {
+            ItemStorage::Owned { items, .. } => items[idx as usize],
+            ItemStorage::Mapped(m) => m.item(idx as usize)
+        }
+    }
+
+    fn file_packages_range(&self, offset: u32, count: u32) -> &[FileToPackage] {
+        let all = match &self.storage {
+            ItemStorage::Owned { file_packages, .. } => file_packages.as_slice(),
+            ItemStorage::Mapped(m) => m.file_packages()
+        };
+        &all[(offset as usize)..((offset + count) as usize)]
+    }
+
+    fn folder_packages_range(&self, offset: u32, count: u32) -> &[u32] {
+        let all = match &self.storage {
+            ItemStorage::Owned { folder_packages, .. } => folder_packages.as_slice(),
+            ItemStorage::Mapped(m) => m.folder_packages()
+        };
+        &all[(offset as usize)..((offset + count) as usize)]
+    }
+
+    /// Resolves `prefix` to a folder's item index, the same way
+    /// [`Database::get_by_hashes`] resolves a file - folders are interned
+    /// under their full ancestor path (see [`from_bdb`]), so this is one
+    /// binary search rather than a component-by-component walk. `None` if
+    /// `prefix` doesn't name a folder at all.
+    fn resolve_folder(&self, prefix: &str) -> Option<u32> {
+        let path_hash = if prefix.is_empty() { diesel_hash::EMPTY } else { diesel_hash::hash_str(prefix) };
+        let idx = self.lookup_item_index((path_hash, diesel_hash::EMPTY, diesel_hash::EMPTY))?;
+        match self.item_at(idx).tag {
+            TAG_FOLDER => Some(idx),
+            _ => None
+        }
+    }
+
+    /// Delimiter-style listing: the immediate children of `prefix` - what an
+    /// object-store API would call "common prefixes" for the subfolders and
+    /// keys for the files - without the caller resolving `prefix`'s folder
+    /// inode first. `""` lists the root. `None` if `prefix` doesn't name a
+    /// folder. See [`Database::glob`] for recursive, pattern-driven listing.
+    pub fn list_prefix(&'a self, prefix: &str) -> Option<ChildIterator<'a>> {
+        let idx = self.resolve_folder(prefix)?;
+        let item = self.item_at(idx);
+        Some(ChildIterator { db: self, current_index: item.a, end_index: item.a + item.b })
+    }
+
+    /// Recursively walks every descendant of `prefix`, yielding the ones
+    /// `matcher` accepts - the glob-mode counterpart to
+    /// [`Database::list_prefix`]'s single-level delimiter listing, for
+    /// patterns like `units/**/*.model`. Streams through [`GlobIterator`]
+    /// rather than collecting up front, so a `**` query over a large subtree
+    /// starts yielding matches immediately. `None` if `prefix` doesn't name a
+    /// folder.
+    pub fn glob<F: Fn(HashStrKey) -> bool>(&'a self, prefix: &str, matcher: F) -> Option<GlobIterator<'a, F>> {
+        let idx = self.resolve_folder(prefix)?;
+        let item = self.item_at(idx);
+        let stack = if item.b > 0 { vec![(item.a, item.a + item.b)] } else { Vec::new() };
+        Some(GlobIterator { db: self, matcher, stack })
+    }
+
+    /// Typo-tolerant lookup by a possibly-misspelled path - see
+    /// [`super::search`]. Rebuilds its trigram index from scratch on every
+    /// call, same as [`Database::glob`] walks the tree fresh each time
+    /// rather than keeping a standing index around.
+    pub fn search(&self, query: &str, max_results: usize) -> Vec<(HashStrKey, u32)> {
+        super::search::search(self, query, max_results)
+    }
+
+    /// Every [`ItemType::File`] item in the database, in storage order - the
+    /// `DatabaseItem` counterpart to iterating `0..item_count()`, which
+    /// call sites outside this module can't do directly since `item_count`
+    /// and `item_at` are private.
+    pub fn files(&'a self) -> impl Iterator<Item = DatabaseItem<'a>> + 'a {
+        (0..self.item_count() as u32)
+            .map(move |i| self.get_by_inode(i))
+            .filter(|item| matches!(item.item_type(), ItemType::File))
+    }
+
     pub fn print_stats(&self) {
         let mut foldercount = 0;
-        for i in &self.items {
-            match i.specifics {
-                ItemRecordSpecifics::Folder(_) => foldercount += 1,
-                _ => {}
+        for i in 0..self.item_count() {
+            if self.item_at(i as u32).tag == TAG_FOLDER {
+                foldercount += 1;
             }
         }
 
-        println!("Items: {}", self.items.len());
+        println!("Items: {}", self.item_count());
         println!("Folders: {}", foldercount);
         println!("Packages: {}", self.packages.len());
-        println!("{}", self.item_index.contains_key(&(diesel_hash::EMPTY,diesel_hash::EMPTY,diesel_hash::EMPTY)));
+        println!("{}", self.lookup_item_index((diesel_hash::EMPTY, diesel_hash::EMPTY, diesel_hash::EMPTY)).is_some());
     }
 
-    pub fn filter_key_sort_physical(&self, cond: fn(HashStrKey) -> bool) -> Vec<(&Path, Vec<ReadItem>)> {
+    pub fn filter_key_sort_physical(&self, cond: impl Fn(HashStrKey) -> bool) -> Vec<(&Path, Vec<ReadItem>)> {
         // 0: path, 1: total bytes to read from this bundle, 2: files to read.
         let mut packs = Vec::<(&Path, usize, Vec<ReadItem>)>::with_capacity(self.packages.len());
 
         for pkg in self.packages.iter() {
             let items: Vec<ReadItem> = pkg.files.iter().filter_map(|per| {
-                let item = &self.items[per.item_number as usize];
+                let item = self.item_at(per.item_number);
                 let key = HashStrKey::from_hashes(&self.hashes, (item.path, item.language, item.extension));
                 if !cond(key) { return None }
 
-                match &item.specifics {
-                    ItemRecordSpecifics::Folder(_) => None,
-                    ItemRecordSpecifics::File(_) => Some(ReadItem {
-                        key,
-                        last_modified: pkg.last_modified,
-                        offset: per.offset as usize,
-                        length: per.length as usize
-                    })
-                }
+                if item.tag == TAG_FOLDER { return None }
+                Some(ReadItem {
+                    key,
+                    last_modified: pkg.last_modified,
+                    offset: per.offset as usize,
+                    length: per.length as usize
+                })
             }).collect();
 
             if items.len() == 0 { continue; }
@@ -195,6 +341,241 @@ impl<'a> Database {
 
         return filtered_packs;
     }
+
+    /// Writes a [`load_cache`]-readable snapshot of this database to `path`,
+    /// so the next run can skip [`from_bdb`]'s hashing/sorting/path-walking
+    /// entirely. Best-effort: a caller that can't write the cache (read-only
+    /// asset directory, etc.) should just carry on without one.
+    pub fn write_cache(&self, path: &Path) -> io::Result<()> {
+        let (index, items, file_packages, folder_packages) = match &self.storage {
+            ItemStorage::Owned { item_index, items, file_packages, folder_packages } => (item_index, items, file_packages, folder_packages),
+            ItemStorage::Mapped(_) => return Err(io::Error::new(io::ErrorKind::Other, "cannot re-serialize a database already backed by a cache")),
+        };
+
+        // `item_index` is already a sorted `Vec<IndexEntry>`, ready to write
+        // out as-is - see [`ItemStorage::Owned`].
+
+        // `Database::incremental_update` tombstones a removed package's slot
+        // rather than deleting it, so `FileToPackage::package_number` keeps
+        // pointing at the right `PackageRecord` for every package that's
+        // still live. The cache has to preserve those slots at the same
+        // index for the same reason, so it records which ones are tombstoned
+        // instead of just dropping them.
+        let live_numbers: FnvHashSet<u32> = self.package_index.values().copied().collect();
+
+        let mut strings = Vec::<u8>::new();
+        let mut package_entries = Vec::<PackageEntryRecord>::new();
+        let mut packages = Vec::<PackageOnDisk>::with_capacity(self.packages.len());
+        for (number, pkg) in self.packages.iter().enumerate() {
+            let path_bytes = pkg.data_path.to_string_lossy().into_owned().into_bytes();
+            let data_path_offset = strings.len() as u32;
+            let data_path_len = path_bytes.len() as u32;
+            strings.extend_from_slice(&path_bytes);
+
+            let entries_offset = package_entries.len() as u32;
+            let entries_count = pkg.files.len() as u32;
+            package_entries.extend_from_slice(&pkg.files);
+
+            let since_epoch = pkg.last_modified.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+            packages.push(PackageOnDisk {
+                package_id: pkg.package_id,
+                last_modified_secs: since_epoch.as_secs(),
+                last_modified_nanos: since_epoch.subsec_nanos(),
+                data_path_offset,
+                data_path_len,
+                entries_offset,
+                entries_count,
+                live: live_numbers.contains(&(number as u32)) as u32,
+                device: pkg.device,
+                inode: pkg.inode,
+                data_len: pkg.data_len
+            });
+        }
+
+        let mut body = Vec::<u8>::new();
+        body.extend_from_slice(bytemuck::cast_slice(items));
+        body.extend_from_slice(bytemuck::cast_slice(file_packages));
+        body.extend_from_slice(bytemuck::cast_slice(index.as_slice()));
+        body.extend_from_slice(bytemuck::cast_slice(folder_packages.as_slice()));
+        // `folder_packages` elements are only 4 bytes wide, so an odd count
+        // would otherwise leave `packages` (which has `u64` fields) starting
+        // on a 4-but-not-8-aligned offset - pad back up to a multiple of 8.
+        let folder_packages_bytes = folder_packages.len() * std::mem::size_of::<u32>();
+        body.resize(body.len() + (round_up_8(folder_packages_bytes) - folder_packages_bytes), 0);
+        body.extend_from_slice(bytemuck::cast_slice(&packages));
+        body.extend_from_slice(bytemuck::cast_slice(&package_entries));
+        body.extend_from_slice(&strings);
+
+        let mut out = Vec::<u8>::with_capacity(CACHE_HEADER_SIZE + body.len());
+        out.extend_from_slice(CACHE_MAGIC);
+        out.push(CACHE_VERSION);
+        out.extend_from_slice(&[0u8; 3]);
+        out.extend_from_slice(&(items.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(file_packages.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(index.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(packages.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(package_entries.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(folder_packages.len() as u32).to_le_bytes());
+        out.extend_from_slice(&crc32c::crc32c(&body).to_le_bytes());
+        out.extend_from_slice(&body);
+
+        let mut file = File::create(path)?;
+        file.write_all(&out)?;
+        Ok(())
+    }
+
+    /// Materializes a cache-backed ([`ItemStorage::Mapped`]) database into an
+    /// owned one, copying its records out of the memory map into plain
+    /// `Vec`s. A no-op if the database is already owned. Needed before
+    /// [`Database::incremental_update`] or [`Database::write_cache`], both of
+    /// which mutate/re-derive the owned fields directly.
+    pub fn into_owned(mut self) -> Database {
+        if let ItemStorage::Mapped(m) = &self.storage {
+            let items: Vec<ItemRecord> = (0..m.item_count).map(|i| m.item(i)).collect();
+            let file_packages = m.file_packages().to_vec();
+            let folder_packages = m.folder_packages().to_vec();
+            // The cache's index table is already sorted by key, so this can
+            // just be collected straight across rather than re-sorted.
+            let item_index: Vec<IndexEntry> = (0..m.index_count).map(|i| m.index_entry(i)).collect();
+            self.storage = ItemStorage::Owned { item_index, items, file_packages, folder_packages };
+        }
+        self
+    }
+
+    /// Patches this database in place against a freshly rescanned
+    /// `packages`, reprocessing only the packages whose identity -
+    /// `(device, inode, length, last_modified)` - changed since it was last
+    /// built, instead of redoing all of [`from_bdb`]'s hashing, sorting and
+    /// folder-tree walking.
+    ///
+    /// This assumes the item set itself (the paths/languages/extensions
+    /// `bdb` describes) hasn't changed - only which package backs which
+    /// file. `items` and `item_index` are left untouched; only the per-file
+    /// `packages` back-references are rebuilt. Callers whose bdb changed
+    /// should call [`from_bdb`] instead. Panics if called on a database
+    /// still backed by a cache - call [`Database::into_owned`] first.
+    pub fn incremental_update(&mut self, bdb: &bundledb_reader::BundleDbFile, packages: &[loader::ParsedBundle]) {
+        if !matches!(self.storage, ItemStorage::Owned { .. }) {
+            panic!("incremental_update requires an owned database - call into_owned() first");
+        }
+
+        let live_ids: FnvHashSet<u64> = packages.iter().map(|p| p.package_id).collect();
+
+        // Packages that disappeared from the scan: drop their back-references,
+        // but tombstone the slot rather than removing it outright, since
+        // `FileToPackage::package_number` indexes into `self.packages`
+        // directly and every other package's index has to keep meaning the
+        // same thing.
+        let mut dead_numbers = FnvHashSet::<u32>::default();
+        let stale_ids: Vec<u64> = self.package_index.keys().filter(|id| !live_ids.contains(id)).copied().collect();
+        for id in stale_ids {
+            let number = self.package_index.remove(&id).unwrap();
+            self.packages[number as usize].files.clear();
+            dead_numbers.insert(number);
+        }
+
+        // Packages that are new, or whose identity changed, need their
+        // header re-parsed; everything else keeps its `PackageRecord` (and
+        // its file-to-package references) exactly as it was.
+        let mut to_reparse = Vec::<u32>::new();
+        for pack in packages {
+            match self.package_index.get(&pack.package_id).copied() {
+                Some(number) => {
+                    let existing = &self.packages[number as usize];
+                    if existing.device == pack.device && existing.inode == pack.inode
+                        && existing.data_len == pack.data_len && existing.last_modified == pack.last_modified {
+                        continue;
+                    }
+                    dead_numbers.insert(number);
+                    to_reparse.push(number);
+                }
+                None => {
+                    let number = self.packages.len() as u32;
+                    self.packages.push(PackageRecord {
+                        package_id: pack.package_id,
+                        data_path: pack.data_path.clone(),
+                        last_modified: pack.last_modified,
+                        device: pack.device,
+                        inode: pack.inode,
+                        data_len: pack.data_len,
+                        files: Vec::new()
+                    });
+                    self.package_index.insert(pack.package_id, number);
+                    to_reparse.push(number);
+                }
+            }
+        }
+
+        if dead_numbers.is_empty() && to_reparse.is_empty() { return; }
+
+        let itemkeys = itemkeys_by_file_id(bdb);
+        let item_index = match &self.storage { ItemStorage::Owned { item_index, .. } => item_index, ItemStorage::Mapped(_) => unreachable!() };
+
+        // Carry over every file-to-package reference that doesn't point at a
+        // dead package, then re-parse the changed/new packages' headers and
+        // add theirs back in.
+        let item_count = self.item_count();
+        let mut packages_by_item = vec![Vec::<FileToPackage>::new(); item_count];
+        {
+            let (items, file_packages) = match &self.storage {
+                ItemStorage::Owned { items, file_packages, .. } => (items, file_packages),
+                ItemStorage::Mapped(_) => unreachable!()
+            };
+            for (idx, item) in items.iter().enumerate() {
+                if item.tag != TAG_FILE { continue; }
+                let existing_refs = &file_packages[(item.a as usize)..((item.a + item.b) as usize)];
+                for r in existing_refs {
+                    if !dead_numbers.contains(&r.package_number) {
+                        packages_by_item[idx].push(*r);
+                    }
+                }
+            }
+        }
+
+        for &number in &to_reparse {
+            let pack = packages.iter().find(|p| p.package_id == self.packages[number as usize].package_id).unwrap();
+            let pr = &mut self.packages[number as usize];
+            pr.data_path = pack.data_path.clone();
+            pr.last_modified = pack.last_modified;
+            pr.device = pack.device;
+            pr.inode = pack.inode;
+            pr.data_len = pack.data_len;
+            pr.files.clear();
+            pr.files.reserve_exact(pack.header.entries.len());
+
+            for (i, entry) in pack.header.entries.iter().enumerate() {
+                let key = itemkeys.get(&entry.file_id).unwrap();
+                let item_number = binary_search_index(item_index.len(), *key, |i| item_index[i]).unwrap();
+                pr.files.push(PackageEntryRecord { item_number, offset: entry.offset, length: entry.length });
+                packages_by_item[item_number as usize].push(FileToPackage { package_number: number, file_number: i as u32 });
+            }
+        }
+
+        let (items, file_packages, folder_packages) = match &mut self.storage {
+            ItemStorage::Owned { items, file_packages, folder_packages, .. } => (items, file_packages, folder_packages),
+            ItemStorage::Mapped(_) => unreachable!()
+        };
+
+        let mut new_file_packages = Vec::<FileToPackage>::new();
+        for (idx, refs) in packages_by_item.into_iter().enumerate() {
+            if items[idx].tag != TAG_FILE { continue; }
+            if refs.is_empty() {
+                items[idx].a = 0;
+                items[idx].b = 0;
+                continue;
+            }
+            items[idx].a = new_file_packages.len() as u32;
+            items[idx].b = refs.len() as u32;
+            new_file_packages.extend(refs);
+        }
+        *file_packages = new_file_packages;
+
+        // Which packages back which file changed, so every folder's
+        // aggregated subtree view (`DatabaseItem::packages_in_subtree`,
+        // `DatabaseItem::last_modified`) needs folding again too.
+        *folder_packages = compute_folder_aggregates(items, file_packages, &self.packages);
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -211,8 +592,8 @@ pub struct DatabaseItem<'a> {
 }
 
 impl<'a> DatabaseItem<'a> {
-    fn item(&self) -> &ItemRecord {
-        self.db.items.get(self.item_number as usize).unwrap()
+    fn item(&self) -> ItemRecord {
+        self.db.item_at(self.item_number)
     }
 
     pub fn key(&self) -> (HashedStr, HashedStr, HashedStr) {
@@ -246,71 +627,93 @@ impl<'a> DatabaseItem<'a> {
 
     pub fn last_modified(&self) -> SystemTime {
         let item = self.item();
-        match &item.specifics {
-            ItemRecordSpecifics::File(file) => {
-                let packref = file.packages.get(0).unwrap();
-                let package = self.db.packages.get(packref.package_number as usize).unwrap();
-                package.last_modified
-            },
-            /*ItemRecordSpecifics::Folder(folder) => {
-                let packid = folder.packages.get(0).unwrap();
-                let package = self.db.packages.get(*packid as usize).unwrap();
-                package.last_modified
-            }*/
-            _ => SystemTime::UNIX_EPOCH
+        if item.tag != TAG_FILE {
+            // Recursive max over the subtree, folded bottom-up by
+            // `compute_folder_aggregates` - see `ItemRecord`.
+            return SystemTime::UNIX_EPOCH + std::time::Duration::new(item.folder_last_modified_secs, item.folder_last_modified_nanos);
+        }
+        // A file that just had its only backing package removed by
+        // `Database::incremental_update` has no references left here yet.
+        match self.db.file_packages_range(item.a, item.b).get(0) {
+            None => SystemTime::UNIX_EPOCH,
+            Some(packref) => self.db.packages.get(packref.package_number as usize).unwrap().last_modified
         }
     }
 
-    pub fn item_type(&self) -> ItemType {
+    /// Every package with a file somewhere under this item: just this file's
+    /// own backing packages for a file, or the deduplicated union across the
+    /// whole subtree (folded bottom-up by `compute_folder_aggregates`) for a
+    /// folder - "which bundles back this directory" for a virtual-filesystem
+    /// front-end.
+    pub fn packages_in_subtree(&self) -> Vec<u32> {
         let item = self.item();
-        match item.specifics {
-            ItemRecordSpecifics::File(_) => ItemType::File,
-            ItemRecordSpecifics::Folder(_) => ItemType::Folder
+        if item.tag == TAG_FILE {
+            self.db.file_packages_range(item.a, item.b).iter().map(|r| r.package_number).collect()
+        }
+        else {
+            self.db.folder_packages_range(item.folder_packages_offset, item.folder_packages_count).to_vec()
+        }
+    }
+
+    pub fn item_type(&self) -> ItemType {
+        match self.item().tag {
+            TAG_FILE => ItemType::File,
+            _ => ItemType::Folder
         }
     }
 
     pub fn children(&'a self) -> ChildIterator<'a> {
         let item = self.item();
-        match &item.specifics {
-            ItemRecordSpecifics::File(_) => ChildIterator {
+        match item.tag {
+            TAG_FOLDER => ChildIterator {
                 db: self.db,
-                current_index: 0,
-                end_index: 0
+                current_index: item.a,
+                end_index: item.a + item.b
             },
-            ItemRecordSpecifics::Folder(folder) => ChildIterator {
+            _ => ChildIterator {
                 db: self.db,
-                current_index: folder.first_child,
-                end_index: folder.first_child + folder.child_count
+                current_index: 0,
+                end_index: 0
             }
         }
      }
 
     pub fn data_len(&self) -> usize {
         let item = self.item();
-        match &item.specifics {
-            ItemRecordSpecifics::Folder(_) => 0,
-            ItemRecordSpecifics::File(fi) => {
-                let packref = fi.packages.get(0).unwrap();
-                let maybe_package = self.db.packages.get(packref.package_number as usize);
-                let maybe_packentry = maybe_package.and_then(|p| p.files.get(packref.file_number as usize));
-                maybe_packentry.unwrap().length.try_into().unwrap()
-            }
-        }
+        if item.tag != TAG_FILE { return 0; }
+        // As in `last_modified`, a file can briefly have no backing package
+        // between an incremental removal and its next repack being seen.
+        let packref = match self.db.file_packages_range(item.a, item.b).get(0) {
+            None => return 0,
+            Some(p) => p
+        };
+        let maybe_package = self.db.packages.get(packref.package_number as usize);
+        let maybe_packentry = maybe_package.and_then(|p| p.files.get(packref.file_number as usize));
+        maybe_packentry.unwrap().length.try_into().unwrap()
     }
 
     pub fn item_index(&self) -> u32 { self.item_number }
 
     pub fn get_backing_details(&self) -> Option<(&'a Path, usize, usize)> {
         let item = self.item();
-        match &item.specifics {
-            ItemRecordSpecifics::Folder(_) => None,
-            ItemRecordSpecifics::File(fi) => {
-                let packref = fi.packages.get(0).unwrap();
-                let package = self.db.packages.get(packref.package_number as usize).unwrap();
-                let packentry = package.files.get(packref.file_number as usize).unwrap();
-                return Some((&package.data_path, packentry.offset as usize, packentry.length as usize));
-            }
-        }
+        if item.tag != TAG_FILE { return None; }
+        let packref = self.db.file_packages_range(item.a, item.b).get(0)?;
+        let package = self.db.packages.get(packref.package_number as usize).unwrap();
+        let packentry = package.files.get(packref.file_number as usize).unwrap();
+        return Some((&package.data_path, packentry.offset as usize, packentry.length as usize));
+    }
+
+    /// The stable id (from the bundle header, not the index into
+    /// `Database::packages`) of the package this file's bytes were last read
+    /// from - what `user.diesel.package` on the FUSE/Dokan mounts reports,
+    /// since the index itself can be reassigned across an
+    /// `incremental_update`.
+    pub fn backing_package_id(&self) -> Option<u64> {
+        let item = self.item();
+        if item.tag != TAG_FILE { return None; }
+        let packref = self.db.file_packages_range(item.a, item.b).get(0)?;
+        let package = self.db.packages.get(packref.package_number as usize)?;
+        Some(package.package_id)
     }
 }
 
@@ -339,28 +742,139 @@ impl<'a> Iterator for ChildIterator<'a> {
     }
 }
 
+/// Recursive counterpart to [`ChildIterator`], built by [`Database::glob`]:
+/// walks a subtree depth-first via an explicit stack of child ranges rather
+/// than recursing through [`DatabaseItem::children`], since a folder's
+/// `ChildIterator` needs to outlive the call that pushed it. Yields every
+/// descendant `matcher` accepts, folders and files alike.
+pub struct GlobIterator<'a, F: Fn(HashStrKey) -> bool> {
+    db: &'a Database,
+    matcher: F,
+    stack: Vec<(u32, u32)>
+}
+
+impl<'a, F: Fn(HashStrKey) -> bool> Iterator for GlobIterator<'a, F> {
+    type Item = DatabaseItem<'a>;
+
+    fn next(&mut self) -> Option<DatabaseItem<'a>> {
+        loop {
+            let (current, end) = *self.stack.last()?;
+            if current >= end {
+                self.stack.pop();
+                continue;
+            }
+            self.stack.last_mut().unwrap().0 += 1;
+
+            let item = self.db.item_at(current);
+            if item.tag == TAG_FOLDER && item.b > 0 {
+                self.stack.push((item.a, item.a + item.b));
+            }
+
+            let key = HashStrKey::from_hashes(&self.db.hashes, (item.path, item.language, item.extension));
+            if (self.matcher)(key) {
+                return Some(self.db.get_by_inode(current));
+            }
+        }
+    }
+}
+
+/// Maps each bdb file's `file_id` to the `(path, language, extension)` key
+/// `item_index` stores it under, resolving `lang_id` against `bdb.languages`
+/// along the way. Shared between [`from_bdb`] and
+/// [`Database::incremental_update`], which both need it to turn a package
+/// header's file ids back into item lookups without re-sorting anything.
+fn itemkeys_by_file_id(bdb: &bundledb_reader::BundleDbFile) -> FnvHashMap<u32, (u64, u64, u64)> {
+    let mut out = FnvHashMap::<u32, (u64, u64, u64)>::default();
+    out.reserve(bdb.files.len());
+    for bdbe in &bdb.files {
+        let le = match bdbe.lang_id {
+            0 => diesel_hash::EMPTY,
+            id => bdb.languages.iter().find(|i| i.id == id).unwrap().hash
+        };
+        out.insert(bdbe.file_id, (bdbe.path, le, bdbe.extension));
+    }
+    out
+}
+
+/// Folds each folder's direct files and child folders into a deduplicated,
+/// sorted set of package numbers and a recursive max `last_modified`, then
+/// flattens those sets into a single array the same way `file_packages`
+/// already flattens each file's backing packages - so [`DatabaseItem::packages_in_subtree`]
+/// and a folder's [`DatabaseItem::last_modified`] don't have to walk the
+/// subtree at query time.
+///
+/// Walks `items` in reverse index order: [`from_bdb`] sorts the item list
+/// breadth-first (shallower items first), so every item's children already
+/// sit at higher indices, and a reverse pass visits them - whether files or
+/// already-folded child folders - before their parent needs them. Writes
+/// `folder_packages_offset`/`folder_packages_count` and
+/// `folder_last_modified_*` directly onto the relevant items.
+fn compute_folder_aggregates(items: &mut [ItemRecord], file_packages: &[FileToPackage], packages: &[PackageRecord]) -> Vec<u32> {
+    let mut subtree_packages = vec![FnvHashSet::<u32>::default(); items.len()];
+    let mut subtree_last_modified = vec![SystemTime::UNIX_EPOCH; items.len()];
+
+    for idx in (0..items.len()).rev() {
+        let item = items[idx];
+        if item.tag == TAG_FILE {
+            for r in &file_packages[(item.a as usize)..((item.a + item.b) as usize)] {
+                subtree_packages[idx].insert(r.package_number);
+                let mtime = packages[r.package_number as usize].last_modified;
+                if mtime > subtree_last_modified[idx] { subtree_last_modified[idx] = mtime; }
+            }
+        }
+        else {
+            for child in item.a..(item.a + item.b) {
+                let child = child as usize;
+                // Cloned rather than drained: a child folder's own set is
+                // still needed later, when the flatten pass below reaches
+                // that child's own `ItemRecord`.
+                let child_packages = subtree_packages[child].clone();
+                subtree_packages[idx].extend(child_packages);
+                if subtree_last_modified[child] > subtree_last_modified[idx] {
+                    subtree_last_modified[idx] = subtree_last_modified[child];
+                }
+            }
+        }
+    }
+
+    let mut folder_packages = Vec::<u32>::new();
+    for (idx, item) in items.iter_mut().enumerate() {
+        if item.tag != TAG_FOLDER { continue; }
+
+        let mut sorted: Vec<u32> = subtree_packages[idx].iter().copied().collect();
+        sorted.sort_unstable();
+        item.folder_packages_offset = folder_packages.len() as u32;
+        item.folder_packages_count = sorted.len() as u32;
+        folder_packages.extend(sorted);
+
+        let since_epoch = subtree_last_modified[idx].duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        item.folder_last_modified_secs = since_epoch.as_secs();
+        item.folder_last_modified_nanos = since_epoch.subsec_nanos();
+    }
+    folder_packages
+}
+
 pub fn from_bdb<'a>(mut hashlist: HashIndex, bdb: &bundledb_reader::BundleDbFile, packages: &Vec<loader::ParsedBundle>) -> Database {
     println!("{:?} from_bdb() start", SystemTime::now());
     let mut items = Vec::<ItemRecord>::new();
-    let mut itemkeys_by_file_id = FnvHashMap::<u32, (u64, u64, u64)>::default();
+    let itemkeys_by_file_id = itemkeys_by_file_id(bdb);
     let mut folder_paths = FnvHashSet::<u64>::default();
     items.reserve(bdb.files.len());
-    itemkeys_by_file_id.reserve(bdb.files.len());
 
     for bdbe in &bdb.files {
-        let le = match bdbe.lang_id {
-            0 => diesel_hash::EMPTY,
-            id => bdb.languages.iter().find(|i| i.id == id).unwrap().hash
-        };
+        let &(_, le, _) = itemkeys_by_file_id.get(&bdbe.file_id).unwrap();
         items.push(ItemRecord {
             path: bdbe.path,
             language: le,
             extension: bdbe.extension,
-            specifics: ItemRecordSpecifics::File(FileRecord {
-                packages: Vec::new()
-            })
+            folder_last_modified_secs: 0,
+            tag: TAG_FILE,
+            a: 0,
+            b: 0,
+            folder_last_modified_nanos: 0,
+            folder_packages_offset: 0,
+            folder_packages_count: 0
         });
-        itemkeys_by_file_id.insert(bdbe.file_id, (bdbe.path, le, bdbe.extension));
 
         let hs = hashlist.get_hash(bdbe.path);
         let path = match hs.text {
@@ -381,11 +895,13 @@ pub fn from_bdb<'a>(mut hashlist: HashIndex, bdb: &bundledb_reader::BundleDbFile
             path: diesel_hash::EMPTY,
             language: diesel_hash::EMPTY,
             extension: diesel_hash::EMPTY,
-            specifics: ItemRecordSpecifics::Folder(FolderRecord {
-                //packages: Vec::new(),
-                first_child: 0,
-                child_count: 0
-            })
+            folder_last_modified_secs: 0,
+            tag: TAG_FOLDER,
+            a: 0,
+            b: 0,
+            folder_last_modified_nanos: 0,
+            folder_packages_offset: 0,
+            folder_packages_count: 0
         });
     }
 
@@ -394,11 +910,13 @@ pub fn from_bdb<'a>(mut hashlist: HashIndex, bdb: &bundledb_reader::BundleDbFile
             path: h,
             language: diesel_hash::EMPTY,
             extension: diesel_hash::EMPTY,
-            specifics: ItemRecordSpecifics::Folder(FolderRecord {
-                //packages: Vec::new(),
-                first_child: 0,
-                child_count: 0
-            })
+            folder_last_modified_secs: 0,
+            tag: TAG_FOLDER,
+            a: 0,
+            b: 0,
+            folder_last_modified_nanos: 0,
+            folder_packages_offset: 0,
+            folder_packages_count: 0
         });
     }
 
@@ -417,11 +935,14 @@ pub fn from_bdb<'a>(mut hashlist: HashIndex, bdb: &bundledb_reader::BundleDbFile
         }
     });
 
-    let mut item_index = FnvHashMap::<(u64, u64, u64), u32>::default();
+    // Built as a hashmap while walking the (breadth-first, not key-sorted)
+    // `items` list below, then converted to the sorted table `ItemStorage::Owned`
+    // actually stores once every item's final index is known.
+    let mut item_index_map = FnvHashMap::<(u64, u64, u64), u32>::default();
 
     // the list is now in breadth-first order
     // now we have to tell each folder where its children are.
-    // 
+    //
     // Breadth first order means that we see folders in the same order that we would
     // if we scanned each item and calculated its parent
 
@@ -431,14 +952,14 @@ pub fn from_bdb<'a>(mut hashlist: HashIndex, bdb: &bundledb_reader::BundleDbFile
     let mut current_folder_start = 1;
     let mut current_folder_len = 0;
 
-    item_index.insert((diesel_hash::EMPTY,diesel_hash::EMPTY,diesel_hash::EMPTY), 0);
+    item_index_map.insert((diesel_hash::EMPTY,diesel_hash::EMPTY,diesel_hash::EMPTY), 0);
 
     while current_item < items.len() {
-        
+
         let ci = items.get(current_item).unwrap();
         let ci_path_hs = hashlist.get_hash(ci.path);
 
-        item_index.insert((ci.path, ci.language, ci.extension), current_item.try_into().unwrap());
+        item_index_map.insert((ci.path, ci.language, ci.extension), current_item.try_into().unwrap());
 
         // it doesn't matter what the default is, but it has to be something.
         // with no slashes, since unhashed things end up in the root
@@ -451,14 +972,10 @@ pub fn from_bdb<'a>(mut hashlist: HashIndex, bdb: &bundledb_reader::BundleDbFile
             current_folder_len += 1;
         }
         else {
-            let cfs = &mut items.get_mut(current_folder).unwrap().specifics;
-            match cfs {
-                ItemRecordSpecifics::File(_) => panic!("Current folder is a file"),
-                ItemRecordSpecifics::Folder(f) => {
-                    f.first_child = current_folder_start;
-                    f.child_count = current_folder_len;
-                }
-            }
+            let cf = items.get_mut(current_folder).unwrap();
+            if cf.tag != TAG_FOLDER { panic!("Current folder is a file"); }
+            cf.a = current_folder_start;
+            cf.b = current_folder_len;
 
             current_folder_start = current_item.try_into().unwrap();
             current_folder_len = 1; // folders are only implied in the bdb, by paths having
@@ -469,7 +986,7 @@ pub fn from_bdb<'a>(mut hashlist: HashIndex, bdb: &bundledb_reader::BundleDbFile
             loop {
                 current_folder += 1;
                 let next_folder = items.get(current_folder).unwrap();
-                if let ItemRecordSpecifics::Folder(_) = next_folder.specifics {
+                if next_folder.tag == TAG_FOLDER {
                     current_folder_path = hashlist.get_hash(next_folder.path).text.unwrap();
                     break;
                 }
@@ -479,51 +996,82 @@ pub fn from_bdb<'a>(mut hashlist: HashIndex, bdb: &bundledb_reader::BundleDbFile
         current_item += 1;
     }
 
+    // The loop above only flushes a folder's `a`/`b` once it sees the next
+    // item that isn't one of its children - so the very last folder reached
+    // in breadth-first order, having nothing after it to trigger that, needs
+    // flushing here instead.
+    {
+        let cf = items.get_mut(current_folder).unwrap();
+        if cf.tag != TAG_FOLDER { panic!("Current folder is a file"); }
+        cf.a = current_folder_start;
+        cf.b = current_folder_len;
+    }
+
     // Now we need to line up the package entries with the items. There's probably a
     // much better way to do this.
+    //
+    // Files can appear in more than one package, so we first collect each file's
+    // references in a per-item scratch Vec, then flatten those into the single
+    // `file_packages` array `items[].a/b` index into - the shape [`load_cache`] reads
+    // straight off disk.
     let mut package_catalog = Vec::<PackageRecord>::new();
     package_catalog.reserve_exact(packages.len());
     let mut package_index = FnvHashMap::<u64, u32>::default();
     package_index.reserve(packages.len());
+    let mut packages_by_item = vec![Vec::<FileToPackage>::new(); items.len()];
 
     for pack in packages {
         let mut pr = PackageRecord {
             data_path: pack.data_path.to_owned(),
             last_modified: pack.last_modified,
+            device: pack.device,
+            inode: pack.inode,
+            data_len: pack.data_len,
             package_id: pack.package_id,
             files: Vec::new()
         };
 
         pr.files.reserve_exact(pack.header.entries.len());
-        
+
         for entry in &pack.header.entries {
             let fk = itemkeys_by_file_id.get(&entry.file_id).unwrap();
-            let fid = item_index.get(&fk).unwrap();
-            
+            let fid = *item_index_map.get(fk).unwrap();
+
             pr.files.push( PackageEntryRecord {
-                item_number: *fid,
+                item_number: fid,
                 offset: entry.offset,
                 length: entry.length
             });
-            
-            let item = items.get_mut(*fid as usize).unwrap();
-            match &mut item.specifics {
-                ItemRecordSpecifics::Folder(_) => panic!(),
-                ItemRecordSpecifics::File(fs) => fs.packages.push(FileToPackage {
-                    package_number: package_catalog.len().try_into().unwrap(),
-                    file_number: (pr.files.len() - 1).try_into().unwrap()
-                })
-            };
+
+            packages_by_item[fid as usize].push(FileToPackage {
+                package_number: package_catalog.len().try_into().unwrap(),
+                file_number: (pr.files.len() - 1).try_into().unwrap()
+            });
         }
         package_index.insert(pack.package_id, package_catalog.len().try_into().unwrap());
         package_catalog.push(pr);
     }
 
+    let mut file_packages = Vec::<FileToPackage>::new();
+    for (idx, refs) in packages_by_item.into_iter().enumerate() {
+        if refs.is_empty() { continue; }
+        let item = items.get_mut(idx).unwrap();
+        item.a = file_packages.len().try_into().unwrap();
+        item.b = refs.len().try_into().unwrap();
+        file_packages.extend(refs);
+    }
+
+    let mut item_index: Vec<IndexEntry> = item_index_map.into_iter()
+        .map(|((path, language, extension), item)| IndexEntry { path, language, extension, item, _pad: 0 })
+        .collect();
+    item_index.sort_by_key(|e| (e.path, e.language, e.extension));
+
+    let folder_packages = compute_folder_aggregates(&mut items, &file_packages, &package_catalog);
+
     println!("{:?} from_bdb() end", SystemTime::now());
     Database {
         hashes: Arc::new(hashlist),
-        item_index,
-        items,
+        storage: ItemStorage::Owned { item_index, items, file_packages, folder_packages },
         packages: package_catalog,
         package_index
     }
@@ -553,4 +1101,251 @@ pub fn print_record_sizes() {
     println!("    SortKey: {}", std::mem::size_of::<PathSortKey>());
     println!();
     println!("Vec<&str>: {}", std::mem::size_of::<Vec<&str>>());
-}
\ No newline at end of file
+}
+
+/// Magic number of a [`Database`] cache file written by [`Database::write_cache`].
+const CACHE_MAGIC: &[u8; 4] = b"PDB1";
+/// On-disk format version; bumped whenever a field in [`ItemRecord`],
+/// [`FileToPackage`], [`IndexEntry`], [`PackageOnDisk`] or [`PackageEntryRecord`]
+/// changes shape.
+const CACHE_VERSION: u8 = 3;
+/// Size in bytes of the fixed header: magic, version+padding, six section
+/// counts, a strings-blob length, and a CRC over everything that follows.
+/// Conveniently a multiple of 8 as it stands, which keeps the body (and
+/// every record array packed into it) 8-byte aligned, since [`PackageOnDisk`],
+/// [`ItemRecord`] and [`IndexEntry`] all contain `u64` fields and are
+/// reinterpreted out of the mmap with `bytemuck::cast_slice`, which requires
+/// correct alignment rather than just matching size. If a future field
+/// changes this sum off a multiple of 8, pad it back up explicitly rather
+/// than relying on the section lengths to cooperate.
+const CACHE_HEADER_SIZE: usize = 4 + 4 + 4*7 + 4;
+
+/// One `(path, language, extension) -> item` entry of the sorted key table a
+/// cache file stores in place of [`ItemStorage::Owned`]'s `FnvHashMap` -
+/// [`MappedItems::find_index`] binary-searches this instead of hashing.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct IndexEntry {
+    path: u64,
+    language: u64,
+    extension: u64,
+    item: u32,
+    _pad: u32
+}
+
+/// A [`PackageRecord`], minus its heap allocations: `data_path` becomes a
+/// byte range into the cache's trailing strings blob, and `files` becomes a
+/// range into the shared `package_entries` array.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct PackageOnDisk {
+    package_id: u64,
+    last_modified_secs: u64,
+    last_modified_nanos: u32,
+    data_path_offset: u32,
+    data_path_len: u32,
+    entries_offset: u32,
+    entries_count: u32,
+    /// 1 if this slot is a package `Database::incremental_update` still
+    /// considers live, 0 if it's a tombstone kept only so later slots' indices
+    /// (referenced by `FileToPackage::package_number`) don't shift.
+    live: u32,
+    device: u64,
+    inode: u64,
+    data_len: u64
+}
+
+/// The item list, file-to-package refs and sorted index of a [`Database`]
+/// loaded straight from a memory-mapped cache file, rather than rebuilt by
+/// [`from_bdb`]. Every accessor reslices `mmap` fresh rather than keeping
+/// borrowed slices around, the same way [`crate::hashindex::MappedHashIndex`]
+/// does, since a struct can't borrow from a sibling field it also owns.
+struct MappedItems {
+    mmap: Mmap,
+    item_count: usize,
+    file_package_count: usize,
+    index_count: usize,
+    folder_package_count: usize,
+    items_offset: usize,
+    file_packages_offset: usize,
+    index_offset: usize,
+    folder_packages_offset: usize
+}
+
+impl MappedItems {
+    fn item(&self, i: usize) -> ItemRecord {
+        let start = self.items_offset + i * std::mem::size_of::<ItemRecord>();
+        let bytes = &self.mmap[start..(start + std::mem::size_of::<ItemRecord>())];
+        bytemuck::pod_read_unaligned(bytes)
+    }
+
+    fn file_packages(&self) -> &[FileToPackage] {
+        let end = self.file_packages_offset + self.file_package_count * std::mem::size_of::<FileToPackage>();
+        bytemuck::cast_slice(&self.mmap[self.file_packages_offset..end])
+    }
+
+    fn index_entry(&self, i: usize) -> IndexEntry {
+        let start = self.index_offset + i * std::mem::size_of::<IndexEntry>();
+        let bytes = &self.mmap[start..(start + std::mem::size_of::<IndexEntry>())];
+        bytemuck::pod_read_unaligned(bytes)
+    }
+
+    fn folder_packages(&self) -> &[u32] {
+        let end = self.folder_packages_offset + self.folder_package_count * std::mem::size_of::<u32>();
+        bytemuck::cast_slice(&self.mmap[self.folder_packages_offset..end])
+    }
+
+    fn find_index(&self, key: (u64, u64, u64)) -> Option<u32> {
+        binary_search_index(self.index_count, key, |i| self.index_entry(i))
+    }
+}
+
+/// Rounds `n` up to the next multiple of 8, so a variable-length section
+/// whose own elements don't need 8-byte alignment (like the `u32`-only
+/// `folder_packages` table) doesn't leave the *next* section - which may
+/// contain `u64` fields - misaligned for `bytemuck::cast_slice`.
+fn round_up_8(n: usize) -> usize {
+    (n + 7) / 8 * 8
+}
+
+/// Binary-searches a `(path, language, extension) -> item` table sorted by
+/// that key, given `count` entries and a way to read entry `i` - shared
+/// between [`ItemStorage::Owned`]'s in-memory `Vec<IndexEntry>` and
+/// [`MappedItems`]'s byte-reinterpreted slice out of a cache's mmap.
+fn binary_search_index(count: usize, key: (u64, u64, u64), entry_at: impl Fn(usize) -> IndexEntry) -> Option<u32> {
+    let mut lo = 0usize;
+    let mut hi = count;
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let e = entry_at(mid);
+        let mid_key = (e.path, e.language, e.extension);
+        match mid_key.cmp(&key) {
+            Ordering::Equal => return Some(e.item),
+            Ordering::Less => lo = mid + 1,
+            Ordering::Greater => hi = mid
+        }
+    }
+    None
+}
+
+/// Loads a [`Database`] from a cache file written by [`Database::write_cache`],
+/// validating it against the live bundle files in `packages` first.
+///
+/// Mirrors [`crate::formats::scriptdata::binary::to_binary`]'s stance on
+/// giving up cleanly rather than trusting stale data: if the cache's magic,
+/// version, or per-package id/mtime don't match what's on disk right now,
+/// this returns an error instead of a `Database`, and the caller should fall
+/// back to [`from_bdb`] and write a fresh cache afterwards.
+pub fn load_cache(path: &Path, hashes: Arc<HashIndex>, packages: &[loader::ParsedBundle]) -> io::Result<Database> {
+    let file = File::open(path)?;
+    let mmap = unsafe { Mmap::map(&file)? };
+
+    if mmap.len() < CACHE_HEADER_SIZE || &mmap[0..4] != CACHE_MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a package database cache file"));
+    }
+    if mmap[4] != CACHE_VERSION {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Package database cache is a different format version"));
+    }
+
+    let u32_at = |offset: usize| u32::from_le_bytes(mmap[offset..offset+4].try_into().unwrap());
+    let item_count = u32_at(8) as usize;
+    let file_package_count = u32_at(12) as usize;
+    let index_count = u32_at(16) as usize;
+    let package_count = u32_at(20) as usize;
+    let package_entry_count = u32_at(24) as usize;
+    let strings_len = u32_at(28) as usize;
+    let folder_package_count = u32_at(32) as usize;
+    let expected_crc = u32_at(36);
+
+    let items_offset = CACHE_HEADER_SIZE;
+    let file_packages_offset = items_offset + item_count * std::mem::size_of::<ItemRecord>();
+    let index_offset = file_packages_offset + file_package_count * std::mem::size_of::<FileToPackage>();
+    let folder_packages_offset = index_offset + index_count * std::mem::size_of::<IndexEntry>();
+    let packages_offset = folder_packages_offset + round_up_8(folder_package_count * std::mem::size_of::<u32>());
+    let package_entries_offset = packages_offset + package_count * std::mem::size_of::<PackageOnDisk>();
+    let strings_offset = package_entries_offset + package_entry_count * std::mem::size_of::<PackageEntryRecord>();
+    let end = strings_offset + strings_len;
+
+    let body = mmap.get(CACHE_HEADER_SIZE..end)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "Package database cache truncated"))?;
+    if crc32c::crc32c(body) != expected_crc {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Package database cache checksum mismatch - truncated or corrupt file"));
+    }
+
+    let packages_bytes = &mmap[packages_offset..package_entries_offset];
+    let on_disk_packages: &[PackageOnDisk] = bytemuck::cast_slice(packages_bytes);
+    let strings = &mmap[strings_offset..end];
+    let package_entries_bytes = &mmap[package_entries_offset..strings_offset];
+    let all_package_entries: &[PackageEntryRecord] = bytemuck::cast_slice(package_entries_bytes);
+
+    // Live packages are matched by id, not position - `Database::incremental_update`
+    // leaves tombstoned slots in place so `FileToPackage::package_number` stays
+    // valid, and nothing guarantees the live scan revisits packages in the
+    // same directory order the cache was written in anyway.
+    let live_by_id: FnvHashMap<u64, &PackageOnDisk> = on_disk_packages.iter()
+        .filter(|p| p.live != 0)
+        .map(|p| (p.package_id, p))
+        .collect();
+
+    if live_by_id.len() != packages.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Package database cache doesn't match the current set of packages"));
+    }
+
+    for live in packages {
+        let cached = live_by_id.get(&live.package_id)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Package database cache doesn't match the current set of packages"))?;
+        let cached_mtime = SystemTime::UNIX_EPOCH + std::time::Duration::new(cached.last_modified_secs, cached.last_modified_nanos);
+        // Compare identity the same way `Database::incremental_update` does -
+        // mtime alone can't tell a repack that lands on the same second from
+        // a genuinely unchanged file, but (device, inode, length) can.
+        if cached_mtime != live.last_modified || cached.device != live.device || cached.inode != live.inode || cached.data_len != live.data_len {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "A package is newer than the package database cache"));
+        }
+    }
+
+    // Every slot - live or tombstoned - gets reconstructed at its original
+    // index, so `package_catalog`'s indices keep matching the
+    // `FileToPackage::package_number`s already baked into `items`/`file_packages`.
+    let mut package_catalog = Vec::<PackageRecord>::with_capacity(on_disk_packages.len());
+    let mut package_index = FnvHashMap::<u64, u32>::default();
+    package_index.reserve(live_by_id.len());
+
+    for (number, cached) in on_disk_packages.iter().enumerate() {
+        let path_bytes = &strings[(cached.data_path_offset as usize)..((cached.data_path_offset + cached.data_path_len) as usize)];
+        let data_path = PathBuf::from(String::from_utf8_lossy(path_bytes).into_owned());
+        let files = all_package_entries[(cached.entries_offset as usize)..((cached.entries_offset + cached.entries_count) as usize)].to_vec();
+        let cached_mtime = SystemTime::UNIX_EPOCH + std::time::Duration::new(cached.last_modified_secs, cached.last_modified_nanos);
+
+        if cached.live != 0 {
+            package_index.insert(cached.package_id, number as u32);
+        }
+        package_catalog.push(PackageRecord {
+            package_id: cached.package_id,
+            data_path,
+            last_modified: cached_mtime,
+            device: cached.device,
+            inode: cached.inode,
+            data_len: cached.data_len,
+            files
+        });
+    }
+
+    let storage = ItemStorage::Mapped(MappedItems {
+        mmap,
+        item_count,
+        file_package_count,
+        index_count,
+        folder_package_count,
+        items_offset,
+        file_packages_offset,
+        index_offset,
+        folder_packages_offset
+    });
+
+    Ok(Database {
+        hashes,
+        storage,
+        package_index,
+        packages: package_catalog
+    })
+}
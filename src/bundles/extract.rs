@@ -0,0 +1,74 @@
+//! Concurrently reads the byte ranges [`Database::filter_key_sort_physical`]
+//! plans out, bounding how many bundle files are open (and how much disk
+//! bandwidth is contended for) at once - the same tradeoff DataFusion makes
+//! when it bounds how many partitions it lists/reads in parallel.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use rayon::prelude::*;
+
+use super::database::{HashStrKey, ReadItem};
+
+/// Builds the `path.language.extension` virtual path convention the rest of
+/// `bundles` uses (see e.g. `filesystem::raw_bundledb::key_to_name`) over a
+/// key's whole path rather than just its last segment, so callers writing
+/// files straight out of [`extract_parallel`] get the directory structure
+/// back too.
+pub fn key_to_path(key: &HashStrKey) -> String {
+    let path = format!("{}", key.path);
+    let lang = format!("{}", key.language);
+    let extn = format!("{}", key.extension);
+
+    let basename = path.rsplit('/').next().unwrap();
+    let hasdot = basename.contains('.');
+
+    let mut name = path;
+    if lang.len() > 0 || hasdot {
+        name += ".";
+        name += &lang;
+    }
+    if extn.len() > 0 || hasdot {
+        name += ".";
+        name += &extn;
+    }
+    name
+}
+
+/// Reads every item in `plan` using a pool of at most `worker_count` threads,
+/// handing each worker whole packages as its unit of work so it opens one
+/// `data_path` and streams its already offset-sorted items in one sequential
+/// pass rather than seeking back and forth. Extracted bytes are handed to
+/// `sink` as each item finishes - there's no guaranteed order across packages,
+/// since they're worked on concurrently.
+///
+/// `worker_count` of `0` uses [`std::thread::available_parallelism`].
+pub fn extract_parallel<'a>(
+    plan: Vec<(&'a Path, Vec<ReadItem<'a>>)>,
+    worker_count: usize,
+    sink: impl Fn(HashStrKey<'a>, Vec<u8>) + Send + Sync
+) -> io::Result<()> {
+    let worker_count = match worker_count {
+        0 => std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        n => n
+    };
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(worker_count)
+        .build()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+    pool.install(|| {
+        plan.into_par_iter().try_for_each(|(path, items)| -> io::Result<()> {
+            let mut bundle = File::open(path)?;
+            for item in items {
+                let mut bytes = vec![0u8; item.length];
+                bundle.seek(SeekFrom::Start(item.offset as u64))?;
+                bundle.read_exact(&mut bytes)?;
+                sink(item.key, bytes);
+            }
+            Ok(())
+        })
+    })
+}
@@ -0,0 +1,124 @@
+//! A minimal path glob matcher - `*`/`?` within one path segment, `**` to
+//! span any number of segments - plus [`KeyMatcher`], which bundles a
+//! [`PathGlob`] with optional exact language/extension-set constraints so
+//! [`super::database::Database::glob`] and
+//! [`super::database::Database::filter_key_sort_physical`] can both be
+//! driven by the same compiled pattern instead of a fresh ad hoc closure per
+//! call site. No existing crate in this tree does glob matching, and this
+//! repo otherwise hand-rolls its small parsing/matching needs (see
+//! `diesel_hash`, `hashlist_scan::combinator`) rather than pulling one in
+//! for a single feature.
+
+use fnv::FnvHashSet;
+
+use crate::diesel_hash;
+
+use super::database::HashStrKey;
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Exact(String),
+    Wild(String),
+    Recursive
+}
+
+/// A compiled `/`-separated glob pattern: `**` matches zero or more whole
+/// segments, `*`/`?` match within a single segment, anything else must match
+/// that segment exactly.
+#[derive(Debug, Clone)]
+pub struct PathGlob {
+    segments: Vec<Segment>
+}
+
+impl PathGlob {
+    pub fn compile(pattern: &str) -> PathGlob {
+        let segments = pattern.split('/').map(|s| {
+            if s == "**" { Segment::Recursive }
+            else if s.contains('*') || s.contains('?') { Segment::Wild(s.to_owned()) }
+            else { Segment::Exact(s.to_owned()) }
+        }).collect();
+        PathGlob { segments }
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let components: Vec<&str> = if path.is_empty() { Vec::new() } else { path.split('/').collect() };
+        match_segments(&self.segments, &components)
+    }
+}
+
+fn match_segments(pattern: &[Segment], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((Segment::Recursive, rest)) => {
+            // `**` can absorb any number of components - try every split point
+            // rather than trying to be clever, since paths here are never deep
+            // enough for that to matter.
+            (0..=path.len()).any(|i| match_segments(rest, &path[i..]))
+        }
+        Some((seg, rest)) => match path.split_first() {
+            None => false,
+            Some((head, tail)) => segment_matches(seg, head) && match_segments(rest, tail)
+        }
+    }
+}
+
+fn segment_matches(seg: &Segment, text: &str) -> bool {
+    match seg {
+        Segment::Exact(s) => s == text,
+        Segment::Wild(pattern) => wildcard_matches(pattern.as_bytes(), text.as_bytes()),
+        Segment::Recursive => unreachable!("a Recursive segment is consumed by match_segments before reaching here")
+    }
+}
+
+/// Classic `*`/`?` matching within one path segment: `*` matches any run of
+/// characters (including none), `?` matches exactly one.
+fn wildcard_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match (pattern.first(), text.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => wildcard_matches(&pattern[1..], text) || (!text.is_empty() && wildcard_matches(pattern, &text[1..])),
+        (Some(b'?'), Some(_)) => wildcard_matches(&pattern[1..], &text[1..]),
+        (Some(&p), Some(&t)) if p == t => wildcard_matches(&pattern[1..], &text[1..]),
+        _ => false
+    }
+}
+
+/// A compiled filter over a [`HashStrKey`]: a path glob plus optional exact
+/// language/extension constraints. The matcher-backed counterpart to a bare
+/// `Fn(HashStrKey) -> bool` closure, passed to
+/// [`super::database::Database::glob`] or
+/// [`super::database::Database::filter_key_sort_physical`] as
+/// `|key| matcher.matches(key)`.
+pub struct KeyMatcher {
+    path: PathGlob,
+    language: Option<u64>,
+    extensions: Option<FnvHashSet<u64>>
+}
+
+impl KeyMatcher {
+    pub fn new(path_pattern: &str) -> KeyMatcher {
+        KeyMatcher { path: PathGlob::compile(path_pattern), language: None, extensions: None }
+    }
+
+    pub fn with_language(mut self, language: &str) -> KeyMatcher {
+        self.language = Some(diesel_hash::hash_str(language));
+        self
+    }
+
+    pub fn with_extensions(mut self, extensions: impl IntoIterator<Item = String>) -> KeyMatcher {
+        self.extensions = Some(extensions.into_iter().map(|e| diesel_hash::hash_str(&e)).collect());
+        self
+    }
+
+    pub fn matches(&self, key: HashStrKey) -> bool {
+        if let Some(language) = self.language {
+            if key.language.hash != language { return false; }
+        }
+        if let Some(extensions) = &self.extensions {
+            if !extensions.contains(&key.extension.hash) { return false; }
+        }
+        match key.path.text {
+            Some(text) => self.path.matches(text),
+            None => false
+        }
+    }
+}
@@ -7,66 +7,217 @@ use std::path::PathBuf;
 use std::time::SystemTime;
 
 use crate::diesel_hash;
+use crate::util::read_helpers::*;
 
 use super::bundledb_reader;
 use super::packageheader_reader;
 use super::ReadError;
 
-pub fn load_bundle_dir(dir: &Path) -> Result<(bundledb_reader::BundleDbFile, Vec<ParsedBundle>), ReadError> {
+/// Parses every `*.bundle`/`*_h.bundle` pair in `dir`, consulting (and then
+/// refreshing) the on-disk header cache this writes to `dir` as
+/// `bundle_headers.cache` when `use_cache` is true - see
+/// [`read_bundle_header_cache`]/[`write_bundle_header_cache`]. Pass `false`
+/// for a guaranteed cold read: a first run against a fresh install, or
+/// anywhere a caller suspects the cache itself might be the problem.
+pub fn load_bundle_dir(dir: &Path, use_cache: bool) -> Result<(bundledb_reader::BundleDbFile, Vec<ParsedBundle>), ReadError> {
     let bdb_path = dir.join("bundle_db.blb");
     let bdb_data = fs::read(bdb_path)?;
-    let bdb = bundledb_reader::read_bundle_db(&bdb_data);
+    let (bdb, _bdb_format) = bundledb_reader::read_bundle_db(&bdb_data);
 
     let bundle_paths = collect_bundle_files(&dir)?;
 
+    let cache_path = dir.join(BUNDLE_HEADER_CACHE_NAME);
+    let old_cache = if use_cache { read_bundle_header_cache(&cache_path) } else { HashMap::new() };
+
     let mut multi_headers = HashMap::<PathBuf, packageheader_reader::MultiBundleHeader>::new();
     let mut bundle_headers = Vec::<ParsedBundle>::new();
-    
+    let mut new_cache = HashMap::<PathBuf, CachedBundleHeader>::with_capacity(bundle_paths.len());
+
     for fi in bundle_paths {
         let data_stat = fs::metadata(&fi.data_path)?;
         let header_stat = fs::metadata(&fi.header_path)?;
-        
+
         let data_mtime = data_stat.modified()?;
         let header_mtime = header_stat.modified()?;
         let last_modified = if data_mtime > header_mtime { data_mtime } else { header_mtime };
+        let (device, inode) = file_identity(&data_stat);
 
-        let header: packageheader_reader::PackageHeaderFile;
-
-        match fi.multi_header_index {
-            None => {
-                let bundle_bytes = fs::read(&fi.header_path)?;
-                header = packageheader_reader::read_normal(&bundle_bytes, data_stat.len())?;
-            },
-            Some(idx) => {
-                if !multi_headers.contains_key(&fi.header_path) {
-                    let headers_bytes = fs::read(&fi.header_path)?;
-                    let multi_header = packageheader_reader::read_multi(&headers_bytes)?;
-                    multi_headers.insert(PathBuf::from(&fi.header_path), multi_header);
-                }
-                let mh = multi_headers.get(&fi.header_path).unwrap();
-                let header_maybe = mh.bundles.get(&idx);
-                match header_maybe {
-                    None => return Err(ReadError::BadMultiBundleHeader),
-                    Some(h) => header = h.clone()
+        let cached = old_cache.get(&fi.data_path).filter(|c| {
+            c.header_mtime == header_mtime && c.header_len == header_stat.len()
+                && c.data_mtime == data_mtime && c.data_len == data_stat.len()
+        });
+
+        let header = match cached {
+            Some(c) => packageheader_reader::PackageHeaderFile { entries: c.entries.clone() },
+            None => match fi.multi_header_index {
+                None => {
+                    let bundle_bytes = fs::read(&fi.header_path)?;
+                    packageheader_reader::read_normal(&bundle_bytes, data_stat.len())?
+                },
+                Some(idx) => {
+                    if !multi_headers.contains_key(&fi.header_path) {
+                        let headers_bytes = fs::read(&fi.header_path)?;
+                        let multi_header = packageheader_reader::read_multi(&headers_bytes)?;
+                        multi_headers.insert(PathBuf::from(&fi.header_path), multi_header);
+                    }
+                    let mh = multi_headers.get(&fi.header_path).unwrap();
+                    match mh.bundles.get(&idx) {
+                        None => return Err(ReadError::BadMultiBundleHeader),
+                        Some(h) => h.clone()
+                    }
                 }
             }
-        }
+        };
+
+        new_cache.insert(fi.data_path.clone(), CachedBundleHeader {
+            header_mtime,
+            header_len: header_stat.len(),
+            data_mtime,
+            data_len: data_stat.len(),
+            entries: header.entries.clone()
+        });
 
         bundle_headers.push(ParsedBundle {
             data_path: fi.data_path,
             last_modified,
             package_id: fi.package_id,
+            device,
+            inode,
+            data_len: data_stat.len(),
             header
         });
     }
 
+    if use_cache {
+        if let Err(e) = write_bundle_header_cache(&cache_path, &new_cache) {
+            println!("Couldn't write bundle header cache: {}", e);
+        }
+    }
+
     return Ok((bdb, bundle_headers));
 }
 
+const BUNDLE_HEADER_CACHE_NAME: &str = "bundle_headers.cache";
+const BUNDLE_HEADER_CACHE_MAGIC: &[u8; 4] = b"PHC1";
+
+/// One [`load_bundle_dir`] entry's already-parsed header, keyed by
+/// `data_path` in the cache so a rescan can skip re-reading and re-parsing
+/// `header_path` entirely once its mtime and length (and the data file's)
+/// still match what was cached - see [`read_bundle_header_cache`].
+struct CachedBundleHeader {
+    header_mtime: SystemTime,
+    header_len: u64,
+    data_mtime: SystemTime,
+    data_len: u64,
+    entries: Vec<packageheader_reader::PackageHeaderEntry>
+}
+
+fn write_time(out: &mut Vec<u8>, t: SystemTime) {
+    let since_epoch = t.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+    out.extend_from_slice(&since_epoch.as_secs().to_le_bytes());
+    out.extend_from_slice(&since_epoch.subsec_nanos().to_le_bytes());
+}
+
+fn read_time(data: &[u8], pos: usize) -> SystemTime {
+    SystemTime::UNIX_EPOCH + std::time::Duration::new(read_u64_le(data, pos), read_u32_le(data, pos + 8))
+}
+
+/// Writes `entries` back out to `path` as a fresh cache, dropping any row
+/// for a bundle that's disappeared from the directory since the last run -
+/// `entries` only ever has rows for bundles [`load_bundle_dir`] just saw.
+fn write_bundle_header_cache(path: &Path, entries: &HashMap<PathBuf, CachedBundleHeader>) -> std::io::Result<()> {
+    let mut body = Vec::<u8>::new();
+    body.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    for (data_path, cached) in entries {
+        let path_bytes = data_path.to_string_lossy().into_owned().into_bytes();
+        body.extend_from_slice(&(path_bytes.len() as u32).to_le_bytes());
+        body.extend_from_slice(&path_bytes);
+        write_time(&mut body, cached.header_mtime);
+        body.extend_from_slice(&cached.header_len.to_le_bytes());
+        write_time(&mut body, cached.data_mtime);
+        body.extend_from_slice(&cached.data_len.to_le_bytes());
+        body.extend_from_slice(&(cached.entries.len() as u32).to_le_bytes());
+        for entry in &cached.entries {
+            body.extend_from_slice(bytemuck::bytes_of(entry));
+        }
+    }
+
+    let mut out = Vec::<u8>::with_capacity(8 + body.len());
+    out.extend_from_slice(BUNDLE_HEADER_CACHE_MAGIC);
+    out.extend_from_slice(&crc32c::crc32c(&body).to_le_bytes());
+    out.extend_from_slice(&body);
+    fs::write(path, out)
+}
+
+/// Reads back a cache [`write_bundle_header_cache`] wrote, or an empty map
+/// if it's missing, truncated, or fails its checksum - any of which just
+/// means every bundle gets re-parsed this run, same as a cold read.
+fn read_bundle_header_cache(path: &Path) -> HashMap<PathBuf, CachedBundleHeader> {
+    match fs::read(path) {
+        Ok(data) => parse_bundle_header_cache(&data).unwrap_or_default(),
+        Err(_) => HashMap::new()
+    }
+}
+
+fn parse_bundle_header_cache(data: &[u8]) -> Option<HashMap<PathBuf, CachedBundleHeader>> {
+    if data.len() < 8 || &data[0..4] != BUNDLE_HEADER_CACHE_MAGIC {
+        return None;
+    }
+    let stored_crc = read_u32_le(data, 4);
+    let body = &data[8..];
+    if crc32c::crc32c(body) != stored_crc {
+        return None;
+    }
+
+    let mut pos = 0usize;
+    let count = read_u32_le(body, pos); pos += 4;
+    let mut result = HashMap::with_capacity(count as usize);
+    for _ in 0..count {
+        let path_len = read_u32_le(body, pos) as usize; pos += 4;
+        let data_path = PathBuf::from(std::str::from_utf8(&body[pos..pos + path_len]).ok()?);
+        pos += path_len;
+
+        let header_mtime = read_time(body, pos); pos += 12;
+        let header_len = read_u64_le(body, pos); pos += 8;
+        let data_mtime = read_time(body, pos); pos += 12;
+        let data_len = read_u64_le(body, pos); pos += 8;
+
+        let entry_count = read_u32_le(body, pos) as usize; pos += 4;
+        let mut entries = Vec::with_capacity(entry_count);
+        for _ in 0..entry_count {
+            entries.push(bytemuck::pod_read_unaligned::<packageheader_reader::PackageHeaderEntry>(&body[pos..pos + 12]));
+            pos += 12;
+        }
+
+        result.insert(data_path.clone(), CachedBundleHeader { header_mtime, header_len, data_mtime, data_len, entries });
+    }
+    Some(result)
+}
+
+/// The `(device, inode)` pair a package's data file was found at, so a
+/// rescan can tell "still the same file" from "a different file that
+/// happens to share a name and size" even when the mtime alone doesn't
+/// change fast enough to notice (see `bundles::database`'s incremental cache
+/// update, which follows Mercurial's dirstate-v2 lead here).
+#[cfg(unix)]
+fn file_identity(meta: &std::fs::Metadata) -> (u64, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (meta.dev(), meta.ino())
+}
+
+#[cfg(windows)]
+fn file_identity(meta: &std::fs::Metadata) -> (u64, u64) {
+    use std::os::windows::fs::MetadataExt;
+    (meta.volume_serial_number().unwrap_or(0) as u64, meta.file_index().unwrap_or(0))
+}
+
 pub struct ParsedBundle {
     pub data_path: PathBuf,
     pub last_modified: SystemTime,
     pub package_id: u64,
+    pub device: u64,
+    pub inode: u64,
+    pub data_len: u64,
     pub header: packageheader_reader::PackageHeaderFile
 }
 
@@ -0,0 +1,200 @@
+//! Extraction manifest for the `extract-manifest` subcommand, written in the
+//! line-oriented [mtree(5)](https://man.freebsd.org/cgi/man.cgi?mtree(5))
+//! spec format: blank lines and `#`-comments are ignored, a `/set key=value
+//! ...` line establishes defaults for the entries that follow, each entry is
+//! a path component plus whitespace-separated `keyword=value` pairs, and a
+//! bare `..` line pops back up to the parent directory. Nesting the entries
+//! this way (rather than writing one full path per line) keeps the file
+//! readable and makes the directory structure part of the format itself.
+//!
+//! Every file entry also carries a crate-specific `dieselhash=<16 hex>` of
+//! the un-hashed asset path it was read under - `sha256digest` alone can
+//! prove the bytes weren't corrupted, but only the Diesel hash says which
+//! asset they actually are.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+/// One file written by `extract-manifest`: its virtual path (the same
+/// `path.language.extension` form [`super::database`]'s own callers build),
+/// byte length, SHA-256 digest and Diesel path hash.
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256: [u8; 32],
+    pub diesel_hash: u64,
+}
+
+impl ManifestEntry {
+    pub fn new(path: String, bytes: &[u8], diesel_hash: u64) -> ManifestEntry {
+        ManifestEntry {
+            path,
+            size: bytes.len() as u64,
+            sha256: Sha256::digest(bytes).into(),
+            diesel_hash,
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// A file's record as read back out of a manifest by [`read_manifest`]: its
+/// full virtual path (reconstructed from the `/`-nesting) alongside the
+/// `size`/`sha256digest`/`dieselhash` keywords [`write_manifest`] wrote for
+/// it.
+pub struct VerifyEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256digest: String,
+    pub diesel_hash: u64,
+}
+
+/// One directory level of the tree [`write_manifest`] builds up before
+/// serializing it, so siblings can be written in sorted order and a
+/// subdirectory's entries all land between its opening line and the `..`
+/// that closes it.
+#[derive(Default)]
+struct DirNode {
+    dirs: BTreeMap<String, DirNode>,
+    files: BTreeMap<String, (u64, [u8; 32], u64)>,
+}
+
+impl DirNode {
+    fn insert(&mut self, components: &[&str], size: u64, sha256: [u8; 32], diesel_hash: u64) {
+        match components {
+            [] => (),
+            [name] => { self.files.insert((*name).to_owned(), (size, sha256, diesel_hash)); },
+            [dir, rest @ ..] => {
+                self.dirs.entry((*dir).to_owned()).or_default().insert(rest, size, sha256, diesel_hash);
+            }
+        }
+    }
+
+    fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        for (name, node) in &self.dirs {
+            writeln!(out, "{} type=dir", name)?;
+            node.write(out)?;
+            writeln!(out, "..")?;
+        }
+        for (name, (size, sha256, diesel_hash)) in &self.files {
+            writeln!(out, "{} size={} sha256digest={} dieselhash={:016x}", name, size, to_hex(sha256), diesel_hash)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes `entries` to `path` as an mtree manifest, one line per path
+/// component nested under its parent directory. `entries` don't need to
+/// already be sorted; [`DirNode`] re-sorts everything by name on the way in
+/// so the output is deterministic regardless of extraction order.
+pub fn write_manifest(path: &Path, entries: &[ManifestEntry]) -> io::Result<()> {
+    let mut root = DirNode::default();
+    for entry in entries {
+        let components: Vec<&str> = entry.path.split('/').collect();
+        root.insert(&components, entry.size, entry.sha256, entry.diesel_hash);
+    }
+
+    let mut out = Vec::new();
+    writeln!(out, "#mtree")?;
+    writeln!(out, "/set type=file")?;
+    root.write(&mut out)?;
+    fs::write(path, out)
+}
+
+/// Reads a manifest written by [`write_manifest`] back into one
+/// [`VerifyEntry`] per file, reconstructing each one's full virtual path
+/// from the `..`-delimited nesting.
+pub fn read_manifest(path: &Path) -> io::Result<Vec<VerifyEntry>> {
+    let text = fs::read_to_string(path)?;
+    let mut defaults: BTreeMap<String, String> = BTreeMap::new();
+    let mut stack: Vec<String> = Vec::new();
+    let mut out = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue; }
+
+        if let Some(rest) = line.strip_prefix("/set") {
+            for kv in rest.split_whitespace() {
+                if let Some((k, v)) = kv.split_once('=') {
+                    defaults.insert(k.to_owned(), v.to_owned());
+                }
+            }
+            continue;
+        }
+
+        if line == ".." {
+            stack.pop();
+            continue;
+        }
+
+        let mut tokens = line.split_whitespace();
+        let name = match tokens.next() {
+            Some(n) => n,
+            None => continue
+        };
+        let mut kv: BTreeMap<String, String> = defaults.clone();
+        for pair in tokens {
+            if let Some((k, v)) = pair.split_once('=') {
+                kv.insert(k.to_owned(), v.to_owned());
+            }
+        }
+
+        if kv.get("type").map(String::as_str) == Some("dir") {
+            stack.push(name.to_owned());
+            continue;
+        }
+
+        let mut full_path = stack.clone();
+        full_path.push(name.to_owned());
+        let size = kv.get("size").and_then(|s| s.parse().ok()).unwrap_or(0);
+        let sha256digest = kv.get("sha256digest").cloned().unwrap_or_default();
+        let diesel_hash = kv.get("dieselhash").and_then(|s| u64::from_str_radix(s, 16).ok()).unwrap_or(0);
+        out.push(VerifyEntry { path: full_path.join("/"), size, sha256digest, diesel_hash });
+    }
+
+    Ok(out)
+}
+
+/// One discrepancy [`verify`] found between a manifest and what's actually
+/// on disk.
+#[derive(Debug)]
+pub enum VerifyDiff {
+    Missing(String),
+    SizeMismatch(String),
+    DigestMismatch(String),
+}
+
+/// Re-reads every file `entries` names under `out_dir` and reports anything
+/// that doesn't match: missing files, a size that disagrees with the
+/// manifest (cheap, checked first), or one whose SHA-256 digest doesn't
+/// match despite matching size (expensive, so only computed when needed).
+pub fn verify(entries: &[VerifyEntry], out_dir: &Path) -> Vec<VerifyDiff> {
+    let mut diffs = Vec::new();
+
+    for entry in entries {
+        let on_disk = out_dir.join(&entry.path);
+        let bytes = match fs::read(&on_disk) {
+            Ok(b) => b,
+            Err(_) => { diffs.push(VerifyDiff::Missing(entry.path.clone())); continue; }
+        };
+
+        if bytes.len() as u64 != entry.size {
+            diffs.push(VerifyDiff::SizeMismatch(entry.path.clone()));
+            continue;
+        }
+
+        let digest = to_hex(&Sha256::digest(&bytes));
+        if digest != entry.sha256digest {
+            diffs.push(VerifyDiff::DigestMismatch(entry.path.clone()));
+        }
+    }
+
+    diffs
+}
@@ -1,7 +1,13 @@
 pub mod bundledb_reader;
 pub mod packageheader_reader;
 pub mod database;
+pub mod extract;
+pub mod glob;
 pub mod loader;
+pub mod manifest;
+pub mod search;
+pub mod stats;
+pub mod verify;
 
 #[derive(Debug)]
 pub enum ReadError {
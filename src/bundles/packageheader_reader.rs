@@ -1,6 +1,9 @@
 use std::convert::TryInto;
 use std::convert::TryFrom;
 use std::collections::HashMap;
+
+use bytemuck::{Pod, Zeroable};
+
 use crate::util::read_helpers::*;
 use super::ReadError;
 
@@ -9,17 +12,84 @@ pub struct PackageHeaderFile {
     pub entries: Vec<PackageHeaderEntry>
 }
 
-#[derive(Debug, Copy, Clone)]
+/// One entry's wire row in the has-length layout `read_normal`/`read_multi`
+/// both use, and the only layout [`PackageHeaderFile::to_bytes`] writes back
+/// out - see [`PackageHeaderEntryShort`] for the no-length variant. Read
+/// straight out of a byte range via [`read_record`].
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
 pub struct PackageHeaderEntry {
     pub file_id: u32,
     pub offset: u32,
     pub length: u32
 }
 
+/// The no-length wire row `read_normal` falls back to when a header's
+/// has-length word comparison comes up false: just `file_id`/`offset`, with
+/// `length` backfilled from the next entry's `offset` (and the last entry's
+/// from `datafile_length`).
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+struct PackageHeaderEntryShort {
+    file_id: u32,
+    offset: u32
+}
+
+/// One bundle's 28-byte header record inside a [`MultiBundleHeader`] - see
+/// `read_multi`/`MultiBundleHeader::to_bytes`. The two 64-bit fields are
+/// split into `_lo`/`_hi` halves rather than declared as plain `u64`s,
+/// since at 28 bytes this record isn't a multiple of 8 and an all-`u32`
+/// layout is the only way to keep it padding-free for `Pod`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+struct MultiBundleEntryHeader {
+    bundle_index_lo: u32,
+    bundle_index_hi: u32,
+    entry_count_1: u32,
+    entry_count_2: u32,
+    offset_lo: u32,
+    offset_hi: u32,
+    always_one: u32
+}
+
+impl MultiBundleEntryHeader {
+    fn bundle_index(&self) -> u64 {
+        (self.bundle_index_lo as u64) | ((self.bundle_index_hi as u64) << 32)
+    }
+
+    fn offset(&self) -> u64 {
+        (self.offset_lo as u64) | ((self.offset_hi as u64) << 32)
+    }
+
+    fn new(bundle_index: u64, entry_count: u32, offset: u64) -> MultiBundleEntryHeader {
+        MultiBundleEntryHeader {
+            bundle_index_lo: bundle_index as u32,
+            bundle_index_hi: (bundle_index >> 32) as u32,
+            entry_count_1: entry_count,
+            entry_count_2: entry_count,
+            offset_lo: offset as u32,
+            offset_hi: (offset >> 32) as u32,
+            always_one: 1
+        }
+    }
+}
+
 pub struct MultiBundleHeader {
     pub bundles: HashMap<u64, PackageHeaderFile>
 }
 
+/// Casts the `size_of::<T>()` bytes at `offset` into a `T`, bounds-checked
+/// so a truncated `data` returns [`ReadError::UnknownFormatOrMalformed`]
+/// instead of panicking. Unaligned (`pod_read_unaligned`) rather than a
+/// `cast_slice` view, since none of this format's record offsets (8-, 12-
+/// and 28-byte strides) are guaranteed 8-byte aligned the way
+/// [`super::database`]'s cache records are.
+fn read_record<T: Pod>(data: &[u8], offset: usize) -> Result<T, ReadError> {
+    let len = std::mem::size_of::<T>();
+    let bytes = data.get(offset..offset + len).ok_or(ReadError::UnknownFormatOrMalformed)?;
+    Ok(bytemuck::pod_read_unaligned(bytes))
+}
+
 pub fn read_normal(data: &[u8], datafile_length: u64) -> Result<PackageHeaderFile, ReadError> {
     let mut res = PackageHeaderFile {
         entries: Vec::new()
@@ -57,7 +127,7 @@ pub fn read_normal(data: &[u8], datafile_length: u64) -> Result<PackageHeaderFil
     else {
         return Err(ReadError::UnknownFormatOrMalformed);
     }
-    
+
     let actual_offset : usize = if offset == 0 {
         ref_offset.try_into().unwrap()
     } else {
@@ -67,20 +137,17 @@ pub fn read_normal(data: &[u8], datafile_length: u64) -> Result<PackageHeaderFil
     if has_length {
         for i in 0..item_count {
             let offs : usize = actual_offset + usize::try_from(i).unwrap() * 12;
-            res.entries.push(PackageHeaderEntry {
-                file_id: read_u32_le(data, offs+0),
-                offset: read_u32_le(data, offs+4),
-                length: read_u32_le(data, offs+8)
-            });
+            res.entries.push(read_record::<PackageHeaderEntry>(data, offs)?);
         }
     }
     else {
         for i in 0..item_count {
             let offs : usize = actual_offset + usize::try_from(i).unwrap() * 8;
+            let short = read_record::<PackageHeaderEntryShort>(data, offs)?;
             let maybe_prev = res.entries.last_mut();
             let curr = PackageHeaderEntry {
-                file_id: read_u32_le(data, offs+0),
-                offset: read_u32_le(data, offs+4),
+                file_id: short.file_id,
+                offset: short.offset,
                 length: 0
             };
             if let Some(prev) = maybe_prev {
@@ -102,7 +169,7 @@ pub fn read_multi(data: &[u8]) -> Result<MultiBundleHeader, ReadError> {
     let mut res = MultiBundleHeader {
         bundles: HashMap::new()
     };
-    
+
     let bundle_count = read_u32_le(data, 4);
     let bundle_base: usize = 20;
 
@@ -110,28 +177,88 @@ pub fn read_multi(data: &[u8]) -> Result<MultiBundleHeader, ReadError> {
 
     for i in 0..bundle_count {
         let header_offs = bundle_base + 28 * (i as usize);
-        let bundle_index = read_u64_le(data, header_offs+0);
-        let entry_count_1: usize = read_u32_le(data, header_offs+8).try_into().unwrap();
-        let entry_count_2: usize = read_u32_le(data, header_offs+12).try_into().unwrap();
-        let offset: usize = read_u64_le(data, header_offs+16).try_into().unwrap();
-        let always_one = read_u32_le(data, header_offs+24);
+        let header = read_record::<MultiBundleEntryHeader>(data, header_offs)?;
 
-        if always_one != 1 || entry_count_1 != entry_count_2 {
+        if header.always_one != 1 || header.entry_count_1 != header.entry_count_2 {
             return Err(ReadError::BadMultiBundleHeader);
         }
 
+        let entry_count: usize = header.entry_count_1.try_into().unwrap();
+        let offset: usize = header.offset().try_into().unwrap();
+
         let mut entries: Vec<PackageHeaderEntry> = Vec::new();
-        entries.reserve_exact(entry_count_1);
-        for ie in 0..entry_count_1 {
+        entries.reserve_exact(entry_count);
+        for ie in 0..entry_count {
             let pe_offset = offset + (12*ie) + 4;
-            let pe = PackageHeaderEntry {
-                file_id: read_u32_le(data, pe_offset+0),
-                offset: read_u32_le(data, pe_offset+4),
-                length: read_u32_le(data, pe_offset+8)
-            };
-            entries.push(pe);
+            entries.push(read_record::<PackageHeaderEntry>(data, pe_offset)?);
         }
-        res.bundles.insert(bundle_index, PackageHeaderFile { entries });
+        res.bundles.insert(header.bundle_index(), PackageHeaderFile { entries });
     }
     return Ok(res);
-}
\ No newline at end of file
+}
+
+impl PackageHeaderFile {
+    /// Serializes back to the canonical little-endian with-length layout
+    /// `read_normal` parses: a 24-byte x86 word header whose words are
+    /// arranged so the `words.1 == words.2` has-length branch is the one
+    /// that fires, followed by one 12-byte [`PackageHeaderEntry`] row per
+    /// entry, enabling round-trip editing of a bundle's header.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        const HEADER_SIZE: u32 = 24;
+
+        let item_count = self.entries.len() as u32;
+        let entries_offset = HEADER_SIZE;
+
+        let mut out = Vec::with_capacity(HEADER_SIZE as usize + self.entries.len() * 12);
+        out.extend_from_slice(&0u32.to_le_bytes());          // ref_offset, unused once offset (below) is nonzero
+        out.extend_from_slice(&u32::MAX.to_le_bytes());      // distinct from item_count, so the no-length branch doesn't fire
+        out.extend_from_slice(&item_count.to_le_bytes());
+        out.extend_from_slice(&item_count.to_le_bytes());
+        out.extend_from_slice(&(entries_offset - 4).to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes());
+
+        for entry in &self.entries {
+            out.extend_from_slice(bytemuck::bytes_of(entry));
+        }
+
+        out
+    }
+}
+
+impl MultiBundleHeader {
+    /// Serializes back to the canonical little-endian multi-bundle layout
+    /// `read_multi` parses: a 28-byte header per bundle (sorted by bundle
+    /// index, for deterministic output), followed by every bundle's
+    /// [`PackageHeaderEntry`] rows back to back in the same order.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        const BUNDLE_BASE: u32 = 20;
+        const HEADER_RECORD_SIZE: u32 = 28;
+
+        let mut bundles: Vec<(&u64, &PackageHeaderFile)> = self.bundles.iter().collect();
+        bundles.sort_by_key(|(id, _)| **id);
+
+        let headers_size = BUNDLE_BASE + HEADER_RECORD_SIZE * bundles.len() as u32;
+
+        let mut headers = Vec::with_capacity(bundles.len() * HEADER_RECORD_SIZE as usize);
+        let mut entries = Vec::new();
+        let mut entries_offset = headers_size;
+
+        for (bundle_index, file) in &bundles {
+            let entry_count = file.entries.len() as u32;
+            let header = MultiBundleEntryHeader::new(**bundle_index, entry_count, (entries_offset - 4) as u64);
+            headers.extend_from_slice(bytemuck::bytes_of(&header));
+            for entry in &file.entries {
+                entries.extend_from_slice(bytemuck::bytes_of(entry));
+            }
+            entries_offset += entry_count * 12;
+        }
+
+        let mut out = Vec::with_capacity(headers_size as usize + entries.len());
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend_from_slice(&(bundles.len() as u32).to_le_bytes());
+        out.extend_from_slice(&[0u8; BUNDLE_BASE as usize - 8]);
+        out.extend_from_slice(&headers);
+        out.extend_from_slice(&entries);
+        out
+    }
+}
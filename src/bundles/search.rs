@@ -0,0 +1,130 @@
+//! Typo-tolerant lookup over a [`Database`]'s known path strings - see
+//! [`Database::search`]. A trigram index narrows the whole database down to
+//! the handful of paths that share any 3-byte run with the query, then each
+//! candidate is scored by bounded Levenshtein distance so a misspelling like
+//! `dome_oclusion` still finds `dome_occlusion`. Built fresh from scratch on
+//! every call rather than cached on `Database`, the same way [`Database::glob`]
+//! walks the tree anew each time instead of keeping a standing index.
+
+use fnv::{FnvHashMap, FnvHashSet};
+
+use super::database::{Database, HashStrKey};
+
+const MAX_EDIT_DISTANCE: u32 = 2;
+
+/// Every known path, trigram-indexed for [`search`].
+struct SearchIndex<'a> {
+    trigrams: FnvHashMap<[u8; 3], Vec<u32>>,
+    entries: Vec<(u32, HashStrKey<'a>, String)>
+}
+
+impl<'a> SearchIndex<'a> {
+    fn build(db: &'a Database) -> SearchIndex<'a> {
+        let mut trigrams: FnvHashMap<[u8; 3], Vec<u32>> = FnvHashMap::default();
+        let mut entries = Vec::new();
+
+        for item in db.files() {
+            let (path, language, extension) = item.key();
+            let text = match path.text {
+                Some(t) => t,
+                None => continue
+            };
+            let lower = text.to_lowercase();
+            let file_id = item.item_index();
+            for trigram in trigrams_of(&lower) {
+                trigrams.entry(trigram).or_default().push(file_id);
+            }
+            entries.push((file_id, HashStrKey { path, language, extension }, lower));
+        }
+
+        SearchIndex { trigrams, entries }
+    }
+}
+
+fn trigrams_of(s: &str) -> impl Iterator<Item = [u8; 3]> + '_ {
+    let bytes = s.as_bytes();
+    (0..bytes.len().saturating_sub(2)).map(move |i| [bytes[i], bytes[i + 1], bytes[i + 2]])
+}
+
+/// The lowest edit distance between `query` and either `path` as a whole or
+/// one of its `/`-separated segments, whichever is closer - so a single-word
+/// query like `cube_lights` can still find `environment/cube_lights/foo`
+/// without being penalised for every character outside that one segment.
+fn best_distance(query: &[char], path: &str) -> Option<u32> {
+    let whole: Vec<char> = path.chars().collect();
+    let mut best = bounded_levenshtein(query, &whole, MAX_EDIT_DISTANCE);
+
+    for segment in path.split('/') {
+        let chars: Vec<char> = segment.chars().collect();
+        if let Some(d) = bounded_levenshtein(query, &chars, MAX_EDIT_DISTANCE) {
+            best = match best {
+                Some(b) => Some(b.min(d)),
+                None => Some(d)
+            };
+        }
+    }
+
+    best
+}
+
+/// Levenshtein distance between `a` and `b`, or `None` as soon as it's
+/// certain to exceed `max` - both from the length difference up front and
+/// from a row of the DP table whose every entry already exceeds `max`.
+fn bounded_levenshtein(a: &[char], b: &[char], max: u32) -> Option<u32> {
+    let len_diff = (a.len() as i64 - b.len() as i64).unsigned_abs();
+    if len_diff > max as u64 {
+        return None;
+    }
+
+    let mut prev: Vec<u32> = (0..=b.len() as u32).collect();
+    let mut curr = vec![0u32; b.len() + 1];
+
+    for i in 1..=a.len() {
+        curr[0] = i as u32;
+        let mut row_min = curr[0];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+            row_min = row_min.min(curr[j]);
+        }
+        if row_min > max {
+            return None;
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    let distance = prev[b.len()];
+    if distance <= max { Some(distance) } else { None }
+}
+
+/// Looks `query` up against every path [`Database::files`] knows a string
+/// for, returning at most `max_results` matches sorted by edit distance then
+/// path length. See [`Database::search`].
+pub fn search<'a>(db: &'a Database, query: &str, max_results: usize) -> Vec<(HashStrKey<'a>, u32)> {
+    let index = SearchIndex::build(db);
+    let lower = query.to_lowercase();
+    let query_chars: Vec<char> = lower.chars().collect();
+
+    let mut candidates: FnvHashSet<u32> = FnvHashSet::default();
+    for trigram in trigrams_of(&lower) {
+        if let Some(ids) = index.trigrams.get(&trigram) {
+            candidates.extend(ids.iter().copied());
+        }
+    }
+    // A query shorter than 3 bytes has no trigrams of its own, so there's
+    // nothing to union against - fall back to scoring every known path.
+    if candidates.is_empty() && lower.len() < 3 {
+        candidates.extend(index.entries.iter().map(|(id, _, _)| *id));
+    }
+
+    let mut scored: Vec<(u32, HashStrKey<'a>, usize)> = index.entries.iter()
+        .filter(|(id, _, _)| candidates.contains(id))
+        .filter_map(|(_, key, path)| {
+            best_distance(&query_chars, path).map(|distance| (distance, *key, path.len()))
+        })
+        .collect();
+
+    scored.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.2.cmp(&b.2)));
+    scored.truncate(max_results);
+    scored.into_iter().map(|(distance, key, _)| (key, distance)).collect()
+}
@@ -0,0 +1,157 @@
+//! Content-deduplication and size reporting over a [`Database`]. Most virtual
+//! files here are aliases pointing at a handful of shared bundle offsets, so
+//! grouping by `(data_path, offset, length)` - no decompression, no reading -
+//! already finds almost all of the savings; `--hash` mode additionally reads
+//! each distinct extent once and groups by content hash, to catch the rarer
+//! case of byte-identical files stored at two different offsets.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::diesel_hash::hash_level;
+use crate::hashindex::HashedStr;
+use super::database::Database;
+
+/// One group of virtual paths that all read back the same bytes - either
+/// because they share a single physical extent, or (in `--hash` mode) because
+/// their distinct extents happen to hold identical content.
+#[derive(Serialize)]
+pub struct DupGroup {
+    pub data_path: PathBuf,
+    pub offset: usize,
+    pub length: usize,
+    pub content_hash: Option<u64>,
+    pub paths: Vec<String>
+}
+
+impl DupGroup {
+    fn bytes_saved(&self) -> u64 {
+        self.length as u64 * (self.paths.len().saturating_sub(1)) as u64
+    }
+}
+
+#[derive(Serialize)]
+pub struct DedupReport {
+    /// Sum of every file's logical length, counting each virtual path once
+    /// even when several alias the same bytes.
+    pub total_logical_size: u64,
+    /// How many distinct groups of aliased bytes were found.
+    pub distinct_extents: usize,
+    /// Bytes that would be read twice if every virtual path were extracted
+    /// naively instead of de-duplicated first.
+    pub bytes_saved: u64,
+    pub largest: Vec<DupGroup>,
+    pub most_duplicated: Vec<DupGroup>
+}
+
+/// Builds a [`DedupReport`] over every [`super::database::ItemType::File`] in
+/// `db`. With `with_hash`, each distinct physical extent is read once (via a
+/// plain seek+read over its backing package, the same bytes `read_at` would
+/// hand back) and extent-groups sharing a hash are folded together; without
+/// it, only the cheap `(data_path, offset, length)` grouping runs, so this
+/// never touches a single file's contents. `top_n` caps how many groups
+/// `largest`/`most_duplicated` keep, so a database with no bugs in endless
+/// duplication still produces a report worth reading.
+pub fn collect(db: &Database, with_hash: bool, top_n: usize) -> io::Result<DedupReport> {
+    let mut extents: HashMap<(PathBuf, usize, usize), Vec<String>> = HashMap::new();
+
+    for item in db.files() {
+        let (path, offset, length) = match item.get_backing_details() {
+            Some(d) => d,
+            None => continue
+        };
+        let (path_hash, _language, extension_hash) = item.key();
+        let name = display_name(path_hash, extension_hash);
+        extents.entry((path.to_owned(), offset, length)).or_default().push(name);
+    }
+
+    let mut total_logical_size = 0u64;
+    let mut groups: Vec<DupGroup> = Vec::with_capacity(extents.len());
+    for ((data_path, offset, length), paths) in extents {
+        total_logical_size += length as u64 * paths.len() as u64;
+        groups.push(DupGroup { data_path, offset, length, content_hash: None, paths });
+    }
+
+    if with_hash {
+        groups = merge_by_content_hash(groups)?;
+    }
+
+    let distinct_extents = groups.len();
+    let bytes_saved = groups.iter().map(DupGroup::bytes_saved).sum();
+
+    let mut largest = groups;
+    largest.sort_by(|a, b| b.length.cmp(&a.length));
+
+    let mut most_duplicated: Vec<DupGroup> = largest.iter()
+        .map(|g| DupGroup { data_path: g.data_path.clone(), offset: g.offset, length: g.length, content_hash: g.content_hash, paths: g.paths.clone() })
+        .collect();
+    most_duplicated.sort_by(|a, b| b.paths.len().cmp(&a.paths.len()));
+
+    largest.truncate(top_n);
+    most_duplicated.truncate(top_n);
+
+    Ok(DedupReport { total_logical_size, distinct_extents, bytes_saved, largest, most_duplicated })
+}
+
+/// Reads each group's backing bytes once and merges groups whose content
+/// hashes to the same value - duplicate content that `(data_path, offset,
+/// length)` grouping alone can't see because it lives at two different
+/// offsets (possibly even in two different packages).
+fn merge_by_content_hash(groups: Vec<DupGroup>) -> io::Result<Vec<DupGroup>> {
+    let mut by_hash: HashMap<u64, DupGroup> = HashMap::new();
+
+    for mut group in groups {
+        let hash = read_and_hash(&group.data_path, group.offset, group.length)?;
+        group.content_hash = Some(hash);
+
+        match by_hash.get_mut(&hash) {
+            Some(existing) => existing.paths.append(&mut group.paths),
+            None => { by_hash.insert(hash, group); }
+        }
+    }
+
+    Ok(by_hash.into_values().collect())
+}
+
+fn read_and_hash(path: &PathBuf, offset: usize, length: usize) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset as u64))?;
+    let mut buf = vec![0u8; length];
+    file.read_exact(&mut buf)?;
+    Ok(hash_level(&buf, 0))
+}
+
+fn display_name(path: HashedStr, extension: HashedStr) -> String {
+    if extension.hash == crate::diesel_hash::EMPTY {
+        format!("{}", path)
+    }
+    else {
+        format!("{}.{}", path, extension)
+    }
+}
+
+/// Prints `report` as two sorted tables - by size, then by duplicate count -
+/// followed by the summary totals.
+pub fn print_table(report: &DedupReport) {
+    println!("{:>14}  {:>4}  path", "length", "dups");
+    println!("-- largest --");
+    for g in &report.largest {
+        println!("{:>14}  {:>4}  {}", g.length, g.paths.len(), g.paths.join(", "));
+    }
+    println!("-- most duplicated --");
+    for g in &report.most_duplicated {
+        println!("{:>14}  {:>4}  {}", g.length, g.paths.len(), g.paths.join(", "));
+    }
+    println!();
+    println!("Total logical size: {}", report.total_logical_size);
+    println!("Distinct extents: {}", report.distinct_extents);
+    println!("Bytes saved by dedup: {}", report.bytes_saved);
+}
+
+pub fn to_json(report: &DedupReport) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(report)
+}
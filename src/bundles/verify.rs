@@ -0,0 +1,135 @@
+//! Integrity checks over an already-parsed [`ParsedBundle`], independent of
+//! whatever I/O or parsing already happened to produce it. A bundle's header
+//! entries and its data file's length are two numbers that should always
+//! agree; this module is the place that actually checks them, instead of
+//! `load_bundle_dir`'s callers finding out the hard way when an offset reads
+//! past EOF.
+//!
+//! Checksums are opt-in (`ChecksumMode::Compute`/`CompareAgainst`) since they
+//! mean reading every byte of the data file, not just its length - fine for
+//! an explicit "verify my install" pass, wasteful to do on every load.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+
+use serde::Serialize;
+
+use super::loader::ParsedBundle;
+
+/// One thing found wrong with a bundle's entries or data file.
+#[derive(Debug, Serialize)]
+pub enum BundleProblem {
+    /// `offset + length` for `file_id` runs past the end of the data file.
+    OutOfBounds { file_id: u32, offset: u32, length: u32, data_len: u64 },
+    /// `first_file_id`'s extent runs into `second_file_id`'s, which starts at `at`.
+    Overlap { first_file_id: u32, second_file_id: u32, at: u32 },
+    /// The highest `offset + length` among all entries doesn't match the data file's actual size.
+    SizeMismatch { computed_len: u64, actual_len: u64 },
+    /// `file_id`'s freshly computed checksum doesn't match the one it was compared against.
+    ChecksumMismatch { file_id: u32, expected: u32, actual: u32 }
+}
+
+/// What, if anything, [`verify`] should do with checksums.
+pub enum ChecksumMode {
+    /// Skip checksums entirely - just the offset/length/overlap checks.
+    None,
+    /// Compute each entry's CRC32C and the whole file's, but don't compare
+    /// them against anything; they're returned on [`VerifyReport`] for the
+    /// caller to stash away (e.g. to check against on a later run).
+    Compute,
+    /// Compute checksums as with `Compute`, and additionally report a
+    /// [`BundleProblem::ChecksumMismatch`] for any `file_id` present in
+    /// `expected` whose computed value differs.
+    CompareAgainst(HashMap<u32, u32>)
+}
+
+/// One entry's checksum, as computed by [`verify`] under [`ChecksumMode::Compute`]/[`ChecksumMode::CompareAgainst`].
+#[derive(Debug, Serialize)]
+pub struct EntryChecksum {
+    pub file_id: u32,
+    pub crc32c: u32
+}
+
+#[derive(Serialize)]
+pub struct VerifyReport {
+    pub problems: Vec<BundleProblem>,
+    pub entry_checksums: Vec<EntryChecksum>,
+    pub whole_file_checksum: Option<u32>
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Checks `bundle`'s entries against its own data file: every entry fits
+/// inside it, the entries (sorted by offset) don't overlap, and the highest
+/// `offset + length` matches the file's actual size. Under
+/// [`ChecksumMode::Compute`]/[`ChecksumMode::CompareAgainst`] this also reads
+/// the whole data file once, so it's an `io::Result` rather than infallible.
+pub fn verify(bundle: &ParsedBundle, checksums: ChecksumMode) -> io::Result<VerifyReport> {
+    let mut problems = Vec::new();
+    let data_len = bundle.data_len;
+
+    let mut by_offset: Vec<_> = bundle.header.entries.iter().collect();
+    by_offset.sort_by_key(|e| e.offset);
+
+    for entry in &by_offset {
+        let end = entry.offset as u64 + entry.length as u64;
+        if end > data_len {
+            problems.push(BundleProblem::OutOfBounds {
+                file_id: entry.file_id, offset: entry.offset, length: entry.length, data_len
+            });
+        }
+    }
+
+    for pair in by_offset.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let a_end = a.offset as u64 + a.length as u64;
+        if a_end > b.offset as u64 {
+            problems.push(BundleProblem::Overlap {
+                first_file_id: a.file_id, second_file_id: b.file_id, at: b.offset
+            });
+        }
+    }
+
+    let computed_len = by_offset.iter().map(|e| e.offset as u64 + e.length as u64).max().unwrap_or(0);
+    if computed_len != data_len {
+        problems.push(BundleProblem::SizeMismatch { computed_len, actual_len: data_len });
+    }
+
+    let mut entry_checksums = Vec::new();
+    let mut whole_file_checksum = None;
+
+    if !matches!(checksums, ChecksumMode::None) {
+        let mut file = File::open(&bundle.data_path)?;
+        let mut whole = Vec::with_capacity(data_len as usize);
+        file.read_to_end(&mut whole)?;
+        whole_file_checksum = Some(crc32c::crc32c(&whole));
+
+        let expected = match &checksums {
+            ChecksumMode::CompareAgainst(e) => Some(e),
+            _ => None
+        };
+
+        for entry in &by_offset {
+            let start = entry.offset as usize;
+            let end = (entry.offset as u64 + entry.length as u64).min(data_len) as usize;
+            let actual = crc32c::crc32c(whole.get(start..end).unwrap_or(&[]));
+
+            if let Some(expected) = expected {
+                if let Some(&want) = expected.get(&entry.file_id) {
+                    if want != actual {
+                        problems.push(BundleProblem::ChecksumMismatch { file_id: entry.file_id, expected: want, actual });
+                    }
+                }
+            }
+
+            entry_checksums.push(EntryChecksum { file_id: entry.file_id, crc32c: actual });
+        }
+    }
+
+    Ok(VerifyReport { problems, entry_checksums, whole_file_checksum })
+}
@@ -33,6 +33,48 @@ fn wu64_from(v: u8) -> Wrapping<u64> {
     return Wrapping(u64::from(v));
 }
 
+/// Mixes whatever's left of `tail` (fewer than 24 bytes) into `a`/`b`/`c`,
+/// exactly the way [`hash_level`]'s own trailing partial block does - pulled
+/// out so [`DieselHasher`] can run the same logic against its own buffered
+/// tail.
+fn mix_tail(tail: &[u8], mut len: usize, a: &mut Wrapping<u64>, b: &mut Wrapping<u64>, c: &mut Wrapping<u64>) {
+    while len > 0 {
+        *c += match len {
+            23 => wu64_from(tail[22]) << 56,
+            22 => wu64_from(tail[21]) << 48,
+            21 => wu64_from(tail[20]) << 40,
+            20 => wu64_from(tail[19]) << 32,
+            19 => wu64_from(tail[18]) << 24,
+            18 => wu64_from(tail[17]) << 16,
+            17 => wu64_from(tail[16]) << 8,
+            _ => Wrapping(0)
+        };
+        *b += match len {
+            16 => wu64_from(tail[15]) << 56,
+            15 => wu64_from(tail[14]) << 48,
+            14 => wu64_from(tail[13]) << 40,
+            13 => wu64_from(tail[12]) << 32,
+            12 => wu64_from(tail[11]) << 24,
+            11 => wu64_from(tail[10]) << 16,
+            10 => wu64_from(tail[9]) << 8,
+            9  => wu64_from(tail[8]),
+            _ => Wrapping(0)
+        };
+        *a += match len {
+            8 => wu64_from(tail[7]) << 56,
+            7 => wu64_from(tail[6]) << 48,
+            6 => wu64_from(tail[5]) << 40,
+            5 => wu64_from(tail[4]) << 32,
+            4 => wu64_from(tail[3]) << 24,
+            3 => wu64_from(tail[2]) << 16,
+            2 => wu64_from(tail[1]) << 8,
+            1 => wu64_from(tail[0]),
+            _ => Wrapping(0)
+        };
+        len -= 1;
+    }
+}
+
 //pub fn hash(k: &[u8]) -> u64 { return hash_level(k, 0); }
 pub fn hash_str(s: &str) -> u64 { return hash_level(s.as_bytes(), 0); }
 
@@ -41,7 +83,7 @@ pub fn hash_level(k : &[u8], level: u64) -> u64 {
     let mut a = Wrapping(level);
     let mut b = Wrapping(level);
     let mut c = Wrapping::<u64>(0x9e3779b97f4a7c13);
-    
+
     let mut len_x = 0;
     while len >= 24 {
         a += read_le_u64(k, len_x);
@@ -52,44 +94,88 @@ pub fn hash_level(k : &[u8], level: u64) -> u64 {
     }
 
     c += Wrapping(u64::try_from(k.len()).expect("What, are you running this on a machine with 128-bit memory addresses? o.O"));
+    mix_tail(&k[len_x..], len, &mut a, &mut b, &mut c);
+    mix64(&mut a, &mut b, &mut c);
+    return c.0;
+}
+
+/// Incremental equivalent of [`hash_level`]: feed it bytes with [`write`][Self::write]
+/// across as many calls as convenient, then call [`finish`][Self::finish] to get the
+/// same hash `hash_level` would have produced from the concatenation of everything
+/// written.
+///
+/// Exists so callers that hash many candidate strings sharing a common prefix -
+/// [`crate::hashlist_scan::combinator`], notably - can cheaply clone the state after
+/// the shared prefix and only mix in each candidate's own suffix, rather than
+/// rehashing the whole string from scratch every time.
+#[derive(Clone)]
+pub struct DieselHasher {
+    a: Wrapping<u64>,
+    b: Wrapping<u64>,
+    c: Wrapping<u64>,
+    total_len: u64,
+    tail: [u8; 24],
+    tail_len: usize
+}
 
-    if len <= 23 {
-        while len > 0 {
-            c += match len {
-                23 => wu64_from(k[len_x + 22]) << 56,
-                22 => wu64_from(k[len_x + 21]) << 48,
-                21 => wu64_from(k[len_x + 20]) << 40,
-                20 => wu64_from(k[len_x + 19]) << 32,
-                19 => wu64_from(k[len_x + 18]) << 24,
-                18 => wu64_from(k[len_x + 17]) << 16,
-                17 => wu64_from(k[len_x + 16]) << 8,
-                _ => Wrapping(0)
-            };
-            b += match len {
-                16 => wu64_from(k[len_x + 15]) << 56,
-                15 => wu64_from(k[len_x + 14]) << 48,
-                14 => wu64_from(k[len_x + 13]) << 40,
-                13 => wu64_from(k[len_x + 12]) << 32,
-                12 => wu64_from(k[len_x + 11]) << 24,
-                11 => wu64_from(k[len_x + 10]) << 16,
-                10 => wu64_from(k[len_x + 9]) << 8,
-                9  => wu64_from(k[len_x + 8]),
-                _ => Wrapping(0)
-            };
-            a += match len {
-                8 => wu64_from(k[len_x + 7]) << 56,
-                7 => wu64_from(k[len_x + 6]) << 48,
-                6 => wu64_from(k[len_x + 5]) << 40,
-                5 => wu64_from(k[len_x + 4]) << 32,
-                4 => wu64_from(k[len_x + 3]) << 24,
-                3 => wu64_from(k[len_x + 2]) << 16,
-                2 => wu64_from(k[len_x + 1]) << 8,
-                1 => wu64_from(k[len_x + 0]),
-                _ => Wrapping(0)
-            };
-            len -= 1;
+impl DieselHasher {
+    /// Starts a hasher equivalent to `hash_level(_, level)`.
+    pub fn new(level: u64) -> DieselHasher {
+        DieselHasher {
+            a: Wrapping(level),
+            b: Wrapping(level),
+            c: Wrapping(0x9e3779b97f4a7c13),
+            total_len: 0,
+            tail: [0; 24],
+            tail_len: 0
         }
     }
-    mix64(&mut a, &mut b, &mut c);
-    return c.0;
+
+    /// Folds `bytes` in as the next bytes of the string being hashed.
+    pub fn write(&mut self, mut bytes: &[u8]) {
+        self.total_len += bytes.len() as u64;
+
+        if self.tail_len > 0 {
+            let want = 24 - self.tail_len;
+            let take = want.min(bytes.len());
+            self.tail[self.tail_len..self.tail_len + take].copy_from_slice(&bytes[..take]);
+            self.tail_len += take;
+            bytes = &bytes[take..];
+            if self.tail_len == 24 {
+                self.mix_block();
+                self.tail_len = 0;
+            }
+        }
+
+        while bytes.len() >= 24 {
+            self.tail[..24].copy_from_slice(&bytes[..24]);
+            self.mix_block();
+            bytes = &bytes[24..];
+        }
+
+        if !bytes.is_empty() {
+            self.tail[..bytes.len()].copy_from_slice(bytes);
+            self.tail_len = bytes.len();
+        }
+    }
+
+    fn mix_block(&mut self) {
+        self.a += read_le_u64(&self.tail, 0);
+        self.b += read_le_u64(&self.tail, 8);
+        self.c += read_le_u64(&self.tail, 16);
+        mix64(&mut self.a, &mut self.b, &mut self.c);
+    }
+
+    /// Finishes the hash of everything written so far, without consuming
+    /// `self` - so a shared prefix's state can be finished off down several
+    /// different branches.
+    pub fn finish(&self) -> u64 {
+        let mut a = self.a;
+        let mut b = self.b;
+        let mut c = self.c;
+        c += Wrapping(self.total_len);
+        mix_tail(&self.tail[..self.tail_len], self.tail_len, &mut a, &mut b, &mut c);
+        mix64(&mut a, &mut b, &mut c);
+        c.0
+    }
 }
\ No newline at end of file
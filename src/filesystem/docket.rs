@@ -0,0 +1,156 @@
+//! Sidecar index ("docket") for a bundle archive.
+//!
+//! The docket is a small file living next to the archive it indexes. It
+//! records a content hash of the archive (so a stale docket is detected
+//! rather than silently trusted) and, for every logical path the archive
+//! contains, the byte range of its payload and how many records it parses
+//! into. [`super::raw_bundledb::BundleFs`] does not read or write dockets;
+//! its `open_readable` looks paths up in the bundle database instead.
+
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use fnv::FnvHashMap;
+
+/// On-disk docket format version. Bump this if the layout changes, so an
+/// old docket is treated as stale rather than misread.
+const DOCKET_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// Where one logical path's payload lives in the backing archive, and how
+/// many records it's expected to parse into (0 if that isn't meaningful,
+/// e.g. for an opaque blob).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DocketEntry {
+    pub range: ByteRange,
+    pub record_count: u32,
+}
+
+#[derive(Debug)]
+pub struct Docket {
+    version: u32,
+    content_hash: u64,
+    entries: FnvHashMap<String, DocketEntry>,
+}
+
+impl Docket {
+    pub fn new(content_hash: u64) -> Docket {
+        Docket {
+            version: DOCKET_VERSION,
+            content_hash,
+            entries: FnvHashMap::default(),
+        }
+    }
+
+    pub fn insert(&mut self, path: impl Into<String>, entry: DocketEntry) {
+        self.entries.insert(path.into(), entry);
+    }
+
+    pub fn get(&self, path: &str) -> Option<DocketEntry> {
+        self.entries.get(path).copied()
+    }
+
+    /// Whether this docket was built from the same archive bytes that `content_hash`
+    /// describes. Call before trusting a docket loaded from disk.
+    pub fn matches(&self, content_hash: u64) -> bool {
+        self.version == DOCKET_VERSION && self.content_hash == content_hash
+    }
+
+    /// Loads a docket previously written with [`Docket::write`]. Any I/O or
+    /// format error is treated as "no usable docket" by the caller, which
+    /// should fall back to a full parse and call [`Docket::write`] again.
+    pub fn read(path: &Path) -> io::Result<Docket> {
+        let mut f = std::fs::File::open(path)?;
+        let mut buf = Vec::new();
+        f.read_to_end(&mut buf)?;
+        Docket::from_bytes(&buf).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed docket"))
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut f = std::fs::File::create(path)?;
+        f.write_all(&self.to_bytes())
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.extend_from_slice(&self.content_hash.to_le_bytes());
+        out.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for (path, entry) in &self.entries {
+            let path_bytes = path.as_bytes();
+            out.extend_from_slice(&(path_bytes.len() as u32).to_le_bytes());
+            out.extend_from_slice(path_bytes);
+            out.extend_from_slice(&entry.range.offset.to_le_bytes());
+            out.extend_from_slice(&entry.range.length.to_le_bytes());
+            out.extend_from_slice(&entry.record_count.to_le_bytes());
+        }
+        out
+    }
+
+    fn from_bytes(buf: &[u8]) -> Option<Docket> {
+        let mut pos = 0usize;
+        let mut take = |len: usize| -> Option<&[u8]> {
+            let slice = buf.get(pos..pos + len)?;
+            pos += len;
+            Some(slice)
+        };
+
+        let version = u32::from_le_bytes(take(4)?.try_into().ok()?);
+        let content_hash = u64::from_le_bytes(take(8)?.try_into().ok()?);
+        let count = u32::from_le_bytes(take(4)?.try_into().ok()?);
+
+        let mut entries = FnvHashMap::default();
+        entries.reserve(count as usize);
+        for _ in 0..count {
+            let path_len = u32::from_le_bytes(take(4)?.try_into().ok()?) as usize;
+            let path = std::str::from_utf8(take(path_len)?).ok()?.to_owned();
+            let offset = u64::from_le_bytes(take(8)?.try_into().ok()?);
+            let length = u64::from_le_bytes(take(8)?.try_into().ok()?);
+            let record_count = u32::from_le_bytes(take(4)?.try_into().ok()?);
+            entries.insert(path, DocketEntry { range: ByteRange { offset, length }, record_count });
+        }
+
+        Some(Docket { version, content_hash, entries })
+    }
+}
+
+/// Cheap, non-cryptographic content hash used to tell whether a docket is
+/// still valid for the archive beside it. Not a substitute for a real
+/// checksum if tamper-resistance mattered, but this is only a staleness check.
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    use std::hash::Hasher;
+    let mut h = fnv::FnvHasher::default();
+    h.write_u64(bytes.len() as u64);
+    // Sampling rather than hashing the whole multi-gigabyte archive keeps
+    // this cheap enough to call on every mount.
+    const SAMPLE: usize = 4096;
+    h.write(&bytes[..bytes.len().min(SAMPLE)]);
+    if bytes.len() > SAMPLE {
+        h.write(&bytes[bytes.len() - SAMPLE..]);
+    }
+    h.finish()
+}
+
+/// Default location for a docket sidecar: next to the archive, with a
+/// `.docket` extension appended.
+pub fn sidecar_path(archive_path: &Path) -> std::path::PathBuf {
+    let mut p = archive_path.as_os_str().to_owned();
+    p.push(".docket");
+    std::path::PathBuf::from(p)
+}
+
+/// Loads the docket beside `archive_path` if it exists and still matches
+/// `content_hash`, otherwise returns `None` so the caller can do a full parse.
+pub fn load_if_fresh(archive_path: &Path, content_hash: u64) -> Option<Docket> {
+    let docket = Docket::read(&sidecar_path(archive_path)).ok()?;
+    if docket.matches(content_hash) {
+        Some(docket)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,161 @@
+//! Bulk recursive export of a [`ReadOnlyFs`] subtree onto real disk - the
+//! read-side counterpart to a restore/extract command, for dumping assets out
+//! without going through `mount`/`mount-fuse` at all. Built only on
+//! [`FsReadHandle::find_files`] and [`FsReadHandle::read_at`], same as
+//! [`super::listing::walk`], so it works against any backing filesystem, not
+//! just [`super::raw_bundledb::BundleFs`] directly.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use filetime::{set_file_mtime, FileTime};
+
+use crate::bundles::glob::PathGlob;
+use super::{FsError, FsReadHandle, ReadOnlyFs};
+
+/// How much of a file to hold in memory at once while copying it out - large
+/// enough that the per-chunk overhead is noise, small enough that extracting
+/// a many-gigabyte video doesn't balloon memory the way reading it into one
+/// `Vec` would.
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// An include/exclude pair of [`PathGlob`]s, checked against the same
+/// `/`-separated virtual path [`super::raw_bundledb::key_to_name`] builds.
+/// A missing include matches everything; a missing exclude matches nothing.
+pub struct ExtractFilter {
+    include: Option<PathGlob>,
+    exclude: Option<PathGlob>
+}
+
+impl ExtractFilter {
+    pub fn new() -> ExtractFilter {
+        ExtractFilter { include: None, exclude: None }
+    }
+
+    pub fn with_include(mut self, pattern: &str) -> ExtractFilter {
+        self.include = Some(PathGlob::compile(pattern));
+        self
+    }
+
+    pub fn with_exclude(mut self, pattern: &str) -> ExtractFilter {
+        self.exclude = Some(PathGlob::compile(pattern));
+        self
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let included = self.include.as_ref().map_or(true, |g| g.matches(path));
+        !self.excluded(path) && included
+    }
+
+    /// Just the exclude half, checked against folders too: a folder this
+    /// matches is pruned outright rather than descended into, but a folder
+    /// failing `--include` still gets walked, since a deeper descendant might
+    /// match a pattern like `units/**` that the folder itself doesn't.
+    fn excluded(&self, path: &str) -> bool {
+        self.exclude.as_ref().map_or(false, |g| g.matches(path))
+    }
+}
+
+impl Default for ExtractFilter {
+    fn default() -> ExtractFilter { ExtractFilter::new() }
+}
+
+/// Why extracting one file failed - kept per-file in [`extract_tree`]'s
+/// result rather than aborting the whole walk.
+#[derive(Debug)]
+pub enum ExtractError {
+    Fs(FsError),
+    Io(io::Error)
+}
+variant_from!(ExtractError::Fs, FsError);
+variant_from!(ExtractError::Io, io::Error);
+
+/// What became of one file [`extract_tree`] considered: written out,
+/// dropped because `--include`/`--exclude` didn't match it, or failed partway
+/// through.
+#[derive(Debug)]
+pub enum ExtractOutcome {
+    Extracted,
+    Skipped,
+    Failed(ExtractError)
+}
+variant_from!(ExtractOutcome::Failed, ExtractError);
+
+/// Recursively copies `root` (and everything below it, if it's a directory)
+/// out of `fs` into `dest`, recreating folders and preserving each file's
+/// `last_write_time`. Entries whose virtual path doesn't match `filter` are
+/// reported as [`ExtractOutcome::Skipped`] rather than written (directories
+/// failing `--exclude` are pruned outright and never appear here at all, same
+/// as [`ExtractFilter::excluded`] already prunes their whole branch). Returns
+/// one `(path, outcome)` pair per *file* considered, so a single unreadable or
+/// unwritable file doesn't stop the rest of the tree from extracting.
+pub fn extract_tree(fs: &dyn ReadOnlyFs, root: &str, dest: &Path, filter: &ExtractFilter) -> Vec<(String, ExtractOutcome)> {
+    let mut out = Vec::new();
+    extract_into(fs, root, dest, filter, &mut out);
+    out
+}
+
+fn extract_into(fs: &dyn ReadOnlyFs, path: &str, dest: &Path, filter: &ExtractFilter, out: &mut Vec<(String, ExtractOutcome)>) {
+    let handle = match fs.open_readable(path, "") {
+        Ok(h) => h,
+        Err(e) => { out.push((path.to_owned(), ExtractError::from(e).into())); return; }
+    };
+    let info = match handle.get_file_info() {
+        Ok(i) => i,
+        Err(e) => { out.push((path.to_owned(), ExtractError::from(e).into())); return; }
+    };
+
+    if info.is_dir {
+        if filter.excluded(path) { return; }
+        if let Err(e) = fs::create_dir_all(dest) {
+            out.push((path.to_owned(), ExtractError::from(e).into()));
+            return;
+        }
+        let children = match handle.find_files() {
+            Ok(c) => c,
+            Err(e) => { out.push((path.to_owned(), ExtractError::from(e).into())); return; }
+        };
+        for child in children {
+            let child_path = if path.is_empty() { child.name.clone() } else { format!("{}/{}", path, child.name) };
+            let child_dest = dest.join(&child.name);
+            extract_into(fs, &child_path, &child_dest, filter, out);
+        }
+    }
+    else if filter.matches(path) {
+        let outcome = match extract_file(handle.as_ref(), &info, dest) {
+            Ok(()) => ExtractOutcome::Extracted,
+            Err(e) => ExtractOutcome::Failed(e)
+        };
+        out.push((path.to_owned(), outcome));
+    }
+    else {
+        out.push((path.to_owned(), ExtractOutcome::Skipped));
+    }
+}
+
+fn extract_file(handle: &dyn FsReadHandle, info: &super::FsFileInfo, dest: &Path) -> Result<(), ExtractError> {
+    match extract_file_contents(handle, dest) {
+        Ok(()) => {
+            set_file_mtime(dest, FileTime::from_system_time(info.last_write_time))?;
+            Ok(())
+        }
+        Err(e) => {
+            let _ = fs::remove_file(dest);
+            Err(e)
+        }
+    }
+}
+
+fn extract_file_contents(handle: &dyn FsReadHandle, dest: &Path) -> Result<(), ExtractError> {
+    let mut file = fs::File::create(dest)?;
+    let mut chunk = vec![0u8; CHUNK_SIZE];
+    let mut offset = 0u64;
+    loop {
+        let n = handle.read_at(&mut chunk, offset)?;
+        if n == 0 { break; }
+        file.write_all(&chunk[..n])?;
+        offset += n as u64;
+    }
+    Ok(())
+}
@@ -0,0 +1,334 @@
+//! Linux/macOS mount path, parallel to the Dokan adapter but driving the same
+//! [`ReadOnlyFs`] through the `fuser` crate's `Filesystem` trait instead of
+//! Dokany. Everything below `find_files`/`open_readable`/`read_at` is shared
+//! with the Windows mount path; this file only adapts FUSE's inode-based
+//! calling convention onto the path-based one `ReadOnlyFs` uses.
+//!
+//! FUSE has no equivalent of NTFS alternate data streams, so the transcoder's
+//! extra streams (e.g. `.banksinfo:json`) are surfaced as sibling files named
+//! `name.stream` in directory listings, rather than being invisible until
+//! asked for by name like they are on the Dokan side. Each inode remembers
+//! the `(path, stream)` pair it resolves to so reads can go straight back to
+//! `open_readable` without re-parsing the synthetic name.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use fuser::{FileAttr, FileType, Filesystem, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry, ReplyOpen, ReplyXattr, Request};
+
+use super::{FsDirEntry, FsError, FsFileInfo, ReadOnlyFs};
+
+const TTL: Duration = Duration::from_secs(1);
+const ROOT_INODE: u64 = 1;
+
+fn fs_error_to_errno(err: FsError) -> i32 {
+    match err {
+        FsError::NotFound => libc::ENOENT,
+        FsError::IsDirectory => libc::EISDIR,
+        FsError::NotDirectory => libc::ENOTDIR,
+        FsError::PastEnd => 0,
+        FsError::FileCorrupt => libc::EIO,
+        FsError::ReadError => libc::EIO,
+        FsError::OsError(e) => e,
+        FsError::Unsupported => libc::ENOSYS,
+    }
+}
+
+fn file_info_to_attr(ino: u64, info: &FsFileInfo) -> FileAttr {
+    FileAttr {
+        ino,
+        size: info.file_size,
+        blocks: (info.file_size + 511) / 512,
+        atime: info.last_access_time,
+        mtime: info.last_write_time,
+        ctime: info.last_write_time,
+        crtime: info.creation_time,
+        kind: if info.is_dir { FileType::Directory } else { FileType::RegularFile },
+        perm: if info.read_only { 0o444 } else { 0o644 },
+        nlink: info.number_of_links,
+        uid: 0,
+        gid: 0,
+        rdev: 0,
+        blksize: 512,
+        flags: 0,
+    }
+}
+
+/// What an inode names: a real backing path, with `stream` set to the name
+/// of an extra stream if this inode is a synthetic `name.stream` sibling.
+#[derive(Clone)]
+struct InodeTarget {
+    path: String,
+    stream: String,
+}
+
+/// Combines an item's stable `FsFileInfo::file_index` with its stream name
+/// into a FUSE inode number that's the same every time this item/stream pair
+/// is looked up, rather than depending on allocation order - matching the
+/// "each filesystem uses less than all 64 bits" convention `crate::filesystem`
+/// documents for inode numbers generally. `0` is reserved/invalid and `1` is
+/// [`ROOT_INODE`], so a plain file or folder (`stream` empty) gets
+/// `file_index + 2` directly; a named extra stream is pushed into the upper
+/// half of the space so it can never collide with a plain item's inode.
+fn encode_inode(file_index: u64, stream: &str) -> u64 {
+    if stream.is_empty() {
+        file_index + 2
+    }
+    else {
+        let stream_hash = stream.bytes().fold(0u64, |h, b| h.wrapping_mul(31).wrapping_add(b as u64));
+        (1u64 << 63) | ((file_index + 2) << 16) | (stream_hash & 0xffff)
+    }
+}
+
+/// Bridges a [`ReadOnlyFs`] onto `fuser::Filesystem`. Inode numbers are
+/// derived deterministically from each item's `file_index` via
+/// [`encode_inode`]; `by_inode` is populated as paths are looked up purely so
+/// a later `getattr`/`open`/`read` call (which the kernel only gives an
+/// inode number for) can be resolved back to the path/stream it names.
+/// There's no eviction, which is fine for the modding/inspection use case
+/// this exists for.
+pub struct FuseAdapter {
+    backing: Arc<dyn ReadOnlyFs>,
+    inodes: Mutex<InodeTable>,
+}
+
+struct InodeTable {
+    by_inode: HashMap<u64, InodeTarget>,
+}
+
+impl FuseAdapter {
+    pub fn new(backing: Arc<dyn ReadOnlyFs>) -> FuseAdapter {
+        let mut by_inode = HashMap::new();
+        let root = InodeTarget { path: String::from("\\"), stream: String::new() };
+        by_inode.insert(ROOT_INODE, root);
+
+        FuseAdapter {
+            backing,
+            inodes: Mutex::new(InodeTable { by_inode }),
+        }
+    }
+
+    fn inode_for(&self, path: &str, stream: &str, file_index: u64) -> u64 {
+        let ino = encode_inode(file_index, stream);
+        let mut t = self.inodes.lock().unwrap();
+        t.by_inode.entry(ino).or_insert_with(|| InodeTarget { path: path.to_owned(), stream: stream.to_owned() });
+        ino
+    }
+
+    fn target_for(&self, ino: u64) -> Option<InodeTarget> {
+        self.inodes.lock().unwrap().by_inode.get(&ino).cloned()
+    }
+
+    fn child_path(parent: &str, name: &str) -> String {
+        if parent == "\\" { format!("\\{}", name) } else { format!("{}\\{}", parent, name) }
+    }
+
+    fn attr_for(&self, ino: u64, target: &InodeTarget) -> Result<FileAttr, FsError> {
+        let info = self.backing.open_readable(&target.path, &target.stream)?.get_file_info()?;
+        Ok(file_info_to_attr(ino, &info))
+    }
+}
+
+impl Filesystem for FuseAdapter {
+    fn lookup(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        let parent_target = match self.target_for(parent) {
+            Some(t) => t,
+            None => return reply.error(libc::ENOENT)
+        };
+        let name = match name.to_str() {
+            Some(n) => n,
+            None => return reply.error(libc::ENOENT)
+        };
+
+        // A plain child first; if that's not a file, try it as `base.stream`
+        // against the extra streams of `base`.
+        let plain_path = Self::child_path(&parent_target.path, name);
+        let (path, stream) = match self.backing.open_readable(&plain_path, "") {
+            Ok(_) => (plain_path, String::new()),
+            Err(_) => match name.rsplit_once('.') {
+                Some((base, stream)) => (Self::child_path(&parent_target.path, base), stream.to_owned()),
+                None => return reply.error(libc::ENOENT)
+            }
+        };
+
+        match self.backing.open_readable(&path, &stream) {
+            Ok(handle) => match handle.get_file_info() {
+                Ok(info) => {
+                    let ino = self.inode_for(&path, &stream, info.file_index);
+                    reply.entry(&TTL, &file_info_to_attr(ino, &info), 0);
+                },
+                Err(e) => reply.error(fs_error_to_errno(e))
+            },
+            Err(e) => reply.error(fs_error_to_errno(e))
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request, ino: u64, reply: ReplyAttr) {
+        let target = match self.target_for(ino) {
+            Some(t) => t,
+            None => return reply.error(libc::ENOENT)
+        };
+        match self.attr_for(ino, &target) {
+            Ok(attr) => reply.attr(&TTL, &attr),
+            Err(e) => reply.error(fs_error_to_errno(e))
+        }
+    }
+
+    fn open(&mut self, _req: &Request, ino: u64, _flags: i32, reply: ReplyOpen) {
+        match self.target_for(ino) {
+            Some(_) => reply.opened(0, 0),
+            None => reply.error(libc::ENOENT)
+        }
+    }
+
+    fn read(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, size: u32, _flags: i32, _lock: Option<u64>, reply: ReplyData) {
+        let target = match self.target_for(ino) {
+            Some(t) => t,
+            None => return reply.error(libc::ENOENT)
+        };
+        let handle = match self.backing.open_readable(&target.path, &target.stream) {
+            Ok(h) => h,
+            Err(e) => return reply.error(fs_error_to_errno(e))
+        };
+
+        let mut buf = vec![0u8; size as usize];
+        match handle.read_at(&mut buf, offset as u64) {
+            Ok(n) => reply.data(&buf[..n]),
+            Err(FsError::PastEnd) => reply.data(&[]),
+            Err(e) => reply.error(fs_error_to_errno(e))
+        }
+    }
+
+    fn getxattr(&mut self, _req: &Request, ino: u64, name: &OsStr, size: u32, reply: ReplyXattr) {
+        let target = match self.target_for(ino) {
+            Some(t) => t,
+            None => return reply.error(libc::ENOENT)
+        };
+        let handle = match self.backing.open_readable(&target.path, &target.stream) {
+            Ok(h) => h,
+            Err(e) => return reply.error(fs_error_to_errno(e))
+        };
+
+        let name = match name.to_str() {
+            Some(n) => n,
+            None => return reply.error(libc::ENODATA)
+        };
+        let value = match handle.list_xattrs().into_iter().find(|(n, _)| n == name) {
+            Some((_, v)) => v,
+            None => return reply.error(libc::ENODATA)
+        };
+
+        if size == 0 {
+            reply.size(value.len() as u32);
+        }
+        else if (value.len() as u32) > size {
+            reply.error(libc::ERANGE);
+        }
+        else {
+            reply.data(&value);
+        }
+    }
+
+    fn listxattr(&mut self, _req: &Request, ino: u64, size: u32, reply: ReplyXattr) {
+        let target = match self.target_for(ino) {
+            Some(t) => t,
+            None => return reply.error(libc::ENOENT)
+        };
+        let handle = match self.backing.open_readable(&target.path, &target.stream) {
+            Ok(h) => h,
+            Err(e) => return reply.error(fs_error_to_errno(e))
+        };
+
+        let mut names = Vec::new();
+        for (name, _) in handle.list_xattrs() {
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+        }
+
+        if size == 0 {
+            reply.size(names.len() as u32);
+        }
+        else if (names.len() as u32) > size {
+            reply.error(libc::ERANGE);
+        }
+        else {
+            reply.data(&names);
+        }
+    }
+
+    fn readdir(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, mut reply: ReplyDirectory) {
+        let target = match self.target_for(ino) {
+            Some(t) => t,
+            None => return reply.error(libc::ENOENT)
+        };
+        let handle = match self.backing.open_readable(&target.path, "") {
+            Ok(h) => h,
+            Err(e) => return reply.error(fs_error_to_errno(e))
+        };
+
+        let entries = match handle.find_files() {
+            Ok(it) => it,
+            Err(e) => return reply.error(fs_error_to_errno(e))
+        };
+
+        let mut all: Vec<(String, FileType, u64)> = vec![
+            (".".to_owned(), FileType::Directory, ino),
+            ("..".to_owned(), FileType::Directory, ino),
+        ];
+
+        for entry in entries {
+            all.extend(self.dir_entry_to_fuse(&target.path, entry));
+        }
+
+        for (i, (name, kind, child_ino)) in all.into_iter().enumerate().skip(offset as usize) {
+            if reply.add(child_ino, (i + 1) as i64, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+}
+
+impl FuseAdapter {
+    /// Turns one backing `FsDirEntry` into the one-or-more entries readdir
+    /// should present, synthesizing `name.stream` siblings for any extra
+    /// streams the real file has beyond its default content.
+    fn dir_entry_to_fuse(&self, parent_path: &str, fd: FsDirEntry) -> Vec<(String, FileType, u64)> {
+        let child_path = Self::child_path(parent_path, &fd.name);
+        let kind = if fd.is_dir { FileType::Directory } else { FileType::RegularFile };
+
+        let handle = match self.backing.open_readable(&child_path, "") {
+            Ok(h) => h,
+            Err(_) => return Vec::new()
+        };
+        let file_index = match handle.get_file_info() {
+            Ok(info) => info.file_index,
+            Err(_) => return Vec::new()
+        };
+
+        let ino = self.inode_for(&child_path, "", file_index);
+        let mut out = vec![(fd.name.clone(), kind, ino)];
+
+        if !fd.is_dir {
+            if let Ok(streams) = handle.list_streams() {
+                for s in streams {
+                    if s.name.is_empty() { continue; }
+                    let synthetic_name = format!("{}.{}", fd.name, s.name);
+                    let synthetic_ino = self.inode_for(&child_path, &s.name, file_index);
+                    out.push((synthetic_name, FileType::RegularFile, synthetic_ino));
+                }
+            }
+        }
+
+        out
+    }
+}
+
+/// Mounts `backing` at `mountpoint` using FUSE, blocking until unmounted.
+/// This is the non-Windows sibling of `mount_cooked_database`.
+pub fn mount_cooked_database_fuse(mountpoint: impl AsRef<std::path::Path>, backing: Arc<dyn ReadOnlyFs>) -> std::io::Result<()> {
+    let options = vec![fuser::MountOption::RO, fuser::MountOption::FSName("pd2bundlefs".to_owned())];
+    fuser::mount2(FuseAdapter::new(backing), mountpoint, &options)
+}
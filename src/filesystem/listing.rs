@@ -0,0 +1,169 @@
+//! Recursive directory listing and content-verification over any
+//! [`ReadOnlyFs`], in the spirit of a classic archive lister: walk the tree,
+//! print type/size/mtime per entry (optionally a content hash), and compare
+//! hashes against a saved manifest to report what changed between two runs.
+
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+use fnv::FnvHashMap;
+
+use crate::diesel_hash::hash_level;
+use super::{FsError, FsReadHandle, ReadOnlyFs};
+
+pub struct ListEntry {
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modification_time: SystemTime,
+    pub hash: Option<u64>,
+}
+
+/// Recursively walks `fs` depth-first starting from `root` (usually `""`),
+/// producing one [`ListEntry`] per file and directory encountered. Hashing
+/// is the expensive part of a walk, so it only happens when `with_hash` is
+/// set - the hash is computed by reading the whole file through `read_at`
+/// and running the buffer through [`hash_level`] at level 0.
+pub fn walk(fs: &dyn ReadOnlyFs, root: &str, with_hash: bool) -> Result<Vec<ListEntry>, FsError> {
+    let mut out = Vec::new();
+    walk_into(fs, root, with_hash, &mut out)?;
+    Ok(out)
+}
+
+fn walk_into(fs: &dyn ReadOnlyFs, path: &str, with_hash: bool, out: &mut Vec<ListEntry>) -> Result<(), FsError> {
+    let handle = fs.open_readable(path, "")?;
+    let info = handle.get_file_info()?;
+
+    if info.is_dir {
+        out.push(ListEntry {
+            path: path.to_owned(), is_dir: true, size: 0,
+            modification_time: info.last_write_time, hash: None
+        });
+        for entry in handle.find_files()? {
+            let child = if path.is_empty() { entry.name.clone() } else { format!("{}/{}", path, entry.name) };
+            walk_into(fs, &child, with_hash, out)?;
+        }
+    }
+    else {
+        let hash = if with_hash { Some(hash_handle(handle.as_ref())?) } else { None };
+        out.push(ListEntry {
+            path: path.to_owned(), is_dir: false, size: info.file_size,
+            modification_time: info.last_write_time, hash
+        });
+    }
+    Ok(())
+}
+
+fn hash_handle(handle: &dyn FsReadHandle) -> Result<u64, FsError> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 65536];
+    let mut offset = 0u64;
+    loop {
+        let n = handle.read_at(&mut chunk, offset)?;
+        if n == 0 { break; }
+        buf.extend_from_slice(&chunk[..n]);
+        offset += n as u64;
+    }
+    Ok(hash_level(&buf, 0))
+}
+
+/// ANSI styling for a single entry's path column, roughly matching `ls
+/// --color`'s defaults: directories in bold blue, everything else plain.
+fn style_name(entry: &ListEntry, color: bool) -> String {
+    if !color { return entry.path.clone(); }
+    if entry.is_dir { format!("\x1b[1;34m{}\x1b[0m", entry.path) }
+    else { entry.path.clone() }
+}
+
+/// Prints `entries` in long form: type, size, modification time, optional
+/// hash column, then path.
+pub fn print_long(entries: &[ListEntry], color: bool) {
+    for entry in entries {
+        let kind = if entry.is_dir { 'd' } else { '-' };
+        let mtime: chrono::DateTime<chrono::Utc> = entry.modification_time.into();
+        let mtime = mtime.format("%F %H:%M:%S");
+        match entry.hash {
+            Some(h) => println!("{} {:>12} {} {:016x} {}", kind, entry.size, mtime, h, style_name(entry, color)),
+            None => println!("{} {:>12} {} {}", kind, entry.size, mtime, style_name(entry, color)),
+        }
+    }
+}
+
+/// Prints `entries` in short form: just the path, one per line.
+pub fn print_short(entries: &[ListEntry], color: bool) {
+    for entry in entries {
+        println!("{}", style_name(entry, color));
+    }
+}
+
+#[derive(Debug)]
+pub enum VerifyDiff {
+    Added(String),
+    Removed(String),
+    Changed(String),
+}
+
+/// Loads a manifest saved by [`save_manifest`]: one `hash size path` line
+/// per file, hash printed as 16 hex digits.
+pub fn load_manifest(path: &Path) -> io::Result<FnvHashMap<String, (u64, u64)>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut out = FnvHashMap::default();
+    for line in text.lines() {
+        let mut parts = line.splitn(3, ' ');
+        let hash = parts.next().and_then(|s| u64::from_str_radix(s, 16).ok());
+        let size = parts.next().and_then(|s| s.parse().ok());
+        let name = parts.next();
+        if let (Some(hash), Some(size), Some(name)) = (hash, size, name) {
+            out.insert(name.to_owned(), (hash, size));
+        }
+    }
+    Ok(out)
+}
+
+/// Saves `entries` (which must have hashes - i.e. came from a [`walk`] with
+/// `with_hash: true`) as a manifest [`load_manifest`] can read back. Skips
+/// writing if `path` already exists with identical contents, so a `--verify`
+/// loop run alongside an unrelated `--save-manifest` doesn't dirty the file
+/// (and whatever's tracking it in version control) on every run.
+pub fn save_manifest(entries: &[ListEntry], path: &Path) -> io::Result<()> {
+    use std::fmt::Write as _;
+    let mut ordered: Vec<&ListEntry> = entries.iter().filter(|e| !e.is_dir).collect();
+    ordered.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut text = String::new();
+    for entry in ordered {
+        let hash = entry.hash.expect("save_manifest requires entries with hashes");
+        writeln!(text, "{:016x} {} {}", hash, entry.size, entry.path).unwrap();
+    }
+
+    if let Ok(existing) = std::fs::read_to_string(path) {
+        if existing == text { return Ok(()); }
+    }
+    std::fs::write(path, text)
+}
+
+/// Compares `entries` against a loaded `manifest`, reporting every file
+/// that's new, gone, or whose hash or size no longer matches.
+pub fn verify(entries: &[ListEntry], manifest: &FnvHashMap<String, (u64, u64)>) -> Vec<VerifyDiff> {
+    let mut diffs = Vec::new();
+    let mut seen = FnvHashMap::default();
+
+    for entry in entries {
+        if entry.is_dir { continue; }
+        seen.insert(entry.path.clone(), ());
+        match manifest.get(&entry.path) {
+            None => diffs.push(VerifyDiff::Added(entry.path.clone())),
+            Some(&(hash, size)) => {
+                let changed = entry.hash.map(|h| h != hash).unwrap_or(false) || entry.size != size;
+                if changed { diffs.push(VerifyDiff::Changed(entry.path.clone())); }
+            }
+        }
+    }
+
+    let mut removed: Vec<&String> = manifest.keys().filter(|k| !seen.contains_key(*k)).collect();
+    removed.sort();
+    diffs.extend(removed.into_iter().map(|k| VerifyDiff::Removed(k.clone())));
+
+    diffs
+}
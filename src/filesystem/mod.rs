@@ -17,6 +17,15 @@ use std::time::SystemTime;
 pub mod teststub;
 pub mod raw_bundledb;
 pub mod transcoder;
+pub mod docket;
+pub mod writable_cache;
+pub mod overlay_fs;
+pub mod nested_archive_fs;
+pub mod unhash_fs;
+pub mod listing;
+pub mod extract;
+#[cfg(feature="fuse")]
+pub mod fuse_adapter;
 
 /// Trait of read-only filesystems
 /// 
@@ -38,6 +47,12 @@ pub trait FsReadHandle : Send + Sync {
     fn find_files(&self) -> Result<Box<dyn Iterator<Item=FsDirEntry>>, FsError>;
     fn list_streams(&self) -> Result<Box<dyn Iterator<Item=FsStreamEntry>>, FsError>;
     fn get_file_info(&self) -> Result<FsFileInfo, FsError>;
+
+    /// Extended attributes to expose on this file, e.g. `user.diesel.hash` -
+    /// provenance a mount's users can read with `getfattr`/equivalent
+    /// without a separate lookup. Empty by default; only [`raw_bundledb`](super::raw_bundledb)'s
+    /// `RawFileHandle` actually has Diesel metadata to report.
+    fn list_xattrs(&self) -> Vec<(String, Vec<u8>)> { Vec::new() }
 }
 
 #[derive(Clone)]
@@ -87,5 +102,10 @@ pub enum FsError {
     IsDirectory,
     NotFound,
     ReadError,
-    OsError(i32)
+    OsError(i32),
+
+    /// The filesystem recognised what it was looking at, but doesn't (yet)
+    /// know how to produce data for it - e.g. a compression method a
+    /// container reader hasn't implemented.
+    Unsupported,
 }
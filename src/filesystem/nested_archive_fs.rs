@@ -0,0 +1,314 @@
+//! Transparent mounting of archive files nested inside another [`ReadOnlyFs`], so a path
+//! that walks through e.g. `some/dir/data.zip/readme.txt` resolves without the caller
+//! having to extract `data.zip` first.
+//!
+//! The only container format understood right now is ZIP, and only its `stored`
+//! (uncompressed) entry method - there's no inflate implementation anywhere in this tree
+//! and nothing to vendor one from, so a compressed member reads back as
+//! [`FsError::Unsupported`] rather than silently returning garbage. `stored` entries are
+//! served straight off the backing handle's own `read_at`, so reads stay as lazy and
+//! seekable as whatever `inner` already provides - there's no upfront decompression pass
+//! to go stale or to pay for unless the member is actually read.
+//!
+//! Archives can nest (a zip inside a zip's stored member, say); each recognised boundary
+//! bumps a nesting depth that gets folded into the top byte of the synthetic file indices
+//! this layer hands out, per the convention described on [`super`].
+
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use super::{FsDirEntry, FsError, FsFileInfo, FsReadHandle, FsStreamEntry, ReadOnlyFs};
+
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const METHOD_STORED: u16 = 0;
+
+/// A `ReadOnlyFs` wrapping `inner`, making any ZIP archive found in it browsable as a
+/// directory tree in its own right.
+pub struct NestedArchiveFs {
+    inner: Arc<dyn ReadOnlyFs>,
+}
+
+impl NestedArchiveFs {
+    pub fn new(inner: Arc<dyn ReadOnlyFs>) -> NestedArchiveFs {
+        NestedArchiveFs { inner }
+    }
+}
+
+impl ReadOnlyFs for NestedArchiveFs {
+    fn open_readable(&self, path: &str, stream: &str) -> Result<Arc<dyn FsReadHandle>, FsError> {
+        let normalized = path.replace('\\', "/");
+        let normalized = normalized.trim_start_matches('/');
+
+        // The path may name a container itself (`foo.zip`), not just something inside one -
+        // in which case it should browse as a directory rather than hand back raw bytes.
+        if stream == "" && is_recognized_container(normalized) {
+            if let Ok(container) = self.inner.open_readable(path, "") {
+                if !container.is_dir() {
+                    let info = container.get_file_info()?;
+                    let src: Arc<dyn ByteSource> = Arc::new(HandleSource(container));
+                    return open_in_archive(src, info.file_index, 1, "", stream);
+                }
+            }
+        }
+
+        if let Ok(handle) = self.inner.open_readable(path, stream) {
+            return Ok(handle);
+        }
+
+        for (i, c) in normalized.char_indices() {
+            if c != '/' { continue; }
+            let prefix = &normalized[..i];
+            let Ok(container) = self.inner.open_readable(prefix, "") else { continue };
+            if container.is_dir() { continue; }
+
+            let info = container.get_file_info()?;
+            let src: Arc<dyn ByteSource> = Arc::new(HandleSource(container));
+            return open_in_archive(src, info.file_index, 1, &normalized[i + 1..], stream);
+        }
+
+        Err(FsError::NotFound)
+    }
+}
+
+fn is_recognized_container(path: &str) -> bool {
+    path.rsplit('.').next().is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// A lazily-read, byte-addressable span, either a whole backing `FsReadHandle` or a
+/// `stored` member carved out of one - so that an archive nested inside another archive's
+/// member is read the same way as a top-level one, without the container parser caring
+/// which kind it's looking at.
+trait ByteSource: Send + Sync {
+    fn len(&self) -> u64;
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize, FsError>;
+}
+
+struct HandleSource(Arc<dyn FsReadHandle>);
+impl ByteSource for HandleSource {
+    fn len(&self) -> u64 {
+        self.0.get_file_info().map(|i| i.file_size).unwrap_or(0)
+    }
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize, FsError> {
+        self.0.read_at(buf, offset)
+    }
+}
+
+struct MemberSource {
+    parent: Arc<dyn ByteSource>,
+    data_offset: u64,
+    size: u64,
+}
+impl ByteSource for MemberSource {
+    fn len(&self) -> u64 { self.size }
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize, FsError> {
+        if offset >= self.size { return Ok(0); }
+        let n = (buf.len() as u64).min(self.size - offset) as usize;
+        self.parent.read_at(&mut buf[..n], self.data_offset + offset)
+    }
+}
+
+fn read_exact_at(src: &dyn ByteSource, offset: u64, len: usize) -> Result<Vec<u8>, FsError> {
+    let mut buf = vec![0u8; len];
+    let mut got = 0;
+    while got < len {
+        let n = src.read_at(&mut buf[got..], offset + got as u64)?;
+        if n == 0 { return Err(FsError::FileCorrupt); }
+        got += n;
+    }
+    Ok(buf)
+}
+
+fn u16_at(b: &[u8], at: usize) -> u16 { u16::from_le_bytes(b[at..at + 2].try_into().unwrap()) }
+fn u32_at(b: &[u8], at: usize) -> u32 { u32::from_le_bytes(b[at..at + 4].try_into().unwrap()) }
+
+struct ZipMember {
+    name: String,
+    method: u16,
+    uncompressed_size: u64,
+    local_header_offset: u64,
+}
+
+/// Reads the end-of-central-directory record and central directory of a ZIP archive.
+/// Deliberately doesn't scan backwards for the end-of-central-directory signature: it
+/// requires the record to be the last 22 bytes of the file, i.e. a zero-length archive
+/// comment. That covers anything this tool itself would ever produce; a hand-crafted
+/// archive with a comment isn't recognised and reads back as `FsError::NotFound`.
+fn parse_zip_index(src: &dyn ByteSource) -> Result<Vec<ZipMember>, FsError> {
+    let size = src.len();
+    if size < 22 { return Err(FsError::NotFound); }
+
+    let eocd = read_exact_at(src, size - 22, 22)?;
+    if u32_at(&eocd, 0) != EOCD_SIGNATURE { return Err(FsError::NotFound); }
+    if u16_at(&eocd, 20) != 0 { return Err(FsError::Unsupported); }
+
+    let entry_count = u16_at(&eocd, 10) as usize;
+    let cd_size = u32_at(&eocd, 12) as usize;
+    let cd_offset = u32_at(&eocd, 16) as u64;
+
+    let cd = read_exact_at(src, cd_offset, cd_size)?;
+    let mut members = Vec::with_capacity(entry_count);
+    let mut pos = 0usize;
+    for _ in 0..entry_count {
+        if pos + 46 > cd.len() || u32_at(&cd, pos) != CENTRAL_DIRECTORY_SIGNATURE {
+            return Err(FsError::FileCorrupt);
+        }
+
+        let method = u16_at(&cd, pos + 10);
+        let uncompressed_size = u32_at(&cd, pos + 24) as u64;
+        let filename_len = u16_at(&cd, pos + 28) as usize;
+        let extra_len = u16_at(&cd, pos + 30) as usize;
+        let comment_len = u16_at(&cd, pos + 32) as usize;
+        let local_header_offset = u32_at(&cd, pos + 42) as u64;
+
+        let name_start = pos + 46;
+        let name = String::from_utf8_lossy(&cd[name_start..name_start + filename_len]).into_owned();
+
+        members.push(ZipMember { name, method, uncompressed_size, local_header_offset });
+        pos = name_start + filename_len + extra_len + comment_len;
+    }
+
+    Ok(members)
+}
+
+/// The central directory's offset points at a member's local file header, not its data -
+/// and the local header repeats the name/extra fields at a length that can differ from the
+/// central directory's copy, so the payload's real start has to be read off the local
+/// header itself. Only done for a member that's actually being opened, not during listing.
+fn resolve_data_offset(src: &dyn ByteSource, local_header_offset: u64) -> Result<u64, FsError> {
+    let header = read_exact_at(src, local_header_offset, 30)?;
+    if u32_at(&header, 0) != LOCAL_HEADER_SIGNATURE { return Err(FsError::FileCorrupt); }
+    let name_len = u16_at(&header, 26) as u64;
+    let extra_len = u16_at(&header, 28) as u64;
+    Ok(local_header_offset + 30 + name_len + extra_len)
+}
+
+/// Folds `depth` into the top byte of a synthetic serial, per the layering convention
+/// described on [`super`], so a member's index can't collide with the container's own or
+/// with another nesting level's.
+fn make_file_index(depth: u8, base: u64, ordinal: u32) -> u64 {
+    let payload = base ^ (ordinal as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    ((depth as u64) << 56) | (payload & 0x00FF_FFFF_FFFF_FFFF)
+}
+
+fn open_in_archive(src: Arc<dyn ByteSource>, base_file_index: u64, depth: u8, rest: &str, stream: &str) -> Result<Arc<dyn FsReadHandle>, FsError> {
+    let members = Arc::new(parse_zip_index(src.as_ref())?);
+
+    if rest.is_empty() {
+        return Ok(Arc::new(ZipDirHandle { members, prefix: String::new(), base_file_index, depth }));
+    }
+
+    if let Some((ordinal, member)) = members.iter().enumerate().find(|(_, m)| m.name == rest) {
+        if stream != "" { return Err(FsError::NotFound); }
+        if member.method != METHOD_STORED { return Err(FsError::Unsupported); }
+        let data_offset = resolve_data_offset(src.as_ref(), member.local_header_offset)?;
+        return Ok(Arc::new(ZipMemberHandle {
+            src,
+            data_offset,
+            size: member.uncompressed_size,
+            file_index: make_file_index(depth, base_file_index, ordinal as u32),
+        }));
+    }
+
+    let dir_prefix = format!("{}/", rest.trim_end_matches('/'));
+    if members.iter().any(|m| m.name.starts_with(&dir_prefix)) {
+        return Ok(Arc::new(ZipDirHandle { members, prefix: dir_prefix, base_file_index, depth }));
+    }
+
+    for (i, c) in rest.char_indices() {
+        if c != '/' { continue; }
+        let candidate = &rest[..i];
+        let Some((ordinal, member)) = members.iter().enumerate().find(|(_, m)| m.name == candidate) else { continue };
+        if member.method != METHOD_STORED { return Err(FsError::Unsupported); }
+        let data_offset = resolve_data_offset(src.as_ref(), member.local_header_offset)?;
+        let nested: Arc<dyn ByteSource> = Arc::new(MemberSource { parent: src.clone(), data_offset, size: member.uncompressed_size });
+        let nested_base = make_file_index(depth, base_file_index, ordinal as u32);
+        return open_in_archive(nested, nested_base, depth + 1, &rest[i + 1..], stream);
+    }
+
+    Err(FsError::NotFound)
+}
+
+struct ZipMemberHandle {
+    src: Arc<dyn ByteSource>,
+    data_offset: u64,
+    size: u64,
+    file_index: u64,
+}
+impl FsReadHandle for ZipMemberHandle {
+    fn is_dir(&self) -> bool { false }
+    fn len(&self) -> Option<usize> { Some(self.size as usize) }
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize, FsError> {
+        if offset >= self.size { return Ok(0); }
+        let n = (buf.len() as u64).min(self.size - offset) as usize;
+        self.src.read_at(&mut buf[..n], self.data_offset + offset)
+    }
+    fn find_files(&self) -> Result<Box<dyn Iterator<Item=FsDirEntry>>, FsError> {
+        Err(FsError::NotDirectory)
+    }
+    fn list_streams(&self) -> Result<Box<dyn Iterator<Item=FsStreamEntry>>, FsError> {
+        Ok(Box::new(std::iter::once(FsStreamEntry { name: String::new(), size: self.size as i64 })))
+    }
+    fn get_file_info(&self) -> Result<FsFileInfo, FsError> {
+        Ok(FsFileInfo {
+            is_dir: false,
+            read_only: true,
+            file_size: self.size,
+            file_index: self.file_index,
+            creation_time: SystemTime::UNIX_EPOCH,
+            last_write_time: SystemTime::UNIX_EPOCH,
+            last_access_time: SystemTime::UNIX_EPOCH,
+            number_of_links: 1,
+        })
+    }
+}
+
+struct ZipDirHandle {
+    members: Arc<Vec<ZipMember>>,
+    /// Empty for the archive root, else a `"some/dir/"` prefix of member names.
+    prefix: String,
+    base_file_index: u64,
+    depth: u8,
+}
+impl FsReadHandle for ZipDirHandle {
+    fn is_dir(&self) -> bool { true }
+    fn len(&self) -> Option<usize> { None }
+    fn read_at(&self, _buf: &mut [u8], _offset: u64) -> Result<usize, FsError> { Err(FsError::IsDirectory) }
+    fn find_files(&self) -> Result<Box<dyn Iterator<Item=FsDirEntry>>, FsError> {
+        let mut seen = std::collections::HashSet::new();
+        let mut out = Vec::new();
+        for m in self.members.iter() {
+            let Some(rel) = m.name.strip_prefix(self.prefix.as_str()) else { continue };
+            if rel.is_empty() { continue; }
+            let child = match rel.find('/') {
+                Some(i) => &rel[..i],
+                None => rel,
+            };
+            if !seen.insert(child.to_owned()) { continue; }
+            let is_dir = rel.len() > child.len();
+            out.push(FsDirEntry {
+                is_dir,
+                size: if is_dir { 0 } else { m.uncompressed_size },
+                modification_time: SystemTime::UNIX_EPOCH,
+                name: child.to_owned(),
+            });
+        }
+        Ok(Box::new(out.into_iter()))
+    }
+    fn list_streams(&self) -> Result<Box<dyn Iterator<Item=FsStreamEntry>>, FsError> {
+        Err(FsError::IsDirectory)
+    }
+    fn get_file_info(&self) -> Result<FsFileInfo, FsError> {
+        Ok(FsFileInfo {
+            is_dir: true,
+            read_only: true,
+            file_size: 0,
+            file_index: make_file_index(self.depth, self.base_file_index, 0),
+            creation_time: SystemTime::UNIX_EPOCH,
+            last_write_time: SystemTime::UNIX_EPOCH,
+            last_access_time: SystemTime::UNIX_EPOCH,
+            number_of_links: 1,
+        })
+    }
+}
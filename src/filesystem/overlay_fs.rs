@@ -0,0 +1,256 @@
+//! Copy-on-write writable overlay over a [`ReadOnlyFs`].
+//!
+//! Reads fall through to the wrapped filesystem unless the path has been
+//! written to (or deleted) in this overlay's scratch directory on the host,
+//! in which case the scratch copy wins. Writes never touch the wrapped
+//! filesystem; they're redirected into the scratch directory, mirroring the
+//! logical path so a recursive `find` over the scratch dir alone shows
+//! everything that's been changed. Deletions are recorded as whiteouts
+//! rather than actually removing anything, since the underlying bundle
+//! can't be edited in place.
+//!
+//! This only implements the filesystem-agnostic half of editable mounted
+//! bundles: neither mount adapter (the Dokan one in `bundlefs`, or
+//! [`super::fuse_adapter`]) uses it, and both still mount read-only.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use super::writable_cache::ReadWriteFs;
+use super::{FsDirEntry, FsError, FsFileInfo, FsReadHandle, FsStreamEntry, ReadOnlyFs};
+
+const WHITEOUT_FILE: &str = ".overlay-whiteouts";
+
+fn normalize(path: &str) -> String {
+    path.replace('\\', "/").trim_start_matches('/').to_owned()
+}
+
+/// A `ReadOnlyFs` (reads) and [`ReadWriteFs`] (writes) over `inner`, backed
+/// by a scratch directory on the host filesystem.
+pub struct OverlayFs {
+    inner: Arc<dyn ReadOnlyFs>,
+    scratch_dir: PathBuf,
+    whiteouts: Mutex<HashSet<String>>,
+    open_files: Mutex<std::collections::HashMap<u64, File>>,
+    next_handle: Mutex<u64>,
+}
+
+impl OverlayFs {
+    pub fn new(inner: Arc<dyn ReadOnlyFs>, scratch_dir: impl Into<PathBuf>) -> io::Result<OverlayFs> {
+        let scratch_dir = scratch_dir.into();
+        fs::create_dir_all(&scratch_dir)?;
+
+        let whiteouts = match fs::read_to_string(scratch_dir.join(WHITEOUT_FILE)) {
+            Ok(contents) => contents.lines().map(str::to_owned).collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e)
+        };
+
+        Ok(OverlayFs {
+            inner,
+            scratch_dir,
+            whiteouts: Mutex::new(whiteouts),
+            open_files: Mutex::new(std::collections::HashMap::new()),
+            next_handle: Mutex::new(1),
+        })
+    }
+
+    fn scratch_path(&self, path: &str) -> PathBuf {
+        self.scratch_dir.join(normalize(path))
+    }
+
+    fn persist_whiteouts(&self) -> io::Result<()> {
+        let whiteouts = self.whiteouts.lock().unwrap();
+        let contents = whiteouts.iter().cloned().collect::<Vec<_>>().join("\n");
+        fs::write(self.scratch_dir.join(WHITEOUT_FILE), contents)
+    }
+
+    /// Marks `path` as deleted: subsequent reads see `FsError::NotFound`
+    /// regardless of what `inner` has, until the entry is recreated.
+    pub fn delete(&self, path: &str) -> io::Result<()> {
+        let key = normalize(path);
+        let scratch = self.scratch_path(path);
+        if scratch.is_file() {
+            fs::remove_file(&scratch)?;
+        }
+        self.whiteouts.lock().unwrap().insert(key);
+        self.persist_whiteouts()
+    }
+}
+
+impl ReadOnlyFs for OverlayFs {
+    fn open_readable(&self, path: &str, stream: &str) -> Result<Arc<dyn FsReadHandle>, FsError> {
+        let key = normalize(path);
+        if stream == "" {
+            let scratch = self.scratch_path(path);
+            if scratch.is_file() {
+                return Ok(Arc::new(ScratchFileHandle::open(scratch)?))
+            }
+            if scratch.is_dir() {
+                return Ok(Arc::new(OverlayDirHandle {
+                    inner: self.inner.open_readable(path, "").ok(),
+                    scratch: Some(scratch),
+                    overlay: self,
+                    logical_path: key,
+                }))
+            }
+        }
+
+        if self.whiteouts.lock().unwrap().contains(&key) {
+            return Err(FsError::NotFound)
+        }
+
+        let handle = self.inner.open_readable(path, stream)?;
+        if handle.is_dir() {
+            Ok(Arc::new(OverlayDirHandle {
+                inner: Some(handle),
+                scratch: self.scratch_path(path).is_dir().then(|| self.scratch_path(path)),
+                overlay: self,
+                logical_path: key,
+            }))
+        }
+        else {
+            Ok(handle)
+        }
+    }
+}
+
+impl ReadWriteFs for OverlayFs {
+    fn create(&self, path: &str) -> Result<u64, FsError> {
+        let scratch = self.scratch_path(path);
+        if let Some(parent) = scratch.parent() {
+            fs::create_dir_all(parent).map_err(|_| FsError::ReadError)?;
+        }
+        let file = fs::OpenOptions::new().read(true).write(true).create(true).truncate(true)
+            .open(&scratch)
+            .map_err(|_| FsError::ReadError)?;
+
+        self.whiteouts.lock().unwrap().remove(&normalize(path));
+
+        let mut next = self.next_handle.lock().unwrap();
+        let handle = *next;
+        *next += 1;
+        self.open_files.lock().unwrap().insert(handle, file);
+        Ok(handle)
+    }
+
+    fn write_at(&self, handle: u64, buf: &[u8], offset: u64) -> Result<usize, FsError> {
+        let mut files = self.open_files.lock().unwrap();
+        let file = files.get_mut(&handle).ok_or(FsError::NotFound)?;
+        file.seek(SeekFrom::Start(offset)).map_err(|_| FsError::ReadError)?;
+        file.write(buf).map_err(|_| FsError::ReadError)
+    }
+
+    fn flush(&self, handle: u64) -> Result<(), FsError> {
+        let mut files = self.open_files.lock().unwrap();
+        let file = files.get_mut(&handle).ok_or(FsError::NotFound)?;
+        file.flush().map_err(|_| FsError::ReadError)?;
+        self.persist_whiteouts().map_err(|_| FsError::ReadError)
+    }
+}
+
+struct ScratchFileHandle {
+    path: PathBuf,
+    len: u64,
+}
+impl ScratchFileHandle {
+    fn open(path: PathBuf) -> Result<ScratchFileHandle, FsError> {
+        let len = fs::metadata(&path).map_err(|_| FsError::ReadError)?.len();
+        Ok(ScratchFileHandle { path, len })
+    }
+}
+impl FsReadHandle for ScratchFileHandle {
+    fn is_dir(&self) -> bool { false }
+    fn len(&self) -> Option<usize> { Some(self.len as usize) }
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize, FsError> {
+        let mut f = File::open(&self.path).map_err(|_| FsError::ReadError)?;
+        f.seek(SeekFrom::Start(offset)).map_err(|_| FsError::ReadError)?;
+        f.read(buf).map_err(|_| FsError::ReadError)
+    }
+    fn find_files(&self) -> Result<Box<dyn Iterator<Item=FsDirEntry>>, FsError> {
+        Err(FsError::NotDirectory)
+    }
+    fn list_streams(&self) -> Result<Box<dyn Iterator<Item=FsStreamEntry>>, FsError> {
+        Ok(Box::new(std::iter::once(FsStreamEntry { name: String::new(), size: self.len as i64 })))
+    }
+    fn get_file_info(&self) -> Result<FsFileInfo, FsError> {
+        let meta = fs::metadata(&self.path).map_err(|_| FsError::ReadError)?;
+        Ok(FsFileInfo {
+            is_dir: false,
+            read_only: false,
+            file_size: meta.len(),
+            file_index: 0,
+            creation_time: meta.created().unwrap_or(SystemTime::UNIX_EPOCH),
+            last_write_time: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            last_access_time: meta.accessed().unwrap_or(SystemTime::UNIX_EPOCH),
+            number_of_links: 1
+        })
+    }
+}
+
+struct OverlayDirHandle<'o> {
+    inner: Option<Arc<dyn FsReadHandle>>,
+    scratch: Option<PathBuf>,
+    overlay: &'o OverlayFs,
+    logical_path: String,
+}
+impl<'o> FsReadHandle for OverlayDirHandle<'o> {
+    fn is_dir(&self) -> bool { true }
+    fn len(&self) -> Option<usize> { None }
+    fn read_at(&self, _buf: &mut [u8], _offset: u64) -> Result<usize, FsError> { Err(FsError::IsDirectory) }
+    fn find_files(&self) -> Result<Box<dyn Iterator<Item=FsDirEntry>>, FsError> {
+        let mut seen = HashSet::new();
+        let mut merged = Vec::new();
+
+        if let Some(scratch) = &self.scratch {
+            for entry in fs::read_dir(scratch).map_err(|_| FsError::ReadError)? {
+                let entry = entry.map_err(|_| FsError::ReadError)?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if name == WHITEOUT_FILE { continue; }
+                let meta = entry.metadata().map_err(|_| FsError::ReadError)?;
+                seen.insert(name.clone());
+                merged.push(FsDirEntry {
+                    is_dir: meta.is_dir(),
+                    size: meta.len(),
+                    modification_time: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                    name,
+                });
+            }
+        }
+
+        if let Some(inner) = &self.inner {
+            let whiteouts = self.overlay.whiteouts.lock().unwrap();
+            for fd in inner.find_files()? {
+                if seen.contains(&fd.name) { continue; }
+                let child_logical = format!("{}/{}", self.logical_path, fd.name);
+                if whiteouts.contains(&child_logical) { continue; }
+                merged.push(fd);
+            }
+        }
+
+        Ok(Box::new(merged.into_iter()))
+    }
+    fn list_streams(&self) -> Result<Box<dyn Iterator<Item=FsStreamEntry>>, FsError> {
+        Err(FsError::IsDirectory)
+    }
+    fn get_file_info(&self) -> Result<FsFileInfo, FsError> {
+        if let Some(scratch) = &self.scratch {
+            let meta = fs::metadata(scratch).map_err(|_| FsError::ReadError)?;
+            return Ok(FsFileInfo {
+                is_dir: true,
+                read_only: false,
+                file_size: 0,
+                file_index: 0,
+                creation_time: meta.created().unwrap_or(SystemTime::UNIX_EPOCH),
+                last_write_time: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                last_access_time: meta.accessed().unwrap_or(SystemTime::UNIX_EPOCH),
+                number_of_links: 1
+            })
+        }
+        self.inner.as_ref().ok_or(FsError::NotFound)?.get_file_info()
+    }
+}
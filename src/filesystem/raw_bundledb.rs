@@ -1,22 +1,130 @@
 use std::cmp::min;
 use std::convert::TryInto;
 use std::fs;
-use std::io::{prelude::*, SeekFrom};
-use std::path::PathBuf;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
+use lru::LruCache;
+use memmap2::Mmap;
+
 use crate::bundles::database::{Database, DatabaseItem, HashStrKey, ItemType};
 use crate::diesel_hash;
 use super::{ReadOnlyFs, FsReadHandle, FsDirEntry, FsError, FsFileInfo, FsStreamEntry};
 
+/// One directory entry in [`DirIndex`]: `parent` and `name_hash` together
+/// form the search key, `child` is the item it resolves to.
+#[derive(Debug, Clone, Copy)]
+struct DirIndexEntry {
+    parent: u32,
+    name_hash: u64,
+    child: u32
+}
+
+/// Flat, sorted-by-`(parent, name_hash)` index over every folder's direct
+/// children, built once at mount time so each path component along a walk
+/// resolves via a binary search instead of re-deriving `DatabaseItem::children()`
+/// (which itself is already an O(1) slice, but still means re-walking a
+/// `ChildIterator` and re-hashing the whole remaining path per lookup).
+struct DirIndex {
+    entries: Vec<DirIndexEntry>
+}
+impl DirIndex {
+    fn build(database: &Database) -> DirIndex {
+        let root = database.get_by_hashes(diesel_hash::EMPTY, diesel_hash::EMPTY, diesel_hash::EMPTY);
+        let mut entries = Vec::new();
+        if let Some(root) = root {
+            let mut stack = vec![root.item_index()];
+            while let Some(parent_idx) = stack.pop() {
+                let parent = database.get_by_inode(parent_idx);
+                for child in parent.children() {
+                    let name_hash = diesel_hash::from_str(&key_to_name(&child.key()));
+                    entries.push(DirIndexEntry { parent: parent_idx, name_hash, child: child.item_index() });
+                    if let ItemType::Folder = child.item_type() {
+                        stack.push(child.item_index());
+                    }
+                }
+            }
+        }
+        entries.sort_unstable_by_key(|e| (e.parent, e.name_hash));
+        DirIndex { entries }
+    }
+
+    fn root() -> u32 { 0 }
+
+    /// Binary-searches for `name_hash` among `parent`'s children, returning
+    /// the child's item index if found.
+    fn find(&self, parent: u32, name_hash: u64) -> Option<u32> {
+        let idx = self.entries.partition_point(|e| (e.parent, e.name_hash) < (parent, name_hash));
+        let entry = self.entries.get(idx)?;
+        if entry.parent == parent && entry.name_hash == name_hash {
+            Some(entry.child)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Resolves a full `/`-separated path by walking component-by-component
+    /// from `start`, used as the fast path in [`BundleFs::open_readable`].
+    fn resolve(&self, start: u32, path: &str) -> Option<u32> {
+        let mut current = start;
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            current = self.find(current, diesel_hash::from_str(component))?;
+        }
+        Some(current)
+    }
+}
+
+/// How many distinct backing bundle paths [`FileHandlePool`] keeps an open,
+/// shared file descriptor for at once. The Payday bundle layout backs
+/// thousands of virtual files with only a few dozen physical archives, so
+/// this comfortably covers a whole mount without pinning open every archive
+/// the game ships forever.
+const FILE_POOL_CAPACITY: usize = 64;
+
+/// Shared, reference-counted open files keyed by backing bundle path, so the
+/// thousands of virtual files one archive backs don't each pay for their own
+/// fd - every [`RawFileHandle`] whose bytes live in the same archive shares
+/// one [`fs::File`] and reads it positionally (see [`positional_read`])
+/// rather than taking it in turn under a lock. Bounded by an LRU so a
+/// long-running mount doesn't accumulate an unbounded number of open
+/// archives.
+struct FileHandlePool {
+    cache: Mutex<LruCache<PathBuf, Arc<fs::File>>>
+}
+
+impl FileHandlePool {
+    fn new(capacity: usize) -> FileHandlePool {
+        FileHandlePool { cache: Mutex::new(LruCache::new(NonZeroUsize::new(capacity).unwrap())) }
+    }
+
+    /// Returns the shared handle for `path`, opening and caching it first if
+    /// this is the first time it's been asked for.
+    fn get(&self, path: &Path) -> io::Result<Arc<fs::File>> {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(file) = cache.get(&path.to_path_buf()) {
+            return Ok(file.clone());
+        }
+        let file = Arc::new(fs::File::open(path)?);
+        cache.put(path.to_owned(), file.clone());
+        Ok(file)
+    }
+}
+
 pub struct BundleFs{
-    database: Arc<Database>
+    database: Arc<Database>,
+    dir_index: DirIndex,
+    file_pool: FileHandlePool
 }
 
 impl<'a> BundleFs {
     pub fn new(database: Arc<Database>) -> BundleFs {
-        BundleFs { database }
+        let dir_index = DirIndex::build(&database);
+        let file_pool = FileHandlePool::new(FILE_POOL_CAPACITY);
+        BundleFs { database, dir_index, file_pool }
     }
 }
 
@@ -29,22 +137,24 @@ impl<'ctx, 'fs: 'ctx> ReadOnlyFs for BundleFs {
         };
         let forwards_path = deslashed_path.replace('\\', "/");
 
-        let (db_path, lang, extn) = split_path_to_key(&forwards_path);
+        let item = match self.dir_index.resolve(DirIndex::root(), &forwards_path) {
+            Some(idx) => self.database.get_by_inode(idx),
+            None => {
+                let (db_path, lang, extn) = split_path_to_key(&forwards_path);
+                self.database.get_by_hashes(db_path, lang, extn).ok_or(FsError::NotFound)?
+            }
+        };
 
-        let item = self.database
-            .get_by_hashes(db_path, lang, extn)
-            .ok_or(FsError::NotFound)?;
-        
         match item.item_type() {
             ItemType::File => match stream {
-                "" => return Ok(Arc::new(RawFileHandle::new(&item))),
-                "raw" => return Ok(Arc::new(RawFileHandle::new(&item))),
-                //"info" => return Ok(file_info_stream(item)),
+                "" => return Ok(Arc::new(RawFileHandle::new(&item, &self.file_pool))),
+                "raw" => return Ok(Arc::new(RawFileHandle::new(&item, &self.file_pool))),
+                "info" => return Ok(file_info_stream(&item)),
                 _ => Err(FsError::NotFound)
             },
             ItemType::Folder => match stream {
                 "" => return Ok(Arc::new(FolderHandle::new(&item))),
-                //"info" => Ok(folder_info_stream(item)),
+                "info" => return Ok(folder_info_stream(&item)),
                 _ => Err(FsError::NotFound)
             }
         }
@@ -91,25 +201,272 @@ fn key_to_name(key: &HashStrKey) -> String {
     name
 }
 
+/// One decoded [`HashStrKey`] component, serialized as the hash plus
+/// whatever string the hashlist could reverse-look-up for it (`None` if it's
+/// never been seen as cleartext).
+#[derive(serde::Serialize)]
+struct KeyComponentInfo {
+    hash: u64,
+    text: Option<String>
+}
+impl From<crate::hashindex::HashedStr<'_>> for KeyComponentInfo {
+    fn from(h: crate::hashindex::HashedStr) -> KeyComponentInfo {
+        KeyComponentInfo { hash: h.hash, text: h.text.map(str::to_owned) }
+    }
+}
+
+/// Everything [`file_info_stream`]/[`folder_info_stream`] report about an
+/// item: its decoded key, where (if anywhere) its bytes physically live, and
+/// its timestamps - a debugging aid so a user can answer "where does this
+/// virtual file actually come from" without a separate tool.
+#[derive(serde::Serialize)]
+struct ItemInfo {
+    path: KeyComponentInfo,
+    language: KeyComponentInfo,
+    extension: KeyComponentInfo,
+    item_type: &'static str,
+    backing_path: Option<PathBuf>,
+    storage_offset: Option<usize>,
+    length: Option<usize>,
+    last_modified: SystemTime
+}
+
+impl ItemInfo {
+    fn for_item(item: &DatabaseItem) -> ItemInfo {
+        let (path, language, extension) = item.key();
+        let backing = item.get_backing_details();
+        ItemInfo {
+            path: path.into(),
+            language: language.into(),
+            extension: extension.into(),
+            item_type: match item.item_type() { ItemType::File => "file", ItemType::Folder => "folder" },
+            backing_path: backing.map(|b| b.0.to_owned()),
+            storage_offset: backing.map(|b| b.1),
+            length: backing.map(|b| b.2),
+            last_modified: item.last_modified()
+        }
+    }
+}
+
+/// Renders `item`'s [`ItemInfo`] as YAML - the contents of its `info` stream.
+/// Falls back to a comment describing the serialization failure rather than
+/// panicking, since a stream read shouldn't be able to crash the whole
+/// filesystem over a formatting bug.
+fn render_info(item: &DatabaseItem) -> Vec<u8> {
+    let info = ItemInfo::for_item(item);
+    match serde_yaml::to_string(&info) {
+        Ok(text) => text.into_bytes(),
+        Err(e) => format!("# failed to render item info: {}\n", e).into_bytes()
+    }
+}
+
+/// A synthetic, read-only text stream backed by an in-memory byte buffer -
+/// what `info` streams are made of, rather than a real file on disk.
+struct InfoStreamHandle {
+    bytes: Vec<u8>,
+    last_modified: SystemTime
+}
+
+impl FsReadHandle for InfoStreamHandle {
+    fn is_dir(&self) -> bool { false }
+    fn len(&self) -> Option<usize> { Some(self.bytes.len()) }
+    fn find_files(&self) -> Result<Box<dyn Iterator<Item=FsDirEntry>>, FsError> {
+        Err(FsError::NotDirectory)
+    }
+    fn list_streams(&self) -> Result<Box<dyn Iterator<Item=FsStreamEntry>>, FsError> {
+        Ok(Box::new(std::iter::once(
+            FsStreamEntry {
+                name: "".into(),
+                size: self.bytes.len().try_into().unwrap()
+            }
+        )))
+    }
+    fn get_file_info(&self) -> Result<FsFileInfo, FsError> {
+        Ok(FsFileInfo {
+            is_dir: false,
+            read_only: true,
+            file_size: self.bytes.len() as u64,
+            file_index: 0,
+            creation_time: self.last_modified,
+            last_write_time: self.last_modified,
+            last_access_time: self.last_modified,
+            number_of_links: 1
+        })
+    }
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize, FsError> {
+        let offset = offset as usize;
+        if offset >= self.bytes.len() { return Ok(0); }
+        let amount = min(buf.len(), self.bytes.len() - offset);
+        buf[0..amount].copy_from_slice(&self.bytes[offset..(offset + amount)]);
+        Ok(amount)
+    }
+}
+
+/// The `info` stream of a file: its decoded key plus where its bytes
+/// physically live (backing bundle path, offset, length).
+fn file_info_stream(item: &DatabaseItem) -> Arc<dyn FsReadHandle> {
+    Arc::new(InfoStreamHandle { bytes: render_info(item), last_modified: item.last_modified() })
+}
+
+/// The `info` stream of a folder: its decoded key and recursive
+/// `last_modified`; `backing_path`/`storage_offset`/`length` are `None`
+/// since a folder has no bytes of its own.
+fn folder_info_stream(item: &DatabaseItem) -> Arc<dyn FsReadHandle> {
+    Arc::new(InfoStreamHandle { bytes: render_info(item), last_modified: item.last_modified() })
+}
+
+/// Whether `path` resides on a network filesystem (NFS/CIFS/SMB and the
+/// like), where `mmap`ing a region is either unsupported or prone to
+/// surprising stalls/`SIGBUS`es if the share hiccups mid-read. Used once per
+/// [`RawFileHandle`] to decide whether to memory-map its backing bundle or
+/// fall back to plain seek+read.
+#[cfg(unix)]
+fn is_remote_mount(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    // Magic numbers from linux's statfs(2)/magic.h.
+    const NFS_SUPER_MAGIC: i64 = 0x6969;
+    const SMB_SUPER_MAGIC: i64 = 0x517B;
+    const CIFS_SUPER_MAGIC: i64 = 0xFF534D42u32 as i64;
+    const SMB2_MAGIC_NUMBER: i64 = 0xFE534D42u32 as i64;
+
+    let cpath = match std::ffi::CString::new(path.as_os_str().as_bytes()) {
+        Ok(c) => c,
+        Err(_) => return true
+    };
+
+    unsafe {
+        let mut stats: libc::statfs = std::mem::zeroed();
+        if libc::statfs(cpath.as_ptr(), &mut stats) != 0 {
+            // Can't tell - assume the safe, slower path.
+            return true;
+        }
+        let fs_type = stats.f_type as i64;
+        matches!(fs_type, NFS_SUPER_MAGIC | SMB_SUPER_MAGIC | CIFS_SUPER_MAGIC | SMB2_MAGIC_NUMBER)
+    }
+}
+
+#[cfg(windows)]
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetDriveTypeW(lpRootPathName: *const u16) -> u32;
+}
+
+/// As [`is_remote_mount`] above, but for Windows: resolves `path`'s drive
+/// root and asks the OS via `GetDriveTypeW`, since there's no statfs
+/// equivalent. UNC paths (`\\server\share\...`) are treated as remote
+/// outright, since they have no drive letter to resolve.
+#[cfg(windows)]
+fn is_remote_mount(path: &Path) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::{Component, Prefix};
+
+    const DRIVE_REMOTE: u32 = 4;
+
+    let canonical = match path.canonicalize() {
+        Ok(p) => p,
+        Err(_) => return true
+    };
+
+    let root = match canonical.components().next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::UNC(_, _) | Prefix::VerbatimUNC(_, _) => return true,
+            Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => format!("{}:\\", letter as char),
+            _ => return true
+        },
+        _ => return true
+    };
+
+    let wide: Vec<u16> = root.encode_utf16().chain(std::iter::once(0)).collect();
+    let drive_type = unsafe { GetDriveTypeW(wide.as_ptr()) };
+    drive_type == DRIVE_REMOTE
+}
+
+#[cfg(not(any(unix, windows)))]
+fn is_remote_mount(_path: &Path) -> bool {
+    // Can't detect it, so assume the worst and take the safe, slower path.
+    true
+}
+
+/// Reads `buf.len()` bytes (at most) from `file` starting at `offset`,
+/// without moving any shared seek position - the positional-read
+/// counterpart to a `seek`+`read` pair, safe to call concurrently on a
+/// [`fs::File`] shared via [`FileHandlePool`].
+#[cfg(unix)]
+fn positional_read(file: &fs::File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::os::unix::fs::FileExt;
+    file.read_at(buf, offset)
+}
+
+/// As [`positional_read`] above, but for Windows, via `seek_read`.
+#[cfg(windows)]
+fn positional_read(file: &fs::File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::os::windows::fs::FileExt;
+    file.seek_read(buf, offset)
+}
+
+/// Where a [`RawFileHandle`]'s bytes actually come from: a memory mapping of
+/// its backing bundle's `[storage_offset, storage_offset+length)` region
+/// when that's safe (`read_at` then becomes a lock-free `copy_from_slice`),
+/// or a handle shared out of [`FileHandlePool`] plus a positional read when
+/// the bundle lives on a remote/NFS-style mount (or the region is empty, or
+/// the mapping attempt simply failed). Decided once in
+/// [`RawFileHandle::new`] rather than per read.
+enum Backing {
+    Mapped(Mmap),
+    Pooled(Arc<fs::File>)
+}
+
 struct RawFileHandle {
     file_id: u64,
-    storage_path: PathBuf,
     storage_offset: usize,
     length: usize,
+    info_len: usize,
     last_modified: SystemTime,
-    backing_store: Mutex<Option<fs::File>>
+    backing: Backing,
+    path_hash: u64,
+    package_id: Option<u64>,
+    language: Option<String>
 }
 
 impl RawFileHandle {
-    fn new(item: &DatabaseItem) -> RawFileHandle {
+    fn new(item: &DatabaseItem, file_pool: &FileHandlePool) -> RawFileHandle {
         let back_deets = item.get_backing_details().unwrap();
+        let storage_path = back_deets.0;
+        let storage_offset = back_deets.1;
+        let length = back_deets.2;
+
+        let mapped = if length > 0 && !is_remote_mount(storage_path) {
+            fs::File::open(storage_path).ok()
+                .and_then(|file| unsafe {
+                    memmap2::MmapOptions::new()
+                        .offset(storage_offset as u64)
+                        .len(length)
+                        .map(&file)
+                        .ok()
+                })
+                .map(Backing::Mapped)
+        }
+        else {
+            None
+        };
+
+        let backing = mapped.unwrap_or_else(|| {
+            let file = file_pool.get(storage_path)
+                .unwrap_or_else(|e| panic!("Unable to open backing file {:?}: {}", storage_path, e));
+            Backing::Pooled(file)
+        });
+
         RawFileHandle {
             file_id: item.item_index() as u64,
-            storage_path: back_deets.0.to_owned(),
-            storage_offset: back_deets.1,
-            length: back_deets.2,
+            storage_offset,
+            length,
+            info_len: render_info(item).len(),
             last_modified: item.last_modified(),
-            backing_store: Mutex::new(None)
+            backing,
+            path_hash: item.path().hash,
+            package_id: item.backing_package_id(),
+            language: item.language().and_then(|h| h.text).map(str::to_owned)
         }
     }
 }
@@ -122,12 +479,23 @@ impl FsReadHandle for RawFileHandle {
     }
 
     fn list_streams(&self) -> Result<Box<dyn Iterator<Item=FsStreamEntry>>, FsError> {
-        Ok(Box::new(std::iter::once(
-            FsStreamEntry {
-                name: "".into(),
-                size: self.length.try_into().unwrap()
-            }
-        )))
+        Ok(Box::new(vec![
+            FsStreamEntry { name: "".into(), size: self.length.try_into().unwrap() },
+            FsStreamEntry { name: "info".into(), size: self.info_len.try_into().unwrap() }
+        ].into_iter()))
+    }
+
+    fn list_xattrs(&self) -> Vec<(String, Vec<u8>)> {
+        let mut xattrs = vec![
+            ("user.diesel.hash".to_owned(), format!("{:016x}", self.path_hash).into_bytes())
+        ];
+        if let Some(package_id) = self.package_id {
+            xattrs.push(("user.diesel.package".to_owned(), format!("{:016x}", package_id).into_bytes()));
+        }
+        if let Some(language) = &self.language {
+            xattrs.push(("user.diesel.language".to_owned(), language.clone().into_bytes()));
+        }
+        xattrs
     }
 
     fn get_file_info(&self) -> Result<FsFileInfo, FsError> {
@@ -144,39 +512,37 @@ impl FsReadHandle for RawFileHandle {
     }
 
     fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize, FsError> {
-        let mut mg = self.backing_store.try_lock().unwrap();
-        let backing = mg.get_or_insert_with(|| {
-            let file_result = fs::File::open(&self.storage_path);
-            match file_result {
-                Ok(file) => file,
-                // If opening fails, then the error is probably unrecoverable without
-                // restarting anyway.
-                Err(e) => panic!("Unable to read backing file {:?}: {}", self.storage_path, e)
-            }
-        });
-
-        let read_from = self.storage_offset + (offset as usize);
-        if read_from >= self.storage_offset + self.length {
+        let offset = offset as usize;
+        if offset >= self.length {
             return Ok(0);
         }
-        let amount_to_read = min(buf.len(), self.length - (offset as usize));
+        let amount_to_read = min(buf.len(), self.length - offset);
         if amount_to_read <= 0 { return Ok(0); }
 
-        let capped_buf = &mut buf[0..(amount_to_read)];
+        match &self.backing {
+            Backing::Mapped(mmap) => {
+                buf[0..amount_to_read].copy_from_slice(&mmap[offset..(offset + amount_to_read)]);
+                Ok(amount_to_read)
+            }
+            Backing::Pooled(file) => {
+                let read_from = self.storage_offset + offset;
+                let capped_buf = &mut buf[0..amount_to_read];
 
-        let res = backing.seek(SeekFrom::Start(read_from as u64))
-            .and_then(|_| backing.read(capped_buf));
-        return res.map_err(|e| {
-            match e.raw_os_error(){
-                Some(error) => FsError::OsError(error),
-                None => FsError::ReadError
+                positional_read(file, capped_buf, read_from as u64).map_err(|e| {
+                    match e.raw_os_error(){
+                        Some(error) => FsError::OsError(error),
+                        None => FsError::ReadError
+                    }
+                })
             }
-        });
+        }
     }
 }
 
 struct FolderHandle {
+    file_id: u64,
     last_modified: SystemTime,
+    info_len: usize,
     items : Vec<FsDirEntry>
 }
 impl FolderHandle {
@@ -193,7 +559,9 @@ impl FolderHandle {
             }
         }).collect();
         FolderHandle {
+            file_id: item.item_index() as u64,
             items,
+            info_len: render_info(item).len(),
             last_modified: item.last_modified()
         }
     }
@@ -211,7 +579,7 @@ impl FsReadHandle for FolderHandle {
         Ok(Box::new(std::iter::once(
             FsStreamEntry {
                 name: String::from("info"),
-                size: 0
+                size: self.info_len.try_into().unwrap()
             }
         )))
     }
@@ -220,10 +588,10 @@ impl FsReadHandle for FolderHandle {
             is_dir: true,
             read_only: true,
             file_size: 0,
-            file_index: 0,
-            creation_time: SystemTime::UNIX_EPOCH,
-            last_write_time: SystemTime::UNIX_EPOCH,
-            last_access_time: SystemTime::UNIX_EPOCH,
+            file_index: self.file_id,
+            creation_time: self.last_modified,
+            last_write_time: self.last_modified,
+            last_access_time: self.last_modified,
             number_of_links: 1
         })
     }
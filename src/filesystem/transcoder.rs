@@ -1,21 +1,53 @@
+use std::sync::OnceLock;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::io::Read;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
 use crate::hashindex::HashIndex;
+use super::writable_cache::ReadWriteFs;
 use super::{ReadOnlyFs, FsReadHandle, FsDirEntry, FsError, FsFileInfo, FsStreamEntry};
 
 pub struct TranscoderFs<'a> {
     hashlist: Arc<HashIndex>,
-    backing: Arc<dyn ReadOnlyFs + 'a>
+    backing: Arc<dyn ReadOnlyFs + 'a>,
+    /// Where packed (text-edited-back-to-binary) files get written. `None`
+    /// means this `TranscoderFs` is read-only, same as before the write path
+    /// existed: raw bundle archives have no writable backing in this tree,
+    /// so callers that want to repack something supply their own sink
+    /// (e.g. an [`overlay_fs::OverlayFs`](super::overlay_fs::OverlayFs)
+    /// wrapped around the same `backing`).
+    sink: Option<Arc<dyn ReadWriteFs + 'a>>,
+    pending: Mutex<HashMap<u64, PendingPack>>,
+    next_handle: Mutex<u64>
+}
+
+struct PendingPack {
+    path: String,
+    data: Vec<u8>
 }
 
 impl<'a> TranscoderFs<'a> {
     pub fn new(hashlist: Arc<HashIndex>, backing: Arc<dyn ReadOnlyFs + 'a>) -> TranscoderFs<'a> {
         TranscoderFs {
             hashlist,
-            backing
+            backing,
+            sink: None,
+            pending: Mutex::new(HashMap::new()),
+            next_handle: Mutex::new(1)
+        }
+    }
+
+    /// Like [`new`](Self::new), but with somewhere for [`ReadWriteFs::flush`]
+    /// to put the repacked bytes of an edited, displayed-format file.
+    pub fn new_writable(hashlist: Arc<HashIndex>, backing: Arc<dyn ReadOnlyFs + 'a>, sink: Arc<dyn ReadWriteFs + 'a>) -> TranscoderFs<'a> {
+        TranscoderFs {
+            hashlist,
+            backing,
+            sink: Some(sink),
+            pending: Mutex::new(HashMap::new()),
+            next_handle: Mutex::new(1)
         }
     }
 }
@@ -32,29 +64,42 @@ impl ReadOnlyFs for TranscoderFs<'_> {
             }
         }
 
+        if let Some(extra) = maybe_rule.and_then(|r| r.extra_streams.iter().find(|e| e.name == stream)) {
+            let backing_handle = self.backing.open_readable(&real_path, "")?;
+            if backing_handle.is_dir() { return Err(FsError::NotFound) }
+            let front_buf = read_and_convert(&backing_handle, &self.hashlist, extra.convert)?;
+            let info = backing_handle.get_file_info()?;
+            return Ok(Arc::new(VecFileHandle {
+                data: front_buf,
+                timestamp: info.creation_time,
+                file_id: info.file_index
+            }))
+        }
+
         let backing_handle = self.backing.open_readable(&real_path, if stream == "raw" { "" } else { stream })?;
         if backing_handle.is_dir() {
             Ok(Arc::new(FolderHandle { backing: backing_handle }))
         }
         else if stream == "" {
-            if let Some(converter) = maybe_rule.map(|r| r.transformer).flatten() {
-                let info = backing_handle.get_file_info().unwrap();
-                let mut back_buf = Vec::<u8>::new();
-                back_buf.resize(info.file_size as usize, 0);
-                backing_handle.read_at(&mut back_buf, 0)?;
-                let front_buf = converter(&self.hashlist, &back_buf);
-
-                let front_handle = VecFileHandle {
-                    data: front_buf,
-                    timestamp: info.creation_time,
-                    file_id: info.file_index
-                };
-
-                Ok(Arc::new(front_handle))
-            }
-            else {
-                Ok(backing_handle)
+            if let Some(rule) = maybe_rule {
+                if let Some(converter) = rule.transformer {
+                    let front_buf = read_and_convert(&backing_handle, &self.hashlist, converter)?;
+                    let info = backing_handle.get_file_info()?;
+
+                    let front_handle = ConvertedFileHandle {
+                        data: front_buf,
+                        timestamp: info.creation_time,
+                        file_id: info.file_index,
+                        hashlist: self.hashlist.clone(),
+                        backing: backing_handle,
+                        extra_streams: rule.extra_streams,
+                        extra_cache: OnceLock::new()
+                    };
+
+                    return Ok(Arc::new(front_handle))
+                }
             }
+            Ok(backing_handle)
         }
         else {
             Ok(backing_handle)
@@ -62,6 +107,59 @@ impl ReadOnlyFs for TranscoderFs<'_> {
     }
 }
 
+/// Write path for a `TranscoderFs`: accepts the displayed (text) form of a
+/// file, packs it back to the real binary format via the matching
+/// [`TranscodeRule::packer`], and hands the result to `sink` under the
+/// backing extension - the inverse of what `open_readable` does on the way
+/// out. Buffers each open handle's bytes in memory until `flush`, the same
+/// way [`super::writable_cache::CacheFs`] does, since packing needs the
+/// whole file rather than a byte range.
+impl ReadWriteFs for TranscoderFs<'_> {
+    fn create(&self, path: &str) -> Result<u64, FsError> {
+        let mut next = self.next_handle.lock().unwrap();
+        let handle = *next;
+        *next += 1;
+        self.pending.lock().unwrap().insert(handle, PendingPack { path: path.to_owned(), data: Vec::new() });
+        Ok(handle)
+    }
+
+    fn write_at(&self, handle: u64, buf: &[u8], offset: u64) -> Result<usize, FsError> {
+        let mut pending = self.pending.lock().unwrap();
+        let entry = pending.get_mut(&handle).ok_or(FsError::NotFound)?;
+        let end = offset as usize + buf.len();
+        if entry.data.len() < end {
+            entry.data.resize(end, 0);
+        }
+        entry.data[offset as usize..end].copy_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&self, handle: u64) -> Result<(), FsError> {
+        let entry = self.pending.lock().unwrap().remove(&handle).ok_or(FsError::NotFound)?;
+
+        let sink = self.sink.as_ref().ok_or(FsError::Unsupported)?;
+        let rule = TRANSCODE_RULES.iter().find(|r| entry.path.ends_with(r.displayed_extension)).ok_or(FsError::Unsupported)?;
+        let packer = rule.packer.ok_or(FsError::Unsupported)?;
+
+        let packed = packer(&self.hashlist, &entry.data).map_err(|_| FsError::FileCorrupt)?;
+
+        let mut backing_path = entry.path.clone();
+        backing_path.truncate(backing_path.len() - rule.displayed_extension.len());
+        backing_path.push_str(rule.backing_extension);
+
+        let backing_handle = sink.create(&backing_path)?;
+        sink.write_at(backing_handle, &packed, 0)?;
+        sink.flush(backing_handle)
+    }
+}
+
+fn read_and_convert(handle: &Arc<dyn FsReadHandle>, hashlist: &HashIndex, convert: fn(&HashIndex, &[u8]) -> Vec<u8>) -> Result<Vec<u8>, FsError> {
+    let info = handle.get_file_info()?;
+    let mut back_buf = vec![0u8; info.file_size as usize];
+    handle.read_at(&mut back_buf, 0)?;
+    Ok(convert(hashlist, &back_buf))
+}
+
 struct FolderHandle {
     backing: Arc<dyn FsReadHandle>
 }
@@ -140,6 +238,81 @@ impl FsReadHandle for VecFileHandle {
     }
 }
 
+/// Builds and names one derived representation of a converted file, exposed
+/// as an additional stream alongside the usual converted/raw pair, e.g.
+/// `:json` on a `.banksinfo` file.
+struct ExtraStream {
+    name: &'static str,
+    convert: fn(&HashIndex, &[u8]) -> Vec<u8>,
+}
+
+/// The default-stream handle for a file that was converted by a [`TranscodeRule`].
+/// Besides serving the converted bytes, it advertises `rule.extra_streams` via
+/// `list_streams` and builds each one's bytes at most once, the first time
+/// they're needed, then reuses that for the rest of the handle's lifetime.
+struct ConvertedFileHandle {
+    data: Vec<u8>,
+    timestamp: SystemTime,
+    file_id: u64,
+    hashlist: Arc<HashIndex>,
+    backing: Arc<dyn FsReadHandle>,
+    extra_streams: &'static [ExtraStream],
+    extra_cache: OnceLock<Vec<(&'static str, Vec<u8>)>>,
+}
+impl ConvertedFileHandle {
+    fn extras(&self) -> Result<&[(&'static str, Vec<u8>)], FsError> {
+        if let Some(cached) = self.extra_cache.get() {
+            return Ok(cached)
+        }
+        let info = self.backing.get_file_info()?;
+        let mut raw = vec![0u8; info.file_size as usize];
+        self.backing.read_at(&mut raw, 0)?;
+        let built = self.extra_streams.iter()
+            .map(|e| (e.name, (e.convert)(&self.hashlist, &raw)))
+            .collect();
+        Ok(self.extra_cache.get_or_init(|| built))
+    }
+}
+impl FsReadHandle for ConvertedFileHandle {
+    fn is_dir(&self) -> bool { false }
+    fn len(&self) -> Option<usize> { Some(self.data.len()) }
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize, FsError> {
+        let ofs: usize = offset.try_into().unwrap_or(usize::MAX);
+        if ofs > self.data.len() {
+            return Err(FsError::PastEnd)
+        }
+        let mut bs = &self.data[ofs..];
+        bs.read(buf).or(Err(FsError::FileCorrupt))
+    }
+    fn find_files(&self) -> Result<Box<dyn Iterator<Item=FsDirEntry>>, FsError> {
+        Err(FsError::NotDirectory)
+    }
+    fn list_streams(&self) -> Result<Box<dyn Iterator<Item=FsStreamEntry>>, FsError> {
+        let mut entries = vec![
+            FsStreamEntry { name: String::from(""), size: self.data.len() as i64 },
+            FsStreamEntry { name: String::from("raw"), size: self.backing.len().unwrap_or(0) as i64 },
+        ];
+        if !self.extra_streams.is_empty() {
+            for (name, data) in self.extras()? {
+                entries.push(FsStreamEntry { name: (*name).to_owned(), size: data.len() as i64 });
+            }
+        }
+        Ok(Box::new(entries.into_iter()))
+    }
+    fn get_file_info(&self) -> Result<FsFileInfo, FsError> {
+        Ok(FsFileInfo {
+            is_dir: false,
+            read_only: true,
+            file_size: self.data.len() as u64,
+            file_index: self.file_id,
+            creation_time: self.timestamp,
+            last_write_time: self.timestamp,
+            last_access_time: self.timestamp,
+            number_of_links: 1
+        })
+    }
+}
+
 macro_rules! struct_from_tuple_table {
     (@make_row $sn:ident {$($sin:ident : $sit:ty),*} ($($ri:expr),*) ) => {
         $sn { $($sin: $ri,)* }
@@ -160,66 +333,170 @@ struct_from_tuple_table! {
         backing_extension: &'static str,
         displayed_extension: &'static str,
         hide_original: bool,
-        transformer: Option<fn(&HashIndex, &[u8]) -> Vec<u8>>
+        transformer: Option<fn(&HashIndex, &[u8]) -> Vec<u8>>,
+        /// Inverse of `transformer`: repacks an edited displayed-format file
+        /// back to binary scriptdata, for [`ReadWriteFs::flush`] above. Only
+        /// meaningful for the scriptdata rows; everything else is `None`.
+        packer: Option<fn(&HashIndex, &[u8]) -> anyhow::Result<Vec<u8>>>,
+        extra_streams: &'static [ExtraStream]
     }
 
     TRANSCODE_RULES = [
         // renames
-        (".movie"           , ".bik"             , true , None                       ),
-        (".texture"         , ".dds"             , true , None                       ),
-        (".stream"          , ".wem"             , true , None                       ),
+        (".movie"           , ".bik"             , true , None                       , None                 , &[]                  ),
+        (".texture"         , ".dds"             , true , None                       , None                 , &[]                  ),
+        (".stream"          , ".wem"             , true , None                       , None                 , &[]                  ),
 
         // non-scriptdata
-        (".strings"         , ".strings"         , true , Some(transcode_strings   ) ),
-        (".banksinfo"       , ".banksinfo"       , true , Some(transcode_banksinfo ) ),
-        
+        (".strings"         , ".strings"         , true , Some(transcode_strings   ) , None                 , &[]                  ),
+        (".banksinfo"       , ".banksinfo"       , true , Some(transcode_banksinfo ) , None                 , &BANKSINFO_STREAMS    ),
+        (".font"            , ".fnt"             , true , Some(transcode_font      ) , None                 , &[]                  ),
+
         // specific scriptdata files
-        ("mission.mission"  , "mission.mission"  , true , Some(transcode_sd_custom ) ),
-        ("world.world"      , "world.world"      , true , Some(transcode_sd_generic) ),
+        ("mission.mission"  , "mission.mission"  , true , Some(transcode_sd_custom ) , Some(pack_sd_custom ), &SCRIPTDATA_STREAMS  ),
+        ("world.world"      , "world.world"      , true , Some(transcode_sd_generic), Some(pack_sd_generic), &SCRIPTDATA_STREAMS  ),
 
         // extensions
-        (".achievement"     , ".achievement"     , true , Some(transcode_sd_custom ) ),
-        (".action_message"  , ".action_message"  , true , Some(transcode_sd_custom ) ),
-        (".credits"         , ".credits"         , true , Some(transcode_sd_custom ) ),
-        (".comment"         , ".comment"         , true , Some(transcode_sd_custom ) ),
-        (".continent"       , ".continent"       , true , Some(transcode_sd_custom ) ),
-        (".continents"      , ".continents"      , true , Some(transcode_sd_custom ) ),
-        (".cover_data"      , ".cover_data"      , true , Some(transcode_sd_generic) ),
-        (".dialog"          , ".dialog"          , true , Some(transcode_sd_custom ) ),
-        (".environment"     , ".environment"     , true , Some(transcode_sd_custom ) ),
-        (".hint"            , ".hint"            , true , Some(transcode_sd_custom ) ),
-        (".menu"            , ".menu"            , true , Some(transcode_sd_custom ) ),
-        (".mission"         , ".mission"         , true , Some(transcode_sd_generic) ),
-        (".nav_data"        , ".nav_data"        , true , Some(transcode_sd_generic) ),
-        (".objective"       , ".objective"       , true , Some(transcode_sd_custom ) ),
-        (".sequence_manager", ".sequence_manager", true , Some(transcode_sd_generic) ),
-        (".timeline"        , ".timeline"        , true , Some(transcode_sd_custom ) ),
-        (".world"           , ".world"           , true , Some(transcode_sd_generic) ),
-        (".world_cameras"   , ".world_cameras"   , true , Some(transcode_sd_custom ) ),
-        (".world_sounds"    , ".world_sounds"    , true , Some(transcode_sd_generic) )
-    ] 
+        (".achievement"     , ".achievement"     , true , Some(transcode_sd_custom ) , Some(pack_sd_custom ), &SCRIPTDATA_STREAMS  ),
+        (".action_message"  , ".action_message"  , true , Some(transcode_sd_custom ) , Some(pack_sd_custom ), &SCRIPTDATA_STREAMS  ),
+        (".credits"         , ".credits"         , true , Some(transcode_sd_custom ) , Some(pack_sd_custom ), &SCRIPTDATA_STREAMS  ),
+        (".comment"         , ".comment"         , true , Some(transcode_sd_custom ) , Some(pack_sd_custom ), &SCRIPTDATA_STREAMS  ),
+        (".continent"       , ".continent"       , true , Some(transcode_sd_custom ) , Some(pack_sd_custom ), &SCRIPTDATA_STREAMS  ),
+        (".continents"      , ".continents"      , true , Some(transcode_sd_custom ) , Some(pack_sd_custom ), &SCRIPTDATA_STREAMS  ),
+        (".cover_data"      , ".cover_data"      , true , Some(transcode_sd_generic), Some(pack_sd_generic), &SCRIPTDATA_STREAMS  ),
+        (".dialog"          , ".dialog"          , true , Some(transcode_sd_custom ) , Some(pack_sd_custom ), &SCRIPTDATA_STREAMS  ),
+        (".environment"     , ".environment"     , true , Some(transcode_sd_custom ) , Some(pack_sd_custom ), &SCRIPTDATA_STREAMS  ),
+        (".hint"            , ".hint"            , true , Some(transcode_sd_custom ) , Some(pack_sd_custom ), &SCRIPTDATA_STREAMS  ),
+        (".menu"            , ".menu"            , true , Some(transcode_sd_custom ) , Some(pack_sd_custom ), &SCRIPTDATA_STREAMS  ),
+        (".mission"         , ".mission"         , true , Some(transcode_sd_generic), Some(pack_sd_generic), &SCRIPTDATA_STREAMS  ),
+        (".nav_data"        , ".nav_data"        , true , Some(transcode_sd_generic), Some(pack_sd_generic), &SCRIPTDATA_STREAMS  ),
+        (".objective"       , ".objective"       , true , Some(transcode_sd_custom ) , Some(pack_sd_custom ), &SCRIPTDATA_STREAMS  ),
+        (".sequence_manager", ".sequence_manager", true , Some(transcode_sd_generic), Some(pack_sd_generic), &SCRIPTDATA_STREAMS  ),
+        (".timeline"        , ".timeline"        , true , Some(transcode_sd_custom ) , Some(pack_sd_custom ), &SCRIPTDATA_STREAMS  ),
+        (".world"           , ".world"           , true , Some(transcode_sd_generic), Some(pack_sd_generic), &SCRIPTDATA_STREAMS  ),
+        (".world_cameras"   , ".world_cameras"   , true , Some(transcode_sd_custom ) , Some(pack_sd_custom ), &SCRIPTDATA_STREAMS  ),
+        (".world_sounds"    , ".world_sounds"    , true , Some(transcode_sd_generic), Some(pack_sd_generic), &SCRIPTDATA_STREAMS  )
+    ]
 }
 
+static BANKSINFO_STREAMS: [ExtraStream; 1] = [
+    ExtraStream { name: "json", convert: banksinfo_to_json }
+];
+
+/// The `:cbor` alternate stream every scriptdata extension carries: a
+/// compact, typed binary view (see [`crate::formats::scriptdata::cbor`]) for
+/// tools that would rather parse that than either XML dump.
+static SCRIPTDATA_STREAMS: [ExtraStream; 1] = [
+    ExtraStream { name: "cbor", convert: transcode_sd_cbor }
+];
+
 fn transcode_strings(hi: &HashIndex, input: &[u8]) -> Vec<u8> {
     let mut buf = Vec::<u8>::with_capacity(input.len());
     crate::formats::string_table::bytes_to_json(hi, input, &mut buf).unwrap();
     buf
 }
 
-fn transcode_sd_generic(_hi: &HashIndex, input: &[u8]) -> Vec<u8> {
-    let doc = crate::formats::scriptdata::binary::from_binary(input, false);
-    let gx = crate::formats::scriptdata::generic_xml::dump(&doc.unwrap());
-    return gx.into_bytes();
+fn transcode_sd_generic(hi: &HashIndex, input: &[u8]) -> Vec<u8> {
+    let doc = crate::formats::scriptdata::binary::from_binary(input, false, Some(hi), None).unwrap();
+    let warnings = crate::formats::scriptdata::validate_for_format(&doc, crate::formats::scriptdata::TargetFormat::GenericXml);
+    let gx = crate::formats::scriptdata::generic_xml::dump(&doc);
+    prepend_format_warnings(gx, &warnings)
+}
+
+fn transcode_sd_custom(hi: &HashIndex, input: &[u8]) -> Vec<u8> {
+    let doc = crate::formats::scriptdata::binary::from_binary(input, false, Some(hi), None).unwrap();
+    let warnings = crate::formats::scriptdata::validate_for_format(&doc, crate::formats::scriptdata::TargetFormat::CustomXml);
+    let gx = crate::formats::scriptdata::custom_xml::dump(&doc);
+    prepend_format_warnings(gx, &warnings)
 }
 
-fn transcode_sd_custom(_hi: &HashIndex, input: &[u8]) -> Vec<u8> {
-    let doc = crate::formats::scriptdata::binary::from_binary(input, false);
-    let gx = crate::formats::scriptdata::custom_xml::dump(&doc.unwrap());
-    return gx.into_bytes();
+/// Inverse of [`transcode_sd_generic`]: parses edited generic-XML text back
+/// into a `Document` and packs it to binary scriptdata.
+fn pack_sd_generic(_hi: &HashIndex, input: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let text = std::str::from_utf8(input)?;
+    let doc = crate::formats::scriptdata::generic_xml::load(text)?;
+    crate::formats::scriptdata::binary::to_binary(&doc, crate::formats::scriptdata::binary::Variant::X86)
+}
+
+/// Inverse of [`transcode_sd_custom`]: parses edited custom-XML text back
+/// into a `Document` and packs it to binary scriptdata.
+fn pack_sd_custom(_hi: &HashIndex, input: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let text = std::str::from_utf8(input)?;
+    let doc = crate::formats::scriptdata::custom_xml::load(text)?;
+    crate::formats::scriptdata::binary::to_binary(&doc, crate::formats::scriptdata::binary::Variant::X86)
+}
+
+/// Prepend a [`validate_for_format`](crate::formats::scriptdata::validate_for_format)
+/// report to a dumped XML document as a leading comment, so the transcoder FS
+/// surfaces fields that won't survive instead of silently dropping them.
+/// Placed after the `<?xml ... ?>` declaration, if any, since a comment isn't
+/// allowed to come before it.
+fn prepend_format_warnings(mut xml: String, warnings: &[crate::formats::scriptdata::FormatWarning]) -> Vec<u8> {
+    if warnings.is_empty() {
+        return xml.into_bytes();
+    }
+
+    let mut comment = String::from("<!--\n");
+    for w in warnings {
+        comment.push_str(&format!("{}\n", w));
+    }
+    comment.push_str("-->\n");
+
+    let insert_at = if xml.starts_with("<?xml") {
+        xml.find("?>").map(|i| i + 2).unwrap_or(0)
+    }
+    else {
+        0
+    };
+    xml.insert_str(insert_at, &comment);
+    xml.into_bytes()
+}
+
+fn transcode_sd_cbor(hi: &HashIndex, input: &[u8]) -> Vec<u8> {
+    let doc = crate::formats::scriptdata::binary::from_binary(input, false, Some(hi), None);
+    crate::formats::scriptdata::cbor::write_cbor(&doc.unwrap())
 }
 
 fn transcode_banksinfo(_hi: &HashIndex, input: &[u8]) -> Vec<u8> {
     let bkif = crate::formats::banksinfo::try_from_bytes(input);
     let s = format!("{:?}", bkif);
     return s.into_bytes();
+}
+
+/// Renders a `.font` file as plain-text BMFont (`.fnt`), so it can be edited with
+/// off-the-shelf tools instead of a hex editor. There's no write path back through
+/// this filesystem layer for fonts (the `.font` rule has no packer) - see
+/// [`crate::formats::font::from_bmfont`] for the other direction.
+fn transcode_font(_hi: &HashIndex, input: &[u8]) -> Vec<u8> {
+    match crate::formats::font::from_binary(input) {
+        Ok(font) => crate::formats::font::to_bmfont(&font).into_bytes(),
+        Err(e) => format!("{:?}", e).into_bytes()
+    }
+}
+
+/// The `:json` alternate stream for `.banksinfo` files, for tools that want
+/// actual structured data rather than the debug dump `transcode_banksinfo` gives.
+fn banksinfo_to_json(_hi: &HashIndex, input: &[u8]) -> Vec<u8> {
+    use crate::util::escape_json_str;
+
+    let bkif = match crate::formats::banksinfo::try_from_bytes(input) {
+        Ok(b) => b,
+        Err(e) => return format!("{{\"error\":{}}}", escape_json_str(&e.to_string())).into_bytes()
+    };
+
+    let mut out = String::from("{\"sound_banks\":[");
+    for (i, bank) in bkif.sound_banks.iter().enumerate() {
+        if i > 0 { out.push(','); }
+        out.push_str(&escape_json_str(bank));
+    }
+    out.push_str("],\"sound_lookups\":{");
+
+    let mut pairs: Vec<_> = bkif.sound_lookups.iter().collect();
+    pairs.sort_by_key(|(id, _)| **id);
+    for (i, (id, (hash, name))) in pairs.into_iter().enumerate() {
+        if i > 0 { out.push(','); }
+        out.push_str(&format!("\"{}\":{{\"hash\":\"{:016x}\",\"name\":{}}}", id, hash.0, escape_json_str(name)));
+    }
+    out.push_str("}}");
+    out.into_bytes()
 }
\ No newline at end of file
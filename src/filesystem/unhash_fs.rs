@@ -0,0 +1,82 @@
+//! Wraps another [`ReadOnlyFs`] to recover human-readable names for directory
+//! entries that `inner` can only produce as bare 16-hex-digit hashes, using a
+//! dictionary built by [`crate::hashlist_scan::build_dictionary`] (or loaded back
+//! with [`crate::hashlist_scan::load_dictionary`]) from a previous scan of the same
+//! bundles. Entries this still can't place are rewritten into the `@ID...@` form
+//! [`crate::diesel_hash::parse_flexibly`] accepts, so at least they stay
+//! round-trippable by hand instead of sitting there as a bare hex string that could
+//! just as easily be a legitimately-named file.
+
+use std::rc::Rc;
+use std::sync::Arc;
+
+use fnv::FnvHashMap;
+
+use crate::diesel_hash;
+use crate::hashindex::is_hash_like;
+use super::{FsDirEntry, FsError, FsFileInfo, FsReadHandle, FsStreamEntry, ReadOnlyFs};
+
+pub struct UnhashFs {
+    inner: Arc<dyn ReadOnlyFs>,
+    dictionary: Arc<FnvHashMap<u64, Arc<str>>>,
+}
+
+impl UnhashFs {
+    /// `dictionary` comes in as `Rc<str>` because that's what [`crate::hashlist_scan`]
+    /// builds it as (it's single-threaded scanning code, same as the rest of that
+    /// module) - it's re-interned as `Arc<str>` here since a [`ReadOnlyFs`] has to be
+    /// `Send + Sync` and `Rc` isn't.
+    pub fn new(inner: Arc<dyn ReadOnlyFs>, dictionary: FnvHashMap<u64, Rc<str>>) -> UnhashFs {
+        let dictionary = dictionary.into_iter().map(|(h, s)| (h, Arc::from(s.as_ref()))).collect();
+        UnhashFs { inner, dictionary: Arc::new(dictionary) }
+    }
+}
+
+impl ReadOnlyFs for UnhashFs {
+    fn open_readable(&self, path: &str, stream: &str) -> Result<Arc<dyn FsReadHandle>, FsError> {
+        let inner = self.inner.open_readable(path, stream)?;
+        Ok(Arc::new(UnhashReadHandle { inner, dictionary: self.dictionary.clone() }))
+    }
+}
+
+struct UnhashReadHandle {
+    inner: Arc<dyn FsReadHandle>,
+    dictionary: Arc<FnvHashMap<u64, Arc<str>>>,
+}
+
+impl FsReadHandle for UnhashReadHandle {
+    fn is_dir(&self) -> bool { self.inner.is_dir() }
+    fn len(&self) -> Option<usize> { self.inner.len() }
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize, FsError> {
+        self.inner.read_at(buf, offset)
+    }
+    fn list_streams(&self) -> Result<Box<dyn Iterator<Item=FsStreamEntry>>, FsError> {
+        self.inner.list_streams()
+    }
+    fn get_file_info(&self) -> Result<FsFileInfo, FsError> {
+        self.inner.get_file_info()
+    }
+
+    fn find_files(&self) -> Result<Box<dyn Iterator<Item=FsDirEntry>>, FsError> {
+        let dictionary = self.dictionary.clone();
+        let entries = self.inner.find_files()?;
+        Ok(Box::new(entries.map(move |mut entry| {
+            entry.name = resolve_name(&dictionary, &entry.name);
+            entry
+        })))
+    }
+}
+
+/// Resolves a single directory-entry name, leaving anything that doesn't look like
+/// a bare hash (e.g. already has an extension attached) untouched.
+fn resolve_name(dictionary: &FnvHashMap<u64, Arc<str>>, name: &str) -> String {
+    if !is_hash_like(name) { return name.to_owned(); }
+
+    let hash = diesel_hash::from_str(name);
+    if let Some(resolved) = dictionary.get(&hash) {
+        return resolved.to_string();
+    }
+
+    // Byte-swapped relative to the raw hash, to match parse_flexibly's decoding.
+    format!("@ID{:016X}@", hash.swap_bytes())
+}
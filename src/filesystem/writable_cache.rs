@@ -0,0 +1,209 @@
+//! Append-mode writable data file for caching extracted/converted output.
+//!
+//! A [`CacheFs`] is a `ReadWriteFs` backed by an ever-growing data file and
+//! an in-memory index mapping logical paths to byte ranges within it. The
+//! index is not written to disk, so reopening a data file starts with an
+//! empty index.
+//! Overwriting an entry doesn't rewrite the data file in place (files on
+//! disk don't shrink or splice cheaply); instead the new bytes are appended
+//! and the index is repointed, leaving the old bytes as unreachable dead
+//! space. Once the fraction of dead space passes `compact_threshold` the
+//! whole data file is rewritten compacted, so repeated exports of a
+//! mostly-unchanged bundle only pay for the deltas instead of a full rewrite
+//! every time, while the file still doesn't grow without bound.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::FsError;
+
+/// Write-capable counterpart to [`super::ReadOnlyFs`]. Kept as a separate
+/// trait, rather than folded into `ReadOnlyFs`, because most filesystems in
+/// this crate (bundle contents, transcoded views) are inherently read-only
+/// and have no sensible implementation of these methods.
+pub trait ReadWriteFs : Send + Sync {
+    /// Begins (or replaces) the entry at `path`, returning a handle `write_at`
+    /// and `flush` operate on. Any previous contents at `path` are discarded
+    /// once the returned handle is flushed.
+    fn create(&self, path: &str) -> Result<u64, FsError>;
+
+    /// Writes `buf` at `offset` within the entry opened by `create`.
+    fn write_at(&self, handle: u64, buf: &[u8], offset: u64) -> Result<usize, FsError>;
+
+    /// Commits the entry's current bytes, making them visible to readers.
+    /// [`CacheFs`] keeps its index in memory only, so its entries don't
+    /// survive a restart.
+    fn flush(&self, handle: u64) -> Result<(), FsError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ByteRange {
+    offset: u64,
+    length: u64,
+}
+
+struct PendingWrite {
+    path: String,
+    data: Vec<u8>,
+}
+
+/// The fraction of the data file's bytes that belong to superseded entries
+/// at which `flush` triggers a full compaction instead of another append.
+const DEFAULT_COMPACT_THRESHOLD: f64 = 0.5;
+
+struct CacheState {
+    data_file: File,
+    data_len: u64,
+    dead_bytes: u64,
+    index: HashMap<String, ByteRange>,
+    pending: HashMap<u64, PendingWrite>,
+    next_handle: u64,
+}
+
+/// Append-only writable cache: a data file plus an in-memory index of where
+/// each logical path's bytes currently live within it.
+pub struct CacheFs {
+    data_path: PathBuf,
+    compact_threshold: f64,
+    state: Mutex<CacheState>,
+}
+
+impl CacheFs {
+    /// Opens (creating if absent) the cache rooted at `data_path`, using
+    /// the default compaction threshold.
+    pub fn open(data_path: impl Into<PathBuf>) -> io::Result<CacheFs> {
+        Self::open_with_threshold(data_path, DEFAULT_COMPACT_THRESHOLD)
+    }
+
+    pub fn open_with_threshold(data_path: impl Into<PathBuf>, compact_threshold: f64) -> io::Result<CacheFs> {
+        let data_path = data_path.into();
+        let mut data_file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(&data_path)?;
+        let data_len = data_file.seek(SeekFrom::End(0))?;
+
+        Ok(CacheFs {
+            data_path,
+            compact_threshold,
+            state: Mutex::new(CacheState {
+                data_file,
+                data_len,
+                dead_bytes: 0,
+                index: HashMap::new(),
+                pending: HashMap::new(),
+                next_handle: 1,
+            })
+        })
+    }
+
+    /// Reads back the bytes currently indexed for `path`, or `None` if
+    /// nothing has been written there yet.
+    pub fn read(&self, path: &str) -> Result<Option<Vec<u8>>, FsError> {
+        let mut state = self.state.lock().unwrap();
+        let range = match state.index.get(path) {
+            Some(r) => *r,
+            None => return Ok(None)
+        };
+        let mut buf = vec![0u8; range.length as usize];
+        state.data_file.seek(SeekFrom::Start(range.offset)).map_err(|_| FsError::ReadError)?;
+        state.data_file.read_exact(&mut buf).map_err(|_| FsError::ReadError)?;
+        Ok(Some(buf))
+    }
+}
+
+impl ReadWriteFs for CacheFs {
+    fn create(&self, path: &str) -> Result<u64, FsError> {
+        let mut state = self.state.lock().unwrap();
+        let handle = state.next_handle;
+        state.next_handle += 1;
+        state.pending.insert(handle, PendingWrite { path: path.to_owned(), data: Vec::new() });
+        Ok(handle)
+    }
+
+    fn write_at(&self, handle: u64, buf: &[u8], offset: u64) -> Result<usize, FsError> {
+        let mut state = self.state.lock().unwrap();
+        let pending = state.pending.get_mut(&handle).ok_or(FsError::NotFound)?;
+        let end = offset as usize + buf.len();
+        if pending.data.len() < end {
+            pending.data.resize(end, 0);
+        }
+        pending.data[offset as usize..end].copy_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&self, handle: u64) -> Result<(), FsError> {
+        let mut state = self.state.lock().unwrap();
+        let pending = state.pending.remove(&handle).ok_or(FsError::NotFound)?;
+
+        if let Some(old) = state.index.get(&pending.path) {
+            state.dead_bytes += old.length;
+        }
+
+        let offset = state.data_len;
+        let length = pending.data.len() as u64;
+        state.data_file.seek(SeekFrom::End(0)).map_err(|_| FsError::ReadError)?;
+        state.data_file.write_all(&pending.data).map_err(|_| FsError::ReadError)?;
+        state.data_len += length;
+
+        state.index.insert(pending.path, ByteRange { offset, length });
+
+        let total = state.data_len.max(1);
+        if (state.dead_bytes as f64 / total as f64) > self.compact_threshold {
+            Self::compact(&mut state, &self.data_path)?;
+        }
+        Ok(())
+    }
+}
+
+impl CacheFs {
+    /// Rewrites the data file containing only the bytes entries in the
+    /// index actually point to, then repoints the index at the new, packed
+    /// offsets. Called automatically once dead space passes the threshold.
+    fn compact(state: &mut CacheState, data_path: &Path) -> Result<(), FsError> {
+        let tmp_path = {
+            let mut p = data_path.as_os_str().to_owned();
+            p.push(".compact-tmp");
+            PathBuf::from(p)
+        };
+
+        let mut new_file = fs::OpenOptions::new()
+            .read(true).write(true).create(true).truncate(true)
+            .open(&tmp_path)
+            .map_err(|_| FsError::ReadError)?;
+
+        let mut new_index = HashMap::with_capacity(state.index.len());
+        let mut new_len = 0u64;
+
+        let mut entries: Vec<(String, ByteRange)> = state.index.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        entries.sort_by_key(|(_, r)| r.offset);
+
+        for (path, range) in entries {
+            let mut buf = vec![0u8; range.length as usize];
+            state.data_file.seek(SeekFrom::Start(range.offset)).map_err(|_| FsError::ReadError)?;
+            state.data_file.read_exact(&mut buf).map_err(|_| FsError::ReadError)?;
+            new_file.write_all(&buf).map_err(|_| FsError::ReadError)?;
+            new_index.insert(path, ByteRange { offset: new_len, length: range.length });
+            new_len += range.length;
+        }
+
+        new_file.flush().map_err(|_| FsError::ReadError)?;
+        drop(new_file);
+        fs::rename(&tmp_path, data_path).map_err(|_| FsError::ReadError)?;
+
+        state.data_file = fs::OpenOptions::new()
+            .read(true).write(true)
+            .open(data_path)
+            .map_err(|_| FsError::ReadError)?;
+        state.data_file.seek(SeekFrom::End(0)).map_err(|_| FsError::ReadError)?;
+        state.data_len = new_len;
+        state.dead_bytes = 0;
+        state.index = new_index;
+
+        Ok(())
+    }
+}
@@ -1,8 +1,10 @@
 use std::rc::Rc;
+use std::collections::HashMap;
+use std::io::Write;
 use fnv::FnvHashMap;
+use bytemuck::{Pod, Zeroable};
 
 use crate::hashindex::Hash;
-use crate::util::read_helpers::*;
 
 #[derive(Debug)]
 pub struct BanksInfo {
@@ -12,45 +14,127 @@ pub struct BanksInfo {
 
 #[derive(Debug)]
 pub enum BankParseFailure {
-    SliceError(TryFromBytesError),
-    BadString(std::str::Utf8Error)
+    SliceError(SliceError),
+    BadString(std::str::Utf8Error),
+    UnterminatedString(usize)
 }
-impl From<TryFromBytesError> for BankParseFailure { fn from(e: TryFromBytesError) -> BankParseFailure { BankParseFailure::SliceError(e) } }
+impl From<SliceError> for BankParseFailure { fn from(e: SliceError) -> BankParseFailure { BankParseFailure::SliceError(e) } }
 impl From<std::str::Utf8Error> for BankParseFailure { fn from(e: std::str::Utf8Error) -> BankParseFailure { BankParseFailure::BadString(e) } }
+impl std::fmt::Display for BankParseFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BankParseFailure::SliceError(e) => write!(f, "truncated bank file: wanted bytes at {} but only had {}", e.idx, e.len),
+            BankParseFailure::BadString(e) => write!(f, "bank file string wasn't valid UTF-8: {}", e),
+            BankParseFailure::UnterminatedString(idx) => write!(f, "bank file string starting at {} runs off the end of the file", idx),
+        }
+    }
+}
+impl std::error::Error for BankParseFailure {}
+
+#[derive(Debug)]
+pub struct SliceError { pub idx: usize, pub len: usize }
+
+/// Little-endian `u32` that's safe to put in a `#[repr(C)]` struct cast directly
+/// over file bytes: no alignment requirement, and the byte order is fixed
+/// regardless of host endianness.
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(transparent)]
+struct LeU32([u8; 4]);
+impl LeU32 {
+    fn get(self) -> u32 { u32::from_le_bytes(self.0) }
+}
+
+/// As [`LeU32`], but for `u64`.
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(transparent)]
+struct LeU64([u8; 8]);
+impl LeU64 {
+    fn get(self) -> u64 { u64::from_le_bytes(self.0) }
+}
+
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct BankListEntry {
+    _skip: [u8; 4],
+    name_offset: LeU32
+}
+
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct SoundRecord {
+    id: LeU64,
+    hash: LeU64
+}
+
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct LookupRecord {
+    hash: LeU64,
+    _zero: LeU32,
+    string_offset: LeU32
+}
+
+/// Casts a `T` (or a slice of `T`) off the front of `src` without copying,
+/// rejecting the slice if it's too short instead of panicking.
+fn from_bytes<T: Pod>(src: &[u8]) -> Result<(&T, &[u8]), SliceError> {
+    let size = std::mem::size_of::<T>();
+    if src.len() < size {
+        return Err(SliceError { idx: 0, len: src.len() })
+    }
+    let (head, tail) = src.split_at(size);
+    let value = bytemuck::try_from_bytes(head).map_err(|_| SliceError { idx: 0, len: src.len() })?;
+    Ok((value, tail))
+}
+
+fn read_u32(src: &[u8], idx: usize) -> Result<u32, SliceError> {
+    let slice = src.get(idx..idx+4).ok_or(SliceError { idx, len: src.len() })?;
+    let (v, _) = from_bytes::<LeU32>(slice)?;
+    Ok(v.get())
+}
+
+/// Reads a NUL-terminated string starting at `offset`, without ever indexing
+/// past the end of `src`.
+fn read_cstr(src: &[u8], offset: usize) -> Result<&str, BankParseFailure> {
+    let tail = src.get(offset..).ok_or(SliceError { idx: offset, len: src.len() })?;
+    let len = tail.iter().position(|&b| b == 0).ok_or(BankParseFailure::UnterminatedString(offset))?;
+    Ok(std::str::from_utf8(&tail[..len])?)
+}
+
+fn item_at<T: Pod>(src: &[u8], offset: usize) -> Result<T, SliceError> {
+    let size = std::mem::size_of::<T>();
+    let slice = src.get(offset..offset+size).ok_or(SliceError { idx: offset, len: src.len() })?;
+    let (v, _) = from_bytes::<T>(slice)?;
+    Ok(*v)
+}
 
 pub fn try_from_bytes(src: &[u8]) -> Result<BanksInfo, BankParseFailure> {
-    let bnk_count = u32::try_from_le(src, 0)? as usize;
+    let bnk_count = read_u32(src, 0)? as usize;
     // skip a second copy of the count
-    let bnk_offset = u32::try_from_le(src, 8)? as usize;
-    let _section_pointer = u32::try_from_le(src, 12)?;
-    let _unknown_1 = u32::try_from_le(src, 16)?;
+    let bnk_offset = read_u32(src, 8)? as usize;
+    let _section_pointer = read_u32(src, 12)?;
+    let _unknown_1 = read_u32(src, 16)?;
 
-    let sound_count = u32::try_from_le(src, 20)? as usize;
+    let sound_count = read_u32(src, 20)? as usize;
     // skip a second copy of the count
-    let sound_offset = u32::try_from_le(src, 28)? as usize;
-    let _section_pointer = u32::try_from_le(src, 32)?;
-    let _unknown_2 = u32::try_from_le(src, 36)?;
-    let _unknown_3 = u32::try_from_le(src, 40)?;
+    let sound_offset = read_u32(src, 28)? as usize;
+    let _section_pointer = read_u32(src, 32)?;
+    let _unknown_2 = read_u32(src, 36)?;
+    let _unknown_3 = read_u32(src, 40)?;
 
-    let u_count = u32::try_from_le(src, 44)? as usize;
+    let u_count = read_u32(src, 44)? as usize;
     // skip yet another copy of a count
-    let u_offset = u32::try_from_le(src, 52)? as usize;
+    let u_offset = read_u32(src, 52)? as usize;
 
     let mut res = BanksInfo {
         sound_banks: Vec::with_capacity(bnk_count),
         sound_lookups: FnvHashMap::default()
     };
     res.sound_lookups.reserve(sound_count);
-    
-    for i in  0..bnk_count {
-        let offset_offset = bnk_offset + i*8;
-
-        // theres four zeroes skipped in each item, no idea what they're for.
-        let start_offset = u32::try_from_le(src, offset_offset+4)? as usize;
-        let mut end_offset = start_offset;
-        while src[end_offset] != 0 { end_offset += 1; }
-        let slice = &src[start_offset..end_offset];
-        let text = std::str::from_utf8(slice)?;
+
+    for i in 0..bnk_count {
+        let entry_offset = bnk_offset + i * std::mem::size_of::<BankListEntry>();
+        let entry: BankListEntry = item_at(src, entry_offset)?;
+        let text = read_cstr(src, entry.name_offset.get() as usize)?;
         res.sound_banks.push(Rc::<str>::from(text));
     }
 
@@ -58,27 +142,164 @@ pub fn try_from_bytes(src: &[u8]) -> Result<BanksInfo, BankParseFailure> {
     sound_hash_to_id.reserve(sound_count);
 
     for i in 0..sound_count {
-        let offset = sound_offset + i * 16;
-        let id = u64::try_from_le(src, offset + 0)?;
-        let hash = u64::try_from_le(src, offset + 8)?;
-        sound_hash_to_id.entry(Hash(hash)).or_insert(id);
+        let offset = sound_offset + i * std::mem::size_of::<SoundRecord>();
+        let record: SoundRecord = item_at(src, offset)?;
+        sound_hash_to_id.entry(Hash(record.hash.get())).or_insert(record.id.get());
     }
 
     for i in 0..u_count {
-        let offset = u_offset + i*16;
-        let hash = Hash(u64::try_from_le(src, offset+0)?);
-        let _zero = u32::try_from_le(src, offset+8)?;
-        let string_offset = u32::try_from_le(src, offset+12)? as usize;
-        let mut string_end = string_offset;
-        while src[string_end] != 0 { string_end += 1; }
-        let slice = &src[string_offset..string_end];
-        let text = std::str::from_utf8(slice)?;
+        let offset = u_offset + i * std::mem::size_of::<LookupRecord>();
+        let record: LookupRecord = item_at(src, offset)?;
+        let hash = Hash(record.hash.get());
+        let text = read_cstr(src, record.string_offset.get() as usize)?;
         let string = Rc::<str>::from(text);
-        
+
         if let Some(id) = sound_hash_to_id.get(&hash) {
             res.sound_lookups.entry(*id).or_insert((hash, string));
         }
     }
-    
-    return Ok(res);
-}
\ No newline at end of file
+
+    Ok(res)
+}
+
+/// Serializes `info` back to the on-disk bank-list format. Inverse of
+/// [`try_from_bytes`], modulo the unknown header fields, which are re-synthesized
+/// as zero/best-guess since their meaning has never been figured out.
+pub fn to_bytes(info: &BanksInfo) -> Vec<u8> {
+    let mut out = Vec::new();
+    info.write(&mut out).expect("writing to a Vec<u8> can't fail");
+    out
+}
+
+/// Interns `s` into `heap`, sharing storage with any identical string already
+/// written, and returns its absolute offset in the file.
+fn intern<'a>(s: &'a str, heap_base: usize, heap: &mut Vec<u8>, offsets: &mut HashMap<&'a str, u32>) -> u32 {
+    if let Some(&off) = offsets.get(s) {
+        return off;
+    }
+    let off = (heap_base + heap.len()) as u32;
+    heap.extend_from_slice(s.as_bytes());
+    heap.push(0);
+    offsets.insert(s, off);
+    off
+}
+
+impl BanksInfo {
+    pub fn write<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        const HEADER_LEN: usize = 56;
+
+        let bnk_count = self.sound_banks.len();
+        let pair_count = self.sound_lookups.len();
+
+        let bnk_offset = HEADER_LEN;
+        let sound_offset = bnk_offset + bnk_count * std::mem::size_of::<BankListEntry>();
+        let u_offset = sound_offset + pair_count * std::mem::size_of::<SoundRecord>();
+        let heap_base = u_offset + pair_count * std::mem::size_of::<LookupRecord>();
+
+        // Stable order so that re-serializing the same BanksInfo twice produces
+        // the same bytes.
+        let mut pairs: Vec<(&u64, &(Hash, Rc<str>))> = self.sound_lookups.iter().collect();
+        pairs.sort_by_key(|(id, _)| **id);
+
+        let mut heap = Vec::<u8>::new();
+        let mut offsets = HashMap::<&str, u32>::new();
+
+        let bank_offsets: Vec<u32> = self.sound_banks.iter()
+            .map(|s| intern(s, heap_base, &mut heap, &mut offsets))
+            .collect();
+        let string_offsets: Vec<u32> = pairs.iter()
+            .map(|(_, (_, s))| intern(s, heap_base, &mut heap, &mut offsets))
+            .collect();
+
+        w.write_all(&(bnk_count as u32).to_le_bytes())?;
+        w.write_all(&(bnk_count as u32).to_le_bytes())?;
+        w.write_all(&(bnk_offset as u32).to_le_bytes())?;
+        w.write_all(&(bnk_offset as u32).to_le_bytes())?; // section pointer: unknown meaning
+        w.write_all(&0u32.to_le_bytes())?; // unknown_1
+
+        w.write_all(&(pair_count as u32).to_le_bytes())?;
+        w.write_all(&(pair_count as u32).to_le_bytes())?;
+        w.write_all(&(sound_offset as u32).to_le_bytes())?;
+        w.write_all(&(sound_offset as u32).to_le_bytes())?; // section pointer: unknown meaning
+        w.write_all(&0u32.to_le_bytes())?; // unknown_2
+        w.write_all(&0u32.to_le_bytes())?; // unknown_3
+
+        w.write_all(&(pair_count as u32).to_le_bytes())?;
+        w.write_all(&(pair_count as u32).to_le_bytes())?;
+        w.write_all(&(u_offset as u32).to_le_bytes())?;
+
+        for &name_offset in &bank_offsets {
+            w.write_all(&[0u8; 4])?; // the four skipped bytes try_from_bytes never explains
+            w.write_all(&name_offset.to_le_bytes())?;
+        }
+
+        for (id, (hash, _)) in &pairs {
+            w.write_all(&id.to_le_bytes())?;
+            w.write_all(&hash.0.to_le_bytes())?;
+        }
+
+        for ((_, (hash, _)), &string_offset) in pairs.iter().zip(string_offsets.iter()) {
+            w.write_all(&hash.0.to_le_bytes())?;
+            w.write_all(&0u32.to_le_bytes())?;
+            w.write_all(&string_offset.to_le_bytes())?;
+        }
+
+        w.write_all(&heap)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> BanksInfo {
+        let mut sound_lookups = FnvHashMap::default();
+        sound_lookups.insert(2u64, (Hash(0xdead_beef_0000_0002), Rc::<str>::from("footsteps")));
+        sound_lookups.insert(1u64, (Hash(0xdead_beef_0000_0001), Rc::<str>::from("gunshot")));
+        // Shares its string with the `id: 1` lookup above, to exercise heap dedup.
+        sound_lookups.insert(3u64, (Hash(0xdead_beef_0000_0003), Rc::<str>::from("gunshot")));
+
+        BanksInfo {
+            sound_banks: vec![Rc::<str>::from("weapons"), Rc::<str>::from("player")],
+            sound_lookups
+        }
+    }
+
+    #[test]
+    fn round_trips_byte_identically() {
+        let known_good = to_bytes(&sample());
+        let parsed = try_from_bytes(&known_good).expect("known-good sample should parse");
+        let reserialized = to_bytes(&parsed);
+        assert_eq!(known_good, reserialized);
+    }
+
+    #[test]
+    fn round_trip_is_stable_regardless_of_lookup_insertion_order() {
+        let a = sample();
+
+        let mut sound_lookups = FnvHashMap::default();
+        for id in [3u64, 1, 2] {
+            sound_lookups.insert(id, a.sound_lookups[&id].clone());
+        }
+        let b = BanksInfo { sound_banks: a.sound_banks.clone(), sound_lookups };
+
+        assert_eq!(to_bytes(&a), to_bytes(&b));
+    }
+
+    #[test]
+    fn shared_strings_are_deduplicated_in_the_heap() {
+        let bytes = to_bytes(&sample());
+        let parsed = try_from_bytes(&bytes).unwrap();
+        let gunshot_1 = &parsed.sound_lookups[&1].1;
+        let gunshot_3 = &parsed.sound_lookups[&3].1;
+        assert_eq!(gunshot_1.as_ref(), "gunshot");
+        assert_eq!(gunshot_3.as_ref(), "gunshot");
+
+        // Both lookups' strings should point at the exact same heap offset.
+        let needle = b"gunshot\0";
+        let first = bytes.windows(needle.len()).position(|w| w == needle).unwrap();
+        assert_eq!(bytes.windows(needle.len()).filter(|w| *w == needle).count(), 1,
+            "\"gunshot\" should only be written once into the string heap, at offset {}", first);
+    }
+}
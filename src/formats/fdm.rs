@@ -2,6 +2,7 @@
 
 pub mod container;
 pub use container::*;
+pub mod export_gltf;
 
 use std::convert::TryInto;
 
@@ -126,23 +127,23 @@ make_document! {
     (0x2c1f096f, NormalManagingGP,               Unknown                              )
     (0x5ed2532f, TextureSpaceGP,                 Unknown                              )
     (0xe3a3b1ca, PassthroughGP,                  PassthroughGPSection                 )
-    (0x65cc1825, SkinBones,                      Unknown                              )
+    (0x65cc1825, SkinBones,                      SkinBonesSection                      )
     (0x4c507a13, Topology,                       TopologySection                      )
     (0x03b634bd, TopologyIP,                     TopologyIPSection                    )
     (0x46bf31a7, Camera,                         Unknown                              )
     (0xffa13b80, Light,                          LightSection                         )
-    (0x2060697e, ConstFloatController,           Unknown                              )
-    (0x6da951b2, StepFloatController,            Unknown                              )
+    (0x2060697e, ConstFloatController,           ConstFloatControllerSection          )
+    (0x6da951b2, StepFloatController,            StepFloatControllerSection           )
     (0x76bf5b66, LinearFloatController,          LinearFloatControllerSection         )
-    (0x29743550, BezierFloatController,          Unknown                              )
-    (0x5b0168d0, ConstVector3Controller,         Unknown                              )
-    (0x544e238f, StepVector3Controller,          Unknown                              )
+    (0x29743550, BezierFloatController,          BezierFloatControllerSection         )
+    (0x5b0168d0, ConstVector3Controller,         ConstVector3ControllerSection        )
+    (0x544e238f, StepVector3Controller,          StepVector3ControllerSection         )
     (0x26a5128c, LinearVector3Controller,        LinearVector3ControllerSection       )
-    (0x28db639a, BezierVector3Controller,        Unknown                              )
+    (0x28db639a, BezierVector3Controller,        BezierVector3ControllerSection       )
     (0x33da0fc4, XYZVector3Controller,           Unknown                              )
     (0x2e540f3c, ConstRotationController,        Unknown                              )
     (0x033606e8, EulerRotationController,        Unknown                              )
-    (0x007fb371, QuatStepRotationController,     Unknown                              )
+    (0x007fb371, QuatStepRotationController,     QuatStepRotationControllerSection    )
     (0x648a206c, QuatLinearRotationController,   QuatLinearRotationControllerSection  )
     (0x197345a5, QuatBezRotationController,      Unknown                              )
     (0x22126dc0, LookAtRotationController,       Unknown                              )
@@ -165,6 +166,50 @@ pub fn parse_stream(input: &mut impl ReadExt) -> Result<DieselContainer, ReadErr
     input.read_item()
 }
 
+/// Writes `container` back out byte-for-byte in the same section layout
+/// [`parse_stream`] reads - every section's own `#[derive(ItemReader)]`
+/// impl (honoring `#[skip_before]`, `#[read_as]`, `CountedVec`,
+/// `CountedString<u16>` and the rest) already round-trips, so this just
+/// gives callers that want to edit and re-save a model (retiming an
+/// animation, remapping a material) the same one-line entry point
+/// `parse_stream` gives readers, instead of having to reach for
+/// `output.write_item(container)` themselves.
+pub fn write_stream(container: &DieselContainer, output: &mut impl WriteExt) -> Result<(), ReadError> {
+    output.write_item(container)
+}
+
+impl Section {
+    /// The controller this section holds, if it's one of the `*Controller`
+    /// section types that implement [`Animatable`].
+    pub fn as_animatable(&self) -> Option<&dyn Animatable> {
+        match self {
+            Section::ConstFloatController(s) => Some(s.as_ref()),
+            Section::StepFloatController(s) => Some(s.as_ref()),
+            Section::LinearFloatController(s) => Some(s.as_ref()),
+            Section::BezierFloatController(s) => Some(s.as_ref()),
+            Section::ConstVector3Controller(s) => Some(s.as_ref()),
+            Section::StepVector3Controller(s) => Some(s.as_ref()),
+            Section::LinearVector3Controller(s) => Some(s.as_ref()),
+            Section::BezierVector3Controller(s) => Some(s.as_ref()),
+            Section::QuatStepRotationController(s) => Some(s.as_ref()),
+            Section::QuatLinearRotationController(s) => Some(s.as_ref()),
+            _ => None
+        }
+    }
+
+    /// The embedded [`Object3dSection`], if this is one of the section
+    /// types that takes part in the node hierarchy - plain empties, but
+    /// also models and lights.
+    pub fn as_object3d(&self) -> Option<&Object3dSection> {
+        match self {
+            Section::Object3D(o) => Some(o.as_ref()),
+            Section::Model(m) => Some(&m.object),
+            Section::Light(l) => Some(&l.object),
+            _ => None
+        }
+    }
+}
+
 /// Metadata about the model file. Release Diesel never, AFAIK, actually cares about this.
 #[derive(Debug, ItemReader)]
 pub struct AuthorSection {
@@ -338,6 +383,67 @@ pub enum LightType {
     Spot = 2
 }
 
+/// Skin (skeleton) referenced by a [`MeshModel::skinbones`], giving meaning
+/// to its `GeometrySection`'s `blend_indices_*`/`blend_weight_*` attributes.
+///
+/// `bones` and `inverse_bind_matrices` always have the same length - one
+/// entry per bone - and a `blend_indices_0` value of `i` means "bone
+/// `bones[i]`", i.e. they're both indexed by the same bone palette index.
+#[derive(Debug)]
+pub struct SkinBonesSection {
+    /// The `Object3D` that's this skeleton's root.
+    pub root: u32,
+
+    /// Transform taking skin space into the scene's coordinate space.
+    pub global_transform: Mat4f,
+
+    /// One inverse bind-pose matrix per bone.
+    pub inverse_bind_matrices: Vec<Mat4f>,
+
+    /// The `Object3D` section ID backing each bone.
+    pub bones: Vec<u32>
+}
+impl ItemReader for SkinBonesSection {
+    type Error = ReadError;
+    type Item = Self;
+
+    fn read_from_stream<R: ReadExt>(stream: &mut R) -> Result<Self::Item, Self::Error> {
+        let root: u32 = stream.read_item()?;
+        let global_transform: Mat4f = stream.read_item_as::<Mat4fWithPos>()?;
+        let bone_count: u32 = stream.read_item()?;
+
+        let mut inverse_bind_matrices = Vec::with_capacity(bone_count as usize);
+        for _ in 0..bone_count {
+            inverse_bind_matrices.push(stream.read_item()?);
+        }
+
+        let mut bones = Vec::with_capacity(bone_count as usize);
+        for _ in 0..bone_count {
+            bones.push(stream.read_item()?);
+        }
+
+        Ok(SkinBonesSection { root, global_transform, inverse_bind_matrices, bones })
+    }
+
+    fn write_to_stream<W: WriteExt>(stream: &mut W, item: &Self::Item) -> Result<(), Self::Error> {
+        stream.write_item(&item.root)?;
+        stream.write_item_as::<Mat4fWithPos>(&item.global_transform)?;
+
+        let bone_count: u32 = item.bones.len()
+            .try_into()
+            .map_err(|_| ReadError::TooManyItems(item.bones.len(), "u32", "u32"))?;
+        stream.write_item(&bone_count)?;
+
+        for m in &item.inverse_bind_matrices {
+            stream.write_item(m)?;
+        }
+        for b in &item.bones {
+            stream.write_item(b)?;
+        }
+        Ok(())
+    }
+}
+
 /// Indirection to vertex and index data
 ///
 /// It's unclear what the exact role is: Diesel itself has two more "Geometry Provider" classes that aren't used in any
@@ -360,13 +466,173 @@ pub struct TopologyIPSection {
 #[derive(Debug, ItemReader)]
 pub struct TopologySection {
     pub unknown_1: u32,
-    
+
     pub faces: Vec<u16>,
 
     pub unknown_2: Vec<u8>,
     pub name: Idstring
 }
 
+impl TopologySection {
+    /// Reorders `faces` in place to improve post-transform vertex cache
+    /// reuse on export, using Tom Forsyth's linear-speed vertex cache
+    /// optimisation algorithm: a simulated direct-mapped LRU cache of 32
+    /// vertices is fed triangles one at a time, always picking whichever
+    /// remaining triangle scores highest (sum of its vertices' cache
+    /// position and valence scores, ties broken by lowest original
+    /// triangle index), then that triangle's vertices are pushed to the
+    /// front of the cache before the next pick.
+    ///
+    /// This only ever permutes which order triangles are emitted in - it
+    /// doesn't touch `GeometrySection`'s per-vertex attribute arrays, and
+    /// it doesn't introduce, drop or renumber any index. But anything
+    /// that addresses a sub-range of `faces` as a contiguous run (for
+    /// instance a `RenderAtom` that assumes "triangles N..M belong to
+    /// material X") will have that assumption invalidated, since
+    /// triangles can move across such boundaries; reorder each atom's own
+    /// slice of `faces` independently (or re-slice the atoms afterwards)
+    /// if that matters.
+    ///
+    /// No-ops on a mesh with more vertices than a `u16` index can address,
+    /// since `faces` can't reference them anyway and the cache/valence
+    /// bookkeeping below is sized off the vertex count.
+    pub fn optimize_vertex_cache(&mut self) {
+        let vertex_count = self.faces.iter().map(|&i| i as usize + 1).max().unwrap_or(0);
+        if vertex_count > u16::MAX as usize + 1 {
+            return;
+        }
+        forsyth_reorder(&mut self.faces, vertex_count);
+    }
+}
+
+const VERTEX_CACHE_SIZE: usize = 32;
+
+fn vertex_cache_score(cache_pos: Option<usize>, remaining_uses: u32) -> f32 {
+    let cache_score = match cache_pos {
+        None => 0.0,
+        Some(p) if p < 3 => 0.75,
+        Some(p) if p < VERTEX_CACHE_SIZE => {
+            (((VERTEX_CACHE_SIZE - p) as f32) / ((VERTEX_CACHE_SIZE - 3) as f32)).powf(1.5)
+        },
+        Some(_) => 0.0
+    };
+    let valence_bonus = if remaining_uses == 0 { 0.0 } else { 2.0 * (remaining_uses as f32).powf(-0.5) };
+    cache_score + valence_bonus
+}
+
+#[derive(Clone, Copy)]
+struct VertexCacheCandidate { score: f32, triangle: u32 }
+impl PartialEq for VertexCacheCandidate {
+    fn eq(&self, other: &Self) -> bool { self.score == other.score && self.triangle == other.triangle }
+}
+impl Eq for VertexCacheCandidate {}
+impl PartialOrd for VertexCacheCandidate {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> { Some(self.cmp(other)) }
+}
+impl Ord for VertexCacheCandidate {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Highest score wins; ties go to the lowest original triangle index,
+        // which sorts "greater" here so `BinaryHeap` (a max-heap) pops it first.
+        self.score.partial_cmp(&other.score).unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| other.triangle.cmp(&self.triangle))
+    }
+}
+
+/// Forsyth's algorithm, operating on a flat u16 triangle list. Scores are
+/// only ever recomputed for vertices whose cache position or remaining
+/// triangle count just changed (and the triangles touching them), so the
+/// candidate heap below carries possibly-stale entries that get skipped
+/// on pop rather than eagerly removed.
+fn forsyth_reorder(faces: &mut [u16], vertex_count: usize) {
+    use std::collections::BinaryHeap;
+
+    if vertex_count == 0 || faces.len() < 3 { return; }
+    let triangle_count = faces.len() / 3;
+
+    let mut vertex_tris: Vec<Vec<u32>> = vec![Vec::new(); vertex_count];
+    for t in 0..triangle_count {
+        for k in 0..3 {
+            vertex_tris[faces[t * 3 + k] as usize].push(t as u32);
+        }
+    }
+
+    let mut remaining_uses: Vec<u32> = vertex_tris.iter().map(|tris| tris.len() as u32).collect();
+    let mut cache_pos: Vec<Option<usize>> = vec![None; vertex_count];
+    let mut vertex_score: Vec<f32> = (0..vertex_count)
+        .map(|v| vertex_cache_score(cache_pos[v], remaining_uses[v]))
+        .collect();
+    let mut emitted = vec![false; triangle_count];
+
+    let triangle_score = |faces: &[u16], vertex_score: &[f32], t: usize| {
+        vertex_score[faces[t * 3] as usize]
+            + vertex_score[faces[t * 3 + 1] as usize]
+            + vertex_score[faces[t * 3 + 2] as usize]
+    };
+
+    let mut current_triangle_score: Vec<f32> = (0..triangle_count)
+        .map(|t| triangle_score(faces, &vertex_score, t))
+        .collect();
+
+    let mut heap: BinaryHeap<VertexCacheCandidate> = (0..triangle_count as u32)
+        .map(|t| VertexCacheCandidate { score: current_triangle_score[t as usize], triangle: t })
+        .collect();
+
+    let mut cache: Vec<u32> = Vec::with_capacity(VERTEX_CACHE_SIZE + 3);
+    let mut output = Vec::with_capacity(faces.len());
+
+    for _ in 0..triangle_count {
+        let best = loop {
+            let top = heap.pop().expect("ran out of candidates before every triangle was emitted");
+            if emitted[top.triangle as usize] { continue; }
+            if top.score != current_triangle_score[top.triangle as usize] { continue; }
+            break top.triangle as usize;
+        };
+        emitted[best] = true;
+
+        let verts = [faces[best * 3] as usize, faces[best * 3 + 1] as usize, faces[best * 3 + 2] as usize];
+        for &v in &verts { output.push(v as u16); }
+
+        for &v in &verts {
+            remaining_uses[v] -= 1;
+        }
+
+        let cache_before = cache.clone();
+        let mut new_cache: Vec<u32> = Vec::with_capacity(VERTEX_CACHE_SIZE + 3);
+        new_cache.extend(verts.iter().map(|&v| v as u32));
+        new_cache.extend(cache.iter().copied().filter(|v| !verts.contains(&(*v as usize))));
+        new_cache.truncate(VERTEX_CACHE_SIZE);
+        cache = new_cache;
+
+        for &v in &cache_before {
+            if !cache.contains(&v) { cache_pos[v as usize] = None; }
+        }
+        for (i, &v) in cache.iter().enumerate() {
+            cache_pos[v as usize] = Some(i);
+        }
+
+        let mut affected: Vec<u32> = Vec::with_capacity(cache_before.len() + cache.len() + 3);
+        affected.extend(cache_before.iter().copied());
+        affected.extend(cache.iter().copied());
+        affected.extend(verts.iter().map(|&v| v as u32));
+        affected.sort_unstable();
+        affected.dedup();
+
+        for &v in &affected {
+            vertex_score[v as usize] = vertex_cache_score(cache_pos[v as usize], remaining_uses[v as usize]);
+        }
+        for &v in &affected {
+            for &t in &vertex_tris[v as usize] {
+                if emitted[t as usize] { continue; }
+                let s = triangle_score(faces, &vertex_score, t as usize);
+                current_triangle_score[t as usize] = s;
+                heap.push(VertexCacheCandidate { score: s, triangle: t });
+            }
+        }
+    }
+
+    faces.copy_from_slice(&output);
+}
+
 /// Vertex attributes
 ///
 /// I couldn't think of a definitely better way to do this, so a non-present attribute is represented by being empty.
@@ -575,6 +841,152 @@ impl ItemReader for GeometrySection {
     }
 }
 
+impl GeometrySection {
+    /// Fills in `tangent`/`binormal` for a mesh that only shipped
+    /// positions, normals and `tex_coord_0` - the MikkTSpace-compatible
+    /// way, so exported/round-tripped models still get correct tangent
+    /// space in a PBR pipeline. Per-triangle tangent/bitangent (Lengyel's
+    /// formula) are accumulated into each corner's vertex weighted by that
+    /// corner's angle, so one huge triangle in a fan doesn't drown out its
+    /// smaller neighbours; each vertex's accumulated tangent is then
+    /// Gram-Schmidt orthonormalized against its normal, and `binormal` is
+    /// reconstructed as `cross(normal, tangent)` with MikkTSpace's
+    /// handedness sign folded in.
+    ///
+    /// No-ops if `position`/`normal`/`tex_coord_0` aren't all populated
+    /// with one entry per vertex.
+    pub fn generate_tangents(&mut self, topology: &TopologySection) {
+        let vertex_count = self.position.len();
+        if vertex_count == 0 || self.normal.len() != vertex_count || self.tex_coord_0.len() != vertex_count {
+            return;
+        }
+
+        let mut tangent_accum = vec![Vec3f::zero(); vertex_count];
+        let mut bitangent_accum = vec![Vec3f::zero(); vertex_count];
+
+        for corners in topology.faces.chunks_exact(3) {
+            let indices = [corners[0] as usize, corners[1] as usize, corners[2] as usize];
+            if indices.iter().any(|&i| i >= vertex_count) { continue; }
+
+            let p = indices.map(|i| self.position[i]);
+            let uv = indices.map(|i| self.tex_coord_0[i]);
+
+            let e1 = p[1] - p[0];
+            let e2 = p[2] - p[0];
+            let duv1 = uv[1] - uv[0];
+            let duv2 = uv[2] - uv[0];
+
+            let r = 1.0 / (duv1.x * duv2.y - duv2.x * duv1.y);
+            if !r.is_finite() { continue; }
+
+            let tangent = (e1 * duv2.y - e2 * duv1.y) * r;
+            let bitangent = (e2 * duv1.x - e1 * duv2.x) * r;
+
+            let angles = [
+                corner_angle(p[1] - p[0], p[2] - p[0]),
+                corner_angle(p[0] - p[1], p[2] - p[1]),
+                corner_angle(p[0] - p[2], p[1] - p[2]),
+            ];
+
+            for (corner, &i) in indices.iter().enumerate() {
+                tangent_accum[i] += tangent * angles[corner];
+                bitangent_accum[i] += bitangent * angles[corner];
+            }
+        }
+
+        let mut tangent = Vec::with_capacity(vertex_count);
+        let mut binormal = Vec::with_capacity(vertex_count);
+
+        for i in 0..vertex_count {
+            let n = self.normal[i];
+            let t = tangent_accum[i] - n * n.dot(tangent_accum[i]);
+
+            let t = if t.magnitude_squared() > 1e-12 { t.normalized() } else { arbitrary_perpendicular(n) };
+
+            let handedness = if n.cross(t).dot(bitangent_accum[i]) < 0.0 { -1.0 } else { 1.0 };
+
+            tangent.push(t);
+            binormal.push(n.cross(t) * handedness);
+        }
+
+        self.tangent = tangent;
+        self.binormal = binormal;
+    }
+
+    /// Interprets `position_1`/`normal_1` as a morph target relative to the
+    /// base `position`/`normal`, returning its per-vertex deltas. Empty if
+    /// either secondary set is absent or doesn't have one entry per base
+    /// vertex - the format only carries a single secondary attribute set,
+    /// so this is always zero or one targets, not an arbitrary list.
+    pub fn morph_targets(&self) -> Vec<MorphTarget> {
+        let vertex_count = self.position.len();
+        if vertex_count == 0
+            || self.position_1.len() != vertex_count
+            || self.normal.len() != vertex_count
+            || self.normal_1.len() != vertex_count
+        {
+            return Vec::new();
+        }
+
+        let position_delta = (0..vertex_count).map(|i| self.position_1[i] - self.position[i]).collect();
+        let normal_delta = (0..vertex_count).map(|i| self.normal_1[i] - self.normal[i]).collect();
+
+        vec![MorphTarget { position_delta, normal_delta }]
+    }
+
+    /// Blends `position`/`normal` with `weights[i]` applied to
+    /// [`morph_targets`](Self::morph_targets)'s `i`th delta, returning the
+    /// deformed `(position, normal)` arrays. Targets past the end of
+    /// `weights` are left at weight 0; a weight past the end of the
+    /// targets is ignored. No-ops (returns the base arrays unchanged) if
+    /// there are no morph targets.
+    pub fn apply_weights(&self, weights: &[f32]) -> (Vec<Vec3f>, Vec<Vec3f>) {
+        let targets = self.morph_targets();
+        if targets.is_empty() {
+            return (self.position.clone(), self.normal.clone());
+        }
+
+        let mut position = self.position.clone();
+        let mut normal = self.normal.clone();
+
+        for (target, &weight) in targets.iter().zip(weights.iter()) {
+            if weight == 0.0 { continue; }
+            for i in 0..position.len() {
+                position[i] += target.position_delta[i] * weight;
+                normal[i] += target.normal_delta[i] * weight;
+            }
+        }
+
+        (position, normal)
+    }
+}
+
+/// A single blend shape derived from [`GeometrySection`]'s secondary
+/// position/normal attribute set: the per-vertex offset from the base mesh
+/// that [`GeometrySection::apply_weights`] scales and adds back in.
+#[derive(Debug, Clone)]
+pub struct MorphTarget {
+    pub position_delta: Vec<Vec3f>,
+    pub normal_delta: Vec<Vec3f>
+}
+
+/// The unsigned angle at the corner between two edges leaving it, used to
+/// weight that corner's contribution to [`GeometrySection::generate_tangents`]'s
+/// per-vertex tangent/bitangent accumulation.
+fn corner_angle(a: Vec3f, b: Vec3f) -> f32 {
+    let denom = a.magnitude() * b.magnitude();
+    if denom <= 0.0 { return 0.0; }
+    (a.dot(b) / denom).clamp(-1.0, 1.0).acos()
+}
+
+/// An arbitrary unit vector perpendicular to `n`, used when a vertex's
+/// accumulated tangent degenerates to (near) zero - e.g. every triangle
+/// touching it had degenerate UVs.
+fn arbitrary_perpendicular(n: Vec3f) -> Vec3f {
+    let helper = if n.x.abs() < 0.9 { Vec3f::new(1.0, 0.0, 0.0) } else { Vec3f::new(0.0, 1.0, 0.0) };
+    helper.cross(n).normalized()
+}
+
 #[derive(Debug, ItemReader)]
 pub struct GeometryHeader {
     pub attribute_format: u32,
@@ -675,6 +1087,70 @@ pub struct LinearFloatControllerSection {
     pub keyframes: Vec<(f32, f32)>
 }
 
+#[derive(Debug, ItemReader)]
+pub struct ConstFloatControllerSection {
+    pub name: Idstring,
+    pub flags: u32,
+    pub unknown_1: u32,
+    pub duration: f32,
+    pub value: f32
+}
+
+#[derive(Debug, ItemReader)]
+pub struct StepFloatControllerSection {
+    pub name: Idstring,
+    pub flags: u32,
+    pub unknown_1: u32,
+    pub duration: f32,
+    pub keyframes: Vec<(f32, f32)>
+}
+
+/// A single key of a [`BezierFloatControllerSection`]: time, value, and the
+/// in/out tangent control points (also plain floats - Bezier float curves
+/// don't need a 2D handle, just how far the curve departs from `value` on
+/// either side).
+#[derive(Debug, ItemReader)]
+pub struct BezierFloatControllerSection {
+    pub name: Idstring,
+    pub flags: u32,
+    pub unknown_1: u32,
+    pub duration: f32,
+    pub keyframes: Vec<(f32, f32, f32, f32)>
+}
+
+#[derive(Debug, ItemReader)]
+pub struct ConstVector3ControllerSection {
+    pub name: Idstring,
+    pub flags: u32,
+    pub unknown_1: u32,
+    pub duration: f32,
+    pub value: Vec3f
+}
+
+#[derive(Debug, ItemReader)]
+pub struct StepVector3ControllerSection {
+    pub name: Idstring,
+    pub flags: u32,
+    pub unknown_1: u32,
+    pub duration: f32,
+    pub keyframes: Vec<(f32, Vec3f)>
+}
+
+/// The Vec3 equivalent of [`BezierFloatControllerSection`], carrying a
+/// `(time, value, in_tangent, out_tangent)` key per sample. Unlike that
+/// float variant - whose tangents are already absolute control-point
+/// offsets, sampled via De Casteljau - this one's tangents are genuine
+/// rates of change, sampled with the cubic Hermite basis in
+/// [`sample_cubic_vec3_keys`] instead.
+#[derive(Debug, ItemReader)]
+pub struct BezierVector3ControllerSection {
+    pub name: Idstring,
+    pub flags: u32,
+    pub unknown_1: u32,
+    pub duration: f32,
+    pub keyframes: Vec<(f32, Vec3f, Vec3f, Vec3f)>
+}
+
 #[derive(Debug, ItemReader)]
 pub struct QuatLinearRotationControllerSection {
     pub name: Idstring,
@@ -684,6 +1160,284 @@ pub struct QuatLinearRotationControllerSection {
     pub keyframes: Vec<(f32, Vec4f)>
 }
 
+#[derive(Debug, ItemReader)]
+pub struct QuatStepRotationControllerSection {
+    pub name: Idstring,
+    pub flags: u32,
+    pub unknown_1: u32,
+    pub duration: f32,
+    pub keyframes: Vec<(f32, Vec4f)>
+}
+
+/// Value a [`Animatable`] controller can drive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Float(f32),
+    Vec3(Vec3f),
+
+    /// A rotation, as an `(x, y, z, w)` quaternion.
+    Quat(Vec4f)
+}
+
+/// Common interface to the `*Controller` sections: whatever shape its
+/// keyframes are stored in, it can be sampled at an arbitrary point in time
+/// to get the value it's driving at that instant.
+pub trait Animatable {
+    /// Evaluate this controller at time `t`, clamped to its duration.
+    fn sample(&self, t: f32) -> Value;
+}
+
+/// Find the pair of keyframes bracketing `t` and the interpolation factor
+/// between them, locating them by binary search since `keys` is sorted by
+/// time. `t` is assumed already clamped to `[keys[0].0, keys[last].0]`.
+/// Panics if `keys` is empty; callers handle the empty/single-key cases
+/// themselves since the value returned for those doesn't need interpolating.
+fn bracket_keys<T: Copy>(keys: &[(f32, T)], t: f32) -> (T, T, f32) {
+    let hi = keys.partition_point(|(kt, _)| *kt < t).clamp(1, keys.len() - 1);
+    let (t0, v0) = keys[hi - 1];
+    let (t1, v1) = keys[hi];
+    let u = if t1 > t0 { (t - t0) / (t1 - t0) } else { 0.0 };
+    (v0, v1, u)
+}
+
+fn sample_float_keys(keys: &[(f32, f32)], t: f32, duration: f32) -> f32 {
+    match keys {
+        [] => 0.0,
+        [(_, v)] => *v,
+        _ => {
+            let t = t.clamp(keys[0].0, duration.min(keys[keys.len() - 1].0));
+            let (v0, v1, u) = bracket_keys(keys, t);
+            v0 + (v1 - v0) * u
+        }
+    }
+}
+
+fn step_float_keys(keys: &[(f32, f32)], t: f32, duration: f32) -> f32 {
+    match keys {
+        [] => 0.0,
+        [(_, v)] => *v,
+        _ => {
+            let t = t.clamp(keys[0].0, duration.min(keys[keys.len() - 1].0));
+            let (v0, v1, u) = bracket_keys(keys, t);
+            if u >= 1.0 { v1 } else { v0 }
+        }
+    }
+}
+
+fn sample_vec3_keys(keys: &[(f32, Vec3f)], t: f32, duration: f32) -> Vec3f {
+    match keys {
+        [] => Vec3f::zero(),
+        [(_, v)] => *v,
+        _ => {
+            let t = t.clamp(keys[0].0, duration.min(keys[keys.len() - 1].0));
+            let (v0, v1, u) = bracket_keys(keys, t);
+            v0 + (v1 - v0) * u
+        }
+    }
+}
+
+fn step_vec3_keys(keys: &[(f32, Vec3f)], t: f32, duration: f32) -> Vec3f {
+    match keys {
+        [] => Vec3f::zero(),
+        [(_, v)] => *v,
+        _ => {
+            let t = t.clamp(keys[0].0, duration.min(keys[keys.len() - 1].0));
+            let (v0, v1, u) = bracket_keys(keys, t);
+            if u >= 1.0 { v1 } else { v0 }
+        }
+    }
+}
+
+/// Evaluates a `(time, value, in_tangent, out_tangent)` keyframe curve with
+/// the standard cubic Hermite basis - `h00(u)=2u³-3u²+1`, `h10(u)=u³-2u²+u`,
+/// `h01(u)=-2u³+3u²`, `h11(u)=u³-u²` - scaling each key's tangent by the
+/// segment's own duration `(t1-t0)` so a tangent means a rate of change
+/// rather than an absolute offset.
+fn sample_cubic_vec3_keys(keys: &[(f32, Vec3f, Vec3f, Vec3f)], t: f32, duration: f32) -> Vec3f {
+    match keys {
+        [] => Vec3f::zero(),
+        [(_, v, _, _)] => *v,
+        _ => {
+            let t = t.clamp(keys[0].0, duration.min(keys[keys.len() - 1].0));
+            let hi = keys.partition_point(|(kt, _, _, _)| *kt < t).clamp(1, keys.len() - 1);
+            let (t0, v0, _, out0) = keys[hi - 1];
+            let (t1, v1, in1, _) = keys[hi];
+            let dt = t1 - t0;
+            let u = if dt > 0.0 { (t - t0) / dt } else { 0.0 };
+
+            let u2 = u * u;
+            let u3 = u2 * u;
+            let h00 = 2.0 * u3 - 3.0 * u2 + 1.0;
+            let h10 = u3 - 2.0 * u2 + u;
+            let h01 = -2.0 * u3 + 3.0 * u2;
+            let h11 = u3 - u2;
+
+            v0 * h00 + out0 * (dt * h10) + v1 * h01 + in1 * (dt * h11)
+        }
+    }
+}
+
+/// Spherically interpolates between two `(x, y, z, w)` quaternions, taking
+/// the shorter way round (negating `to` if the quaternions are more than 90
+/// degrees apart) and falling back to a normalized lerp when they're close
+/// enough together that slerp's `sin(theta0)` divisor would be unstable.
+fn slerp_shortest(from: Vec4f, to: Vec4f, u: f32) -> Vec4f {
+    let from = from.normalized();
+    let mut to = to.normalized();
+    let mut dot = from.dot(to);
+    if dot < 0.0 {
+        to = -to;
+        dot = -dot;
+    }
+
+    if dot > 0.9995 {
+        return (from + (to - from) * u).normalized();
+    }
+
+    let theta0 = dot.clamp(-1.0, 1.0).acos();
+    let theta = theta0 * u;
+    let s0 = (theta0 - theta).sin() / theta0.sin();
+    let s1 = theta.sin() / theta0.sin();
+    from * s0 + to * s1
+}
+
+/// Reads the `(x, y, z, w)` quaternion off the orthonormal basis whose axes
+/// are `right`/`up`/`forward`, via the standard "largest diagonal term"
+/// rotation-matrix-to-quaternion conversion.
+fn quaternion_from_basis(right: Vec3f, up: Vec3f, forward: Vec3f) -> Vec4f {
+    let (rx, ry, rz) = (right.x, right.y, right.z);
+    let (ux, uy, uz) = (up.x, up.y, up.z);
+    let (fx, fy, fz) = (forward.x, forward.y, forward.z);
+
+    let trace = rx + uy + fz;
+    if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        Vec4f::new((uz - fy) / s, (fx - rz) / s, (ry - ux) / s, 0.25 * s)
+    }
+    else if rx > uy && rx > fz {
+        let s = (1.0 + rx - uy - fz).sqrt() * 2.0;
+        Vec4f::new(0.25 * s, (ux + ry) / s, (fx + rz) / s, (uz - fy) / s)
+    }
+    else if uy > fz {
+        let s = (1.0 + uy - rx - fz).sqrt() * 2.0;
+        Vec4f::new((ux + ry) / s, 0.25 * s, (fy + uz) / s, (fx - rz) / s)
+    }
+    else {
+        let s = (1.0 + fz - rx - uy).sqrt() * 2.0;
+        Vec4f::new((fx + rz) / s, (fy + uz) / s, 0.25 * s, (ry - ux) / s)
+    }
+}
+
+fn sample_quat_keys(keys: &[(f32, Vec4f)], t: f32, duration: f32) -> Vec4f {
+    match keys {
+        [] => Vec4f::new(0.0, 0.0, 0.0, 1.0),
+        [(_, v)] => *v,
+        _ => {
+            let t = t.clamp(keys[0].0, duration.min(keys[keys.len() - 1].0));
+            let (v0, v1, u) = bracket_keys(keys, t);
+            slerp_shortest(v0, v1, u)
+        }
+    }
+}
+
+fn step_quat_keys(keys: &[(f32, Vec4f)], t: f32, duration: f32) -> Vec4f {
+    match keys {
+        [] => Vec4f::new(0.0, 0.0, 0.0, 1.0),
+        [(_, v)] => *v,
+        _ => {
+            let t = t.clamp(keys[0].0, duration.min(keys[keys.len() - 1].0));
+            let (v0, v1, u) = bracket_keys(keys, t);
+            if u >= 1.0 { v1 } else { v0 }
+        }
+    }
+}
+
+impl Animatable for ConstFloatControllerSection {
+    fn sample(&self, _t: f32) -> Value { Value::Float(self.value) }
+}
+
+impl Animatable for StepFloatControllerSection {
+    fn sample(&self, t: f32) -> Value {
+        Value::Float(step_float_keys(&self.keyframes, t, self.duration))
+    }
+}
+
+impl Animatable for LinearFloatControllerSection {
+    fn sample(&self, t: f32) -> Value {
+        Value::Float(sample_float_keys(&self.keyframes, t, self.duration))
+    }
+}
+
+impl Animatable for BezierFloatControllerSection {
+    fn sample(&self, t: f32) -> Value {
+        match self.keyframes.as_slice() {
+            [] => Value::Float(0.0),
+            [(_, v, _, _)] => Value::Float(*v),
+            keys => {
+                let t = t.clamp(keys[0].0, self.duration.min(keys[keys.len() - 1].0));
+                let hi = keys.iter().position(|(kt, _, _, _)| *kt >= t).unwrap_or(keys.len() - 1).max(1);
+                let (t0, v0, _, out0) = keys[hi - 1];
+                let (t1, v1, in1, _) = keys[hi];
+                let u = if t1 > t0 { (t - t0) / (t1 - t0) } else { 0.0 };
+
+                // Cubic Bezier through (v0, v0+out0, v1-in1, v1), De Casteljau.
+                let p0 = v0;
+                let p1 = v0 + out0;
+                let p2 = v1 - in1;
+                let p3 = v1;
+                let a = p0 + (p1 - p0) * u;
+                let b = p1 + (p2 - p1) * u;
+                let c = p2 + (p3 - p2) * u;
+                let d = a + (b - a) * u;
+                let e = b + (c - b) * u;
+                Value::Float(d + (e - d) * u)
+            }
+        }
+    }
+}
+
+impl Animatable for ConstVector3ControllerSection {
+    fn sample(&self, _t: f32) -> Value { Value::Vec3(self.value) }
+}
+
+impl Animatable for StepVector3ControllerSection {
+    fn sample(&self, t: f32) -> Value {
+        Value::Vec3(step_vec3_keys(&self.keyframes, t, self.duration))
+    }
+}
+
+impl Animatable for LinearVector3ControllerSection {
+    fn sample(&self, t: f32) -> Value {
+        Value::Vec3(sample_vec3_keys(&self.keyframes, t, self.duration))
+    }
+}
+
+impl Animatable for BezierVector3ControllerSection {
+    fn sample(&self, t: f32) -> Value {
+        Value::Vec3(sample_cubic_vec3_keys(&self.keyframes, t, self.duration))
+    }
+}
+
+impl Animatable for QuatStepRotationControllerSection {
+    fn sample(&self, t: f32) -> Value {
+        Value::Quat(step_quat_keys(&self.keyframes, t, self.duration))
+    }
+}
+
+impl Animatable for QuatLinearRotationControllerSection {
+    fn sample(&self, t: f32) -> Value {
+        Value::Quat(sample_quat_keys(&self.keyframes, t, self.duration))
+    }
+}
+
+/// A look-at constraint: rather than storing keyframes, it orients its
+/// object toward another one. `section_1` is the look-at target,
+/// `section_2` is the up-vector reference, and `section_3` is the
+/// constrained object itself - all three are ids into the surrounding
+/// section table, resolved and sampled by
+/// [`DieselContainer::sample_look_at`](super::container::DieselContainer::sample_look_at)
+/// since, unlike the other controllers, evaluating this one needs access
+/// to those other sections rather than just its own fields.
 #[derive(Debug, ItemReader)]
 pub struct LookAtConstrRotationControllerSection {
     pub name: Idstring,
@@ -693,6 +1447,114 @@ pub struct LookAtConstrRotationControllerSection {
     pub section_3: u32
 }
 
+#[cfg(test)]
+mod animatable_tests {
+    use super::*;
+
+    fn controller_name() -> Idstring { Idstring(0) }
+
+    #[test]
+    fn step_float_controller_holds_the_left_key() {
+        let c = StepFloatControllerSection {
+            name: controller_name(), flags: 0, unknown_1: 0, duration: 10.0,
+            keyframes: vec![(0.0, 1.0), (5.0, 2.0), (10.0, 3.0)]
+        };
+        assert_eq!(c.sample(4.9), Value::Float(1.0));
+        assert_eq!(c.sample(5.0), Value::Float(2.0));
+        assert_eq!(c.sample(9.9), Value::Float(2.0));
+    }
+
+    #[test]
+    fn linear_float_controller_interpolates_between_keys() {
+        let c = LinearFloatControllerSection {
+            name: controller_name(), flags: 0, unknown_1: 0, duration: 10.0,
+            keyframes: vec![(0.0, 0.0), (10.0, 10.0)]
+        };
+        assert_eq!(c.sample(2.5), Value::Float(2.5));
+    }
+
+    #[test]
+    fn linear_float_controller_clamps_past_its_duration() {
+        let c = LinearFloatControllerSection {
+            name: controller_name(), flags: 0, unknown_1: 0, duration: 10.0,
+            keyframes: vec![(0.0, 0.0), (10.0, 10.0)]
+        };
+        assert_eq!(c.sample(50.0), Value::Float(10.0));
+    }
+
+    #[test]
+    fn const_float_controller_ignores_time() {
+        let c = ConstFloatControllerSection { name: controller_name(), flags: 0, unknown_1: 0, duration: 10.0, value: 7.0 };
+        assert_eq!(c.sample(0.0), Value::Float(7.0));
+        assert_eq!(c.sample(100.0), Value::Float(7.0));
+    }
+
+    #[test]
+    fn bezier_float_controller_passes_through_its_keys() {
+        let c = BezierFloatControllerSection {
+            name: controller_name(), flags: 0, unknown_1: 0, duration: 10.0,
+            keyframes: vec![(0.0, 0.0, 0.0, 1.0), (10.0, 10.0, 1.0, 0.0)]
+        };
+        assert_eq!(c.sample(0.0), Value::Float(0.0));
+        assert_eq!(c.sample(10.0), Value::Float(10.0));
+        if let Value::Float(mid) = c.sample(5.0) {
+            assert!(mid > 0.0 && mid < 10.0);
+        } else { panic!("expected a float value"); }
+    }
+
+    #[test]
+    fn linear_vector3_controller_interpolates_componentwise() {
+        let c = LinearVector3ControllerSection {
+            name: controller_name(), flags: 0, unknown_1: 0, duration: 10.0,
+            keyframes: vec![(0.0, Vec3f::new(0.0, 0.0, 0.0)), (10.0, Vec3f::new(10.0, -10.0, 20.0))]
+        };
+        assert_eq!(c.sample(5.0), Value::Vec3(Vec3f::new(5.0, -5.0, 10.0)));
+    }
+
+    #[test]
+    fn single_key_controller_returns_that_key_regardless_of_time() {
+        let c = LinearFloatControllerSection {
+            name: controller_name(), flags: 0, unknown_1: 0, duration: 10.0,
+            keyframes: vec![(3.0, 42.0)]
+        };
+        assert_eq!(c.sample(0.0), Value::Float(42.0));
+        assert_eq!(c.sample(100.0), Value::Float(42.0));
+    }
+
+    #[test]
+    fn empty_controller_has_a_defined_fallback_value() {
+        let c = LinearFloatControllerSection { name: controller_name(), flags: 0, unknown_1: 0, duration: 10.0, keyframes: vec![] };
+        assert_eq!(c.sample(0.0), Value::Float(0.0));
+    }
+
+    #[test]
+    fn quat_linear_controller_takes_the_shortest_path() {
+        // Two quaternions representing the same rotation with opposite sign -
+        // slerping between them the "long way" would visibly spin the wrong way.
+        let a = Vec4f::new(0.0, 0.0, 0.0, 1.0);
+        let b = Vec4f::new(0.0, 0.0, 0.0, -1.0);
+        let c = QuatLinearRotationControllerSection {
+            name: controller_name(), flags: 0, unknown_1: 0, duration: 1.0,
+            keyframes: vec![(0.0, a), (1.0, b)]
+        };
+        if let Value::Quat(mid) = c.sample(0.5) {
+            // Midpoint of the short path (a to -b, i.e. a to a) should stay near identity.
+            assert!(mid.dot(a).abs() > 0.99, "slerp should take the short way round, got {:?}", mid);
+        } else { panic!("expected a quat value"); }
+    }
+
+    #[test]
+    fn quat_step_controller_holds_the_left_key() {
+        let a = Vec4f::new(0.0, 0.0, 0.7071, 0.7071);
+        let b = Vec4f::new(0.7071, 0.0, 0.0, 0.7071);
+        let c = QuatStepRotationControllerSection {
+            name: controller_name(), flags: 0, unknown_1: 0, duration: 1.0,
+            keyframes: vec![(0.0, a), (1.0, b)]
+        };
+        assert_eq!(c.sample(0.5), Value::Quat(a));
+    }
+}
+
 #[derive(Debug, ItemReader)]
 pub struct ModelToolHashSection {
     version: u16,
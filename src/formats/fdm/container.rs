@@ -78,6 +78,147 @@ impl DieselContainer {
         let res: Option<&T> = sec_ref.try_into().ok();
         res
     }
+
+    /// Evaluates `obj`'s transform at time `t` by sampling whichever
+    /// animation controllers it references. Translation and rotation are
+    /// each taken from the last controller of the matching kind (Vec3 or
+    /// Quat) found in `obj.animation_controllers`, falling back to the
+    /// static transform's own translation/rotation where nothing animates
+    /// them. Scale is never driven by any known controller type, so it's
+    /// always taken from the static transform.
+    pub fn sample_transform(&self, obj: &super::Object3dSection, t: f32) -> super::Mat4f {
+        use vek::Vec3;
+
+        let mut translation = None;
+        let mut rotation = None;
+        for &id in &obj.animation_controllers {
+            let Some(ctrl) = self.get(id).and_then(super::Section::as_animatable) else { continue };
+            match ctrl.sample(t) {
+                super::Value::Vec3(v) => translation = Some(v),
+                super::Value::Quat(q) => rotation = Some(q),
+                super::Value::Float(_) => {}
+            }
+        }
+
+        let mut result = obj.transform;
+
+        if let Some(q) = rotation {
+            let scale = Vec3::new(
+                Vec3::new(result[(0,0)], result[(1,0)], result[(2,0)]).magnitude(),
+                Vec3::new(result[(0,1)], result[(1,1)], result[(2,1)]).magnitude(),
+                Vec3::new(result[(0,2)], result[(1,2)], result[(2,2)]).magnitude()
+            );
+            let rot: super::Mat4f = vek::Quaternion::from_xyzw(q.x, q.y, q.z, q.w).normalized().into();
+            for c in 0..3 {
+                for r in 0..3 {
+                    result[(r, c)] = rot[(r, c)] * scale[c];
+                }
+            }
+        }
+
+        if let Some(v) = translation {
+            result[(0,3)] = v.x;
+            result[(1,3)] = v.y;
+            result[(2,3)] = v.z;
+        }
+
+        result
+    }
+
+    /// Resolves `mesh.skinbones` and returns its joints as `(bone Object3D
+    /// section id, inverse bind matrix)` pairs, in bone-palette order - the
+    /// same order `GeometrySection::blend_indices_0`/`_1` index into, and the
+    /// order a glTF skin's JOINTS_0/WEIGHTS_0 attributes expect.
+    pub fn skin_joints(&self, mesh: &super::MeshModel) -> Option<Vec<(u32, super::Mat4f)>> {
+        match self.get(mesh.skinbones)? {
+            Section::SkinBones(skin) => Some(
+                skin.bones.iter().copied().zip(skin.inverse_bind_matrices.iter().copied()).collect()
+            ),
+            _ => None
+        }
+    }
+
+    /// Axis-aligned bounding box over every position a Vec3-driving
+    /// animation controller `obj` references can reach across that
+    /// controller's own `duration`, not just `obj`'s bind pose - the box a
+    /// viewer or culler should test instead of a bind-pose-only bounds,
+    /// so a moving object doesn't get culled as "offscreen" while a
+    /// keyframe is actually pulling it into view.
+    ///
+    /// Every keyframe position of every referenced Vec3 controller is
+    /// folded in (not just the last one [`sample_transform`](Self::sample_transform)
+    /// treats as "the" translation - any of them could matter for
+    /// bounding), along with `obj`'s own static translation as a
+    /// fallback/floor. When `sample_rate` is `Some(n)`, `n` extra
+    /// evenly-spaced samples per unit time are also folded in, to catch a
+    /// curved ([`BezierVector3ControllerSection`](super::BezierVector3ControllerSection))
+    /// controller's bulge between keyframes that the keyframes alone miss.
+    pub fn animated_bounds(&self, obj: &super::Object3dSection, sample_rate: Option<f32>) -> vek::Aabb<f32> {
+        let base = obj.transform.cols[3].xyz();
+        let mut aabb = vek::Aabb::new_empty(base);
+
+        for &id in &obj.animation_controllers {
+            let keys: Vec<(f32, super::Vec3f)> = match self.get(id) {
+                Some(Section::ConstVector3Controller(c)) => vec![(0.0, c.value)],
+                Some(Section::StepVector3Controller(c)) => c.keyframes.clone(),
+                Some(Section::LinearVector3Controller(c)) => c.keyframes.clone(),
+                Some(Section::BezierVector3Controller(c)) => c.keyframes.iter().map(|&(t, v, _, _)| (t, v)).collect(),
+                _ => continue
+            };
+            if keys.is_empty() {
+                continue;
+            }
+
+            for &(_, v) in &keys {
+                aabb = aabb.expanded_to_contain_point(v);
+            }
+
+            if let Some(rate) = sample_rate {
+                let Some(ctrl) = self.get(id).and_then(super::Section::as_animatable) else { continue };
+                let duration = keys.iter().map(|&(t, _)| t).fold(0.0f32, f32::max);
+                let step_count = (duration * rate).ceil().max(1.0) as u32;
+                for i in 0..=step_count {
+                    let t = duration * (i as f32 / step_count as f32);
+                    if let super::Value::Vec3(v) = ctrl.sample(t) {
+                        aabb = aabb.expanded_to_contain_point(v);
+                    }
+                }
+            }
+        }
+
+        aabb
+    }
+
+    /// Evaluates `ctrl`'s look-at constraint at time `t`, returning the
+    /// `(x, y, z, w)` quaternion that orients the constrained object
+    /// toward its target. `ctrl.section_1`/`section_2`/`section_3` are
+    /// resolved against this container as the target, the up-vector
+    /// reference, and the constrained object itself, and each is sampled
+    /// through [`sample_transform`](Self::sample_transform) for its
+    /// current position, so an animated target or up reference carries
+    /// the look-at along with it. `None` if any of the three ids doesn't
+    /// resolve to an object.
+    ///
+    /// The basis is built the way [`LookAtConstrRotationControllerSection`]
+    /// documents: `forward` points from the constrained object to the
+    /// target, `up` is the up reference re-orthogonalized against
+    /// `forward`, and `right` is `up × forward`.
+    pub fn sample_look_at(&self, ctrl: &super::LookAtConstrRotationControllerSection, t: f32) -> Option<super::Vec4f> {
+        let target_obj = self.get(ctrl.section_1).and_then(Section::as_object3d)?;
+        let up_obj = self.get(ctrl.section_2).and_then(Section::as_object3d)?;
+        let self_obj = self.get(ctrl.section_3).and_then(Section::as_object3d)?;
+
+        let origin = self.sample_transform(self_obj, t).cols[3].xyz();
+        let target = self.sample_transform(target_obj, t).cols[3].xyz();
+        let up_pos = self.sample_transform(up_obj, t).cols[3].xyz();
+
+        let forward = (target - origin).normalized();
+        let up_raw = up_pos - origin;
+        let up = (up_raw - forward * up_raw.dot(forward)).normalized();
+        let right = up.cross(forward);
+
+        Some(super::quaternion_from_basis(right, up, forward))
+    }
 }
 
 impl crate::util::binaryreader::ItemReader for DieselContainer {
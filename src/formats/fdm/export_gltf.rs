@@ -0,0 +1,783 @@
+//! Standalone glTF 2.0 export for a parsed [`DieselContainer`] - so Diesel
+//! models can be opened in Blender/three.js without bespoke tooling.
+//!
+//! Every [`Section::Object3D`], [`Section::Model`] and [`Section::Light`]
+//! becomes one glTF node (the latter two just wrap an embedded
+//! [`Object3dSection`]); `parent` links them into the node hierarchy the
+//! same way the format itself does. A [`MeshModel`]'s render atoms all draw
+//! out of the same vertex buffer, so rather than re-slicing vertices per
+//! atom, each mesh gets one shared set of attribute accessors (built from
+//! whichever vertex attribute vectors are actually populated) and one
+//! *indices* accessor per atom, sliced out of the topology's index buffer -
+//! which is exactly what a glTF primitive needs to tell two draws with
+//! different materials apart.
+//!
+//! [`MaterialSection`] carries nothing glTF understands (no base colour, no
+//! texture references - just an opaque `items` table), so materials only
+//! carry a name through and otherwise export as glTF's default PBR material.
+//! [`LightSection`] has no candela/lumen field either; `far_range` is used
+//! as the punctual light's `range` since it already describes the same
+//! falloff-distance idea, while `intensity` is left at glTF's default of 1.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+use vek::{Mat3, Quaternion, Vec3, Vec4};
+
+use super::DieselContainer;
+use super::{
+    GeometrySection, LightSection, LightType, MaterialGroupSection,
+    MaterialSection, Mat4f, MeshModel, ModelData, Object3dSection, PassthroughGPSection, Rgba,
+    Section, TopologyIPSection, TopologySection, Vec2f, Vec3f
+};
+
+#[derive(Debug)]
+pub enum ExportGltfError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// A `u32` section reference didn't resolve to a section of the expected type.
+    UnresolvedSection(u32),
+    /// A render atom sliced past the end of its topology's index buffer.
+    RenderAtomOutOfRange { model: u32 }
+}
+variant_from!(ExportGltfError::Io, io::Error);
+variant_from!(ExportGltfError::Json, serde_json::Error);
+
+const COMPONENT_UNSIGNED_SHORT: u32 = 5123;
+const COMPONENT_FLOAT: u32 = 5126;
+
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+const NO_PARENT: u32 = 0xFFFFFFFF;
+const NO_MATERIAL: u32 = 0xFFFFFFFF;
+
+/// Parses `container`'s scene graph into a glTF document and writes it to
+/// `gltf_path`, alongside a sibling `.bin` holding every accessor's data.
+pub fn write_to_files(container: &DieselContainer, gltf_path: &Path) -> Result<(), ExportGltfError> {
+    let bin_path = gltf_path.with_extension("bin");
+    let bin_name = bin_path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "scene.bin".to_owned());
+
+    let mut exporter = Exporter::new(container);
+    exporter.run()?;
+    let (document, bin_bytes) = exporter.finish(bin_name);
+
+    fs::write(&bin_path, &bin_bytes)?;
+    fs::write(gltf_path, serde_json::to_vec_pretty(&document)?)?;
+    Ok(())
+}
+
+macro_rules! section_accessor {
+    ($name:ident, $variant:ident, $ty:ty) => {
+        fn $name(container: &DieselContainer, id: u32) -> Result<&$ty, ExportGltfError> {
+            match container.get(id) {
+                Some(Section::$variant(s)) => Ok(s.as_ref()),
+                _ => Err(ExportGltfError::UnresolvedSection(id))
+            }
+        }
+    }
+}
+section_accessor!(passthrough_gp_of, PassthroughGP, PassthroughGPSection);
+section_accessor!(topology_ip_of, TopologyIP, TopologyIPSection);
+section_accessor!(geometry_of, Geometry, GeometrySection);
+section_accessor!(topology_of, Topology, TopologySection);
+section_accessor!(material_group_of, MaterialGroup, MaterialGroupSection);
+section_accessor!(material_of, Material, MaterialSection);
+
+/// The [`Object3dSection`] embedded in any section that takes part in the
+/// node hierarchy - plain empties, but also models and lights.
+fn object3d_of(sec: &Section) -> Option<&Object3dSection> {
+    sec.as_object3d()
+}
+
+struct Exporter<'c> {
+    container: &'c DieselContainer,
+    buffer: BufferBuilder,
+    nodes: Vec<Node>,
+    meshes: Vec<Mesh>,
+    materials: Vec<Material>,
+    material_index: HashMap<u32, usize>,
+    lights: Vec<Light>,
+    animations: Vec<Animation>,
+    section_to_node: HashMap<u32, usize>,
+    parent_requests: Vec<(usize, u32)>,
+    root_nodes: Vec<usize>
+}
+
+impl<'c> Exporter<'c> {
+    fn new(container: &'c DieselContainer) -> Self {
+        Exporter {
+            container,
+            buffer: BufferBuilder::new(),
+            nodes: Vec::new(),
+            meshes: Vec::new(),
+            materials: Vec::new(),
+            material_index: HashMap::new(),
+            lights: Vec::new(),
+            animations: Vec::new(),
+            section_to_node: HashMap::new(),
+            parent_requests: Vec::new(),
+            root_nodes: Vec::new()
+        }
+    }
+
+    fn run(&mut self) -> Result<(), ExportGltfError> {
+        for (id, sec) in self.container.iter() {
+            if let Some(obj) = object3d_of(sec) {
+                let node_idx = self.add_node(id, obj);
+                match sec {
+                    Section::Model(m) => self.add_model_data(node_idx, id, &m.data)?,
+                    Section::Light(l) => self.add_light(node_idx, l),
+                    _ => {}
+                }
+            }
+        }
+        self.connect_parents();
+        self.add_animations();
+        Ok(())
+    }
+
+    fn add_node(&mut self, sec_id: u32, obj: &Object3dSection) -> usize {
+        let (translation, rotation, scale) = decompose_trs(obj.transform);
+
+        let idx = self.nodes.len();
+        self.nodes.push(Node {
+            name: Some(format!("{}", obj.name)),
+            children: Vec::new(),
+            mesh: None,
+            translation,
+            rotation,
+            scale,
+            extensions: None
+        });
+        self.section_to_node.insert(sec_id, idx);
+
+        if obj.parent != NO_PARENT {
+            self.parent_requests.push((idx, obj.parent));
+        }
+        idx
+    }
+
+    fn add_model_data(&mut self, node_idx: usize, model_id: u32, data: &ModelData) -> Result<(), ExportGltfError> {
+        // BoundsOnly models are culling/collision volumes, not renderable
+        // geometry - there's nothing glTF-shaped to put on the node.
+        if let ModelData::Mesh(mesh) = data {
+            let mesh_idx = self.add_mesh(model_id, mesh)?;
+            self.nodes[node_idx].mesh = Some(mesh_idx);
+        }
+        Ok(())
+    }
+
+    fn add_mesh(&mut self, model_id: u32, mesh: &MeshModel) -> Result<usize, ExportGltfError> {
+        let pt_gp = passthrough_gp_of(self.container, mesh.geometry_provider)?;
+        let topo_ip = topology_ip_of(self.container, mesh.topology_ip)?;
+        let geom = geometry_of(self.container, pt_gp.geometry)?;
+        let topo = topology_of(self.container, topo_ip.topology)?;
+
+        let attributes = self.buffer.push_attributes(geom);
+
+        let material_group = material_group_of(self.container, mesh.material_group).ok();
+
+        let mut primitives = Vec::with_capacity(mesh.render_atoms.len());
+        for ra in &mesh.render_atoms {
+            let start = ra.base_index as usize;
+            let end = start + (ra.triangle_count as usize) * 3;
+            let indices = topo.faces.get(start..end)
+                .ok_or(ExportGltfError::RenderAtomOutOfRange { model: model_id })?;
+
+            let material = match material_group {
+                Some(mg) if ra.material != NO_MATERIAL => mg.material_ids.get(ra.material as usize)
+                    .map(|&mat_id| self.material_for(mat_id))
+                    .transpose()?,
+                _ => None
+            };
+
+            primitives.push(Primitive {
+                attributes: attributes.clone(),
+                indices: self.buffer.push_indices(indices),
+                material
+            });
+        }
+
+        self.meshes.push(Mesh { primitives });
+        Ok(self.meshes.len() - 1)
+    }
+
+    fn material_for(&mut self, mat_id: u32) -> Result<usize, ExportGltfError> {
+        if let Some(&idx) = self.material_index.get(&mat_id) {
+            return Ok(idx);
+        }
+        let mat = material_of(self.container, mat_id)?;
+        let idx = self.materials.len();
+        self.materials.push(Material {
+            name: format!("{:016x}", mat.name),
+            pbr_metallic_roughness: PbrMetallicRoughness::default()
+        });
+        self.material_index.insert(mat_id, idx);
+        Ok(idx)
+    }
+
+    fn add_light(&mut self, node_idx: usize, light: &LightSection) {
+        let light_idx = self.lights.len();
+        self.lights.push(Light {
+            type_: match light.light_type {
+                LightType::Omnidirectional => "point",
+                LightType::Spot => "spot"
+            },
+            color: [light.color.r, light.color.g, light.color.b],
+            intensity: 1.0,
+            range: if light.far_range > 0.0 { Some(light.far_range) } else { None }
+        });
+        self.nodes[node_idx].extensions = Some(NodeExtensions {
+            khr_lights_punctual: NodeLightRef { light: light_idx }
+        });
+    }
+
+    fn connect_parents(&mut self) {
+        let mut has_parent = HashSet::new();
+        for (child_idx, parent_sec_id) in std::mem::take(&mut self.parent_requests) {
+            if let Some(&parent_idx) = self.section_to_node.get(&parent_sec_id) {
+                self.nodes[parent_idx].children.push(child_idx);
+                has_parent.insert(child_idx);
+            }
+        }
+        self.root_nodes = (0..self.nodes.len()).filter(|i| !has_parent.contains(i)).collect();
+    }
+
+    /// Turns every `*Controller` section an [`Object3dSection`] references
+    /// into a glTF animation channel targeting that object's node - the same
+    /// `animation_controllers` linkage [`DieselContainer::sample_transform`]
+    /// samples at runtime, just baked out as keyframes instead. Vec3
+    /// controllers drive `translation` (matching `sample_transform`'s own
+    /// reading of them - nothing in the format marks a Vec3 controller as
+    /// driving scale instead) and quaternion controllers drive `rotation`;
+    /// every controller becomes its own sampler since nothing else in a glTF
+    /// animation sampler can represent per-controller duration/`name`.
+    /// No-ops (emits no `animations` array) if no object references a
+    /// controller of either kind.
+    fn add_animations(&mut self) {
+        let mut channels = Vec::new();
+        let mut samplers = Vec::new();
+
+        let targets: Vec<(usize, u32)> = self.section_to_node.iter()
+            .map(|(&sec_id, &node_idx)| (node_idx, sec_id))
+            .collect();
+
+        for (node_idx, sec_id) in targets {
+            let Some(obj) = self.container.get(sec_id).and_then(object3d_of) else { continue };
+
+            for &ctrl_id in &obj.animation_controllers {
+                let path = match self.container.get(ctrl_id) {
+                    Some(Section::LinearVector3Controller(c)) => {
+                        if c.keyframes.is_empty() { continue; }
+                        let times: Vec<f32> = c.keyframes.iter().map(|&(t, _)| t).collect();
+                        let values: Vec<Vec3f> = c.keyframes.iter().map(|&(_, v)| v).collect();
+                        let input = self.buffer.push_time_accessor(&times);
+                        let output = self.buffer.push_vec3_accessor(&values, false, None);
+                        samplers.push(AnimationSampler { input, interpolation: "LINEAR", output });
+                        "translation"
+                    },
+                    Some(Section::QuatLinearRotationController(c)) => {
+                        if c.keyframes.is_empty() { continue; }
+                        let times: Vec<f32> = c.keyframes.iter().map(|&(t, _)| t).collect();
+                        let values: Vec<Vec4<f32>> = c.keyframes.iter().map(|&(_, v)| v).collect();
+                        let input = self.buffer.push_time_accessor(&times);
+                        let output = self.buffer.push_rotation_accessor(&values);
+                        samplers.push(AnimationSampler { input, interpolation: "LINEAR", output });
+                        "rotation"
+                    },
+                    _ => continue
+                };
+
+                channels.push(AnimationChannel {
+                    sampler: samplers.len() - 1,
+                    target: AnimationChannelTarget { node: node_idx, path }
+                });
+            }
+        }
+
+        if !channels.is_empty() {
+            self.animations.push(Animation { channels, samplers });
+        }
+    }
+
+    fn finish(self, bin_name: String) -> (Document, Vec<u8>) {
+        let root_nodes = self.root_nodes.clone();
+        let extensions_used = if self.lights.is_empty() { Vec::new() } else { vec!["KHR_lights_punctual"] };
+        let extensions = if self.lights.is_empty() {
+            None
+        } else {
+            Some(DocumentExtensions { khr_lights_punctual: KhrLightsPunctual { lights: self.lights } })
+        };
+
+        let buffer_bytes = self.buffer.bytes;
+        let document = Document {
+            asset: Asset { version: "2.0" },
+            extensions_used,
+            scene: 0,
+            scenes: vec![Scene { nodes: root_nodes }],
+            nodes: self.nodes,
+            meshes: self.meshes,
+            materials: self.materials,
+            accessors: self.buffer.accessors,
+            buffer_views: self.buffer.buffer_views,
+            buffers: vec![Buffer { uri: bin_name, byte_length: buffer_bytes.len() }],
+            animations: self.animations,
+            extensions
+        };
+        (document, buffer_bytes)
+    }
+}
+
+/// Splits a transform matrix into translation/rotation(quaternion xyzw)/scale,
+/// the same decomposition Blender's own importer expects a node's TRS to be.
+fn decompose_trs(mat: Mat4f) -> ([f32; 3], [f32; 4], [f32; 3]) {
+    let translation = mat.cols[3].xyz();
+
+    let mut rotation_scale: Mat3<f32> = mat.into();
+    let (col0, sx) = rotation_scale.cols[0].normalized_and_get_magnitude();
+    let (col1, sy) = rotation_scale.cols[1].normalized_and_get_magnitude();
+    let (col2, sz) = rotation_scale.cols[2].normalized_and_get_magnitude();
+    rotation_scale.cols[0] = col0;
+    rotation_scale.cols[1] = col1;
+    rotation_scale.cols[2] = col2;
+    let mut scale = Vec3::new(sx, sy, sz);
+
+    // A negative determinant means the exported scale flipped handedness;
+    // fold the flip into one axis so the remaining 3x3 is a pure rotation.
+    if rotation_scale.determinant() < 0.0 {
+        rotation_scale.cols[0] = -rotation_scale.cols[0];
+        scale.x = -scale.x;
+    }
+
+    let rotation = quaternion_from_mat3(rotation_scale);
+
+    (translation.into_array(), [rotation.x, rotation.y, rotation.z, rotation.w], scale.into_array())
+}
+
+/// Standard "pick the largest diagonal term" rotation-matrix-to-quaternion
+/// conversion - vek doesn't provide `Quaternion: From<Mat3<T>>` itself (it's
+/// present in the source but commented out pending a blocking fix upstream).
+fn quaternion_from_mat3(m: Mat3<f32>) -> Quaternion<f32> {
+    let trace = m.trace();
+
+    if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        Quaternion {
+            w: 0.25 * s,
+            x: (m.cols[1][2] - m.cols[2][1]) / s,
+            y: (m.cols[2][0] - m.cols[0][2]) / s,
+            z: (m.cols[0][1] - m.cols[1][0]) / s
+        }
+    }
+    else if m.cols[0][0] > m.cols[1][1] && m.cols[0][0] > m.cols[2][2] {
+        let s = (1.0 + m.cols[0][0] - m.cols[1][1] - m.cols[2][2]).sqrt() * 2.0;
+        Quaternion {
+            w: (m.cols[1][2] - m.cols[2][1]) / s,
+            x: 0.25 * s,
+            y: (m.cols[1][0] + m.cols[0][1]) / s,
+            z: (m.cols[2][0] + m.cols[0][2]) / s
+        }
+    }
+    else if m.cols[1][1] > m.cols[2][2] {
+        let s = (1.0 + m.cols[1][1] - m.cols[0][0] - m.cols[2][2]).sqrt() * 2.0;
+        Quaternion {
+            w: (m.cols[2][0] - m.cols[0][2]) / s,
+            x: (m.cols[1][0] + m.cols[0][1]) / s,
+            y: 0.25 * s,
+            z: (m.cols[2][1] + m.cols[1][2]) / s
+        }
+    }
+    else {
+        let s = (1.0 + m.cols[2][2] - m.cols[0][0] - m.cols[1][1]).sqrt() * 2.0;
+        Quaternion {
+            w: (m.cols[0][1] - m.cols[1][0]) / s,
+            x: (m.cols[2][0] + m.cols[0][2]) / s,
+            y: (m.cols[2][1] + m.cols[1][2]) / s,
+            z: 0.25 * s
+        }
+    }
+}
+
+/// Accumulates every accessor's raw bytes into one flat buffer, padding each
+/// new bufferView onto a 4-byte boundary as glTF requires.
+struct BufferBuilder {
+    bytes: Vec<u8>,
+    buffer_views: Vec<BufferView>,
+    accessors: Vec<Accessor>
+}
+
+impl BufferBuilder {
+    fn new() -> Self {
+        BufferBuilder { bytes: Vec::new(), buffer_views: Vec::new(), accessors: Vec::new() }
+    }
+
+    fn push_view(&mut self, data: &[u8], target: Option<u32>) -> usize {
+        while self.bytes.len() % 4 != 0 { self.bytes.push(0); }
+        let byte_offset = self.bytes.len();
+        self.bytes.extend_from_slice(data);
+        self.buffer_views.push(BufferView { byte_offset, byte_length: data.len(), target });
+        self.buffer_views.len() - 1
+    }
+
+    fn push_vec3_accessor(&mut self, data: &[Vec3f], bounded: bool, target: Option<u32>) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 12);
+        for v in data {
+            raw.extend_from_slice(&v.x.to_le_bytes());
+            raw.extend_from_slice(&v.y.to_le_bytes());
+            raw.extend_from_slice(&v.z.to_le_bytes());
+        }
+        let view = self.push_view(&raw, target);
+        let (min, max) = if bounded { vec3_bounds(data) } else { (None, None) };
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: data.len(), type_: "VEC3",
+            normalized: None, min, max
+        });
+        self.accessors.len() - 1
+    }
+
+    fn push_vec2_accessor(&mut self, data: &[Vec2f]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 8);
+        for v in data {
+            raw.extend_from_slice(&v.x.to_le_bytes());
+            raw.extend_from_slice(&v.y.to_le_bytes());
+        }
+        let view = self.push_view(&raw, Some(TARGET_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: data.len(), type_: "VEC2",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    /// Tangent accessor: glTF wants `vec4(tangent.xyz, handedness)`, not the
+    /// separate tangent/binormal vectors the format stores - handedness is
+    /// recovered the same way [`GeometrySection::generate_tangents`] derives
+    /// it in the first place: `sign(dot(cross(normal, tangent), binormal))`.
+    fn push_tangent_accessor(&mut self, tangent: &[Vec3f], binormal: &[Vec3f], normal: &[Vec3f]) -> usize {
+        let mut raw = Vec::with_capacity(tangent.len() * 16);
+        for i in 0..tangent.len() {
+            let w = if normal[i].cross(tangent[i]).dot(binormal[i]) < 0.0 { -1.0f32 } else { 1.0f32 };
+            raw.extend_from_slice(&tangent[i].x.to_le_bytes());
+            raw.extend_from_slice(&tangent[i].y.to_le_bytes());
+            raw.extend_from_slice(&tangent[i].z.to_le_bytes());
+            raw.extend_from_slice(&w.to_le_bytes());
+        }
+        let view = self.push_view(&raw, Some(TARGET_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: tangent.len(), type_: "VEC4",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    fn push_color_accessor(&mut self, data: &[Rgba]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 4);
+        for c in data {
+            raw.extend_from_slice(&[c.r, c.g, c.b, c.a]);
+        }
+        let view = self.push_view(&raw, Some(TARGET_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: 5121, count: data.len(), type_: "VEC4",
+            normalized: Some(true), min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    fn push_joints_accessor(&mut self, data: &[Vec4<u16>]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 8);
+        for v in data {
+            raw.extend_from_slice(&v.x.to_le_bytes());
+            raw.extend_from_slice(&v.y.to_le_bytes());
+            raw.extend_from_slice(&v.z.to_le_bytes());
+            raw.extend_from_slice(&v.w.to_le_bytes());
+        }
+        let view = self.push_view(&raw, Some(TARGET_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_UNSIGNED_SHORT, count: data.len(), type_: "VEC4",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    fn push_weights_accessor(&mut self, data: &[Vec4<f32>]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 16);
+        for v in data {
+            raw.extend_from_slice(&v.x.to_le_bytes());
+            raw.extend_from_slice(&v.y.to_le_bytes());
+            raw.extend_from_slice(&v.z.to_le_bytes());
+            raw.extend_from_slice(&v.w.to_le_bytes());
+        }
+        let view = self.push_view(&raw, Some(TARGET_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: data.len(), type_: "VEC4",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    fn push_indices(&mut self, data: &[u16]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 2);
+        for i in data { raw.extend_from_slice(&i.to_le_bytes()); }
+        let view = self.push_view(&raw, Some(TARGET_ELEMENT_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_UNSIGNED_SHORT, count: data.len(), type_: "SCALAR",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    /// An animation sampler's `input` accessor: keyframe times, which glTF
+    /// requires `min`/`max` bounds on same as it does `POSITION`. Not a
+    /// vertex/index buffer, so its bufferView carries no `target`.
+    fn push_time_accessor(&mut self, times: &[f32]) -> usize {
+        let mut raw = Vec::with_capacity(times.len() * 4);
+        for t in times { raw.extend_from_slice(&t.to_le_bytes()); }
+        let view = self.push_view(&raw, None);
+        let min = times.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = times.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: times.len(), type_: "SCALAR",
+            normalized: None, min: Some(vec![min]), max: Some(vec![max])
+        });
+        self.accessors.len() - 1
+    }
+
+    /// An animation sampler's `output` accessor for a `rotation` channel -
+    /// like [`push_weights_accessor`](Self::push_weights_accessor), a plain
+    /// VEC4 of floats, but without the `ARRAY_BUFFER` target that only
+    /// applies to an actual vertex attribute.
+    fn push_rotation_accessor(&mut self, data: &[Vec4<f32>]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 16);
+        for v in data {
+            raw.extend_from_slice(&v.x.to_le_bytes());
+            raw.extend_from_slice(&v.y.to_le_bytes());
+            raw.extend_from_slice(&v.z.to_le_bytes());
+            raw.extend_from_slice(&v.w.to_le_bytes());
+        }
+        let view = self.push_view(&raw, None);
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: data.len(), type_: "VEC4",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    /// Builds the shared attribute set for every primitive in one mesh, from
+    /// whichever of [`GeometrySection`]'s vectors actually got populated.
+    fn push_attributes(&mut self, geom: &GeometrySection) -> BTreeMap<String, usize> {
+        let mut attrs = BTreeMap::new();
+
+        attrs.insert("POSITION".to_owned(), self.push_vec3_accessor(&geom.position, true, Some(TARGET_ARRAY_BUFFER)));
+
+        if !geom.normal.is_empty() {
+            attrs.insert("NORMAL".to_owned(), self.push_vec3_accessor(&geom.normal, false, Some(TARGET_ARRAY_BUFFER)));
+        }
+        if !geom.tangent.is_empty() && !geom.binormal.is_empty() && !geom.normal.is_empty() {
+            attrs.insert("TANGENT".to_owned(), self.push_tangent_accessor(&geom.tangent, &geom.binormal, &geom.normal));
+        }
+
+        let texcoords: [&[Vec2f]; 8] = [
+            &geom.tex_coord_0, &geom.tex_coord_1, &geom.tex_coord_2, &geom.tex_coord_3,
+            &geom.tex_coord_4, &geom.tex_coord_5, &geom.tex_coord_6, &geom.tex_coord_7
+        ];
+        for (i, uv) in texcoords.iter().enumerate() {
+            if !uv.is_empty() {
+                attrs.insert(format!("TEXCOORD_{}", i), self.push_vec2_accessor(uv));
+            }
+        }
+
+        if !geom.color_0.is_empty() {
+            attrs.insert("COLOR_0".to_owned(), self.push_color_accessor(&geom.color_0));
+        }
+        if !geom.color_1.is_empty() {
+            attrs.insert("COLOR_1".to_owned(), self.push_color_accessor(&geom.color_1));
+        }
+
+        if !geom.blend_indices_0.is_empty() && !geom.blend_weight_0.is_empty() {
+            attrs.insert("JOINTS_0".to_owned(), self.push_joints_accessor(&geom.blend_indices_0));
+            attrs.insert("WEIGHTS_0".to_owned(), self.push_weights_accessor(&geom.blend_weight_0));
+        }
+        if !geom.blend_indices_1.is_empty() && !geom.blend_weight_1.is_empty() {
+            attrs.insert("JOINTS_1".to_owned(), self.push_joints_accessor(&geom.blend_indices_1));
+            attrs.insert("WEIGHTS_1".to_owned(), self.push_weights_accessor(&geom.blend_weight_1));
+        }
+
+        attrs
+    }
+}
+
+fn vec3_bounds(data: &[Vec3f]) -> (Option<Vec<f32>>, Option<Vec<f32>>) {
+    let mut min = data[0];
+    let mut max = data[0];
+    for v in data {
+        min = Vec3::partial_min(min, *v);
+        max = Vec3::partial_max(max, *v);
+    }
+    (Some(vec![min.x, min.y, min.z]), Some(vec![max.x, max.y, max.z]))
+}
+
+#[derive(Serialize)]
+struct Document {
+    asset: Asset,
+    #[serde(rename = "extensionsUsed", skip_serializing_if = "Vec::is_empty")]
+    extensions_used: Vec<&'static str>,
+    scene: usize,
+    scenes: Vec<Scene>,
+    nodes: Vec<Node>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    meshes: Vec<Mesh>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    materials: Vec<Material>,
+    accessors: Vec<Accessor>,
+    #[serde(rename = "bufferViews")]
+    buffer_views: Vec<BufferView>,
+    buffers: Vec<Buffer>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    animations: Vec<Animation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extensions: Option<DocumentExtensions>
+}
+
+#[derive(Serialize)]
+struct Asset { version: &'static str }
+
+#[derive(Serialize)]
+struct Scene { nodes: Vec<usize> }
+
+#[derive(Serialize)]
+struct Node {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mesh: Option<usize>,
+    translation: [f32; 3],
+    rotation: [f32; 4],
+    scale: [f32; 3],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extensions: Option<NodeExtensions>
+}
+
+#[derive(Serialize)]
+struct NodeExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    khr_lights_punctual: NodeLightRef
+}
+
+#[derive(Serialize)]
+struct NodeLightRef { light: usize }
+
+#[derive(Serialize)]
+struct Mesh { primitives: Vec<Primitive> }
+
+#[derive(Serialize, Clone)]
+struct Primitive {
+    attributes: BTreeMap<String, usize>,
+    indices: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    material: Option<usize>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Material {
+    name: String,
+    pbr_metallic_roughness: PbrMetallicRoughness
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PbrMetallicRoughness {
+    base_color_factor: [f32; 4],
+    metallic_factor: f32,
+    roughness_factor: f32
+}
+impl Default for PbrMetallicRoughness {
+    fn default() -> Self {
+        PbrMetallicRoughness { base_color_factor: [1.0, 1.0, 1.0, 1.0], metallic_factor: 1.0, roughness_factor: 1.0 }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: usize,
+    component_type: u32,
+    count: usize,
+    #[serde(rename = "type")]
+    type_: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normalized: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<Vec<f32>>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    byte_offset: usize,
+    byte_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<u32>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Buffer {
+    uri: String,
+    byte_length: usize
+}
+
+#[derive(Serialize)]
+struct DocumentExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    khr_lights_punctual: KhrLightsPunctual
+}
+
+#[derive(Serialize)]
+struct KhrLightsPunctual { lights: Vec<Light> }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Light {
+    #[serde(rename = "type")]
+    type_: &'static str,
+    color: [f32; 3],
+    intensity: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    range: Option<f32>
+}
+
+#[derive(Serialize)]
+struct Animation {
+    channels: Vec<AnimationChannel>,
+    samplers: Vec<AnimationSampler>
+}
+
+#[derive(Serialize)]
+struct AnimationChannel {
+    sampler: usize,
+    target: AnimationChannelTarget
+}
+
+#[derive(Serialize)]
+struct AnimationChannelTarget {
+    node: usize,
+    path: &'static str
+}
+
+#[derive(Serialize)]
+struct AnimationSampler {
+    input: usize,
+    interpolation: &'static str,
+    output: usize
+}
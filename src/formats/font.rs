@@ -1,5 +1,20 @@
-use fnv::{FnvHashMap as HashMap};
+//! Diesel bitmap font (`.font`) binary format, and conversion to/from the plain-text
+//! BMFont (`.fnt`) format used by AngelCode's `bmfont` tool and most engines' font
+//! pipelines, so a font can be edited with off-the-shelf tools instead of a hex editor.
+//!
+//! A few fields (`unknown_1` through `unknown_7`, and all four bytes of [`Kerning`]) have
+//! no known meaning yet - BMFont's text format has no slot for them, so they're
+//! round-tripped through extra, non-standard `key=value` pairs on the `info`/`kerning`
+//! lines rather than silently dropped.
 
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use pd2tools_macros::ItemReader;
+
+use crate::util::binaryreader::{ItemReader, ReadExt, WriteExt, ReadError};
+
+#[derive(Debug, ItemReader)]
 pub struct DieselFont {
     kernings: Vec<Kerning>,
     texture_width: i32,
@@ -14,20 +29,26 @@ pub struct DieselFont {
     unknown_4: i32,
     unknown_5: i64,
     unknown_7: i32,
+    #[read_as(CharacterMap)]
     characters: BTreeMap<char, Character>,
 }
 
+#[derive(Debug, ItemReader)]
 pub struct Kerning {
+    #[read_as(Char32)]
     char_1: char,
+    #[read_as(Char32)]
     char_2: char,
     unknown_1: u8,
     unknown_2: u8,
-    unknown_3: u8
-    unknown_4: u8
+    unknown_3: u8,
+    unknown_4: u8,
 }
 
+#[derive(Debug, ItemReader)]
 pub struct Character {
     id: i32,
+    #[read_as(Char32)]
     character: char,
     x: i16,
     y: i16,
@@ -38,3 +59,262 @@ pub struct Character {
     y_offset: i16,
 }
 
+/// Reads a Unicode code point stored on the wire as a plain `u32`.
+struct Char32;
+impl ItemReader for Char32 {
+    type Error = ReadError;
+    type Item = char;
+
+    fn read_from_stream<R: ReadExt>(stream: &mut R) -> Result<Self::Item, Self::Error> {
+        let code: u32 = stream.read_item()?;
+        char::from_u32(code).ok_or(ReadError::BadConvert("u32", "char"))
+    }
+
+    fn write_to_stream<W: WriteExt>(stream: &mut W, item: &Self::Item) -> Result<(), Self::Error> {
+        stream.write_item(&(*item as u32))
+    }
+}
+
+/// Reads/writes [`DieselFont::characters`]: a `u32` count followed by that many
+/// [`Character`] records, keyed by their own `character` field on the way in.
+struct CharacterMap;
+impl ItemReader for CharacterMap {
+    type Error = ReadError;
+    type Item = BTreeMap<char, Character>;
+
+    fn read_from_stream<R: ReadExt>(stream: &mut R) -> Result<Self::Item, Self::Error> {
+        let count: u32 = stream.read_item()?;
+        let mut map = BTreeMap::new();
+        for _ in 0..count {
+            let c: Character = stream.read_item()?;
+            map.insert(c.character, c);
+        }
+        Ok(map)
+    }
+
+    fn write_to_stream<W: WriteExt>(stream: &mut W, item: &Self::Item) -> Result<(), Self::Error> {
+        let wire_count: u32 = item.len().try_into()
+            .map_err(|_| ReadError::TooManyItems(item.len(), "DieselFont::characters", "u32"))?;
+        stream.write_item(&wire_count)?;
+        for c in item.values() {
+            stream.write_item(c)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a [`DieselFont`] out of its on-disk binary representation.
+pub fn from_binary(input: &[u8]) -> Result<DieselFont, ReadError> {
+    let mut cursor = std::io::Cursor::new(input);
+    cursor.read_item()
+}
+
+/// The inverse of [`from_binary`].
+pub fn to_binary(font: &DieselFont) -> Result<Vec<u8>, ReadError> {
+    let mut out = Vec::new();
+    out.write_item(font)?;
+    Ok(out)
+}
+
+fn quote_bmfont_str(what: &str) -> String {
+    let mut buffer = String::with_capacity(what.len() + 2);
+    buffer.push('"');
+    for ch in what.chars() {
+        if ch == '"' || ch == '\\' { buffer.push('\\'); }
+        buffer.push(ch);
+    }
+    buffer.push('"');
+    buffer
+}
+
+/// Renders `font` as a plain-text BMFont (`.fnt`) file. `page id=0` always names
+/// `<name>.png`, since the texture itself isn't carried by [`DieselFont`].
+pub fn to_bmfont(font: &DieselFont) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "info face={} size={} unknown1={} unknown2={} unknown3={} unknown4={} unknown5={} unknown7={} infosize={}",
+        quote_bmfont_str(&font.name), font.line_height,
+        font.unknown_1, font.unknown_2, font.unknown_3, font.unknown_4, font.unknown_5, font.unknown_7, font.info_size).unwrap();
+
+    writeln!(out, "common lineHeight={} base={} scaleW={} scaleH={} pages=1 packed=0",
+        font.line_height, font.common_base, font.texture_width, font.texture_height).unwrap();
+
+    writeln!(out, "page id=0 file={}", quote_bmfont_str(&format!("{}.png", font.name))).unwrap();
+
+    writeln!(out, "chars count={}", font.characters.len()).unwrap();
+    for c in font.characters.values() {
+        writeln!(out, "char id={} x={} y={} width={} height={} xoffset={} yoffset={} xadvance={} page=0 chnl=15",
+            c.id, c.x, c.y, c.w, c.h, c.x_offset, c.y_offset, c.x_advance).unwrap();
+    }
+
+    writeln!(out, "kernings count={}", font.kernings.len()).unwrap();
+    for k in &font.kernings {
+        writeln!(out, "kerning first={} second={} amount=0 unknown1={} unknown2={} unknown3={} unknown4={}",
+            k.char_1 as u32, k.char_2 as u32, k.unknown_1, k.unknown_2, k.unknown_3, k.unknown_4).unwrap();
+    }
+
+    out
+}
+
+#[derive(Debug)]
+pub struct FontParseError { message: String }
+impl std::fmt::Display for FontParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+impl std::error::Error for FontParseError { }
+
+fn err(message: impl Into<String>) -> FontParseError {
+    FontParseError { message: message.into() }
+}
+
+/// Splits a BMFont line into its leading tag (`info`, `common`, `char`, ...) and its
+/// `key=value` attributes, where a value may be a `"quoted string"` containing spaces.
+fn parse_attrs(line: &str) -> (&str, BTreeMap<String, String>) {
+    let mut chars = line.char_indices().peekable();
+    let tag_start = 0;
+    let mut tag_end = line.len();
+    while let Some(&(i, c)) = chars.peek() {
+        if c.is_whitespace() { tag_end = i; break; }
+        chars.next();
+    }
+    let tag = &line[tag_start..tag_end];
+
+    let mut attrs = BTreeMap::new();
+    let rest = &line[tag_end..];
+    let bytes: Vec<char> = rest.chars().collect();
+    let mut i = 0;
+    while i < bytes.len() {
+        while i < bytes.len() && bytes[i].is_whitespace() { i += 1; }
+        if i >= bytes.len() { break; }
+        let key_start = i;
+        while i < bytes.len() && bytes[i] != '=' && !bytes[i].is_whitespace() { i += 1; }
+        let key: String = bytes[key_start..i].iter().collect();
+        if i >= bytes.len() || bytes[i] != '=' { break; }
+        i += 1;
+
+        let value = if i < bytes.len() && bytes[i] == '"' {
+            i += 1;
+            let mut v = String::new();
+            while i < bytes.len() && bytes[i] != '"' {
+                if bytes[i] == '\\' && i + 1 < bytes.len() { i += 1; }
+                v.push(bytes[i]);
+                i += 1;
+            }
+            i += 1;
+            v
+        }
+        else {
+            let value_start = i;
+            while i < bytes.len() && !bytes[i].is_whitespace() { i += 1; }
+            bytes[value_start..i].iter().collect()
+        };
+
+        if !key.is_empty() { attrs.insert(key, value); }
+    }
+
+    (tag, attrs)
+}
+
+fn get_attr<'a>(attrs: &'a BTreeMap<String, String>, line_tag: &str, key: &str) -> Result<&'a str, FontParseError> {
+    attrs.get(key).map(String::as_str).ok_or_else(|| err(format!("{} line missing '{}'", line_tag, key)))
+}
+
+fn parse_num<T: std::str::FromStr>(attrs: &BTreeMap<String, String>, line_tag: &str, key: &str) -> Result<T, FontParseError> {
+    let raw = get_attr(attrs, line_tag, key)?;
+    raw.parse().map_err(|_| err(format!("{} line has non-numeric '{}': {:?}", line_tag, key, raw)))
+}
+
+/// Parses the text [`to_bmfont`] produces (or, for the fields it reads, any standard
+/// BMFont `.fnt` file) back into a [`DieselFont`]. Unknown/kerning-amount fields default
+/// to zero when they're absent, so a genuine third-party `.fnt` file imports cleanly.
+pub fn from_bmfont(text: &str) -> Result<DieselFont, FontParseError> {
+    let mut name = String::new();
+    let mut line_height = 0i32;
+    let mut common_base = 0i32;
+    let mut texture_width = 0i32;
+    let mut texture_height = 0i32;
+    let mut info_size = 0i64;
+    let mut unknown_1 = 0i64;
+    let mut unknown_2 = 0i64;
+    let mut unknown_3 = 0i64;
+    let mut unknown_4 = 0i32;
+    let mut unknown_5 = 0i64;
+    let mut unknown_7 = 0i32;
+    let mut characters = BTreeMap::new();
+    let mut kernings = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() { continue; }
+        let (tag, attrs) = parse_attrs(line);
+
+        match tag {
+            "info" => {
+                name = get_attr(&attrs, tag, "face")?.to_owned();
+                if let Some(v) = attrs.get("size") { line_height = v.parse().unwrap_or(line_height); }
+                if let Some(v) = attrs.get("unknown1") { unknown_1 = v.parse().unwrap_or(0); }
+                if let Some(v) = attrs.get("unknown2") { unknown_2 = v.parse().unwrap_or(0); }
+                if let Some(v) = attrs.get("unknown3") { unknown_3 = v.parse().unwrap_or(0); }
+                if let Some(v) = attrs.get("unknown4") { unknown_4 = v.parse().unwrap_or(0); }
+                if let Some(v) = attrs.get("unknown5") { unknown_5 = v.parse().unwrap_or(0); }
+                if let Some(v) = attrs.get("unknown7") { unknown_7 = v.parse().unwrap_or(0); }
+                if let Some(v) = attrs.get("infosize") { info_size = v.parse().unwrap_or(0); }
+            },
+            "common" => {
+                line_height = parse_num(&attrs, tag, "lineHeight")?;
+                common_base = parse_num(&attrs, tag, "base")?;
+                texture_width = parse_num(&attrs, tag, "scaleW")?;
+                texture_height = parse_num(&attrs, tag, "scaleH")?;
+            },
+            "char" => {
+                let id: i32 = parse_num(&attrs, tag, "id")?;
+                let character = char::from_u32(id as u32).ok_or_else(|| err(format!("char id {} is not a valid code point", id)))?;
+                let c = Character {
+                    id,
+                    character,
+                    x: parse_num(&attrs, tag, "x")?,
+                    y: parse_num(&attrs, tag, "y")?,
+                    w: parse_num(&attrs, tag, "width")?,
+                    h: parse_num(&attrs, tag, "height")?,
+                    x_advance: parse_num(&attrs, tag, "xadvance")?,
+                    x_offset: parse_num(&attrs, tag, "xoffset")?,
+                    y_offset: parse_num(&attrs, tag, "yoffset")?,
+                };
+                characters.insert(c.character, c);
+            },
+            "kerning" => {
+                let first: u32 = parse_num(&attrs, tag, "first")?;
+                let second: u32 = parse_num(&attrs, tag, "second")?;
+                kernings.push(Kerning {
+                    char_1: char::from_u32(first).ok_or_else(|| err(format!("kerning first {} is not a valid code point", first)))?,
+                    char_2: char::from_u32(second).ok_or_else(|| err(format!("kerning second {} is not a valid code point", second)))?,
+                    unknown_1: attrs.get("unknown1").and_then(|v| v.parse().ok()).unwrap_or(0),
+                    unknown_2: attrs.get("unknown2").and_then(|v| v.parse().ok()).unwrap_or(0),
+                    unknown_3: attrs.get("unknown3").and_then(|v| v.parse().ok()).unwrap_or(0),
+                    unknown_4: attrs.get("unknown4").and_then(|v| v.parse().ok()).unwrap_or(0),
+                });
+            },
+            // "page", "chars", "kernings" carry nothing we need to keep beyond their counts.
+            _ => {}
+        }
+    }
+
+    Ok(DieselFont {
+        kernings,
+        texture_width,
+        texture_height,
+        name,
+        info_size,
+        common_base,
+        line_height,
+        unknown_1,
+        unknown_2,
+        unknown_3,
+        unknown_4,
+        unknown_5,
+        unknown_7,
+        characters,
+    })
+}
@@ -0,0 +1,8 @@
+pub mod banksinfo;
+pub mod fdm;
+pub mod font;
+pub mod oil;
+pub mod player_save;
+pub mod scriptdata;
+pub mod string_table;
+pub mod xml;
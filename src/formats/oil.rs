@@ -19,13 +19,16 @@
 //! Note that `total_size_of_chunks` is thus the size of the file minus 12, and
 //! `count_of_preceding bytes` is the size of the file minus four.
 
+pub mod export_gltf;
+
 use std::convert::TryInto;
 use std::fmt::Debug;
-use std::{path::Path, io::Write};
-use vek::{Rgb, Vec2, Vec3};
+use std::{path::Path, io::{Write, Read, BufRead}};
+use vek::{Rgb, Vec2, Vec3, Vec4};
 
 use crate::util::{binaryreader, binaryreader::*, AsHex, DbgDisplay, DbgMatrixF64, SimpleDbgTable};
 use pd2tools_macros::{EnumTryFrom, ItemReader, EnumFromData};
+use serde::Serialize;
 
 struct PrintNodeRef(u32);
 impl std::fmt::Debug for PrintNodeRef {
@@ -47,13 +50,13 @@ struct UnparsedSection<'a> {
 
 macro_rules! make_chunks {
     ($($name:ident = $tag:literal),+) => {
-        #[derive(Debug, EnumTryFrom, ItemReader)]
+        #[derive(Debug, Serialize, EnumTryFrom, ItemReader)]
         #[repr(u32)]
         pub enum ChunkId {
             $($name = $tag),+
         }
 
-        #[derive(EnumFromData)]
+        #[derive(Serialize, EnumFromData)]
         pub enum Chunk {
             $($name($name)),+
         }
@@ -79,18 +82,25 @@ macro_rules! make_chunks {
                 }
                 Ok(())
             }
-        }
-        
-        impl<'a> UnparsedSection<'a> {
-            fn try_into_chunk(&self) -> (&'a [u8], Result<Chunk, ReadError>) {
 
-                let mut reader = self.bytes;
-                let res = match self.type_code {
+            /// Reads the chunk for `type_code` from `reader`, the shared
+            /// dispatch `UnparsedSection::try_into_chunk` and [`print_sections`]
+            /// both build on - the former over a byte slice, the latter over
+            /// the length-bounded sub-reader [`walk_sections`] hands it.
+            fn read_tagged<R: ReadExt>(type_code: u32, reader: &mut R) -> Result<Chunk, ReadError> {
+                match type_code {
                     $($tag => {
                         reader.read_item_as::<$name>().map(Chunk::$name)
                     }),+
                     d => Err(ReadError::BadDiscriminant("ChunkId", d as u128))
-                };
+                }
+            }
+        }
+
+        impl<'a> UnparsedSection<'a> {
+            fn try_into_chunk(&self) -> (&'a [u8], Result<Chunk, ReadError>) {
+                let mut reader = self.bytes;
+                let res = Chunk::read_tagged(self.type_code, &mut reader);
                 (reader, res)
             }
         }
@@ -109,25 +119,25 @@ make_chunks! {
     Geometry = 5,
     Light = 10,
     Camera = 19,
-    
-    KeyEvents = 21
-    
-    //PositionController = 1,
-    //RotationController = 2,
-    //LookatController = 6,
-    //ColorController = 7,
-    //AttenuationController = 8,
-    //MultiplierController = 9,
-    //HotspotController = 13,
-    //FalloffController = 14,
-    //FovController = 15,
-    //FarClipController = 16,
-    //NearClipController = 17,
-    //TargetDistanceController = 18,
-    //IkChainController = 22,
-    //IkChainTargetController = 23,
-    //CompositePositionController = 24,
-    //CompositeRotationController = 25
+
+    KeyEvents = 21,
+
+    PositionController = 1,
+    RotationController = 2,
+    LookatController = 6,
+    ColorController = 7,
+    AttenuationController = 8,
+    MultiplierController = 9,
+    HotspotController = 13,
+    FalloffController = 14,
+    FovController = 15,
+    FarClipController = 16,
+    NearClipController = 17,
+    TargetDistanceController = 18,
+    IkChainController = 22,
+    IkChainTargetController = 23,
+    CompositePositionController = 24,
+    CompositeRotationController = 25
 }
 
 struct UnparsedBytes(Vec<u8>);
@@ -144,13 +154,13 @@ impl std::fmt::Debug for UnparsedBytes {
     }
 }
 
-#[derive(Debug, ItemReader)]
+#[derive(Debug, Serialize, ItemReader)]
 pub struct SceneInfo1 {
     pub start_time: f64,
     pub end_time: f64,
 }
 
-#[derive(Debug, ItemReader)]
+#[derive(Debug, Serialize, ItemReader)]
 pub struct SceneInfo2 {
     pub start_time: f64,
     pub end_time: f64,
@@ -159,7 +169,7 @@ pub struct SceneInfo2 {
     pub source_filename: String,
 }
 
-#[derive(Debug, Default, ItemReader)]
+#[derive(Debug, Default, Serialize, ItemReader)]
 pub struct SceneInfo3 {
     pub start_time: f64,
     pub end_time: f64,
@@ -169,7 +179,7 @@ pub struct SceneInfo3 {
     pub scene_type: String,
 }
 
-#[derive(ItemReader)]
+#[derive(Serialize, ItemReader)]
 pub struct Material {
     pub id: u32,
     pub name: String,
@@ -185,12 +195,12 @@ impl std::fmt::Debug for Material {
     }
 }
 
-#[derive(Debug, ItemReader)]
+#[derive(Debug, Serialize, ItemReader)]
 pub struct MaterialsXml {
     pub xml: String
 }
 
-#[derive(ItemReader)]
+#[derive(Serialize, ItemReader)]
 pub struct Node {
     pub id: u32,
     pub name: String,
@@ -217,7 +227,7 @@ impl std::fmt::Debug for Node {
 }
 
 // Can't derive ItemReader, we have to pass the vertex count in to GeometrySkin.
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize)]
 pub struct Geometry {
     pub node_id: u32,
 
@@ -292,7 +302,7 @@ impl ItemReader for Geometry {
 }
 
 // Can't derive ItemReader for this, it depends on passing in the vertex count.
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct GeometrySkin {
     pub root_node_id: u32,
     pub postmul_transform: vek::Mat4<f64>,
@@ -346,7 +356,7 @@ impl std::fmt::Debug for GeometrySkin {
     }
 }
 
-#[derive(Clone, Copy, ItemReader)]
+#[derive(Clone, Copy, Serialize, ItemReader)]
 pub struct SkinBoneEntry {
     pub bone_node_id: u32,
     pub premul_transform: vek::Mat4<f64>
@@ -358,19 +368,19 @@ impl std::fmt::Debug for SkinBoneEntry {
 }
 
 
-#[derive(Default, Debug, Clone, Copy, ItemReader)]
+#[derive(Default, Debug, Clone, Copy, Serialize, ItemReader)]
 pub struct VertexWeight {
     pub bone_id: u32,
     pub weight: f64
 }
 
-#[derive(Debug, Clone, Copy, ItemReader)]
+#[derive(Debug, Clone, Copy, Serialize, ItemReader)]
 pub struct BoundingBox {
     pub min: Vec3<f64>,
     pub max: Vec3<f64>
 }
 
-#[derive(Clone, ItemReader)]
+#[derive(Clone, Serialize, ItemReader)]
 pub enum GeometryChannel {
     #[tag(0)] Position(u32, Vec<Vec3<f64>>),
     #[tag(1)] TexCoord(u32, Vec<Vec2<f64>>),
@@ -394,7 +404,7 @@ impl Debug for GeometryChannel {
     }
 }
 
-#[derive(Clone, ItemReader)]
+#[derive(Clone, Serialize, ItemReader)]
 pub struct GeometryFace {
     pub material_id: u32,
     pub smoothing_group: u32,
@@ -410,7 +420,7 @@ impl Debug for GeometryFace {
     }
 }
 
-#[derive(Debug, Clone, Copy, ItemReader)]
+#[derive(Debug, Clone, Copy, Serialize, ItemReader)]
 pub struct GeometryFaceloop {
     pub channel: u32,
     pub a: u32,
@@ -418,7 +428,7 @@ pub struct GeometryFaceloop {
     pub c: u32
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, EnumTryFrom, ItemReader)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, EnumTryFrom, ItemReader)]
 #[repr(u32)]
 pub enum LightType {
     Spot = 0,
@@ -426,14 +436,14 @@ pub enum LightType {
     Omni = 2
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, EnumTryFrom, ItemReader)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, EnumTryFrom, ItemReader)]
 #[repr(u32)]
 pub enum SpotlightShape {
     Rectangular = 0,
     Circular = 1
 }
 
-#[derive(Debug, ItemReader)]
+#[derive(Debug, Serialize, ItemReader)]
 pub struct Light {
     pub node_id: u32,
     pub lamp_type: LightType,
@@ -452,7 +462,7 @@ pub struct Light {
     pub on: bool
 }
 
-#[derive(Debug, PartialEq, Clone, Copy, ItemReader,)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, ItemReader,)]
 pub struct Camera {
     pub node_id: u32,
     pub fov: f64,
@@ -464,12 +474,12 @@ pub struct Camera {
 }
 
 /// "Beats and triggers" block.
-#[derive(Debug, ItemReader)]
+#[derive(Debug, Serialize, ItemReader)]
 pub struct KeyEvents {
     pub events: Vec<KeyEvent>
 }
 
-#[derive(Debug, ItemReader)]
+#[derive(Debug, Serialize, ItemReader)]
 pub struct KeyEvent {
     pub id: u32,
     pub name: String,
@@ -479,7 +489,124 @@ pub struct KeyEvent {
     pub parameter_count: u32     // Exporter always writes 0
 }
 
+// Animation controllers: each is a node reference followed by a keyframe
+// list (a `u32` count, as usual for `Vec<T>`, then that many `{ time, value }`
+// entries). The value type varies by controller; `ScalarKey`/`Vec3Key`/
+// `ColorKey`/`RotationKey`/`NodeRefKey` cover every shape seen in practice.
+
+#[derive(Debug, Clone, Copy, Serialize, ItemReader)]
+pub struct ScalarKey {
+    pub time: f64,
+    pub value: f64
+}
+
+#[derive(Debug, Clone, Copy, Serialize, ItemReader)]
+pub struct Vec3Key {
+    pub time: f64,
+    pub value: Vec3<f64>
+}
+
+#[derive(Debug, Clone, Copy, Serialize, ItemReader)]
+pub struct ColorKey {
+    pub time: f64,
+    pub value: Rgb<f64>
+}
+
+/// Value of a [`LookatController`] keyframe: the node to look at.
+#[derive(Debug, Clone, Copy, Serialize, ItemReader)]
+pub struct NodeRefKey {
+    pub time: f64,
+    pub value: u32
+}
+
+/// A [`RotationController`] keyframe's value: some exporters write a
+/// quaternion, others an Euler triple.
+#[derive(Clone, Copy, Serialize, ItemReader)]
+pub enum RotationValue {
+    #[tag(0)] Quaternion(Vec4<f64>),
+    #[tag(1)] Euler(Vec3<f64>)
+}
+impl Debug for RotationValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Quaternion(v) => f.debug_tuple("Quaternion").field(v).finish(),
+            Self::Euler(v) => f.debug_tuple("Euler").field(v).finish(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, ItemReader)]
+pub struct RotationKey {
+    pub time: f64,
+    pub value: RotationValue
+}
+
+macro_rules! make_controller {
+    ($name:ident, $key:ty) => {
+        #[derive(Serialize, ItemReader)]
+        pub struct $name {
+            pub node_id: u32,
+            pub keys: Vec<$key>
+        }
+        impl std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("node_id", &PrintNodeRef(self.node_id))
+                    .field("keys", &self.keys)
+                    .finish()
+            }
+        }
+    }
+}
+
+make_controller!(PositionController, Vec3Key);
+make_controller!(RotationController, RotationKey);
+make_controller!(LookatController, NodeRefKey);
+make_controller!(ColorController, ColorKey);
+make_controller!(AttenuationController, Vec3Key);
+make_controller!(MultiplierController, ScalarKey);
+make_controller!(HotspotController, ScalarKey);
+make_controller!(FalloffController, ScalarKey);
+make_controller!(FovController, ScalarKey);
+make_controller!(FarClipController, ScalarKey);
+make_controller!(NearClipController, ScalarKey);
+make_controller!(TargetDistanceController, ScalarKey);
+
+// The shape of these two is a guess: the exporter never emits any sample
+// data that would confirm it, so they decode as a plain keyframed scalar
+// (presumably a chain weight/blend) like the other minor controllers, and
+// aren't wired into the Blender animation converter.
+make_controller!(IkChainController, ScalarKey);
+make_controller!(IkChainTargetController, ScalarKey);
+
+macro_rules! make_composite_controller {
+    ($name:ident) => {
+        #[derive(Serialize, ItemReader)]
+        pub struct $name {
+            pub node_id: u32,
+            pub child_ids: Vec<u32>
+        }
+        impl std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("node_id", &PrintNodeRef(self.node_id))
+                    .field("child_ids", &self.child_ids)
+                    .finish()
+            }
+        }
+    }
+}
+
+/// References the node ids of other [`PositionController`]s to sum, in
+/// order, into this controller's own keyframes.
+make_composite_controller!(CompositePositionController);
+
+/// References the node ids of other [`RotationController`]s to compose, in
+/// order, into this controller's own keyframes.
+make_composite_controller!(CompositeRotationController);
+
 fn split_to_sections<'a>(mut src: &'a [u8]) -> Result<Vec<UnparsedSection<'a>>, ReadError> {
+    let file_len = src.len();
     let mut out = Vec::<UnparsedSection>::new();
 
     let magic: [u8; 4] = src.read_item()?;
@@ -487,12 +614,15 @@ fn split_to_sections<'a>(mut src: &'a [u8]) -> Result<Vec<UnparsedSection<'a>>,
         return Err(ReadError::Schema("No magic number"));
     }
 
-    let _total_size = src.read_item_as::<u32>()?;
+    let total_size: u32 = src.read_item_as::<u32>()?;
+    if total_size as usize != file_len - 12 {
+        return Err(ReadError::Schema("total_size_of_chunks doesn't match the file's actual length"));
+    }
 
     while src.len() > 8 {
         let type_code: u32 = src.read_item()?;
         let length: usize = src.read_item_as::<u32>()?.try_into().unwrap();
-        if length > src.len() { 
+        if length > src.len() {
             return Err(ReadError::ItemTooLong(length as usize))
         }
         let (chunk_body, remaining) = src.split_at(length);
@@ -504,30 +634,200 @@ fn split_to_sections<'a>(mut src: &'a [u8]) -> Result<Vec<UnparsedSection<'a>>,
         src = remaining;
     }
 
+    if src.len() != 4 {
+        return Err(ReadError::Schema("Trailing data between the last section and count_of_preceding_bytes"));
+    }
+    let count_of_preceding_bytes: u32 = src.read_item()?;
+    if count_of_preceding_bytes as usize != file_len - 4 {
+        return Err(ReadError::Schema("count_of_preceding_bytes doesn't match the file's actual length"));
+    }
+
     Ok(out)
 }
 
+/// A non-fatal anomaly found while parsing: the file is well-formed enough
+/// to read, but some data in it isn't accounted for by the current chunk
+/// readers.
+#[derive(Debug)]
+pub enum SectionWarning {
+    /// The chunk reader for `type_code` didn't consume all of the bytes the
+    /// section header declared for it.
+    TrailingBytes { offset: usize, type_code: u32, bytes: Vec<u8> }
+}
+
+/// Parses a whole OIL file into an owned list of chunks, for callers (e.g.
+/// [`chunks_to_json`]/[`chunks_to_yaml`]) that want the data rather than a
+/// printed dump.
+pub fn parse_chunks(bytes: &[u8]) -> Result<Vec<Chunk>, ReadError> {
+    split_to_sections(bytes)?.iter()
+        .map(|sec| sec.try_into_chunk().1)
+        .collect()
+}
+
+/// Like [`parse_chunks`], but also collects a [`SectionWarning`] for every
+/// section whose chunk reader left bytes unconsumed.
+fn parse_chunks_with_warnings(bytes: &[u8]) -> Result<(Vec<Chunk>, Vec<SectionWarning>), ReadError> {
+    let sections = split_to_sections(bytes)?;
+    let mut chunks = Vec::with_capacity(sections.len());
+    let mut warnings = Vec::new();
+
+    let mut offset = 8;
+    for sec in &sections {
+        let (remain, res) = sec.try_into_chunk();
+        if !remain.is_empty() {
+            warnings.push(SectionWarning::TrailingBytes {
+                offset, type_code: sec.type_code, bytes: remain.to_vec()
+            });
+        }
+        chunks.push(res?);
+        offset += 8 + sec.length;
+    }
+
+    Ok((chunks, warnings))
+}
+
+/// Walks a FORM container section-by-section over any `Read + BufRead + Seek`
+/// source, calling `f` with each section's starting offset, type code, and a
+/// sub-reader bounded to that section's declared length. Whatever `f` leaves
+/// unread is skipped by seeking rather than buffered, so unknown chunk types
+/// and huge geometry sections never need to be held in memory; a
+/// beyond-file-end `length` is rejected before `f` is called, so it can't
+/// desynchronise every section that follows.
+///
+/// `f` itself isn't expected to fail the whole walk over a single bad
+/// chunk - that's handled the same way [`print_sections`] always has, by
+/// reporting the error for that section and continuing.
+fn walk_sections<R, F>(mut stream: R, mut f: F) -> Result<(), ReadError>
+where
+    R: Read + BufRead + std::io::Seek,
+    F: FnMut(u64, u32, &mut std::io::Take<&mut R>) -> Result<(), ReadError>
+{
+    use std::io::{Seek, SeekFrom};
+
+    let file_len = stream.seek(SeekFrom::End(0))?;
+    stream.seek(SeekFrom::Start(0))?;
+
+    let magic: [u8; 4] = stream.read_item()?;
+    if magic != *b"FORM" {
+        return Err(ReadError::Schema("No magic number"));
+    }
+
+    let total_size: u32 = stream.read_item_as::<u32>()?;
+    if total_size as u64 != file_len - 12 {
+        return Err(ReadError::Schema("total_size_of_chunks doesn't match the file's actual length"));
+    }
+
+    let end_of_nodes = file_len - 4;
+    while stream.stream_position()? < end_of_nodes {
+        let offset = stream.stream_position()?;
+        let type_code: u32 = stream.read_item()?;
+        let length: u64 = stream.read_item_as::<u32>()?.into();
+        if offset + 8 + length > end_of_nodes {
+            return Err(ReadError::ItemTooLong(length as usize));
+        }
+
+        let mut body = (&mut stream).take(length);
+        f(offset, type_code, &mut body)?;
+
+        let unread = body.limit();
+        if unread > 0 {
+            stream.seek(SeekFrom::Current(unread as i64))?;
+        }
+    }
+
+    let count_of_preceding_bytes: u32 = stream.read_item()?;
+    if count_of_preceding_bytes as u64 != file_len - 4 {
+        return Err(ReadError::Schema("count_of_preceding_bytes doesn't match the file's actual length"));
+    }
+
+    Ok(())
+}
+
 pub fn print_sections(filename: &Path) {
+    let file = match std::fs::File::open(filename) {
+        Err(e) => { println!("Error opening {:?}: {}", filename, e); return }
+        Ok(f) => f
+    };
+    let stream = std::io::BufReader::new(file);
+
+    let result = walk_sections(stream, |offset, type_code, body| {
+        let declared_length = body.limit();
+        print!("{:6} {:6} ", offset, declared_length);
+        match Chunk::read_tagged(type_code, body) {
+            Ok(chunk) => {
+                println!("{:#?}", chunk);
+                let trailing = body.limit();
+                if trailing > 0 {
+                    println!("       WARNING: {} trailing byte(s) in section", trailing);
+                }
+            },
+            Err(e) => println!("{:4} {:?} {:}", type_code, e, declared_length - body.limit())
+        }
+        Ok(())
+    });
+
+    if let Err(e) = result {
+        println!("Error reading {:?}: {:?}", filename, e);
+    }
+}
+
+/// Report produced by [`verify`]: whether re-encoding the parsed chunks via
+/// [`chunks_to_bytes`] reproduces the original file byte-for-byte, catching
+/// silent reader/writer asymmetries (e.g. the skin/bbox flag handling in
+/// [`Geometry`]) that per-field validation can't.
+#[derive(Debug)]
+pub struct RoundTripReport {
+    pub original_len: usize,
+    pub reencoded_len: usize,
+    /// Offset of the first byte that differs, if any.
+    pub first_difference: Option<usize>,
+}
+impl RoundTripReport {
+    pub fn is_identical(&self) -> bool {
+        self.first_difference.is_none()
+    }
+}
+
+/// Parses `bytes`, then re-emits the parsed chunks through
+/// [`chunks_to_bytes`] and compares the result against `bytes`.
+pub fn verify_round_trip(bytes: &[u8]) -> Result<(RoundTripReport, Vec<SectionWarning>), ReadError> {
+    let (chunks, warnings) = parse_chunks_with_warnings(bytes)?;
+    let reencoded = chunks_to_bytes(&chunks)?;
+
+    let first_difference = bytes.iter().zip(reencoded.iter()).position(|(a, b)| a != b)
+        .or_else(|| if bytes.len() != reencoded.len() { Some(bytes.len().min(reencoded.len())) } else { None });
+
+    Ok((RoundTripReport { original_len: bytes.len(), reencoded_len: reencoded.len(), first_difference }, warnings))
+}
+
+/// Parses `filename`, checks its header fields and prints any
+/// [`SectionWarning`]s, then reports whether it round-trips byte-identically
+/// through [`chunks_to_bytes`].
+pub fn verify(filename: &Path) {
     let bytes = match std::fs::read(filename) {
-        Err(e) => { println!("Error reading {:?}: {}", filename, e); return} 
+        Err(e) => { println!("Error reading {:?}: {}", filename, e); return}
         Ok(v) => v
     };
-    
-    let data = match split_to_sections(&bytes) {
-        Err(e) => { println!("Error reading {:?}: {:?}", filename, e); return},
+
+    let (report, warnings) = match verify_round_trip(&bytes) {
+        Err(e) => { println!("Error reading {:?}: {:?}", filename, e); return },
         Ok(v) => v
     };
 
-    let mut offset = 8;
-    for sec in data {
-        print!("{:6} {:6} ", offset, sec.length);
-        offset += sec.length;
-        let (remain, res) = sec.try_into_chunk();
-        match res {
-            Ok(chunk) => println!("{:#?} {:}", chunk, AsHex(remain)),
-            Err(e) => println!("{:4} {:?} {:}", sec.type_code, e, sec.length - remain.len())
+    for w in &warnings {
+        match w {
+            SectionWarning::TrailingBytes { offset, type_code, bytes } =>
+                println!("WARNING: {:6} type {:4}: {} trailing byte(s): {}", offset, type_code, bytes.len(), AsHex(bytes))
         }
     }
+
+    if report.is_identical() {
+        println!("Round trip OK: {} bytes", report.original_len);
+    }
+    else {
+        println!("Round trip MISMATCH: original {} bytes, re-encoded {} bytes, first difference at offset {:?}",
+            report.original_len, report.reencoded_len, report.first_difference);
+    }
 }
 
 pub fn chunks_to_bytes(chunks: &[Chunk]) -> std::io::Result<Vec<u8>> {
@@ -551,4 +851,16 @@ pub fn chunks_to_bytes(chunks: &[Chunk]) -> std::io::Result<Vec<u8>> {
     buf.write_item(&len)?;
     (&mut buf[4..8]).write_item(&(len-8))?;
     Ok(buf)
+}
+
+/// Dumps `chunks` as pretty-printed JSON, preserving the struct-of-arrays
+/// layout (channels, faces, weights, ...) used throughout this module so the
+/// output round-trips and is easy to diff against another exporter's output.
+pub fn chunks_to_json(chunks: &[Chunk]) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(chunks)
+}
+
+/// Dumps `chunks` as YAML; see [`chunks_to_json`].
+pub fn chunks_to_yaml(chunks: &[Chunk]) -> Result<String, serde_yaml::Error> {
+    serde_yaml::to_string(chunks)
 }
\ No newline at end of file
@@ -0,0 +1,707 @@
+//! Standalone glTF 2.0 export for a parsed OIL chunk list - so models
+//! exported from 3ds Max/Maya toward Diesel can also be opened directly in
+//! Blender/three.js, the same motivation as [`super::super::fdm::export_gltf`]
+//! for the release format. Built the same way: no external `gltf` crate,
+//! just hand-rolled `serde_json` structs written out alongside a binary
+//! buffer.
+//!
+//! Every [`Node`](super::Node) chunk becomes one glTF node; OIL's `parent_id`
+//! links (like the release format's section references) are resolved into
+//! a child list in a second pass. A [`Geometry`](super::Geometry) chunk's
+//! `channels`/`faces` use their own per-attribute index spaces rather than
+//! one shared vertex index (closer to an OBJ's separate `v`/`vt`/`vn`
+//! indices than to a glTF primitive), so building one is mostly about
+//! welding each triangle corner's distinct per-channel indices back down
+//! to the single shared vertex index glTF requires. Only the first
+//! `Tangent`/`Binormal` pair and the first `Colour` channel are exported;
+//! `Alpha` channels and any additional `Colour` channel have no glTF
+//! attribute to land on and are dropped. Skinning isn't exported either -
+//! there's no glTF skin/joint-hierarchy counterpart built yet, so a
+//! skinned [`Geometry`] still exports as a static mesh.
+//!
+//! [`Material`](super::Material) chunks carry nothing glTF understands
+//! (just a name and a parent, the actual parameters living in the
+//! sibling [`MaterialsXml`](super::MaterialsXml) chunk this doesn't
+//! parse), so they export as glTF's default PBR material with only the
+//! name carried across. [`Camera`](super::Camera) chunks map onto glTF
+//! perspective cameras directly - `fov` is already the same "full angle,
+//! radians" quantity [`ir_writer_oil::camera_to_oil`](../../../blender/fdm_python/src/ir_writer_oil.rs)
+//! writes straight from Blender's `camera.angle` with no conversion, so
+//! it's passed straight through as `yfov` here too.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use vek::{Mat3, Mat4, Quaternion, Vec3};
+use serde::Serialize;
+
+use super::{Camera, Chunk, Geometry, GeometryChannel, Material, Node};
+
+#[derive(Debug)]
+pub enum ExportGltfError {
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+variant_from!(ExportGltfError::Io, io::Error);
+variant_from!(ExportGltfError::Json, serde_json::Error);
+
+const COMPONENT_UNSIGNED_SHORT: u32 = 5123;
+const COMPONENT_FLOAT: u32 = 5126;
+
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+const NO_PARENT: u32 = 0xFFFFFFFF;
+const NO_MATERIAL: u32 = 0xFFFFFFFF;
+
+/// Parses `chunks` into a glTF document and writes it to `gltf_path`,
+/// alongside a sibling `.bin` holding every accessor's data.
+pub fn write_to_files(chunks: &[Chunk], gltf_path: &Path) -> Result<(), ExportGltfError> {
+    let bin_path = gltf_path.with_extension("bin");
+    let bin_name = bin_path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "scene.bin".to_owned());
+
+    let mut exporter = Exporter::new();
+    exporter.run(chunks);
+    let (document, bin_bytes) = exporter.finish(bin_name);
+
+    fs::write(&bin_path, &bin_bytes)?;
+    fs::write(gltf_path, serde_json::to_vec_pretty(&document)?)?;
+    Ok(())
+}
+
+struct Exporter {
+    buffer: BufferBuilder,
+    nodes: Vec<GltfNode>,
+    meshes: Vec<Mesh>,
+    materials: Vec<GltfMaterial>,
+    material_index: HashMap<u32, usize>,
+    cameras: Vec<GltfCamera>,
+    node_id_to_index: HashMap<u32, usize>,
+    parent_requests: Vec<(usize, u32)>,
+    root_nodes: Vec<usize>
+}
+
+impl Exporter {
+    fn new() -> Self {
+        Exporter {
+            buffer: BufferBuilder::new(),
+            nodes: Vec::new(),
+            meshes: Vec::new(),
+            materials: Vec::new(),
+            material_index: HashMap::new(),
+            cameras: Vec::new(),
+            node_id_to_index: HashMap::new(),
+            parent_requests: Vec::new(),
+            root_nodes: Vec::new()
+        }
+    }
+
+    fn run(&mut self, chunks: &[Chunk]) {
+        let materials_by_id: HashMap<u32, &Material> = chunks.iter()
+            .filter_map(|c| match c { Chunk::Material(m) => Some((m.id, m)), _ => None })
+            .collect();
+
+        for chunk in chunks {
+            if let Chunk::Node(node) = chunk {
+                self.add_node(node);
+            }
+        }
+        for chunk in chunks {
+            match chunk {
+                Chunk::Geometry(geom) => self.add_geometry(geom, &materials_by_id),
+                Chunk::Camera(cam) => self.add_camera(cam),
+                _ => {}
+            }
+        }
+        self.connect_parents();
+    }
+
+    fn add_node(&mut self, node: &Node) {
+        let (translation, rotation, scale) = decompose_trs(mat4_to_f32(&node.transform));
+
+        let idx = self.nodes.len();
+        self.nodes.push(GltfNode {
+            name: Some(node.name.clone()),
+            children: Vec::new(),
+            mesh: None,
+            camera: None,
+            translation,
+            rotation,
+            scale
+        });
+        self.node_id_to_index.insert(node.id, idx);
+
+        if node.parent_id != NO_PARENT {
+            self.parent_requests.push((idx, node.parent_id));
+        }
+    }
+
+    fn add_geometry(&mut self, geom: &Geometry, materials_by_id: &HashMap<u32, &Material>) {
+        let Some(&node_idx) = self.node_id_to_index.get(&geom.node_id) else { return };
+
+        let raw_mesh = build_mesh(&mut self.buffer, geom);
+        if raw_mesh.primitives.is_empty() { return; }
+
+        let primitives = raw_mesh.primitives.into_iter().map(|p| Primitive {
+            attributes: p.attributes,
+            indices: p.indices,
+            material: p.material.and_then(|mat_id| self.material_for(mat_id, materials_by_id))
+        }).collect();
+
+        let mesh_idx = self.meshes.len();
+        self.meshes.push(Mesh { primitives });
+        self.nodes[node_idx].mesh = Some(mesh_idx);
+    }
+
+    /// Resolves (and, on first use, registers) the glTF material for
+    /// `mat_id`, carrying only [`Material`]'s name across since the format
+    /// otherwise has nothing glTF's PBR model understands.
+    fn material_for(&mut self, mat_id: u32, materials_by_id: &HashMap<u32, &Material>) -> Option<usize> {
+        if let Some(&idx) = self.material_index.get(&mat_id) {
+            return Some(idx);
+        }
+        let name = materials_by_id.get(&mat_id).map(|m| m.name.clone()).unwrap_or_else(|| format!("material_{}", mat_id));
+        let idx = self.materials.len();
+        self.materials.push(GltfMaterial { name, pbr_metallic_roughness: PbrMetallicRoughness::default() });
+        self.material_index.insert(mat_id, idx);
+        Some(idx)
+    }
+
+    fn add_camera(&mut self, cam: &Camera) {
+        let Some(&node_idx) = self.node_id_to_index.get(&cam.node_id) else { return };
+
+        let camera_idx = self.cameras.len();
+        self.cameras.push(GltfCamera {
+            type_: "perspective",
+            perspective: GltfPerspective {
+                yfov: cam.fov as f32,
+                aspect_ratio: if cam.aspect_ratio > 0.0 { Some(cam.aspect_ratio as f32) } else { None },
+                znear: cam.near_clip as f32,
+                zfar: if cam.far_clip > 0.0 { Some(cam.far_clip as f32) } else { None }
+            }
+        });
+        self.nodes[node_idx].camera = Some(camera_idx);
+    }
+
+    fn connect_parents(&mut self) {
+        let mut has_parent = HashSet::new();
+        for (child_idx, parent_node_id) in std::mem::take(&mut self.parent_requests) {
+            if let Some(&parent_idx) = self.node_id_to_index.get(&parent_node_id) {
+                self.nodes[parent_idx].children.push(child_idx);
+                has_parent.insert(child_idx);
+            }
+        }
+        self.root_nodes = (0..self.nodes.len()).filter(|i| !has_parent.contains(i)).collect();
+    }
+
+    fn finish(self, bin_name: String) -> (Document, Vec<u8>) {
+        let buffer_bytes = self.buffer.bytes;
+        let document = Document {
+            asset: Asset { version: "2.0" },
+            scene: 0,
+            scenes: vec![Scene { nodes: self.root_nodes }],
+            nodes: self.nodes,
+            meshes: self.meshes,
+            materials: self.materials,
+            cameras: self.cameras,
+            accessors: self.buffer.accessors,
+            buffer_views: self.buffer.buffer_views,
+            buffers: vec![Buffer { uri: bin_name, byte_length: buffer_bytes.len() }]
+        };
+        (document, buffer_bytes)
+    }
+}
+
+/// A triangle corner's identity across every channel it draws from -
+/// `(channel id, index within that channel)` pairs, sorted so two corners
+/// that reference the same set of per-channel vertices weld to one glTF
+/// vertex regardless of the order their [`super::GeometryFaceloop`]s were
+/// written in.
+type CornerKey = BTreeMap<u32, u32>;
+
+fn channel_id(c: &GeometryChannel) -> u32 {
+    match c {
+        GeometryChannel::Position(id, _) => *id,
+        GeometryChannel::TexCoord(id, _) => *id,
+        GeometryChannel::Normal(id, _) => *id,
+        GeometryChannel::Binormal(id, _) => *id,
+        GeometryChannel::Tangent(id, _) => *id,
+        GeometryChannel::Colour(id, _) => *id,
+        GeometryChannel::Alpha(id, _) => *id,
+    }
+}
+
+/// A mesh's primitives before material ids have been resolved into glTF
+/// material indices - [`build_mesh`] only sees one [`Geometry`] at a time,
+/// so it can't maintain the document-wide material list itself.
+struct RawMesh {
+    primitives: Vec<RawPrimitive>
+}
+struct RawPrimitive {
+    attributes: BTreeMap<String, usize>,
+    indices: usize,
+    material: Option<u32>
+}
+
+/// Builds one glTF mesh's worth of primitives out of `geom`'s
+/// channels/faces, welding each triangle corner's distinct per-channel
+/// indices down to a single shared vertex the way
+/// [`super::super::fdm::export_gltf`]'s meshes already come pre-welded from
+/// the release format.
+fn build_mesh(buffer: &mut BufferBuilder, geom: &Geometry) -> RawMesh {
+    let channels_by_id: HashMap<u32, &GeometryChannel> = geom.channels.iter()
+        .map(|c| (channel_id(c), c))
+        .collect();
+
+    let position_id = geom.channels.iter().find_map(|c| match c {
+        GeometryChannel::Position(id, _) => Some(*id), _ => None
+    });
+
+    let normal_id = geom.channels.iter().find_map(|c| match c {
+        GeometryChannel::Normal(id, _) => Some(*id), _ => None
+    });
+    let tangent_id = geom.channels.iter().find_map(|c| match c {
+        GeometryChannel::Tangent(id, _) => Some(*id), _ => None
+    });
+    let binormal_id = geom.channels.iter().find_map(|c| match c {
+        GeometryChannel::Binormal(id, _) => Some(*id), _ => None
+    });
+    let colour_id = geom.channels.iter().find_map(|c| match c {
+        GeometryChannel::Colour(id, _) => Some(*id), _ => None
+    });
+    let texcoord_ids: Vec<u32> = geom.channels.iter().filter_map(|c| match c {
+        GeometryChannel::TexCoord(id, _) => Some(*id), _ => None
+    }).collect();
+
+    let mut vertex_keys: Vec<CornerKey> = Vec::new();
+    let mut vertex_lookup: HashMap<CornerKey, u32> = HashMap::new();
+
+    // (material_id, indices)
+    let mut primitives_by_material: BTreeMap<u32, Vec<u16>> = BTreeMap::new();
+
+    for face in &geom.faces {
+        if face.loops.is_empty() { continue; }
+
+        let mut corners: [CornerKey; 3] = [CornerKey::new(), CornerKey::new(), CornerKey::new()];
+        for l in &face.loops {
+            corners[0].insert(l.channel, l.a);
+            corners[1].insert(l.channel, l.b);
+            corners[2].insert(l.channel, l.c);
+        }
+
+        let mut tri_indices = [0u16; 3];
+        for (i, corner) in corners.into_iter().enumerate() {
+            let idx = *vertex_lookup.entry(corner.clone()).or_insert_with(|| {
+                let idx = vertex_keys.len() as u32;
+                vertex_keys.push(corner);
+                idx
+            });
+            tri_indices[i] = idx as u16;
+        }
+
+        primitives_by_material.entry(face.material_id).or_default().extend_from_slice(&tri_indices);
+    }
+
+    let mut positions = Vec::with_capacity(vertex_keys.len());
+    let mut normals = Vec::with_capacity(vertex_keys.len());
+    let mut tangents = Vec::with_capacity(vertex_keys.len());
+    let mut binormals = Vec::with_capacity(vertex_keys.len());
+    let mut colours = Vec::with_capacity(vertex_keys.len());
+    let mut texcoords: Vec<Vec<[f32; 2]>> = vec![Vec::with_capacity(vertex_keys.len()); texcoord_ids.len()];
+
+    for key in &vertex_keys {
+        positions.push(position_id
+            .and_then(|id| key.get(&id).and_then(|&idx| vec3_at(channels_by_id[&id], idx)))
+            .unwrap_or([0.0, 0.0, 0.0]));
+
+        if let Some(id) = normal_id {
+            normals.push(key.get(&id).and_then(|&idx| vec3_at(channels_by_id[&id], idx)).unwrap_or([0.0, 0.0, 1.0]));
+        }
+        if let Some(id) = tangent_id {
+            tangents.push(key.get(&id).and_then(|&idx| vec3_at(channels_by_id[&id], idx)).unwrap_or([1.0, 0.0, 0.0]));
+        }
+        if let Some(id) = binormal_id {
+            binormals.push(key.get(&id).and_then(|&idx| vec3_at(channels_by_id[&id], idx)).unwrap_or([0.0, 1.0, 0.0]));
+        }
+        if let Some(id) = colour_id {
+            colours.push(key.get(&id).and_then(|&idx| rgb_at(channels_by_id[&id], idx)).unwrap_or([1.0, 1.0, 1.0, 1.0]));
+        }
+        for (i, &id) in texcoord_ids.iter().enumerate() {
+            texcoords[i].push(key.get(&id).and_then(|&idx| vec2_at(channels_by_id[&id], idx)).unwrap_or([0.0, 0.0]));
+        }
+    }
+
+    let mut attributes = BTreeMap::new();
+    attributes.insert("POSITION".to_owned(), buffer.push_vec3_accessor(&positions, true, Some(TARGET_ARRAY_BUFFER)));
+    if normal_id.is_some() {
+        attributes.insert("NORMAL".to_owned(), buffer.push_vec3_accessor(&normals, false, Some(TARGET_ARRAY_BUFFER)));
+    }
+    if tangent_id.is_some() && binormal_id.is_some() && normal_id.is_some() {
+        attributes.insert("TANGENT".to_owned(), buffer.push_tangent_accessor(&tangents, &binormals, &normals));
+    }
+    if colour_id.is_some() {
+        attributes.insert("COLOR_0".to_owned(), buffer.push_color_accessor(&colours));
+    }
+    for (i, uv) in texcoords.iter().enumerate() {
+        attributes.insert(format!("TEXCOORD_{}", i), buffer.push_vec2_accessor(uv));
+    }
+
+    let mut primitives = Vec::with_capacity(primitives_by_material.len());
+    for (material_id, indices) in primitives_by_material {
+        primitives.push(RawPrimitive {
+            attributes: attributes.clone(),
+            indices: buffer.push_indices(&indices),
+            material: if material_id != NO_MATERIAL { Some(material_id) } else { None }
+        });
+    }
+
+    RawMesh { primitives }
+}
+
+fn vec3_at(c: &GeometryChannel, idx: u32) -> Option<[f32; 3]> {
+    let v = match c {
+        GeometryChannel::Position(_, data) | GeometryChannel::Normal(_, data)
+        | GeometryChannel::Binormal(_, data) | GeometryChannel::Tangent(_, data) => data.get(idx as usize)?,
+        _ => return None
+    };
+    Some([v.x as f32, v.y as f32, v.z as f32])
+}
+
+fn vec2_at(c: &GeometryChannel, idx: u32) -> Option<[f32; 2]> {
+    match c {
+        GeometryChannel::TexCoord(_, data) => {
+            let v = data.get(idx as usize)?;
+            Some([v.x as f32, v.y as f32])
+        },
+        _ => None
+    }
+}
+
+fn rgb_at(c: &GeometryChannel, idx: u32) -> Option<[f32; 4]> {
+    match c {
+        GeometryChannel::Colour(_, data) => {
+            let v = data.get(idx as usize)?;
+            Some([v.r as f32, v.g as f32, v.b as f32, 1.0])
+        },
+        _ => None
+    }
+}
+
+/// Splits a transform matrix into translation/rotation(quaternion xyzw)/scale,
+/// the same decomposition Blender's own importer expects a node's TRS to be -
+/// identical to [`super::super::fdm::export_gltf`]'s helper of the same name,
+/// just operating on an OIL node's already-`f32` matrix.
+fn decompose_trs(mat: Mat4<f32>) -> ([f32; 3], [f32; 4], [f32; 3]) {
+    let translation = mat.cols[3].xyz();
+
+    let mut rotation_scale: Mat3<f32> = mat.into();
+    let (col0, sx) = rotation_scale.cols[0].normalized_and_get_magnitude();
+    let (col1, sy) = rotation_scale.cols[1].normalized_and_get_magnitude();
+    let (col2, sz) = rotation_scale.cols[2].normalized_and_get_magnitude();
+    rotation_scale.cols[0] = col0;
+    rotation_scale.cols[1] = col1;
+    rotation_scale.cols[2] = col2;
+    let mut scale = Vec3::new(sx, sy, sz);
+
+    if rotation_scale.determinant() < 0.0 {
+        rotation_scale.cols[0] = -rotation_scale.cols[0];
+        scale.x = -scale.x;
+    }
+
+    let rotation = quaternion_from_mat3(rotation_scale);
+
+    (translation.into_array(), [rotation.x, rotation.y, rotation.z, rotation.w], scale.into_array())
+}
+
+/// Standard "pick the largest diagonal term" rotation-matrix-to-quaternion
+/// conversion - vek doesn't provide `Quaternion: From<Mat3<T>>` itself (it's
+/// present in the source but commented out pending a blocking fix upstream).
+fn quaternion_from_mat3(m: Mat3<f32>) -> Quaternion<f32> {
+    let trace = m.trace();
+
+    if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        Quaternion {
+            w: 0.25 * s,
+            x: (m.cols[1][2] - m.cols[2][1]) / s,
+            y: (m.cols[2][0] - m.cols[0][2]) / s,
+            z: (m.cols[0][1] - m.cols[1][0]) / s
+        }
+    }
+    else if m.cols[0][0] > m.cols[1][1] && m.cols[0][0] > m.cols[2][2] {
+        let s = (1.0 + m.cols[0][0] - m.cols[1][1] - m.cols[2][2]).sqrt() * 2.0;
+        Quaternion {
+            w: (m.cols[1][2] - m.cols[2][1]) / s,
+            x: 0.25 * s,
+            y: (m.cols[1][0] + m.cols[0][1]) / s,
+            z: (m.cols[2][0] + m.cols[0][2]) / s
+        }
+    }
+    else if m.cols[1][1] > m.cols[2][2] {
+        let s = (1.0 + m.cols[1][1] - m.cols[0][0] - m.cols[2][2]).sqrt() * 2.0;
+        Quaternion {
+            w: (m.cols[2][0] - m.cols[0][2]) / s,
+            x: (m.cols[1][0] + m.cols[0][1]) / s,
+            y: 0.25 * s,
+            z: (m.cols[2][1] + m.cols[1][2]) / s
+        }
+    }
+    else {
+        let s = (1.0 + m.cols[2][2] - m.cols[0][0] - m.cols[1][1]).sqrt() * 2.0;
+        Quaternion {
+            w: (m.cols[0][1] - m.cols[1][0]) / s,
+            x: (m.cols[2][0] + m.cols[0][2]) / s,
+            y: (m.cols[2][1] + m.cols[1][2]) / s,
+            z: 0.25 * s
+        }
+    }
+}
+
+fn mat4_to_f32(m: &Mat4<f64>) -> Mat4<f32> {
+    m.map_cols(|c| c.map(|v| v as f32))
+}
+
+/// Accumulates every accessor's raw bytes into one flat buffer, padding each
+/// new bufferView onto a 4-byte boundary as glTF requires - identical to
+/// [`super::super::fdm::export_gltf`]'s `BufferBuilder`.
+struct BufferBuilder {
+    bytes: Vec<u8>,
+    buffer_views: Vec<BufferView>,
+    accessors: Vec<Accessor>
+}
+
+impl BufferBuilder {
+    fn new() -> Self {
+        BufferBuilder { bytes: Vec::new(), buffer_views: Vec::new(), accessors: Vec::new() }
+    }
+
+    fn push_view(&mut self, data: &[u8], target: Option<u32>) -> usize {
+        while self.bytes.len() % 4 != 0 { self.bytes.push(0); }
+        let byte_offset = self.bytes.len();
+        self.bytes.extend_from_slice(data);
+        self.buffer_views.push(BufferView { byte_offset, byte_length: data.len(), target });
+        self.buffer_views.len() - 1
+    }
+
+    fn push_vec3_accessor(&mut self, data: &[[f32; 3]], bounded: bool, target: Option<u32>) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 12);
+        for v in data {
+            raw.extend_from_slice(&v[0].to_le_bytes());
+            raw.extend_from_slice(&v[1].to_le_bytes());
+            raw.extend_from_slice(&v[2].to_le_bytes());
+        }
+        let view = self.push_view(&raw, target);
+        let (min, max) = if bounded { vec3_bounds(data) } else { (None, None) };
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: data.len(), type_: "VEC3",
+            normalized: None, min, max
+        });
+        self.accessors.len() - 1
+    }
+
+    fn push_vec2_accessor(&mut self, data: &[[f32; 2]]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 8);
+        for v in data {
+            raw.extend_from_slice(&v[0].to_le_bytes());
+            raw.extend_from_slice(&v[1].to_le_bytes());
+        }
+        let view = self.push_view(&raw, Some(TARGET_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: data.len(), type_: "VEC2",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    /// Tangent accessor: glTF wants `vec4(tangent.xyz, handedness)`, not the
+    /// separate tangent/binormal vectors OIL stores - handedness is derived
+    /// the same way [`super::super::fdm::export_gltf`]'s does:
+    /// `sign(dot(cross(normal, tangent), binormal))`.
+    fn push_tangent_accessor(&mut self, tangent: &[[f32; 3]], binormal: &[[f32; 3]], normal: &[[f32; 3]]) -> usize {
+        let mut raw = Vec::with_capacity(tangent.len() * 16);
+        for i in 0..tangent.len() {
+            let (t, b, n) = (tangent[i], binormal[i], normal[i]);
+            let cross = [
+                n[1] * t[2] - n[2] * t[1],
+                n[2] * t[0] - n[0] * t[2],
+                n[0] * t[1] - n[1] * t[0]
+            ];
+            let dot = cross[0] * b[0] + cross[1] * b[1] + cross[2] * b[2];
+            let w = if dot < 0.0 { -1.0f32 } else { 1.0f32 };
+            raw.extend_from_slice(&tangent[i][0].to_le_bytes());
+            raw.extend_from_slice(&tangent[i][1].to_le_bytes());
+            raw.extend_from_slice(&tangent[i][2].to_le_bytes());
+            raw.extend_from_slice(&w.to_le_bytes());
+        }
+        let view = self.push_view(&raw, Some(TARGET_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: tangent.len(), type_: "VEC4",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    fn push_color_accessor(&mut self, data: &[[f32; 4]]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 16);
+        for c in data {
+            raw.extend_from_slice(&c[0].to_le_bytes());
+            raw.extend_from_slice(&c[1].to_le_bytes());
+            raw.extend_from_slice(&c[2].to_le_bytes());
+            raw.extend_from_slice(&c[3].to_le_bytes());
+        }
+        let view = self.push_view(&raw, Some(TARGET_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_FLOAT, count: data.len(), type_: "VEC4",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+
+    fn push_indices(&mut self, data: &[u16]) -> usize {
+        let mut raw = Vec::with_capacity(data.len() * 2);
+        for i in data { raw.extend_from_slice(&i.to_le_bytes()); }
+        let view = self.push_view(&raw, Some(TARGET_ELEMENT_ARRAY_BUFFER));
+        self.accessors.push(Accessor {
+            buffer_view: view, component_type: COMPONENT_UNSIGNED_SHORT, count: data.len(), type_: "SCALAR",
+            normalized: None, min: None, max: None
+        });
+        self.accessors.len() - 1
+    }
+}
+
+fn vec3_bounds(data: &[[f32; 3]]) -> (Option<Vec<f32>>, Option<Vec<f32>>) {
+    let mut min = data.first().copied().unwrap_or([0.0, 0.0, 0.0]);
+    let mut max = min;
+    for v in data {
+        for i in 0..3 {
+            min[i] = min[i].min(v[i]);
+            max[i] = max[i].max(v[i]);
+        }
+    }
+    (Some(min.to_vec()), Some(max.to_vec()))
+}
+
+#[derive(Serialize)]
+struct Document {
+    asset: Asset,
+    scene: usize,
+    scenes: Vec<Scene>,
+    nodes: Vec<GltfNode>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    meshes: Vec<Mesh>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    materials: Vec<GltfMaterial>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cameras: Vec<GltfCamera>,
+    accessors: Vec<Accessor>,
+    #[serde(rename = "bufferViews")]
+    buffer_views: Vec<BufferView>,
+    buffers: Vec<Buffer>
+}
+
+#[derive(Serialize)]
+struct Asset { version: &'static str }
+
+#[derive(Serialize)]
+struct Scene { nodes: Vec<usize> }
+
+#[derive(Serialize)]
+struct GltfNode {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mesh: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    camera: Option<usize>,
+    translation: [f32; 3],
+    rotation: [f32; 4],
+    scale: [f32; 3]
+}
+
+#[derive(Serialize)]
+struct Mesh { primitives: Vec<Primitive> }
+
+#[derive(Serialize, Clone)]
+struct Primitive {
+    attributes: BTreeMap<String, usize>,
+    indices: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    material: Option<usize>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GltfMaterial {
+    name: String,
+    pbr_metallic_roughness: PbrMetallicRoughness
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PbrMetallicRoughness {
+    base_color_factor: [f32; 4],
+    metallic_factor: f32,
+    roughness_factor: f32
+}
+impl Default for PbrMetallicRoughness {
+    fn default() -> Self {
+        PbrMetallicRoughness { base_color_factor: [1.0, 1.0, 1.0, 1.0], metallic_factor: 1.0, roughness_factor: 1.0 }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GltfCamera {
+    #[serde(rename = "type")]
+    type_: &'static str,
+    perspective: GltfPerspective
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GltfPerspective {
+    yfov: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aspect_ratio: Option<f32>,
+    znear: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    zfar: Option<f32>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: usize,
+    component_type: u32,
+    count: usize,
+    #[serde(rename = "type")]
+    type_: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normalized: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<Vec<f32>>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    byte_offset: usize,
+    byte_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<u32>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Buffer {
+    uri: String,
+    byte_length: usize
+}
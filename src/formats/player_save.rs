@@ -94,32 +94,69 @@ pub fn scramble(scrambled_data: &[u8]) -> Vec<u8> {
     data
 }
 
-pub fn parse(data: &[u8]) -> Result<SaveData> {
+/// Parses a SaveData blob. `verify_checksums` controls whether each
+/// datablock's trailing MD5 digest is checked against the block body: pass
+/// `false` to inspect a save that's known (or suspected) to be corrupt
+/// without [`read_datablock`] bailing out over it.
+pub fn parse(data: &[u8], verify_checksums: bool) -> Result<SaveData> {
     //let unscrambled = scramble(data);
     let mut cursor: &[u8] = data.as_ref();
 
     let version: u32 = cursor.read_item().context("Failed to read version (empty input?)")?;
     ensure!(version == 10, "Unknown SaveData version {}", version);
 
-    let head = read_datablock(&mut cursor).context("Failed reading head")?;
-    let body_bytes = read_datablock(&mut cursor).context("Failed reading body")?;
+    let head = read_datablock(&mut cursor, verify_checksums).context("Failed reading head")?;
+    let body_bytes = read_datablock(&mut cursor, verify_checksums).context("Failed reading body")?;
     let body = read_item(&mut body_bytes.as_ref()).context("Failed decoding body")?;
-    let foot = read_datablock(&mut cursor).context("Failed reading foot")?;
+    let foot = read_datablock(&mut cursor, verify_checksums).context("Failed reading foot")?;
 
     Ok(SaveData {head, body, foot})
 }
 
-fn read_datablock(bytes: &mut &[u8]) -> Result<Vec<u8>> {
+impl SaveData {
+    /// The inverse of [`parse`]: re-serializes `head`/`foot` and the `body`
+    /// tree back into `[u32 size][u32 version=10][body][16-byte digest]`
+    /// datablocks, with each digest freshly computed as the MD5 of its body -
+    /// the same digest [`read_datablock`] checks when asked to verify it.
+    pub fn write(&self) -> Vec<u8> {
+        let mut out = Vec::<u8>::new();
+        out.write_item(&10u32).unwrap();
+
+        write_datablock(&mut out, &self.head);
+
+        let mut body_bytes = Vec::<u8>::new();
+        write_item(&mut body_bytes, &self.body);
+        write_datablock(&mut out, &body_bytes);
+
+        write_datablock(&mut out, &self.foot);
+        out
+    }
+}
+
+fn read_datablock(bytes: &mut &[u8], verify_checksum: bool) -> Result<Vec<u8>> {
     let block_size: u32 = bytes.read_item().context("Failed reading block size")?;
     let block_version: u32 = bytes.read_item().context("Failed reading block version")?;
     ensure!(block_version == 10, "Unknown datablock version");
     let body_size = block_size - 16 - 4; // 16 bytes of checksum at the end, 4 bytes of size, length doesn't count
     let (body, rest) = bytes.split_at(body_size as usize);
-    let (_checksum, rest) = rest.split_at(16);
+    let (checksum, rest) = rest.split_at(16);
+    if verify_checksum {
+        let computed = md5::compute(body).0;
+        ensure!(computed.as_slice() == checksum, "Datablock checksum mismatch: stored {:x?}, computed {:x?}", checksum, computed);
+    }
     *bytes = rest;
     Ok(body.to_owned())
 }
 
+fn write_datablock(out: &mut Vec<u8>, body: &[u8]) {
+    let digest = md5::compute(body);
+    let block_size = body.len() as u32 + 16 + 4;
+    out.write_item(&block_size).unwrap();
+    out.write_item(&10u32).unwrap();
+    out.extend_from_slice(body);
+    out.extend_from_slice(&digest.0);
+}
+
 fn read_item(bytes: &mut &[u8]) -> Result<DataItem> {
     let item_addr = bytes.as_ptr();
     let tag: u8 = bytes.read_item().context("Failed to read tag")?;
@@ -137,6 +174,28 @@ fn read_item(bytes: &mut &[u8]) -> Result<DataItem> {
     Ok(res)
 }
 
+fn write_item(bytes: &mut Vec<u8>, item: &DataItem) {
+    match item {
+        DataItem::String(s) => { bytes.write_item(&1u8).unwrap(); write_string(bytes, s); },
+        DataItem::ScrambledString(s) => { bytes.write_item(&1u8).unwrap(); write_scrambled_string(bytes, s); },
+        DataItem::Float(f) => { bytes.write_item(&2u8).unwrap(); bytes.write_item(f).unwrap(); },
+        DataItem::Empty => bytes.write_item(&3u8).unwrap(),
+        DataItem::Byte(b) => { bytes.write_item(&4u8).unwrap(); bytes.write_item(b).unwrap(); },
+        DataItem::Short(s) => { bytes.write_item(&5u8).unwrap(); bytes.write_item(s).unwrap(); },
+        DataItem::Bool(b) => { bytes.write_item(&6u8).unwrap(); bytes.write_item(b).unwrap(); },
+        DataItem::Dictionary(dict) => { bytes.write_item(&7u8).unwrap(); write_dictionary(bytes, dict); },
+        DataItem::Unknown9(b) => { bytes.write_item(&9u8).unwrap(); bytes.write_item(b).unwrap(); }
+    }
+}
+
+fn write_dictionary(bytes: &mut Vec<u8>, dict: &HashMap<DataItem, DataItem>) {
+    bytes.write_item(&(dict.len() as u32)).unwrap();
+    for (key, value) in dict {
+        write_item(bytes, key);
+        write_item(bytes, value);
+    }
+}
+
 const STRING_PADDING: &[u8] = &[ 0xDF, 0xC1, 0xA3, 0x85, 0x67, 0x49, 0x2B, 0x0D, 0xED, 0xCF, 0xB1, 0x93 ];
 /*
 If rohvani's code is correct, which I'm assuming it is, digested values are always an even number of bytes.
@@ -178,6 +237,24 @@ fn read_string(bytes: &mut &[u8]) -> Result<DataItem> {
     Ok(DataItem::ScrambledString(descrambled))
 }
 
+/// Writes an ordinary UTF-8 string as tag-1 data reads it back as: the raw
+/// bytes followed by a NUL terminator.
+fn write_string(bytes: &mut Vec<u8>, s: &str) {
+    bytes.extend_from_slice(s.as_bytes());
+    bytes.push(0);
+}
+
+/// The inverse of the digest branch of [`read_string`]: interleaves
+/// `0xFE - byte` with the fixed [`STRING_PADDING`] bytes, then terminates
+/// with a NUL like any other tag-1 string.
+fn write_scrambled_string(bytes: &mut Vec<u8>, descrambled: &[u8]) {
+    for (idx, byte) in descrambled.iter().enumerate() {
+        bytes.push(0xFEu8.wrapping_sub(*byte));
+        bytes.push(STRING_PADDING[idx % STRING_PADDING.len()]);
+    }
+    bytes.push(0);
+}
+
 fn read_dictionary(bytes: &mut &[u8]) -> Result<HashMap<DataItem, DataItem>> {
     let len: u32 = bytes.read_item().context("Failed to read dictionary length")?;
     let mut res = HashMap::with_capacity(len as usize);
@@ -0,0 +1,269 @@
+//! Arena-backed alternative to the `RcCell<DocTable>`/`Rc<str>` graph
+//! [`Document`] is built from - see that module's doc comment for the
+//! refcounted model this sits alongside. Every table here lives in one
+//! `Vec` and is referenced by index (`TableId`) rather than a separate heap
+//! allocation with its own refcount, so walking a large document for bulk
+//! processing (dumping, schema checking, whole-tree rewrites) touches one
+//! contiguous buffer instead of chasing pointers, and the whole graph is
+//! freed in one shot when the `ArenaDoc` is dropped. Strings stay
+//! [`AtomId`]s throughout, resolved through whichever [`Document`] the
+//! `ArenaDoc` was built from or is converted back into - an `ArenaDoc` on
+//! its own doesn't own an atom table.
+//!
+//! [`from_document`] and [`to_document`] convert to and from the `RcCell`
+//! model, so a caller can load and dump through the existing path and only
+//! pay for an arena where it actually helps - one bulk-processing pass in
+//! the middle.
+
+use fnv::FnvHashMap;
+use std::collections::HashMap;
+
+use super::document::{AtomId, DocTable, DocValue, Document, Quaternion, Vector};
+use crate::hashindex::Hash as IdString;
+use crate::util::ordered_float::OrderedFloat;
+use crate::util::rc_cell::{RcCell, WeakCell};
+
+/// A handle into an [`ArenaDoc`]'s table arena. Only valid for the
+/// `ArenaDoc` that produced it - like [`AtomId`], cheap to copy and
+/// compare, with no meaning on its own.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct TableId(u32);
+
+/// [`DocValue`]'s counterpart in the arena model: identical except
+/// `Table(RcCell<DocTable>)` becomes `Table(TableId)`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ArenaValue {
+    Bool(bool),
+    Number(OrderedFloat),
+    IdString(IdString),
+    String(AtomId),
+    Vector(Vector<OrderedFloat>),
+    Quaternion(Quaternion<OrderedFloat>),
+    Table(TableId)
+}
+
+/// One table's contents in the arena - [`DocTable`]'s counterpart, holding
+/// its entries inline rather than behind a further indirection since the
+/// whole point is to avoid an `RcCell` per table.
+#[derive(PartialEq, Debug)]
+pub struct ArenaTable {
+    metatable: Option<AtomId>,
+    entries: Vec<(ArenaValue, ArenaValue)>
+}
+impl ArenaTable {
+    pub fn get_metatable(&self) -> Option<AtomId> { self.metatable }
+    pub fn entries(&self) -> &[(ArenaValue, ArenaValue)] { &self.entries }
+}
+
+/// A [`Document`]'s graph, reshaped into one arena of [`ArenaTable`]s - see
+/// the module doc comment. Diamond/shared-table detection is just "do two
+/// `ArenaValue::Table`s carry the same `TableId`", no separate identity
+/// tracking needed.
+pub struct ArenaDoc {
+    tables: Vec<ArenaTable>,
+    root: Option<ArenaValue>
+}
+impl ArenaDoc {
+    pub fn table(&self, id: TableId) -> &ArenaTable { &self.tables[id.0 as usize] }
+    pub fn table_count(&self) -> usize { self.tables.len() }
+    pub fn root(&self) -> Option<&ArenaValue> { self.root.as_ref() }
+}
+
+/// Copies `doc`'s graph into a fresh [`ArenaDoc`]. Tables are assigned ids
+/// in [`Document::walk_tables`]'s visit-once order, so a cycle or shared
+/// table is only copied once - the same guarantee the `RcCell` model gives
+/// for free through pointer identity.
+pub fn from_document(doc: &Document) -> ArenaDoc {
+    let mut id_of = FnvHashMap::<WeakCell<DocTable>, TableId>::default();
+    let mut order: Vec<RcCell<DocTable>> = Vec::new();
+    doc.walk_tables(|t| {
+        id_of.insert(t.downgrade(), TableId(order.len() as u32));
+        order.push(t.clone());
+    });
+
+    let tables = order.iter().map(|t| {
+        let borrowed = t.borrow();
+        let entries = (&*borrowed).into_iter()
+            .map(|(k, v)| (to_arena_value(k, &id_of), to_arena_value(v, &id_of)))
+            .collect();
+        ArenaTable { metatable: borrowed.get_metatable(), entries }
+    }).collect();
+
+    let root = doc.root().map(|r| to_arena_value(&r, &id_of));
+    ArenaDoc { tables, root }
+}
+
+fn to_arena_value(v: &DocValue, id_of: &FnvHashMap<WeakCell<DocTable>, TableId>) -> ArenaValue {
+    match v {
+        DocValue::Bool(b) => ArenaValue::Bool(*b),
+        DocValue::Number(n) => ArenaValue::Number(*n),
+        DocValue::IdString(i) => ArenaValue::IdString(*i),
+        DocValue::String(s) => ArenaValue::String(*s),
+        DocValue::Vector(v) => ArenaValue::Vector(*v),
+        DocValue::Quaternion(q) => ArenaValue::Quaternion(*q),
+        DocValue::Table(t) => ArenaValue::Table(*id_of.get(&t.downgrade())
+            .expect("Document::walk_tables didn't visit a table reachable from the root")),
+    }
+}
+
+/// Where a table's entry sorts in [`from_document_canonical`]'s traversal:
+/// `Number` keys first in ascending order, then `String` keys in lexical
+/// order of their resolved text, then anything else (a key kind scriptdata
+/// tables basically never use) in whatever order [`DocValue`]'s derived
+/// `Ord` gives it.
+fn canonical_key_order(doc: &Document, key: &DocValue) -> (u8, Option<OrderedFloat>, String) {
+    match key {
+        DocValue::Number(n) => (0, Some(*n), String::new()),
+        DocValue::String(a) => (1, None, doc.resolve(*a).to_owned()),
+        _ => (2, None, String::new())
+    }
+}
+
+/// Like [`from_document`], but assigns `TableId`s by a canonical
+/// breadth-first order instead of [`Document::walk_tables`]'s
+/// first-encounter order (which depends on `DocTable`'s `HashMap` iteration
+/// order, and so isn't stable across runs): every table's entries are
+/// sorted by [`canonical_key_order`] before its child tables are
+/// discovered and queued. Two documents that are identical in shape and
+/// content but built from `RcCell`s in a different order - the common case
+/// after parsing the same logical data twice - end up with their tables
+/// numbered identically, which is what makes them serialize
+/// byte-for-byte the same and is what [`alpha_eq`] checks for without the
+/// ids actually having to match.
+pub fn from_document_canonical(doc: &Document) -> ArenaDoc {
+    let mut id_of = FnvHashMap::<WeakCell<DocTable>, TableId>::default();
+    let mut order: Vec<RcCell<DocTable>> = Vec::new();
+    let mut queue: std::collections::VecDeque<RcCell<DocTable>> = std::collections::VecDeque::new();
+
+    if let Some(DocValue::Table(root_table)) = doc.root() {
+        id_of.insert(root_table.downgrade(), TableId(0));
+        queue.push_back(root_table.clone());
+        order.push(root_table);
+    }
+
+    while let Some(table) = queue.pop_front() {
+        for (_, v) in sorted_entries(doc, &table) {
+            if let DocValue::Table(t) = v {
+                let down = t.downgrade();
+                if !id_of.contains_key(&down) {
+                    id_of.insert(down, TableId(order.len() as u32));
+                    queue.push_back(t.clone());
+                    order.push(t);
+                }
+            }
+        }
+    }
+
+    let tables = order.iter().map(|t| {
+        let entries = sorted_entries(doc, t).into_iter()
+            .map(|(k, v)| (to_arena_value(&k, &id_of), to_arena_value(&v, &id_of)))
+            .collect();
+        ArenaTable { metatable: t.borrow().get_metatable(), entries }
+    }).collect();
+
+    let root = doc.root().map(|r| to_arena_value(&r, &id_of));
+    ArenaDoc { tables, root }
+}
+
+fn sorted_entries(doc: &Document, table: &RcCell<DocTable>) -> Vec<(DocValue, DocValue)> {
+    let mut entries: Vec<(DocValue, DocValue)> = (&*table.borrow()).into_iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    entries.sort_by(|(a, _), (b, _)| canonical_key_order(doc, a).cmp(&canonical_key_order(doc, b)).then_with(|| a.cmp(b)));
+    entries
+}
+
+/// Dhall's alpha-equivalence, for scriptdata: whether `a` and `b` have the
+/// same shape and values once each is numbered by
+/// [`from_document_canonical`], regardless of the `RcCell` identities or
+/// `AtomId` numbering (resolved against each document's own atom table,
+/// not compared as raw ids) either happened to end up with. Tables already
+/// compared as part of an enclosing table - including a table reached
+/// again through a cycle - are taken as equal without being re-walked.
+pub fn alpha_eq(a: &Document, b: &Document) -> bool {
+    let arena_a = from_document_canonical(a);
+    let arena_b = from_document_canonical(b);
+    let mut seen = FnvHashMap::<TableId, TableId>::default();
+    arena_value_eq(arena_a.root.as_ref(), arena_b.root.as_ref(), a, b, &arena_a, &arena_b, &mut seen)
+}
+
+fn arena_value_eq(
+    a: Option<&ArenaValue>, b: Option<&ArenaValue>,
+    doc_a: &Document, doc_b: &Document,
+    arena_a: &ArenaDoc, arena_b: &ArenaDoc,
+    seen: &mut FnvHashMap<TableId, TableId>
+) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(ArenaValue::Bool(x)), Some(ArenaValue::Bool(y))) => x == y,
+        (Some(ArenaValue::Number(x)), Some(ArenaValue::Number(y))) => x == y,
+        (Some(ArenaValue::IdString(x)), Some(ArenaValue::IdString(y))) => x == y,
+        (Some(ArenaValue::String(x)), Some(ArenaValue::String(y))) => doc_a.resolve(*x) == doc_b.resolve(*y),
+        (Some(ArenaValue::Vector(x)), Some(ArenaValue::Vector(y))) => x == y,
+        (Some(ArenaValue::Quaternion(x)), Some(ArenaValue::Quaternion(y))) => x == y,
+        (Some(ArenaValue::Table(x)), Some(ArenaValue::Table(y))) => {
+            if let Some(expected) = seen.get(x) { return *expected == *y; }
+            seen.insert(*x, *y);
+
+            let ta = arena_a.table(*x);
+            let tb = arena_b.table(*y);
+            let meta_eq = match (ta.metatable, tb.metatable) {
+                (None, None) => true,
+                (Some(ma), Some(mb)) => doc_a.resolve(ma) == doc_b.resolve(mb),
+                _ => false
+            };
+            meta_eq && ta.entries.len() == tb.entries.len()
+                && ta.entries.iter().zip(&tb.entries).all(|((ka, va), (kb, vb))| {
+                    arena_value_eq(Some(ka), Some(kb), doc_a, doc_b, arena_a, arena_b, seen)
+                        && arena_value_eq(Some(va), Some(vb), doc_a, doc_b, arena_a, arena_b, seen)
+                })
+        },
+        _ => false
+    }
+}
+
+impl ArenaDoc {
+    /// Rebuilds this arena's graph as `RcCell`s and sets it as `into`'s
+    /// root - the inverse of [`from_document`]. `into` should already carry
+    /// whatever atom table the `AtomId`s here were resolved against (the
+    /// same `Document` this was built from, typically), since an
+    /// `ArenaDoc` doesn't keep one of its own.
+    pub fn to_document(&self, into: &mut Document) {
+        let mut built = HashMap::<u32, RcCell<DocTable>>::new();
+        let root = self.root.as_ref().map(|r| self.to_doc_value(r, &mut built));
+        into.set_root(root);
+    }
+
+    fn to_doc_value(&self, v: &ArenaValue, built: &mut HashMap<u32, RcCell<DocTable>>) -> DocValue {
+        match v {
+            ArenaValue::Bool(b) => DocValue::Bool(*b),
+            ArenaValue::Number(n) => DocValue::Number(*n),
+            ArenaValue::IdString(i) => DocValue::IdString(*i),
+            ArenaValue::String(s) => DocValue::String(*s),
+            ArenaValue::Vector(v) => DocValue::Vector(*v),
+            ArenaValue::Quaternion(q) => DocValue::Quaternion(*q),
+            ArenaValue::Table(id) => DocValue::Table(self.build_table(*id, built))
+        }
+    }
+
+    fn build_table(&self, id: TableId, built: &mut HashMap<u32, RcCell<DocTable>>) -> RcCell<DocTable> {
+        if let Some(existing) = built.get(&id.0) {
+            return existing.clone();
+        }
+
+        let cell = RcCell::new(DocTable::new());
+        built.insert(id.0, cell.clone()); // inserted before recursing, so a cycle finds itself already in progress
+
+        let table = self.table(id);
+        let mut borrowed = cell.borrow_mut();
+        borrowed.set_metatable(table.metatable);
+        for (k, v) in &table.entries {
+            let key = self.to_doc_value(k, built);
+            let value = self.to_doc_value(v, built);
+            borrowed.insert(key, value);
+        }
+        drop(borrowed);
+
+        cell
+    }
+}
@@ -0,0 +1,279 @@
+//! A self-describing binary transfer syntax for `Document`.
+//!
+//! This is *not* the on-disk Diesel format `binary` reads (that one has a
+//! fixed header/offset-table layout tied to the engine's needs); this is a
+//! tagged, self-describing encoding of the same data model, playing the
+//! same role for `Document` that `lua_like` does but as bytes instead of
+//! text. Tables [`IdTracker`](super::id_tracker::IdTracker) flags as shared
+//! (reachable from more than one place) are written in full exactly once,
+//! tagged with the id it assigns, and every later occurrence is just a
+//! back-reference carrying that id.
+//!
+//! Layout, one tag byte followed by its payload:
+//!
+//! | tag | payload |
+//! |-----|---------|
+//! | 0   | (none) -- `false` |
+//! | 1   | (none) -- `true` |
+//! | 2   | `f32` LE -- number |
+//! | 3   | `u64` LE -- idstring |
+//! | 4   | varint length, then that many UTF-8 bytes -- string |
+//! | 5   | 3x `f32` LE -- vector |
+//! | 6   | 4x `f32` LE -- quaternion |
+//! | 7   | table, not (yet) referenced again: see below |
+//! | 8   | varint id, then a table as under tag 7: first occurrence of a shared table |
+//! | 9   | varint id -- back-reference to a table written under tag 8 |
+//!
+//! A table's body (tags 7 and 8) is: one byte (0/1, metatable present),
+//! the metatable name as a tag-4 string if present, a varint entry count,
+//! then that many `(key, value)` pairs, each a self-describing value.
+
+use anyhow::{anyhow, bail};
+use fnv::FnvHashMap;
+
+use super::document::*;
+use super::id_tracker::*;
+use crate::hashindex::Hash as IdString;
+use crate::util::ordered_float::OrderedFloat;
+use crate::util::rc_cell::RcCell;
+use crate::util::read_helpers::TryFromIndexedLE;
+
+const TAG_FALSE: u8 = 0;
+const TAG_TRUE: u8 = 1;
+const TAG_NUMBER: u8 = 2;
+const TAG_IDSTRING: u8 = 3;
+const TAG_STRING: u8 = 4;
+const TAG_VECTOR: u8 = 5;
+const TAG_QUATERNION: u8 = 6;
+const TAG_TABLE: u8 = 7;
+const TAG_TABLE_WITH_ID: u8 = 8;
+const TAG_TABLE_REF: u8 = 9;
+
+pub fn write_binary(doc: &Document) -> Vec<u8> {
+    let mut state = WriteState {
+        doc,
+        output: Vec::new(),
+        id_tracker: IdTracker::new(doc)
+    };
+    match doc.root() {
+        Some(item) => write_item(&item, &mut state),
+        None => ()
+    }
+    state.output
+}
+
+struct WriteState<'d> {
+    doc: &'d Document,
+    output: Vec<u8>,
+    id_tracker: IdTracker
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_item(item: &DocValue, state: &mut WriteState<'_>) {
+    match item {
+        DocValue::Bool(false) => state.output.push(TAG_FALSE),
+        DocValue::Bool(true) => state.output.push(TAG_TRUE),
+        DocValue::Number(n) => {
+            state.output.push(TAG_NUMBER);
+            state.output.extend_from_slice(&n.0.to_le_bytes());
+        },
+        DocValue::IdString(ids) => {
+            state.output.push(TAG_IDSTRING);
+            state.output.extend_from_slice(&ids.0.to_le_bytes());
+        },
+        DocValue::String(s) => write_string(TAG_STRING, &state.doc.resolve_rc(*s), &mut state.output),
+        DocValue::Vector(v) => {
+            state.output.push(TAG_VECTOR);
+            state.output.extend_from_slice(&v.x.0.to_le_bytes());
+            state.output.extend_from_slice(&v.y.0.to_le_bytes());
+            state.output.extend_from_slice(&v.z.0.to_le_bytes());
+        },
+        DocValue::Quaternion(q) => {
+            state.output.push(TAG_QUATERNION);
+            state.output.extend_from_slice(&q.x.0.to_le_bytes());
+            state.output.extend_from_slice(&q.y.0.to_le_bytes());
+            state.output.extend_from_slice(&q.z.0.to_le_bytes());
+            state.output.extend_from_slice(&q.w.0.to_le_bytes());
+        },
+        DocValue::Table(tab) => write_table(tab, state)
+    }
+}
+
+fn write_string(tag: u8, s: &str, out: &mut Vec<u8>) {
+    out.push(tag);
+    write_varint(out, s.len() as u64);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_table(table: &RcCell<DocTable>, state: &mut WriteState<'_>) {
+    match state.id_tracker.track_table(table) {
+        RefCheck::Ref(id) => {
+            state.output.push(TAG_TABLE_REF);
+            write_varint(&mut state.output, id as u64);
+            return;
+        },
+        RefCheck::Id(id) => {
+            state.output.push(TAG_TABLE_WITH_ID);
+            write_varint(&mut state.output, id as u64);
+        },
+        RefCheck::None => {
+            state.output.push(TAG_TABLE);
+        }
+    }
+
+    let tref = &*table.borrow();
+    match tref.get_metatable() {
+        Some(mt) => {
+            state.output.push(1);
+            write_string(TAG_STRING, &state.doc.resolve_rc(mt), &mut state.output);
+        },
+        None => state.output.push(0)
+    }
+
+    write_varint(&mut state.output, tref.len() as u64);
+    for (k, v) in tref {
+        write_item(k, state);
+        write_item(v, state);
+    }
+}
+
+pub fn read_binary(bytes: &[u8]) -> anyhow::Result<Document> {
+    let mut r = Reader {
+        input: bytes,
+        pos: 0,
+        doc: Document::new(),
+        ids: FnvHashMap::default()
+    };
+    if r.input.is_empty() {
+        return Ok(r.doc);
+    }
+    let root = r.read_item()?;
+    if r.pos != r.input.len() {
+        bail!("Binary scriptdata: {} trailing byte(s) after the root value", r.input.len() - r.pos);
+    }
+    r.doc.set_root(Some(root));
+    r.doc.gc();
+    Ok(r.doc)
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+    pos: usize,
+    doc: Document,
+    ids: FnvHashMap<u64, RcCell<DocTable>>
+}
+
+impl<'a> Reader<'a> {
+    fn read_u8(&mut self) -> anyhow::Result<u8> {
+        let b = *self.input.get(self.pos).ok_or_else(|| anyhow!("Binary scriptdata: unexpected end of input at byte {}", self.pos))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn read_fixed<T: TryFromIndexedLE<Error = crate::util::read_helpers::TryFromBytesError>>(&mut self, len: usize) -> anyhow::Result<T> {
+        let v = T::try_from_le(self.input, self.pos).map_err(|e| anyhow!("Binary scriptdata: {}", e))?;
+        self.pos += len;
+        Ok(v)
+    }
+
+    fn read_varint(&mut self) -> anyhow::Result<u64> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_u8()?;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 { break; }
+            shift += 7;
+            if shift >= 64 { bail!("Binary scriptdata: varint too long at byte {}", self.pos); }
+        }
+        Ok(value)
+    }
+
+    fn read_string(&mut self) -> anyhow::Result<AtomId> {
+        let len = self.read_varint()? as usize;
+        let end = self.pos.checked_add(len).ok_or_else(|| anyhow!("Binary scriptdata: string length overflow at byte {}", self.pos))?;
+        let bytes = self.input.get(self.pos..end).ok_or_else(|| anyhow!("Binary scriptdata: string runs past end of input at byte {}", self.pos))?;
+        let s = std::str::from_utf8(bytes).map_err(|e| anyhow!("Binary scriptdata: invalid UTF-8 string at byte {}: {}", self.pos, e))?;
+        self.pos = end;
+        Ok(self.doc.intern(s))
+    }
+
+    fn read_item(&mut self) -> anyhow::Result<DocValue> {
+        let tag = self.read_u8()?;
+        match tag {
+            TAG_FALSE => Ok(DocValue::Bool(false)),
+            TAG_TRUE => Ok(DocValue::Bool(true)),
+            TAG_NUMBER => Ok(DocValue::Number(OrderedFloat(self.read_fixed::<f32>(4)?))),
+            TAG_IDSTRING => Ok(DocValue::IdString(IdString(self.read_fixed::<u64>(8)?))),
+            TAG_STRING => Ok(DocValue::String(self.read_string()?)),
+            TAG_VECTOR => {
+                let x = self.read_fixed::<f32>(4)?;
+                let y = self.read_fixed::<f32>(4)?;
+                let z = self.read_fixed::<f32>(4)?;
+                Ok(DocValue::Vector(Vector { x: OrderedFloat(x), y: OrderedFloat(y), z: OrderedFloat(z) }))
+            },
+            TAG_QUATERNION => {
+                let x = self.read_fixed::<f32>(4)?;
+                let y = self.read_fixed::<f32>(4)?;
+                let z = self.read_fixed::<f32>(4)?;
+                let w = self.read_fixed::<f32>(4)?;
+                Ok(DocValue::Quaternion(Quaternion { x: OrderedFloat(x), y: OrderedFloat(y), z: OrderedFloat(z), w: OrderedFloat(w) }))
+            },
+            TAG_TABLE => Ok(DocValue::Table(self.read_table(None)?)),
+            TAG_TABLE_WITH_ID => {
+                let id = self.read_varint()?;
+                Ok(DocValue::Table(self.read_table(Some(id))?))
+            },
+            TAG_TABLE_REF => {
+                let id = self.read_varint()?;
+                match self.ids.get(&id) {
+                    Some(cell) => Ok(DocValue::Table(cell.clone())),
+                    None => bail!("Binary scriptdata: table reference {} used before it was defined", id)
+                }
+            },
+            other => bail!("Binary scriptdata: unrecognised tag {} at byte {}", other, self.pos - 1)
+        }
+    }
+
+    fn read_table(&mut self, id: Option<u64>) -> anyhow::Result<RcCell<DocTable>> {
+        let cell = RcCell::<DocTable>::default();
+        if let Some(id) = id {
+            self.ids.insert(id, cell.clone());
+        }
+
+        let has_meta = self.read_u8()?;
+        let meta = match has_meta {
+            0 => None,
+            1 => {
+                self.read_u8()?; // the metatable name's own TAG_STRING
+                Some(self.read_string()?)
+            },
+            other => bail!("Binary scriptdata: bad metatable-present flag {} at byte {}", other, self.pos - 1)
+        };
+
+        {
+            let mut table = cell.borrow_mut();
+            table.set_metatable(meta);
+        }
+
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let key = self.read_item()?;
+            let value = self.read_item()?;
+            cell.borrow_mut().insert(key, value);
+        }
+
+        Ok(cell)
+    }
+}
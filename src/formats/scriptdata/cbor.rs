@@ -0,0 +1,357 @@
+//! A CBOR (RFC 8949) encoding of `Document`, for tools that would rather
+//! parse a compact, self-describing binary format than the on-disk Diesel
+//! layout `binary` reads or either of the XML dumps.
+//!
+//! Scalars map onto the obvious CBOR primitives - `Bool` to `true`/`false`,
+//! `String` to a text string - except:
+//!
+//! - `Number` is written as a plain (positive or negative) CBOR integer when
+//!   it holds a whole number a `u64`/CBOR negative int can represent exactly,
+//!   and as a `f32` major-7 float otherwise.
+//! - `IdString`, `Vector` and `Quaternion` have no CBOR equivalent, so each
+//!   is a private-use tag ([`TAG_IDSTRING`]/[`TAG_VECTOR`]/[`TAG_QUATERNION`])
+//!   wrapping a plain uint (idstring) or float array (vector/quaternion).
+//!
+//! A `Table` is a CBOR map of its entries; if it has a metatable, the map
+//! carries one extra entry first, keyed by the byte string `b"meta"` (a key
+//! type `write_item` never otherwise produces, so it can't collide with a
+//! real entry) whose value is the metatable name.
+//!
+//! A table can be referenced from more than one place, and - since a table
+//! can hold a reference to itself, directly or through others - the whole
+//! graph isn't even guaranteed to be acyclic. Rather than a homebrew
+//! `_id`/`_ref` scheme like [`IdTracker`](super::id_tracker), shared and
+//! cyclic tables both use the IANA-registered value-sharing tags: a table
+//! [`Document::tables_used_repeatedly`] flags is wrapped in [`TAG_SHAREABLE`]
+//! (28) the first time it's written, which - per that tag's definition -
+//! assigns it the next index in the shared-value list purely by the order
+//! tag-28 values are encountered; every later occurrence, including one
+//! reached by walking back into the table currently being written, is just
+//! [`TAG_SHARED_REF`] (29) wrapping that index. This also makes a CBOR
+//! dump of a `Document` a cache that's fast to read back with [`read_cbor`]
+//! without re-running the XML or binary parsers that produced it.
+
+use anyhow::{anyhow, bail};
+use fnv::{FnvHashMap, FnvHashSet};
+
+use super::document::*;
+use crate::hashindex::Hash as IdString;
+use crate::util::ordered_float::OrderedFloat;
+use crate::util::rc_cell::{RcCell, WeakCell};
+
+/// IANA CBOR tag: "mark value as (potentially) shared". Wraps the table the
+/// first time a table [`Document::tables_used_repeatedly`] flagged is written.
+const TAG_SHAREABLE: u64 = 28;
+/// IANA CBOR tag: "reference the nth marked value". Wraps a uint index into
+/// the implicit list [`TAG_SHAREABLE`] values are numbered into, in the
+/// order they were encountered while decoding.
+const TAG_SHARED_REF: u64 = 29;
+/// Private-use tag wrapping a plain uint: an `IdString`'s 64-bit hash.
+const TAG_IDSTRING: u64 = 1_000_000;
+/// Private-use tag wrapping a 3-element float array: a `Vector`.
+const TAG_VECTOR: u64 = 1_000_001;
+/// Private-use tag wrapping a 4-element float array: a `Quaternion`.
+const TAG_QUATERNION: u64 = 1_000_002;
+
+fn write_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let top = major << 5;
+    if value < 24 {
+        out.push(top | value as u8);
+    }
+    else if value <= u8::MAX as u64 {
+        out.push(top | 24);
+        out.push(value as u8);
+    }
+    else if value <= u16::MAX as u64 {
+        out.push(top | 25);
+        out.extend_from_slice(&(value as u16).to_be_bytes());
+    }
+    else if value <= u32::MAX as u64 {
+        out.push(top | 26);
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    }
+    else {
+        out.push(top | 27);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn write_bool(out: &mut Vec<u8>, b: bool) {
+    out.push(if b { 0xF5 } else { 0xF4 });
+}
+
+fn write_float32(out: &mut Vec<u8>, f: f32) {
+    out.push(0xFA);
+    out.extend_from_slice(&f.to_bits().to_be_bytes());
+}
+
+fn write_number(out: &mut Vec<u8>, f: f32) {
+    if f.is_finite() && f.fract() == 0.0 {
+        if (0.0..=u64::MAX as f32).contains(&f) {
+            write_head(out, 0, f as u64);
+            return;
+        }
+        if (-(u64::MAX as f32)..0.0).contains(&f) {
+            write_head(out, 1, (-f) as u64 - 1);
+            return;
+        }
+    }
+    write_float32(out, f);
+}
+
+fn write_text(out: &mut Vec<u8>, s: &str) {
+    write_head(out, 3, s.len() as u64);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_byte_string(out: &mut Vec<u8>, s: &[u8]) {
+    write_head(out, 2, s.len() as u64);
+    out.extend_from_slice(s);
+}
+
+struct WriteState<'d> {
+    doc: &'d Document,
+    seen_table_ids: FnvHashMap<WeakCell<DocTable>, u32>,
+    referenced_tables: FnvHashSet<WeakCell<DocTable>>,
+    next_id: u32,
+}
+
+pub fn write_cbor(doc: &Document) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut state = WriteState {
+        doc,
+        seen_table_ids: FnvHashMap::default(),
+        referenced_tables: doc.tables_used_repeatedly(),
+        next_id: 0,
+    };
+    if let Some(item) = doc.root() {
+        write_item(&item, &mut out, &mut state);
+    }
+    out
+}
+
+fn write_item(item: &DocValue, out: &mut Vec<u8>, state: &mut WriteState<'_>) {
+    match item {
+        DocValue::Bool(b) => write_bool(out, *b),
+        DocValue::Number(n) => write_number(out, n.0),
+        DocValue::IdString(ids) => {
+            write_head(out, 6, TAG_IDSTRING);
+            write_head(out, 0, ids.0);
+        },
+        DocValue::String(s) => write_text(out, &state.doc.resolve_rc(*s)),
+        DocValue::Vector(v) => {
+            write_head(out, 6, TAG_VECTOR);
+            write_head(out, 4, 3);
+            write_float32(out, v.x.0);
+            write_float32(out, v.y.0);
+            write_float32(out, v.z.0);
+        },
+        DocValue::Quaternion(q) => {
+            write_head(out, 6, TAG_QUATERNION);
+            write_head(out, 4, 4);
+            write_float32(out, q.x.0);
+            write_float32(out, q.y.0);
+            write_float32(out, q.z.0);
+            write_float32(out, q.w.0);
+        },
+        DocValue::Table(tab) => write_table(tab, out, state)
+    }
+}
+
+fn write_table(table: &RcCell<DocTable>, out: &mut Vec<u8>, state: &mut WriteState<'_>) {
+    let downgraded = table.downgrade();
+    if let Some(&id) = state.seen_table_ids.get(&downgraded) {
+        write_head(out, 6, TAG_SHARED_REF);
+        write_head(out, 0, id as u64);
+        return;
+    }
+
+    let is_shared = state.referenced_tables.contains(&downgraded);
+    if is_shared {
+        write_head(out, 6, TAG_SHAREABLE);
+        // TAG_SHAREABLE's index is implicit on the decode side (it's just
+        // this value's position among TAG_SHAREABLE-wrapped values in
+        // encounter order), but the writer still has to remember which
+        // index that'll be so a later TAG_SHARED_REF can name it.
+        state.seen_table_ids.insert(downgraded, state.next_id);
+        state.next_id += 1;
+    }
+
+    let tref = &*table.borrow();
+    let meta = tref.get_metatable();
+    write_head(out, 5, tref.len() as u64 + meta.is_some() as u64);
+    if let Some(mt) = meta {
+        write_byte_string(out, b"meta");
+        write_text(out, &state.doc.resolve_rc(mt));
+    }
+    for (k, v) in tref {
+        write_item(k, out, state);
+        write_item(v, out, state);
+    }
+}
+
+pub fn read_cbor(bytes: &[u8]) -> anyhow::Result<Document> {
+    let mut r = Reader { input: bytes, pos: 0, doc: Document::new(), shared: Vec::new() };
+    if r.input.is_empty() {
+        return Ok(r.doc);
+    }
+    let root = r.read_item()?;
+    if r.pos != r.input.len() {
+        bail!("CBOR scriptdata: {} trailing byte(s) after the root value", r.input.len() - r.pos);
+    }
+    r.doc.set_root(Some(root));
+    r.doc.gc();
+    Ok(r.doc)
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+    pos: usize,
+    doc: Document,
+    shared: Vec<RcCell<DocTable>>,
+}
+
+impl<'a> Reader<'a> {
+    fn read_u8(&mut self) -> anyhow::Result<u8> {
+        let b = *self.input.get(self.pos).ok_or_else(|| anyhow!("CBOR scriptdata: unexpected end of input at byte {}", self.pos))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).ok_or_else(|| anyhow!("CBOR scriptdata: length overflow at byte {}", self.pos))?;
+        let slice = self.input.get(self.pos..end).ok_or_else(|| anyhow!("CBOR scriptdata: item runs past end of input at byte {}", self.pos))?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    /// Reads an initial byte and the length/value bytes that follow it,
+    /// returning the major type and the decoded argument - the CBOR spec
+    /// calls this argument different things depending on the major type
+    /// (a length, a tag number, a uint value, a simple-value selector), but
+    /// it's always encoded the same way.
+    fn read_head(&mut self) -> anyhow::Result<(u8, u64)> {
+        let ib = self.read_u8()?;
+        let major = ib >> 5;
+        let value = match ib & 0x1F {
+            n @ 0..=23 => n as u64,
+            24 => self.read_u8()? as u64,
+            25 => u16::from_be_bytes(self.read_bytes(2)?.try_into().unwrap()) as u64,
+            26 => u32::from_be_bytes(self.read_bytes(4)?.try_into().unwrap()) as u64,
+            27 => u64::from_be_bytes(self.read_bytes(8)?.try_into().unwrap()),
+            n => bail!("CBOR scriptdata: unsupported additional-info {} at byte {}", n, self.pos - 1)
+        };
+        Ok((major, value))
+    }
+
+    fn read_text(&mut self, len: usize) -> anyhow::Result<AtomId> {
+        let bytes = self.read_bytes(len)?;
+        let s = std::str::from_utf8(bytes).map_err(|e| anyhow!("CBOR scriptdata: invalid UTF-8 text string at byte {}: {}", self.pos - len, e))?;
+        Ok(self.doc.intern(s))
+    }
+
+    fn read_float32(&mut self) -> anyhow::Result<f32> {
+        Ok(f32::from_bits(u32::from_be_bytes(self.read_bytes(4)?.try_into().unwrap())))
+    }
+
+    fn read_item(&mut self) -> anyhow::Result<DocValue> {
+        let tag_start = self.pos;
+        let (major, value) = self.read_head()?;
+        match major {
+            0 => Ok(DocValue::Number(OrderedFloat(value as f32))),
+            1 => Ok(DocValue::Number(OrderedFloat(-1.0 - value as f32))),
+            3 => Ok(DocValue::String(self.read_text(value as usize)?)),
+            5 => Ok(DocValue::Table(self.read_table_body(value as usize, None)?)),
+            6 => self.read_tagged(value, tag_start),
+            7 => match value {
+                20 => Ok(DocValue::Bool(false)),
+                21 => Ok(DocValue::Bool(true)),
+                26 => Ok(DocValue::Number(OrderedFloat(self.read_float32()?))),
+                other => bail!("CBOR scriptdata: unsupported simple value {} at byte {}", other, tag_start)
+            },
+            other => bail!("CBOR scriptdata: unsupported major type {} at byte {}", other, tag_start)
+        }
+    }
+
+    fn read_tagged(&mut self, tag: u64, tag_start: usize) -> anyhow::Result<DocValue> {
+        match tag {
+            TAG_IDSTRING => {
+                let (major, value) = self.read_head()?;
+                if major != 0 { bail!("CBOR scriptdata: idstring tag at byte {} must wrap a uint", tag_start); }
+                Ok(DocValue::IdString(IdString(value)))
+            },
+            TAG_VECTOR => {
+                let (major, len) = self.read_head()?;
+                if major != 4 || len != 3 { bail!("CBOR scriptdata: vector tag at byte {} must wrap a 3-element array", tag_start); }
+                Ok(DocValue::Vector(Vector {
+                    x: OrderedFloat(self.read_float32()?),
+                    y: OrderedFloat(self.read_float32()?),
+                    z: OrderedFloat(self.read_float32()?),
+                }))
+            },
+            TAG_QUATERNION => {
+                let (major, len) = self.read_head()?;
+                if major != 4 || len != 4 { bail!("CBOR scriptdata: quaternion tag at byte {} must wrap a 4-element array", tag_start); }
+                Ok(DocValue::Quaternion(Quaternion {
+                    x: OrderedFloat(self.read_float32()?),
+                    y: OrderedFloat(self.read_float32()?),
+                    z: OrderedFloat(self.read_float32()?),
+                    w: OrderedFloat(self.read_float32()?),
+                }))
+            },
+            TAG_SHAREABLE => {
+                let index = self.shared.len();
+                let cell = RcCell::<DocTable>::default();
+                self.shared.push(cell.clone());
+                let (major, value) = self.read_head()?;
+                if major != 5 { bail!("CBOR scriptdata: shareable tag at byte {} must wrap a map", tag_start); }
+                self.read_table_body(value as usize, Some((index, cell)))
+                    .map(DocValue::Table)
+            },
+            TAG_SHARED_REF => {
+                let (major, index) = self.read_head()?;
+                if major != 0 { bail!("CBOR scriptdata: shared-ref tag at byte {} must wrap a uint", tag_start); }
+                let cell = self.shared.get(index as usize)
+                    .ok_or_else(|| anyhow!("CBOR scriptdata: shared-ref {} at byte {} used before it was marked", index, tag_start))?;
+                Ok(DocValue::Table(cell.clone()))
+            },
+            other => bail!("CBOR scriptdata: unrecognised tag {} at byte {}", other, tag_start)
+        }
+    }
+
+    /// Reads a map's `count` key/value pairs as a table body, recognising
+    /// the reserved `b"meta"` byte-string key. `shared` is `Some((index,
+    /// cell))` when this table was already registered (and pushed to
+    /// `self.shared`) by [`Reader::read_tagged`]'s `TAG_SHAREABLE` arm, so
+    /// cyclic references to it resolve correctly while it's still being
+    /// filled in; otherwise a fresh cell is used.
+    fn read_table_body(&mut self, count: usize, shared: Option<(usize, RcCell<DocTable>)>) -> anyhow::Result<RcCell<DocTable>> {
+        let cell = match shared {
+            Some((_, cell)) => cell,
+            None => RcCell::<DocTable>::default()
+        };
+
+        for _ in 0..count {
+            let key_start = self.pos;
+            let (major, value) = self.read_head()?;
+            if major == 2 {
+                let bytes = self.read_bytes(value as usize)?;
+                if bytes != b"meta" {
+                    bail!("CBOR scriptdata: unexpected byte-string key at byte {}", key_start);
+                }
+                let (text_major, text_len) = self.read_head()?;
+                if text_major != 3 { bail!("CBOR scriptdata: \"meta\" entry at byte {} must be a text string", key_start); }
+                let mt = self.read_text(text_len as usize)?;
+                cell.borrow_mut().set_metatable(Some(mt));
+                continue;
+            }
+
+            self.pos = key_start;
+            let key = self.read_item()?;
+            let value = self.read_item()?;
+            cell.borrow_mut().insert(key, value);
+        }
+
+        Ok(cell)
+    }
+}
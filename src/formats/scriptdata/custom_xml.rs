@@ -31,11 +31,19 @@
 //! numbers or strings, and will ignore any numeric keys which are outside
 //! the array-like range or aren't an integer. If a table has `_meta` then
 //! its name actually overrides the key.
+//!
+//! Plain `dump`/`load` are lossy: a string that happens to read as `true`,
+//! a number, or a vector comes back from `load` as that other kind, not as
+//! a string. [`dump_typed`]/[`load_typed`] fix this by tagging scalars with
+//! their real kind: a `_type` attribute on `value_node`, or a sibling
+//! `_type:name` attribute next to a table attribute named `name`. Non-string
+//! kinds are always tagged in typed mode, so a consumer never has to guess;
+//! a string is only tagged when leaving it untagged would make it come back
+//! as something else, so a plain `"hello"` stays untagged.
 
 use std::fmt;
 use std::fmt::Write;
 use std::str::FromStr;
-use std::rc::Rc;
 
 use anyhow::{anyhow, bail};
 use fnv::{FnvHashMap, FnvHashSet};
@@ -43,13 +51,26 @@ use roxmltree;
 use xmlwriter::XmlWriter;
 
 use crate::util::rc_cell::*;
-use super::document::{Document, DocTable, DocValue};
+use super::document::{AtomId, Document, DocTable, DocValue};
 use super::id_tracker::*;
 
 pub fn dump(doc: &Document) -> String {
+    dump_impl(doc, false)
+}
+
+/// Like [`dump`], but tags scalars with `_type` attributes so [`load_typed`]
+/// can recover them exactly; see the module documentation for which
+/// scalars get tagged.
+pub fn dump_typed(doc: &Document) -> String {
+    dump_impl(doc, true)
+}
+
+fn dump_impl(doc: &Document, typed: bool) -> String {
     let mut state = DumperState {
+        doc,
         writer: XmlWriter::new(xmlwriter::Options::default()),
-        id_tracker: IdTracker::new(doc)
+        id_tracker: IdTracker::new(doc),
+        typed
     };
 
     match doc.root() {
@@ -60,18 +81,25 @@ pub fn dump(doc: &Document) -> String {
     state.end()
 }
 
-struct DumperState {
+struct DumperState<'d> {
+    doc: &'d Document,
     writer: XmlWriter,
-    id_tracker: IdTracker
+    id_tracker: IdTracker,
+    typed: bool
 }
 
-impl DumperState {
+impl DumperState<'_> {
     fn write_item_element(&mut self, val: DocValue) {
         match val {
             DocValue::Table(tab) => self.write_table_element_named(None, tab),
             _ => {
                 self.writer.start_element("value_node");
-                self.writer.write_attribute("value", &ScalarValueString(val));
+                if self.typed {
+                    if let Some(kind) = type_marker_for(self.doc, &val) {
+                        self.writer.write_attribute("_type", kind);
+                    }
+                }
+                self.writer.write_attribute("value", &ScalarValueString(self.doc, val));
                 self.writer.end_element();
             }
         }
@@ -80,7 +108,7 @@ impl DumperState {
     fn write_table_element_named(&mut self, name: Option<&str>, table: RcCell<DocTable>) {
         let tr = table.borrow();
         match tr.get_metatable() {
-            Some(s) => self.writer.start_element(&s),
+            Some(s) => self.writer.start_element(&self.doc.resolve_rc(s)),
             None => self.writer.start_element(name.unwrap_or("table"))
         };
 
@@ -108,18 +136,26 @@ impl DumperState {
             if let DocValue::String(k) = key {
                 match value {
                     DocValue::Table(_) => (),
-                    _ => self.writer.write_attribute(k, &ScalarValueString(value.clone()))
+                    _ => {
+                        let k = self.doc.resolve_rc(*k);
+                        if self.typed {
+                            if let Some(kind) = type_marker_for(self.doc, value) {
+                                self.writer.write_attribute(&format!("_type:{}", k), kind);
+                            }
+                        }
+                        self.writer.write_attribute(&k, &ScalarValueString(self.doc, value.clone()))
+                    }
                 }
             }
         }
 
-        let mut seen_keys = FnvHashSet::<Rc<str>>::default();
+        let mut seen_keys = FnvHashSet::<AtomId>::default();
 
         for (_, value) in table.ipairs() {
             self.write_item_element(value.clone());
             if let DocValue::Table(vt) = value {
                 match vt.borrow().get_metatable(){
-                    Some(mt) => seen_keys.insert(mt.clone()),
+                    Some(mt) => seen_keys.insert(mt),
                     None => false
                 };
             }
@@ -130,8 +166,8 @@ impl DumperState {
                 if seen_keys.contains(k) { continue; }
                 match value {
                     DocValue::Table(tab) => {
-                        self.write_table_element_named(Some(k), tab.clone());
-                        seen_keys.insert(tab.borrow().get_metatable().unwrap_or(k.clone()));
+                        self.write_table_element_named(Some(&self.doc.resolve_rc(*k)), tab.clone());
+                        seen_keys.insert(tab.borrow().get_metatable().unwrap_or(*k));
                     }
                     _ => ()
                 }
@@ -144,17 +180,35 @@ impl DumperState {
     }
 }
 
-struct ScalarValueString(DocValue);
-impl fmt::Display for ScalarValueString {
+struct ScalarValueString<'d>(&'d Document, DocValue);
+impl fmt::Display for ScalarValueString<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &self.0 {
+        match &self.1 {
             DocValue::Table(_)      => panic!("Tried to convert a table to a string using scalar rules."),
             DocValue::Bool(b)       => write!(f, "{}", b),
             DocValue::IdString(ids) => write!(f, "@ID{}@", ids),
             DocValue::Number(n)     => write!(f, "{}", n.0),
             DocValue::Quaternion(n) => write!(f, "{} {} {} {}", n.x, n.y, n.z, n.w),
             DocValue::Vector(n)     => write!(f, "{} {} {}", n.x, n.y, n.z),
-            DocValue::String(s)     => write!(f, "{}", s)
+            DocValue::String(s)     => write!(f, "{}", self.0.resolve(*s))
+        }
+    }
+}
+
+/// The name [`dump_typed`] gives a value's kind in a `_type` attribute, or
+/// `None` if the value doesn't need one because it already comes back as
+/// the right kind without help.
+fn type_marker_for(doc: &Document, val: &DocValue) -> Option<&'static str> {
+    match val {
+        DocValue::Table(_) => None,
+        DocValue::Bool(_) => Some("bool"),
+        DocValue::Number(_) => Some("number"),
+        DocValue::IdString(_) => Some("idstring"),
+        DocValue::Vector(_) => Some("vector"),
+        DocValue::Quaternion(_) => Some("quaternion"),
+        DocValue::String(s) => match classify_scalar(doc.resolve(*s)) {
+            ScalarKind::String(_) => None,
+            _ => Some("string")
         }
     }
 }
@@ -164,17 +218,44 @@ enum LoadError {
     NoValue,
     SpuriousAttribute,
     SpuriousContent,
-    DanglingRef,
+    DanglingRef(String),
     DuplicateId,
     RootIsRef,
-    RootIsBroken
+    RootIsBroken,
+    BadTypeHint
+}
+
+impl LoadError {
+    fn message(&self) -> String {
+        match self {
+            LoadError::NoValue => "value_node is missing its `value` attribute".to_owned(),
+            LoadError::SpuriousAttribute => "unexpected extra attribute".to_owned(),
+            LoadError::SpuriousContent => "unexpected child content".to_owned(),
+            LoadError::DanglingRef(id) => format!("unknown ref id \"{}\"", id),
+            LoadError::DuplicateId => "duplicate _id".to_owned(),
+            LoadError::RootIsRef => "root element can't be a _ref".to_owned(),
+            LoadError::RootIsBroken => "root element failed to parse".to_owned(),
+            LoadError::BadTypeHint => "value doesn't match its _type hint".to_owned()
+        }
+    }
 }
 
 pub fn load(src: &str) -> anyhow::Result<Document> {
+    load_impl(src, false)
+}
+
+/// Like [`load`], but honours `_type`/`_type:name` attributes written by
+/// [`dump_typed`], so a scalar that would otherwise auto-detect to the
+/// wrong kind comes back the way it went in.
+pub fn load_typed(src: &str) -> anyhow::Result<Document> {
+    load_impl(src, true)
+}
+
+fn load_impl(src: &str, typed: bool) -> anyhow::Result<Document> {
     match roxmltree::Document::parse(src) {
         Err(e) => bail!(e),
         Ok(in_doc) => {
-            let mut loader = Loader::new(&in_doc);
+            let mut loader = Loader::new(src, &in_doc, typed);
             loader.parse_everything();
             loader.finish()
         }
@@ -194,23 +275,27 @@ enum ParseNode<'a> {
 }
 
 struct Loader<'input> {
+    src: &'input str,
     source_doc: &'input roxmltree::Document<'input>,
     output_doc: Document,
     pending_refs: FnvHashMap<&'input str, Vec<PendingRef>>,
     refs: FnvHashMap<&'input str, RcCell<DocTable>>,
     errors: Vec<(LoadError, usize)>,
-    current_place: Option<(RcCell<DocTable>, DocValue)>
+    current_place: Option<(RcCell<DocTable>, DocValue)>,
+    typed: bool
 }
 
 impl<'a> Loader<'a> {
-    fn new(source_doc: &'a roxmltree::Document<'a>) -> Loader<'a> {
+    fn new(src: &'a str, source_doc: &'a roxmltree::Document<'a>, typed: bool) -> Loader<'a> {
         Loader {
+            src,
             source_doc,
             output_doc: Document::new(),
             pending_refs: FnvHashMap::default(),
             refs: FnvHashMap::default(),
             errors: Vec::new(),
-            current_place: None
+            current_place: None,
+            typed
         }
     }
 
@@ -247,7 +332,9 @@ impl<'a> Loader<'a> {
     }
 
     fn parse_value_node(&mut self, node: roxmltree::Node) -> DocValue {
-        if node.attributes().len() > 1 {
+        let type_hint = if self.typed { node.attribute("_type") } else { None };
+        let max_attrs = if type_hint.is_some() { 2 } else { 1 };
+        if node.attributes().len() > max_attrs {
             self.errors.push((LoadError::SpuriousAttribute, node.range().start));
             return DocValue::Bool(false);
         }
@@ -256,7 +343,13 @@ impl<'a> Loader<'a> {
             return DocValue::Bool(false);
         }
         if let Some(val) = node.attribute("value") {
-            return parse_scalar(&mut self.output_doc, val);
+            return match parse_scalar_typed(&mut self.output_doc, val, type_hint) {
+                Some(dv) => dv,
+                None => {
+                    self.errors.push((LoadError::BadTypeHint, node.range().start));
+                    DocValue::Bool(false)
+                }
+            };
         }
         else {
             self.errors.push((LoadError::NoValue, node.range().start));
@@ -288,10 +381,19 @@ impl<'a> Loader<'a> {
             let mut tab = tabr.borrow_mut();
 
             if node.tag_name().name() != "table" {
-                let mt = self.output_doc.cache_string(node.tag_name().name());
+                let mt = self.output_doc.intern(node.tag_name().name());
                 tab.set_metatable(Some(mt));
             }
 
+            let type_hints: FnvHashMap<&str, &str> = if self.typed {
+                node.attributes().iter()
+                    .filter_map(|a| a.name().strip_prefix("_type:").map(|n| (n, a.value())))
+                    .collect()
+            }
+            else {
+                FnvHashMap::default()
+            };
+
             for attr in node.attributes() {
                 if attr.name() == "_id" {
                     if self.refs.contains_key(attr.value()) {
@@ -303,8 +405,17 @@ impl<'a> Loader<'a> {
                     continue;
                 }
 
-                let val = parse_scalar(&mut self.output_doc, attr.value());
-                let key = self.output_doc.cache_string(attr.name());
+                if attr.name().starts_with("_type:") { continue; }
+
+                let type_hint = type_hints.get(attr.name()).copied();
+                let val = match parse_scalar_typed(&mut self.output_doc, attr.value(), type_hint) {
+                    Some(v) => v,
+                    None => {
+                        self.errors.push((LoadError::BadTypeHint, attr.range().start));
+                        DocValue::Bool(false)
+                    }
+                };
+                let key = self.output_doc.intern(attr.name());
                 tab.insert(DocValue::from(key), val);
             }
 
@@ -312,7 +423,7 @@ impl<'a> Loader<'a> {
             for n in node.children().filter(|n| n.is_element()) {
                 let key_n = DocValue::from(idx);
                 let key_s = if n.tag_name().name() != "table" {
-                    Some(DocValue::from(self.output_doc.cache_string(n.tag_name().name())))
+                    Some(DocValue::from(self.output_doc.intern(n.tag_name().name())))
                 }
                 else {
                     None
@@ -341,50 +452,112 @@ impl<'a> Loader<'a> {
     }
 
     fn finish(mut self) -> anyhow::Result<Document> {
-        if self.errors.len() == 0 {
+        for (refname, pends) in &self.pending_refs {
+            for pr in pends {
+                self.errors.push((LoadError::DanglingRef((*refname).to_owned()), pr.position));
+            }
+        }
+
+        if self.errors.is_empty() {
             self.output_doc.gc();
             return Ok(self.output_doc);
         }
 
-        let mut errmsg = String::from("Generic_xml document has bad structure:\n");
-        for (err, pos) in self.errors {
-            match write!(errmsg, "    {:?} at {}", err, self.source_doc.text_pos_at(pos)) {
-                Ok(_) => (),
-                Err(_) => panic!("Somehow failed to build a list of error messages. SOMEHOW.")
-            }
+        let mut errmsg = String::from("custom_xml document has bad structure:\n\n");
+        for (err, pos) in &self.errors {
+            let diag = super::diagnostics::Diagnostic::at(err.message(), *pos);
+            let _ = writeln!(errmsg, "{}\n", super::diagnostics::render_text(self.src, &diag));
         }
         Err(anyhow!(errmsg))
     }
 }
 
-fn parse_scalar(doc: &mut Document, text: &str) -> DocValue {
+/// What [`parse_scalar`] would make of a scalar string, without needing a
+/// `Document` to intern the string case into. Split out so [`type_marker_for`]
+/// can ask "would this string come back as something other than a string?"
+/// without a `&mut Document` to hand it.
+enum ScalarKind<'a> {
+    Bool(bool),
+    IdString(crate::hashindex::Hash),
+    Number(f32),
+    Vector([f32; 3]),
+    Quaternion([f32; 4]),
+    String(&'a str)
+}
+
+fn classify_scalar(text: &str) -> ScalarKind {
     if text == "true" {
-        return DocValue::Bool(true)
+        return ScalarKind::Bool(true)
     }
 
     if text == "false" {
-        return DocValue::Bool(false)
+        return ScalarKind::Bool(false)
     }
 
     if text.starts_with("@ID") && text.ends_with("@") {
         let hex = &text[3..(text.len()-1)];
         if let Ok(val) = u64::from_str_radix(hex, 16) {
-            return DocValue::IdString(crate::hashindex::Hash(val));
+            return ScalarKind::IdString(crate::hashindex::Hash(val));
         }
     }
 
     if let Ok(val) = f32::from_str(text) {
-        return DocValue::from(val);
+        return ScalarKind::Number(val);
     }
 
     if let Ok(parts) = text.splitn(4, ' ').map(f32::from_str).collect::<Result<Vec<_>,_>>() {
         if parts.len() == 3 {
-            return DocValue::from((parts[0], parts[1], parts[2]));
+            return ScalarKind::Vector([parts[0], parts[1], parts[2]]);
         }
         if parts.len() == 4 {
-            return DocValue::from((parts[0], parts[1], parts[2], parts[3]));
+            return ScalarKind::Quaternion([parts[0], parts[1], parts[2], parts[3]]);
         }
     }
 
-    return DocValue::String(doc.cache_string(text));
+    ScalarKind::String(text)
+}
+
+fn parse_scalar(doc: &mut Document, text: &str) -> DocValue {
+    match classify_scalar(text) {
+        ScalarKind::Bool(b) => DocValue::Bool(b),
+        ScalarKind::IdString(h) => DocValue::IdString(h),
+        ScalarKind::Number(n) => DocValue::from(n),
+        ScalarKind::Vector(v) => DocValue::from((v[0], v[1], v[2])),
+        ScalarKind::Quaternion(q) => DocValue::from((q[0], q[1], q[2], q[3])),
+        ScalarKind::String(s) => DocValue::String(doc.intern(s))
+    }
+}
+
+/// As [`parse_scalar`], but when `type_hint` is `Some`, that type is forced
+/// instead of auto-detecting - the counterpart to the `_type`/`_type:name`
+/// attributes [`dump_typed`] writes. Returns `None` if `text` doesn't
+/// actually parse as the hinted type.
+fn parse_scalar_typed(doc: &mut Document, text: &str, type_hint: Option<&str>) -> Option<DocValue> {
+    match type_hint {
+        None => Some(parse_scalar(doc, text)),
+        Some("string") => Some(DocValue::String(doc.intern(text))),
+        Some("bool") => match text {
+            "true" => Some(DocValue::Bool(true)),
+            "false" => Some(DocValue::Bool(false)),
+            _ => None
+        },
+        Some("number") => f32::from_str(text).ok().map(DocValue::from),
+        Some("idstring") => {
+            let hex = text.strip_prefix("@ID").and_then(|s| s.strip_suffix('@')).unwrap_or(text);
+            u64::from_str_radix(hex, 16).ok().map(|v| DocValue::IdString(crate::hashindex::Hash(v)))
+        },
+        Some("vector") => {
+            match text.splitn(3, ' ').map(f32::from_str).collect::<Result<Vec<_>,_>>() {
+                Ok(p) if p.len() == 3 => Some(DocValue::from((p[0], p[1], p[2]))),
+                _ => None
+            }
+        },
+        Some("quaternion") => {
+            match text.splitn(4, ' ').map(f32::from_str).collect::<Result<Vec<_>,_>>() {
+                Ok(p) if p.len() == 4 => Some(DocValue::from((p[0], p[1], p[2], p[3]))),
+                _ => None
+            }
+        },
+        Some(_) => None
+    }
 }
\ No newline at end of file
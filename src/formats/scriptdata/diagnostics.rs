@@ -0,0 +1,99 @@
+//! Small, dependency-free renderer for turning a byte offset/range in a
+//! scriptdata source into a labelled source snippet, in the spirit of
+//! `codespan-reporting`/rustc's own diagnostics: a line of source with a
+//! caret run under the offending bit, or (for the binary format, where a
+//! "line" doesn't mean anything) a hex-dump excerpt with the same bytes
+//! underlined instead. Used by [`super::custom_xml`], [`super::generic_xml`]
+//! and [`super::binary`] so a malformed file reports what's wrong and where,
+//! rather than an opaque panic or a `{:?}` dump of raw error values.
+
+use std::fmt::Write;
+use std::ops::Range;
+
+/// The byte range in the original source a [`Diagnostic`] is anchored to.
+/// Never empty - a single offending byte/character is still a one-long
+/// range - so [`render_text`]/[`render_binary`] always have something to
+/// underline.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Range<usize>
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>, span: Range<usize>) -> Diagnostic {
+        let span = if span.is_empty() { span.start..(span.start + 1) } else { span };
+        Diagnostic { message: message.into(), span }
+    }
+
+    /// A diagnostic anchored to a single byte offset, for errors (like "bad
+    /// tag byte") that don't have a more specific range to point at.
+    pub fn at(message: impl Into<String>, offset: usize) -> Diagnostic {
+        Diagnostic::new(message, offset..(offset + 1))
+    }
+}
+
+/// Renders `diag` against text source `src`: the line containing the span,
+/// a caret run underneath it, and the message - the same shape
+/// codespan-reporting/rustc use, just without the dependency.
+pub fn render_text(src: &str, diag: &Diagnostic) -> String {
+    let offset = diag.span.start.min(src.len());
+    let line_start = src[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = src[offset..].find('\n').map(|i| offset + i).unwrap_or(src.len());
+    let line_no = src[..line_start].matches('\n').count() + 1;
+    let col = offset - line_start;
+    let span_len = diag.span.end.min(line_end).saturating_sub(offset).max(1);
+
+    let gutter = format!("{} | ", line_no);
+    let mut out = String::new();
+    let _ = writeln!(out, "{}{}", gutter, &src[line_start..line_end]);
+    let _ = writeln!(out, "{}{}{}", " ".repeat(gutter.len()), " ".repeat(col), "^".repeat(span_len));
+    let _ = write!(out, "{}{}", " ".repeat(gutter.len()), diag.message);
+    out
+}
+
+/// Renders `diag` against raw binary `data` as a hex-dump excerpt: 16 bytes
+/// per row (offset gutter, hex, ASCII column), centred on the row
+/// containing `diag.span.start` with a row of context on either side, and
+/// a caret run under the hex column(s) the span actually covers.
+pub fn render_binary(data: &[u8], diag: &Diagnostic) -> String {
+    const COLS: usize = 16;
+
+    let start = diag.span.start.min(data.len());
+    let span_len = (diag.span.end - diag.span.start).max(1);
+    let center_row = start / COLS;
+    let first_row = center_row.saturating_sub(1);
+    let last_row = (start + span_len - 1) / COLS + 2;
+    let last_row = last_row.min((data.len() + COLS - 1) / COLS).max(first_row + 1);
+
+    let mut out = String::new();
+    for row in first_row..last_row {
+        let row_start = row * COLS;
+        let row_end = (row_start + COLS).min(data.len());
+        let row_bytes = &data[row_start..row_end];
+
+        let _ = write!(out, "{:08x} | ", row_start);
+        for (i, b) in row_bytes.iter().enumerate() {
+            let _ = write!(out, "{:02x} ", b);
+            if i == 7 { out.push(' '); }
+        }
+        for _ in row_bytes.len()..COLS {
+            out.push_str("   ");
+        }
+        out.push_str(" |");
+        for b in row_bytes {
+            out.push(if b.is_ascii_graphic() { *b as char } else { '.' });
+        }
+        out.push('\n');
+
+        if row_start <= diag.span.start && diag.span.start < row_start + COLS {
+            let col_start = diag.span.start - row_start;
+            let col_end = diag.span.end.min(row_start + COLS) - row_start;
+            let _ = write!(out, "{}", " ".repeat(11 + col_start * 3 + (col_start / 8)));
+            out.push_str(&"^^^".repeat(col_end - col_start).trim_end());
+            out.push('\n');
+        }
+    }
+    let _ = write!(out, "{}", diag.message);
+    out
+}
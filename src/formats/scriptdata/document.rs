@@ -9,31 +9,129 @@ use crate::hashindex::{Hash as IdString};
 use crate::util::ordered_float::OrderedFloat;
 use crate::util::rc_cell::{RcCell, WeakCell};
 
+/// A handle into a [`Document`]'s [`AtomTable`]. Cheap to copy and compare -
+/// `DocValue::String` and `DocTable`'s metatable hold one of these instead
+/// of an `Rc<str>`, so comparing and hashing strings is an integer
+/// operation rather than a byte walk. Only valid for the `Document` whose
+/// table produced it.
+#[derive(Copy, Clone, PartialOrd, PartialEq, Ord, Eq, Debug, Hash)]
+pub struct AtomId(pub u32);
+
+/// Interns strings to [`AtomId`] handles, deduplicating by content so equal
+/// strings always resolve to the same id.
+#[derive(Default)]
+pub struct AtomTable {
+    strings: Vec<Rc<str>>,
+    lookup: FnvHashMap<Rc<str>, u32>
+}
+impl AtomTable {
+    pub fn new() -> AtomTable { AtomTable::default() }
+
+    pub fn intern(&mut self, input: &str) -> AtomId {
+        if let Some(&id) = self.lookup.get(input) {
+            return AtomId(id);
+        }
+        let rcs: Rc<str> = Rc::from(input);
+        let id = self.strings.len() as u32;
+        self.strings.push(rcs.clone());
+        self.lookup.insert(rcs, id);
+        AtomId(id)
+    }
+
+    /// Like [`AtomTable::intern`], but takes an already-allocated `Rc<str>`
+    /// and reuses it instead of copying `input` into a fresh one - for a
+    /// caller (e.g. [`binary::from_binary`](super::binary::from_binary)'s
+    /// `Interner` support) that already has one shared across documents.
+    pub fn intern_rc(&mut self, input: Rc<str>) -> AtomId {
+        if let Some(&id) = self.lookup.get(input.as_ref()) {
+            return AtomId(id);
+        }
+        let id = self.strings.len() as u32;
+        self.strings.push(input.clone());
+        self.lookup.insert(input, id);
+        AtomId(id)
+    }
+
+    pub fn resolve(&self, id: AtomId) -> &str {
+        &self.strings[id.0 as usize]
+    }
+
+    pub fn resolve_rc(&self, id: AtomId) -> Rc<str> {
+        self.strings[id.0 as usize].clone()
+    }
+}
+
 pub struct Document {
     root_value: Option<DocValue>,
-    string_cache: HashSet<Rc<str>>
+    atoms: AtomTable,
+    idstring_names: FnvHashMap<IdString, Rc<str>>
 }
 impl Document {
     pub fn new() -> Document {
         Document {
             root_value: None,
-            string_cache: HashSet::new()
+            atoms: AtomTable::new(),
+            idstring_names: FnvHashMap::default()
         }
     }
 
-    pub fn cache_string(&mut self, input: &str) -> Rc<str> {
-        if let Some(s) = self.string_cache.get(input) {
-            return s.clone();
-        } 
-        else {
-            let rcs: Rc<str> = Rc::from(input);
-            self.string_cache.insert(rcs.clone());
-            return rcs;
-        }
+    pub fn intern(&mut self, input: &str) -> AtomId {
+        self.atoms.intern(input)
+    }
+
+    pub fn intern_rc(&mut self, input: Rc<str>) -> AtomId {
+        self.atoms.intern_rc(input)
+    }
+
+    pub fn resolve(&self, id: AtomId) -> &str {
+        self.atoms.resolve(id)
+    }
+
+    pub fn resolve_rc(&self, id: AtomId) -> Rc<str> {
+        self.atoms.resolve_rc(id)
+    }
+
+    /// Records the human-readable name a hashlist resolved `id` to, without
+    /// discarding `id` itself - callers that only have the raw hash (no
+    /// hashlist was supplied, or this particular one wasn't in it) still get
+    /// a usable [`DocValue::IdString`], this is purely additional.
+    pub fn set_idstring_name(&mut self, id: IdString, name: Rc<str>) {
+        self.idstring_names.insert(id, name);
     }
 
+    /// The name a hashlist resolved `id` to, if any was supplied to the
+    /// loader and it knew about this hash.
+    pub fn idstring_name(&self, id: IdString) -> Option<Rc<str>> {
+        self.idstring_names.get(&id).cloned()
+    }
+
+    /// Compact the atom table to just the strings still reachable from the
+    /// root, remapping every live [`AtomId`] in place. Unlike the old
+    /// `Rc<str>`-backed cache, a bare `u32` handle carries no refcount of
+    /// its own, so the only way to know what's still live is to walk the
+    /// tree and ask.
     pub fn gc(&mut self) {
-        self.string_cache.retain(|item| Rc::strong_count(item) > 1);
+        let mut used = FnvHashSet::<u32>::default();
+        if let Some(root) = &self.root_value {
+            collect_atoms(root, &mut used);
+        }
+
+        let mut remap = FnvHashMap::<u32, u32>::default();
+        let mut strings = Vec::with_capacity(used.len());
+        let mut lookup = FnvHashMap::default();
+        for (old_id, s) in self.atoms.strings.iter().enumerate() {
+            if used.contains(&(old_id as u32)) {
+                let new_id = strings.len() as u32;
+                remap.insert(old_id as u32, new_id);
+                strings.push(s.clone());
+                lookup.insert(s.clone(), new_id);
+            }
+        }
+        self.atoms = AtomTable { strings, lookup };
+
+        if let Some(root) = self.root_value.take() {
+            self.root_value = Some(remap_atoms(root, &remap, &mut FnvHashMap::default()));
+        }
     }
 
     pub fn root(&self) -> Option<DocValue> {
@@ -60,6 +158,239 @@ impl Document {
             .collect();
         return result;
     }
+
+    /// Collapse structurally-identical tables reachable from the root into
+    /// a single shared table: the same hash-consing trick a lot of
+    /// normalisers use on their value trees. Two tables are identical if
+    /// they have the same metatable and the same set of key/value pairs,
+    /// recursing into child tables post-order so a child is only ever
+    /// compared once it's already been canonicalised itself.
+    ///
+    /// This is worth doing before writing a document out: `binary_transfer`
+    /// and `cbor` both detect and share tables by reference
+    /// ([`Document::tables_used_repeatedly`]), so merging duplicate tables
+    /// here makes that sharing catch structural duplicates too, not just
+    /// tables that already happened to be the same `Rc`.
+    ///
+    /// A table that's still being visited when it's reached again (i.e. a
+    /// cycle) is left exactly where it is: its identity stands in for its
+    /// hash, which can't collide with anything else, so the walk
+    /// terminates without ever merging cyclic structure into something it
+    /// only resembles.
+    pub fn dedup_tables(&mut self) {
+        let root = match &self.root_value {
+            Some(r) => r.clone(),
+            None => return
+        };
+
+        let mut canonical = FnvHashMap::<WeakCell<DocTable>, RcCell<DocTable>>::default();
+        let mut by_hash = FnvHashMap::<StructuralHash, Vec<RcCell<DocTable>>>::default();
+        let mut in_progress = FnvHashSet::<WeakCell<DocTable>>::default();
+
+        let new_root = canonicalize_value(&root, &mut canonical, &mut by_hash, &mut in_progress);
+        self.root_value = Some(new_root);
+    }
+
+    /// Visit every distinct table reachable from the root exactly once, in
+    /// depth-first order, regardless of how many places reference it or
+    /// whether it's part of a cycle. This is the cycle-safe traversal
+    /// [`reference_index`](Self::reference_index) is built on, and the same
+    /// walk [`gc`](Self::gc) uses to find live atoms; callers that just need
+    /// "every table once" can use it directly instead of re-deriving it from
+    /// a [`table_refcounts`](Self::table_refcounts) map.
+    pub fn walk_tables<F: FnMut(&RcCell<DocTable>)>(&self, mut visit: F) {
+        let mut seen = FnvHashSet::<WeakCell<DocTable>>::default();
+        if let Some(root) = self.root() {
+            walk_value(&root, &mut seen, &mut visit);
+        }
+    }
+
+    /// Build a reverse index from each table reachable from the root to
+    /// every `(table, key)` pair elsewhere in the document whose value
+    /// points at it. This is what a writer needs to decide which tables are
+    /// aliased (and so need an explicit id/ref pair, rather than being
+    /// written out inline) without re-walking the document once per
+    /// candidate table.
+    pub fn reference_index(&self) -> FnvHashMap<WeakCell<DocTable>, Vec<(RcCell<DocTable>, DocValue)>> {
+        let mut index = FnvHashMap::<WeakCell<DocTable>, Vec<(RcCell<DocTable>, DocValue)>>::default();
+        self.walk_tables(|table| {
+            for (k, v) in &*table.borrow() {
+                if let DocValue::Table(target) = v {
+                    index.entry(target.downgrade()).or_insert_with(Vec::new).push((table.clone(), k.clone()));
+                }
+            }
+        });
+        index
+    }
+}
+
+fn collect_atoms(value: &DocValue, used: &mut FnvHashSet<u32>) {
+    let mut seen = FnvHashSet::<WeakCell<DocTable>>::default();
+    walk_value(value, &mut seen, &mut |table| {
+        let borrowed = table.borrow();
+        if let Some(AtomId(id)) = borrowed.metatable {
+            used.insert(id);
+        }
+        for (k, v) in &*borrowed {
+            if let DocValue::String(AtomId(id)) = k { used.insert(*id); }
+            if let DocValue::String(AtomId(id)) = v { used.insert(*id); }
+        }
+    });
+    if let DocValue::String(AtomId(id)) = value {
+        used.insert(*id);
+    }
+}
+
+/// Rebuild `value`, remapping every atom id it (or a table it contains)
+/// holds through `remap`. Tables are rebuilt post-order and memoised by
+/// original identity, same as [`canonicalize_table`], so a table reachable
+/// through several paths - or a cycle - is only rebuilt once.
+fn remap_atoms(value: DocValue, remap: &FnvHashMap<u32, u32>, rebuilt: &mut FnvHashMap<WeakCell<DocTable>, RcCell<DocTable>>) -> DocValue {
+    match value {
+        DocValue::String(AtomId(id)) => DocValue::String(AtomId(*remap.get(&id).unwrap_or(&id))),
+        DocValue::Table(t) => {
+            let down = t.downgrade();
+            if let Some(existing) = rebuilt.get(&down) {
+                return DocValue::Table(existing.clone());
+            }
+            let new_table = RcCell::new(DocTable::new());
+            rebuilt.insert(down, new_table.clone());
+
+            let (meta, entries) = {
+                let borrowed = t.borrow();
+                let meta = borrowed.metatable;
+                let entries: Vec<(DocValue, DocValue)> = (&*borrowed).into_iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                (meta, entries)
+            };
+
+            let mut borrowed = new_table.borrow_mut();
+            borrowed.set_metatable(meta.map(|AtomId(id)| AtomId(*remap.get(&id).unwrap_or(&id))));
+            for (k, v) in entries {
+                let k2 = remap_atoms(k, remap, rebuilt);
+                let v2 = remap_atoms(v, remap, rebuilt);
+                borrowed.insert(k2, v2);
+            }
+            drop(borrowed);
+
+            DocValue::Table(new_table)
+        },
+        other => other
+    }
+}
+
+fn walk_value<F: FnMut(&RcCell<DocTable>)>(value: &DocValue, seen: &mut FnvHashSet<WeakCell<DocTable>>, visit: &mut F) {
+    if let DocValue::Table(t) = value {
+        let down = t.downgrade();
+        if seen.insert(down) {
+            visit(t);
+            for (_, v) in &*t.borrow() {
+                walk_value(v, seen, visit);
+            }
+        }
+    }
+}
+
+/// The hash [`canonicalize_table`] buckets tables by - a digest of a
+/// table's metatable and its `(key, value)` entries (with child tables
+/// already canonicalised, so it's really a digest of *content*, not of the
+/// `Rc`s involved). Two tables can collide here without being equal, which
+/// is why `by_hash`'s buckets still get an exact [`table_contents_equal`]
+/// check before anything is merged.
+type StructuralHash = u64;
+
+/// Recurse into `value`, returning it unchanged unless it's a table, in
+/// which case the table (and everything under it) is canonicalised first.
+fn canonicalize_value(
+    value: &DocValue,
+    canonical: &mut FnvHashMap<WeakCell<DocTable>, RcCell<DocTable>>,
+    by_hash: &mut FnvHashMap<StructuralHash, Vec<RcCell<DocTable>>>,
+    in_progress: &mut FnvHashSet<WeakCell<DocTable>>
+) -> DocValue {
+    match value {
+        DocValue::Table(t) => DocValue::Table(canonicalize_table(t, canonical, by_hash, in_progress)),
+        other => other.clone()
+    }
+}
+
+fn canonicalize_table(
+    table: &RcCell<DocTable>,
+    canonical: &mut FnvHashMap<WeakCell<DocTable>, RcCell<DocTable>>,
+    by_hash: &mut FnvHashMap<StructuralHash, Vec<RcCell<DocTable>>>,
+    in_progress: &mut FnvHashSet<WeakCell<DocTable>>
+) -> RcCell<DocTable> {
+    let down = table.downgrade();
+    if let Some(existing) = canonical.get(&down) {
+        return existing.clone();
+    }
+    if in_progress.contains(&down) {
+        return table.clone();
+    }
+    in_progress.insert(down.clone());
+
+    let (meta, entries) = {
+        let borrowed = table.borrow();
+        let meta = borrowed.get_metatable();
+        let entries: Vec<(DocValue, DocValue)> = (&*borrowed).into_iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        (meta, entries)
+    };
+
+    let mut canon_entries = Vec::with_capacity(entries.len());
+    for (k, v) in &entries {
+        // Keys aren't rewritten in place: a table used as a key is already
+        // the case Diesel crashes on (see the module doc), and swapping a
+        // `HashMap` key out from under itself needs a remove+reinsert we'd
+        // rather not do for something this marginal.
+        let k2 = canonicalize_value(k, canonical, by_hash, in_progress);
+        let v2 = canonicalize_value(v, canonical, by_hash, in_progress);
+        canon_entries.push((k2, v2));
+    }
+
+    in_progress.remove(&down);
+
+    {
+        let mut borrowed = table.borrow_mut();
+        for (k, v2) in &canon_entries {
+            if let Some(slot) = borrowed.dict_like.get_mut(k) {
+                *slot = v2.clone();
+            }
+        }
+    }
+
+    let hash = hash_table_contents(&meta, &canon_entries);
+    let bucket = by_hash.entry(hash).or_insert_with(Vec::new);
+    for existing in bucket.iter() {
+        if table_contents_equal(&meta, &canon_entries, existing) {
+            let representative = existing.clone();
+            canonical.insert(down, representative.clone());
+            return representative;
+        }
+    }
+    bucket.push(table.clone());
+    canonical.insert(down, table.clone());
+    table.clone()
+}
+
+fn hash_table_contents(meta: &Option<AtomId>, entries: &[(DocValue, DocValue)]) -> StructuralHash {
+    use std::hash::{Hash, Hasher};
+
+    let mut sorted: Vec<&(DocValue, DocValue)> = entries.iter().collect();
+    sorted.sort();
+
+    let mut hasher = fnv::FnvHasher::default();
+    meta.hash(&mut hasher);
+    sorted.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn table_contents_equal(meta: &Option<AtomId>, entries: &[(DocValue, DocValue)], other: &RcCell<DocTable>) -> bool {
+    let other = other.borrow();
+    if other.get_metatable() != *meta { return false; }
+    if other.dict_like.len() != entries.len() { return false; }
+    entries.iter().all(|(k, v)| other.dict_like.get(k) == Some(v))
 }
 
 fn count_table_references(item: &DocValue, counter: &mut FnvHashMap<WeakCell<DocTable>, u32>) {
@@ -98,13 +429,13 @@ pub enum DocValue {
     Bool(bool),
     Number(OrderedFloat),
     IdString(IdString),
-    String(Rc<str>),
+    String(AtomId),
     Vector(Vector<OrderedFloat>),
     Quaternion(Quaternion<OrderedFloat>),
     Table(RcCell<DocTable>)
 }
 impl From<f32> for DocValue { fn from(src: f32) -> DocValue { DocValue::Number(OrderedFloat(src)) } }
-impl From<Rc<str>> for DocValue { fn from(src: Rc<str>) -> DocValue { DocValue::String(src)}}
+impl From<AtomId> for DocValue { fn from(src: AtomId) -> DocValue { DocValue::String(src)}}
 
 macro_rules! dv_try_from {
     ($v:ident, $t:ty) => {
@@ -132,32 +463,12 @@ macro_rules! dv_try_from {
 dv_try_from!(Bool, bool);
 dv_try_from!(Number, OrderedFloat);
 dv_try_from!(Vector, Vector<OrderedFloat>);
-dv_try_from!(String, Rc<str>);
+dv_try_from!(String, AtomId);
 dv_try_from!(Table, RcCell<DocTable>);
 
-impl std::convert::TryFrom<&DocValue> for Box<str> {
-    type Error = ();
-    fn try_from(v: &DocValue) -> Result<Box<str>, ()> {
-        match v {
-            DocValue::String(s) => Ok(Box::from(s.as_ref())),
-            _ => Err(())
-        }
-    }
-}
-
-impl std::convert::TryFrom<DocValue> for Box<str> {
-    type Error = ();
-    fn try_from(v: DocValue) -> Result<Box<str>, ()> {
-        match v {
-            DocValue::String(s) => Ok(Box::from(s.as_ref())),
-            _ => Err(())
-        }
-    }
-}
-
 #[derive(Default)]
 pub struct DocTable {
-    metatable: Option<Rc<str>>,
+    metatable: Option<AtomId>,
     dict_like: HashMap<DocValue, DocValue>,
     keys_in_order_of_add: Vec<DocValue>
 }
@@ -167,8 +478,8 @@ impl DocTable {
         self.keys_in_order_of_add.push(key.clone());
         self.dict_like.insert(key, value);
     }
-    pub fn get_metatable(&self) -> Option<Rc<str>> { self.metatable.clone() }
-    pub fn set_metatable<T: Into<Option<Rc<str>>>>(&mut self, newtable: T) {
+    pub fn get_metatable(&self) -> Option<AtomId> { self.metatable }
+    pub fn set_metatable<T: Into<Option<AtomId>>>(&mut self, newtable: T) {
         self.metatable = newtable.into();
     }
 
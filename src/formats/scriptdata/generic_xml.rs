@@ -1,57 +1,93 @@
 use std::fmt::Display;
+use std::fmt::Write;
+use std::io;
 use std::rc::Rc;
 use std::str::FromStr;
 
+use anyhow::bail;
 use fnv::{FnvHashMap, FnvHashSet};
-use xmlwriter::*;
 
-use super::document::{Document, DocTable, DocValue};
-use super::{TextEvent, SchemaError, TextParseError};
+use super::document::{Document, DocTable, DocValue, Vector, Quaternion};
+use super::{TextEvent, TextId, SchemaError, TextParseError};
+use crate::hashindex::Hash as IdString;
+use crate::util::ordered_float::OrderedFloat;
 use crate::util::rc_cell::*;
 
+/// Which text form [`dump_with`] writes a [`DocValue::IdString`] as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdStringEncoding {
+    /// The legacy plain 16-hex-digit form.
+    Legacy,
+    /// The checksummed `id1...` form - see [`super::idstring_text`].
+    Checksummed
+}
+
 pub fn dump(doc: &Document) -> String {
+    dump_with(doc, IdStringEncoding::Legacy)
+}
+
+/// As [`dump`], but with control over how idstrings are written out.
+pub fn dump_with(doc: &Document, idstring_encoding: IdStringEncoding) -> String {
+    let mut buf = Vec::new();
+    dump_to(doc, &mut buf, idstring_encoding, false).expect("writing to a Vec<u8> can't fail");
+    String::from_utf8(buf).expect("XmlSink only ever writes valid UTF-8")
+}
+
+/// As [`dump_with`], but streams straight to `out` as the document is
+/// walked instead of building the whole thing in memory first - the
+/// difference that matters for a huge world, where [`dump`]'s `String`
+/// would otherwise have to hold the entire serialized tree at once.
+/// `pretty` turns on one-indent-per-nesting-level formatting, in the style
+/// of [`crate::util::DbgMatrixF64`]'s alternate `Debug` output.
+pub fn dump_to<W: io::Write>(doc: &Document, out: &mut W, idstring_encoding: IdStringEncoding, pretty: bool) -> io::Result<()> {
+    let root = match doc.root() {
+        Some(item) => item,
+        None => return write!(out, "<generic_scriptdata type=\"nil\"/>")
+    };
+
     let mut state = DumperState {
-        writer: Writer::new(),
+        doc,
+        writer: Writer::new(out, pretty),
         diamond_subjects: doc.tables_used_repeatedly(),
         seen_ids: FnvHashMap::default(),
-        next_id: 0
+        next_id: 0,
+        idstring_encoding
     };
-    
-    let root = doc.root();
-    match root {
-        Some(item) => state.write_item(Name::Index(0), &item),
-        None => return "<generic_scriptdata type=\"nil\"/>".to_owned()
-    }
 
+    state.write_item(Name::Index(0), &root)?;
     state.writer.end_document()
 }
 
-struct DumperState {
-    writer: Writer,
+struct DumperState<'d, 'w, W: io::Write> {
+    doc: &'d Document,
+    writer: Writer<'w, W>,
     diamond_subjects: FnvHashSet<WeakCell<DocTable>>,
     seen_ids: FnvHashMap<WeakCell<DocTable>, Rc<str>>,
-    next_id: u32
+    next_id: u32,
+    idstring_encoding: IdStringEncoding
 }
-impl DumperState {
-    fn write_item(&mut self, name: Name, item: &DocValue) {
+impl<W: io::Write> DumperState<'_, '_, W> {
+    fn write_item(&mut self, name: Name, item: &DocValue) -> io::Result<()> {
         match item {
             DocValue::Bool(b) => self.writer.scalar(name, Type::Boolean, b),
             DocValue::Number(n) => self.writer.scalar(name, Type::Number, n),
-            DocValue::IdString(s) => self.writer.scalar(name, Type::IdString, s),
-            DocValue::String(s) => self.writer.scalar(name, Type::String, s),
+            DocValue::IdString(s) => match self.idstring_encoding {
+                IdStringEncoding::Legacy => self.writer.scalar(name, Type::IdString, s),
+                IdStringEncoding::Checksummed => self.writer.scalar(name, Type::IdString, super::idstring_text::encode(s.0))
+            },
+            DocValue::String(s) => self.writer.scalar(name, Type::String, self.doc.resolve(*s)),
             DocValue::Vector(v) => self.writer.scalar(name, Type::Vector, format_args!("{} {} {}", v.x, v.y, v.z)),
             DocValue::Quaternion(v) => self.writer.scalar(name, Type::Quaternion, format_args!("{} {} {} {}", v.x, v.y, v.z, v.w)),
             DocValue::Table(tr) => self.write_table(name, tr)
         }
     }
 
-    fn write_table(&mut self, name: Name, table: &RcCell<DocTable>) {
+    fn write_table(&mut self, name: Name, table: &RcCell<DocTable>) -> io::Result<()> {
         let downgraded = table.downgrade();
         if let Some(id) = self.seen_ids.get(&downgraded) {
-            self.writer.xref(name, id);
-            return;
+            return self.writer.xref(name, id);
         }
-        
+
         let id = if self.diamond_subjects.contains(&downgraded) {
             let entry = self.seen_ids.entry(downgraded);
             Some(match entry {
@@ -64,13 +100,15 @@ impl DumperState {
             })
         }
         else { None };
-        
+
         let table_ref = table.borrow();
         let tab = &*table_ref;
 
-        self.writer.start_table(name, tab.get_metatable().as_deref(), id.as_deref());
+        let metatable = tab.get_metatable().map(|m| self.doc.resolve_rc(m));
+        self.writer.start_table(name, metatable.as_deref(), id.as_deref())?;
 
         for (k, v) in tab {
+            let key_str;
             let name = match k {
                 DocValue::Number(n) => {
                     if n.0.trunc() == n.0 && n.0 >= 0.0 {
@@ -81,15 +119,16 @@ impl DumperState {
                     }
                 },
                 DocValue::String(s) => {
-                    Name::Key(s)
+                    key_str = self.doc.resolve_rc(*s);
+                    Name::Key(&key_str)
                 },
                 _ => panic!("generic_xml only supports nonnegative integers and strings as keys")
             };
 
-            self.write_item(name, v);
+            self.write_item(name, v)?;
         }
 
-        self.writer.end_entry();
+        self.writer.end_entry()
     }
 }
 
@@ -99,15 +138,93 @@ enum Type {
 enum Name<'a> { Index(usize), Key(&'a str) }
 enum Value<V: Display> { Literal(V), Ref(V), None }
 
-struct Writer {
-    w: XmlWriter,
+/// A minimal streaming XML writer, just enough for `generic_xml`'s flat
+/// elements-with-attributes-only shape - no text nodes, no namespaces.
+/// Unlike a DOM-building library, it commits each tag to `out` as soon as
+/// it's known to be complete, so the whole document is never resident at
+/// once.
+///
+/// An open element's start tag is left without its closing `>` until
+/// either a child element or [`end_element`](Self::end_element) forces the
+/// issue, which is how it decides between `<entry .../>` and
+/// `<entry ...>...</entry>` without looking ahead.
+struct XmlSink<'w, W: io::Write> {
+    out: &'w mut W,
+    /// One entry per currently-open element: its tag name, and whether its
+    /// start tag is still unclosed (no child written yet).
+    stack: Vec<(&'static str, bool)>,
+    pretty: bool,
+    wrote_decl: bool
+}
+impl<'w, W: io::Write> XmlSink<'w, W> {
+    fn new(out: &'w mut W, pretty: bool) -> XmlSink<'w, W> {
+        XmlSink { out, stack: Vec::new(), pretty, wrote_decl: false }
+    }
+
+    fn start_element(&mut self, name: &'static str) -> io::Result<()> {
+        if !self.wrote_decl {
+            writeln!(self.out, "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>")?;
+            self.wrote_decl = true;
+        }
+        self.close_start_tag()?;
+        if self.pretty && !self.stack.is_empty() {
+            write!(self.out, "\n{}", "  ".repeat(self.stack.len()))?;
+        }
+        write!(self.out, "<{}", name)?;
+        self.stack.push((name, true));
+        Ok(())
+    }
+
+    fn write_attribute(&mut self, name: &str, value: &str) -> io::Result<()> {
+        write!(self.out, " {}=\"", name)?;
+        for c in value.chars() {
+            match c {
+                '&' => write!(self.out, "&amp;")?,
+                '<' => write!(self.out, "&lt;")?,
+                '>' => write!(self.out, "&gt;")?,
+                '"' => write!(self.out, "&quot;")?,
+                _ => write!(self.out, "{}", c)?
+            }
+        }
+        write!(self.out, "\"")
+    }
+
+    fn end_element(&mut self) -> io::Result<()> {
+        let (name, pending) = self.stack.pop().expect("end_element without a matching start_element");
+        if pending {
+            write!(self.out, "/>")
+        }
+        else {
+            if self.pretty {
+                write!(self.out, "\n{}", "  ".repeat(self.stack.len()))?;
+            }
+            write!(self.out, "</{}>", name)
+        }
+    }
+
+    /// Closes this sink's innermost open start tag with `>`, if it's still
+    /// open - called just before writing anything (a child element) that
+    /// would otherwise make `<entry .../>` ambiguous with `<entry ...>`.
+    fn close_start_tag(&mut self) -> io::Result<()> {
+        if let Some(top) = self.stack.last_mut() {
+            if top.1 {
+                top.1 = false;
+                write!(self.out, ">")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Writer<'w, W: io::Write> {
+    sink: XmlSink<'w, W>,
     started: bool
 }
 
-impl Writer {
-    fn new() -> Writer {
+impl<'w, W: io::Write> Writer<'w, W> {
+    fn new(out: &'w mut W, pretty: bool) -> Writer<'w, W> {
         Writer {
-            w: XmlWriter::new(Options::default()),
+            sink: XmlSink::new(out, pretty),
             started: false
         }
     }
@@ -118,20 +235,20 @@ impl Writer {
         ty: Type,
         id: Option<&str>,
         value: Value<V>,
-    ) {
+    ) -> io::Result<()> {
         if self.started {
-            self.w.start_element("entry");
+            self.sink.start_element("entry")?;
             match name {
-                Name::Index(i) => self.w.write_attribute("index", &i),
-                Name::Key(k) => self.w.write_attribute("key", k)
+                Name::Index(i) => self.sink.write_attribute("index", &i.to_string())?,
+                Name::Key(k) => self.sink.write_attribute("key", k)?
             };
         }
         else {
-            self.w.start_element("generic_scriptdata");
+            self.sink.start_element("generic_scriptdata")?;
             self.started = true;
         }
 
-        self.w.write_attribute("type", match ty {
+        self.sink.write_attribute("type", match ty {
             Type::Table => "table",
             Type::Boolean => "boolean",
             Type::Number => "number",
@@ -139,42 +256,219 @@ impl Writer {
             Type::Vector => "vector",
             Type::IdString => "idstring",
             Type::String => "string"
-        });
+        })?;
 
-        match id {
-            Some(id) => self.w.write_attribute("_id", id),
-            None => ()
+        if let Some(id) = id {
+            self.sink.write_attribute("_id", id)?;
         }
-        match metatable {
-            Some(mt) => self.w.write_attribute("metatable", mt),
-            None => ()
+        if let Some(mt) = metatable {
+            self.sink.write_attribute("metatable", mt)?;
         }
         match value {
-            Value::Literal(lit) => self.w.write_attribute("value", &lit),
-            Value::Ref(r) => self.w.write_attribute("_ref", &r),
+            Value::Literal(lit) => self.sink.write_attribute("value", &lit.to_string())?,
+            Value::Ref(r) => self.sink.write_attribute("_ref", &r.to_string())?,
             Value::None => ()
         }
+        Ok(())
     }
-    fn end_entry(&mut self) {
-        self.w.end_element();
+    fn end_entry(&mut self) -> io::Result<()> {
+        self.sink.end_element()
     }
 
-    fn end_document(self) -> String {
-        self.w.end_document()
+    fn end_document(self) -> io::Result<()> {
+        Ok(())
     }
 
-    fn start_table(&mut self, name: Name, metatable: Option<&str>, id: Option<&str>) {
+    fn start_table(&mut self, name: Name, metatable: Option<&str>, id: Option<&str>) -> io::Result<()> {
         self.start_entry(name, metatable, Type::Table, id, Value::<&str>::None)
     }
 
-    fn scalar<V: Display>(&mut self, name: Name, ty: Type, value: V) {
-        self.start_entry(name, None, ty, None, Value::Literal(value));
-        self.end_entry();
+    fn scalar<V: Display>(&mut self, name: Name, ty: Type, value: V) -> io::Result<()> {
+        self.start_entry(name, None, ty, None, Value::Literal(value))?;
+        self.end_entry()
+    }
+
+    fn xref<V: Display>(&mut self, name: Name, target: V) -> io::Result<()> {
+        self.start_entry(name, None, Type::Table, None, Value::Ref(target))?;
+        self.end_entry()
+    }
+}
+
+/// Parse a `generic_xml` document back into a [`Document`], the inverse of
+/// [`dump`]. Built on top of [`load_events`]: the event stream is consumed
+/// by a small stack machine rather than walking the DOM a second time,
+/// since by the time an event fires its originating node no longer matters.
+pub fn load(src: &str) -> anyhow::Result<Document> {
+    let in_doc = match roxmltree::Document::parse(src) {
+        Ok(d) => d,
+        Err(e) => bail!(e)
+    };
+
+    let mut loader = Loader::new(src);
+    for event in load_events(&in_doc) {
+        match event {
+            Ok(ev) => loader.handle_event(ev),
+            Err(e) => loader.schema_errors.push(e)
+        }
+    }
+    loader.finish()
+}
+
+enum PendingKey<'a> {
+    Key(&'a str),
+    Index(u32)
+}
+
+/// A table that's been started but not yet closed, along with the key it'll
+/// be inserted under in its parent once [`TextEvent::EndTable`] arrives.
+/// `None` for the root table, which isn't inserted into anything.
+struct Frame {
+    table: RcCell<DocTable>,
+    key_in_parent: Option<DocValue>
+}
+
+struct Loader<'a> {
+    src: &'a str,
+    output_doc: Document,
+    stack: Vec<Frame>,
+    pending_key: Option<PendingKey<'a>>,
+    root: Option<DocValue>,
+    refs: FnvHashMap<&'a str, RcCell<DocTable>>,
+    pending_refs: FnvHashMap<&'a str, Vec<(RcCell<DocTable>, DocValue)>>,
+    schema_errors: Vec<TextParseError>,
+    struct_errors: Vec<&'static str>
+}
+
+impl<'a> Loader<'a> {
+    fn new(src: &'a str) -> Loader<'a> {
+        Loader {
+            src,
+            output_doc: Document::new(),
+            stack: Vec::new(),
+            pending_key: None,
+            root: None,
+            refs: FnvHashMap::default(),
+            pending_refs: FnvHashMap::default(),
+            schema_errors: Vec::new(),
+            struct_errors: Vec::new()
+        }
+    }
+
+    fn take_key(&mut self) -> DocValue {
+        match self.pending_key.take() {
+            Some(PendingKey::Key(k)) => DocValue::from(self.output_doc.intern(k)),
+            Some(PendingKey::Index(i)) => DocValue::from(i as f32),
+            None => DocValue::from(0.0f32)
+        }
     }
 
-    fn xref<V: Display>(&mut self, name: Name, target: V) {
-        self.start_entry(name, None, Type::Table, None, Value::Ref(target));
-        self.end_entry();
+    fn place_value(&mut self, value: DocValue) {
+        let parent = self.stack.last().map(|frame| frame.table.clone());
+        match parent {
+            Some(table) => {
+                let key = self.take_key();
+                table.borrow_mut().insert(key, value);
+            },
+            None => self.root = Some(value)
+        }
+    }
+
+    fn handle_event(&mut self, event: TextEvent<'a>) {
+        match event {
+            TextEvent::Key(k) => self.pending_key = Some(PendingKey::Key(k)),
+            TextEvent::Index(i) => self.pending_key = Some(PendingKey::Index(i)),
+            TextEvent::Bool(b) => self.place_value(DocValue::Bool(b)),
+            TextEvent::Number(n) => self.place_value(DocValue::from(n)),
+            TextEvent::IdString(s) => self.place_value(DocValue::IdString(IdString(s))),
+            TextEvent::String(s) => {
+                let cached = self.output_doc.intern(s);
+                self.place_value(DocValue::String(cached));
+            },
+            TextEvent::Vector(x, y, z) => self.place_value(DocValue::Vector(
+                Vector { x: OrderedFloat(x), y: OrderedFloat(y), z: OrderedFloat(z) }
+            )),
+            TextEvent::Quaternion(x, y, z, w) => self.place_value(DocValue::Quaternion(
+                Quaternion { x: OrderedFloat(x), y: OrderedFloat(y), z: OrderedFloat(z), w: OrderedFloat(w) }
+            )),
+            TextEvent::StartTable{id, meta} => {
+                let key_in_parent = if self.stack.is_empty() { None } else { Some(self.take_key()) };
+
+                let table = RcCell::<DocTable>::default();
+                if let Some(m) = meta {
+                    let cached = self.output_doc.intern(m);
+                    table.borrow_mut().set_metatable(Some(cached));
+                }
+
+                if let TextId::Str(idname) = id {
+                    if self.refs.contains_key(idname) {
+                        self.struct_errors.push("duplicate _id in generic_xml document");
+                    }
+                    else {
+                        self.refs.insert(idname, table.clone());
+                        if let Some(pends) = self.pending_refs.remove(idname) {
+                            for (source, key) in pends {
+                                source.borrow_mut().insert(key, DocValue::Table(table.clone()));
+                            }
+                        }
+                    }
+                }
+
+                self.stack.push(Frame { table, key_in_parent });
+            },
+            TextEvent::EndTable => {
+                match self.stack.pop() {
+                    Some(Frame{table, key_in_parent: Some(key)}) => {
+                        match self.stack.last() {
+                            Some(parent) => parent.table.borrow_mut().insert(key, DocValue::Table(table)),
+                            None => self.struct_errors.push("table placement inconsistency in generic_xml document")
+                        }
+                    },
+                    Some(Frame{table, key_in_parent: None}) => self.root = Some(DocValue::Table(table)),
+                    None => self.struct_errors.push("unmatched end of table in generic_xml document")
+                }
+            },
+            TextEvent::Reference(id) => {
+                match id {
+                    TextId::Str(refname) => {
+                        if let Some(target) = self.refs.get(refname).cloned() {
+                            self.place_value(DocValue::Table(target));
+                        }
+                        else {
+                            let key = self.take_key();
+                            match self.stack.last() {
+                                Some(frame) => { self.pending_refs.entry(refname).or_default().push((frame.table.clone(), key)); },
+                                None => self.struct_errors.push("root can't be a forward reference in generic_xml document")
+                            }
+                        }
+                    },
+                    _ => self.struct_errors.push("_ref without a name in generic_xml document")
+                }
+            }
+        }
+    }
+
+    fn finish(self) -> anyhow::Result<Document> {
+        if !self.schema_errors.is_empty() || !self.struct_errors.is_empty() || !self.stack.is_empty() || !self.pending_refs.is_empty() {
+            let mut errmsg = String::from("generic_xml document has bad structure:\n\n");
+            for e in &self.schema_errors {
+                let _ = writeln!(errmsg, "{}\n", e.render(self.src));
+            }
+            for e in &self.struct_errors {
+                let _ = writeln!(errmsg, "{}\n", e);
+            }
+            if !self.stack.is_empty() {
+                let _ = writeln!(errmsg, "{} table(s) left unclosed\n", self.stack.len());
+            }
+            if !self.pending_refs.is_empty() {
+                let _ = writeln!(errmsg, "{} dangling _ref(s) never resolved\n", self.pending_refs.len());
+            }
+            bail!("{}", errmsg);
+        }
+
+        let mut doc = self.output_doc;
+        doc.set_root(self.root);
+        doc.gc();
+        Ok(doc)
     }
 }
 
@@ -234,6 +528,12 @@ fn collect_events_scalar<'a>(ty: &'a str, val: &'a str) -> Result<TextEvent<'a>,
             Err(_) => Err(SchemaError::InvalidFloat)
         },
         "idstring" => {
+            if super::idstring_text::looks_checksummed(val) {
+                return match super::idstring_text::decode(val) {
+                    Some(hash) => Ok(TextEvent::IdString(hash)),
+                    None => Err(SchemaError::IdStringChecksum)
+                };
+            }
             if val.len() == 16 {
                 if let Ok(val) = u64::from_str_radix(val, 16) {
                     return Ok(TextEvent::IdString(val.swap_bytes()))
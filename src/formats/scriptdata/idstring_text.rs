@@ -0,0 +1,146 @@
+//! A checksummed, human-typeable text encoding for idstrings, modeled on
+//! bech32: the 64-bit hash is repacked into 5-bit groups, mapped through a
+//! 32-character alphabet, and followed by a 6-group BCH checksum computed
+//! over the whole string, so a one- or two-character typo is caught on
+//! read instead of silently resolving to a different, valid-looking hash.
+//! Distinguished from `generic_xml`/`custom_xml`'s legacy 16-hex-digit form
+//! by the `id1` prefix (bech32's human-readable part `id`, then its `1`
+//! separator). Unlike the legacy form, [`encode`]/[`decode`] round-trip the
+//! hash's bytes directly with no swap - that swap exists only to match the
+//! legacy hex form's byte order, which this encoding isn't bound by.
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const HRP: &str = "id";
+const CHECKSUM_LEN: usize = 6;
+
+/// Encodes `hash` as `id1...` text - see the module doc comment.
+pub fn encode(hash: u64) -> String {
+    let data = convert_bits(&hash.to_be_bytes(), 8, 5, true);
+    let checksum = create_checksum(&data);
+
+    let mut out = String::with_capacity(HRP.len() + 1 + data.len() + checksum.len());
+    out.push_str(HRP);
+    out.push('1');
+    for &d in data.iter().chain(checksum.iter()) {
+        out.push(CHARSET[d as usize] as char);
+    }
+    out
+}
+
+/// Whether `text` looks like [`encode`]'s output rather than the legacy
+/// 16-hex-digit form - a cheap prefix check, not a checksum verification
+/// (that's [`decode`]'s job, which also accepts only this after a positive
+/// check here).
+pub fn looks_checksummed(text: &str) -> bool {
+    text.len() > HRP.len() + 1 && text.is_char_boundary(HRP.len() + 1) && &text[..HRP.len() + 1] == "id1"
+}
+
+/// Decodes text [`encode`] produced, or `None` if it's malformed or its
+/// checksum doesn't verify.
+pub fn decode(text: &str) -> Option<u64> {
+    if !looks_checksummed(text) {
+        return None;
+    }
+    let body = &text[HRP.len() + 1..];
+    if body.len() != 13 + CHECKSUM_LEN {
+        return None;
+    }
+
+    let mut values = Vec::with_capacity(body.len());
+    for c in body.chars() {
+        values.push(CHARSET.iter().position(|&x| x as char == c)? as u8);
+    }
+
+    if !verify_checksum(&values) {
+        return None;
+    }
+
+    let data = &values[..values.len() - CHECKSUM_LEN];
+    let bytes = convert_bits_back(data, 5, 8)?;
+    Some(u64::from_be_bytes(bytes.try_into().ok()?))
+}
+
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Vec<u8> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let maxv = (1u32 << to) - 1;
+    let mut out = Vec::new();
+    for &value in data {
+        acc = (acc << from) | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & maxv) as u8);
+        }
+    }
+    if pad && bits > 0 {
+        out.push(((acc << (to - bits)) & maxv) as u8);
+    }
+    out
+}
+
+/// The inverse of [`convert_bits`] with `pad: true` - `None` if the
+/// leftover bits past the last full group aren't all zero, which means
+/// `data` wasn't produced by padding a shorter bitstring out to `from`-bit
+/// groups in the first place.
+fn convert_bits_back(data: &[u8], from: u32, to: u32) -> Option<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let maxv = (1u32 << to) - 1;
+    let mut out = Vec::new();
+    for &value in data {
+        acc = (acc << from) | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & maxv) as u8);
+        }
+    }
+    if bits >= from || (acc & ((1 << bits) - 1)) != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+fn hrp_expand() -> Vec<u8> {
+    let mut v: Vec<u8> = HRP.bytes().map(|b| b >> 5).collect();
+    v.push(0);
+    v.extend(HRP.bytes().map(|b| b & 31));
+    v
+}
+
+/// The bech32 generator polynomial's BCH checksum over `values`, the same
+/// algorithm bech32 uses to turn a single substitution, transposition, or
+/// short burst error into a near-certain checksum failure.
+fn polymod(values: &[u8]) -> u32 {
+    const GEN: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut chk: u32 = 1;
+    for &v in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ (v as u32);
+        for i in 0..5 {
+            if (top >> i) & 1 == 1 {
+                chk ^= GEN[i];
+            }
+        }
+    }
+    chk
+}
+
+fn create_checksum(data: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let mut values = hrp_expand();
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0u8; CHECKSUM_LEN]);
+    let mod_ = polymod(&values) ^ 1;
+    let mut checksum = [0u8; CHECKSUM_LEN];
+    for (i, slot) in checksum.iter_mut().enumerate() {
+        *slot = ((mod_ >> (5 * (CHECKSUM_LEN - 1 - i))) & 31) as u8;
+    }
+    checksum
+}
+
+fn verify_checksum(data_and_checksum: &[u8]) -> bool {
+    let mut values = hrp_expand();
+    values.extend_from_slice(data_and_checksum);
+    polymod(&values) == 1
+}
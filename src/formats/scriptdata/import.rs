@@ -0,0 +1,214 @@
+//! Import resolution for [`Document`]s, the way Dhall's `resolve.rs` splices
+//! one expression into another before typechecking: a table written as
+//!
+//! ```text
+//! { @meta "include", path = "shared/colors.generic" }
+//! ```
+//!
+//! (metatable [`INCLUDE_METATABLE`], a single string `path` attribute) is
+//! replaced in place by the root value of whatever `path` points at, parsed
+//! with whichever backend [`default_backend`] picks for its extension. This
+//! lets a large configuration be split across reusable fragments instead of
+//! one monolithic file, for the same reason Dhall split its standard
+//! library across many small files.
+//!
+//! Only [`generic_xml`](super::generic_xml), [`custom_xml`](super::custom_xml)
+//! and [`lua_like`](super::lua_like) are wired into [`default_backend`] -
+//! `binary` and `cbor` round-trip a whole document already and have no
+//! reason to reference another file from inside themselves.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use fnv::{FnvHashMap, FnvHashSet};
+use thiserror::Error;
+
+use super::document::{AtomId, DocTable, DocValue, Document};
+use crate::util::rc_cell::{RcCell, WeakCell};
+
+/// The metatable name that marks a table as an import directive rather than
+/// ordinary data.
+pub const INCLUDE_METATABLE: &str = "include";
+
+/// A scriptdata reader usable as an import target - the common shape of
+/// [`super::generic_xml::load`], [`super::custom_xml::load`] and
+/// [`super::lua_like::load`].
+pub type Backend = fn(&str) -> anyhow::Result<Document>;
+
+/// Picks a [`Backend`] by `path`'s extension: `.generic`/`.xml` for
+/// [`super::generic_xml`], `.custom` for [`super::custom_xml`], and
+/// `.clua`/`.lua` for [`super::lua_like`].
+pub fn default_backend(path: &Path) -> anyhow::Result<Backend> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("generic") | Some("xml") => Ok(super::generic_xml::load),
+        Some("custom") => Ok(super::custom_xml::load),
+        Some("clua") | Some("lua") => Ok(super::lua_like::load),
+        other => bail!("don't know which scriptdata backend should read a \"{:?}\" import", other)
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum ImportError {
+    #[error("import cycle detected: {0:?} is already being resolved")]
+    Cycle(PathBuf),
+    #[error("imports nested deeper than the configured limit of {0}")]
+    TooDeep(usize),
+    #[error("an include table has no string \"path\" attribute")]
+    MissingPath
+}
+
+/// Reads `path` with `backend` and resolves every `include` table reachable
+/// from its root against `path`'s own directory - the entry point a caller
+/// loading a top-level file wants. `max_depth` bounds how many files deep
+/// the chain may go, `path` itself included - so at most `max_depth - 1`
+/// `include`s can nest beneath it - on top of the cycle check every level
+/// already gets.
+pub fn load_with_imports(path: &Path, backend: Backend, max_depth: usize) -> anyhow::Result<Document> {
+    let src = std::fs::read_to_string(path).with_context(|| format!("reading {:?}", path))?;
+    let mut doc = backend(&src).with_context(|| format!("parsing {:?}", path))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut visiting = VecDeque::new();
+    visiting.push_back(path.to_path_buf());
+    resolve_imports(&mut doc, base_dir, max_depth, &mut visiting)?;
+    Ok(doc)
+}
+
+/// Resolves every `include` table reachable from `doc`'s root in place,
+/// relative to `base_dir`. `visiting` is the chain of import paths already
+/// being resolved - pass an empty deque when `doc` wasn't itself loaded as
+/// someone else's import.
+pub fn resolve_imports(doc: &mut Document, base_dir: &Path, max_depth: usize, visiting: &mut VecDeque<PathBuf>) -> anyhow::Result<()> {
+    let root = doc.root();
+    let mut seen = FnvHashSet::<WeakCell<DocTable>>::default();
+    let resolved = root.map(|r| resolve_value(&r, doc, base_dir, max_depth, visiting, &mut seen)).transpose()?;
+    doc.set_root(resolved);
+    Ok(())
+}
+
+fn resolve_value(
+    value: &DocValue,
+    doc: &mut Document,
+    base_dir: &Path,
+    max_depth: usize,
+    visiting: &mut VecDeque<PathBuf>,
+    seen: &mut FnvHashSet<WeakCell<DocTable>>
+) -> anyhow::Result<DocValue> {
+    let table = match value {
+        DocValue::Table(t) => t.clone(),
+        other => return Ok(other.clone())
+    };
+
+    if let Some(include_path) = include_path(&table, doc)? {
+        return resolve_include(&include_path, base_dir, doc, max_depth, visiting);
+    }
+
+    let down = table.downgrade();
+    if seen.insert(down) {
+        let entries: Vec<(DocValue, DocValue)> = (&*table.borrow()).into_iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        for (k, v) in entries {
+            let resolved = resolve_value(&v, doc, base_dir, max_depth, visiting, seen)?;
+            table.borrow_mut().insert(k, resolved);
+        }
+    }
+    Ok(DocValue::Table(table))
+}
+
+/// Loads the file `relative_path` (resolved against `base_dir`), splices
+/// its root into `doc`'s atom table, then resolves any `include`s *it*
+/// contains before returning - so a fragment can itself import further
+/// fragments, up to `max_depth` deep.
+fn resolve_include(
+    relative_path: &Path,
+    base_dir: &Path,
+    doc: &mut Document,
+    max_depth: usize,
+    visiting: &mut VecDeque<PathBuf>
+) -> anyhow::Result<DocValue> {
+    if visiting.len() >= max_depth {
+        bail!(ImportError::TooDeep(max_depth));
+    }
+
+    let full_path = base_dir.join(relative_path);
+    let canon = full_path.canonicalize().unwrap_or_else(|_| full_path.clone());
+    if visiting.contains(&canon) {
+        bail!(ImportError::Cycle(canon));
+    }
+
+    let backend = default_backend(&full_path)?;
+    let src = std::fs::read_to_string(&full_path).with_context(|| format!("reading import {:?}", full_path))?;
+    let imported = backend(&src).with_context(|| format!("parsing import {:?}", full_path))?;
+
+    let spliced = imported.root().map(|r| splice_value(&r, &imported, doc, &mut FnvHashMap::default()));
+
+    visiting.push_back(canon);
+    let import_dir = full_path.parent().unwrap_or(base_dir);
+    let mut seen = FnvHashSet::<WeakCell<DocTable>>::default();
+    let resolved = spliced.map(|v| resolve_value(&v, doc, import_dir, max_depth, visiting, &mut seen)).transpose();
+    visiting.pop_back();
+
+    Ok(resolved?.unwrap_or(DocValue::Bool(false)))
+}
+
+/// `table`'s `path` attribute, if `table` carries the [`INCLUDE_METATABLE`]
+/// metatable - `Ok(None)` for an ordinary table, `Err` for an include table
+/// that's missing its `path`.
+fn include_path(table: &RcCell<DocTable>, doc: &Document) -> anyhow::Result<Option<PathBuf>> {
+    let borrowed = table.borrow();
+    match borrowed.get_metatable() {
+        Some(m) if doc.resolve(m) == INCLUDE_METATABLE => (),
+        _ => return Ok(None)
+    };
+
+    let path = (&*borrowed).into_iter()
+        .find_map(|(k, v)| match (k, v) {
+            (DocValue::String(k), DocValue::String(v)) if doc.resolve(*k) == "path" => Some(PathBuf::from(doc.resolve(*v))),
+            _ => None
+        });
+
+    path.map(Some).ok_or_else(|| ImportError::MissingPath.into())
+}
+
+/// Rebuilds `value` - which belongs to `from` - as a fresh value belonging
+/// to `into`, interning every string it or a table it contains holds
+/// through `into`'s own atom table rather than `from`'s. Tables are
+/// rebuilt post-order and memoised by original identity, the same
+/// `rebuilt`-map trick [`Document::gc`](super::document::Document::gc)
+/// uses for its own atom remapping, so a table reachable through several
+/// paths - or a cycle - is only rebuilt once.
+fn splice_value(value: &DocValue, from: &Document, into: &mut Document, rebuilt: &mut FnvHashMap<WeakCell<DocTable>, RcCell<DocTable>>) -> DocValue {
+    match value {
+        DocValue::String(id) => DocValue::String(into.intern(from.resolve(*id))),
+        DocValue::Table(t) => {
+            let down = t.downgrade();
+            if let Some(existing) = rebuilt.get(&down) {
+                return DocValue::Table(existing.clone());
+            }
+            let new_table = RcCell::new(DocTable::new());
+            rebuilt.insert(down, new_table.clone());
+
+            let (meta, entries) = {
+                let borrowed = t.borrow();
+                let meta: Option<AtomId> = borrowed.get_metatable().map(|id| into.intern(from.resolve(id)));
+                let entries: Vec<(DocValue, DocValue)> = (&*borrowed).into_iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                (meta, entries)
+            };
+
+            let mut borrowed = new_table.borrow_mut();
+            borrowed.set_metatable(meta);
+            for (k, v) in entries {
+                let k2 = splice_value(&k, from, into, rebuilt);
+                let v2 = splice_value(&v, from, into, rebuilt);
+                borrowed.insert(k2, v2);
+            }
+            drop(borrowed);
+
+            DocValue::Table(new_table)
+        },
+        other => other.clone()
+    }
+}
@@ -0,0 +1,299 @@
+//! JSON scriptdata format: unlike `custom_xml`/`generic_xml`, which mirror
+//! Diesel's own on-disk text dialects warts and all, this one is meant to
+//! actually be pleasant for ordinary JSON tooling (jq, editors, diff) to
+//! work with.
+//!
+//! A scriptdata table becomes a JSON object. String keys and nonnegative
+//! integer keys (Lua's array-like part) become the object's own keys
+//! directly, stringified; a table's metatable name, if any, is recorded
+//! under the reserved `"$meta"` key. Idstring keys have no natural JSON
+//! representation, so they're written as their hex hash with a sidecar
+//! `"$keytype:<key>"` entry recording that it needs converting back.
+//!
+//! Values that aren't one of JSON's own types (idstrings, vectors,
+//! quaternions) are written as the closest JSON shape - a hex string, or a
+//! fixed-length array - tagged with a sidecar `"$type:<key>"` entry so
+//! `load` can recover the original type, the same trick `custom_xml`'s
+//! `dump_typed` uses with `_type:name` attributes.
+//!
+//! A table referenced from more than one place gets a `"$id"` entry the
+//! first time it's written, and every later reference to it becomes
+//! `{"$ref": "<id>"}` instead of being written out again. Every table on a
+//! cycle is referenced more than once, so a cycle is written the same way:
+//! the back-reference becomes a `"$ref"` to the table still being written,
+//! and `load` rebuilds it as the same shared table.
+//!
+//! Lossiness this doesn't try to fix: a string key that happens to look
+//! like a bare nonnegative integer (e.g. `"3"`) round-trips back as a
+//! `Number` key, not a `String` one, and a table, vector, or bool used as a
+//! key is rejected outright rather than worked around.
+
+use std::fmt::Write;
+use std::rc::Rc;
+
+use anyhow::{bail, Context};
+use fnv::{FnvHashMap, FnvHashSet};
+use serde_json::Value as Json;
+
+use super::document::{Document, DocTable, DocValue, Vector, Quaternion};
+use crate::hashindex::Hash as IdString;
+use crate::util::escape_json_str;
+use crate::util::ordered_float::OrderedFloat;
+use crate::util::rc_cell::*;
+
+pub fn dump(doc: &Document) -> String {
+    let mut state = DumperState {
+        doc,
+        out: String::new(),
+        diamond_subjects: doc.tables_used_repeatedly(),
+        seen_ids: FnvHashMap::default(),
+        in_progress: FnvHashSet::default(),
+        next_id: 0
+    };
+
+    match doc.root() {
+        Some(root) => state.write_value(&root),
+        None => state.out.push_str("null")
+    }
+
+    state.out
+}
+
+struct DumperState<'d> {
+    doc: &'d Document,
+    out: String,
+    diamond_subjects: FnvHashSet<WeakCell<DocTable>>,
+    seen_ids: FnvHashMap<WeakCell<DocTable>, Rc<str>>,
+    in_progress: FnvHashSet<WeakCell<DocTable>>,
+    next_id: u32
+}
+
+impl DumperState<'_> {
+    fn write_value(&mut self, value: &DocValue) {
+        match value {
+            DocValue::Bool(b) => write!(self.out, "{}", b).unwrap(),
+            DocValue::Number(n) => write!(self.out, "{}", n.0).unwrap(),
+            DocValue::String(s) => self.out.push_str(&escape_json_str(&self.doc.resolve_rc(*s))),
+            DocValue::IdString(id) => self.out.push_str(&escape_json_str(&format_idstring(*id))),
+            DocValue::Vector(v) => write!(self.out, "[{},{},{}]", v.x.0, v.y.0, v.z.0).unwrap(),
+            DocValue::Quaternion(q) => write!(self.out, "[{},{},{},{}]", q.x.0, q.y.0, q.z.0, q.w.0).unwrap(),
+            DocValue::Table(t) => self.write_table(t)
+        }
+    }
+
+    fn write_table(&mut self, table: &RcCell<DocTable>) {
+        let down = table.downgrade();
+
+        if let Some(id) = self.seen_ids.get(&down) {
+            write!(self.out, "{{\"$ref\":{}}}", escape_json_str(id)).unwrap();
+            return;
+        }
+
+        if self.in_progress.contains(&down) {
+            panic!("scriptdata table contains itself (directly or indirectly); json scriptdata can't represent cycles");
+        }
+        self.in_progress.insert(down.clone());
+
+        let id = if self.diamond_subjects.contains(&down) {
+            let s: Rc<str> = Rc::from(self.next_id.to_string());
+            self.next_id += 1;
+            self.seen_ids.insert(down.clone(), s.clone());
+            Some(s)
+        }
+        else { None };
+
+        let borrowed = table.borrow();
+
+        self.out.push('{');
+        let mut first = true;
+
+        if let Some(id) = &id {
+            write_sep(&mut self.out, &mut first);
+            write!(self.out, "\"$id\":{}", escape_json_str(id)).unwrap();
+        }
+        if let Some(meta) = borrowed.get_metatable() {
+            write_sep(&mut self.out, &mut first);
+            write!(self.out, "\"$meta\":{}", escape_json_str(&self.doc.resolve_rc(meta))).unwrap();
+        }
+
+        for (k, v) in &*borrowed {
+            let key = match k {
+                DocValue::String(s) => self.doc.resolve(*s).to_string(),
+                DocValue::Number(n) if n.0.trunc() == n.0 && n.0 >= 0.0 => format!("{}", n.0 as i64),
+                DocValue::Number(n) => format!("{}", n.0),
+                DocValue::IdString(id) => format_idstring(*id),
+                _ => panic!("json scriptdata only supports string, number and idstring keys")
+            };
+
+            if matches!(k, DocValue::IdString(_)) {
+                write_sep(&mut self.out, &mut first);
+                write!(self.out, "\"$keytype:{}\":\"idstring\"", key).unwrap();
+            }
+            if let Some(ty) = value_type_tag(v) {
+                write_sep(&mut self.out, &mut first);
+                write!(self.out, "{}:{}", escape_json_str(&format!("$type:{}", key)), escape_json_str(ty)).unwrap();
+            }
+
+            write_sep(&mut self.out, &mut first);
+            write!(self.out, "{}:", escape_json_str(&key)).unwrap();
+            self.write_value(v);
+        }
+
+        self.out.push('}');
+        self.in_progress.remove(&down);
+    }
+}
+
+fn write_sep(out: &mut String, first: &mut bool) {
+    if !*first { out.push(','); }
+    *first = false;
+}
+
+fn value_type_tag(v: &DocValue) -> Option<&'static str> {
+    match v {
+        DocValue::IdString(_) => Some("idstring"),
+        DocValue::Vector(_) => Some("vector"),
+        DocValue::Quaternion(_) => Some("quaternion"),
+        _ => None
+    }
+}
+
+fn format_idstring(id: IdString) -> String {
+    format!("{:016x}", id.0)
+}
+
+/// Parse `src` (as produced by [`dump`]) back into a `Document`.
+pub fn load(src: &str) -> anyhow::Result<Document> {
+    let json: Json = serde_json::from_str(src).context("Parsing json scriptdata")?;
+
+    let mut loader = Loader {
+        doc: Document::new(),
+        refs: FnvHashMap::default()
+    };
+
+    let root = loader.value_from_json(&json)?;
+    loader.doc.set_root(Some(root));
+    loader.doc.gc();
+    Ok(loader.doc)
+}
+
+/// `refs` maps a `"$id"` to the table it names, created eagerly (empty, to
+/// be filled in) the first time either its `"$id"` table or a `"$ref"` to
+/// it is encountered - whichever comes first, since a `serde_json::Map` is
+/// a `BTreeMap` and so iterates in key order, not document order, and a
+/// `"$ref"` can sort before the `"$id"` it points to. Both sides end up
+/// sharing the same `RcCell`, so whichever is filled in second just
+/// populates the table the other is already holding a handle to.
+struct Loader {
+    doc: Document,
+    refs: FnvHashMap<String, RcCell<DocTable>>
+}
+
+impl Loader {
+    fn value_from_json(&mut self, json: &Json) -> anyhow::Result<DocValue> {
+        match json {
+            Json::Null => bail!("json scriptdata can't represent a bare null"),
+            Json::Bool(b) => Ok(DocValue::Bool(*b)),
+            Json::Number(n) => Ok(DocValue::Number(OrderedFloat(n.as_f64().context("out-of-range json number")? as f32))),
+            Json::String(s) => Ok(DocValue::String(self.doc.intern(s))),
+            Json::Array(_) => bail!("json scriptdata array found outside of a tagged vector/quaternion value"),
+            Json::Object(obj) => self.table_from_json(obj)
+        }
+    }
+
+    fn table_from_json(&mut self, obj: &serde_json::Map<String, Json>) -> anyhow::Result<DocValue> {
+        if let Some(Json::String(target)) = obj.get("$ref") {
+            return Ok(match self.refs.get(target) {
+                Some(t) => DocValue::Table(t.clone()),
+                None => {
+                    // The referent hasn't been parsed yet - see the struct doc comment.
+                    let placeholder = RcCell::<DocTable>::default();
+                    self.refs.insert(target.clone(), placeholder.clone());
+                    DocValue::Table(placeholder)
+                }
+            });
+        }
+
+        let table = match obj.get("$id") {
+            Some(Json::String(id)) => match self.refs.get(id) {
+                Some(existing) => existing.clone(),
+                None => {
+                    let t = RcCell::<DocTable>::default();
+                    self.refs.insert(id.to_string(), t.clone());
+                    t
+                }
+            },
+            Some(_) => bail!("\"$id\" must be a string"),
+            None => RcCell::<DocTable>::default()
+        };
+
+        if let Some(meta) = obj.get("$meta") {
+            let meta = meta.as_str().context("\"$meta\" must be a string")?;
+            let cached = self.doc.intern(meta);
+            table.borrow_mut().set_metatable(Some(cached));
+        }
+
+        for (key, value) in obj {
+            if key == "$id" || key == "$meta" || key.starts_with("$type:") || key.starts_with("$keytype:") {
+                continue;
+            }
+
+            let doc_key = self.key_from_json(obj, key)?;
+            let doc_value = match obj.get(&format!("$type:{}", key)) {
+                Some(Json::String(ty)) => self.tagged_value_from_json(ty, value)?,
+                _ => self.value_from_json(value)?
+            };
+
+            table.borrow_mut().insert(doc_key, doc_value);
+        }
+
+        Ok(DocValue::Table(table))
+    }
+
+    fn key_from_json(&mut self, obj: &serde_json::Map<String, Json>, key: &str) -> anyhow::Result<DocValue> {
+        let is_idstring = matches!(obj.get(&format!("$keytype:{}", key)), Some(Json::String(t)) if t == "idstring");
+        if is_idstring {
+            let hash = u64::from_str_radix(key, 16).with_context(|| format!("\"{}\" isn't a valid idstring hex key", key))?;
+            return Ok(DocValue::IdString(IdString(hash)));
+        }
+
+        if let Ok(n) = key.parse::<i64>() {
+            if n >= 0 {
+                return Ok(DocValue::Number(OrderedFloat(n as f32)));
+            }
+        }
+        if let Ok(n) = key.parse::<f32>() {
+            return Ok(DocValue::Number(OrderedFloat(n)));
+        }
+
+        Ok(DocValue::String(self.doc.intern(key)))
+    }
+
+    fn tagged_value_from_json(&mut self, tag: &str, value: &Json) -> anyhow::Result<DocValue> {
+        match tag {
+            "idstring" => {
+                let s = value.as_str().context("a \"$type:...\":\"idstring\" value must be a hex string")?;
+                let hash = u64::from_str_radix(s, 16).with_context(|| format!("\"{}\" isn't a valid idstring hex value", s))?;
+                Ok(DocValue::IdString(IdString(hash)))
+            },
+            "vector" => {
+                let v = components(value)?;
+                if v.len() != 3 { bail!("a vector value must have 3 components"); }
+                Ok(DocValue::Vector(Vector { x: OrderedFloat(v[0]), y: OrderedFloat(v[1]), z: OrderedFloat(v[2]) }))
+            },
+            "quaternion" => {
+                let v = components(value)?;
+                if v.len() != 4 { bail!("a quaternion value must have 4 components"); }
+                Ok(DocValue::Quaternion(Quaternion { x: OrderedFloat(v[0]), y: OrderedFloat(v[1]), z: OrderedFloat(v[2]), w: OrderedFloat(v[3]) }))
+            },
+            _ => bail!("unknown json scriptdata \"$type\" tag \"{}\"", tag)
+        }
+    }
+}
+
+fn components(value: &Json) -> anyhow::Result<Vec<f32>> {
+    let arr = value.as_array().context("a tagged vector/quaternion value must be an array")?;
+    arr.iter()
+        .map(|c| c.as_f64().map(|c| c as f32).context("vector/quaternion component must be a number"))
+        .collect()
+}
@@ -0,0 +1,262 @@
+//! An offset-addressed reader for binary scriptdata, backed by any
+//! [`positioned_io::ReadAt`] source (a `File`, a memory-mapped region, or a
+//! plain byte slice) instead of requiring the whole blob resident as one
+//! `&[u8]` the way [`super::binary::from_binary`] does.
+//!
+//! The six section base offsets are read out of the header up front, same
+//! as `binary`; after that, each `DocTable` is pulled off `src` - its header
+//! fields and its 8-byte item records - only when the walk from the root
+//! reaches that pool index, and the resulting `RcCell<DocTable>` is cached
+//! in `seen_tables` so a table referenced from more than one place is still
+//! only read once. [`from_reader`] finishes that walk before it returns, so
+//! every table reachable from the root is read then and the `Document` it
+//! hands back is fully built; nothing is read from `src` on later access.
+//! This is worth having alongside `binary` for scriptdata blobs too large to
+//! comfortably read into memory, or where an mmap already backs the file
+//! and re-copying it would be wasted work.
+
+use std::str;
+
+use fnv::FnvHashMap;
+use positioned_io::ReadAt;
+
+use super::document::*;
+use super::binary::ScriptDataError;
+use crate::hashindex::{Hash as IdString};
+use crate::util::ordered_float::OrderedFloat;
+use crate::util::rc_cell::RcCell;
+
+fn read_exact_at<R: ReadAt>(src: &R, pos: u64, buf: &mut [u8]) -> Result<(), ScriptDataError> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = src.read_at(pos + filled as u64, &mut buf[filled..])
+            .map_err(|_| ScriptDataError::UnexpectedEof { offset: (pos as usize) + filled })?;
+        if n == 0 {
+            return Err(ScriptDataError::UnexpectedEof { offset: (pos as usize) + filled });
+        }
+        filled += n;
+    }
+    Ok(())
+}
+
+macro_rules! read_at_le {
+    ($($name:ident : $len:expr => $type:ident;)*) => {
+        $(fn $name<R: ReadAt>(src: &R, pos: u64) -> Result<$type, ScriptDataError> {
+            let mut buf = [0u8; $len];
+            read_exact_at(src, pos, &mut buf)?;
+            Ok($type::from_le_bytes(buf))
+        })*
+    }
+}
+
+read_at_le! {
+    read_u32_at: 4 => u32;
+    read_u64_at: 8 => u64;
+    read_i32_at: 4 => i32;
+    read_i64_at: 8 => i64;
+    read_f32_at: 4 => f32;
+}
+
+/// A table discovered but not yet populated - see
+/// [`FromReaderState::drain_pending_tables`].
+struct PendingTable {
+    table: RcCell<DocTable>,
+    items_offset: u64,
+    item_count: usize
+}
+
+struct FromReaderState<R: ReadAt> {
+    src: R,
+    is_x64: bool,
+    is_raid: bool,
+    offset_size: u64,
+    float_offset: u64,
+    string_offset: u64,
+    vector_offset: u64,
+    quaternion_offset: u64,
+    idstring_offset: u64,
+    table_offset: u64,
+    seen_tables: FnvHashMap<u32, RcCell<DocTable>>,
+    pending: Vec<PendingTable>,
+    doc: Document
+}
+
+impl<R: ReadAt> FromReaderState<R> {
+    fn by_variant<T>(&self, raid: T, x64: T, x86: T) -> T {
+        if self.is_raid { raid } else if self.is_x64 { x64 } else { x86 }
+    }
+
+    fn read_offset(&self, pos: u64) -> Result<u64, ScriptDataError> {
+        if self.is_x64 {
+            read_u64_at(&self.src, pos)
+        }
+        else {
+            Ok(read_u32_at(&self.src, pos)? as u64)
+        }
+    }
+
+    fn read_string(&mut self, index: u64) -> Result<AtomId, ScriptDataError> {
+        let string_offset_offset = self.string_offset + self.offset_size + index * self.by_variant(16, 16, 8);
+        let string_offset = self.read_offset(string_offset_offset)?;
+        let mut bytes = Vec::new();
+        let mut pos = string_offset;
+        loop {
+            let mut b = [0u8; 1];
+            read_exact_at(&self.src, pos, &mut b)?;
+            if b[0] == 0 { break; }
+            bytes.push(b[0]);
+            pos += 1;
+        }
+        let s = str::from_utf8(&bytes).map_err(|_| ScriptDataError::InvalidUtf8 { offset: string_offset as usize })?;
+        Ok(self.doc.intern(s))
+    }
+
+    /// Decode a single value at `offset`. Same division of labour as
+    /// `binary::FromBinaryState::decode_value`: a table value resolves to
+    /// its (possibly still-empty) `RcCell` via `table_ref` without reading
+    /// its items, so this never recurses into a table's contents.
+    fn decode_value(&mut self, offset: u64) -> Result<DocValue, ScriptDataError> {
+        let item_type = read_u32_at(&self.src, offset)?;
+        let tag = (item_type >> 24) & 0xFF;
+        let value = (item_type & 0xFFFFFF) as u64;
+
+        match tag {
+            0 => Err(ScriptDataError::NullNotSupported { offset: offset as usize }),
+            1 => Ok(DocValue::Bool(false)),
+            2 => Ok(DocValue::Bool(true)),
+            3 => {
+                let float_offset = self.float_offset + value * 4;
+                Ok(DocValue::Number(OrderedFloat(read_f32_at(&self.src, float_offset)?)))
+            },
+            4 => Ok(DocValue::String(self.read_string(value)?)),
+            5 => {
+                let vector_offset = self.vector_offset + 12 * value;
+                let vec = Vector {
+                    x: OrderedFloat(read_f32_at(&self.src, vector_offset)?),
+                    y: OrderedFloat(read_f32_at(&self.src, vector_offset + 4)?),
+                    z: OrderedFloat(read_f32_at(&self.src, vector_offset + 8)?)
+                };
+                Ok(DocValue::Vector(vec))
+            },
+            6 => {
+                let quaternion_offset = self.quaternion_offset + 16 * value;
+                let quat = Quaternion {
+                    x: OrderedFloat(read_f32_at(&self.src, quaternion_offset)?),
+                    y: OrderedFloat(read_f32_at(&self.src, quaternion_offset + 4)?),
+                    z: OrderedFloat(read_f32_at(&self.src, quaternion_offset + 8)?),
+                    w: OrderedFloat(read_f32_at(&self.src, quaternion_offset + 12)?)
+                };
+                Ok(DocValue::Quaternion(quat))
+            },
+            7 => {
+                let idstring_offset = self.idstring_offset + 8 * value;
+                Ok(DocValue::IdString(IdString(read_u64_at(&self.src, idstring_offset)?)))
+            },
+            8 => Ok(DocValue::Table(self.table_ref(value as u32)?)),
+            _ => Err(ScriptDataError::UnknownTag { tag, offset: offset as usize })
+        }
+    }
+
+    /// Look up (or lazily allocate) the `RcCell` for table pool entry
+    /// `index`. A freshly allocated table's own 8-byte item records aren't
+    /// read here - they're queued in `pending` and worked off by
+    /// [`Self::drain_pending_tables`] - so a table is only read off `src`
+    /// the first time something actually reaches its pool index.
+    fn table_ref(&mut self, index: u32) -> Result<RcCell<DocTable>, ScriptDataError> {
+        if let Some(tab) = self.seen_tables.get(&index) {
+            return Ok(tab.clone());
+        }
+
+        let table_offset = self.table_offset + (index as u64) * self.by_variant(40, 32, 20);
+
+        let metatable_index = if self.is_x64 {
+            read_i64_at(&self.src, table_offset)?
+        }
+        else {
+            read_i32_at(&self.src, table_offset)? as i64
+        };
+        let metatable_atom = if metatable_index >= 0 {
+            Some(self.read_string(metatable_index as u64)?)
+        }
+        else { None };
+
+        let item_count = if self.is_raid {
+            read_u64_at(&self.src, table_offset + self.offset_size)? as usize
+        }
+        else {
+            read_u32_at(&self.src, table_offset + self.offset_size)? as usize
+        };
+        let items_offset = self.read_offset(table_offset + self.by_variant(24, 16, 12))?;
+
+        let mut table = DocTable::new();
+        table.set_metatable(metatable_atom);
+        let tab_ref = RcCell::new(table);
+
+        self.seen_tables.insert(index, tab_ref.clone());
+        self.pending.push(PendingTable { table: tab_ref.clone(), items_offset, item_count });
+
+        Ok(tab_ref)
+    }
+
+    fn drain_pending_tables(&mut self) -> Result<(), ScriptDataError> {
+        while let Some(frame) = self.pending.pop() {
+            for i in 0..frame.item_count {
+                let item_offset = frame.items_offset + (i as u64) * 8;
+                let key = self.decode_value(item_offset)?;
+                let value = self.decode_value(item_offset + 4)?;
+                frame.table.borrow_mut().insert(key, value);
+            }
+        }
+        Ok(())
+    }
+
+    fn value_from_reader(&mut self, offset: u64) -> Result<DocValue, ScriptDataError> {
+        let value = self.decode_value(offset)?;
+        self.drain_pending_tables()?;
+        Ok(value)
+    }
+}
+
+fn from_reader_impl<R: ReadAt>(src: R, is_raid: bool) -> Result<Document, ScriptDataError> {
+    let is_x64 = is_raid || read_u32_at(&src, 0)? == 568494624;
+
+    let mut state = FromReaderState {
+        src,
+        is_raid,
+        is_x64,
+        offset_size: if is_x64 { 8 } else { 4 },
+        float_offset: 0,
+        string_offset: 0,
+        vector_offset: 0,
+        quaternion_offset: 0,
+        idstring_offset: 0,
+        table_offset: 0,
+        seen_tables: FnvHashMap::default(),
+        pending: Vec::new(),
+        doc: Document::new()
+    };
+
+    let header_pad = state.by_variant(24, 16, 12) as u64;
+    let offset_size = state.offset_size;
+    state.float_offset      = state.read_offset(header_pad + (header_pad + offset_size) * 0)?;
+    state.string_offset     = state.read_offset(header_pad + (header_pad + offset_size) * 1)?;
+    state.vector_offset     = state.read_offset(header_pad + (header_pad + offset_size) * 2)?;
+    state.quaternion_offset = state.read_offset(header_pad + (header_pad + offset_size) * 3)?;
+    state.idstring_offset   = state.read_offset(header_pad + (header_pad + offset_size) * 4)?;
+    state.table_offset      = state.read_offset(header_pad + (header_pad + offset_size) * 5)?;
+
+    let root_offset = state.by_variant(200, 152, 100) as u64;
+    let root = state.value_from_reader(root_offset)?;
+    state.doc.set_root(Some(root));
+
+    Ok(state.doc)
+}
+
+/// Parse binary scriptdata out of `src` by offset rather than requiring the
+/// whole blob as one in-memory `&[u8]` - pass a memory-mapped file (anything
+/// implementing [`positioned_io::ReadAt`], e.g. `positioned_io::RandomAccessFile`
+/// or a wrapped [`memmap2::Mmap`]) to decode very large scriptdata without
+/// copying it first.
+pub fn from_reader<R: ReadAt>(src: R, is_raid: bool) -> anyhow::Result<Document> {
+    Ok(from_reader_impl(src, is_raid)?)
+}
@@ -1,9 +1,14 @@
 use std::fmt;
 use std::fmt::Write;
+use std::rc::Rc;
+use std::str::FromStr;
 
+use anyhow::{anyhow, bail};
 use fnv::{FnvHashMap, FnvHashSet};
 
 use super::document::*;
+use crate::hashindex::Hash as IdString;
+use crate::util::ordered_float::OrderedFloat;
 use crate::util::rc_cell::*;
 
 pub fn dump(doc: &Document) -> String {
@@ -12,6 +17,7 @@ pub fn dump(doc: &Document) -> String {
     match doc.root() {
         Some(item) => {
             let mut state = DumpState {
+                doc,
                 output: &mut output,
                 seen_table_ids: FnvHashMap::default(),
                 referenced_tables: doc.tables_used_repeatedly(),
@@ -25,7 +31,8 @@ pub fn dump(doc: &Document) -> String {
     output
 }
 
-struct DumpState<'o> {
+struct DumpState<'o, 'd> {
+    doc: &'d Document,
     output: &'o mut String,
     seen_table_ids: FnvHashMap<WeakCell<DocTable>, String>,
     referenced_tables: FnvHashSet<WeakCell<DocTable>>,
@@ -44,7 +51,7 @@ fn dump_item(item: &DocValue, state: &mut DumpState, indent_level: usize) -> Res
         DocValue::Number(f) => write!(state.output, "{}", f),
         DocValue::Quaternion(q) => write!(state.output, "Quaternion({}, {}, {}, {})", q.x, q.y, q.z, q.w),
         DocValue::Vector(v) => write!(state.output, "Vector3({}, {}, {})", v.x, v.y, v.z),
-        DocValue::String(s) => write!(state.output, "{}", WriteLuaString(s)),
+        DocValue::String(s) => write!(state.output, "{}", WriteLuaString(state.doc.resolve(*s))),
         DocValue::Table(tab) => write_lua_table(tab, state, indent_level)
     }
 }
@@ -75,17 +82,17 @@ impl<S: AsRef<str>> fmt::Display for WriteLuaString<S> {
 fn write_lua_table(table: &RcCell<DocTable>, state: &mut DumpState, indent_level: usize) -> Result<(), fmt::Error> {
     let downgraded = table.downgrade();
     if let Some(id) = state.seen_table_ids.get(&downgraded) {
-        write!(state.output, "Ref(\'{}\')", id)?;
+        write!(state.output, "Ref(\"{}\")", id)?;
     }
     else {
         if state.referenced_tables.contains(&downgraded) {
-            write!(state.output, "RefId(\'{}\', ", state.next_id)?;
+            write!(state.output, "RefId(\"{}\", ", state.next_id)?;
         }
         state.seen_table_ids.insert(downgraded.clone(), state.next_id.to_string());
         state.next_id += 1;
         let tref = &*table.borrow();
         if let Some(mt) = tref.get_metatable() {
-            write!(state.output, "{} ", mt)?;
+            write!(state.output, "{} ", state.doc.resolve(mt))?;
         }
         write!(state.output, "{{")?;
 
@@ -122,6 +129,7 @@ fn write_indent<O: Write>(output: &mut O, level: usize) -> Result<(), fmt::Error
 fn write_key(item: &DocValue, state: &mut DumpState, indent_level: usize) -> Result<(), fmt::Error> {
     match item {
         DocValue::String(s) => {
+            let s = state.doc.resolve(*s);
             if is_valid_ident(s) {
                 write!(state.output, "{}", s)?;
                 return Ok(());
@@ -153,4 +161,406 @@ fn is_valid_ident<S: AsRef<str>>(s: S) -> bool {
         if !(char::is_alphabetic(fc) || fc == '_') { return false; }
     }
     return true;
+}
+
+/// Parses the Lua-like syntax `dump` produces back into a `Document`. The
+/// exact inverse of `dump_item`/`write_lua_table`: literals, `Vector3`/
+/// `Quaternion`/`IdString` calls and `["key"] = value`/`ident = value`
+/// table entries all undo their corresponding write, including the
+/// `WriteLuaString` escapes.
+///
+/// `RefId("n", ...)` always dumps a table's full contents at the point
+/// where it's first reached by the writer's depth-first walk, so by the
+/// time a `Ref("n")` is encountered for the same id, that table's `RcCell`
+/// already exists; we allocate the cell as soon as we see the id, so a
+/// table can refer to itself or to an ancestor that's still being filled
+/// in, not just to a completed sibling.
+pub fn load(src: &str) -> anyhow::Result<Document> {
+    parse_scriptdata(src).map_err(|errors| anyhow!("{}", render_errors(&errors, src)))
+}
+
+/// As [`load`], but collects every malformed table entry instead of
+/// aborting at the first one: a document with three broken fields reports
+/// all three in one pass rather than making the caller fix and rerun three
+/// times. Anything outside a table's `{...}` body (a broken top-level
+/// value, a missing `return`, trailing garbage) is still fatal, since
+/// there's no table-entry boundary to resync on; that case reports the
+/// single error that stopped the parse.
+pub fn parse_scriptdata(src: &str) -> Result<Document, Vec<ParseError>> {
+    let mut p = Parser {
+        input: src.as_bytes(),
+        pos: 0,
+        doc: Document::new(),
+        ids: FnvHashMap::default(),
+        errors: Vec::new()
+    };
+    p.skip_ws();
+    if let Err(e) = p.expect_word("return") {
+        return Err(vec![ParseError { span: p.pos..p.pos, message: e.to_string() }]);
+    }
+    p.skip_ws();
+    if p.input[p.pos..].starts_with(b"nil") {
+        p.pos += 3;
+    }
+    else {
+        let start = p.pos;
+        match p.parse_value() {
+            Ok(root) => p.doc.set_root(Some(root)),
+            Err(e) => p.errors.push(ParseError { span: start..p.pos, message: e.to_string() })
+        }
+    }
+    p.skip_ws();
+    if p.errors.is_empty() && p.pos != p.input.len() {
+        p.errors.push(ParseError {
+            span: p.pos..p.input.len(),
+            message: "trailing garbage after top-level value".to_owned()
+        });
+    }
+    if !p.errors.is_empty() {
+        return Err(p.errors);
+    }
+    p.doc.gc();
+    Ok(p.doc)
+}
+
+/// One problem found while parsing, as a byte span into the source plus a
+/// human-readable message - since [`Parser::parse_table`] recovers after a
+/// malformed entry instead of aborting, a single parse can produce more
+/// than one of these.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub span: std::ops::Range<usize>,
+    pub message: String
+}
+
+impl ParseError {
+    /// Renders a `rustc`-style single-line caret diagnostic pointing at
+    /// `self.span` within `src`.
+    pub fn render(&self, src: &str) -> String {
+        let (line, col) = line_col(src, self.span.start);
+        let line_text = src.lines().nth(line - 1).unwrap_or("");
+        let underline_len = self.span.len().max(1).min(line_text.len().saturating_sub(col - 1).max(1));
+        format!(
+            "error: {}\n  --> line {}, column {}\n   |\n{:>3} | {}\n   | {}{}",
+            self.message, line, col, line, line_text,
+            " ".repeat(col - 1), "^".repeat(underline_len)
+        )
+    }
+}
+
+fn line_col(src: &str, offset: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut col = 1;
+    for c in src[..offset.min(src.len())].chars() {
+        if c == '\n' { line += 1; col = 1; } else { col += 1; }
+    }
+    (line, col)
+}
+
+/// Renders every error in `errors` against `src`, separated by blank lines.
+pub fn render_errors(errors: &[ParseError], src: &str) -> String {
+    errors.iter().map(|e| e.render(src)).collect::<Vec<_>>().join("\n\n")
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    doc: Document,
+    ids: FnvHashMap<Rc<str>, RcCell<DocTable>>,
+    errors: Vec<ParseError>
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> { self.input.get(self.pos).copied() }
+
+    fn skip_ws(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n') | Some(b',') => self.pos += 1,
+                Some(b'-') if self.input[self.pos..].starts_with(b"--") => {
+                    while !matches!(self.peek(), None | Some(b'\n')) { self.pos += 1; }
+                },
+                _ => break
+            }
+        }
+    }
+
+    fn expect_byte(&mut self, b: u8) -> anyhow::Result<()> {
+        self.skip_ws();
+        if self.peek() != Some(b) {
+            bail!("Lua-like scriptdata: expected {:?} at byte {}", b as char, self.pos);
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn expect_word(&mut self, word: &str) -> anyhow::Result<()> {
+        self.skip_ws();
+        if self.input[self.pos..].starts_with(word.as_bytes()) {
+            self.pos += word.len();
+            Ok(())
+        }
+        else {
+            bail!("Lua-like scriptdata: expected {:?} at byte {}", word, self.pos)
+        }
+    }
+
+    fn read_ident(&mut self) -> &'a str {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == b'_') {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.input[start..self.pos]).unwrap()
+    }
+
+    fn read_number_token(&mut self) -> &'a str {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit() || b"+-.eE".contains(&c)) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.input[start..self.pos]).unwrap()
+    }
+
+    /// Parses one value: a scalar literal, a `Vector3`/`Quaternion`/
+    /// `IdString` call, a `Ref`, or a (possibly id'd, possibly metatabled)
+    /// table.
+    fn parse_value(&mut self) -> anyhow::Result<DocValue> {
+        self.skip_ws();
+        match self.peek() {
+            Some(b'"') => {
+                let s = self.parse_short_string()?;
+                Ok(DocValue::String(self.doc.intern_rc(s)))
+            },
+            Some(b'{') => Ok(DocValue::Table(self.parse_table(None, None)?)),
+            Some(c) if c.is_ascii_digit() || c == b'-' || c == b'+' => {
+                let tok = self.read_number_token();
+                let n: f32 = tok.parse().map_err(|e| anyhow!("Lua-like scriptdata: bad number {:?}: {}", tok, e))?;
+                Ok(DocValue::Number(OrderedFloat(n)))
+            },
+            Some(c) if c.is_ascii_alphabetic() || c == b'_' => {
+                let word = self.read_ident().to_owned();
+                self.skip_ws();
+                match (word.as_str(), self.peek()) {
+                    ("true", _) => Ok(DocValue::Bool(true)),
+                    ("false", _) => Ok(DocValue::Bool(false)),
+                    ("Vector3", Some(b'(')) => self.parse_vector(),
+                    ("Quaternion", Some(b'(')) => self.parse_quaternion(),
+                    ("IdString", Some(b'(')) => self.parse_idstring(),
+                    ("Ref", Some(b'(')) => self.parse_ref(),
+                    ("RefId", Some(b'(')) => self.parse_refid(),
+                    (_, Some(b'{')) => {
+                        let meta = self.doc_cache(&word);
+                        Ok(DocValue::Table(self.parse_table(None, Some(meta))?))
+                    },
+                    (other, _) => bail!("Lua-like scriptdata: unknown function or bare word {:?}", other)
+                }
+            },
+            other => bail!("Lua-like scriptdata: unexpected {:?} at byte {}", other, self.pos)
+        }
+    }
+
+    fn doc_cache(&mut self, s: &str) -> AtomId { self.doc.intern(s) }
+
+    fn parse_vector(&mut self) -> anyhow::Result<DocValue> {
+        self.expect_byte(b'(')?;
+        let x = self.parse_float_arg()?;
+        let y = self.parse_float_arg()?;
+        let z = self.parse_last_float_arg()?;
+        Ok(DocValue::Vector(Vector { x: OrderedFloat(x), y: OrderedFloat(y), z: OrderedFloat(z) }))
+    }
+
+    fn parse_quaternion(&mut self) -> anyhow::Result<DocValue> {
+        self.expect_byte(b'(')?;
+        let x = self.parse_float_arg()?;
+        let y = self.parse_float_arg()?;
+        let z = self.parse_float_arg()?;
+        let w = self.parse_last_float_arg()?;
+        Ok(DocValue::Quaternion(Quaternion { x: OrderedFloat(x), y: OrderedFloat(y), z: OrderedFloat(z), w: OrderedFloat(w) }))
+    }
+
+    fn parse_idstring(&mut self) -> anyhow::Result<DocValue> {
+        self.expect_byte(b'(')?;
+        self.skip_ws();
+        self.expect_word("0x")?;
+        let hex = self.read_ident_like_hex();
+        let val = u64::from_str_radix(hex, 16).map_err(|e| anyhow!("Lua-like scriptdata: bad IdString {:?}: {}", hex, e))?;
+        self.expect_byte(b')')?;
+        Ok(DocValue::IdString(IdString(val)))
+    }
+
+    fn read_ident_like_hex(&mut self) -> &'a str {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_hexdigit()) { self.pos += 1; }
+        std::str::from_utf8(&self.input[start..self.pos]).unwrap()
+    }
+
+    fn parse_float_arg(&mut self) -> anyhow::Result<f32> {
+        let tok = self.read_number_token();
+        let v = f32::from_str(tok).map_err(|e| anyhow!("Lua-like scriptdata: bad number {:?}: {}", tok, e))?;
+        self.expect_byte(b',')?;
+        Ok(v)
+    }
+
+    fn parse_last_float_arg(&mut self) -> anyhow::Result<f32> {
+        self.skip_ws();
+        let tok = self.read_number_token();
+        let v = f32::from_str(tok).map_err(|e| anyhow!("Lua-like scriptdata: bad number {:?}: {}", tok, e))?;
+        self.expect_byte(b')')?;
+        Ok(v)
+    }
+
+    fn parse_ref(&mut self) -> anyhow::Result<DocValue> {
+        self.expect_byte(b'(')?;
+        let id = self.parse_short_string()?;
+        self.expect_byte(b')')?;
+        match self.ids.get(&id) {
+            Some(cell) => Ok(DocValue::Table(cell.clone())),
+            None => bail!("Lua-like scriptdata: Ref({:?}) used before it was defined", id)
+        }
+    }
+
+    fn parse_refid(&mut self) -> anyhow::Result<DocValue> {
+        self.expect_byte(b'(')?;
+        let id = self.parse_short_string()?;
+        self.expect_byte(b',')?;
+        self.skip_ws();
+        let meta = if self.peek() == Some(b'{') {
+            None
+        }
+        else {
+            let ident = self.read_ident().to_owned();
+            Some(self.doc_cache(&ident))
+        };
+        let cell = self.parse_table(Some(id), meta)?;
+        self.expect_byte(b')')?;
+        Ok(DocValue::Table(cell))
+    }
+
+    /// Parses one value that is itself a table: `{...}` or `ident {...}`.
+    ///
+    /// A malformed entry doesn't abort the whole parse: it's recorded as a
+    /// [`ParseError`] and [`recover_to_next_entry`](Self::recover_to_next_entry)
+    /// skips forward to the next entry (or the table's close brace), so one
+    /// bad field doesn't hide every other error in the same document.
+    fn parse_table(&mut self, id: Option<Rc<str>>, meta: Option<AtomId>) -> anyhow::Result<RcCell<DocTable>> {
+        let cell = RcCell::<DocTable>::default();
+        if let Some(id) = &id {
+            self.ids.insert(id.clone(), cell.clone());
+        }
+        cell.borrow_mut().set_metatable(meta);
+
+        self.expect_byte(b'{')?;
+        self.skip_ws();
+        while self.peek() != Some(b'}') {
+            let entry_start = self.pos;
+            match self.parse_table_entry() {
+                Ok((key, value)) => cell.borrow_mut().insert(key, value),
+                Err(e) => {
+                    self.errors.push(ParseError { span: entry_start..self.pos, message: e.to_string() });
+                    if !self.recover_to_next_entry() {
+                        break;
+                    }
+                }
+            }
+            self.skip_ws();
+        }
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+        }
+        else {
+            self.errors.push(ParseError {
+                span: self.pos..self.input.len(),
+                message: "unterminated table: ran out of input before a closing '}'".to_owned()
+            });
+        }
+        Ok(cell)
+    }
+
+    /// Parses one `key = value` table entry. Deliberately doesn't touch the
+    /// enclosing table's `RefCell` borrow - unlike the key, the value may
+    /// recurse into `parse_table` for the same cell (a self-referential or
+    /// mutually-cyclic table), which would double-borrow it if we were still
+    /// holding a `RefMut` here.
+    fn parse_table_entry(&mut self) -> anyhow::Result<(DocValue, DocValue)> {
+        let key = self.parse_key()?;
+        self.skip_ws();
+        self.expect_byte(b'=')?;
+        let value = self.parse_value()?;
+        Ok((key, value))
+    }
+
+    /// Skips forward from a failed table entry to the start of the next one,
+    /// tracking bracket nesting (and skipping over string literals, so a
+    /// `}` or `,` inside a quoted value doesn't confuse the scan). Consumes
+    /// a top-level `,`/`;` separator and returns `true` to resume the entry
+    /// loop, or stops (without consuming it) at a top-level `}` and returns
+    /// `true` so the loop's own exit check ends it; returns `false` only if
+    /// it runs off the end of the input without finding either, which the
+    /// caller must treat as fatal for this table rather than looping forever.
+    fn recover_to_next_entry(&mut self) -> bool {
+        let mut depth: i32 = 0;
+        loop {
+            match self.peek() {
+                None => return false,
+                Some(b'"') => { let _ = self.parse_short_string(); },
+                Some(b'{') | Some(b'(') | Some(b'[') => { depth += 1; self.pos += 1; },
+                Some(b'}') if depth == 0 => return true,
+                Some(b')') | Some(b']') | Some(b'}') => { depth -= 1; self.pos += 1; },
+                Some(b',') | Some(b';') if depth == 0 => { self.pos += 1; return true; },
+                Some(_) => { self.pos += 1; }
+            }
+        }
+    }
+
+    /// Parses a table key: a bare identifier (`ident =`) or a bracketed
+    /// value (`[expr] =`), the inverse of `write_key`.
+    fn parse_key(&mut self) -> anyhow::Result<DocValue> {
+        self.skip_ws();
+        if self.peek() == Some(b'[') {
+            self.pos += 1;
+            let v = self.parse_value()?;
+            self.expect_byte(b']')?;
+            Ok(v)
+        }
+        else {
+            let ident = self.read_ident();
+            Ok(DocValue::from(self.doc.intern(ident)))
+        }
+    }
+
+    fn parse_short_string(&mut self) -> anyhow::Result<Rc<str>> {
+        self.skip_ws();
+        self.expect_byte(b'"')?;
+        let mut buf = String::new();
+        loop {
+            match self.peek() {
+                None => bail!("Lua-like scriptdata: unterminated string starting before byte {}", self.pos),
+                Some(b'"') => { self.pos += 1; break; },
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let esc = self.peek().ok_or_else(|| anyhow!("Lua-like scriptdata: unterminated escape at byte {}", self.pos))?;
+                    buf.push(match esc {
+                        b'a' => '\x07',
+                        b'b' => '\x08',
+                        b'f' => '\x0C',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'v' => '\x0B',
+                        b'\\' => '\\',
+                        b'"' => '"',
+                        other => bail!("Lua-like scriptdata: unrecognised string escape '\\{}'", other as char)
+                    });
+                    self.pos += 1;
+                },
+                Some(_) => {
+                    let rest = std::str::from_utf8(&self.input[self.pos..]).map_err(|_| anyhow!("Lua-like scriptdata: invalid UTF-8 in string"))?;
+                    let c = rest.chars().next().unwrap();
+                    buf.push(c);
+                    self.pos += c.len_utf8();
+                }
+            }
+        }
+        Ok(Rc::from(buf))
+    }
 }
\ No newline at end of file
@@ -19,16 +19,35 @@
 //!
 //! This implementation does NOT reproduce the broken behaviours. In
 //! addition, it supports a lua-like format which may be easier to type
-//! by hand.
+//! by hand, and a `json` format (see [`json`]) which Diesel itself never
+//! reads or writes, for consumption by ordinary JSON tooling; it
+//! round-trips everything except the key cases its module doc lists.
 
 mod document;
 mod id_tracker;
 pub use document::*;
 
 pub mod binary;
+pub mod lazy_binary;
+pub mod binary_transfer;
+pub mod cbor;
+pub mod diagnostics;
 pub mod lua_like;
 pub mod generic_xml;
 pub mod custom_xml;
+pub mod import;
+pub mod json;
+pub mod shell;
+pub mod query;
+pub mod item_codec;
+pub mod schema;
+pub mod arena;
+pub mod idstring_text;
+
+use std::fmt;
+use std::rc::Rc;
+use fnv::FnvHashSet;
+use crate::util::rc_cell::WeakCell;
 
 #[derive(Debug, Copy, Clone)]
 pub enum TextEvent<'a> {
@@ -78,6 +97,10 @@ pub enum SchemaError {
     InvalidBool,
     InvalidFloat,
     InvalidIdString,
+    /// A `value` that parsed as the checksummed `id1...` idstring form (see
+    /// [`idstring_text`]) but whose checksum doesn't verify - most likely a
+    /// typo, since a single corrupted character defeats the checksum.
+    IdStringChecksum,
     InvalidVector,
     InvalidQuaternion,
     UnknownItemType,
@@ -92,18 +115,215 @@ pub enum SchemaError {
 impl SchemaError {
     fn at(self, node: &roxmltree::Node) -> TextParseError {
         TextParseError::SchemaError {
-            pos: node.document().text_pos_at(node.range().start),
+            pos: node.range().start,
             kind: self
         }
     }
+
+    /// The human-readable half of a [`Diagnostic`][diagnostics::Diagnostic]
+    /// built from this error, matching the register of [`custom_xml`]'s
+    /// own `LoadError::message`.
+    fn message(&self) -> &'static str {
+        match self {
+            SchemaError::WrongElement{expected} => match expected {
+                &"generic_scriptdata" => "root element must be `generic_scriptdata`",
+                _ => "unexpected element"
+            },
+            SchemaError::MissingType => "element is missing its `type` attribute",
+            SchemaError::MissingValue => "element is missing its `value` attribute",
+            SchemaError::InvalidBool => "`value` isn't `true` or `false`",
+            SchemaError::InvalidFloat => "`value` isn't a valid number",
+            SchemaError::InvalidIdString => "`value` isn't a 16-hex-digit idstring",
+            SchemaError::IdStringChecksum => "checksummed idstring's checksum doesn't match - check for a typo",
+            SchemaError::InvalidVector => "`value` isn't 3 space-separated numbers",
+            SchemaError::InvalidQuaternion => "`value` isn't 4 space-separated numbers",
+            SchemaError::UnknownItemType => "unrecognised `type` attribute",
+            SchemaError::BadIndex => "`index` isn't a non-negative integer",
+            SchemaError::KeyAndIndex => "element has both `key` and `index`",
+            SchemaError::NoKeyOrIndex => "element is missing both `key` and `index`",
+            SchemaError::TableHasValue => "a `type=\"table\"` element can't also have `value`",
+            SchemaError::RefAndId => "element has both `_ref` and `_id`",
+            SchemaError::RefHasChildren => "a `_ref` element can't have children"
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
 pub enum TextParseError {
     //DomError(roxmltree::Error),
     SchemaError{
-        pos: roxmltree::TextPos,
+        pos: usize,
         kind: SchemaError
     }
 }
 
+impl TextParseError {
+    /// Renders this error as a labelled snippet of `src`, the text the
+    /// error's byte offset was recorded against.
+    pub fn render(&self, src: &str) -> String {
+        let TextParseError::SchemaError{pos, kind} = self;
+        let diag = diagnostics::Diagnostic::at(kind.message(), *pos);
+        diagnostics::render_text(src, &diag)
+    }
+}
+
+/// One of the writer formats a [`Document`] can be rendered to, for the
+/// purposes of [`validate_for_format`].
+///
+/// `binary` isn't here: per the table above it round-trips everything, so
+/// there's nothing for it to warn about.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TargetFormat {
+    CustomXml,
+    GenericXml
+}
+
+/// Why a value won't survive a trip through a [`TargetFormat`], matching a
+/// "broken" or "crash" cell in the table above.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FormatIssue {
+    Bool,
+    Float,
+    IdString,
+    Vector,
+    Quaternion,
+    /// A table key that isn't a string or a nonnegative integer. `custom_xml`
+    /// only has `ipairs`-style array keys and string attribute keys, so
+    /// Diesel silently drops entries like this rather than writing them out.
+    NonIntegerKey,
+    /// A table used as a table *key*, which Diesel crashes on outright.
+    TableKey
+}
+
+/// A step of the breadcrumb trail [`validate_for_format`] reports a
+/// [`FormatWarning`] against, standing in for the source position a
+/// `Document` doesn't otherwise carry.
+#[derive(Debug, Clone)]
+pub enum DocPathSegment {
+    Key(Rc<str>),
+    Index(i64),
+    Other
+}
+impl fmt::Display for DocPathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocPathSegment::Key(k) => write!(f, ".{}", k),
+            DocPathSegment::Index(i) => write!(f, "[{}]", i),
+            DocPathSegment::Other => write!(f, "[?]")
+        }
+    }
+}
+
+/// A single incompatibility found by [`validate_for_format`].
+#[derive(Debug, Clone)]
+pub struct FormatWarning {
+    pub path: Vec<DocPathSegment>,
+    pub issue: FormatIssue
+}
+impl fmt::Display for FormatWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "root")?;
+        for seg in &self.path {
+            write!(f, "{}", seg)?;
+        }
+        let what = match self.issue {
+            FormatIssue::Bool => "a bool, which this format can't represent",
+            FormatIssue::Float => "a non-integer number, which this format can't represent",
+            FormatIssue::IdString => "an idstring, which this format can't represent",
+            FormatIssue::Vector => "a vector, which this format can't represent",
+            FormatIssue::Quaternion => "a quaternion, which this format can't represent",
+            FormatIssue::NonIntegerKey => "a key that isn't a string or array index, so Diesel will drop it silently",
+            FormatIssue::TableKey => "a table used as a key, which Diesel will crash on"
+        };
+        write!(f, " is {}", what)
+    }
+}
+
+/// Walk `doc` and report every value that won't survive being written out
+/// as `target`, per the table at the top of this module. `binary` isn't a
+/// valid `target` because nothing is lossy there.
+///
+/// This doesn't fail on the first problem: like a typechecker, it collects
+/// everything wrong so a caller converting e.g. `world.world` learns up
+/// front exactly which fields won't survive, rather than finding out one at
+/// a time.
+pub fn validate_for_format(doc: &Document, target: TargetFormat) -> Vec<FormatWarning> {
+    let mut out = Vec::new();
+    let mut seen = FnvHashSet::<WeakCell<DocTable>>::default();
+    let mut path = Vec::new();
+    if let Some(root) = doc.root() {
+        validate_value(doc, &root, target, &mut path, &mut seen, &mut out);
+    }
+    out
+}
+
+fn validate_value(
+    doc: &Document,
+    value: &DocValue,
+    target: TargetFormat,
+    path: &mut Vec<DocPathSegment>,
+    seen: &mut FnvHashSet<WeakCell<DocTable>>,
+    out: &mut Vec<FormatWarning>
+) {
+    let issue = match value {
+        DocValue::Bool(_) => Some(FormatIssue::Bool),
+        DocValue::Number(n) => match target {
+            TargetFormat::CustomXml if n.0.fract() != 0.0 => Some(FormatIssue::Float),
+            _ => None
+        },
+        // Broken under both text formats regardless of `target`.
+        DocValue::IdString(_) => Some(FormatIssue::IdString),
+        DocValue::Vector(_) => Some(FormatIssue::Vector),
+        DocValue::Quaternion(_) => Some(FormatIssue::Quaternion),
+        DocValue::String(_) => None,
+        DocValue::Table(table) => {
+            let down = table.downgrade();
+            if seen.insert(down) {
+                validate_table(doc, &table.borrow(), target, path, seen, out);
+            }
+            None
+        }
+    };
+    if let Some(issue) = issue {
+        out.push(FormatWarning { path: path.clone(), issue });
+    }
+}
+
+fn validate_table(
+    doc: &Document,
+    table: &DocTable,
+    target: TargetFormat,
+    path: &mut Vec<DocPathSegment>,
+    seen: &mut FnvHashSet<WeakCell<DocTable>>,
+    out: &mut Vec<FormatWarning>
+) {
+    for (key, value) in table {
+        let segment = match key {
+            DocValue::String(k) => DocPathSegment::Key(doc.resolve_rc(*k)),
+            DocValue::Number(n) if n.0.fract() == 0.0 && n.0 >= 0.0 => DocPathSegment::Index(n.0 as i64),
+            _ => DocPathSegment::Other
+        };
+
+        if target == TargetFormat::CustomXml {
+            match key {
+                DocValue::String(_) => (),
+                DocValue::Number(n) if n.0.fract() == 0.0 && n.0 >= 0.0 => (),
+                DocValue::Table(_) => {
+                    path.push(segment.clone());
+                    out.push(FormatWarning { path: path.clone(), issue: FormatIssue::TableKey });
+                    path.pop();
+                },
+                _ => {
+                    path.push(segment.clone());
+                    out.push(FormatWarning { path: path.clone(), issue: FormatIssue::NonIntegerKey });
+                    path.pop();
+                }
+            }
+        }
+
+        path.push(segment);
+        validate_value(doc, value, target, path, seen, out);
+        path.pop();
+    }
+}
+
@@ -0,0 +1,664 @@
+//! Path/query mini-language for pulling specific values out of a parsed
+//! [`Document`] without hand-writing a tree walk - e.g. every `Idstring`
+//! under `world.units`, or every table tagged with a given metatable.
+//!
+//! A path is a sequence of steps, written with no separator between them:
+//! - `.name` - descend into the table entry whose key is the string `name`
+//! - `[n]` - descend into the `n`th entry of the array-like part (as
+//!   [`DocTable::ipairs`] counts it, so `1` is the first element)
+//! - `*` - fan out to every entry in the table (array-like and hash-like)
+//! - `**` - fan out to the current nodes and every value reachable from
+//!   them, at any depth; a table is only visited once even if the document
+//!   has cycles or diamonds, so this always terminates
+//! - `@name` - fan out to every entry, keeping only the tables whose
+//!   metatable is `name` - shorthand for `*[@meta == "name"]`, for the
+//!   common case of picking out every `@object`/`@unit`/etc. child without
+//!   writing the filter out longhand
+//! - `.metatable("name")` - the same thing as `@name`, spelled as a call
+//!   rather than a sigil, for callers who find `@` ambiguous with the
+//!   `@meta` predicate syntax below
+//! - `[pred]` - keep only nodes matching `pred`
+//!
+//! A predicate is `@meta == "name"` (the table's metatable), `value <op>
+//! <literal>` (the node itself), or `field <op> <literal>` (the table
+//! entry keyed by the string `field`, e.g. `_id == "foo"`) - each compared
+//! against a string, number, bool, or `IdString(0x...)` literal, combined
+//! with `&&`, `||`, `!` and parens. There's no `@id` filter: unlike the
+//! metatable, a table's id is a transient label assigned by the writer
+//! ([`super::id_tracker`]) to whichever tables turn out to be shared, not
+//! something stored on the table itself, so there's nothing to compare
+//! against after parsing.
+//!
+//! `.objects*[@meta == "unit"]` means: from `objects`, fan out to every
+//! entry, and keep the ones whose metatable is `"unit"` - the same thing
+//! `.objects@unit` says more tersely. `.objects@object[_id == "foo"]`
+//! keeps only the `@object` children whose own `_id` entry is `"foo"`.
+//!
+//! Nodes come back as owned [`DocValue`] clones rather than borrowed
+//! references: a table is reached through an [`RcCell`](crate::util::rc_cell::RcCell),
+//! and the `Ref` its [`borrow`](crate::util::rc_cell::RcCell::borrow) returns
+//! can't outlive the call that produced it, so there's no lifetime to hand
+//! a `&DocValue` back on. Cloning is cheap - scalars are `Copy`-ish and
+//! tables/strings are refcounted - and it's the same tradeoff the rest of
+//! this module already makes (see [`Document::root`]).
+
+use std::fmt;
+use std::rc::Rc;
+
+use fnv::FnvHashSet;
+
+use super::{DocTable, DocValue, Document};
+use crate::hashindex::Hash as IdString;
+use crate::util::ordered_float::OrderedFloat;
+use crate::util::rc_cell::WeakCell;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Field(Rc<str>),
+    Index(usize),
+    Wildcard,
+    Descendant,
+    Tag(Rc<str>),
+    Filter(Predicate)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selector(pub Vec<Step>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp { Eq, Ne, Lt, Le, Gt, Ge }
+
+/// A predicate's right-hand side. Spelled out as its own type, rather than
+/// reusing [`DocValue`] directly, because a `Selector` is parsed once and
+/// applied to whichever documents a caller likes (see [`select_with`]) - a
+/// string literal here is plain text, not a [`super::AtomId`] scoped to one
+/// document's atom table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Bool(bool),
+    Number(OrderedFloat),
+    IdString(IdString),
+    String(Rc<str>)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    Meta(CompareOp, Rc<str>),
+    Value(CompareOp, Literal),
+    Field(Rc<str>, CompareOp, Literal),
+    And(Vec<Predicate>),
+    Or(Vec<Predicate>),
+    Not(Box<Predicate>)
+}
+
+/// A path that failed to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathError {
+    pub message: String
+}
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+impl std::error::Error for PathError {}
+
+/// Applies `path` to `root`, returning a clone of every node it matches.
+/// `doc` is the [`Document`] `root` came from, consulted to resolve the
+/// [`super::AtomId`]s behind any [`DocValue::String`] `root` contains.
+pub fn select(doc: &Document, root: &DocValue, path: &str) -> Result<Vec<DocValue>, PathError> {
+    let selector = parse_path(path)?;
+    Ok(select_with(doc, root, &selector))
+}
+
+/// As [`select`], but starting from a [`Document`]'s root rather than a
+/// `DocValue` already in hand. Returns no matches for an empty document.
+pub fn select_document(doc: &Document, path: &str) -> Result<Vec<DocValue>, PathError> {
+    match doc.root() {
+        Some(root) => select(doc, &root, path),
+        None => Ok(Vec::new())
+    }
+}
+
+/// As [`select`], for an already-parsed [`Selector`] - useful when applying
+/// the same path to many documents, so it's only parsed once.
+pub fn select_with(doc: &Document, root: &DocValue, selector: &Selector) -> Vec<DocValue> {
+    let mut working = vec![root.clone()];
+    for step in &selector.0 {
+        working = apply_step(doc, working, step);
+    }
+    working
+}
+
+fn apply_step(doc: &Document, nodes: Vec<DocValue>, step: &Step) -> Vec<DocValue> {
+    match step {
+        Step::Field(name) => nodes.iter().filter_map(|n| field(doc, n, name)).collect(),
+        Step::Index(i) => nodes.iter().filter_map(|n| index(n, *i)).collect(),
+        Step::Wildcard => nodes.iter().flat_map(children).collect(),
+        Step::Tag(name) => nodes.iter().flat_map(children).filter(|n| has_tag(doc, n, name)).collect(),
+        Step::Descendant => {
+            let mut seen = FnvHashSet::<WeakCell<DocTable>>::default();
+            let mut out = Vec::new();
+            for n in &nodes {
+                descendants_of(n, &mut seen, &mut out);
+            }
+            out
+        },
+        Step::Filter(pred) => nodes.into_iter().filter(|n| eval_predicate(doc, n, pred)).collect()
+    }
+}
+
+fn children(node: &DocValue) -> Vec<DocValue> {
+    match node {
+        DocValue::Table(t) => (&*t.borrow()).into_iter().map(|(_, v)| v.clone()).collect(),
+        _ => Vec::new()
+    }
+}
+
+fn field(doc: &Document, node: &DocValue, name: &Rc<str>) -> Option<DocValue> {
+    match node {
+        DocValue::Table(t) => {
+            let b = t.borrow();
+            (&*b).into_iter()
+                .find(|(k, _)| matches!(k, DocValue::String(id) if doc.resolve(*id) == name.as_ref()))
+                .map(|(_, v)| v.clone())
+        },
+        _ => None
+    }
+}
+
+fn index(node: &DocValue, i: usize) -> Option<DocValue> {
+    match node {
+        DocValue::Table(t) => t.borrow().ipairs().find(|(idx, _)| *idx == i).map(|(_, v)| v),
+        _ => None
+    }
+}
+
+/// Whether `node` is a table whose metatable is `name` - what `@name`
+/// selects for, and what `@meta == "name"` tests in a predicate.
+fn has_tag(doc: &Document, node: &DocValue, name: &Rc<str>) -> bool {
+    matches!(node, DocValue::Table(t) if t.borrow().get_metatable().map(|id| doc.resolve(id) == name.as_ref()).unwrap_or(false))
+}
+
+/// Depth-first, visit-once walk of `node` and everything reachable from it,
+/// appending every node (including `node` itself) to `out`. `seen` tracks
+/// tables already visited by identity, the same guard
+/// [`Document::walk_tables`] uses, so a self-referential or mutually
+/// cyclic table doesn't recurse forever.
+fn descendants_of(node: &DocValue, seen: &mut FnvHashSet<WeakCell<DocTable>>, out: &mut Vec<DocValue>) {
+    if let DocValue::Table(t) = node {
+        if !seen.insert(t.downgrade()) {
+            return;
+        }
+    }
+    out.push(node.clone());
+    if let DocValue::Table(t) = node {
+        for (_, child) in &*t.borrow() {
+            descendants_of(child, seen, out);
+        }
+    }
+}
+
+fn eval_predicate(doc: &Document, node: &DocValue, pred: &Predicate) -> bool {
+    match pred {
+        Predicate::Meta(op, expected) => {
+            let meta = match node {
+                DocValue::Table(t) => t.borrow().get_metatable(),
+                _ => None
+            };
+            match (meta, op) {
+                (Some(m), CompareOp::Eq) => doc.resolve(m) == expected.as_ref(),
+                (Some(m), CompareOp::Ne) => doc.resolve(m) != expected.as_ref(),
+                (None, CompareOp::Eq) => false,
+                (None, CompareOp::Ne) => true,
+                _ => false
+            }
+        },
+        Predicate::Value(op, constant) => compare_to_literal(doc, node, *op, constant),
+        Predicate::Field(name, op, constant) => match field(doc, node, name) {
+            Some(v) => compare_to_literal(doc, &v, *op, constant),
+            None => false
+        },
+        Predicate::And(preds) => preds.iter().all(|p| eval_predicate(doc, node, p)),
+        Predicate::Or(preds) => preds.iter().any(|p| eval_predicate(doc, node, p)),
+        Predicate::Not(p) => !eval_predicate(doc, node, p)
+    }
+}
+
+/// Compares `node` against a parsed [`Literal`], resolving `node`'s atom
+/// through `doc` if it's a string. `node` and `constant` being different
+/// kinds (e.g. a table compared to a number) is never a match and never an
+/// order, the same as comparing `NaN` would be.
+fn compare_to_literal(doc: &Document, node: &DocValue, op: CompareOp, constant: &Literal) -> bool {
+    use std::cmp::Ordering;
+    let ord = match (node, constant) {
+        (DocValue::Bool(a), Literal::Bool(b)) => Some(a.cmp(b)),
+        (DocValue::Number(a), Literal::Number(b)) => Some(a.cmp(b)),
+        (DocValue::IdString(a), Literal::IdString(b)) => Some(a.cmp(b)),
+        (DocValue::String(a), Literal::String(b)) => Some(doc.resolve(*a).cmp(b.as_ref())),
+        _ => None
+    };
+    match (ord, op) {
+        (Some(o), CompareOp::Eq) => o == Ordering::Equal,
+        (Some(o), CompareOp::Ne) => o != Ordering::Equal,
+        (Some(o), CompareOp::Lt) => o == Ordering::Less,
+        (Some(o), CompareOp::Le) => o != Ordering::Greater,
+        (Some(o), CompareOp::Gt) => o == Ordering::Greater,
+        (Some(o), CompareOp::Ge) => o != Ordering::Less,
+        (None, CompareOp::Ne) => true,
+        (None, _) => false
+    }
+}
+
+/// Parses the path syntax described in the module doc comment.
+pub fn parse_path(src: &str) -> Result<Selector, PathError> {
+    let mut p = Parser { input: src, pos: 0 };
+    let selector = p.parse_selector()?;
+    if !p.at_end() {
+        return Err(p.err(format!("unexpected trailing input: {:.20}", p.rest())));
+    }
+    Ok(selector)
+}
+
+/// Cursor-based recursive-descent parser, in the same style as
+/// [`crate::util::query`]'s and `lua_like`'s `Parser`.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str { &self.input[self.pos..] }
+    fn at_end(&self) -> bool { self.rest().is_empty() }
+    fn err(&self, message: String) -> PathError { PathError { message: format!("{} (at byte {})", message, self.pos) } }
+
+    fn skip_ws(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        if self.rest().starts_with(punct) {
+            self.pos += punct.len();
+            self.skip_ws();
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), PathError> {
+        if self.eat(punct) { Ok(()) } else { Err(self.err(format!("expected {:?}", punct))) }
+    }
+
+    fn peek_ident(&self) -> Option<&'a str> {
+        let rest = self.rest();
+        let end = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+        if end == 0 { None } else { Some(&rest[..end]) }
+    }
+
+    fn expect_ident(&mut self) -> Result<&'a str, PathError> {
+        let ident = self.peek_ident().ok_or_else(|| self.err("expected identifier".to_owned()))?;
+        self.pos += ident.len();
+        self.skip_ws();
+        Ok(ident)
+    }
+
+    fn parse_selector(&mut self) -> Result<Selector, PathError> {
+        let mut steps = Vec::new();
+        loop {
+            if self.eat("**") {
+                steps.push(Step::Descendant);
+            }
+            else if self.eat(".") {
+                if self.peek_ident() == Some("metatable") && self.rest()["metatable".len()..].trim_start().starts_with('(') {
+                    self.expect_ident()?;
+                    self.expect("(")?;
+                    let name = self.parse_string_literal()?;
+                    self.expect(")")?;
+                    steps.push(Step::Tag(Rc::from(name.as_str())));
+                }
+                else {
+                    steps.push(Step::Field(Rc::from(self.expect_ident()?)));
+                }
+            }
+            else if self.eat("*") {
+                steps.push(Step::Wildcard);
+            }
+            else if self.eat("@") {
+                steps.push(Step::Tag(Rc::from(self.expect_ident()?)));
+            }
+            else if self.eat("[") {
+                steps.push(self.parse_bracket_step()?);
+            }
+            else {
+                break;
+            }
+        }
+        Ok(Selector(steps))
+    }
+
+    fn parse_bracket_step(&mut self) -> Result<Step, PathError> {
+        self.skip_ws();
+        if self.rest().starts_with(|c: char| c.is_ascii_digit()) {
+            let start = self.pos;
+            while self.rest().starts_with(|c: char| c.is_ascii_digit()) { self.pos += 1; }
+            let n: usize = self.input[start..self.pos].parse().map_err(|_| self.err("bad index".to_owned()))?;
+            self.skip_ws();
+            self.expect("]")?;
+            Ok(Step::Index(n))
+        }
+        else {
+            let pred = self.parse_or()?;
+            self.expect("]")?;
+            Ok(Step::Filter(pred))
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Predicate, PathError> {
+        let mut parts = vec![self.parse_and()?];
+        while self.eat("||") {
+            parts.push(self.parse_and()?);
+        }
+        Ok(if parts.len() == 1 { parts.pop().unwrap() } else { Predicate::Or(parts) })
+    }
+
+    fn parse_and(&mut self) -> Result<Predicate, PathError> {
+        let mut parts = vec![self.parse_not()?];
+        while self.eat("&&") {
+            parts.push(self.parse_not()?);
+        }
+        Ok(if parts.len() == 1 { parts.pop().unwrap() } else { Predicate::And(parts) })
+    }
+
+    fn parse_not(&mut self) -> Result<Predicate, PathError> {
+        if self.eat("!") {
+            Ok(Predicate::Not(Box::new(self.parse_not()?)))
+        }
+        else {
+            self.parse_atom()
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Predicate, PathError> {
+        self.skip_ws();
+        if self.eat("(") {
+            let pred = self.parse_or()?;
+            self.expect(")")?;
+            return Ok(pred);
+        }
+        if self.eat("@") {
+            let attr = self.expect_ident()?;
+            if attr != "meta" {
+                return Err(self.err(format!("unknown attribute '@{}' (only @meta is tracked after parsing)", attr)));
+            }
+            let op = self.parse_compare_op()?;
+            let value = self.parse_string_literal()?;
+            return Ok(Predicate::Meta(op, Rc::from(value.as_str())));
+        }
+        if self.peek_ident() == Some("value") {
+            self.expect_ident()?;
+            let op = self.parse_compare_op()?;
+            let value = self.parse_literal()?;
+            return Ok(Predicate::Value(op, value));
+        }
+        if let Some(field) = self.peek_ident() {
+            self.expect_ident()?;
+            let op = self.parse_compare_op()?;
+            let value = self.parse_literal()?;
+            return Ok(Predicate::Field(Rc::from(field), op, value));
+        }
+        Err(self.err("expected '(', '@meta', 'value', or a field name".to_owned()))
+    }
+
+    fn parse_compare_op(&mut self) -> Result<CompareOp, PathError> {
+        self.skip_ws();
+        if self.eat("==") { Ok(CompareOp::Eq) }
+        else if self.eat("!=") { Ok(CompareOp::Ne) }
+        else if self.eat("<=") { Ok(CompareOp::Le) }
+        else if self.eat(">=") { Ok(CompareOp::Ge) }
+        else if self.eat("<") { Ok(CompareOp::Lt) }
+        else if self.eat(">") { Ok(CompareOp::Gt) }
+        else { Err(self.err("expected a comparison operator".to_owned())) }
+    }
+
+    fn parse_literal(&mut self) -> Result<Literal, PathError> {
+        self.skip_ws();
+        if self.rest().starts_with('"') {
+            Ok(Literal::String(Rc::from(self.parse_string_literal()?.as_str())))
+        }
+        else if self.peek_ident() == Some("true") {
+            self.expect_ident()?;
+            Ok(Literal::Bool(true))
+        }
+        else if self.peek_ident() == Some("false") {
+            self.expect_ident()?;
+            Ok(Literal::Bool(false))
+        }
+        else if self.peek_ident() == Some("IdString") {
+            self.parse_idstring_literal()
+        }
+        else {
+            self.parse_number_literal()
+        }
+    }
+
+    /// Parses `IdString(0x...)`, the same textual form the Lua-like writer
+    /// uses for an [`IdString`], rather than inventing a different one just
+    /// for paths.
+    fn parse_idstring_literal(&mut self) -> Result<Literal, PathError> {
+        self.expect_ident()?;
+        self.expect("(")?;
+        self.expect("0x")?;
+        let start = self.pos;
+        while self.rest().starts_with(|c: char| c.is_ascii_hexdigit()) { self.pos += 1; }
+        if self.pos == start {
+            return Err(self.err("expected hex digits in IdString(0x...)".to_owned()));
+        }
+        let hex = &self.input[start..self.pos];
+        let value = u64::from_str_radix(hex, 16).map_err(|_| self.err(format!("bad IdString hex {:?}", hex)))?;
+        self.skip_ws();
+        self.expect(")")?;
+        Ok(Literal::IdString(IdString(value)))
+    }
+
+    fn parse_string_literal(&mut self) -> Result<String, PathError> {
+        self.skip_ws();
+        self.expect("\"")?;
+        let rest = self.rest();
+        let end = rest.find('"').ok_or_else(|| self.err("unterminated string literal".to_owned()))?;
+        let text = rest[..end].to_owned();
+        self.pos += end + 1;
+        self.skip_ws();
+        Ok(text)
+    }
+
+    fn parse_number_literal(&mut self) -> Result<Literal, PathError> {
+        let rest = self.rest();
+        let bytes = rest.as_bytes();
+        let start = if rest.starts_with('-') { 1 } else { 0 };
+        let mut end = start;
+        while end < bytes.len() && bytes[end].is_ascii_digit() { end += 1; }
+        if end == start {
+            return Err(self.err("expected a number literal".to_owned()));
+        }
+        if end < bytes.len() && bytes[end] == b'.' {
+            end += 1;
+            while end < bytes.len() && bytes[end].is_ascii_digit() { end += 1; }
+        }
+        let text = &rest[..end];
+        let value: f32 = text.parse().map_err(|_| self.err(format!("bad number literal {:?}", text)))?;
+        self.pos += end;
+        self.skip_ws();
+        Ok(Literal::Number(OrderedFloat(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::rc_cell::RcCell;
+
+    fn field(doc: &mut Document, table: &RcCell<DocTable>, name: &str, value: DocValue) {
+        let key = DocValue::String(doc.intern(name));
+        table.borrow_mut().insert(key, value);
+    }
+
+    fn indexed(table: &RcCell<DocTable>, index: usize, value: DocValue) {
+        table.borrow_mut().insert(DocValue::Number(OrderedFloat(index as f32)), value);
+    }
+
+    fn tagged_table(doc: &mut Document, metatable: &str) -> RcCell<DocTable> {
+        let table = RcCell::new(DocTable::new());
+        let mt = doc.intern(metatable);
+        table.borrow_mut().set_metatable(mt);
+        table
+    }
+
+    /// Builds a document shaped like:
+    /// ```text
+    /// @world {
+    ///     units = { [1]=@object{_id="foo"}, [2]=@object{_id="bar"} }
+    /// }
+    /// ```
+    fn sample_document() -> Document {
+        let mut doc = Document::new();
+
+        let obj1 = tagged_table(&mut doc, "object");
+        let foo = DocValue::String(doc.intern("foo"));
+        field(&mut doc, &obj1, "_id", foo);
+
+        let obj2 = tagged_table(&mut doc, "object");
+        let bar = DocValue::String(doc.intern("bar"));
+        field(&mut doc, &obj2, "_id", bar);
+
+        let units = RcCell::new(DocTable::new());
+        indexed(&units, 1, DocValue::Table(obj1));
+        indexed(&units, 2, DocValue::Table(obj2));
+
+        let root = tagged_table(&mut doc, "world");
+        field(&mut doc, &root, "units", DocValue::Table(units));
+
+        doc.set_root(Some(DocValue::Table(root)));
+        doc
+    }
+
+    fn select(doc: &Document, path: &str) -> Vec<DocValue> {
+        select_document(doc, path).unwrap_or_else(|e| panic!("failed to parse {:?}: {}", path, e))
+    }
+
+    fn id_string_of(doc: &Document, v: &DocValue) -> &str {
+        match v {
+            DocValue::String(id) => doc.resolve(*id),
+            other => panic!("expected a string value, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn field_step_descends_into_a_named_entry() {
+        let doc = sample_document();
+        let found = select(&doc, ".units");
+        assert_eq!(found.len(), 1);
+        assert!(matches!(found[0], DocValue::Table(_)));
+    }
+
+    #[test]
+    fn index_step_selects_by_array_position() {
+        let doc = sample_document();
+        let found = select(&doc, ".units[1]._id");
+        assert_eq!(found.len(), 1);
+        assert_eq!(id_string_of(&doc, &found[0]), "foo");
+    }
+
+    #[test]
+    fn wildcard_step_fans_out_to_every_entry() {
+        let doc = sample_document();
+        let found = select(&doc, ".units*._id");
+        let mut names: Vec<&str> = found.iter().map(|v| id_string_of(&doc, v)).collect();
+        names.sort();
+        assert_eq!(names, vec!["bar", "foo"]);
+    }
+
+    #[test]
+    fn descendant_step_finds_nodes_at_any_depth_and_is_cycle_safe() {
+        let mut doc = Document::new();
+        let a = RcCell::new(DocTable::new());
+        let b = RcCell::new(DocTable::new());
+        field(&mut doc, &a, "next", DocValue::Table(b.clone()));
+        field(&mut doc, &b, "next", DocValue::Table(a.clone())); // cycle back to a
+        doc.set_root(Some(DocValue::Table(a)));
+
+        let found = select_document(&doc, "**").unwrap();
+        // Just `a` and `b`, each visited once despite the cycle.
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn tag_step_keeps_only_matching_metatables() {
+        let doc = sample_document();
+        let found = select(&doc, ".units@object");
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn metatable_call_syntax_is_equivalent_to_the_tag_step() {
+        let doc = sample_document();
+        let via_tag = select(&doc, ".units@object");
+        let via_call = select(&doc, r#".units.metatable("object")"#);
+        assert_eq!(via_tag.len(), via_call.len());
+    }
+
+    #[test]
+    fn field_predicate_filters_on_a_sibling_attribute() {
+        let doc = sample_document();
+        let found = select(&doc, r#".units*[_id == "bar"]._id"#);
+        assert_eq!(found.len(), 1);
+        assert_eq!(id_string_of(&doc, &found[0]), "bar");
+    }
+
+    #[test]
+    fn meta_predicate_filters_by_metatable_name() {
+        let doc = sample_document();
+        let found = select(&doc, r#".units*[@meta == "object"]"#);
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn predicates_combine_with_and_or_not() {
+        let doc = sample_document();
+        let and_found = select(&doc, r#".units*[@meta == "object" && _id == "foo"]"#);
+        assert_eq!(and_found.len(), 1);
+
+        let or_found = select(&doc, r#".units*[_id == "foo" || _id == "bar"]"#);
+        assert_eq!(or_found.len(), 2);
+
+        let not_found = select(&doc, r#".units*[!(_id == "foo")]"#);
+        assert_eq!(not_found.len(), 1);
+    }
+
+    #[test]
+    fn idstring_literal_compares_against_an_idstring_field() {
+        let mut doc = Document::new();
+        let root = RcCell::new(DocTable::new());
+        field(&mut doc, &root, "sound", DocValue::IdString(IdString(0xdead_beef)));
+        doc.set_root(Some(DocValue::Table(root)));
+
+        let found = select_document(&doc, "[sound == IdString(0xdeadbeef)]").unwrap();
+        assert_eq!(found.len(), 1);
+
+        let not_found = select_document(&doc, "[sound == IdString(0x1)]").unwrap();
+        assert_eq!(not_found.len(), 0);
+    }
+
+    #[test]
+    fn parse_path_rejects_trailing_garbage() {
+        assert!(parse_path(".units extra").is_err());
+    }
+
+    #[test]
+    fn select_document_returns_no_matches_for_an_empty_document() {
+        let doc = Document::new();
+        assert_eq!(select_document(&doc, ".anything").unwrap(), Vec::new());
+    }
+}
@@ -0,0 +1,339 @@
+//! Describing the expected shape of a [`Document`] and checking a loaded
+//! tree against it.
+//!
+//! This is a different concern from [`validate_for_format`](super::validate_for_format):
+//! that one checks whether a document will round-trip through a lossy text
+//! format, while a [`Schema`] here checks whether a document matches the
+//! shape some piece of downstream code actually expects - which metatables
+//! exist, what attributes they require, and what type each of those
+//! attributes and array-like children should be. That matters because
+//! `custom_xml`'s `parse_scalar` silently coerces untyped text into a
+//! `DocValue` (`"1"` always becomes a `Number`, `"true"` always a `Bool`),
+//! so nothing else catches a field that was mistyped in the source file.
+//!
+//! Like [`validate_for_format`](super::validate_for_format), a `Document`
+//! carries no source position once parsed, so mismatches are reported
+//! against a breadcrumb trail of [`DocPathSegment`]s instead, the same way
+//! `custom_xml::Loader::finish` pairs each [`LoadError`](super::custom_xml::LoadError)
+//! with the byte offset it was found at.
+
+use std::rc::Rc;
+use std::fmt;
+
+use fnv::{FnvHashMap, FnvHashSet};
+
+use crate::util::rc_cell::WeakCell;
+use super::{Document, DocValue, DocTable, DocPathSegment};
+
+/// The type a [`Schema`] expects an attribute or array-like child to have.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueType {
+    Bool,
+    Number,
+    IdString,
+    Vector,
+    Quaternion,
+    String,
+    /// A nested table, itself required to carry the given metatable.
+    Table(Rc<str>),
+    /// Matches if any of the alternatives do - for an attribute whose shape
+    /// genuinely varies, rather than one that's just optional (that's what
+    /// [`AttributeSchema::required`] is for).
+    OneOf(Vec<ValueType>),
+    /// A string restricted to one of a fixed set of values, e.g. a mode
+    /// attribute - [`validate`] reports a non-matching string as
+    /// [`ValidationError::BadValue`] rather than the generic
+    /// [`ValidationError::WrongAttributeType`], since the attribute did
+    /// have the right shape, just not an allowed value.
+    StringEnum(Vec<Rc<str>>)
+}
+impl ValueType {
+    fn matches(&self, doc: &Document, value: &DocValue) -> bool {
+        match (self, value) {
+            (ValueType::Bool, DocValue::Bool(_)) => true,
+            (ValueType::Number, DocValue::Number(_)) => true,
+            (ValueType::IdString, DocValue::IdString(_)) => true,
+            (ValueType::Vector, DocValue::Vector(_)) => true,
+            (ValueType::Quaternion, DocValue::Quaternion(_)) => true,
+            (ValueType::String, DocValue::String(_)) => true,
+            (ValueType::Table(meta), DocValue::Table(t)) => t.borrow().get_metatable().map(|m| doc.resolve(m) == meta.as_ref()).unwrap_or(false),
+            (ValueType::OneOf(alts), _) => alts.iter().any(|ty| ty.matches(doc, value)),
+            (ValueType::StringEnum(allowed), DocValue::String(id)) => allowed.iter().any(|a| a.as_ref() == doc.resolve(*id)),
+            _ => false
+        }
+    }
+}
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueType::Bool => write!(f, "bool"),
+            ValueType::Number => write!(f, "number"),
+            ValueType::IdString => write!(f, "idstring"),
+            ValueType::Vector => write!(f, "vector"),
+            ValueType::Quaternion => write!(f, "quaternion"),
+            ValueType::String => write!(f, "string"),
+            ValueType::Table(meta) => write!(f, "table of {}", meta),
+            ValueType::OneOf(alts) => {
+                write!(f, "one of (")?;
+                for (i, ty) in alts.iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{}", ty)?;
+                }
+                write!(f, ")")
+            },
+            ValueType::StringEnum(allowed) => {
+                write!(f, "one of (")?;
+                for (i, a) in allowed.iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{:?}", a)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// One attribute a [`TableSchema`] expects to find in a table's dict-like
+/// part.
+#[derive(Debug, Clone)]
+pub struct AttributeSchema {
+    pub name: Rc<str>,
+    pub ty: ValueType,
+    pub required: bool
+}
+
+/// The expected shape of every table carrying a given metatable: its
+/// dict-like attributes, and the type(s) its array-like children are
+/// allowed to be.
+#[derive(Debug, Clone, Default)]
+pub struct TableSchema {
+    pub attributes: Vec<AttributeSchema>,
+    pub array_item_types: Vec<ValueType>,
+    /// Whether an attribute not listed above is tolerated rather than
+    /// flagged as [`ValidationError::UnknownAttribute`] - for a table
+    /// modders are known to bolt extra, unvalidated fields onto.
+    pub open: bool
+}
+impl TableSchema {
+    pub fn new() -> TableSchema { Default::default() }
+
+    pub fn add_attribute(&mut self, name: impl Into<Rc<str>>, ty: ValueType, required: bool) -> &mut Self {
+        self.attributes.push(AttributeSchema { name: name.into(), ty, required });
+        self
+    }
+
+    pub fn add_array_item_type(&mut self, ty: ValueType) -> &mut Self {
+        self.array_item_types.push(ty);
+        self
+    }
+
+    pub fn set_open(&mut self, open: bool) -> &mut Self {
+        self.open = open;
+        self
+    }
+
+    fn attribute(&self, name: &str) -> Option<&AttributeSchema> {
+        self.attributes.iter().find(|a| a.name.as_ref() == name)
+    }
+}
+
+/// A set of [`TableSchema`]s, keyed by metatable name, that
+/// [`validate`] checks a [`Document`] against.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    tables: FnvHashMap<Rc<str>, TableSchema>
+}
+impl Schema {
+    pub fn new() -> Schema { Default::default() }
+
+    pub fn add_table(&mut self, metatable: impl Into<Rc<str>>, schema: TableSchema) -> &mut Self {
+        self.tables.insert(metatable.into(), schema);
+        self
+    }
+
+    fn table(&self, metatable: &str) -> Option<&TableSchema> {
+        self.tables.get(metatable)
+    }
+}
+
+/// A single way a [`Document`] failed to match a [`Schema`], found by
+/// [`validate`].
+#[derive(Debug, Clone)]
+pub enum ValidationError {
+    /// The table's metatable isn't declared in the schema at all, so none
+    /// of its attributes or children could be checked either.
+    UnknownMetatable(Rc<str>),
+    /// A table has no metatable, so there's nothing to look up in the
+    /// schema.
+    NoMetatable,
+    MissingAttribute(Rc<str>),
+    WrongAttributeType { name: Rc<str>, expected: ValueType },
+    /// An attribute matched its declared shape (e.g. it is a string) but
+    /// not a [`ValueType::StringEnum`]'s allowed values.
+    BadValue { name: Rc<str>, value: Rc<str>, allowed: Vec<Rc<str>> },
+    UnknownAttribute(Rc<str>),
+    IllegalArrayItem
+}
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::UnknownMetatable(mt) => write!(f, "metatable {:?} isn't declared in the schema", mt),
+            ValidationError::NoMetatable => write!(f, "table has no metatable to look up in the schema"),
+            ValidationError::MissingAttribute(name) => write!(f, "missing required attribute {:?}", name),
+            ValidationError::WrongAttributeType { name, expected } => write!(f, "attribute {:?} should be a {}", name, expected),
+            ValidationError::BadValue { name, value, allowed } => {
+                write!(f, "attribute {:?} is {:?}, which isn't one of (", name, value)?;
+                for (i, a) in allowed.iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{:?}", a)?;
+                }
+                write!(f, ")")
+            },
+            ValidationError::UnknownAttribute(name) => write!(f, "attribute {:?} isn't declared in the schema", name),
+            ValidationError::IllegalArrayItem => write!(f, "array item doesn't match any of the schema's permitted types")
+        }
+    }
+}
+
+/// A single mismatch [`validate`] found, located by a breadcrumb trail of
+/// [`DocPathSegment`]s standing in for the source position a `Document`
+/// doesn't otherwise carry.
+#[derive(Debug, Clone)]
+pub struct ValidationIssue {
+    pub path: Vec<DocPathSegment>,
+    pub error: ValidationError
+}
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "root")?;
+        for seg in &self.path {
+            write!(f, "{}", seg)?;
+        }
+        write!(f, ": {}", self.error)
+    }
+}
+
+/// Walk `doc` and check every table reachable from the root against
+/// `schema`, by its `get_metatable()`. Like [`validate_for_format`](super::validate_for_format),
+/// this doesn't stop at the first problem: it collects every mismatch it
+/// finds, and a table whose own metatable isn't recognised is still
+/// recursed into (its children are still worth checking, even though
+/// nothing is known about the table itself). Each distinct table is only
+/// visited once, so a cyclic or shared document still terminates.
+pub fn validate(doc: &Document, schema: &Schema) -> Vec<ValidationIssue> {
+    let mut out = Vec::new();
+    let mut seen = FnvHashSet::<WeakCell<DocTable>>::default();
+    let mut path = Vec::new();
+    if let Some(DocValue::Table(root)) = doc.root() {
+        seen.insert(root.downgrade());
+        validate_table(doc, &root.borrow(), schema, &mut path, &mut seen, &mut out);
+    }
+    out
+}
+
+fn validate_value(
+    doc: &Document,
+    value: &DocValue,
+    schema: &Schema,
+    path: &mut Vec<DocPathSegment>,
+    seen: &mut FnvHashSet<WeakCell<DocTable>>,
+    out: &mut Vec<ValidationIssue>
+) {
+    if let DocValue::Table(table) = value {
+        let down = table.downgrade();
+        if seen.insert(down) {
+            validate_table(doc, &table.borrow(), schema, path, seen, out);
+        }
+    }
+}
+
+fn validate_table(
+    doc: &Document,
+    table: &DocTable,
+    schema: &Schema,
+    path: &mut Vec<DocPathSegment>,
+    seen: &mut FnvHashSet<WeakCell<DocTable>>,
+    out: &mut Vec<ValidationIssue>
+) {
+    let table_schema = match table.get_metatable() {
+        None => {
+            out.push(ValidationIssue { path: path.clone(), error: ValidationError::NoMetatable });
+            None
+        },
+        Some(mt) => {
+            let mt = doc.resolve_rc(mt);
+            match schema.table(&mt) {
+                Some(ts) => Some(ts),
+                None => {
+                    out.push(ValidationIssue { path: path.clone(), error: ValidationError::UnknownMetatable(mt) });
+                    None
+                }
+            }
+        }
+    };
+
+    if let Some(ts) = table_schema {
+        for attr in &ts.attributes {
+            let present = (&*table).into_iter()
+                .any(|(k, _)| matches!(k, DocValue::String(id) if doc.resolve(*id) == attr.name.as_ref()));
+            if attr.required && !present {
+                out.push(ValidationIssue {
+                    path: path.clone(),
+                    error: ValidationError::MissingAttribute(attr.name.clone())
+                });
+            }
+        }
+    }
+
+    // The array-like part, tracked separately so the dict-like pass below
+    // can recognise custom_xml's double-insertion convention: a
+    // non-"table"-tagged array child is inserted under both its numeric
+    // index and a string key equal to its own tag, both pointing at the
+    // identical value. That shadow string key isn't a real attribute, so
+    // it would be wrong to flag it as unknown.
+    let array_items: Vec<DocValue> = table.ipairs().map(|(_, v)| v).collect();
+    for (i, item) in array_items.iter().enumerate() {
+        path.push(DocPathSegment::Index(i as i64 + 1));
+        if let Some(ts) = table_schema {
+            if !ts.array_item_types.is_empty() && !ts.array_item_types.iter().any(|ty| ty.matches(doc, item)) {
+                out.push(ValidationIssue { path: path.clone(), error: ValidationError::IllegalArrayItem });
+            }
+        }
+        validate_value(doc, item, schema, path, seen, out);
+        path.pop();
+    }
+
+    for (key, value) in table {
+        let name = match key {
+            DocValue::String(k) => doc.resolve_rc(*k),
+            DocValue::Number(_) => continue, // the array-like part, already handled above
+            _ => continue
+        };
+
+        if array_items.contains(value) {
+            continue; // the custom_xml tag-name shadow key for an array item
+        }
+
+        path.push(DocPathSegment::Key(name.clone()));
+        if let Some(ts) = table_schema {
+            match ts.attribute(&name) {
+                Some(attr) => if !attr.ty.matches(doc, value) {
+                    let error = match (&attr.ty, value) {
+                        (ValueType::StringEnum(allowed), DocValue::String(id)) => ValidationError::BadValue {
+                            name: attr.name.clone(),
+                            value: doc.resolve_rc(*id),
+                            allowed: allowed.clone()
+                        },
+                        _ => ValidationError::WrongAttributeType { name: attr.name.clone(), expected: attr.ty.clone() }
+                    };
+                    out.push(ValidationIssue { path: path.clone(), error });
+                },
+                None => if !ts.open {
+                    out.push(ValidationIssue { path: path.clone(), error: ValidationError::UnknownAttribute(name.clone()) });
+                }
+            }
+        }
+        validate_value(doc, value, schema, path, seen, out);
+        path.pop();
+    }
+}
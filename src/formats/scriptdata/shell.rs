@@ -0,0 +1,253 @@
+//! An interactive REPL for loading a scriptdata document once and poking at
+//! it: walking into tables by index or key, listing the tables a diamond
+//! `_id`/`_ref` pair would be needed for, and converting the loaded document
+//! to another format - all without re-running the CLI for each question.
+//! Handy for picking apart Overkill's more creatively-nested `unit`/`object`
+//! XML by hand.
+
+use std::path::Path;
+use std::rc::Rc;
+
+use rustyline::error::ReadlineError;
+use rustyline::Editor;
+
+use super::id_tracker::{IdTracker, RefCheck};
+use super::{binary, binary_transfer, custom_xml, generic_xml, Document, DocValue};
+
+pub fn run(initial_file: Option<&str>) -> anyhow::Result<()> {
+    let mut state = ShellState { doc: None };
+    if let Some(f) = initial_file {
+        state.load(f);
+    }
+
+    let mut rl = Editor::<()>::new()?;
+    loop {
+        match rl.readline("scriptdata> ") {
+            Ok(line) => {
+                rl.add_history_entry(line.as_str());
+                if !state.dispatch(line.trim()) {
+                    break;
+                }
+            },
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                println!("Readline error: {}", e);
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+struct ShellState {
+    doc: Option<Document>
+}
+
+impl ShellState {
+    /// Returns `false` when the shell should exit.
+    fn dispatch(&mut self, line: &str) -> bool {
+        if line.is_empty() { return true; }
+
+        let mut parts = line.splitn(2, char::is_whitespace);
+        let cmd = parts.next().unwrap_or("");
+        let rest = parts.next().unwrap_or("").trim();
+
+        match cmd {
+            "load" => self.load(rest),
+            "show" => self.show(rest),
+            "convert" => self.convert(rest),
+            "ids" => self.ids(),
+            "help" => print_help(),
+            "quit" | "exit" => return false,
+            _ => println!("Unknown command {:?}. Try \"help\".", cmd)
+        }
+        true
+    }
+
+    fn load(&mut self, filename: &str) {
+        if filename.is_empty() {
+            println!("Usage: load <file>");
+            return;
+        }
+        match load_autodetect(Path::new(filename)) {
+            Ok((doc, kind)) => {
+                println!("Loaded {:?} as {}", filename, kind);
+                self.doc = Some(doc);
+            },
+            Err(e) => println!("Failed to load {:?}: {}", filename, e)
+        }
+    }
+
+    fn show(&self, path: &str) {
+        let doc = match self.require_doc() { Some(d) => d, None => return };
+        let root = match doc.root() {
+            Some(r) => r,
+            None => { println!("nil"); return; }
+        };
+
+        match walk_path(doc, &root, path) {
+            Ok(val) => println!("{}", describe(doc, &val)),
+            Err(e) => println!("{}", e)
+        }
+    }
+
+    fn convert(&self, rest: &str) {
+        let doc = match self.require_doc() { Some(d) => d, None => return };
+
+        let mut parts = rest.splitn(2, char::is_whitespace);
+        let format = parts.next().unwrap_or("");
+        let outfile = parts.next().unwrap_or("").trim();
+        if format.is_empty() || outfile.is_empty() {
+            println!("Usage: convert <custom_xml|generic_xml|binary> <outfile>");
+            return;
+        }
+
+        let bytes: Vec<u8> = match format {
+            "custom_xml" => custom_xml::dump(doc).into_bytes(),
+            "generic_xml" => generic_xml::dump(doc).into_bytes(),
+            "binary" => binary_transfer::write_binary(doc),
+            other => {
+                println!("Unknown output format {:?}. Try custom_xml, generic_xml, or binary.", other);
+                return;
+            }
+        };
+
+        match std::fs::write(outfile, &bytes) {
+            Ok(()) => println!("Wrote {} bytes to {:?}", bytes.len(), outfile),
+            Err(e) => println!("Failed to write {:?}: {}", outfile, e)
+        }
+    }
+
+    fn ids(&self) {
+        let doc = match self.require_doc() { Some(d) => d, None => return };
+
+        let ids = collect_ids(doc);
+        if ids.is_empty() {
+            println!("No tables are referenced more than once, so dumping wouldn't assign any _id/_ref pairs.");
+            return;
+        }
+        for (id, meta) in ids {
+            match meta {
+                Some(m) => println!("_id=\"{}\" (meta: {:?})", id, m),
+                None => println!("_id=\"{}\"", id)
+            }
+        }
+    }
+
+    fn require_doc(&self) -> Option<&Document> {
+        let doc = self.doc.as_ref();
+        if doc.is_none() {
+            println!("No document loaded. Use \"load <file>\" first.");
+        }
+        doc
+    }
+}
+
+/// Assigns the same sequential `_id`s [`custom_xml::dump`] would, to every
+/// table [`Document::tables_used_repeatedly`] flags as a diamond (i.e. every
+/// table that would be written with a `_id`/`_ref` pair rather than inline),
+/// in the order dumping would first reach them.
+fn collect_ids(doc: &Document) -> Vec<(usize, Option<Rc<str>>)> {
+    let mut tracker = IdTracker::new(doc);
+    let mut found = Vec::new();
+    if let Some(root) = doc.root() {
+        walk_for_ids(doc, &root, &mut tracker, &mut found);
+    }
+    found
+}
+
+fn walk_for_ids(doc: &Document, item: &DocValue, tracker: &mut IdTracker, found: &mut Vec<(usize, Option<Rc<str>>)>) {
+    if let DocValue::Table(tab) = item {
+        match tracker.track_table(tab) {
+            RefCheck::Ref(_) => (),
+            RefCheck::Id(id) => {
+                found.push((id, tab.borrow().get_metatable().map(|m| doc.resolve_rc(m))));
+                for (_, v) in &*tab.borrow() {
+                    walk_for_ids(doc, v, tracker, found);
+                }
+            },
+            RefCheck::None => {
+                for (_, v) in &*tab.borrow() {
+                    walk_for_ids(doc, v, tracker, found);
+                }
+            }
+        }
+    }
+}
+
+/// Walks `root` by a dotted path of table keys, e.g. `show foo.3.bar`: each
+/// segment is tried as an array index first, falling back to a string key.
+fn walk_path(doc: &Document, root: &DocValue, path: &str) -> Result<DocValue, String> {
+    let mut current = root.clone();
+    if path.is_empty() { return Ok(current); }
+
+    for segment in path.split('.') {
+        let table = match &current {
+            DocValue::Table(t) => t,
+            other => return Err(format!("{} is a scalar, can't walk into {:?}", describe(doc, other), segment))
+        };
+
+        let tb = table.borrow();
+        let found = match segment.parse::<usize>() {
+            Ok(idx) => tb.get(&DocValue::from(idx as f32)).cloned(),
+            Err(_) => (&*tb).into_iter()
+                .find(|(k, _)| matches!(k, DocValue::String(id) if doc.resolve(*id) == segment))
+                .map(|(_, v)| v.clone())
+        };
+
+        match found {
+            Some(v) => current = v,
+            None => return Err(format!("No entry {:?}", segment))
+        }
+    }
+
+    Ok(current)
+}
+
+fn describe(doc: &Document, val: &DocValue) -> String {
+    match val {
+        DocValue::Bool(b) => format!("{}", b),
+        DocValue::Number(n) => format!("{}", n.0),
+        DocValue::IdString(i) => format!("@ID{}@", i),
+        DocValue::String(s) => format!("{:?}", doc.resolve(*s)),
+        DocValue::Vector(v) => format!("{} {} {}", v.x.0, v.y.0, v.z.0),
+        DocValue::Quaternion(q) => format!("{} {} {} {}", q.x.0, q.y.0, q.z.0, q.w.0),
+        DocValue::Table(t) => {
+            let tb = t.borrow();
+            match tb.get_metatable() {
+                Some(m) => format!("table ({} entries, meta {:?})", tb.len(), doc.resolve(m)),
+                None => format!("table ({} entries)", tb.len())
+            }
+        }
+    }
+}
+
+/// Sniffs `path` as custom_xml, generic_xml, or binary the way [`load`] in
+/// the shell's namesake needs to: if it's UTF-8 text starting with `<`, it's
+/// one of the XML formats (distinguished by the root element); otherwise
+/// it's read as Diesel's own binary scriptdata with [`binary::load`].
+fn load_autodetect(path: &Path) -> anyhow::Result<(Document, &'static str)> {
+    let bytes = std::fs::read(path)?;
+    if let Ok(text) = std::str::from_utf8(&bytes) {
+        if text.trim_start().starts_with('<') {
+            if let Ok(xmldoc) = roxmltree::Document::parse(text) {
+                if xmldoc.root_element().tag_name().name() == "generic_scriptdata" {
+                    return Ok((generic_xml::load(text)?, "generic_xml"));
+                }
+            }
+            return Ok((custom_xml::load(text)?, "custom_xml"));
+        }
+    }
+    Ok((binary::load(&bytes)?, "binary"))
+}
+
+fn print_help() {
+    println!("Commands:");
+    println!("  load <file>            load a document, autodetecting custom_xml/generic_xml/binary");
+    println!("  show <path>            print the value at a dotted index/key path, e.g. \"show foo.3.bar\"");
+    println!("  convert <fmt> <out>    write the loaded document as custom_xml, generic_xml, or binary");
+    println!("  ids                    list the _id values a custom_xml dump would assign to shared tables");
+    println!("  help                   show this message");
+    println!("  quit                   leave the shell");
+}
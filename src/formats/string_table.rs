@@ -1,6 +1,8 @@
 use std::collections::BTreeMap;
 
-use crate::hashindex::{HashIndex, HashedStr};
+use fnv::FnvHashMap;
+
+use crate::hashindex::{HashIndex, HashedStr, is_hash_like};
 use crate::diesel_hash;
 use crate::util::*;
 
@@ -46,6 +48,169 @@ pub fn map_from_bytes<'a>(hashlist: &'a HashIndex, bytes: &[u8]) -> BTreeMap<Has
     result
 }
 
+/// The inverse of [`map_from_bytes`]: lays `map` out as a Diesel string
+/// table. Identical values share one pool offset, and entries whose hash is
+/// `diesel_hash::EMPTY` (the empty-string sentinel `map_from_bytes` skips on
+/// the way in) are skipped on the way out too.
+pub fn map_to_bytes(map: &BTreeMap<HashedStr, String>) -> Vec<u8> {
+    let entries: Vec<&HashedStr> = map.keys().filter(|k| k.hash != diesel_hash::EMPTY).collect();
+
+    let header_len = 32 + entries.len() * 24;
+    let mut out = vec![0u8; header_len];
+    out[4..8].copy_from_slice(&(entries.len() as u32).to_le_bytes());
+
+    let mut pool = Vec::<u8>::new();
+    let mut pool_offsets = FnvHashMap::<&str, u32>::default();
+
+    for (i, key) in entries.iter().enumerate() {
+        let value = &map[*key];
+        let value_offset = *pool_offsets.entry(value.as_str()).or_insert_with(|| {
+            let offset = (header_len + pool.len()) as u32;
+            pool.extend_from_slice(value.as_bytes());
+            pool.push(0);
+            offset
+        });
+
+        let entry_base = 32 + i * 24;
+        out[(entry_base + 8)..(entry_base + 16)].copy_from_slice(&key.hash.to_le_bytes());
+        out[(entry_base + 20)..(entry_base + 24)].copy_from_slice(&value_offset.to_le_bytes());
+    }
+
+    out.extend_from_slice(&pool);
+    out
+}
+
+/// Parses the JSON object [`bytes_to_json`] produces (or anything in the
+/// same shape: a flat object of string keys to string values) straight into
+/// the on-disk string table layout, via [`map_to_bytes`]. A key that looks
+/// like a bare 16-hex-digit hash (the form `HashedStr::fmt` falls back to
+/// when it has no resolved text) is taken as that hash directly; any other
+/// key is hashed with [`diesel_hash::hash_str`], exactly as a translator
+/// typing a new key by hand would expect.
+pub fn json_to_bytes(input: &str) -> Result<Vec<u8>, JsonParseError> {
+    let mut map = BTreeMap::<HashedStr, String>::new();
+    for (key, value) in parse_json_object(input)? {
+        let hash = if is_hash_like(&key) {
+            u64::from_str_radix(&key, 16).map_err(|e| JsonParseError { message: format!("bad hash-like key {:?}: {}", key, e) })?
+        }
+        else {
+            diesel_hash::hash_str(&key)
+        };
+        map.insert(HashedStr { hash, text: None }, value);
+    }
+    Ok(map_to_bytes(&map))
+}
+
+#[derive(Debug)]
+pub struct JsonParseError { message: String }
+impl std::fmt::Display for JsonParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+impl std::error::Error for JsonParseError { }
+
+fn parse_json_object(input: &str) -> Result<Vec<(String, String)>, JsonParseError> {
+    let mut p = JsonParser { input: input.as_bytes(), pos: 0 };
+    let mut pairs = Vec::new();
+
+    p.skip_ws();
+    p.expect(b'{')?;
+    p.skip_ws();
+    if p.peek() != Some(b'}') {
+        loop {
+            p.skip_ws();
+            let key = p.parse_string()?;
+            p.skip_ws();
+            p.expect(b':')?;
+            p.skip_ws();
+            let value = p.parse_string()?;
+            pairs.push((key, value));
+            p.skip_ws();
+            match p.peek() {
+                Some(b',') => { p.pos += 1; },
+                Some(b'}') => break,
+                other => return Err(p.err(format!("expected ',' or '}}', found {:?}", other)))
+            }
+        }
+    }
+    p.expect(b'}')?;
+    p.skip_ws();
+    if p.pos != p.input.len() {
+        return Err(p.err(format!("trailing garbage at byte {}", p.pos)));
+    }
+
+    Ok(pairs)
+}
+
+struct JsonParser<'a> { input: &'a [u8], pos: usize }
+impl<'a> JsonParser<'a> {
+    fn peek(&self) -> Option<u8> { self.input.get(self.pos).copied() }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, b: u8) -> Result<(), JsonParseError> {
+        if self.peek() == Some(b) {
+            self.pos += 1;
+            Ok(())
+        }
+        else {
+            Err(self.err(format!("expected {:?}, found {:?}", b as char, self.peek())))
+        }
+    }
+
+    fn err(&self, message: String) -> JsonParseError { JsonParseError { message } }
+
+    fn parse_string(&mut self) -> Result<String, JsonParseError> {
+        self.expect(b'"')?;
+        let mut s = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.err("unterminated string".to_owned())),
+                Some(b'"') => { self.pos += 1; break; },
+                Some(b'\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(b'"') => { s.push('"'); self.pos += 1; },
+                        Some(b'\\') => { s.push('\\'); self.pos += 1; },
+                        Some(b'/') => { s.push('/'); self.pos += 1; },
+                        Some(b'b') => { s.push('\u{8}'); self.pos += 1; },
+                        Some(b'f') => { s.push('\u{c}'); self.pos += 1; },
+                        Some(b'n') => { s.push('\n'); self.pos += 1; },
+                        Some(b'r') => { s.push('\r'); self.pos += 1; },
+                        Some(b't') => { s.push('\t'); self.pos += 1; },
+                        Some(b'u') => {
+                            self.pos += 1;
+                            let hex = self.input.get(self.pos..self.pos + 4)
+                                .and_then(|b| std::str::from_utf8(b).ok())
+                                .ok_or_else(|| self.err("truncated \\u escape".to_owned()))?;
+                            let code = u32::from_str_radix(hex, 16)
+                                .map_err(|e| self.err(format!("bad \\u escape {:?}: {}", hex, e)))?;
+                            let ch = char::from_u32(code)
+                                .ok_or_else(|| self.err(format!("invalid code point {:04x}", code)))?;
+                            s.push(ch);
+                            self.pos += 4;
+                        },
+                        other => return Err(self.err(format!("unsupported escape {:?}", other.map(|b| b as char))))
+                    }
+                },
+                Some(_) => {
+                    let rest = std::str::from_utf8(&self.input[self.pos..])
+                        .map_err(|e| self.err(format!("invalid UTF-8: {}", e)))?;
+                    let ch = rest.chars().next().unwrap();
+                    s.push(ch);
+                    self.pos += ch.len_utf8();
+                }
+            }
+        }
+        Ok(s)
+    }
+}
+
 pub fn bytes_to_json<'a, O: std::io::Write>(hashlist: &'a HashIndex, input: &[u8], output: &mut O) -> std::io::Result<()> {
     let map = map_from_bytes(hashlist, input);
     output.write(b"{\n  ")?;
@@ -1,8 +1,12 @@
 use std::borrow::Cow;
+use std::ops::Range;
 
-use nom::{IResult, branch::alt, bytes::complete::{is_not, take_until, take_while, take_while1}, character::complete::digit1, combinator::{flat_map, map, map_opt, success}, error::{VerboseError, context}, sequence::{delimited, preceded, separated_pair, tuple}};
-use nom::bytes::complete::{tag};
-use nom::character::complete::{hex_digit1};
+use nom::{IResult, branch::alt, bytes::complete::{is_not, tag, take_until, take_while}, character::complete::{digit1, hex_digit1}, combinator::{map, map_opt, success}, error::{VerboseError, context}, sequence::{delimited, preceded, separated_pair}};
+
+use crate::util::subslice::Subslice;
+
+type Input<'a> = Subslice<'a, u8>;
+type PResult<'a, O> = IResult<Input<'a>, O, VerboseError<Input<'a>>>;
 
 pub enum Token<'a> {
     Text(Cow<'a, str>),
@@ -21,50 +25,124 @@ pub enum Token<'a> {
 /// This does not care about misnesting, duplicate attributes, spaces
 /// between attributes, quoting attributes that don't have spaces in,
 /// comments with hyphens in, multiple nodes at the root level, etc.
-struct ForgivingTokeniser<'a> {
-    src: &'a str,
-    current_index: usize
+///
+/// Iterates `(Token, Range<usize>)` pairs, the range being the token's byte span in the
+/// original `&str` it was built from, so a caller can convert that into a line/column and
+/// report precisely where a document went wrong instead of only knowing that it did.
+pub struct ForgivingTokeniser<'a> {
+    remaining: Input<'a>,
+    mode: Mode,
+    current_element: Cow<'a, str>,
+    done: bool
+}
+
+enum Mode {
+    /// Looking for markup (tags, comments, entities...) or plain text between it.
+    Text,
+    /// Just past a start tag's name, looking for attributes and the `>`/`/>` that ends it.
+    Tag
 }
 
-fn nom_comment<'a>(input: &'a str) -> IResult<&str, Token<'a>, VerboseError<&str>> {
+impl<'a> ForgivingTokeniser<'a> {
+    pub fn new(src: &'a str) -> ForgivingTokeniser<'a> {
+        ForgivingTokeniser {
+            remaining: Subslice::from(src.as_bytes()),
+            mode: Mode::Text,
+            current_element: Cow::from(""),
+            done: false
+        }
+    }
+}
+
+impl<'a> Iterator for ForgivingTokeniser<'a> {
+    type Item = (Token<'a>, Range<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.remaining.len() == 0 {
+            return None;
+        }
+
+        let start = self.remaining.offset();
+        let parsed = match self.mode {
+            Mode::Text => mode_text(self.remaining),
+            Mode::Tag => mode_tag(self.remaining)
+        };
+
+        match parsed {
+            Ok((rest, token)) => {
+                let end = rest.offset();
+                self.remaining = rest;
+
+                let token = match token {
+                    Token::StartElement(name) => {
+                        self.current_element = name.clone();
+                        self.mode = Mode::Tag;
+                        Token::StartElement(name)
+                    },
+                    Token::StartBody(_) => {
+                        self.mode = Mode::Text;
+                        Token::StartBody(self.current_element.clone())
+                    },
+                    Token::ShorthandEndElement => {
+                        self.mode = Mode::Text;
+                        Token::ShorthandEndElement
+                    },
+                    other => other
+                };
+
+                Some((token, start..end))
+            },
+            Err(_) => {
+                self.done = true;
+                Some((Token::Error, start..start + self.remaining.len()))
+            }
+        }
+    }
+}
+
+fn str_of<'a>(s: Input<'a>) -> &'a str {
+    std::str::from_utf8(s.inner()).expect("ForgivingTokeniser only runs over valid UTF-8 input")
+}
+
+fn nom_comment<'a>(input: Input<'a>) -> PResult<'a, Token<'a>> {
     let chomped = context("Comment", delimited(
-        tag("<!--"),
-        take_until("-->"),
-        tag("-->")
+        tag(b"<!--"),
+        take_until(&b"-->"[..]),
+        tag(b"-->")
     ))(input);
     let res = match chomped {
-        Ok((i, c)) => Ok((i, Token::Comment(Cow::from(c)))),
+        Ok((i, c)) => Ok((i, Token::Comment(Cow::from(str_of(c))))),
         Err(c) => Err(c)
     };
     res
 }
 
-fn nom_cdata<'a>(input: &'a str) -> IResult<&str, Token<'a>, VerboseError<&str>> {
+fn nom_cdata<'a>(input: Input<'a>) -> PResult<'a, Token<'a>> {
     let chomped = context("CDATA", delimited(
-        tag("<![CDATA["),
-        take_until("]]>"),
-        tag("]]>")
+        tag(b"<![CDATA["),
+        take_until(&b"]]>"[..]),
+        tag(b"]]>")
     ))(input);
     match chomped {
-        Ok((i, c)) => Ok((i, Token::Text(Cow::from(c)))),
+        Ok((i, c)) => Ok((i, Token::Text(Cow::from(str_of(c))))),
         Err(c) => Err(c)
     }
 }
 
-fn nom_pi<'a>(input: &'a str) -> IResult<&str, Token<'a>, VerboseError<&str>> {
+fn nom_pi<'a>(input: Input<'a>) -> PResult<'a, Token<'a>> {
     let chomped = context("Processing Instruction", delimited(
-        tag("<?"),
+        tag(b"<?"),
         separated_pair(
-            take_until(" "),
-            tag(" "),
-            take_until("?>")
+            take_until(&b" "[..]),
+            tag(b" "),
+            take_until(&b"?>"[..])
         ),
-        tag("?>")
+        tag(b"?>")
     ))(input);
-    chomped.map(|(i,c)| (i, Token::ProcessingInstruction(Cow::from(c.0), Cow::from(c.1))))
+    chomped.map(|(i,c)| (i, Token::ProcessingInstruction(Cow::from(str_of(c.0)), Cow::from(str_of(c.1)))))
 }
 
-fn map_tag<'a, R: Clone>(m: &'a str, result: R) -> impl FnMut(&'a str) -> IResult<&'a str, R, VerboseError<&str>> {
+fn map_tag<'a, R: Clone>(m: &'static [u8], result: R) -> impl FnMut(Input<'a>) -> PResult<'a, R> {
     preceded(tag(m), success(result))
 }
 
@@ -78,70 +156,90 @@ fn dec_to_cow(input: &str) -> Option<Cow<'_, str>> {
     num.ok().and_then(std::char::from_u32).map(|i| Cow::from(i.to_string()))
 }
 
-fn nom_entity<'a>(input: &'a str) -> IResult<&str, Token, VerboseError<&str>> {
+fn nom_entity<'a>(input: Input<'a>) -> PResult<'a, Token<'a>> {
     context("Entity", delimited(
-        tag("&"),
+        tag(b"&"),
         alt((
-            map_tag("lt", Cow::from("<")),
-            map_tag("gt", Cow::from(">")),
-            map_tag("apos", Cow::from("\'")),
-            map_tag("quot", Cow::from("\"")),
-            map_tag("amp", Cow::from("&")),
-            map_opt(preceded(tag("#x"), hex_digit1), hex_to_cow),
-            map_opt(preceded(tag("#"), digit1), dec_to_cow)
+            map_tag(b"lt", Cow::from("<")),
+            map_tag(b"gt", Cow::from(">")),
+            map_tag(b"apos", Cow::from("\'")),
+            map_tag(b"quot", Cow::from("\"")),
+            map_tag(b"amp", Cow::from("&")),
+            map_opt(preceded(tag(b"#x"), hex_digit1), |s: Input<'a>| hex_to_cow(str_of(s))),
+            map_opt(preceded(tag(b"#"), digit1), |s: Input<'a>| dec_to_cow(str_of(s)))
         )),
-        tag(";")
+        tag(b";")
     ))(input).map(|(i,o)| (i, Token::Text(o)))
 }
 
-fn nom_endelement<'a>(input: &str) -> IResult<&str, Token, VerboseError<&str>> {
+fn nom_endelement<'a>(input: Input<'a>) -> PResult<'a, Token<'a>> {
     context("End Tag", delimited(
-        tag("</"),
-        take_until(">"),
-        tag(">")
-    ))(input).map(|(i,o)|(i, Token::EndElement(Cow::from(o))))
+        tag(b"</"),
+        take_until(&b">"[..]),
+        tag(b">")
+    ))(input).map(|(i,o)|(i, Token::EndElement(Cow::from(str_of(o)))))
 }
 
-fn nom_startelement<'a>(input: &str) -> IResult<&str, Token, VerboseError<&str>> {
+fn nom_startelement<'a>(input: Input<'a>) -> PResult<'a, Token<'a>> {
     context("Start Tag", preceded(
-        tag("<"),
-        is_not(" \r\n\t>/")
-    ))(input).map(|(i,o)|(i, Token::StartElement(Cow::from(o))))
+        tag(b"<"),
+        is_not(&b" \r\n\t>/"[..])
+    ))(input).map(|(i,o)|(i, Token::StartElement(Cow::from(str_of(o)))))
 }
 
-fn nom_endstarttag(input: &str)-> IResult<&str, bool, VerboseError<&str>> {
+fn nom_endstarttag<'a>(input: Input<'a>) -> PResult<'a, bool> {
     context("End of start tag", alt((
-        map(tag("/>"), |_| false),
-        map(tag(">"), |_| true)
+        map(tag(b"/>"), |_| false),
+        map(tag(b">"), |_| true)
     )))(input)
 }
-fn nom_attribute(input: &str) -> IResult<&str, Token, VerboseError<&str>> {
+
+fn nom_attribute<'a>(input: Input<'a>) -> PResult<'a, Token<'a>> {
     context("Attribute", preceded(
         take_while(is_whitespace),
         separated_pair(
-            take_until("="),
-            tag("="),
+            take_until(&b"="[..]),
+            tag(b"="),
             nom_rcdata
         )
-    ))(input).map(|(i, (n,v))| (i, Token::Attribute(Cow::from(n), v)))
+    ))(input).map(|(i, (n,v))| (i, Token::Attribute(Cow::from(str_of(n)), v)))
 }
 
-fn is_whitespace(c: char) -> bool {
-    unimplemented!()
+fn is_whitespace(c: u8) -> bool {
+    matches!(c, b' ' | b'\t' | b'\r' | b'\n')
 }
 
-fn nom_rcdata<'a>(input: &'a str) -> IResult<&str, Cow<'a, str>, VerboseError<&str>> {
-    unimplemented!()
+fn nom_rcdata<'a>(input: Input<'a>) -> PResult<'a, Cow<'a, str>> {
+    context("Attribute value", alt((
+        delimited(tag(b"\""), take_while(|c| c != b'"'), tag(b"\"")),
+        delimited(tag(b"'"), take_while(|c| c != b'\''), tag(b"'")),
+        is_not(&b" \r\n\t>/"[..])
+    )))(input).map(|(i, o)| (i, Cow::from(str_of(o))))
 }
 
-fn mode_text() {
-    let options = alt((
+fn nom_text<'a>(input: Input<'a>) -> PResult<'a, Token<'a>> {
+    context("Text", is_not(&b"<&"[..]))(input)
+        .map(|(i, o)| (i, Token::Text(Cow::from(str_of(o)))))
+}
+
+fn mode_text<'a>(input: Input<'a>) -> PResult<'a, Token<'a>> {
+    alt((
         nom_endelement,
         nom_startelement,
         nom_comment,
         nom_cdata,
         nom_pi,
         nom_entity,
-        //nom_text
-    ));
-}
\ No newline at end of file
+        nom_text
+    ))(input)
+}
+
+fn mode_tag<'a>(input: Input<'a>) -> PResult<'a, Token<'a>> {
+    alt((
+        map(nom_endstarttag, |has_body| match has_body {
+            true => Token::StartBody(Cow::from("")),
+            false => Token::ShorthandEndElement
+        }),
+        nom_attribute
+    ))(input)
+}
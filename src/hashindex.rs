@@ -1,10 +1,23 @@
+use std::convert::TryInto;
 use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
 use std::ops::Range;
+use std::path::{Path, PathBuf};
 
-use fnv::FnvHashMap;
+use fnv::FnvBuildHasher;
+use hashbrown::hash_map::RawEntryMut;
+use memmap2::Mmap;
+use rayon::prelude::*;
 
 use super::diesel_hash;
 
+/// Keyed by a precomputed 64-bit hash rather than the string it came from, so
+/// every lookup/insert in [`BlobHashIndex`]/[`HashIndex`]'s hot paths goes
+/// through hashbrown's raw-entry API for a single probe instead of an FNV
+/// `contains_key` followed by a separate `get`/`get_mut`.
+type HashMapShard<V> = hashbrown::HashMap<u64, V, FnvBuildHasher>;
+
 #[derive(Copy, Clone, Eq, Ord, PartialEq, PartialOrd, Debug, Hash)]
 pub struct Hash(pub u64);
 impl fmt::Display for Hash {
@@ -13,10 +26,94 @@ impl fmt::Display for Hash {
     }
 }
 
-trait HashList {
+/// A hash function a [`HashIndex`] can be parameterized over. Bitsquid/Stingray
+/// derivatives don't all agree on one hash: different games (and different
+/// eras of the same game) seed or widen things differently, so the actual
+/// mixing is pulled out behind this trait instead of being wired to
+/// [`diesel_hash`] everywhere a hash gets computed.
+pub trait HashAlgorithm {
+    /// The hash of the empty string, i.e. `Self::hash(&[])` - every
+    /// [`HashIndex`] seeds its interned table with this mapping to `""`,
+    /// since the empty string practically never appears in a hashlist file.
+    const EMPTY: u64;
+    fn hash(bytes: &[u8]) -> u64;
+}
+
+/// The hash PAYDAY 2 (and the Diesel engine generally) uses: Bob Jenkins'
+/// `lookup2` mix. The default algorithm - every existing hashlist and bundle
+/// database is keyed by it.
+pub struct DieselHash;
+
+impl HashAlgorithm for DieselHash {
+    const EMPTY: u64 = diesel_hash::EMPTY;
+    fn hash(bytes: &[u8]) -> u64 {
+        diesel_hash::hash_level(bytes, 0)
+    }
+}
+
+/// MurmurHash2 (32-bit), widened to `u64` by zero-extension. Some other
+/// Bitsquid/Stingray-derived games hash asset names this way instead of with
+/// [`DieselHash`].
+pub struct Murmur64;
+
+impl HashAlgorithm for Murmur64 {
+    const EMPTY: u64 = 0;
+    fn hash(bytes: &[u8]) -> u64 {
+        murmur2_32(bytes, 0) as u64
+    }
+}
+
+fn murmur2_32(data: &[u8], seed: u32) -> u32 {
+    const M: u32 = 0x5bd1_e995;
+    const R: u32 = 24;
+
+    let mut h = seed ^ (data.len() as u32);
+
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes(chunk.try_into().unwrap());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M);
+        h ^= k;
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        let mut t: u32 = 0;
+        for (i, &b) in tail.iter().enumerate() {
+            t |= (b as u32) << (8 * i);
+        }
+        h ^= t;
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+    h
+}
+
+/// Names [`HashAlgorithm`]s are known by on the CLI, so a binary can pick one
+/// at runtime (e.g. when pointed at a non-Diesel-engine game's hashlist)
+/// instead of needing a build per algorithm.
+pub const ALGORITHM_NAMES: &[&str] = &["diesel", "murmur64"];
+
+/// Hashes `text` with the [`HashAlgorithm`] named `algorithm` (see
+/// [`ALGORITHM_NAMES`]), or `None` if the name isn't recognised.
+pub fn hash_by_name(algorithm: &str, text: &str) -> Option<u64> {
+    match algorithm {
+        "diesel" => Some(DieselHash::hash(text.as_bytes())),
+        "murmur64" => Some(Murmur64::hash(text.as_bytes())),
+        _ => None
+    }
+}
+
+trait HashList<H: HashAlgorithm = DieselHash> {
     fn get_hash<'s>(&'s self, hash: u64) -> HashedStr<'s>;
     fn get_str<'s>(&'s self, text: &str) -> HashedStr<'s> {
-        self.get_hash(diesel_hash::hash_str(text))
+        self.get_hash(H::hash(text.as_bytes()))
     }
 }
 
@@ -73,7 +170,7 @@ impl fmt::Display for HashedStr<'_> {
     }
 }
 
-fn is_hash_like(txt: &str) -> bool {
+pub(crate) fn is_hash_like(txt: &str) -> bool {
     if txt.len() != 16 { return false; }
     for i in txt.chars() {
         if ('0'..'9').contains(&i) || ('a'..'z').contains(&i) || ('A'..'Z').contains(&i) {
@@ -84,39 +181,161 @@ fn is_hash_like(txt: &str) -> bool {
     return true;
 }
 
-pub struct BlobHashIndex {
-    index: FnvHashMap<u64, (usize, usize)>,
+pub struct BlobHashIndex<H: HashAlgorithm = DieselHash> {
+    index: HashMapShard<(usize, usize)>,
     data: String,
+    _algorithm: std::marker::PhantomData<H>
 }
 
-impl BlobHashIndex {
-    pub fn new(data: String) -> BlobHashIndex {
+impl<H: HashAlgorithm> BlobHashIndex<H> {
+    pub fn new(data: String) -> BlobHashIndex<H> {
         let mut result = BlobHashIndex {
             data,
-            index : FnvHashMap::default()
+            index: HashMapShard::default(),
+            _algorithm: std::marker::PhantomData
         };
         let data_start = result.data.as_ptr() as usize;
         for line in result.data.lines() {
             let line_start_ptr = line.as_ptr() as usize;
             let line_start = line_start_ptr.wrapping_sub(data_start);
-            result.index.insert(diesel_hash::hash_str(line), (line_start, line_start+line.len()));
+            result.index.insert(H::hash(line.as_bytes()), (line_start, line_start+line.len()));
         }
         return result;
     }
+
+    /// The byte range of `hash`'s text in `data`, if this blob has it - a
+    /// single raw-entry probe rather than a `contains_key` plus a separate `get`.
+    fn get_range(&self, hash: u64) -> Option<(usize, usize)> {
+        self.index.raw_entry().from_key(&hash).map(|(_, &range)| range)
+    }
+}
+
+/// Magic number of a [`MappedHashIndex`] file, a sorted-table on-disk format
+/// for hashlists too large to comfortably parse into a `BlobHashIndex`.
+const MAPPED_MAGIC: &[u8; 4] = b"PHL1";
+/// Size in bytes of the fixed header: magic, entry count, index checksum, data checksum.
+const MAPPED_HEADER_SIZE: usize = 16;
+/// Size in bytes of one index record: hash, data offset, data length.
+const MAPPED_RECORD_SIZE: usize = 16;
+
+/// Writes `lines` out as a [`MappedHashIndex`] file: a header, then an index
+/// of `(hash, data_offset, data_len)` records sorted by hash, then the lines'
+/// bytes packed one after another. Readers binary-search the index and slice
+/// straight into a memory map, so opening one never parses the whole file.
+pub fn build_mapped_hashlist<'a, H: HashAlgorithm>(lines: impl Iterator<Item = &'a str>, mut output: impl Write) -> io::Result<()> {
+    let mut data = Vec::new();
+    let mut entries: Vec<(u64, u32, u32)> = Vec::new();
+    for line in lines {
+        let offset = data.len() as u32;
+        data.extend_from_slice(line.as_bytes());
+        entries.push((H::hash(line.as_bytes()), offset, line.len() as u32));
+    }
+    entries.sort_by_key(|&(hash, _, _)| hash);
+
+    let mut index = Vec::with_capacity(entries.len() * MAPPED_RECORD_SIZE);
+    for (hash, offset, len) in &entries {
+        index.extend_from_slice(&hash.to_le_bytes());
+        index.extend_from_slice(&offset.to_le_bytes());
+        index.extend_from_slice(&len.to_le_bytes());
+    }
+
+    output.write_all(MAPPED_MAGIC)?;
+    output.write_all(&(entries.len() as u32).to_le_bytes())?;
+    output.write_all(&crc32c::crc32c(&index).to_le_bytes())?;
+    output.write_all(&crc32c::crc32c(&data).to_le_bytes())?;
+    output.write_all(&index)?;
+    output.write_all(&data)?;
+    Ok(())
 }
 
-pub struct HashIndex {
-    blobs: Vec<BlobHashIndex>,
-    interned: FnvHashMap<u64, String>
+/// Read-only, memory-mapped [`HashList`] backed by a sorted table written by
+/// [`build_mapped_hashlist`]. Looks strings up with a binary search over the
+/// index and slices straight into the map, so opening one costs a CRC check
+/// over two memcpy-free passes rather than a parse, and holding one open
+/// costs no more heap than the index/data the OS chooses to keep resident.
+pub struct MappedHashIndex<H: HashAlgorithm = DieselHash> {
+    mmap: Mmap,
+    count: usize,
+    _algorithm: std::marker::PhantomData<H>
 }
 
-impl HashIndex {
-    pub fn new() -> HashIndex {
+impl<H: HashAlgorithm> MappedHashIndex<H> {
+    pub fn open(path: &Path) -> io::Result<MappedHashIndex<H>> {
+        let file = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        if mmap.len() < MAPPED_HEADER_SIZE || &mmap[0..4] != MAPPED_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a mapped hashlist file"));
+        }
+        let count = u32::from_le_bytes(mmap[4..8].try_into().unwrap()) as usize;
+        let index_crc = u32::from_le_bytes(mmap[8..12].try_into().unwrap());
+        let data_crc = u32::from_le_bytes(mmap[12..16].try_into().unwrap());
+
+        let index_start = MAPPED_HEADER_SIZE;
+        let index_end = index_start + count * MAPPED_RECORD_SIZE;
+        let index = mmap.get(index_start..index_end)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "Mapped hashlist truncated in index"))?;
+        if crc32c::crc32c(index) != index_crc {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Mapped hashlist index checksum mismatch - truncated or corrupt file"));
+        }
+
+        let data = mmap.get(index_end..)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "Mapped hashlist truncated in data"))?;
+        if crc32c::crc32c(data) != data_crc {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Mapped hashlist data checksum mismatch - truncated or corrupt file"));
+        }
+
+        Ok(MappedHashIndex { mmap, count, _algorithm: std::marker::PhantomData })
+    }
+
+    fn record(&self, i: usize) -> (u64, u32, u32) {
+        let start = MAPPED_HEADER_SIZE + i * MAPPED_RECORD_SIZE;
+        let bytes = &self.mmap[start..(start + MAPPED_RECORD_SIZE)];
+        let hash = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        let offset = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+        let len = u32::from_le_bytes(bytes[12..16].try_into().unwrap());
+        (hash, offset, len)
+    }
+
+    fn find(&self, hash: u64) -> Option<&str> {
+        let mut lo = 0usize;
+        let mut hi = self.count;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let (mid_hash, offset, len) = self.record(mid);
+            if mid_hash == hash {
+                let data_start = MAPPED_HEADER_SIZE + self.count * MAPPED_RECORD_SIZE;
+                let start = data_start + offset as usize;
+                let bytes = &self.mmap[start..(start + len as usize)];
+                return std::str::from_utf8(bytes).ok();
+            }
+            else if mid_hash < hash { lo = mid + 1; }
+            else { hi = mid; }
+        }
+        None
+    }
+}
+
+impl<H: HashAlgorithm> HashList<H> for MappedHashIndex<H> {
+    fn get_hash<'s>(&'s self, hash: u64) -> HashedStr<'s> {
+        HashedStr { hash, text: self.find(hash) }
+    }
+}
+
+pub struct HashIndex<H: HashAlgorithm = DieselHash> {
+    blobs: Vec<BlobHashIndex<H>>,
+    mapped: Vec<MappedHashIndex<H>>,
+    interned: HashMapShard<String>
+}
+
+impl<H: HashAlgorithm> HashIndex<H> {
+    pub fn new() -> HashIndex<H> {
         let mut res = HashIndex {
             blobs: Vec::new(),
-            interned: FnvHashMap::default()
+            mapped: Vec::new(),
+            interned: HashMapShard::default()
         };
-        res.interned.insert(diesel_hash::EMPTY, "".to_owned());
+        res.interned.insert(H::EMPTY, "".to_owned());
         return res;
     }
 
@@ -124,53 +343,111 @@ impl HashIndex {
         self.blobs.push(BlobHashIndex::new(data));
     }
 
+    /// Loads `path` as a hashlist, recognising `%include <path>` lines (the
+    /// included path resolved relative to the directory of the file that
+    /// contains the directive) and `#`/`;` comment lines, then [`load_blob`]s
+    /// the result as a single merged blob. Included files are expanded
+    /// depth-first in the order they're written, so within one `--hashlist`
+    /// argument a later `%include` (or a later plain line) overrides an
+    /// earlier one on a hash collision, the same as [`BlobHashIndex::new`]'s
+    /// last-line-wins behaviour already gives within a single file.
+    pub fn load_file(&mut self, path: &Path) -> io::Result<()> {
+        let mut seen = Vec::new();
+        let mut out = String::new();
+        load_file_into(path, &mut seen, &mut out)?;
+        self.load_blob(out);
+        Ok(())
+    }
+
+    /// Load a sorted-table hashlist written by [`build_mapped_hashlist`],
+    /// for wordlists too large to comfortably parse into a blob.
+    pub fn load_mapped(&mut self, path: &Path) -> io::Result<()> {
+        self.mapped.push(MappedHashIndex::open(path)?);
+        Ok(())
+    }
+
     pub fn intern<'s>(&'s mut self, text: String) -> HashedStr<'s> {
-        let hash = diesel_hash::hash_str(&text);
-        for i in 0..self.blobs.len() {
-            if !self.blobs[i].index.contains_key(&hash) {
-                continue;
+        let hash = H::hash(text.as_bytes());
+        for blob in self.blobs.iter().rev() {
+            if let Some((start, end)) = blob.get_range(hash) {
+                return HashedStr { hash, text: Some(&blob.data[start..end]) };
+            }
+        }
+        for mapped in &self.mapped {
+            if let Some(r) = mapped.find(hash) {
+                return HashedStr { hash, text: Some(r) };
             }
-            let indices = self.blobs[i].index.get(&hash).unwrap();
-            let r = &self.blobs[i].data[(indices.0)..(indices.1)];
-            return HashedStr { hash, text: Some(r) };
         }
-        let e = self.interned.entry(hash);
-        let et = e.or_insert(text);
-        HashedStr { hash, text: Some(et)}
+        let text_ref: &mut String = match self.interned.raw_entry_mut().from_key(&hash) {
+            RawEntryMut::Occupied(e) => e.into_mut(),
+            RawEntryMut::Vacant(e) => e.insert(hash, text).1
+        };
+        HashedStr { hash, text: Some(text_ref.as_str()) }
     }
 
     pub fn get_hash<'s>(&'s self, hash: u64) -> HashedStr<'s> {
-        for i in 0..self.blobs.len() {
-            if !self.blobs[i].index.contains_key(&hash) {
-                continue;
+        for blob in self.blobs.iter().rev() {
+            if let Some((start, end)) = blob.get_range(hash) {
+                return HashedStr { hash, text: Some(&blob.data[start..end]) };
+            }
+        }
+        for mapped in &self.mapped {
+            if let Some(r) = mapped.find(hash) {
+                return HashedStr { hash, text: Some(r) };
             }
-            let indices = self.blobs[i].index.get(&hash).unwrap();
-            let r = &self.blobs[i].data[(indices.0)..(indices.1)];
-            return HashedStr { hash, text: Some(r) };
         }
 
-        let from_interned = self.interned.get(&hash);
-        return HashedStr { hash, text: from_interned.map(String::as_str) }
+        let from_interned = self.interned.raw_entry().from_key(&hash).map(|(_, v)| v.as_str());
+        return HashedStr { hash, text: from_interned }
     }
 
     pub fn get_str<'s>(&'s self, text: &str) -> HashedStr<'s> {
-        self.get_hash(diesel_hash::hash_str(text))
+        self.get_hash(H::hash(text.as_bytes()))
+    }
+
+    /// Resolves a whole batch of hashes at once, sharding the work across
+    /// threads with rayon - each lookup only needs `&self`, so a bundle
+    /// unpack that has to resolve millions of hashes can use every core
+    /// instead of walking `self.blobs` serially for each one.
+    pub fn get_hashes<'s>(&'s self, hashes: &[u64]) -> Vec<HashedStr<'s>> {
+        hashes.par_iter().map(|&hash| self.get_hash(hash)).collect()
+    }
+
+    /// Interns every string in `texts` at once, in the same order. Checking
+    /// whether each one already resolves via a blob, mapped table, or
+    /// previous interning runs in parallel like [`get_hashes`][Self::get_hashes];
+    /// only strings genuinely new to this index take the single-probe,
+    /// one-at-a-time raw-entry insert into `interned`.
+    pub fn intern_many(&mut self, texts: Vec<String>) -> Vec<HashedStr<'_>> {
+        let hashed: Vec<(u64, String)> = texts.into_par_iter()
+            .map(|text| (H::hash(text.as_bytes()), text))
+            .collect();
+
+        for (hash, text) in &hashed {
+            if self.get_hash(*hash).text.is_some() { continue; }
+            if let RawEntryMut::Vacant(e) = self.interned.raw_entry_mut().from_key(hash) {
+                e.insert(*hash, text.clone());
+            }
+        }
+
+        let hashes: Vec<u64> = hashed.iter().map(|&(hash, _)| hash).collect();
+        self.get_hashes(&hashes)
     }
 
     /// Intern a string that's very likely a substring of one already loaded from a blob
-    /// 
+    ///
     /// If the parent string isn't in a blob, just intern normally. If not found at all,
     /// return None, otherwise return the substring's hash.
     pub fn intern_substring(&mut self, superstring_hash: u64, indices: Range<usize>) -> Option<u64> {
         for i in 0..self.blobs.len() {
-            if !self.blobs[i].index.contains_key(&superstring_hash) {
-                continue;
-            }
+            let superstring_indices = match self.blobs[i].get_range(superstring_hash) {
+                Some(r) => r,
+                None => continue
+            };
 
-            let superstring_indices = self.blobs[i].index.get(&superstring_hash).unwrap();
             let superstring = &self.blobs[i].data[(superstring_indices.0)..(superstring_indices.1)];
             let substring = &superstring[(indices.start)..(indices.end)];
-            let substring_hash = diesel_hash::from_str(substring);
+            let substring_hash = H::hash(substring.as_bytes());
 
             let data_ptr = self.blobs[i].data.as_ptr() as usize;
             let substring_ptr = substring.as_ptr() as usize;
@@ -181,17 +458,63 @@ impl HashIndex {
             return Some(substring_hash);
         }
 
-        let maybe_substring = self.interned.get(&superstring_hash).and_then(|superstring| {
-            Some(superstring[(indices.start)..(indices.end)].to_owned())
+        for i in 0..self.mapped.len() {
+            let superstring = match self.mapped[i].find(superstring_hash) {
+                Some(s) => s,
+                None => continue
+            };
+            let substring = superstring[(indices.start)..(indices.end)].to_owned();
+            let substring_hash = H::hash(substring.as_bytes());
+            self.interned.insert(substring_hash, substring);
+            return Some(substring_hash);
+        }
+
+        let maybe_substring = self.interned.raw_entry().from_key(&superstring_hash).map(|(_, superstring)| {
+            superstring[(indices.start)..(indices.end)].to_owned()
         });
 
         match maybe_substring {
             None => return None,
             Some(substring) => {
-                let hash = diesel_hash::hash_str(&substring);
+                let hash = H::hash(substring.as_bytes());
                 self.interned.insert(hash, substring);
                 return Some(hash);
             }
         }
     }
+}
+
+/// Reads `path` into `out`, expanding `%include <path>` lines depth-first
+/// (the included path resolved relative to `path`'s own directory) and
+/// dropping `#`/`;` comment lines and blank lines. `seen` is the list of
+/// canonicalized paths currently being read, so a file that (directly or
+/// transitively) includes itself is reported as an error instead of
+/// recursing forever.
+fn load_file_into(path: &Path, seen: &mut Vec<PathBuf>, out: &mut String) -> io::Result<()> {
+    let canonical = path.canonicalize()?;
+    if seen.contains(&canonical) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("hashlist %include cycle: {} includes itself", path.display())
+        ));
+    }
+    seen.push(canonical);
+
+    let text = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+            continue;
+        }
+        if let Some(included) = trimmed.strip_prefix("%include") {
+            load_file_into(&dir.join(included.trim()), seen, out)?;
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+
+    seen.pop();
+    Ok(())
 }
\ No newline at end of file
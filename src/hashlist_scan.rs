@@ -2,20 +2,39 @@ use std::{fs::File, iter::FromIterator, path::Path};
 use std::io;
 use std::os::windows::fs::FileExt;
 use std::rc::Rc;
-use fnv::FnvHashSet;
+use fnv::{FnvHashMap, FnvHashSet};
 
 use crate::bundles::database::{Database, ReadItem};
 use crate::diesel_hash::{hash_str as dhash};
+use crate::util::hyperloglog::HyperLogLog;
 
 mod scriptdata;
 mod xml;
+mod xpath;
 mod soundbanks;
 mod bruteforce;
+mod generic_strings;
+mod rule_config;
+mod wordlist_crack;
+pub mod combinator;
 
-pub fn do_scan<W: std::io::Write>(db: &Database, output: &mut W) -> io::Result<()> {
+pub use rule_config::{Rule, parse_rules};
+pub use wordlist_crack::{PermuteRules, crack_wordlist, unresolved_hashes};
+pub use combinator::{CombinatorRules, crack_combinations};
+
+/// Loads extra scan rules from a config file, in the format documented on
+/// [`rule_config`]. Used to cover extensions `do_scan`/`build_dictionary` don't
+/// have a compiled-in `scan3!` rule for, without having to rebuild the crate.
+pub fn load_rules(path: &Path) -> io::Result<Vec<Rule>> {
+    let text = std::fs::read_to_string(path)?;
+    parse_rules(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn do_scan<W: std::io::Write>(db: &Database, output: &mut W, scan_all_files: bool, extra_rules: &[Rule]) -> io::Result<()> {
     eprintln_time!("Data scan pass 1, preparing file list");
     let to_read = db.filter_key_sort_physical(|key| {
-        key.extension.hash == dhash("credits")
+        scan_all_files
+        || key.extension.hash == dhash("credits")
         || key.extension.hash == dhash("dialog_index")
         || key.extension.hash == dhash("sequence_manager")
         || key.extension.hash == dhash("continent")
@@ -36,7 +55,7 @@ pub fn do_scan<W: std::io::Write>(db: &Database, output: &mut W) -> io::Result<(
     });
 
     eprintln_time!("Data scan pass 1, scanning");
-    let mut found = do_scan_pass(to_read)?;
+    let mut found = do_scan_pass(to_read, extra_rules)?;
     eprintln!("");
 
     eprintln_time!("Analysing existing_banks.banksinfo");
@@ -52,6 +71,9 @@ pub fn do_scan<W: std::io::Write>(db: &Database, output: &mut W) -> io::Result<(
     eprintln_time!("Brute forcing material suffixes");
     found.extend(bruteforce::scan_mat_suffixes(db).iter().map(|s| Rc::from(s.as_ref())));
 
+    eprintln_time!("Estimating unresolved-hash coverage");
+    report_coverage(db, &found);
+
     eprintln_time!("Scan complete. Saving {} strings", found.len());
     let mut ordered: Vec<Rc<str>> = Vec::from_iter(found.drain());
     ordered.sort();
@@ -61,7 +83,39 @@ pub fn do_scan<W: std::io::Write>(db: &Database, output: &mut W) -> io::Result<(
     Ok(())
 }
 
-fn do_scan_pass(to_read: Vec<(&Path, Vec<ReadItem>)>) -> io::Result<FnvHashSet<Rc<str>>> {
+/// Runs a [`HyperLogLog`] estimator alongside `found` to print a rough sense
+/// of how much of the database's unknown-hash space the scan actually
+/// covered, rather than just the exact-but-uninformative `found.len()`: one
+/// sketch folds in every discovered string's hash to estimate how many
+/// distinct strings were found overall, and a second folds in only the
+/// hashes that land on one of the database's still-unresolved path hashes,
+/// to estimate how many of those got recovered.
+fn report_coverage(db: &Database, found: &FnvHashSet<Rc<str>>) {
+    let unresolved: FnvHashSet<u64> = db.files()
+        .filter_map(|item| {
+            let k = item.key();
+            if k.path.text.is_none() { Some(k.path.hash) } else { None }
+        })
+        .collect();
+
+    let mut distinct_hll = HyperLogLog::new(14);
+    let mut coverage_hll = HyperLogLog::new(14);
+
+    for s in found {
+        let h = dhash(s);
+        distinct_hll.add_hash(h);
+        if unresolved.contains(&h) {
+            coverage_hll.add_hash(h);
+        }
+    }
+
+    eprintln!(
+        "≈{:.0} distinct strings found; ≈{:.0} of {} unresolved hashes recovered",
+        distinct_hll.estimate(), coverage_hll.estimate(), unresolved.len()
+    );
+}
+
+fn do_scan_pass(to_read: Vec<(&Path, Vec<ReadItem>)>, extra_rules: &[Rule]) -> io::Result<FnvHashSet<Rc<str>>> {
     let mut found = FnvHashSet::<Rc<str>>::default();
 
     for (path, items) in to_read {
@@ -72,7 +126,7 @@ fn do_scan_pass(to_read: Vec<(&Path, Vec<ReadItem>)>) -> io::Result<FnvHashSet<R
             let mut bytes = Vec::<u8>::new();
             bytes.resize(item.length, 0);
             bundle.seek_read(&mut bytes, item.offset as u64)?;
-            let scanned = do_scan_buffer(&bytes, item);
+            let scanned = do_scan_buffer(&bytes, item, extra_rules);
             match scanned {
                 Err(e) => eprintln!("Failed reading {} byte file \"{}\": {}", bytes.len(), item.key, e),
                 Ok(v) => found.extend(v)
@@ -82,27 +136,99 @@ fn do_scan_pass(to_read: Vec<(&Path, Vec<ReadItem>)>) -> io::Result<FnvHashSet<R
     return Ok(found);
 }
 
-fn do_scan_buffer(buf: &[u8], item: ReadItem) -> Result<Vec<Rc<str>>, Box<dyn std::error::Error>>{
-    let iter_res: Result<Box<dyn Iterator<Item=Rc<str>>>, Box<dyn std::error::Error>> = match item.key.extension.text {
-        Some("credits") => scriptdata::scan_credits(buf),
-        Some("dialog_index") => scriptdata::scan_dialog_index(buf),
-        Some("sequence_manager") => scriptdata::scan_sequence_manager(buf),
-        Some("continent") => scriptdata::scan_continent(buf),
-        Some("continents") => scriptdata::scan_continents(buf, Rc::from(item.key.path.text.unwrap())),
-        Some("world") => scriptdata::scan_world(buf, Rc::from(item.key.path.text.unwrap())),
-        Some("mission") => scriptdata::scan_mission(buf),
-        Some("environment") => scriptdata::scan_environment(buf),
-        Some("object") => xml::scan_object(&buf),
-        Some("animation_state_machine") => xml::scan_animation_state_machine(buf),
-        Some("animation_subset") => xml::scan_animation_subset(buf),
-        Some("effect") => xml::scan_effect(buf),
-        Some("animation_def") => xml::scan_animation_def(buf),
-        Some("scene") => xml::scan_scene(buf),
-        Some("gui") => xml::scan_scene(buf),
-        Some("merged_font") => xml::scan_merged_font(buf),
-        Some("material_config") => xml::scan_material_config(buf),
-        Some("unit") => xml::scan_unit(buf),
-        _ => panic!("Selected a file {:?} to scan and then didn't scan it", item.key)
+/// Runs every `scan_*` function over `db` - the same sources [`do_scan`] draws from,
+/// but across every file regardless of extension, since here we're after any name
+/// we can get rather than just the ones worth committing to a hashlist file - and
+/// folds the results into a hash-keyed dictionary, ready to drive [`super::filesystem::unhash_fs::UnhashFs`]
+/// without the indirection of writing a wordlist and reloading it through a
+/// [`super::hashindex::HashIndex`] blob.
+///
+/// Diesel hashes are case-insensitive in practice (paths get lowercased before
+/// hashing), so each candidate is folded to lowercase before it's hashed; if two
+/// different-looking candidates still collide, the first one seen wins and the
+/// rest are logged rather than silently dropped.
+pub fn build_dictionary(db: &Database, extra_rules: &[Rule]) -> FnvHashMap<u64, Rc<str>> {
+    let to_read = db.filter_key_sort_physical(|_| true);
+    let mut found = do_scan_pass(to_read, extra_rules).unwrap_or_default();
+
+    match soundbanks::scan(db) {
+        Err(e) => eprintln!("Unable to analyse soundbanks: {}", e),
+        Ok(strs) => found.extend(strs.into_iter().map(|s| Rc::from(s.as_ref())))
+    }
+
+    found.extend(bruteforce::scan_cubelights(db).iter().map(|s| Rc::from(s.as_ref())));
+    found.extend(bruteforce::scan_mat_suffixes(db).iter().map(|s| Rc::from(s.as_ref())));
+    found.extend(bruteforce::scan_unit_suffixes(db).iter().map(|s| Rc::from(s.as_ref())));
+    found.extend(bruteforce::scan_texture_suffixes(db).iter().map(|s| Rc::from(s.as_ref())));
+
+    let mut dictionary = FnvHashMap::<u64, Rc<str>>::default();
+    for candidate in found {
+        let hash = dhash(&candidate.to_ascii_lowercase());
+        match dictionary.get(&hash) {
+            Some(existing) if existing.as_ref() != candidate.as_ref() => {
+                eprintln!("Hash collision on {:016x}: keeping {:?}, discarding {:?}", hash, existing, candidate);
+            },
+            Some(_) => (),
+            None => { dictionary.insert(hash, candidate); }
+        }
+    }
+    dictionary
+}
+
+/// Saves a dictionary built by [`build_dictionary`] as a plain list of strings, one
+/// per line - the same format [`do_scan`] writes, so a cache built here can also be
+/// loaded back through [`super::hashindex::HashIndex::load_blob`] if wanted.
+pub fn save_dictionary(dictionary: &FnvHashMap<u64, Rc<str>>, path: &Path) -> io::Result<()> {
+    use std::io::Write;
+    let mut ordered: Vec<&Rc<str>> = dictionary.values().collect();
+    ordered.sort();
+    let mut file = File::create(path)?;
+    for s in ordered {
+        writeln!(file, "{}", s)?;
+    }
+    Ok(())
+}
+
+/// Loads a dictionary previously saved with [`save_dictionary`], so repeated mounts
+/// don't have to re-scan the whole asset database just to get names back.
+pub fn load_dictionary(path: &Path) -> io::Result<FnvHashMap<u64, Rc<str>>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut dictionary = FnvHashMap::default();
+    for line in text.lines() {
+        if line.is_empty() { continue; }
+        let hash = dhash(&line.to_ascii_lowercase());
+        dictionary.entry(hash).or_insert_with(|| Rc::from(line));
+    }
+    Ok(dictionary)
+}
+
+fn do_scan_buffer(buf: &[u8], item: ReadItem, extra_rules: &[Rule]) -> Result<Vec<Rc<str>>, Box<dyn std::error::Error>>{
+    let rule = item.key.extension.text.and_then(|ext| extra_rules.iter().find(|r| r.name == ext));
+
+    let iter_res: Result<Box<dyn Iterator<Item=Rc<str>>>, Box<dyn std::error::Error>> = match (rule, item.key.extension.text) {
+        (Some(rule), _) => {
+            let doc = crate::formats::scriptdata::binary::from_binary(buf, false)?;
+            rule_config::eval_rule(rule, &doc)
+        },
+        (None, Some("credits")) => scriptdata::scan_credits(buf),
+        (None, Some("dialog_index")) => scriptdata::scan_dialog_index(buf),
+        (None, Some("sequence_manager")) => scriptdata::scan_sequence_manager(buf),
+        (None, Some("continent")) => scriptdata::scan_continent(buf),
+        (None, Some("continents")) => scriptdata::scan_continents(buf, Rc::from(item.key.path.text.unwrap())),
+        (None, Some("world")) => scriptdata::scan_world(buf, Rc::from(item.key.path.text.unwrap())),
+        (None, Some("mission")) => scriptdata::scan_mission(buf),
+        (None, Some("environment")) => scriptdata::scan_environment(buf),
+        (None, Some("object")) => xml::scan(&buf, "object"),
+        (None, Some("animation_state_machine")) => xml::scan(buf, "animation_state_machine"),
+        (None, Some("animation_subset")) => xml::scan(buf, "animation_subset"),
+        (None, Some("effect")) => xml::scan(buf, "effect"),
+        (None, Some("animation_def")) => xml::scan(buf, "animation_def"),
+        (None, Some("scene")) => xml::scan(buf, "scene"),
+        (None, Some("gui")) => xml::scan(buf, "scene"),
+        (None, Some("merged_font")) => xml::scan(buf, "merged_font"),
+        (None, Some("material_config")) => xml::scan(buf, "material_config"),
+        (None, Some("unit")) => xml::scan(buf, "unit"),
+        (None, _) => generic_strings::scan(buf)
     };
     let result = iter_res.map(Iterator::collect::<Vec<_>>);
     return result;
@@ -9,43 +9,98 @@ use diesel_hash::hash::{EMPTY, MATERIAL_CONFIG, TEXTURE, UNIT};
 
 use std::iter::FromIterator;
 
-pub struct Bitfield64K([u16; 8192]);
-impl Bitfield64K {
-    pub fn new() -> Bitfield64K { Bitfield64K([0; 8192]) }
-
-    pub fn get(&self, idx: u16) -> bool {
-        let byte_idx = (idx & 0xFFF8) >> 3;
-        let shift = idx & 0x0007;
-        let byte = self.0[byte_idx as usize];
-        //let test = 1 << (idx & 0x0007);
-        let test = 1 << shift;
-        byte & test != 0
-    }
-
-    pub fn set(&mut self, idx: u16, val: bool) {
-        let byte_idx = (idx & 0xFFF8) >> 3;
-        let shift = idx & 0x0007;
-        let byte = self.0[byte_idx as usize];
-        self.0[byte_idx as usize] = match val {
-            true => byte | (1 << shift),
-            false => byte & !(1 << shift)
-        };
-    }
-}
-impl FromIterator<u64> for Bitfield64K {
+/// A Bloom filter over 64-bit diesel hashes, used to prefilter brute-forced
+/// candidates before paying for an [`FnvHashSet::contains`] lookup plus a
+/// `Box<str>` allocation. Replaces an earlier fixed 65536-bit table indexed
+/// by the hash's top 16 bits, which saturated (and so stopped filtering
+/// anything) once a real database's unresolved-hash set grew past a few
+/// thousand entries.
+///
+/// Sized from the expected element count so the false-positive rate stays
+/// bounded regardless of database size; false negatives are impossible, so
+/// recovered paths built from a positive test are always correct.
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    m: u64,
+    k: u32
+}
+
+impl BloomFilter {
+    /// Builds a filter for `n` elements at a target false-positive rate `p`:
+    /// `m = ceil(-n·ln(p) / ln(2)²)` bits and `k = round((m/n)·ln 2)` hash
+    /// functions.
+    pub fn new(n: usize, p: f64) -> BloomFilter {
+        let n = (n.max(1)) as f64;
+        let m = (-n * p.ln() / std::f64::consts::LN_2.powi(2)).ceil().max(1.0) as u64;
+        let k = ((m as f64 / n) * std::f64::consts::LN_2).round().max(1.0) as u32;
+        BloomFilter { bits: vec![0u64; ((m + 63) / 64) as usize], m, k }
+    }
+
+    /// The `k` bit positions for `hash`, derived by double hashing: the low
+    /// and high 32 bits of `hash` stand in for two independent hashes `h1`
+    /// and `h2`, and `bit_i = (h1 + i·h2) mod m`.
+    fn bit_positions(&self, hash: u64) -> impl Iterator<Item=u64> + '_ {
+        let h1 = hash & 0xFFFF_FFFF;
+        let h2 = hash >> 32;
+        (0..self.k as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.m)
+    }
+
+    pub fn insert(&mut self, hash: u64) {
+        for bit in self.bit_positions(hash) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    pub fn get(&self, hash: u64) -> bool {
+        self.bit_positions(hash).all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+}
+impl FromIterator<u64> for BloomFilter {
     fn from_iter<I: IntoIterator<Item=u64>>(iter: I) -> Self {
-        let mut bf = Bitfield64K([0; 8192]);
-        for i in iter {
-            let idx = (i >> 48) as u16;
-            bf.set(idx, true)
+        let items: Vec<u64> = iter.into_iter().collect();
+        let mut bf = BloomFilter::new(items.len(), 0.01);
+        for h in &items {
+            bf.insert(*h);
         }
         bf
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_inserted_hash_tests_positive() {
+        let hashes: Vec<u64> = (0..5000u64).map(|i| i.wrapping_mul(0x9E3779B97F4A7C15) ^ i.rotate_left(17)).collect();
+        let filter: BloomFilter = hashes.iter().copied().collect();
+        for h in &hashes {
+            assert!(filter.get(*h), "false negative for {:#x}", h);
+        }
+    }
+
+    #[test]
+    fn empty_filter_has_no_spurious_positives() {
+        let filter: BloomFilter = std::iter::empty().collect();
+        assert!(!filter.get(0x1234_5678_9abc_def0));
+    }
+
+    #[test]
+    fn false_positive_rate_stays_close_to_target_for_absent_hashes() {
+        let inserted: Vec<u64> = (0..2000u64).map(|i| i * 2).collect();
+        let filter: BloomFilter = inserted.iter().copied().collect();
+
+        let absent = (0..2000u64).map(|i| i * 2 + 1);
+        let false_positives = absent.filter(|h| filter.get(*h)).count();
+
+        // Sized for p=0.01; allow generous headroom so this isn't flaky.
+        assert!(false_positives < 200, "{} false positives out of 2000, expected close to 1%", false_positives);
+    }
+}
+
 pub fn scan_cubelights(database: &Database) -> Vec<Box<str>> {
     let mut hashes_to_find = FnvHashSet::default();
-    let mut prefilter = Bitfield64K::new();
+    let mut prefilter_hashes = Vec::new();
 
     let worlds: Vec<_> = database.files().filter_map(|item|{
         let k = item.key();
@@ -57,11 +112,13 @@ pub fn scan_cubelights(database: &Database) -> Vec<Box<str>> {
             }
         }
         else {
-            prefilter.set((k.path.hash >> 48) as u16, true);
+            prefilter_hashes.push(k.path.hash);
         }
         None
     }).collect();
 
+    let prefilter: BloomFilter = prefilter_hashes.into_iter().collect();
+
     let cubelight_fmap = worlds.par_iter().flat_map(|world| {
         let alloc_len = world.len() + "/cube_lights/".len() + 7;
         let world = *world;
@@ -78,7 +135,7 @@ pub fn scan_cubelights(database: &Database) -> Vec<Box<str>> {
                 write!(buf, "{}", n).unwrap();
                 let hsh = dhash(&buf);
                 //match database.get_by_hashes(hsh, EMPTY, TEXTURE) {
-                if prefilter.get((hsh >> 48) as u16) {
+                if prefilter.get(hsh) {
                     match hashes_to_find.contains(&hsh) {
                         true => { //Some(_) => {
                             let b = Box::<str>::from(buf.as_str());
@@ -117,8 +174,8 @@ pub fn scan_texture_suffixes(database: &Database) -> Vec<Box<str>> {
     let mut hashes_to_find = FnvHashSet::<u64>::default();
     let mut known_paths = FnvHashSet::<&str>::default();
     let mut known_suffixes = FnvHashSet::<&str>::default();
-    let mut prefilter = Bitfield64K::new();
-    
+    let mut prefilter_hashes = Vec::new();
+
     for file in database.files() {
         let k = file.key();
         hashes_to_find.insert(k.path.hash);
@@ -131,12 +188,13 @@ pub fn scan_texture_suffixes(database: &Database) -> Vec<Box<str>> {
                 }
             }
             else {
-                let idx = (k.path.hash >> 48) as u16;
-                prefilter.set(idx, true);
+                prefilter_hashes.push(k.path.hash);
             }
         }
     }
-    
+
+    let prefilter: BloomFilter = prefilter_hashes.into_iter().collect();
+
     eprintln!("Candidates {} {}", known_paths.len(), known_suffixes.len());
 
     let path_len = known_paths.iter().map(|i| i.len()).max().unwrap_or_default();
@@ -152,7 +210,7 @@ pub fn scan_texture_suffixes(database: &Database) -> Vec<Box<str>> {
             buf.push_str(path);
 
             let h = dhash(buf.as_str());
-            if prefilter.get((h >> 48) as u16) {
+            if prefilter.get(h) {
                 if hashes_to_find.contains(&h) {
                     inner_result.insert(Box::<str>::from(buf.as_str()));
                 }
@@ -166,7 +224,7 @@ pub fn scan_texture_suffixes(database: &Database) -> Vec<Box<str>> {
                 buf.push_str(suffix);
 
                 let h = dhash(buf.as_str());
-                if prefilter.get((h >> 48) as u16) {
+                if prefilter.get(h) {
                     if hashes_to_find.contains(&h) {
                         inner_result.insert(Box::<str>::from(buf.as_str()));
                     }
@@ -214,6 +272,138 @@ fn scan_suffixes_for_type(database: &Database, filetypes: &[u64], suffixes: &[&s
     result
 }
 
+/// One placeholder slot in a [`Template`]: either draws from a caller-supplied
+/// wordlist, or walks a fixed-width decimal range (however many `n`s appear
+/// inside the braces, so `{nn}` is `00`..=`99`).
+enum TemplateSlot<'a> {
+    Literal(String),
+    Word(&'a [&'a str]),
+    Number(u32)
+}
+
+/// A brute-force template like `units/{a}/{b}_{nn}`, split into literal runs
+/// and resolved `{name}`/`{n...}` placeholders, ready to expand as a
+/// cartesian product - the same way sourmash expands a codon table into
+/// k-mers, one slot at a time.
+pub struct Template<'a> {
+    slots: Vec<TemplateSlot<'a>>
+}
+
+impl<'a> Template<'a> {
+    /// Parses `template` and resolves each `{name}` placeholder against
+    /// `wordlists`. A placeholder made up entirely of `n` characters (e.g.
+    /// `{nn}`) is a numeric slot instead, with that many decimal digits.
+    ///
+    /// # Panics
+    /// Panics if a placeholder is unterminated, or if `{name}` doesn't match
+    /// a key in `wordlists` and isn't all-`n`.
+    pub fn parse(template: &str, wordlists: &std::collections::HashMap<&str, &'a [&'a str]>) -> Template<'a> {
+        let mut slots = Vec::new();
+        let mut rest = template;
+        loop {
+            match rest.find('{') {
+                None => {
+                    if !rest.is_empty() { slots.push(TemplateSlot::Literal(rest.to_owned())); }
+                    break;
+                },
+                Some(start) => {
+                    if start > 0 { slots.push(TemplateSlot::Literal(rest[..start].to_owned())); }
+                    let after = &rest[(start + 1)..];
+                    let end = after.find('}').expect("unterminated {placeholder} in brute-force template");
+                    let name = &after[..end];
+                    if !name.is_empty() && name.chars().all(|c| c == 'n') {
+                        slots.push(TemplateSlot::Number(name.len() as u32));
+                    }
+                    else {
+                        let words = wordlists.get(name)
+                            .unwrap_or_else(|| panic!("no wordlist supplied for {{{}}}", name));
+                        slots.push(TemplateSlot::Word(words));
+                    }
+                    rest = &after[(end + 1)..];
+                }
+            }
+        }
+        Template { slots }
+    }
+}
+
+/// Expands every candidate a [`Template`] describes, hashing each with
+/// [`dhash`] and keeping only the ones matching an unresolved path hash in
+/// `database`. Candidates are built in a single reusable buffer (each slot
+/// just truncates back to its own mark and appends its next value) so
+/// nothing allocates per-candidate, and the whole expansion stops as soon as
+/// every unresolved hash has been matched.
+pub fn scan_template(database: &Database, template: &Template) -> Vec<Box<str>> {
+    let mut hashes_to_find = FnvHashSet::default();
+    for file in database.files() {
+        let k = file.key();
+        if k.path.text.is_none() {
+            hashes_to_find.insert(k.path.hash);
+        }
+    }
+    let prefilter: BloomFilter = hashes_to_find.iter().copied().collect();
+
+    let buf_capacity: usize = template.slots.iter().map(|slot| match slot {
+        TemplateSlot::Literal(s) => s.len(),
+        TemplateSlot::Word(words) => words.iter().map(|w| w.len()).max().unwrap_or(0),
+        TemplateSlot::Number(digits) => *digits as usize
+    }).sum();
+
+    let mut found = Vec::<Box<str>>::new();
+    let mut buf = String::with_capacity(buf_capacity);
+
+    expand_template(&template.slots, 0, &mut buf, &mut |candidate| {
+        let h = dhash(candidate);
+        if prefilter.get(h) && hashes_to_find.remove(&h) {
+            found.push(Box::from(candidate));
+        }
+        !hashes_to_find.is_empty()
+    });
+
+    found
+}
+
+/// Depth-first cartesian expansion of `slots[slot_idx..]` into `buf`,
+/// calling `on_candidate` with the finished string at each leaf. Returns
+/// `false` (and unwinds without trying further alternatives) as soon as
+/// `on_candidate` does, which [`scan_template`] uses to stop once every
+/// target hash has been found.
+fn expand_template(slots: &[TemplateSlot], slot_idx: usize, buf: &mut String, on_candidate: &mut impl FnMut(&str) -> bool) -> bool {
+    if slot_idx == slots.len() {
+        return on_candidate(buf);
+    }
+
+    let mark = buf.len();
+    let keep_going = match &slots[slot_idx] {
+        TemplateSlot::Literal(s) => {
+            buf.push_str(s);
+            expand_template(slots, slot_idx + 1, buf, on_candidate)
+        },
+        TemplateSlot::Word(words) => {
+            let mut keep_going = true;
+            for word in *words {
+                buf.truncate(mark);
+                buf.push_str(word);
+                keep_going = expand_template(slots, slot_idx + 1, buf, on_candidate);
+                if !keep_going { break; }
+            }
+            keep_going
+        },
+        TemplateSlot::Number(digits) => {
+            let mut keep_going = true;
+            for n in 0..10u64.pow(*digits) {
+                buf.truncate(mark);
+                write!(buf, "{:0width$}", n, width = *digits as usize).unwrap();
+                keep_going = expand_template(slots, slot_idx + 1, buf, on_candidate);
+                if !keep_going { break; }
+            }
+            keep_going
+        }
+    };
+    buf.truncate(mark);
+    keep_going
+}
+
 fn insert_if_exists<D: Extend<Box<str>>>(dest: &mut D, database: &Database, filetypes: &[u64], path: &str) {
     let hsh = dhash(path);
     for filetype in filetypes {
@@ -222,4 +412,97 @@ fn insert_if_exists<D: Extend<Box<str>>>(dest: &mut D, database: &Database, file
             dest.extend(std::iter::once(b));
         }
     }
+}
+
+/// One transformation in a [`Rule`]'s chain, branching every candidate it's
+/// given into one or more new candidates - the data-driven equivalent of
+/// what `scan_mat_suffixes`/`scan_unit_suffixes` do by hand today.
+pub enum RuleStep<'a> {
+    /// Appends each of a fixed list of suffixes in turn (e.g. `_thq`, `_cc`).
+    AppendSuffix(&'a [&'a str]),
+    /// Appends every zero-padded decimal number with this many digits.
+    AppendNumber(u32)
+}
+
+/// A brute-force rule: start from every known path with extension
+/// `base_ext` (harvested from [`Database::files`]), then branch each one
+/// through `steps` in turn. Lets a new naming convention be added as data
+/// instead of a new Rust function - a generalisation of [`Template`] whose
+/// base set is read out of the database rather than supplied by the caller.
+pub struct Rule<'a> {
+    pub base_ext: u64,
+    pub steps: &'a [RuleStep<'a>]
+}
+
+/// Depth-first expansion of `steps[step_idx..]` onto `buf`, mirroring
+/// [`expand_template`]: each step truncates back to its own mark before
+/// trying its next alternative, so nothing allocates per-candidate.
+fn expand_rule_steps(steps: &[RuleStep], step_idx: usize, buf: &mut String, on_candidate: &mut impl FnMut(&str)) {
+    if step_idx == steps.len() {
+        on_candidate(buf);
+        return;
+    }
+
+    let mark = buf.len();
+    match &steps[step_idx] {
+        RuleStep::AppendSuffix(suffixes) => {
+            for suffix in *suffixes {
+                buf.truncate(mark);
+                buf.push_str(suffix);
+                expand_rule_steps(steps, step_idx + 1, buf, on_candidate);
+            }
+        },
+        RuleStep::AppendNumber(digits) => {
+            for n in 0..10u64.pow(*digits) {
+                buf.truncate(mark);
+                write!(buf, "{:0width$}", n, width = *digits as usize).unwrap();
+                expand_rule_steps(steps, step_idx + 1, buf, on_candidate);
+            }
+        }
+    }
+    buf.truncate(mark);
+}
+
+/// Runs `rule` against `database`: for every known path with extension
+/// `rule.base_ext`, branches through `rule.steps` in parallel with rayon,
+/// keeping whichever candidates hash to one of the database's unresolved
+/// path hashes, same as [`scan_template`].
+pub fn scan_rule(database: &Database, rule: &Rule) -> Vec<Box<str>> {
+    let mut hashes_to_find = FnvHashSet::default();
+    let mut prefilter_hashes = Vec::new();
+    let mut base_paths = Vec::new();
+
+    for file in database.files() {
+        let k = file.key();
+        if k.path.text.is_none() {
+            hashes_to_find.insert(k.path.hash);
+            prefilter_hashes.push(k.path.hash);
+        }
+        else if k.extension.hash == rule.base_ext {
+            base_paths.push(k.path.text.unwrap());
+        }
+    }
+    let prefilter: BloomFilter = prefilter_hashes.into_iter().collect();
+
+    let step_capacity: usize = rule.steps.iter().map(|step| match step {
+        RuleStep::AppendSuffix(suffixes) => suffixes.iter().map(|s| s.len()).max().unwrap_or(0),
+        RuleStep::AppendNumber(digits) => *digits as usize
+    }).sum();
+    let buf_capacity = step_capacity + base_paths.iter().map(|p| p.len()).max().unwrap_or(0);
+
+    base_paths.into_par_iter().map_init(
+        || String::with_capacity(buf_capacity),
+        |buf, base| {
+            let mut found = Vec::new();
+            buf.clear();
+            buf.push_str(base);
+            expand_rule_steps(rule.steps, 0, buf, &mut |candidate| {
+                let h = dhash(candidate);
+                if prefilter.get(h) && hashes_to_find.contains(&h) {
+                    found.push(Box::from(candidate));
+                }
+            });
+            found
+        }
+    ).reduce(Vec::new, |mut a, b| { a.extend(b); a })
 }
\ No newline at end of file
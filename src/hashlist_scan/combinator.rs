@@ -0,0 +1,161 @@
+//! Combinatorial hash-reversal: joins wordlist tokens via configurable
+//! separators into a bounded-depth search tree and hashes every candidate,
+//! rather than [`super::wordlist_crack`]'s single-word-plus-template
+//! permutations.
+//!
+//! Most real Diesel engine paths are made of several independent tokens
+//! (`units/masks/mask_01`, say) rather than one word with a fixed prefix, so
+//! this explores depth-first joins of up to `max_depth` tokens separated by
+//! any of a configurable set (`/`, `.`, `_`, `""`), under an optional fixed
+//! prefix/suffix template. Since [`DieselHasher`] mixes its input in order,
+//! every node in the tree caches the hasher state after its own tokens are
+//! folded in, so a child only has to mix its own separator+token bytes
+//! instead of rehashing the whole candidate from scratch - the DFS shares
+//! the hash computation of common prefixes the same way it shares their
+//! text. The frontier (every node at the current depth) is expanded one
+//! depth at a time and sharded across threads with rayon, since real
+//! wordlists make the tree far too wide to explore on one core.
+
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use fnv::{FnvHashMap, FnvHashSet};
+use rayon::prelude::*;
+
+use crate::diesel_hash::DieselHasher;
+use crate::util::iter::FlatMapChain;
+
+/// Configuration for [`crack_combinations`]: which tokens can follow one
+/// another, how they're joined, and how deep the search goes.
+pub struct CombinatorRules<'a> {
+    /// Token wordlist tried at every position in the join.
+    pub tokens: &'a [Rc<str>],
+    /// Separators tried between consecutive tokens, e.g. `/`, `.`, `_`, or
+    /// `""` for direct concatenation. Not used before the first token.
+    pub separators: &'a [&'a str],
+    /// Maximum number of tokens joined into one candidate.
+    pub max_depth: usize,
+    /// Fixed text prepended to every candidate, e.g. `"units/"`.
+    pub prefix: &'a str,
+    /// Fixed text appended to every candidate before hashing, e.g. `".texture"`.
+    pub suffix: &'a str
+}
+
+/// One node of the join tree: the text joined so far (including `prefix`),
+/// and the [`DieselHasher`] state after folding that text in, so a child
+/// only has to mix its own separator+token bytes.
+#[derive(Clone)]
+struct Node {
+    text: Rc<str>,
+    state: DieselHasher,
+    depth: usize
+}
+
+/// What expanding a [`Node`] can produce - another node to keep extending,
+/// or a finished candidate ready to check against the target set. Unified
+/// into one type so [`FlatMapChain`] can interleave the two per node.
+enum Expansion {
+    Node(Node),
+    Candidate(Rc<str>, u64)
+}
+
+/// Every child of `node`: one per `(separator, token)` pair, or bare tokens
+/// (no separator) if `node` is the root. `None` once `max_depth` is reached.
+fn expand_children(node: &Node, rules: &CombinatorRules) -> Option<std::vec::IntoIter<Expansion>> {
+    if node.depth >= rules.max_depth {
+        return None;
+    }
+    let no_separator: &[&str] = &[""];
+    let separators = if node.depth == 0 { no_separator } else { rules.separators };
+
+    let mut children = Vec::with_capacity(separators.len() * rules.tokens.len());
+    for &sep in separators {
+        for token in rules.tokens {
+            let mut text = String::with_capacity(node.text.len() + sep.len() + token.len());
+            text.push_str(&node.text);
+            text.push_str(sep);
+            text.push_str(token);
+
+            let mut state = node.state.clone();
+            state.write(sep.as_bytes());
+            state.write(token.as_bytes());
+
+            children.push(Expansion::Node(Node { text: Rc::from(text), state, depth: node.depth + 1 }));
+        }
+    }
+    Some(children.into_iter())
+}
+
+/// `node` as a finished candidate - `rules.suffix` applied and hashed - if
+/// it's deep enough to be one (the empty root never is).
+fn finish_candidate(node: &Node, rules: &CombinatorRules) -> Option<std::vec::IntoIter<Expansion>> {
+    if node.depth == 0 {
+        return None;
+    }
+
+    let mut state = node.state.clone();
+    state.write(rules.suffix.as_bytes());
+    let hash = state.finish();
+
+    let text: Rc<str> = if rules.suffix.is_empty() {
+        node.text.clone()
+    }
+    else {
+        let mut s = String::with_capacity(node.text.len() + rules.suffix.len());
+        s.push_str(&node.text);
+        s.push_str(rules.suffix);
+        Rc::from(s)
+    };
+
+    Some(vec![Expansion::Candidate(text, hash)].into_iter())
+}
+
+/// Joins `rules.tokens` into every candidate up to `rules.max_depth` tokens
+/// deep, hashing each with the Diesel hash, and returns every one that lands
+/// on a hash in `targets` - in the same shape [`super::build_dictionary`] and
+/// [`super::wordlist_crack::crack`] produce, so results from all three merge
+/// freely.
+pub fn crack_combinations(rules: &CombinatorRules, targets: &FnvHashSet<u64>) -> FnvHashMap<u64, Rc<str>> {
+    let found: Mutex<FnvHashMap<u64, Rc<str>>> = Mutex::new(FnvHashMap::default());
+    let remaining = AtomicUsize::new(targets.len());
+
+    let mut root_state = DieselHasher::new(0);
+    root_state.write(rules.prefix.as_bytes());
+    let mut frontier = vec![Node { text: Rc::from(rules.prefix), state: root_state, depth: 0 }];
+
+    while !frontier.is_empty() && remaining.load(Ordering::Relaxed) > 0 {
+        let chunk_size = (frontier.len() / rayon::current_num_threads().max(1)).max(1);
+        let next_frontier: Mutex<Vec<Node>> = Mutex::new(Vec::new());
+
+        frontier.par_chunks(chunk_size).for_each(|chunk| {
+            if remaining.load(Ordering::Relaxed) == 0 { return; }
+
+            let expansions = FlatMapChain::new(
+                chunk.iter().cloned(),
+                |node: Node| expand_children(&node, rules),
+                |node: Node| finish_candidate(&node, rules)
+            );
+
+            let mut local_frontier = Vec::new();
+            for expansion in expansions {
+                match expansion {
+                    Expansion::Node(n) => local_frontier.push(n),
+                    Expansion::Candidate(text, hash) => {
+                        if targets.contains(&hash) {
+                            let mut f = found.lock().unwrap();
+                            if f.insert(hash, text).is_none() {
+                                remaining.fetch_sub(1, Ordering::Relaxed);
+                            }
+                        }
+                    }
+                }
+            }
+            next_frontier.lock().unwrap().extend(local_frontier);
+        });
+
+        frontier = next_frontier.into_inner().unwrap();
+    }
+
+    found.into_inner().unwrap()
+}
@@ -0,0 +1,51 @@
+//! Generic printable-string extraction, used as a fallback for file types
+//! that don't have a dedicated scanner in [`super::scriptdata`] or
+//! [`super::xml`]. Inspired by decomp-toolkit's section string detection:
+//! rather than understanding the format, just walk the raw bytes and keep
+//! whatever maximal runs look enough like an asset path to be worth hashing.
+
+use std::rc::Rc;
+
+use super::xpath::TryStringIterator;
+
+/// Minimum run length (in bytes) used by [`scan`].
+pub const DEFAULT_MIN_LEN: usize = 5;
+
+/// True for bytes that can appear inside an extracted run. Diesel asset
+/// paths are plain ASCII, so there's no need to deal with decoding
+/// multi-byte UTF-8 here.
+fn is_string_byte(b: u8) -> bool {
+    matches!(b, 0x20..=0x7e)
+}
+
+/// True if `s` looks enough like an asset path to be worth keeping, rather
+/// than e.g. a run of spaces, or printable bytes that happen to spell out
+/// some unrelated embedded text.
+fn looks_like_path(s: &str) -> bool {
+    let has_path_char = s.bytes().any(|b| b == b'/' || b == b'_' || b == b'.');
+    let all_path_like = s.bytes().all(|b| matches!(b,
+        b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'/' | b'_' | b'.' | b'-'
+    ));
+    has_path_char && all_path_like
+}
+
+/// Walks `buf` collecting maximal runs of [`is_string_byte`] bytes at least
+/// `min_len` long, keeping the ones [`looks_like_path`] accepts as
+/// candidate names.
+pub fn scan_min_len(buf: &[u8], min_len: usize) -> TryStringIterator {
+    let mut out = Vec::new();
+    for run in buf.split(|&b| !is_string_byte(b)) {
+        if run.len() < min_len { continue; }
+        // is_string_byte only accepts single-byte ASCII, so this is always valid UTF-8.
+        let s = std::str::from_utf8(run).unwrap();
+        if looks_like_path(s) {
+            out.push(Rc::from(s));
+        }
+    }
+    Ok(Box::new(out.into_iter()))
+}
+
+/// [`scan_min_len`] with [`DEFAULT_MIN_LEN`].
+pub fn scan(buf: &[u8]) -> TryStringIterator {
+    scan_min_len(buf, DEFAULT_MIN_LEN)
+}
@@ -0,0 +1,343 @@
+//! Runtime interpreter over the same [`ops2`](super::scriptdata) combinators the
+//! `scan3!` macro expands into, driven by a text config instead of a macro
+//! invocation baked into the binary. The compiled `scan3!` rules in
+//! [`super::scriptdata`] remain the defaults for the well-known extensions;
+//! this is an escape hatch so a user chasing down a new scriptdata schema can
+//! add an extraction rule for it by dropping a file next to the hashlist,
+//! rather than patching this crate and rebuilding.
+//!
+//! # Syntax
+//!
+//! One rule per block, named after the extension it applies to:
+//!
+//! ```text
+//! unit_overlay {
+//!     root() |> indexed() |> metatable("unit_overlay") |> key("name") |> strings()
+//! }
+//! ```
+//!
+//! Steps are chained with `|>`. `;` separates alternative pipelines that
+//! start from the same upstream and whose results get concatenated - at the
+//! top level of a rule, or inside a `{ ... }` group, which forks whatever
+//! reaches it to each of its `;`-separated branches before rejoining the main
+//! pipeline. This mirrors `scan3!`'s own grammar; see that module's doc
+//! comment-free rules for worked examples of the shape being copied.
+
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use crate::formats::scriptdata::{Document, DocTable, DocValue, TableEntriesThroughCell};
+use crate::util::rc_cell::RcCell;
+
+#[derive(Debug, Clone)]
+pub enum Step {
+    Root,
+    Indexed,
+    Entries,
+    Metatable(String),
+    Key(String),
+    KeyEqualStr(String, String),
+    LiteralStr(String),
+    Strings,
+    FormatPrefix(String),
+    Branch(Vec<Vec<Step>>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name: String,
+    pub branches: Vec<Vec<Step>>,
+}
+
+/// What flows between steps. Most steps work on [`DocValue`]; [`Step::Strings`]
+/// is where a pipeline crosses over into plain text, after which only
+/// [`Step::FormatPrefix`] (and further branching) still makes sense.
+#[derive(Clone)]
+enum Item {
+    Doc(DocValue),
+    Str(Rc<str>),
+}
+
+impl Item {
+    fn into_table(self) -> Option<RcCell<DocTable>> {
+        match self {
+            Item::Doc(v) => RcCell::<DocTable>::try_from(v).ok(),
+            Item::Str(_) => None,
+        }
+    }
+}
+
+/// Parses the contents of a rule config file into zero or more [`Rule`]s.
+pub fn parse_rules(text: &str) -> Result<Vec<Rule>, String> {
+    let tokens = tokenize(text);
+    let mut pos = 0;
+    let mut rules = Vec::new();
+    while pos < tokens.len() {
+        let name = expect_ident(&tokens, &mut pos)?;
+        expect_punct(&tokens, &mut pos, "{")?;
+        let branches = parse_branches(&tokens, &mut pos, "}")?;
+        expect_punct(&tokens, &mut pos, "}")?;
+        rules.push(Rule { name, branches });
+    }
+    Ok(rules)
+}
+
+/// Evaluates `rule` against an already-parsed `doc`, producing the same kind
+/// of result a compiled `scan3!` function would.
+pub fn eval_rule(rule: &Rule, doc: &Document) -> Result<Box<dyn Iterator<Item=Rc<str>>>, Box<dyn std::error::Error>> {
+    let mut out: Box<dyn Iterator<Item=Rc<str>>> = Box::new(std::iter::empty());
+    for branch in &rule.branches {
+        let root: Box<dyn Iterator<Item=Item>> = match doc.root() {
+            Some(v) => Box::new(std::iter::once(Item::Doc(v))),
+            None => Box::new(std::iter::empty()),
+        };
+        let items = eval_steps(root, branch, doc);
+        let strs = items.filter_map(|i| match i {
+            Item::Str(s) => Some(s),
+            Item::Doc(_) => None,
+        });
+        out = Box::new(out.chain(strs));
+    }
+    Ok(out)
+}
+
+fn eval_steps<'d>(input: Box<dyn Iterator<Item=Item> + 'd>, steps: &[Step], doc: &'d Document) -> Box<dyn Iterator<Item=Item> + 'd> {
+    let mut cur = input;
+    for step in steps {
+        cur = eval_step(cur, step, doc);
+    }
+    cur
+}
+
+fn eval_step<'d>(input: Box<dyn Iterator<Item=Item> + 'd>, step: &Step, doc: &'d Document) -> Box<dyn Iterator<Item=Item> + 'd> {
+    match step {
+        Step::Root => input,
+        Step::Indexed => Box::new(input.filter_map(|i| i.into_table()).flat_map(|t| {
+            IndexedValues { table: t, counter: 0 }
+        }).map(Item::Doc)),
+        Step::Entries => Box::new(input.filter_map(|i| i.into_table()).flat_map(|t| {
+            TableEntriesThroughCell::new(t)
+        }).map(Item::Doc)),
+        Step::Metatable(name) => {
+            let name = name.to_ascii_lowercase();
+            Box::new(input.filter_map(|i| i.into_table()).filter(move |t| {
+                t.borrow().get_metatable().map(|id| doc.resolve(id).to_ascii_lowercase() == name).unwrap_or(false)
+            }).map(|t| Item::Doc(DocValue::Table(t))))
+        },
+        Step::Key(name) => {
+            let name = name.clone();
+            Box::new(input.filter_map(|i| i.into_table()).filter_map(move |t| {
+                let b = t.borrow();
+                (&*b).into_iter()
+                    .find(|(k, _)| matches!(k, DocValue::String(id) if doc.resolve(*id) == name))
+                    .map(|(_, v)| v.clone())
+            }).map(Item::Doc))
+        },
+        Step::KeyEqualStr(name, value) => {
+            let name = name.clone();
+            let value = value.clone();
+            Box::new(input.filter_map(|i| i.into_table()).filter(move |t| {
+                let b = t.borrow();
+                (&*b).into_iter().any(|(k, v)| {
+                    matches!(k, DocValue::String(id) if doc.resolve(*id) == name)
+                        && matches!(v, DocValue::String(id) if doc.resolve(*id) == value)
+                })
+            }).map(|t| Item::Doc(DocValue::Table(t))))
+        },
+        Step::LiteralStr(s) => {
+            let s: Rc<str> = Rc::from(s.as_str());
+            Box::new(input.map(move |_| Item::Str(s.clone())))
+        },
+        Step::Strings => Box::new(input.filter_map(move |i| match i {
+            Item::Doc(DocValue::String(s)) => Some(Item::Str(doc.resolve_rc(s))),
+            Item::Str(s) => Some(Item::Str(s)),
+            Item::Doc(_) => None,
+        })),
+        Step::FormatPrefix(prefix) => {
+            let prefix = prefix.clone();
+            Box::new(input.filter_map(move |i| match i {
+                Item::Str(s) => Some(Item::Str(Rc::from(format!("{}{}", prefix, s)))),
+                Item::Doc(_) => None,
+            }))
+        },
+        Step::Branch(branches) => {
+            let branches = branches.clone();
+            Box::new(input.flat_map(move |item| {
+                let mut acc: Box<dyn Iterator<Item=Item>> = Box::new(std::iter::empty());
+                for branch in &branches {
+                    let start: Box<dyn Iterator<Item=Item>> = Box::new(std::iter::once(item.clone()));
+                    acc = Box::new(acc.chain(eval_steps(start, branch, doc)));
+                }
+                acc
+            }))
+        },
+    }
+}
+
+/// Same shape as the `scan3!` macro's own private helper in [`super::scriptdata`] -
+/// walks a table's `1, 2, 3, ...` keys until one is missing.
+struct IndexedValues {
+    table: RcCell<DocTable>,
+    counter: usize,
+}
+impl Iterator for IndexedValues {
+    type Item = DocValue;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.counter += 1;
+        let r = self.table.borrow();
+        r.get(&DocValue::from(self.counter as f32)).cloned()
+    }
+}
+
+// --- tokenizer / parser -----------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Punct(char),
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut out = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        if c.is_whitespace() { chars.next(); continue; }
+        if c == '#' {
+            while let Some(&(_, c2)) = chars.peek() {
+                if c2 == '\n' { break; }
+                chars.next();
+            }
+            continue;
+        }
+        if c == '"' {
+            chars.next();
+            let mut s = String::new();
+            while let Some((_, c2)) = chars.next() {
+                if c2 == '"' { break; }
+                s.push(c2);
+            }
+            out.push(Token::Str(s));
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            let mut end = i + c.len_utf8();
+            chars.next();
+            while let Some(&(j, c2)) = chars.peek() {
+                if c2.is_alphanumeric() || c2 == '_' {
+                    end = j + c2.len_utf8();
+                    chars.next();
+                } else { break; }
+            }
+            out.push(Token::Ident(text[start..end].to_owned()));
+            continue;
+        }
+        if c == '|' {
+            chars.next();
+            if let Some(&(_, '>')) = chars.peek() {
+                chars.next();
+                out.push(Token::Punct('>'));
+                continue;
+            }
+            out.push(Token::Punct('|'));
+            continue;
+        }
+        out.push(Token::Punct(c));
+        chars.next();
+    }
+    out
+}
+
+fn expect_ident(tokens: &[Token], pos: &mut usize) -> Result<String, String> {
+    match tokens.get(*pos) {
+        Some(Token::Ident(s)) => { *pos += 1; Ok(s.clone()) },
+        other => Err(format!("expected identifier, found {:?}", other)),
+    }
+}
+
+fn expect_str(tokens: &[Token], pos: &mut usize) -> Result<String, String> {
+    match tokens.get(*pos) {
+        Some(Token::Str(s)) => { *pos += 1; Ok(s.clone()) },
+        other => Err(format!("expected string literal, found {:?}", other)),
+    }
+}
+
+fn expect_punct(tokens: &[Token], pos: &mut usize, p: &str) -> Result<(), String> {
+    let c = p.chars().next().unwrap();
+    match tokens.get(*pos) {
+        Some(Token::Punct(found)) if *found == c => { *pos += 1; Ok(()) },
+        other => Err(format!("expected '{}', found {:?}", p, other)),
+    }
+}
+
+fn peek_punct(tokens: &[Token], pos: usize, p: char) -> bool {
+    matches!(tokens.get(pos), Some(Token::Punct(c)) if *c == p)
+}
+
+/// Parses `;`-separated pipelines until a `}` (the caller's `terminator`) is
+/// seen without consuming it.
+fn parse_branches(tokens: &[Token], pos: &mut usize, terminator: &str) -> Result<Vec<Vec<Step>>, String> {
+    let term = terminator.chars().next().unwrap();
+    let mut branches = Vec::new();
+    loop {
+        branches.push(parse_pipeline(tokens, pos)?);
+        if peek_punct(tokens, *pos, ';') {
+            *pos += 1;
+            continue;
+        }
+        break;
+    }
+    if !peek_punct(tokens, *pos, term) {
+        return Err(format!("expected '{}' or ';', found {:?}", terminator, tokens.get(*pos)));
+    }
+    Ok(branches)
+}
+
+fn parse_pipeline(tokens: &[Token], pos: &mut usize) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    loop {
+        if peek_punct(tokens, *pos, '{') {
+            *pos += 1;
+            let branches = parse_branches(tokens, pos, "}")?;
+            expect_punct(tokens, pos, "}")?;
+            steps.push(Step::Branch(branches));
+        }
+        else {
+            steps.push(parse_step(tokens, pos)?);
+        }
+
+        if peek_punct(tokens, *pos, '>') {
+            *pos += 1;
+            continue;
+        }
+        break;
+    }
+    Ok(steps)
+}
+
+fn parse_step(tokens: &[Token], pos: &mut usize) -> Result<Step, String> {
+    let name = expect_ident(tokens, pos)?;
+    expect_punct(tokens, pos, "(")?;
+
+    let step = match name.as_str() {
+        "root" => Step::Root,
+        "indexed" => Step::Indexed,
+        "entries" => Step::Entries,
+        "strings" => Step::Strings,
+        "metatable" => Step::Metatable(expect_str(tokens, pos)?),
+        "key" => Step::Key(expect_str(tokens, pos)?),
+        "literal_str" => Step::LiteralStr(expect_str(tokens, pos)?),
+        "format_prefix" => Step::FormatPrefix(expect_str(tokens, pos)?),
+        "key_equal_str" => {
+            let k = expect_str(tokens, pos)?;
+            expect_punct(tokens, pos, ",")?;
+            let v = expect_str(tokens, pos)?;
+            Step::KeyEqualStr(k, v)
+        },
+        other => return Err(format!("unknown scan step \"{}\"", other)),
+    };
+
+    expect_punct(tokens, pos, ")")?;
+    Ok(step)
+}
@@ -6,14 +6,14 @@ macro_rules! scan3 {
         scan3!(@a $chain $id ($path.flat_map(|item| {
             let fm = std::iter::once(item);
             scan3!(@a (std::iter::empty()) (fm.clone()) (fm.clone()) |> $($childs)+ )
-        })) $($rest)*)  
+        })) $($rest)*)
     };
     (@a $chain:tt $id:tt $path:tt |> $t:ident ($($arg:expr),*) $($rest:tt)*) => {
         scan3!(@a $chain $id (ops2::$t($path, $($arg),*)) $($rest)* )
     };
     (@a $chain:tt $id:tt $path:tt ; $($rest:tt)*) => {
         scan3!(@a ($chain.chain($path)) $id $id |> $($rest)*)
-        
+
     };
     (@a $chain:tt $id:tt $path:tt) => {
         ($chain.chain($path))
@@ -32,31 +32,31 @@ macro_rules! scan3 {
 
 scan3! {
     scan_credits() {
-        root() |> indexed() |> metatable("image") |> { key("src") ; key("SRC") } |> strings() |> map(|i| Rc::from(i.to_ascii_lowercase()))
+        root() |> indexed() |> metatable("image", doc) |> { key("src", doc) ; key("SRC", doc) } |> strings(doc) |> map(|i| Rc::from(i.to_ascii_lowercase()))
     }
-    
+
     scan_dialog_index() {
-        root() |> indexed() |> metatable("include") |> key("name") |> strings()
+        root() |> indexed() |> metatable("include", doc) |> key("name", doc) |> strings(doc)
         |> map(|i| Rc::from(format!("gamedata/dialogs/{}", i)))
     }
     scan_sequence_manager() {
-        root() 
-        |> indexed() |> metatable("unit")
-        |> indexed() |> metatable("sequence")
-        |> indexed() |> metatable("material_config")
-        |> key("name") |> strings() |> fmap(unquote_lua)
+        root()
+        |> indexed() |> metatable("unit", doc)
+        |> indexed() |> metatable("sequence", doc)
+        |> indexed() |> metatable("material_config", doc)
+        |> key("name", doc) |> strings(doc) |> fmap(unquote_lua)
     }
     scan_environment() {
-        root() |> indexed() |> metatable("data") |> indexed() |> metatable("others") |> {
-            key("global_world_overlay_texture") ;
-            key("global_texture") ;
-            key("global_world_overlay_mask_texture") ;
-            key("underlay")
-        } |> strings()
-    }
-    
+        root() |> indexed() |> metatable("data", doc) |> indexed() |> metatable("others", doc) |> {
+            key("global_world_overlay_texture", doc) ;
+            key("global_texture", doc) ;
+            key("global_world_overlay_mask_texture", doc) ;
+            key("underlay", doc)
+        } |> strings(doc)
+    }
+
     scan_continent() {
-        root() |> key("instances") |> indexed() |> key("folder") |> strings()
+        root() |> key("instances", doc) |> indexed() |> key("folder", doc) |> strings(doc)
         |> fmap(|i| {
             let trimmed = i.strip_suffix("/world").unwrap_or(&i);
             vec![
@@ -67,45 +67,46 @@ scan3! {
         })
         ;
 
-        root() |> key("statics") |> indexed() |> key("unit_data") |> {
-            key("name") ;
-            key("editable_gui") |> key("font")
-        } |> strings()
+        root() |> key("statics", doc) |> indexed() |> key("unit_data", doc) |> {
+            key("name", doc) ;
+            key("editable_gui", doc) |> key("font", doc)
+        } |> strings(doc)
     }
 
     scan_continents(path: Rc<str>) {
-        root() |> indexed() |> key("name") |> strings() |> map(move |s|{
+        root() |> indexed() |> key("name", doc) |> strings(doc) |> map(move |s|{
             Rc::from(format!("{0}/{1}/{1}", parentof(&path), s))
         })
     }
 
     scan_world(path: Rc<str>) {
-        root() |> key("environment") |> {
-            key("environment_areas") |> indexed() |> key("environment");
-            key("environment_values") |> key("environment") ;
-            key("effects") |> indexed() |> key("name")
-        } |> strings() ;
+        root() |> key("environment", doc) |> {
+            key("environment_areas", doc) |> indexed() |> key("environment", doc);
+            key("environment_values", doc) |> key("environment", doc) ;
+            key("effects", doc) |> indexed() |> key("name", doc)
+        } |> strings(doc) ;
 
         root() |> {
             {
-                key("brush") ;
-                key("sounds") ;
-                key("world_camera") ;
-                key("ai_nav_graphs")
-            } |> key("file") ;
-            key("world_data") |> key("continents_file") ;
-            literal_str("cover_data")
-        } |> strings() |> map(move |i| Rc::from(format!("{}/{}", parentof(&path), i)))
+                key("brush", doc) ;
+                key("sounds", doc) ;
+                key("world_camera", doc) ;
+                key("ai_nav_graphs", doc)
+            } |> key("file", doc) ;
+            key("world_data", doc) |> key("continents_file", doc)
+        } |> strings(doc) |> map({ let path = path.clone(); move |i| Rc::from(format!("{}/{}", parentof(&path), i)) }) ;
+
+        literal_str("cover_data") |> map(move |i: Rc<str>| Rc::from(format!("{}/{}", parentof(&path), i)))
     }
 
     scan_mission() {
-        root() |> entries() |> key("elements") |> indexed() |> {
-            key_equal_str("class", "ElementPlayEffect") |> key("values") |> key("effect");
-            key_equal_str("class", "ElementSpawnUnit") |> key("values") |> key("unit_name");
-            key_equal_str("class", "ElementLoadDelayed") |> key("values") |> key("unit_name");
-            key_equal_str("class", "ElementSpawnCivilian") |> key("values") |> key("enemy");
-            key_equal_str("class", "ElementSpawnEnemyDummy") |> key("values") |> key("enemy")
-        } |> strings()
+        root() |> entries() |> key("elements", doc) |> indexed() |> {
+            key_equal_str("class", "ElementPlayEffect", doc) |> key("values", doc) |> key("effect", doc);
+            key_equal_str("class", "ElementSpawnUnit", doc) |> key("values", doc) |> key("unit_name", doc);
+            key_equal_str("class", "ElementLoadDelayed", doc) |> key("values", doc) |> key("unit_name", doc);
+            key_equal_str("class", "ElementSpawnCivilian", doc) |> key("values", doc) |> key("enemy", doc);
+            key_equal_str("class", "ElementSpawnEnemyDummy", doc) |> key("values", doc) |> key("enemy", doc)
+        } |> strings(doc)
     }
 }
 
@@ -128,7 +129,7 @@ fn unquote_lua(input: Rc<str>) -> Option<Rc<str>> {
     };
 
     let body = trimmed[1..].strip_suffix(first.unwrap())?;
-    
+
     // this is dirty, but the only things you can have in a filename that
     // lua requires quoting you just prefix with a \ anyway.
     Some(Rc::from(body.replace('\\', "")))
@@ -144,8 +145,11 @@ mod ops2 {
         input.root().into_iter()
     }
 
-    pub fn strings<TIter: Iterator<Item=TIn>, TIn: TryInto<Rc<str>>>(input: TIter) -> impl Iterator<Item=Rc<str>> {
-        input.flat_map(|i| i.try_into())
+    pub fn strings<'d, TIter: Iterator<Item=DocValue> + 'd>(input: TIter, doc: &'d Document) -> impl Iterator<Item=Rc<str>> + 'd {
+        input.filter_map(move |v| match v {
+            DocValue::String(id) => Some(doc.resolve_rc(id)),
+            _ => None
+        })
     }
 
     pub fn indexed<TIter, TIn>(input: TIter) -> impl Iterator<Item=DocValue>
@@ -187,40 +191,42 @@ mod ops2 {
         })
     }
 
-    pub fn key<TIter, TIn>(input: TIter, name: &str) -> impl Iterator<Item=DocValue>
+    pub fn key<'d, TIter, TIn>(input: TIter, name: &str, doc: &'d Document) -> impl Iterator<Item=DocValue> + 'd
     where
-        TIter: Iterator<Item=TIn>,
+        TIter: Iterator<Item=TIn> + 'd,
         TIn: TryInto<RcCell<DocTable>>
     {
-        let n = DocValue::String(Rc::from(name));
-        input.flat_map(|i| i.try_into()).flat_map(move |rcct|{
-            rcct.borrow().get(&n).map(|v|v.clone())
+        let name = name.to_string();
+        input.flat_map(|i| i.try_into()).flat_map(move |rcct| {
+            let b = rcct.borrow();
+            (&*b).into_iter()
+                .find(|(k, _)| matches!(k, DocValue::String(id) if doc.resolve(*id) == name))
+                .map(|(_, v)| v.clone())
         })
     }
 
-    pub fn metatable<TIter, TIn>(input: TIter, name: &'static str) -> impl Iterator<Item=RcCell<DocTable>>
+    pub fn metatable<'d, TIter, TIn>(input: TIter, name: &'static str, doc: &'d Document) -> impl Iterator<Item=RcCell<DocTable>> + 'd
     where
-        TIter: Iterator<Item=TIn>,
+        TIter: Iterator<Item=TIn> + 'd,
         TIn: TryInto<RcCell<DocTable>>
     {
         input.flat_map(|i| i.try_into()).filter(move |rct| {
             let b = rct.borrow();
-            b.get_metatable().map(|mt| mt.to_ascii_lowercase() == name).unwrap_or(false)
+            b.get_metatable().map(|id| doc.resolve(id).to_ascii_lowercase() == name).unwrap_or(false)
         })
     }
 
-    pub fn key_equal_str<TIter, TIn>(input: TIter, name: &'static str, value: &'static str) -> impl Iterator<Item=RcCell<DocTable>>
+    pub fn key_equal_str<'d, TIter, TIn>(input: TIter, name: &'static str, value: &'static str, doc: &'d Document) -> impl Iterator<Item=RcCell<DocTable>> + 'd
     where
-        TIter: Iterator<Item=TIn>,
+        TIter: Iterator<Item=TIn> + 'd,
         TIn: TryInto<RcCell<DocTable>>
     {
-        let key = DocValue::String(Rc::from(name));
         input.flat_map(|i| i.try_into()).filter(move |rct| {
             let b = rct.borrow();
-            match b.get(&key) {
-                Some(DocValue::String(s)) => s.as_ref() == value,
-                _ => false
-            }
+            (&*b).into_iter().any(|(k, v)| {
+                matches!(k, DocValue::String(id) if doc.resolve(*id) == name)
+                    && matches!(v, DocValue::String(id) if doc.resolve(*id) == value)
+            })
         })
     }
 
@@ -230,7 +236,7 @@ mod ops2 {
     {
         input.map(f)
     }
-    
+
     pub fn literal_str<TR, TIn>(_: TIn, s: &str) -> std::iter::Once<TR>
     where
         TR: From<Rc<str>>,
@@ -247,4 +253,4 @@ mod ops2 {
     {
         input.flat_map(f)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,149 @@
+//! Bulk wordlist cracking of unresolved Diesel hashes.
+//!
+//! [`crate::diesel_hash`]'s `hash_str`/`hash_level` are pure and cheap, so
+//! rather than brute-forcing a narrow template like [`super::bruteforce`]'s
+//! `Template`/`Rule` do, this throws a whole wordlist - a user-supplied list
+//! of candidate names, the same kind [`super::do_scan`] writes out - at the
+//! database's unresolved path hashes, permuting each word through the usual
+//! Diesel naming conventions (directory prefix, extension suffix, numeric
+//! suffix) and sharding the wordlist across threads with rayon. Matches land
+//! in a shared dictionary of the same shape [`super::build_dictionary`]
+//! produces, so it composes with the rest of the unhashing pipeline.
+
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use fnv::{FnvHashMap, FnvHashSet};
+use rayon::prelude::*;
+
+use diesel_hash::hash_nonconst::hash_str as dhash;
+use diesel_hash::hash::parse_flexibly;
+
+use crate::bundles::database::Database;
+use super::bruteforce::BloomFilter;
+
+/// The permutations to try for each wordlist entry. Each field is
+/// independently optional (an empty slice, or `max_suffix == 0`, skips that
+/// axis); leaving everything empty just tries each word bare.
+pub struct PermuteRules<'a> {
+    /// Directory prefixes to try prepending, each already ending in `/`.
+    /// The bare word (no prefix) is always tried as well.
+    pub prefixes: &'a [&'a str],
+    /// Extensions to try appending as `.ext`, e.g. `"texture"`, `"unit"`,
+    /// `"model"`. The word is always also tried with no extension.
+    pub extensions: &'a [&'a str],
+    /// Tries appending `_0` up to `_{max_suffix - 1}` as well as the bare
+    /// word.
+    pub max_suffix: u32,
+}
+
+impl<'a> Default for PermuteRules<'a> {
+    fn default() -> PermuteRules<'a> {
+        PermuteRules { prefixes: &[], extensions: &[], max_suffix: 0 }
+    }
+}
+
+/// Parses a list of hash-ish strings (raw hex, or the `@ID...@` form) into a
+/// target set, silently dropping anything that doesn't parse as either -
+/// callers that care about rejected entries should validate up front.
+pub fn parse_targets<'a>(strs: impl Iterator<Item=&'a str>) -> FnvHashSet<u64> {
+    strs.filter_map(|s| parse_flexibly(s, 16).ok()).collect()
+}
+
+/// Every unresolved path hash in `database`, ready to feed to [`crack`] as-is
+/// or unioned with an explicit target set from [`parse_targets`].
+pub fn unresolved_hashes(database: &Database) -> FnvHashSet<u64> {
+    database.files().filter_map(|item| {
+        let k = item.key();
+        if k.path.text.is_none() { Some(k.path.hash) } else { None }
+    }).collect()
+}
+
+/// Loads a wordlist as plain newline-separated candidate names, in the same
+/// format [`super::build_dictionary`]'s output can be saved as.
+pub fn load_wordlist(path: &Path) -> io::Result<Vec<Rc<str>>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(text.lines().filter(|l| !l.is_empty()).map(Rc::from).collect())
+}
+
+/// Convenience wrapper around [`crack`] for the common case: crack `words`
+/// against every unresolved path hash in `database`, producing a dictionary
+/// of the same shape [`super::build_dictionary`] does - so it can be merged
+/// straight into one, or handed to [`crate::filesystem::unhash_fs::UnhashFs`]
+/// on its own.
+pub fn crack_wordlist(database: &Database, words: &[Rc<str>], rules: &PermuteRules) -> FnvHashMap<u64, Rc<str>> {
+    let targets = unresolved_hashes(database);
+    crack(words, &targets, rules)
+}
+
+/// Hashes every permutation of every word in `words` (per `rules`), keeping
+/// whichever ones land on one of `targets`. Stops permuting once every
+/// target has been matched - checked between words rather than mid-word,
+/// since a word's own permutations are cheap enough that over-shooting by one
+/// word's worth of work isn't worth the extra synchronisation.
+pub fn crack(words: &[Rc<str>], targets: &FnvHashSet<u64>, rules: &PermuteRules) -> FnvHashMap<u64, Rc<str>> {
+    let prefilter: BloomFilter = targets.iter().copied().collect();
+    let found: Mutex<FnvHashMap<u64, Rc<str>>> = Mutex::new(FnvHashMap::default());
+    let remaining = AtomicUsize::new(targets.len());
+
+    let buf_capacity = rules.prefixes.iter().map(|p| p.len()).max().unwrap_or(0)
+        + words.iter().map(|w| w.len()).max().unwrap_or(0)
+        + "_4294967295".len()
+        + rules.extensions.iter().map(|e| e.len() + 1).max().unwrap_or(0);
+
+    words.par_iter().for_each_init(
+        || String::with_capacity(buf_capacity),
+        |buf, word| {
+            if remaining.load(Ordering::Relaxed) == 0 { return; }
+
+            for_each_candidate(buf, word, rules, &mut |candidate| {
+                let h = dhash(candidate);
+                if prefilter.get(h) && targets.contains(&h) {
+                    let mut f = found.lock().unwrap();
+                    if f.insert(h, Rc::from(candidate)).is_none() {
+                        remaining.fetch_sub(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    );
+
+    found.into_inner().unwrap()
+}
+
+/// Builds every prefix/word/suffix/extension combination into `buf`, calling
+/// `on_candidate` for each - mirrors [`super::bruteforce::expand_template`]'s
+/// truncate-and-append style so nothing allocates per candidate.
+fn for_each_candidate(buf: &mut String, word: &str, rules: &PermuteRules, on_candidate: &mut impl FnMut(&str)) {
+    let no_prefix: &[&str] = &[""];
+    let prefixes = if rules.prefixes.is_empty() { no_prefix } else { rules.prefixes };
+
+    for prefix in prefixes {
+        let prefix_mark = 0;
+        buf.truncate(prefix_mark);
+        buf.push_str(prefix);
+        buf.push_str(word);
+        let word_mark = buf.len();
+
+        for suffix_n in 0..=rules.max_suffix {
+            buf.truncate(word_mark);
+            if suffix_n > 0 {
+                use std::fmt::Write;
+                write!(buf, "_{}", suffix_n - 1).unwrap();
+            }
+            let suffix_mark = buf.len();
+
+            on_candidate(buf.as_str());
+
+            for ext in rules.extensions {
+                buf.truncate(suffix_mark);
+                buf.push('.');
+                buf.push_str(ext);
+                on_candidate(buf.as_str());
+            }
+        }
+    }
+}
@@ -7,6 +7,7 @@ pub mod bundles;
 pub mod formats;
 pub mod hashlist_scan;
 pub mod filesystem;
+pub mod notation_rs;
 
 pub use diesel_hash;
 pub use diesel_hash::hashlist as hashindex;
@@ -17,13 +18,27 @@ use std::path::{Path, PathBuf};
 use hashindex::HashIndex;
 
 pub fn get_packagedb<'a>(hashlist: hashindex::HashIndex, asset_dir: &Path) -> Result<bundles::database::Database, bundles::ReadError> {
-    let coll = bundles::loader::load_bundle_dir(asset_dir)?;
+    use std::sync::Arc;
+
+    let coll = bundles::loader::load_bundle_dir(asset_dir, true)?;
 
     println!("Packages: {}", coll.1.len());
     println!("BDB Entries: {}", coll.0.files.len());
     println!();
 
-    Ok(bundles::database::from_bdb( hashlist, &coll.0, &coll.1))
+    let cache_path = asset_dir.join("bundle_database.cache");
+    let hashes = Arc::new(hashlist);
+    if let Ok(db) = bundles::database::load_cache(&cache_path, hashes.clone(), &coll.1) {
+        println!("Loaded package database from cache");
+        return Ok(db);
+    }
+
+    let hashlist = Arc::try_unwrap(hashes).unwrap_or_else(|_| panic!("package database cache outlived its own load attempt"));
+    let db = bundles::database::from_bdb(hashlist, &coll.0, &coll.1);
+    if let Err(e) = db.write_cache(&cache_path) {
+        println!("Couldn't write package database cache: {}", e);
+    }
+    Ok(db)
 }
 
 pub fn get_hashlist(hashlist_filename: &Option<String>) -> Option<HashIndex> {
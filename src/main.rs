@@ -8,20 +8,24 @@ mod hashindex;
 mod bundles;
 mod formats;
 mod hashlist_scan;
+mod notation_rs;
 
-#[cfg(feature="dokan")]
+#[cfg(any(feature="dokan", feature="fuse"))]
 mod filesystem;
 
 use std::vec::Vec;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::io::{Read,Write};
+use std::rc::Rc;
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use clap::arg_enum;
 use structopt::StructOpt;
 
-use hashindex::HashIndex;
+use util::read_helpers::TryFromIndexedLE;
+
+use hashindex::{HashIndex, HashAlgorithm, DieselHash, Murmur64};
 
 arg_enum! {
     #[derive(Debug, Clone, Copy, Ord, Eq, PartialOrd, PartialEq, Hash)]
@@ -29,16 +33,34 @@ arg_enum! {
         Binary,
         Lua,
         Generic,
-        Custom
+        Custom,
+        Json
+    }
+}
+
+arg_enum! {
+    #[derive(Debug, Clone, Copy, Ord, Eq, PartialOrd, PartialEq, Hash)]
+    enum DumpFormat {
+        Json,
+        Yaml
     }
 }
 
 #[derive(Debug, StructOpt)]
 #[structopt(name="Payday 2 CLI Tools", about="Does various things related to the game Payday 2")]
 struct Opt {
-    /// Path of hashlist to use. By default look in cwd and then next to the executable.
+    /// Path of hashlist to use. By default look in cwd and then next to the
+    /// executable. Can be given more than once to merge several lists into
+    /// one, with later lists overriding earlier ones on a hash collision
+    /// (and a list's own `%include`d files overriding it in turn).
     #[structopt(short, long)]
-    hashlist: Option<String>,
+    hashlist: Vec<String>,
+
+    /// Hash algorithm the hashlist was built with. Most Payday 2 tooling
+    /// wants "diesel" (the default); other Bitsquid/Stingray-derived games
+    /// may need "murmur64" instead.
+    #[structopt(long, default_value="diesel")]
+    algorithm: String,
 
     #[structopt(subcommand)]
     command: Command
@@ -78,12 +100,146 @@ enum Command {
         mountpoint: String
     },
 
+    #[cfg(feature="fuse")]
+    /// Mount packages as a volume using FUSE
+    MountFuse {
+        /// Directory containing bundle_db.blb
+        asset_dir: String,
+        /// Directory to mount on
+        mountpoint: String
+    },
+
     /// Scan packages for strings
     Scan {
         /// Directory containing bundle_db.blb
         asset_dir: String,
         /// File to write the strings to
-        output: String
+        output: String,
+        /// Run the generic strings fallback over every file, not just the
+        /// extensions with a dedicated scanner
+        #[structopt(long)]
+        all_files: bool,
+        /// Extra scan rules to run alongside the built-in ones, for
+        /// extensions this binary doesn't have a compiled-in scanner for.
+        /// See `hashlist_scan::rule_config` for the file format.
+        #[structopt(long)]
+        rules: Option<String>
+    },
+
+    #[cfg(any(feature="dokan", feature="fuse"))]
+    /// Recursively list (or verify) files in a package database
+    List {
+        /// Directory containing bundle_db.blb
+        asset_dir: String,
+        /// Print one path per line instead of the long-form column listing
+        #[structopt(short, long)]
+        short: bool,
+        /// Compute a content-hash column (required for --verify/--save-manifest)
+        #[structopt(long)]
+        hash: bool,
+        /// Disable ANSI colour in the listing
+        #[structopt(long)]
+        no_color: bool,
+        /// Compare against a manifest saved by --save-manifest, reporting
+        /// added/removed/changed entries instead of printing a listing
+        #[structopt(long)]
+        verify: Option<String>,
+        /// Save a manifest usable with --verify. Left untouched if its
+        /// contents wouldn't change.
+        #[structopt(long)]
+        save_manifest: Option<String>
+    },
+
+    #[cfg(any(feature="dokan", feature="fuse"))]
+    /// Content-hash every file in a package database and report mismatches
+    /// against a previously saved manifest, exiting non-zero on any
+    /// difference. Equivalent to `list --hash --verify` with a summary-only
+    /// mode, for use in CI.
+    Verify {
+        /// Directory containing bundle_db.blb
+        asset_dir: String,
+        /// Manifest saved by `list --save-manifest` to diff against. Without
+        /// this, just hashes every file and reports read failures.
+        expected: Option<String>,
+        /// Suppress per-file output, printing only the final pass/fail line
+        #[structopt(long)]
+        quiet: bool
+    },
+
+    #[cfg(any(feature="dokan", feature="fuse"))]
+    /// Recursively copy files out of a package database onto disk, without mounting it
+    Extract {
+        /// Directory containing bundle_db.blb
+        asset_dir: String,
+        /// Directory to write extracted files into
+        dest: String,
+        /// Virtual path to start extracting from, defaults to the root
+        #[structopt(long, default_value="")]
+        path: String,
+        /// Only extract paths matching this glob (`*`/`?` within a segment, `**` across segments)
+        #[structopt(long)]
+        include: Option<String>,
+        /// Skip paths matching this glob, checked after --include
+        #[structopt(long)]
+        exclude: Option<String>
+    },
+
+    /// Extract every asset in a package database to disk, writing a
+    /// verifiable mtree(5)-format manifest alongside it
+    #[structopt(name="extract-manifest")]
+    ExtractManifest {
+        /// Directory containing bundle_db.blb
+        asset_dir: String,
+        /// Directory to write extracted files into (or to re-check, in --verify mode)
+        out_dir: String,
+        /// Manifest file to write, or re-read in --verify mode
+        #[structopt(long, default_value="manifest.mtree")]
+        manifest: String,
+        /// Instead of extracting, re-read `manifest` and check every entry's
+        /// size and SHA-256 digest against what's already in `out_dir`
+        #[structopt(long)]
+        verify: bool
+    },
+
+    /// Report content-deduplication and size statistics over a package database
+    Stats {
+        /// Directory containing bundle_db.blb
+        asset_dir: String,
+        /// Also read each distinct physical extent to catch byte-identical
+        /// files stored at different offsets, not just shared ones
+        #[structopt(long)]
+        hash: bool,
+        /// How many entries to keep in the largest/most-duplicated tables
+        #[structopt(long, default_value="20")]
+        top: usize,
+        /// Write the full report as JSON to this file, instead of (or as
+        /// well as) the table printed to stdout
+        #[structopt(long)]
+        json: Option<String>
+    },
+
+    /// Crack unresolved hashes against a wordlist of candidate names
+    Crack {
+        /// Directory containing bundle_db.blb
+        asset_dir: String,
+        /// File of newline-separated candidate names to permute and hash
+        wordlist: String,
+        /// File to write resolved names to, one per line
+        output: String,
+        /// Directory prefixes to try prepending to each word
+        #[structopt(long)]
+        prefix: Vec<String>,
+        /// Extensions to try appending as `.ext` to each word
+        #[structopt(long, default_value="texture,unit,model", use_delimiter=true)]
+        extension: Vec<String>,
+        /// Also try each word with `_0` up to `_{max-suffix - 1}` appended
+        #[structopt(long, default_value="0")]
+        max_suffix: u32,
+        /// Also try joining up to this many wordlist entries together
+        /// (e.g. "units/masks/mask_01"), separated by any of `/`, `.`, `_`
+        /// or nothing. 0 disables this and only permutes single words.
+        #[structopt(long, default_value="0")]
+        combine_depth: usize
     },
 
     /// Convert between scriptdata formats
@@ -100,6 +256,20 @@ enum Command {
         #[structopt(short, long)]
         events: bool,
 
+        /// Merge structurally identical tables before writing the output,
+        /// so repeated subtrees become `_id`/`_ref` pairs instead of being
+        /// duplicated. Costs extra CPU; only worth it for formats (like
+        /// custom_xml) that can actually represent the sharing.
+        #[structopt(long)]
+        dedup: bool,
+
+        /// Resolve `formats::scriptdata::import`-style `include` tables
+        /// reachable from the root, splicing in the referenced file's
+        /// content before writing the output. Paths are resolved relative
+        /// to the directory of `input`, which must be a real file (not `-`).
+        #[structopt(long)]
+        resolve_imports: bool,
+
         /// File to read
         input: String,
         /// File to write
@@ -109,7 +279,48 @@ enum Command {
 
     /// Parse an OIL-format model file and print all recognised information.
     Oil {
-        input: String
+        input: String,
+
+        /// Instead of printing a human-readable dump, write a structured
+        /// JSON/YAML export of every chunk to this file (or "-" for stdout)
+        #[structopt(long)]
+        export: Option<String>,
+
+        /// Format to use for --export
+        #[structopt(long, default_value="json")]
+        format: DumpFormat,
+
+        /// Gzip-compress the --export output stream
+        #[structopt(long)]
+        gzip: bool,
+
+        /// Instead of printing a dump, check the FORM header fields and
+        /// report whether the file round-trips byte-identically through the
+        /// writer
+        #[structopt(long)]
+        verify: bool,
+
+        /// Instead of printing a dump, convert the scene to glTF 2.0 and
+        /// write it to this path, alongside a sibling `.bin` buffer file.
+        /// Only the loose `.gltf`+`.bin` form is supported, not a single
+        /// embedded `.glb`.
+        #[structopt(long)]
+        output: Option<String>
+    },
+
+    /// Open an interactive shell for inspecting and converting scriptdata documents
+    Shell {
+        /// Scriptdata document to load before the shell starts
+        input: Option<String>
+    },
+
+    /// Convert a Diesel model (.model/.fdm) to a glTF 2.0 scene, writing a
+    /// sibling .bin buffer file alongside it
+    ExportGltf {
+        /// Diesel model file to read
+        input: String,
+        /// .gltf file to write
+        output: String
     }
 }
 
@@ -119,14 +330,29 @@ fn main() {
     match opt.command {
         Command::Hash{ to_hash } => {
             for s in to_hash {
-                let h = diesel_hash::hash_str(&s);
-                println!("{0:>016x} {0:>20} {1:?}", h, s)
+                match hashindex::hash_by_name(&opt.algorithm, &s) {
+                    Some(h) => println!("{0:>016x} {0:>20} {1:?}", h, s),
+                    None => {
+                        println!("Unknown hash algorithm {:?}. Available: {:?}", opt.algorithm, hashindex::ALGORITHM_NAMES);
+                        break;
+                    }
+                }
             }
         },
         Command::Unhash{ to_unhash, decimal } => {
-            if let Some(hashlist) = get_hashlist(&opt.hashlist) {
-                let radix = if decimal { 10 } else { 16 };
-                do_unhash(hashlist, &to_unhash, radix)
+            let radix = if decimal { 10 } else { 16 };
+            match opt.algorithm.as_str() {
+                "diesel" => {
+                    if let Some(hashlist) = get_hashlist::<DieselHash>(&opt.hashlist) {
+                        do_unhash(hashlist, &to_unhash, radix)
+                    }
+                },
+                "murmur64" => {
+                    if let Some(hashlist) = get_hashlist::<Murmur64>(&opt.hashlist) {
+                        do_unhash(hashlist, &to_unhash, radix)
+                    }
+                },
+                other => println!("Unknown hash algorithm {:?}. Available: {:?}", other, hashindex::ALGORITHM_NAMES)
             }
         },
         Command::ReadPackages{ asset_dir } => {
@@ -142,20 +368,73 @@ fn main() {
             let db = get_packagedb(hashlist, &asset_dir).unwrap();
             filesystem::mount_cooked_database(mountpoint, db.hashes.clone(), Arc::new(db));
         },
-        Command::Scan{ asset_dir, output } => {
-            do_scan(&opt.hashlist, &asset_dir, &output)
+        #[cfg(feature="fuse")]
+        Command::MountFuse{ asset_dir, mountpoint } => {
+            use std::sync::Arc;
+
+            let hashlist = get_hashlist(&opt.hashlist).unwrap();
+            let db = get_packagedb(hashlist, &asset_dir).unwrap();
+            let hashes = db.hashes.clone();
+            let backing = Arc::new(filesystem::raw_bundledb::BundleFs::new(Arc::new(db)));
+            let transcoded = Arc::new(filesystem::transcoder::TranscoderFs::new(hashes, backing));
+            filesystem::fuse_adapter::mount_cooked_database_fuse(&mountpoint, transcoded)
+                .expect("failed to mount FUSE filesystem");
+        },
+        Command::Scan{ asset_dir, output, all_files, rules } => {
+            do_scan(&opt.hashlist, &asset_dir, &output, all_files, rules.as_deref())
+        },
+        Command::ExtractManifest{ asset_dir, out_dir, manifest, verify } => {
+            if verify {
+                do_verify_manifest(&out_dir, &manifest)
+            }
+            else {
+                do_extract_manifest(&opt.hashlist, &asset_dir, &out_dir, &manifest)
+            }
         },
-        Command::Convert{ input, output, input_format, output_format, events } => {
-            do_convert(&input, input_format, &output, output_format, events)
+        Command::Stats{ asset_dir, hash, top, json } => {
+            do_stats(&opt.hashlist, &asset_dir, hash, top, json.as_deref())
+        },
+        Command::Crack{ asset_dir, wordlist, output, prefix, extension, max_suffix, combine_depth } => {
+            do_crack(&opt.hashlist, &asset_dir, &wordlist, &output, &prefix, &extension, max_suffix, combine_depth)
+        },
+        #[cfg(any(feature="dokan", feature="fuse"))]
+        Command::List{ asset_dir, short, hash, no_color, verify, save_manifest } => {
+            do_list(&opt.hashlist, &asset_dir, short, hash || verify.is_some() || save_manifest.is_some(), !no_color, verify.as_deref(), save_manifest.as_deref())
+        },
+        #[cfg(any(feature="dokan", feature="fuse"))]
+        Command::Verify{ asset_dir, expected, quiet } => {
+            do_verify(&opt.hashlist, &asset_dir, expected.as_deref(), quiet)
+        },
+        #[cfg(any(feature="dokan", feature="fuse"))]
+        Command::Extract{ asset_dir, dest, path, include, exclude } => {
+            do_extract(&opt.hashlist, &asset_dir, &dest, &path, include.as_deref(), exclude.as_deref())
+        },
+        Command::Convert{ input, output, input_format, output_format, events, dedup, resolve_imports } => {
+            do_convert(&input, input_format, &output, output_format, events, dedup, resolve_imports)
         }
-        Command::Oil{ input } => {
+        Command::Oil{ input, export, format, gzip, verify, output } => {
             let path: std::path::PathBuf = input.into();
-            formats::oil::print_sections(&path);
+            match export {
+                Some(dump_path) => do_oil_export(&path, &dump_path, format, gzip),
+                None if verify => formats::oil::verify(&path),
+                None => match output {
+                    Some(gltf_path) => do_oil_export_gltf(&path, &gltf_path),
+                    None => formats::oil::print_sections(&path)
+                }
+            }
+        },
+        Command::Shell{ input } => {
+            if let Err(e) = formats::scriptdata::shell::run(input.as_deref()) {
+                println!("Shell exited with an error: {}", e)
+            }
+        },
+        Command::ExportGltf{ input, output } => {
+            do_export_gltf(&input, &output)
         }
     };
 }
 
-fn get_hashlist(hashlist_filename: &Option<String>) -> Option<HashIndex> {
+fn get_hashlist<H: HashAlgorithm>(hashlist_filename: &[String]) -> Option<HashIndex<H>> {
     match try_get_hashlist(hashlist_filename) {
         Ok(hi) => Some(hi),
         Err(e) => {
@@ -168,10 +447,13 @@ fn get_hashlist(hashlist_filename: &Option<String>) -> Option<HashIndex> {
     }
 }
 
-fn try_get_hashlist(filename_arg: &Option<String>) -> Result<HashIndex, std::io::Error> {
-    if let Some(hf) = filename_arg {
-        let hp = PathBuf::from(hf);
-        return try_load_hashlist(&hp);
+fn try_get_hashlist<H: HashAlgorithm>(filenames: &[String]) -> Result<HashIndex<H>, std::io::Error> {
+    if !filenames.is_empty() {
+        let mut hi = HashIndex::new();
+        for hf in filenames {
+            hi.load_file(&PathBuf::from(hf))?;
+        }
+        return Ok(hi);
     }
     else {
         let cwd_filename = std::env::current_dir().map(|f| {
@@ -199,23 +481,34 @@ fn try_get_hashlist(filename_arg: &Option<String>) -> Result<HashIndex, std::io:
     }
 }
 
-fn try_load_hashlist(filename: &Path) -> Result<HashIndex, std::io::Error> {
-    fs::read_to_string(filename).map(|c| {
-        let mut hi = HashIndex::new();
-        hi.load_blob(c);
-        hi
-    })
+fn try_load_hashlist<H: HashAlgorithm>(filename: &Path) -> Result<HashIndex<H>, std::io::Error> {
+    let mut hi = HashIndex::new();
+    hi.load_file(filename).map(|()| hi)
 }
 
 fn get_packagedb<'a>(hashlist: hashindex::HashIndex, asset_dir: &str) -> Result<bundles::database::Database, bundles::ReadError> {
+    use std::sync::Arc;
+
     let path = std::path::PathBuf::from(asset_dir);
-    let coll = bundles::loader::load_bundle_dir(&path)?;
+    let coll = bundles::loader::load_bundle_dir(&path, true)?;
 
     println!("Packages: {}", coll.1.len());
     println!("BDB Entries: {}", coll.0.files.len());
     println!();
 
-    Ok(bundles::database::from_bdb( hashlist, &coll.0, &coll.1))
+    let cache_path = path.join("bundle_database.cache");
+    let hashes = Arc::new(hashlist);
+    if let Ok(db) = bundles::database::load_cache(&cache_path, hashes.clone(), &coll.1) {
+        println!("Loaded package database from cache");
+        return Ok(db);
+    }
+
+    let hashlist = Arc::try_unwrap(hashes).unwrap_or_else(|_| panic!("package database cache outlived its own load attempt"));
+    let db = bundles::database::from_bdb(hashlist, &coll.0, &coll.1);
+    if let Err(e) = db.write_cache(&cache_path) {
+        println!("Couldn't write package database cache: {}", e);
+    }
+    Ok(db)
 }
 
 fn do_hash(texts: Vec<&str>) {
@@ -224,7 +517,7 @@ fn do_hash(texts: Vec<&str>) {
     }
 }
 
-fn do_unhash(hashlist: hashindex::HashIndex, texts: &Vec<String>, radix: u32) {
+fn do_unhash<H: HashAlgorithm>(hashlist: hashindex::HashIndex<H>, texts: &Vec<String>, radix: u32) {
     for s in texts {
         match diesel_hash::parse_flexibly(s, radix) {
             Ok(i) => {
@@ -251,23 +544,330 @@ fn do_readpkg(hashlist: hashindex::HashIndex, asset_dir: &str) {
     }
 }
 
-fn do_scan(hashlist_filename: &Option<String>, asset_dir: &str, outname: &str) {
+fn do_scan(hashlist_filename: &[String], asset_dir: &str, outname: &str, all_files: bool, rules_filename: Option<&str>) {
+    let hashlist = get_hashlist(hashlist_filename).unwrap();
+    let db = get_packagedb(hashlist, asset_dir).unwrap();
+    let mut outfile = std::fs::OpenOptions::new().create(true).write(true).open(outname).unwrap();
+    let extra_rules = match rules_filename {
+        Some(f) => hashlist_scan::load_rules(Path::new(f)).unwrap(),
+        None => Vec::new()
+    };
+    hashlist_scan::do_scan(&db, &mut outfile, all_files, &extra_rules).unwrap();
+}
+
+#[cfg(any(feature="dokan", feature="fuse"))]
+fn do_list(hashlist_filename: &[String], asset_dir: &str, short: bool, with_hash: bool, color: bool, verify_manifest: Option<&str>, save_manifest: Option<&str>) {
+    use std::sync::Arc;
+    use filesystem::listing;
+
+    let hashlist = get_hashlist(hashlist_filename).unwrap();
+    let db = get_packagedb(hashlist, asset_dir).unwrap();
+    let hashes = db.hashes.clone();
+    let backing = Arc::new(filesystem::raw_bundledb::BundleFs::new(Arc::new(db)));
+    let transcoded = Arc::new(filesystem::transcoder::TranscoderFs::new(hashes, backing));
+
+    let entries = listing::walk(transcoded.as_ref(), "", with_hash).unwrap();
+
+    if let Some(manifest_path) = verify_manifest {
+        let manifest = listing::load_manifest(Path::new(manifest_path)).unwrap();
+        for diff in listing::verify(&entries, &manifest) {
+            match diff {
+                listing::VerifyDiff::Added(p) => println!("+ {}", p),
+                listing::VerifyDiff::Removed(p) => println!("- {}", p),
+                listing::VerifyDiff::Changed(p) => println!("M {}", p),
+            }
+        }
+        return;
+    }
+
+    if let Some(manifest_path) = save_manifest {
+        listing::save_manifest(&entries, Path::new(manifest_path)).unwrap();
+    }
+
+    if short { listing::print_short(&entries, color); }
+    else { listing::print_long(&entries, color); }
+}
+
+/// Hashes every file in `asset_dir`'s package database and, if `expected`
+/// names a manifest saved by `list --save-manifest`, diffs against it -
+/// built on the same [`filesystem::listing`] walk/verify logic `list
+/// --verify` already uses, just packaged as a standalone command with a
+/// CI-friendly summary and exit code rather than a listing-mode flag.
+/// With `quiet`, per-file diffs are suppressed and only the final
+/// pass/fail line is printed.
+#[cfg(any(feature="dokan", feature="fuse"))]
+fn do_verify(hashlist_filename: &[String], asset_dir: &str, expected: Option<&str>, quiet: bool) {
+    use std::sync::Arc;
+    use filesystem::listing;
+
+    let hashlist = get_hashlist(hashlist_filename).unwrap();
+    let db = get_packagedb(hashlist, asset_dir).unwrap();
+    let hashes = db.hashes.clone();
+    let backing = Arc::new(filesystem::raw_bundledb::BundleFs::new(Arc::new(db)));
+    let transcoded = Arc::new(filesystem::transcoder::TranscoderFs::new(hashes, backing));
+
+    let entries = listing::walk(transcoded.as_ref(), "", true).unwrap();
+
+    let diffs = match expected {
+        Some(manifest_path) => {
+            let manifest = listing::load_manifest(Path::new(manifest_path)).unwrap();
+            listing::verify(&entries, &manifest)
+        },
+        None => Vec::new()
+    };
+
+    if !quiet {
+        for diff in &diffs {
+            match diff {
+                listing::VerifyDiff::Added(p) => println!("+ {}", p),
+                listing::VerifyDiff::Removed(p) => println!("- {}", p),
+                listing::VerifyDiff::Changed(p) => println!("M {}", p),
+            }
+        }
+    }
+
+    if diffs.is_empty() {
+        println!("PASS: {} files verified", entries.iter().filter(|e| !e.is_dir).count());
+    }
+    else {
+        println!("FAIL: {} differences found", diffs.len());
+        std::process::exit(1);
+    }
+}
+
+#[cfg(any(feature="dokan", feature="fuse"))]
+fn do_extract(hashlist_filename: &[String], asset_dir: &str, dest: &str, path: &str, include: Option<&str>, exclude: Option<&str>) {
+    use std::sync::Arc;
+    use filesystem::extract::{self, ExtractFilter, ExtractOutcome};
+
+    let hashlist = get_hashlist(hashlist_filename).unwrap();
+    let db = get_packagedb(hashlist, asset_dir).unwrap();
+    let hashes = db.hashes.clone();
+    let backing = Arc::new(filesystem::raw_bundledb::BundleFs::new(Arc::new(db)));
+    let transcoded = Arc::new(filesystem::transcoder::TranscoderFs::new(hashes, backing));
+
+    let mut filter = ExtractFilter::new();
+    if let Some(p) = include { filter = filter.with_include(p); }
+    if let Some(p) = exclude { filter = filter.with_exclude(p); }
+
+    let results = extract::extract_tree(transcoded.as_ref(), path, Path::new(dest), &filter);
+
+    let mut extracted = 0;
+    let mut skipped = 0;
+    let mut failed = 0;
+    for (path, outcome) in &results {
+        match outcome {
+            ExtractOutcome::Extracted => extracted += 1,
+            ExtractOutcome::Skipped => skipped += 1,
+            ExtractOutcome::Failed(e) => {
+                failed += 1;
+                println!("FAILED {}: {:?}", path, e);
+            }
+        }
+    }
+    println!("Extracted {} files, skipped {}, {} failed", extracted, skipped, failed);
+}
+
+/// Walks every file in `asset_dir`'s package database through
+/// [`bundles::extract::extract_parallel`], writing each one under `out_dir`
+/// (recreating its virtual path's directory structure) and collecting a
+/// [`bundles::manifest::ManifestEntry`] per file so [`bundles::manifest::write_manifest`]
+/// can record the whole extraction as a verifiable mtree manifest once it's done.
+fn do_extract_manifest(hashlist_filename: &[String], asset_dir: &str, out_dir: &str, manifest_path: &str) {
+    use std::sync::Mutex;
+    use bundles::extract::{extract_parallel, key_to_path};
+
     let hashlist = get_hashlist(hashlist_filename).unwrap();
     let db = get_packagedb(hashlist, asset_dir).unwrap();
+    let plan = db.filter_key_sort_physical(|_| true);
+
+    let out_root = Path::new(out_dir);
+    let entries = Mutex::new(Vec::new());
+    let failed = Mutex::new(0usize);
+
+    extract_parallel(plan, 0, |key, bytes| {
+        let path = key_to_path(&key);
+        let dest = out_root.join(&path);
+
+        let write_result = dest.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&dest, &bytes));
+
+        match write_result {
+            Ok(()) => entries.lock().unwrap().push(bundles::manifest::ManifestEntry::new(path, &bytes, key.path.hash)),
+            Err(e) => {
+                println!("FAILED {}: {}", path, e);
+                *failed.lock().unwrap() += 1;
+            }
+        }
+    }).unwrap();
+
+    let entries = entries.into_inner().unwrap();
+    let failed = failed.into_inner().unwrap();
+
+    bundles::manifest::write_manifest(Path::new(manifest_path), &entries).unwrap();
+    println!("Extracted {} files, {} failed. Manifest written to {:?}", entries.len(), failed, manifest_path);
+}
+
+/// Re-reads `manifest_path` and checks every entry it names is present under
+/// `out_dir` with the recorded size and SHA-256 digest, reporting each
+/// discrepancy it finds.
+fn do_verify_manifest(out_dir: &str, manifest_path: &str) {
+    let entries = bundles::manifest::read_manifest(Path::new(manifest_path)).unwrap();
+    let diffs = bundles::manifest::verify(&entries, Path::new(out_dir));
+
+    for diff in &diffs {
+        match diff {
+            bundles::manifest::VerifyDiff::Missing(p) => println!("MISSING {}", p),
+            bundles::manifest::VerifyDiff::SizeMismatch(p) => println!("SIZE MISMATCH {}", p),
+            bundles::manifest::VerifyDiff::DigestMismatch(p) => println!("DIGEST MISMATCH {}", p),
+        }
+    }
+    println!("Checked {} files, {} problems", entries.len(), diffs.len());
+}
+
+fn do_stats(hashlist_filename: &[String], asset_dir: &str, with_hash: bool, top_n: usize, json_path: Option<&str>) {
+    use bundles::stats;
+
+    let hashlist = get_hashlist(hashlist_filename).unwrap();
+    let db = get_packagedb(hashlist, asset_dir).unwrap();
+
+    let report = stats::collect(&db, with_hash, top_n).unwrap();
+    stats::print_table(&report);
+
+    if let Some(path) = json_path {
+        let json = stats::to_json(&report).unwrap();
+        fs::write(path, json).unwrap();
+    }
+}
+
+fn do_crack(hashlist_filename: &[String], asset_dir: &str, wordlist_filename: &str, outname: &str, prefixes: &[String], extensions: &[String], max_suffix: u32, combine_depth: usize) {
+    let hashlist = get_hashlist(hashlist_filename).unwrap();
+    let db = get_packagedb(hashlist, asset_dir).unwrap();
+    let words = hashlist_scan::load_wordlist(Path::new(wordlist_filename)).unwrap();
+
+    let prefixes: Vec<&str> = prefixes.iter().map(String::as_str).collect();
+    let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+    let rules = hashlist_scan::PermuteRules { prefixes: &prefixes, extensions: &extensions, max_suffix };
+
+    let mut found = hashlist_scan::crack_wordlist(&db, &words, &rules);
+
+    if combine_depth > 0 {
+        let targets = hashlist_scan::unresolved_hashes(&db);
+        let combinator_rules = hashlist_scan::CombinatorRules {
+            tokens: &words,
+            separators: &["/", ".", "_", ""],
+            max_depth: combine_depth,
+            prefix: "",
+            suffix: ""
+        };
+        let combined = hashlist_scan::crack_combinations(&combinator_rules, &targets);
+        found.extend(combined);
+    }
+
+    println!("Cracked {} hashes", found.len());
+
     let mut outfile = std::fs::OpenOptions::new().create(true).write(true).open(outname).unwrap();
-    hashlist_scan::do_scan(&db, &mut outfile).unwrap();
+    let mut ordered: Vec<&Rc<str>> = found.values().collect();
+    ordered.sort();
+    for s in ordered {
+        writeln!(outfile, "{}", s).unwrap();
+    }
 }
 
 fn do_print_scriptdata(filename: &str) {
     let sd = std::fs::read(filename).unwrap();
-    let doc = formats::scriptdata::binary::from_binary(&sd, false);
+    let doc = formats::scriptdata::binary::from_binary(&sd, false, None, None);
     let gx = formats::scriptdata::generic_xml::dump(&doc.unwrap());
     println!("{}", gx);
     //formats::scriptdata::lua_like::dump(&doc, &mut std::io::stdout()).unwrap();
     //println!("{:?}", doc.root())
 }
 
-fn do_convert(input_filename: &str, input_type: Option<ConvertType>, output_filename: &str, output_type: ConvertType, events: bool) {
+fn do_oil_export(input_path: &Path, output_filename: &str, format: DumpFormat, gzip: bool) {
+    let bytes = std::fs::read(input_path).unwrap();
+    let chunks = formats::oil::parse_chunks(&bytes)
+        .with_context(|| format!("Decoding {:?}", input_path))
+        .unwrap();
+
+    let text = match format {
+        DumpFormat::Json => formats::oil::chunks_to_json(&chunks).unwrap(),
+        DumpFormat::Yaml => formats::oil::chunks_to_yaml(&chunks).unwrap()
+    };
+
+    let output: Vec<u8> = if gzip {
+        use flate2::{write::GzEncoder, Compression};
+        let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(text.as_bytes()).unwrap();
+        enc.finish().unwrap()
+    } else {
+        text.into_bytes()
+    };
+
+    match output_filename {
+        "-" => std::io::stdout().write_all(&output).unwrap(),
+        name => std::fs::write(name, &output).unwrap()
+    };
+}
+
+fn do_oil_export_gltf(input_path: &Path, output_filename: &str) {
+    let bytes = std::fs::read(input_path).unwrap();
+    let chunks = formats::oil::parse_chunks(&bytes)
+        .with_context(|| format!("Decoding {:?}", input_path))
+        .unwrap();
+    formats::oil::export_gltf::write_to_files(&chunks, Path::new(output_filename)).unwrap();
+}
+
+fn do_export_gltf(input_filename: &str, output_filename: &str) {
+    let bytes = std::fs::read(input_filename).unwrap();
+    let container = formats::fdm::parse_stream(&mut bytes.as_slice()).unwrap();
+    formats::fdm::export_gltf::write_to_files(&container, Path::new(output_filename)).unwrap();
+}
+
+/// Guesses which [`ConvertType`] `data` is in when `--input-format` wasn't
+/// given, so `convert` can be pointed at a file of unknown provenance. Binary
+/// scriptdata is the only format with anything like a fixed header, so it's
+/// checked first and everything else falls back to shallow textual sniffing:
+/// an XML document is `generic_xml` if its root element is
+/// `generic_scriptdata` (the only fixed tag name that format ever writes) and
+/// `custom_xml` otherwise, a document beginning with `return` is `lua_like`
+/// (the only one of these dumps that emits that keyword at all), and one
+/// beginning with `{` or `[` is `json`.
+fn sniff_input_format(data: &[u8]) -> anyhow::Result<ConvertType> {
+    if u32::try_from_le(data, 0).map(|m| m == 568494624).unwrap_or(false) {
+        return Ok(ConvertType::Binary);
+    }
+
+    let text = match std::str::from_utf8(data) {
+        Ok(t) => t.trim_start(),
+        Err(_) => return Ok(ConvertType::Binary)
+    };
+
+    if text.starts_with('<') {
+        if let Ok(tree) = roxmltree::Document::parse(text) {
+            return Ok(match tree.root_element().tag_name().name() {
+                "generic_scriptdata" => ConvertType::Generic,
+                _ => ConvertType::Custom
+            });
+        }
+    }
+
+    if text.starts_with("return") {
+        return Ok(ConvertType::Lua);
+    }
+
+    if text.starts_with('{') || text.starts_with('[') {
+        return Ok(ConvertType::Json);
+    }
+
+    bail!("Couldn't detect this input's scriptdata format; pass --input-format explicitly")
+}
+
+/// How many `include` tables deep [`do_convert`]'s `--resolve-imports` will
+/// follow before giving up on a cycle it didn't otherwise detect.
+const MAX_IMPORT_DEPTH: usize = 32;
+
+fn do_convert(input_filename: &str, input_type: Option<ConvertType>, output_filename: &str, output_type: ConvertType, events: bool, dedup: bool, resolve_imports: bool) {
     let in_data: Vec<u8> = match input_filename {
         "-" => {
             let mut id = Vec::<u8>::new();
@@ -277,39 +877,74 @@ fn do_convert(input_filename: &str, input_type: Option<ConvertType>, output_file
         name => std::fs::read(name).unwrap()
     };
 
+    let input_type = input_type.map_or_else(|| {
+        sniff_input_format(&in_data).with_context(|| {
+            format!("Detecting format of \"{}\"", input_filename)
+        }).unwrap()
+    }, |t| t);
+
     if events {
         let in_text = std::str::from_utf8(&in_data).unwrap();
         let in_tree = roxmltree::Document::parse(&in_text).unwrap();
         let events = match input_type {
-            Some(ConvertType::Custom) => formats::scriptdata::custom_xml::load_events(&in_tree),
-            Some(ConvertType::Generic) => formats::scriptdata::generic_xml::load_events(&in_tree),
+            ConvertType::Custom => formats::scriptdata::custom_xml::load_events(&in_tree),
+            ConvertType::Generic => formats::scriptdata::generic_xml::load_events(&in_tree),
             _ => unimplemented!("Not a format supporting events")
         };
-        let ok_events: Vec<_> = events.iter().filter_map(|i| i.ok()).collect();
-        let err_events: Vec<_> = events.iter().filter_map(|i| i.err()).collect();
-        println!("{:?}", events);
-        //println!("{:?}", err_events);
+        for event in &events {
+            match event {
+                Ok(ev) => println!("{:?}", ev),
+                Err(e) => println!("{}\n", e.render(in_text))
+            }
+        }
     }
 
-    let input_func = match input_type {
-        Some(ConvertType::Binary) => formats::scriptdata::binary::load,
-        Some(ConvertType::Custom) => formats::scriptdata::custom_xml::load,
-        _ => unimplemented!("Only custom and binary are currently implemented")
+    let doc_result = match input_type {
+        ConvertType::Binary => formats::scriptdata::binary::load(&in_data),
+        ConvertType::Custom => formats::scriptdata::custom_xml::load(std::str::from_utf8(&in_data).unwrap()),
+        ConvertType::Generic => formats::scriptdata::generic_xml::load(std::str::from_utf8(&in_data).unwrap()),
+        ConvertType::Lua => formats::scriptdata::lua_like::load(std::str::from_utf8(&in_data).unwrap()),
+        ConvertType::Json => formats::scriptdata::json::load(std::str::from_utf8(&in_data).unwrap())
     };
 
-    let doc = input_func(&in_data).with_context(||{
-        format!("Decoding \"{}\" as {:?}", input_filename, input_type)
-    }).unwrap();
+    let mut doc = match doc_result {
+        Ok(doc) => doc,
+        Err(e) => {
+            eprintln!("Decoding \"{}\" as {:?} failed:\n\n{:#}", input_filename, input_type, e);
+            std::process::exit(1);
+        }
+    };
 
-    
+    if resolve_imports {
+        if input_filename == "-" {
+            eprintln!("--resolve-imports needs a real input file to resolve relative paths against, not stdin");
+            std::process::exit(1);
+        }
+        let base_dir = Path::new(input_filename).parent().unwrap_or_else(|| Path::new("."));
+        let mut visiting = std::collections::VecDeque::new();
+        if let Err(e) = formats::scriptdata::import::resolve_imports(&mut doc, base_dir, MAX_IMPORT_DEPTH, &mut visiting) {
+            eprintln!("Resolving imports in \"{}\" failed:\n\n{:#}", input_filename, e);
+            std::process::exit(1);
+        }
+    }
+
+    if dedup {
+        doc.dedup_tables();
+    }
 
-    let output_func = match output_type {
-        ConvertType::Lua => formats::scriptdata::lua_like::dump,
-        ConvertType::Generic => formats::scriptdata::generic_xml::dump,
-        ConvertType::Custom => formats::scriptdata::custom_xml::dump,
-        ConvertType::Binary => unimplemented!()
+    let output: Vec<u8> = match output_type {
+        ConvertType::Lua => formats::scriptdata::lua_like::dump(&doc).into_bytes(),
+        ConvertType::Generic => formats::scriptdata::generic_xml::dump(&doc).into_bytes(),
+        ConvertType::Custom => formats::scriptdata::custom_xml::dump(&doc).into_bytes(),
+        ConvertType::Json => formats::scriptdata::json::dump(&doc).into_bytes(),
+        ConvertType::Binary => match formats::scriptdata::binary::to_binary(&doc, formats::scriptdata::binary::Variant::X86) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("Encoding \"{}\" as binary failed:\n\n{:#}", output_filename, e);
+                std::process::exit(1);
+            }
+        }
     };
-    let output = output_func(&doc).into_bytes();
 
     match output_filename {
         "-" => {
@@ -318,6 +953,7 @@ fn do_convert(input_filename: &str, input_type: Option<ConvertType>, output_file
         name => {
             std::fs::OpenOptions::new()
                 .write(true)
+                .create(true)
                 .truncate(true)
                 .open(name)
                 .unwrap()
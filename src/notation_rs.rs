@@ -1,142 +1,577 @@
 //! Common data notation for save files and scriptdata
-//! 
+//!
 //! Saves don't have a textual repr at all, and the vanilla ones for scriptdata suck for hand-viewing.
 //! So this is an alternative.
-//! 
-//! Currently there's no way to represent NaN or Inf. 
+//!
+//! Floats round-trip exactly, including NaN, the infinities, and signed zero: non-finite values
+//! are written as the bare tokens `#nan`, `#inf`, `#-inf`, and finite ones go through `FloatLit`.
+//!
+//! Parsing is hand-rolled rather than piggybacking on Rust's own token grammar (which used to be
+//! done via `syn`), since real scriptdata keys and reference ids are arbitrary strings - they can
+//! start with a digit, contain dashes and dots, and generally don't look like Rust identifiers.
 
 use std::fmt::Write;
 
-use proc_macro2::Span;
-use syn::{Ident, LitInt, LitFloat, LitStr, LitByteStr, Lifetime, punctuated::Punctuated, Result as SyResult, token, Token};
-use syn::ext::IdentExt;
-use syn::parse::{Parse, ParseStream};
-use syn::parse::discouraged::AnyDelimiter;
-use proc_macro2::Delimiter;
+/// A byte range into the source text a node was parsed from. Nodes built with the `new_*`
+/// constructors instead of parsing get the synthetic `0..0` span.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at byte {}..{})", self.message, self.span.start, self.span.end)
+    }
+}
+impl std::fmt::Debug for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+impl std::error::Error for ParseError {}
+
+pub type ParseResult<T> = Result<T, ParseError>;
+
+pub struct IntLit { pub value: i64, pub span: Span }
+impl std::fmt::Display for IntLit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+/// An `f32` that prints and parses back to the identical bit pattern - this also covers NaN,
+/// the infinities, and signed zero, none of which a plain decimal literal can represent.
+pub struct FloatLit { pub value: f32, pub span: Span }
+impl std::fmt::Display for FloatLit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.value.is_nan() {
+            write!(f, "#nan")
+        }
+        else if self.value == f32::INFINITY {
+            write!(f, "#inf")
+        }
+        else if self.value == f32::NEG_INFINITY {
+            write!(f, "#-inf")
+        }
+        else {
+            // `f32`'s `Display` impl already produces the shortest decimal string that parses
+            // back to the same bits, including `-0`, so there's no extra work to do here.
+            write!(f, "{}f32", self.value)
+        }
+    }
+}
+
+pub struct StrLit { pub value: String, pub span: Span }
+impl std::fmt::Display for StrLit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_char('"')?;
+        for c in self.value.chars() {
+            match c {
+                '\\' => f.write_str("\\\\")?,
+                '"' => f.write_str("\\\"")?,
+                '\n' => f.write_str("\\n")?,
+                '\t' => f.write_str("\\t")?,
+                '\r' => f.write_str("\\r")?,
+                '\0' => f.write_str("\\0")?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+pub struct BytesLit { pub value: Vec<u8>, pub span: Span }
+impl std::fmt::Display for BytesLit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("b\"")?;
+        for &b in &self.value {
+            match b {
+                b'\\' => f.write_str("\\\\")?,
+                b'"' => f.write_str("\\\"")?,
+                b'\n' => f.write_str("\\n")?,
+                b'\t' => f.write_str("\\t")?,
+                b'\r' => f.write_str("\\r")?,
+                0 => f.write_str("\\0")?,
+                0x20..=0x7e => f.write_char(b as char)?,
+                other => write!(f, "\\x{:02x}", other)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+pub struct BareName { pub name: String, pub span: Span }
+impl std::fmt::Display for BareName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.name)
+    }
+}
+
+pub struct RefId { pub id: String, pub span: Span }
+impl std::fmt::Display for RefId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}", self.id)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delimiter { Paren, Brace, Bracket }
 
 pub enum Item {
-    Integer(LitInt),
-    Float(LitFloat),
-    String(LitStr),
-    Binary(LitByteStr),
-    Bare(Ident),
-    Reference(Lifetime),
+    Integer(IntLit),
+    Float(FloatLit),
+    String(StrLit),
+    Binary(BytesLit),
+    Bare(BareName),
+    Reference(RefId),
     Compound(Compound),
 }
 
 pub struct Compound {
-    pub ref_id: Option<Lifetime>,
-    pub tag: Option<Ident>,
-    //pub delim_span: DelimSpan,
+    pub ref_id: Option<RefId>,
+    pub tag: Option<String>,
     pub delimiter: Delimiter,
-    pub body: Punctuated<CompoundEntry, token::Comma>,
+    pub body: Vec<CompoundEntry>,
+    pub span: Span,
 }
 
 pub enum CompoundEntry {
     Named(Item, Item),
-    BareNamed(Ident, Item),
+    BareNamed(String, Item),
     Indexed(Item)
 }
 
-impl Parse for Item {
-    fn parse(input: ParseStream) -> SyResult<Self> {
-        let lookahead = input.lookahead1();
-        if lookahead.peek(LitInt) {
-            input.parse().map(Item::Integer)
-        }
-        else if lookahead.peek(LitFloat) {
-            input.parse().map(Item::Float)
+// ---- Lexer -----------------------------------------------------------------------------------
+
+#[derive(Clone, Debug)]
+enum TokKind {
+    Word(String),
+    Str(String),
+    Bytes(Vec<u8>),
+    Bare(String),
+    Ref(String),
+    Colon,
+    Comma,
+    LParen, RParen,
+    LBrace, RBrace,
+    LBracket, RBracket,
+    Eof,
+}
+
+#[derive(Clone, Debug)]
+struct Token { kind: TokKind, span: Span }
+
+fn is_reserved(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | '[' | ']' | '{' | '}' | ':' | ',' | '"' | '\'' | '#')
+}
+
+struct Lexer<'a> {
+    text: &'a str,
+    pos: usize,
+}
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Lexer<'a> {
+        Lexer { text, pos: 0 }
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek_char()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            match self.peek_char() {
+                Some(c) if c.is_whitespace() => { self.bump(); },
+                Some('/') if self.text[self.pos..].starts_with("//") => {
+                    while let Some(c) = self.peek_char() {
+                        if c == '\n' { break; }
+                        self.bump();
+                    }
+                },
+                _ => break
+            }
         }
-        else if lookahead.peek(LitStr) {
-            input.parse().map(Item::String)
+    }
+
+    fn next_token(&mut self) -> ParseResult<Token> {
+        self.skip_trivia();
+        let start = self.pos;
+        let c = match self.peek_char() {
+            None => return Ok(Token { kind: TokKind::Eof, span: Span { start, end: start } }),
+            Some(c) => c
+        };
+
+        let kind = match c {
+            '(' => { self.bump(); TokKind::LParen },
+            ')' => { self.bump(); TokKind::RParen },
+            '[' => { self.bump(); TokKind::LBracket },
+            ']' => { self.bump(); TokKind::RBracket },
+            '{' => { self.bump(); TokKind::LBrace },
+            '}' => { self.bump(); TokKind::RBrace },
+            ':' => { self.bump(); TokKind::Colon },
+            ',' => { self.bump(); TokKind::Comma },
+            '"' => self.lex_string()?,
+            '\'' => self.lex_ref()?,
+            '#' => self.lex_bare()?,
+            'b' if self.text[self.pos..].starts_with("b\"") => { self.bump(); self.lex_bytes()? },
+            _ => self.lex_word()?,
+        };
+
+        Ok(Token { kind, span: Span { start, end: self.pos } })
+    }
+
+    /// A "word" is anything except whitespace and the structural characters above - this
+    /// deliberately allows dashes, dots, and leading digits, since real scriptdata keys and
+    /// reference ids use all of those. Whether a given word ends up as a key, a tag, or a
+    /// number is decided positionally by the parser, not by its shape.
+    fn lex_word(&mut self) -> ParseResult<TokKind> {
+        let start = self.pos;
+        while let Some(c) = self.peek_char() {
+            if is_reserved(c) { break; }
+            self.bump();
         }
-        else if lookahead.peek(LitByteStr) {
-            input.parse().map(Item::Binary)
+        Ok(TokKind::Word(self.text[start..self.pos].to_owned()))
+    }
+
+    /// Escapes recognized inside `"..."` and `b"..."`: `\\`, `\"`, `\n`, `\t`, `\r`, `\0`, and
+    /// (byte strings only) `\xNN` for an arbitrary byte.
+    fn lex_string(&mut self) -> ParseResult<TokKind> {
+        let start = self.pos;
+        self.bump();
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None => return Err(ParseError { message: "unterminated string".into(), span: Span { start, end: self.pos } }),
+                Some('"') => break,
+                Some('\\') => s.push(self.lex_escape(start)?),
+                Some(c) => s.push(c),
+            }
         }
-        else if lookahead.peek(Token![#]) {
-            let _: Token![#] = input.parse()?;
-            input.parse().map(Item::Bare)
+        Ok(TokKind::Str(s))
+    }
+
+    fn lex_escape(&mut self, lit_start: usize) -> ParseResult<char> {
+        match self.bump() {
+            Some('\\') => Ok('\\'),
+            Some('"') => Ok('"'),
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('r') => Ok('\r'),
+            Some('0') => Ok('\0'),
+            Some(other) => Err(ParseError { message: format!("unknown escape '\\{}'", other), span: Span { start: lit_start, end: self.pos } }),
+            None => Err(ParseError { message: "unterminated escape".into(), span: Span { start: lit_start, end: self.pos } })
         }
-        else if lookahead.peek(Ident::peek_any) {
-            input.parse().map(Item::Compound)
+    }
+
+    fn lex_bytes(&mut self) -> ParseResult<TokKind> {
+        let start = self.pos;
+        self.bump();
+        let mut bytes = Vec::new();
+        loop {
+            match self.bump() {
+                None => return Err(ParseError { message: "unterminated byte string".into(), span: Span { start, end: self.pos } }),
+                Some('"') => break,
+                Some('\\') => {
+                    match self.bump() {
+                        Some('x') => {
+                            let hi = self.bump();
+                            let lo = self.bump();
+                            let (hi, lo) = match (hi, lo) {
+                                (Some(hi), Some(lo)) => (hi, lo),
+                                _ => return Err(ParseError { message: "truncated \\x escape".into(), span: Span { start, end: self.pos } })
+                            };
+                            let byte = u8::from_str_radix(&format!("{hi}{lo}"), 16)
+                                .map_err(|_| ParseError { message: "invalid \\x escape".into(), span: Span { start, end: self.pos } })?;
+                            bytes.push(byte);
+                        },
+                        Some('\\') => bytes.push(b'\\'),
+                        Some('"') => bytes.push(b'"'),
+                        Some('n') => bytes.push(b'\n'),
+                        Some('t') => bytes.push(b'\t'),
+                        Some('r') => bytes.push(b'\r'),
+                        Some('0') => bytes.push(0u8),
+                        Some(other) => return Err(ParseError { message: format!("unknown escape '\\{}'", other), span: Span { start, end: self.pos } }),
+                        None => return Err(ParseError { message: "unterminated escape".into(), span: Span { start, end: self.pos } })
+                    }
+                },
+                Some(c) if c.is_ascii() => bytes.push(c as u8),
+                Some(c) => return Err(ParseError { message: format!("non-ASCII byte '{}' in byte string", c), span: Span { start, end: self.pos } }),
+            }
         }
-        else if lookahead.peek(token::Brace) {
-            input.parse().map(Item::Compound)
+        Ok(TokKind::Bytes(bytes))
+    }
+
+    fn lex_ref(&mut self) -> ParseResult<TokKind> {
+        let quote_start = self.pos;
+        self.bump();
+        let start = self.pos;
+        while let Some(c) = self.peek_char() {
+            if is_reserved(c) { break; }
+            self.bump();
         }
-        else if lookahead.peek(token::Bracket) {
-            input.parse().map(Item::Compound)
+        if self.pos == start {
+            return Err(ParseError { message: "expected a reference id after '\''".into(), span: Span { start: quote_start, end: self.pos } });
         }
-        else if lookahead.peek(token::Paren) {
-            input.parse().map(Item::Compound)
+        Ok(TokKind::Ref(self.text[start..self.pos].to_owned()))
+    }
+
+    fn lex_bare(&mut self) -> ParseResult<TokKind> {
+        let hash_start = self.pos;
+        self.bump();
+        let start = self.pos;
+        while let Some(c) = self.peek_char() {
+            if is_reserved(c) { break; }
+            self.bump();
         }
-        else if lookahead.peek(Lifetime) {
-            // This could be a reference or a named compound
-            if input.peek2(Ident::peek_any) || input.peek2(token::Brace) || input.peek2(token::Bracket) || input.peek2(token::Paren) {
-                input.parse().map(Item::Compound)
-            }
-            else {
-                input.parse().map(Item::Reference)
-            }
+        if self.pos == start {
+            return Err(ParseError { message: "expected a name after '#'".into(), span: Span { start: hash_start, end: self.pos } });
         }
-        else {
-            Err(lookahead.error())
+        Ok(TokKind::Bare(self.text[start..self.pos].to_owned()))
+    }
+}
+
+/// Tries to read `raw` (the text of a `Word` token) as an integer or float: plain decimal,
+/// `0x`-prefixed hex integer, or a hex-float of the form `0x<hex mantissa>p<decimal exponent>`
+/// (e.g. `0x1p3`). Fractional hex mantissas (`0x1.8p3`) aren't supported, since they don't lex
+/// as a single word. Returns `None` if `raw` isn't shaped like a number at all - the caller then
+/// knows it must be a key or tag instead.
+fn parse_number(raw: &str, span: Span) -> Option<Item> {
+    let (neg, body) = match raw.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, raw)
+    };
+    if body.is_empty() { return None; }
+
+    if let Some(hex) = body.strip_prefix("0x").or_else(|| body.strip_prefix("0X")) {
+        if let Some(p_pos) = hex.find(['p', 'P']) {
+            let mantissa = i64::from_str_radix(&hex[..p_pos], 16).ok()?;
+            let exp: i32 = hex[p_pos + 1..].parse().ok()?;
+            let val = mantissa as f32 * 2f32.powi(exp);
+            return Some(Item::Float(FloatLit { value: if neg { -val } else { val }, span }));
         }
+        let val = i64::from_str_radix(hex, 16).ok()?;
+        return Some(Item::Integer(IntLit { value: if neg { -val } else { val }, span }));
+    }
+
+    if !body.chars().next()?.is_ascii_digit() { return None; }
+
+    // `FloatLit`'s `Display` always appends an `f32` suffix to disambiguate from integers -
+    // its presence settles the int-vs-float question regardless of what the digits look like.
+    if let Some(mantissa) = body.strip_suffix("f32") {
+        let val: f32 = mantissa.parse().ok()?;
+        return Some(Item::Float(FloatLit { value: if neg { -val } else { val }, span }));
+    }
+
+    if body.chars().all(|c| c.is_ascii_digit()) {
+        let val: i64 = body.parse().ok()?;
+        return Some(Item::Integer(IntLit { value: if neg { -val } else { val }, span }));
+    }
+
+    if body.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-')) {
+        let val: f32 = body.parse().ok()?;
+        return Some(Item::Float(FloatLit { value: if neg { -val } else { val }, span }));
     }
+
+    None
+}
+
+// ---- Parser -----------------------------------------------------------------------------------
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
 }
 
-impl Parse for Compound {
-    fn parse(input: ParseStream) -> SyResult<Self> {
-        let ref_id = if input.peek(Lifetime) {
-            input.parse()?
+impl Parser {
+    fn new(text: &str) -> ParseResult<Parser> {
+        let mut lexer = Lexer::new(text);
+        let mut tokens = Vec::new();
+        loop {
+            let tok = lexer.next_token()?;
+            let is_eof = matches!(tok.kind, TokKind::Eof);
+            tokens.push(tok);
+            if is_eof { break; }
+        }
+        Ok(Parser { tokens, pos: 0 })
+    }
+
+    fn peek(&self) -> &Token { &self.tokens[self.pos] }
+    fn peek2(&self) -> &Token {
+        let idx = (self.pos + 1).min(self.tokens.len() - 1);
+        &self.tokens[idx]
+    }
+    fn bump(&mut self) -> Token {
+        let tok = self.tokens[self.pos].clone();
+        if self.pos + 1 < self.tokens.len() { self.pos += 1; }
+        tok
+    }
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError { message: message.into(), span: self.peek().span }
+    }
+    fn expect_eof(&self) -> ParseResult<()> {
+        match self.peek().kind {
+            TokKind::Eof => Ok(()),
+            _ => Err(self.error("unexpected trailing input"))
+        }
+    }
+
+    fn parse_item(&mut self) -> ParseResult<Item> {
+        match &self.peek().kind {
+            TokKind::Word(_) => {
+                // A word directly followed by a delimiter is a compound's tag, not a value.
+                if matches!(self.peek2().kind, TokKind::LParen | TokKind::LBrace | TokKind::LBracket) {
+                    return self.parse_compound().map(Item::Compound);
+                }
+                let tok = self.bump();
+                let raw = match tok.kind { TokKind::Word(s) => s, _ => unreachable!() };
+                parse_number(&raw, tok.span)
+                    .ok_or_else(|| ParseError { message: format!("'{}' is not a valid value", raw), span: tok.span })
+            },
+            TokKind::Str(_) => {
+                let tok = self.bump();
+                match tok.kind {
+                    TokKind::Str(value) => Ok(Item::String(StrLit { value, span: tok.span })),
+                    _ => unreachable!()
+                }
+            },
+            TokKind::Bytes(_) => {
+                let tok = self.bump();
+                match tok.kind {
+                    TokKind::Bytes(value) => Ok(Item::Binary(BytesLit { value, span: tok.span })),
+                    _ => unreachable!()
+                }
+            },
+            TokKind::Bare(_) => {
+                let tok = self.bump();
+                let name = match tok.kind { TokKind::Bare(s) => s, _ => unreachable!() };
+                match name.as_str() {
+                    "nan" => Ok(Item::Float(FloatLit { value: f32::NAN, span: tok.span })),
+                    "inf" => Ok(Item::Float(FloatLit { value: f32::INFINITY, span: tok.span })),
+                    "-inf" => Ok(Item::Float(FloatLit { value: f32::NEG_INFINITY, span: tok.span })),
+                    _ => Ok(Item::Bare(BareName { name, span: tok.span }))
+                }
+            },
+            TokKind::Ref(_) => {
+                if matches!(self.peek2().kind, TokKind::Word(_) | TokKind::LParen | TokKind::LBrace | TokKind::LBracket) {
+                    self.parse_compound().map(Item::Compound)
+                }
+                else {
+                    let tok = self.bump();
+                    match tok.kind {
+                        TokKind::Ref(id) => Ok(Item::Reference(RefId { id, span: tok.span })),
+                        _ => unreachable!()
+                    }
+                }
+            },
+            TokKind::LParen | TokKind::LBrace | TokKind::LBracket => self.parse_compound().map(Item::Compound),
+            _ => Err(self.error("expected a value"))
+        }
+    }
+
+    fn parse_compound(&mut self) -> ParseResult<Compound> {
+        let start = self.peek().span.start;
+
+        let ref_id = if matches!(self.peek().kind, TokKind::Ref(_)) {
+            let tok = self.bump();
+            match tok.kind {
+                TokKind::Ref(id) => Some(RefId { id, span: tok.span }),
+                _ => unreachable!()
+            }
         }
         else {
             None
         };
 
-        let tag = if input.peek(Ident::peek_any) {
-            Some(input.call(Ident::parse_any)?)
+        let tag = if matches!(self.peek().kind, TokKind::Word(_)) {
+            let tok = self.bump();
+            match tok.kind {
+                TokKind::Word(w) => Some(w),
+                _ => unreachable!()
+            }
         }
         else {
             None
         };
 
-        let (delimiter, _delim_span, content) = input.parse_any_delimiter()?;
+        let delimiter = match self.peek().kind {
+            TokKind::LParen => Delimiter::Paren,
+            TokKind::LBrace => Delimiter::Brace,
+            TokKind::LBracket => Delimiter::Bracket,
+            _ => return Err(self.error("expected '(', '[', or '{'"))
+        };
+        let closing = match delimiter {
+            Delimiter::Paren => TokKind::RParen,
+            Delimiter::Brace => TokKind::RBrace,
+            Delimiter::Bracket => TokKind::RBracket,
+        };
+        self.bump();
 
-        let body = content.call(Punctuated::parse_terminated)?;
+        let mut body = Vec::new();
+        while std::mem::discriminant(&self.peek().kind) != std::mem::discriminant(&closing) {
+            body.push(self.parse_compound_entry()?);
+            match self.peek().kind {
+                TokKind::Comma => { self.bump(); },
+                ref k if std::mem::discriminant(k) == std::mem::discriminant(&closing) => break,
+                _ => return Err(self.error("expected ',' or a closing delimiter"))
+            }
+        }
+        let close_tok = self.bump();
 
-        Ok(Compound { ref_id, tag, /*delim_span,*/ delimiter , body })
+        Ok(Compound { ref_id, tag, delimiter, body, span: Span { start, end: close_tok.span.end } })
     }
-}
 
-impl Parse for CompoundEntry {
-    fn parse(input: ParseStream) -> SyResult<Self> {
-        if input.peek(Ident::peek_any) && input.peek2(token::Colon) {
-            let name: Ident = input.call(Ident::parse_any)?;
-            let _colon: token::Colon = input.parse()?;
-            let value: Item = input.parse()?;
+    fn parse_compound_entry(&mut self) -> ParseResult<CompoundEntry> {
+        if matches!(self.peek().kind, TokKind::Word(_)) && matches!(self.peek2().kind, TokKind::Colon) {
+            let tok = self.bump();
+            let name = match tok.kind { TokKind::Word(w) => w, _ => unreachable!() };
+            self.bump();
+            let value = self.parse_item()?;
             return Ok(CompoundEntry::BareNamed(name, value));
         }
-        
-        let first: Item = input.parse()?;
-        if input.peek(token::Colon) {
-            let _colon: token::Colon = input.parse()?;
-            let value: Item = input.parse()?;
-            return Ok(CompoundEntry::Named(first, value))
+
+        let first = self.parse_item()?;
+        if matches!(self.peek().kind, TokKind::Colon) {
+            self.bump();
+            let value = self.parse_item()?;
+            Ok(CompoundEntry::Named(first, value))
         }
         else {
-            return Ok(CompoundEntry::Indexed(first))
+            Ok(CompoundEntry::Indexed(first))
         }
     }
 }
 
+impl Item {
+    /// Parses a single item (a value, or a whole `Compound`) from `text`, erroring if anything
+    /// but trailing whitespace is left over.
+    pub fn parse(text: &str) -> ParseResult<Item> {
+        let mut parser = Parser::new(text)?;
+        let item = parser.parse_item()?;
+        parser.expect_eof()?;
+        Ok(item)
+    }
+}
+
 impl std::fmt::Display for Item {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Item::Integer(t) => t.fmt(f),
             Item::Float(t) => t.fmt(f),
-            Item::String(t) => t.token().fmt(f),
-            Item::Binary(t) => t.token().fmt(f),
-            Item::Bare(b) => write!(f, "#{}", b),
+            Item::String(t) => t.fmt(f),
+            Item::Binary(t) => t.fmt(f),
+            Item::Bare(t) => t.fmt(f),
             Item::Reference(t) => t.fmt(f),
             Item::Compound(t) => t.fmt(f),
         }
@@ -145,39 +580,36 @@ impl std::fmt::Display for Item {
 
 impl std::fmt::Display for Compound {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self.ref_id {
-            Some(r) => write!(f, "{} ", r)?,
-            None => ()
+        if let Some(r) = &self.ref_id {
+            write!(f, "{} ", r)?;
         }
-
-        match &self.tag {
-            Some(r) => write!(f, "{} ", r)?,
-            None => ()
+        if let Some(t) = &self.tag {
+            write!(f, "{} ", t)?;
         }
 
         match self.delimiter {
-            Delimiter::Parenthesis => f.write_char('(')?,
+            Delimiter::Paren => f.write_char('(')?,
             Delimiter::Brace => f.write_char('{')?,
             Delimiter::Bracket => f.write_char('[')?,
-            Delimiter::None => (),
         }
         if !f.alternate() && self.body.len() > 1 {
             f.write_char('\n')?;
         }
 
-        for pair in self.body.pairs() {
-            match pair {
-                syn::punctuated::Pair::Punctuated(i, _) => write!(f,"{},", i)?,
-                syn::punctuated::Pair::End(i) => i.fmt(f)?,
-            };
-            f.write_char(if f.alternate() && self.body.len() > 1 {' '} else {'\n'})?;
+        for (i, entry) in self.body.iter().enumerate() {
+            if i + 1 == self.body.len() {
+                entry.fmt(f)?;
+            }
+            else {
+                write!(f, "{},", entry)?;
+            }
+            f.write_char(if f.alternate() && self.body.len() > 1 { ' ' } else { '\n' })?;
         }
 
         match self.delimiter {
-            Delimiter::Parenthesis => f.write_char(')')?,
+            Delimiter::Paren => f.write_char(')')?,
             Delimiter::Brace => f.write_char('}')?,
             Delimiter::Bracket => f.write_char(']')?,
-            Delimiter::None => (),
         }
 
         Ok(())
@@ -196,49 +628,327 @@ impl std::fmt::Display for CompoundEntry {
 
 impl Item {
     pub fn new_string(val: &str) -> Self {
-        Item::String(LitStr::new(val, Span::call_site()))
+        Item::String(StrLit { value: val.to_owned(), span: Span::default() })
     }
 
     pub fn new_binary(val: &[u8]) -> Self {
-        Item::Binary(LitByteStr::new(val, Span::call_site()))
+        Item::Binary(BytesLit { value: val.to_owned(), span: Span::default() })
     }
 
     pub fn new_float(val: f32) -> Self {
-        Item::Float(LitFloat::from(proc_macro2::Literal::f32_suffixed(val)))
+        Item::new_f32_exact(val)
+    }
+
+    /// Builds a float item that's guaranteed to print and re-parse to the identical `f32`
+    /// bit pattern, including NaN, the infinities, and signed zero.
+    pub fn new_f32_exact(val: f32) -> Self {
+        Item::Float(FloatLit { value: val, span: Span::default() })
     }
 
     pub fn new_i8(val: i8) -> Self {
-        Item::Integer(LitInt::from(proc_macro2::Literal::i8_suffixed(val)))
+        Item::Integer(IntLit { value: val as i64, span: Span::default() })
     }
 
     pub fn new_i16(val: i16) -> Self {
-        Item::Integer(LitInt::from(proc_macro2::Literal::i16_suffixed(val)))
+        Item::Integer(IntLit { value: val as i64, span: Span::default() })
     }
 
     pub fn new_u8(val: u8) -> Self {
-        Item::Integer(LitInt::from(proc_macro2::Literal::u8_suffixed(val)))
+        Item::Integer(IntLit { value: val as i64, span: Span::default() })
     }
 
     pub fn new_u16(val: u16) -> Self {
-        Item::Integer(LitInt::from(proc_macro2::Literal::u16_suffixed(val)))
+        Item::Integer(IntLit { value: val as i64, span: Span::default() })
     }
 
     pub fn new_bare(ident: &str) -> Self {
-        Item::Bare(Ident::new(ident, Span::call_site()))
+        Item::Bare(BareName { name: ident.to_owned(), span: Span::default() })
     }
 
     pub fn new_integer(int: isize) -> Self {
-        Item::Integer(LitInt::from(proc_macro2::Literal::isize_unsuffixed(int)))
+        Item::Integer(IntLit { value: int as i64, span: Span::default() })
+    }
+}
+
+// ---- Binary codec ------------------------------------------------------------------------------
+//
+// This is a compact binary serialization of the `Item`/`Compound` tree itself, modeled on the
+// same overall shape as PAYDAY's on-disk scriptdata (a tagged value stream backed by an interned
+// string pool and a table of reference ids, see `crate::formats::scriptdata::binary`) but not
+// byte-compatible with it: the real format has first-class vector/quaternion/idstring value types
+// and per-platform (x86/x64/RAID) section layouts that this generic notation doesn't model. What's
+// here is `notation_rs`'s own round-trippable wire format for its tree, used so a `Compound` can be
+// cached or shipped without going through the text grammar above.
+//
+// Strings (string items, bare names, compound tags, and bare-named keys) are deduplicated into a
+// single pool and referenced by index. `ref_id`/`Item::Reference` pairs are resolved the same way:
+// every distinct id used anywhere in the tree gets a table slot, and both the declaring `Compound`
+// and any `Reference`s pointing at it store that slot number instead of the original id text -
+// decoding hands back a fresh synthetic id (`r<slot>`) rather than reconstructing the original
+// spelling, exactly like the "a plain integer, not a name" semantics of a real id table.
+
+const TAG_INTEGER: u8 = 0;
+const TAG_FLOAT: u8 = 1;
+const TAG_STRING: u8 = 2;
+const TAG_BINARY: u8 = 3;
+const TAG_BARE: u8 = 4;
+const TAG_REFERENCE: u8 = 5;
+const TAG_COMPOUND: u8 = 6;
+
+const ENTRY_INDEXED: u8 = 0;
+const ENTRY_NAMED: u8 = 1;
+const ENTRY_BARE_NAMED: u8 = 2;
+
+const DELIM_PAREN: u8 = 0;
+const DELIM_BRACE: u8 = 1;
+const DELIM_BRACKET: u8 = 2;
+
+struct StringPool {
+    strings: Vec<String>,
+    indices: std::collections::HashMap<String, u32>,
+}
+impl StringPool {
+    fn new() -> Self {
+        StringPool { strings: Vec::new(), indices: std::collections::HashMap::new() }
+    }
+    fn intern(&mut self, s: &str) -> u32 {
+        if let Some(&idx) = self.indices.get(s) { return idx; }
+        let idx = self.strings.len() as u32;
+        self.strings.push(s.to_owned());
+        self.indices.insert(s.to_owned(), idx);
+        idx
+    }
+}
+
+struct RefPool {
+    indices: std::collections::HashMap<String, u32>,
+    next: u32,
+}
+impl RefPool {
+    fn new() -> Self {
+        RefPool { indices: std::collections::HashMap::new(), next: 0 }
+    }
+    fn slot_for(&mut self, id: &str) -> u32 {
+        if let Some(&slot) = self.indices.get(id) { return slot; }
+        let slot = self.next;
+        self.next += 1;
+        self.indices.insert(id.to_owned(), slot);
+        slot
+    }
+}
+
+fn collect_refs(item: &Item, refs: &mut RefPool) {
+    match item {
+        Item::Reference(r) => { refs.slot_for(&r.id); },
+        Item::Compound(c) => collect_refs_in_compound(c, refs),
+        _ => {}
+    }
+}
+
+fn collect_refs_in_compound(c: &Compound, refs: &mut RefPool) {
+    if let Some(r) = &c.ref_id { refs.slot_for(&r.id); }
+    for entry in &c.body {
+        match entry {
+            CompoundEntry::Named(k, v) => { collect_refs(k, refs); collect_refs(v, refs); },
+            CompoundEntry::BareNamed(_, v) => collect_refs(v, refs),
+            CompoundEntry::Indexed(v) => collect_refs(v, refs),
+        }
+    }
+}
+
+struct Writer {
+    strings: StringPool,
+    refs: RefPool,
+    out: Vec<u8>,
+}
+impl Writer {
+    fn u8(&mut self, v: u8) { self.out.push(v); }
+    fn u32(&mut self, v: u32) { self.out.extend_from_slice(&v.to_le_bytes()); }
+    fn i64(&mut self, v: i64) { self.out.extend_from_slice(&v.to_le_bytes()); }
+    fn bytes(&mut self, v: &[u8]) { self.u32(v.len() as u32); self.out.extend_from_slice(v); }
+
+    fn write_item(&mut self, item: &Item) {
+        match item {
+            Item::Integer(t) => { self.u8(TAG_INTEGER); self.i64(t.value); },
+            Item::Float(t) => { self.u8(TAG_FLOAT); self.u32(t.value.to_bits()); },
+            Item::String(t) => { self.u8(TAG_STRING); let idx = self.strings.intern(&t.value); self.u32(idx); },
+            Item::Binary(t) => { self.u8(TAG_BINARY); self.bytes(&t.value); },
+            Item::Bare(t) => { self.u8(TAG_BARE); let idx = self.strings.intern(&t.name); self.u32(idx); },
+            Item::Reference(t) => { self.u8(TAG_REFERENCE); let slot = self.refs.slot_for(&t.id); self.u32(slot); },
+            Item::Compound(c) => { self.u8(TAG_COMPOUND); self.write_compound(c); },
+        }
+    }
+
+    fn write_compound(&mut self, c: &Compound) {
+        self.u8(match c.delimiter { Delimiter::Paren => DELIM_PAREN, Delimiter::Brace => DELIM_BRACE, Delimiter::Bracket => DELIM_BRACKET });
+
+        match &c.ref_id {
+            Some(r) => { self.u8(1); let slot = self.refs.slot_for(&r.id); self.u32(slot); },
+            None => self.u8(0),
+        }
+        match &c.tag {
+            Some(tag) => { self.u8(1); let idx = self.strings.intern(tag); self.u32(idx); },
+            None => self.u8(0),
+        }
+
+        self.u32(c.body.len() as u32);
+        for entry in &c.body {
+            match entry {
+                CompoundEntry::Indexed(v) => { self.u8(ENTRY_INDEXED); self.write_item(v); },
+                CompoundEntry::Named(k, v) => { self.u8(ENTRY_NAMED); self.write_item(k); self.write_item(v); },
+                CompoundEntry::BareNamed(name, v) => {
+                    self.u8(ENTRY_BARE_NAMED);
+                    let idx = self.strings.intern(name);
+                    self.u32(idx);
+                    self.write_item(v);
+                },
+            }
+        }
+    }
+}
+
+/// Serializes `root` into `notation_rs`'s own binary form. See the module-level comment above
+/// the codec for what this is (and isn't) compatible with.
+pub fn encode(root: &Compound) -> Vec<u8> {
+    let mut refs = RefPool::new();
+    collect_refs_in_compound(root, &mut refs);
+
+    let mut writer = Writer { strings: StringPool::new(), refs, out: Vec::new() };
+    writer.write_compound(root);
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&(writer.strings.strings.len() as u32).to_le_bytes());
+    for s in &writer.strings.strings {
+        out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        out.extend_from_slice(s.as_bytes());
+    }
+    out.extend_from_slice(&writer.out);
+    out
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+    pos: usize,
+    strings: Vec<String>,
+}
+impl<'a> Reader<'a> {
+    fn need(&self, n: usize) -> ParseResult<()> {
+        if self.pos + n > self.input.len() {
+            Err(ParseError { message: "unexpected end of binary data".into(), span: Span { start: self.pos, end: self.pos } })
+        }
+        else {
+            Ok(())
+        }
+    }
+    fn u8(&mut self) -> ParseResult<u8> {
+        self.need(1)?;
+        let v = self.input[self.pos];
+        self.pos += 1;
+        Ok(v)
+    }
+    fn u32(&mut self) -> ParseResult<u32> {
+        self.need(4)?;
+        let v = u32::from_le_bytes(self.input[self.pos..self.pos + 4].try_into().unwrap());
+        self.pos += 4;
+        Ok(v)
+    }
+    fn i64(&mut self) -> ParseResult<i64> {
+        self.need(8)?;
+        let v = i64::from_le_bytes(self.input[self.pos..self.pos + 8].try_into().unwrap());
+        self.pos += 8;
+        Ok(v)
+    }
+    fn bytes(&mut self) -> ParseResult<Vec<u8>> {
+        let len = self.u32()? as usize;
+        self.need(len)?;
+        let v = self.input[self.pos..self.pos + len].to_vec();
+        self.pos += len;
+        Ok(v)
+    }
+    fn string_ref(&mut self) -> ParseResult<String> {
+        let idx = self.u32()? as usize;
+        self.strings.get(idx).cloned()
+            .ok_or_else(|| ParseError { message: format!("string pool index {} out of range", idx), span: Span { start: self.pos, end: self.pos } })
+    }
+
+    fn read_item(&mut self) -> ParseResult<Item> {
+        let start = self.pos;
+        let tag = self.u8()?;
+        match tag {
+            TAG_INTEGER => Ok(Item::Integer(IntLit { value: self.i64()?, span: Span { start, end: self.pos } })),
+            TAG_FLOAT => Ok(Item::Float(FloatLit { value: f32::from_bits(self.u32()?), span: Span { start, end: self.pos } })),
+            TAG_STRING => { let value = self.string_ref()?; Ok(Item::String(StrLit { value, span: Span { start, end: self.pos } })) },
+            TAG_BINARY => { let value = self.bytes()?; Ok(Item::Binary(BytesLit { value, span: Span { start, end: self.pos } })) },
+            TAG_BARE => { let name = self.string_ref()?; Ok(Item::Bare(BareName { name, span: Span { start, end: self.pos } })) },
+            TAG_REFERENCE => {
+                let slot = self.u32()?;
+                Ok(Item::Reference(RefId { id: format!("r{}", slot), span: Span { start, end: self.pos } }))
+            },
+            TAG_COMPOUND => Ok(Item::Compound(self.read_compound(start)?)),
+            other => Err(ParseError { message: format!("unrecognised tag {}", other), span: Span { start, end: self.pos } })
+        }
+    }
+
+    fn read_compound(&mut self, start: usize) -> ParseResult<Compound> {
+        let delimiter = match self.u8()? {
+            DELIM_PAREN => Delimiter::Paren,
+            DELIM_BRACE => Delimiter::Brace,
+            DELIM_BRACKET => Delimiter::Bracket,
+            other => return Err(ParseError { message: format!("unrecognised delimiter {}", other), span: Span { start, end: self.pos } })
+        };
+
+        let ref_id = if self.u8()? != 0 {
+            let slot = self.u32()?;
+            Some(RefId { id: format!("r{}", slot), span: Span { start, end: self.pos } })
+        }
+        else {
+            None
+        };
+        let tag = if self.u8()? != 0 { Some(self.string_ref()?) } else { None };
+
+        let count = self.u32()? as usize;
+        let mut body = Vec::with_capacity(count);
+        for _ in 0..count {
+            let entry = match self.u8()? {
+                ENTRY_INDEXED => CompoundEntry::Indexed(self.read_item()?),
+                ENTRY_NAMED => { let k = self.read_item()?; let v = self.read_item()?; CompoundEntry::Named(k, v) },
+                ENTRY_BARE_NAMED => { let name = self.string_ref()?; let v = self.read_item()?; CompoundEntry::BareNamed(name, v) },
+                other => return Err(ParseError { message: format!("unrecognised entry kind {}", other), span: Span { start: self.pos, end: self.pos } })
+            };
+            body.push(entry);
+        }
+
+        Ok(Compound { ref_id, tag, delimiter, body, span: Span { start, end: self.pos } })
     }
 }
 
+/// Deserializes a tree previously produced by [`encode`]. Reference ids don't round-trip by
+/// spelling (the wire form only keeps their table slot), so they come back as synthetic `r<slot>`
+/// ids rather than whatever text the original tree used.
+pub fn decode(input: &[u8]) -> ParseResult<Compound> {
+    let mut header = Reader { input, pos: 0, strings: Vec::new() };
+    let string_count = header.u32()? as usize;
+    let mut strings = Vec::with_capacity(string_count);
+    for _ in 0..string_count {
+        let bytes = header.bytes()?;
+        let s = String::from_utf8(bytes)
+            .map_err(|_| ParseError { message: "string pool entry isn't valid UTF-8".into(), span: Span { start: header.pos, end: header.pos } })?;
+        strings.push(s);
+    }
+
+    let mut reader = Reader { input, pos: header.pos, strings };
+    let start = reader.pos;
+    reader.read_compound(start)
+}
+
 impl Compound {
     pub fn new_braced() -> Self {
         Compound {
             ref_id: None,
             tag: None,
-            delimiter: proc_macro2::Delimiter::Brace,
-            body: syn::punctuated::Punctuated::new(),
+            delimiter: Delimiter::Brace,
+            body: Vec::new(),
+            span: Span::default(),
         }
     }
 
@@ -246,19 +956,19 @@ impl Compound {
         Compound {
             ref_id: None,
             tag: None,
-            delimiter: proc_macro2::Delimiter::Parenthesis,
-            body: syn::punctuated::Punctuated::new(),
+            delimiter: Delimiter::Paren,
+            body: Vec::new(),
+            span: Span::default(),
         }
     }
 
     pub fn with_tag(mut self, tag: &str) -> Self {
-        self.tag = Some(Ident::new(tag, Span::call_site()));
+        self.tag = Some(tag.to_owned());
         self
     }
 
     pub fn push_bare(&mut self, name: &str, value: Item) -> &mut Self {
-        let bare_ident = Ident::new(name, Span::call_site());
-        self.body.push(CompoundEntry::BareNamed(bare_ident, value));
+        self.body.push(CompoundEntry::BareNamed(name.to_owned(), value));
         self
     }
 
@@ -271,4 +981,77 @@ impl Compound {
         self.body.push(CompoundEntry::Indexed(value));
         self
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod binary_codec_tests {
+    use super::*;
+
+    fn roundtrip(tree: &Compound) -> Compound {
+        decode(&encode(tree)).expect("decode of freshly-encoded tree should succeed")
+    }
+
+    #[test]
+    fn scalar_items_round_trip() {
+        let mut c = Compound::new_braced().with_tag("Scalars");
+        c.push_bare("a", Item::new_integer(-42));
+        c.push_bare("b", Item::new_f32_exact(3.5));
+        c.push_bare("c", Item::new_string("hello"));
+        c.push_bare("d", Item::new_binary(&[1, 2, 3, 255]));
+        c.push_indexed(Item::new_bare("some_const"));
+
+        let back = roundtrip(&c);
+        assert_eq!(back.to_string(), c.to_string());
+    }
+
+    #[test]
+    fn shared_strings_use_the_same_pool_slot_and_still_round_trip() {
+        let mut c = Compound::new_parenthesized();
+        c.push_indexed(Item::new_string("dupe"));
+        c.push_indexed(Item::new_string("dupe"));
+        c.push_indexed(Item::new_bare("dupe"));
+
+        let encoded = encode(&c);
+        let back = decode(&encoded).unwrap();
+        assert_eq!(back.to_string(), c.to_string());
+    }
+
+    #[test]
+    fn nested_compounds_round_trip() {
+        let mut inner = Compound::new_braced().with_tag("Inner");
+        inner.push_bare("x", Item::new_integer(1));
+
+        let mut outer = Compound::new_braced().with_tag("Outer");
+        outer.push_bare("child", Item::Compound(inner));
+        outer.push_indexed(Item::new_integer(99));
+
+        let back = roundtrip(&outer);
+        assert_eq!(back.to_string(), outer.to_string());
+    }
+
+    #[test]
+    fn references_resolve_through_the_ref_table() {
+        let mut target = Compound::new_braced();
+        target.ref_id = Some(RefId { id: "r0".to_owned(), span: Span::default() });
+        target.push_bare("leaf", Item::new_integer(7));
+
+        let mut root = Compound::new_braced();
+        root.push_bare("target", Item::Compound(target));
+        root.push_bare("link", Item::Reference(RefId { id: "r0".to_owned(), span: Span::default() }));
+
+        let back = roundtrip(&root);
+        assert_eq!(back.to_string(), root.to_string());
+    }
+
+    #[test]
+    fn float_edge_cases_preserve_their_exact_bit_pattern() {
+        for value in [0.0f32, -0.0f32, f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            let mut c = Compound::new_parenthesized();
+            c.push_indexed(Item::new_f32_exact(value));
+
+            let back = roundtrip(&c);
+            let CompoundEntry::Indexed(Item::Float(f)) = &back.body[0] else { panic!("expected a float item back") };
+            assert_eq!(f.value.to_bits(), value.to_bits());
+        }
+    }
+}
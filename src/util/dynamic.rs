@@ -0,0 +1,241 @@
+//! A dynamically-typed value standing in for any [`Parse`] type when the
+//! concrete Rust shape isn't known up front: [`Value`] can represent the
+//! same data a hand-written struct using `parse_helpers` would, but generic
+//! code can inspect, edit, and re-serialize it without knowing that struct's
+//! type. Useful for tools that need to load a format, tweak a field or two,
+//! and write it back out byte-for-byte rather than round-tripping through a
+//! Rust type that bakes in every field's exact layout.
+//!
+//! [`Value`]'s own wire format is a straightforward tag-length-value
+//! encoding: one discriminant byte picks the kind, then variable-length
+//! kinds are prefixed with a `u32` count, following the same convention as
+//! [`CountedVec`]/[`CountedString`] elsewhere in this module; [`Tagged`],
+//! [`Record`], and [`List`] recurse into child [`Value`]s the same way.
+//!
+//! [`ToValue`]/[`FromValue`] bridge a concrete `Parse` type to and from
+//! [`Value`], for code that wants to convert between the two.
+//!
+//! [`Tagged`]: Value::Tagged
+//! [`Record`]: Value::Record
+//! [`List`]: Value::List
+
+use std::convert::TryInto;
+use std::io::{Result as IoResult, Write};
+
+use nom::IResult;
+use nom::combinator::map;
+use nom::number::complete::{le_u8, le_u32};
+
+use super::parse_helpers::{ContextFrame, CountedString, CountedVec, Parse, ParseError, WireFormat, push_frame};
+
+const TAG_UNIT: u8 = 0;
+const TAG_BOOL: u8 = 1;
+const TAG_U8: u8 = 2;
+const TAG_U16: u8 = 3;
+const TAG_U32: u8 = 4;
+const TAG_U64: u8 = 5;
+const TAG_I8: u8 = 6;
+const TAG_I16: u8 = 7;
+const TAG_I32: u8 = 8;
+const TAG_I64: u8 = 9;
+const TAG_F32: u8 = 10;
+const TAG_F64: u8 = 11;
+const TAG_TEXT: u8 = 12;
+const TAG_BYTES: u8 = 13;
+const TAG_TAGGED: u8 = 14;
+const TAG_RECORD: u8 = 15;
+const TAG_LIST: u8 = 16;
+
+/// A value whose shape is discovered while parsing, rather than fixed by a
+/// Rust type ahead of time. See the module doc comment for the wire format.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+    /// A named variant: a symbol naming the case, plus its payload.
+    Tagged(String, Box<Value>),
+    /// An ordered list of name→value pairs - a struct whose field set isn't
+    /// known statically.
+    Record(Vec<(String, Value)>),
+    List(Vec<Value>)
+}
+
+impl Parse for Value {
+    fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self, ParseError> {
+        let (rest, tag) = le_u8(input).map_err(|e| push_frame(e, ContextFrame::Expected("Value tag")))?;
+        match tag {
+            TAG_UNIT => Ok((rest, Value::Unit)),
+            TAG_BOOL => map(<bool as Parse>::parse, Value::Bool)(rest),
+            TAG_U8 => map(<u8 as Parse>::parse, Value::U8)(rest),
+            TAG_U16 => map(<u16 as Parse>::parse, Value::U16)(rest),
+            TAG_U32 => map(<u32 as Parse>::parse, Value::U32)(rest),
+            TAG_U64 => map(<u64 as Parse>::parse, Value::U64)(rest),
+            TAG_I8 => map(<i8 as Parse>::parse, Value::I8)(rest),
+            TAG_I16 => map(<i16 as Parse>::parse, Value::I16)(rest),
+            TAG_I32 => map(<i32 as Parse>::parse, Value::I32)(rest),
+            TAG_I64 => map(<i64 as Parse>::parse, Value::I64)(rest),
+            TAG_F32 => map(<f32 as Parse>::parse, Value::F32)(rest),
+            TAG_F64 => map(<f64 as Parse>::parse, Value::F64)(rest),
+            TAG_TEXT => map(CountedString::<u32>::parse_into, Value::Text)(rest),
+            TAG_BYTES => map(CountedVec::<u32, u8>::parse_into, Value::Bytes)(rest),
+            TAG_TAGGED => {
+                let (rest, name) = CountedString::<u32>::parse_into(rest)?;
+                let (rest, payload) = Value::parse(rest)?;
+                Ok((rest, Value::Tagged(name, Box::new(payload))))
+            }
+            TAG_RECORD => {
+                let (mut rest, count) = le_u32(rest).map_err(|e| push_frame(e, ContextFrame::Expected("u32")))?;
+                let mut fields = Vec::with_capacity(count as usize);
+                for i in 0..count {
+                    let (r, name) = CountedString::<u32>::parse_into(rest)?;
+                    let (r, value) = Value::parse(r)
+                        .map_err(|e| push_frame(e, ContextFrame::Element(i as usize)))?;
+                    fields.push((name, value));
+                    rest = r;
+                }
+                Ok((rest, Value::Record(fields)))
+            }
+            TAG_LIST => {
+                let (mut rest, count) = le_u32(rest).map_err(|e| push_frame(e, ContextFrame::Expected("u32")))?;
+                let mut items = Vec::with_capacity(count as usize);
+                for i in 0..count {
+                    let (r, value) = Value::parse(rest)
+                        .map_err(|e| push_frame(e, ContextFrame::Element(i as usize)))?;
+                    items.push(value);
+                    rest = r;
+                }
+                Ok((rest, Value::List(items)))
+            }
+            _ => Err(nom::Err::Failure(<ParseError as nom::error::ParseError<&[u8]>>::from_error_kind(input, nom::error::ErrorKind::Alt)))
+        }
+    }
+
+    fn serialize<O: Write>(&self, output: &mut O) -> IoResult<()> {
+        match self {
+            Value::Unit => TAG_UNIT.serialize(output),
+            Value::Bool(v) => { TAG_BOOL.serialize(output)?; v.serialize(output) }
+            Value::U8(v) => { TAG_U8.serialize(output)?; v.serialize(output) }
+            Value::U16(v) => { TAG_U16.serialize(output)?; v.serialize(output) }
+            Value::U32(v) => { TAG_U32.serialize(output)?; v.serialize(output) }
+            Value::U64(v) => { TAG_U64.serialize(output)?; v.serialize(output) }
+            Value::I8(v) => { TAG_I8.serialize(output)?; v.serialize(output) }
+            Value::I16(v) => { TAG_I16.serialize(output)?; v.serialize(output) }
+            Value::I32(v) => { TAG_I32.serialize(output)?; v.serialize(output) }
+            Value::I64(v) => { TAG_I64.serialize(output)?; v.serialize(output) }
+            Value::F32(v) => { TAG_F32.serialize(output)?; v.serialize(output) }
+            Value::F64(v) => { TAG_F64.serialize(output)?; v.serialize(output) }
+            Value::Text(s) => {
+                TAG_TEXT.serialize(output)?;
+                CountedString::<u32>::serialize_from(s, output)
+            }
+            Value::Bytes(b) => {
+                TAG_BYTES.serialize(output)?;
+                CountedVec::<u32, u8>::serialize_from(b, output)
+            }
+            Value::Tagged(name, payload) => {
+                TAG_TAGGED.serialize(output)?;
+                CountedString::<u32>::serialize_from(name, output)?;
+                payload.serialize(output)
+            }
+            Value::Record(fields) => {
+                TAG_RECORD.serialize(output)?;
+                let count: u32 = fields.len().try_into().map_err(|_| std::io::ErrorKind::InvalidInput)?;
+                count.serialize(output)?;
+                for (name, value) in fields {
+                    CountedString::<u32>::serialize_from(name, output)?;
+                    value.serialize(output)?;
+                }
+                Ok(())
+            }
+            Value::List(items) => {
+                TAG_LIST.serialize(output)?;
+                let count: u32 = items.len().try_into().map_err(|_| std::io::ErrorKind::InvalidInput)?;
+                count.serialize(output)?;
+                for item in items {
+                    item.serialize(output)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Converts a concrete `Parse` type to its [`Value`] representation.
+pub trait ToValue {
+    fn to_value(&self) -> Value;
+}
+
+/// The reverse of [`ToValue`]: recovers a concrete type from a [`Value`],
+/// failing if the value isn't that type's shape.
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+macro_rules! value_primitive {
+    ($t:ty, $variant:ident) => {
+        impl ToValue for $t {
+            fn to_value(&self) -> Value { Value::$variant(*self) }
+        }
+        impl FromValue for $t {
+            fn from_value(value: &Value) -> Option<Self> {
+                match value { Value::$variant(v) => Some(*v), _ => None }
+            }
+        }
+    }
+}
+
+value_primitive!(bool, Bool);
+value_primitive!(u8, U8);
+value_primitive!(u16, U16);
+value_primitive!(u32, U32);
+value_primitive!(u64, U64);
+value_primitive!(i8, I8);
+value_primitive!(i16, I16);
+value_primitive!(i32, I32);
+value_primitive!(i64, I64);
+value_primitive!(f32, F32);
+value_primitive!(f64, F64);
+
+impl ToValue for String {
+    fn to_value(&self) -> Value { Value::Text(self.clone()) }
+}
+impl FromValue for String {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value { Value::Text(s) => Some(s.clone()), _ => None }
+    }
+}
+
+impl ToValue for crate::hashindex::Hash {
+    fn to_value(&self) -> Value { Value::U64(self.0) }
+}
+impl FromValue for crate::hashindex::Hash {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value { Value::U64(v) => Some(crate::hashindex::Hash(*v)), _ => None }
+    }
+}
+
+impl<T: ToValue> ToValue for Vec<T> {
+    fn to_value(&self) -> Value {
+        Value::List(self.iter().map(ToValue::to_value).collect())
+    }
+}
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::List(items) => items.iter().map(T::from_value).collect(),
+            _ => None
+        }
+    }
+}
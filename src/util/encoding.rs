@@ -0,0 +1,396 @@
+//! Byte<->char mappings [`super::binaryreader::NullTerminatedString`] and
+//! [`super::binaryreader::CountedString`] are generic over, so PD2's several
+//! single-byte code pages (and UTF-8) share one pair of string readers
+//! instead of a hand-rolled match-arm table per encoding.
+
+use std::marker::PhantomData;
+
+use super::binaryreader::ReadError;
+
+/// A byte<->char mapping a string [`ItemReader`][super::binaryreader::ItemReader]
+/// can be parameterized over.
+pub trait Encoding {
+    /// Decodes `bytes` - already sliced to exactly the string's own bytes,
+    /// with no length prefix or terminator - into a `String`.
+    fn decode(bytes: &[u8]) -> Result<String, ReadError>;
+    /// Encodes `text` into bytes in this encoding, with no length prefix or
+    /// terminator of its own. Fails on the first char this encoding (and,
+    /// for code pages, [`fold_latin`]) can't represent.
+    fn encode(text: &str) -> Result<Vec<u8>, ReadError>;
+    /// As [`Encoding::encode`], but never fails: any unrepresentable char is
+    /// replaced with `_` and recorded in
+    /// [`EncodeOutcome::replacements`][EncodeOutcome], so batch tooling can
+    /// flag (or a caller can just ignore) the strings that lost data.
+    fn encode_lossy(text: &str) -> EncodeOutcome;
+}
+
+/// A single char [`Encoding::encode_lossy`] couldn't represent and replaced
+/// with `_`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Replacement {
+    /// Index of the replaced char within the original `text`, counted in
+    /// `char`s (not bytes) - i.e. its position in `text.chars()`.
+    pub char_index: usize,
+    /// The codepoint that couldn't be represented.
+    pub original: char,
+}
+
+/// Result of [`Encoding::encode_lossy`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct EncodeOutcome {
+    pub bytes: Vec<u8>,
+    pub replacements: Vec<Replacement>,
+}
+
+/// Plain UTF-8 - what every current PD2 asset format actually uses; the
+/// default for both string readers.
+pub struct Utf8;
+impl Encoding for Utf8 {
+    fn decode(bytes: &[u8]) -> Result<String, ReadError> {
+        Ok(String::from_utf8(bytes.to_vec())?)
+    }
+    fn encode(text: &str) -> Result<Vec<u8>, ReadError> {
+        Ok(text.as_bytes().to_vec())
+    }
+    fn encode_lossy(text: &str) -> EncodeOutcome {
+        // UTF-8 can represent every char there is, so this never loses data.
+        EncodeOutcome { bytes: text.as_bytes().to_vec(), replacements: Vec::new() }
+    }
+}
+
+/// A single-byte code page: every byte maps to at most one Unicode
+/// codepoint. [`code_page_encoding`] turns a [`CodePage::TABLE`] into a full
+/// [`Encoding`] impl, so adding a code page is a 256-entry table, not a pair
+/// of 256-arm match statements.
+pub trait CodePage {
+    /// `TABLE[b as usize]` is the `char` byte `b` decodes to, or `None` if
+    /// `b` is unused in this code page.
+    const TABLE: [Option<char>; 256];
+}
+
+/// Looks a single `char` up in a [`CodePage`]'s table directly, with no
+/// folding - the byte this char encodes to in this code page, if any.
+fn encode_byte<C: CodePage>(c: char) -> Option<u8> {
+    C::TABLE.iter().position(|&t| t == Some(c)).map(|p| p as u8)
+}
+
+/// The bytes a single char encodes to in code page `C` - a direct hit, or
+/// else [`fold_latin`]'s expansion if every char of that folds to a byte of
+/// its own - or `None` if `c` can't be represented at all.
+fn encode_char<C: CodePage>(c: char) -> Option<Vec<u8>> {
+    if let Some(b) = encode_byte::<C>(c) {
+        return Some(vec![b]);
+    }
+    fold_latin(c)?.chars().map(encode_byte::<C>).collect()
+}
+
+macro_rules! code_page_encoding {
+    ($ty:ident) => {
+        impl Encoding for $ty {
+            fn decode(bytes: &[u8]) -> Result<String, ReadError> {
+                bytes.iter().map(|&b| {
+                    <$ty as CodePage>::TABLE[b as usize]
+                        .ok_or(ReadError::BadConvert("byte", stringify!($ty)))
+                }).collect()
+            }
+
+            fn encode(text: &str) -> Result<Vec<u8>, ReadError> {
+                let mut out = Vec::with_capacity(text.len());
+                for c in text.chars() {
+                    match encode_char::<$ty>(c) {
+                        Some(bytes) => out.extend_from_slice(&bytes),
+                        None => return Err(ReadError::BadConvert(stringify!($ty), "byte"))
+                    }
+                }
+                Ok(out)
+            }
+
+            fn encode_lossy(text: &str) -> EncodeOutcome {
+                let mut out = EncodeOutcome { bytes: Vec::with_capacity(text.len()), replacements: Vec::new() };
+                for (char_index, c) in text.chars().enumerate() {
+                    match encode_char::<$ty>(c) {
+                        Some(bytes) => out.bytes.extend_from_slice(&bytes),
+                        None => {
+                            out.bytes.push(b'_');
+                            out.replacements.push(Replacement { char_index, original: c });
+                        }
+                    }
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Folds a Latin-script letter with diacritics (or a digraph like `œ`) down
+/// to the plain ASCII letter(s) it's derived from, for [`code_page_encoding`]
+/// to retry against a code page's table when the accented char itself isn't
+/// one of its bytes. Covers the Latin-1 Supplement and Latin Extended-A
+/// accented letters; anything else returns `None`.
+fn fold_latin(c: char) -> Option<&'static str> {
+    Some(match c {
+        'À'..='Å' | 'à'..='å' => if c.is_uppercase() { "A" } else { "a" },
+        'Æ' => "AE", 'æ' => "ae",
+        'Ç' => "C", 'ç' => "c",
+        'È'..='Ë' | 'è'..='ë' => if c.is_uppercase() { "E" } else { "e" },
+        'Ì'..='Ï' | 'ì'..='ï' => if c.is_uppercase() { "I" } else { "i" },
+        'Ð' => "D", 'ð' => "d",
+        'Ñ' => "N", 'ñ' => "n",
+        'Ò'..='Ö' | 'Ø' | 'ò'..='ö' | 'ø' => if c.is_uppercase() { "O" } else { "o" },
+        'Ù'..='Ü' | 'ù'..='ü' => if c.is_uppercase() { "U" } else { "u" },
+        'Ý' | 'ý' | 'ÿ' => if c == 'Ý' { "Y" } else { "y" },
+        'Þ' => "T", 'þ' => "t",
+        'ß' => "ss",
+        // Latin Extended-A: macron/breve/ogonek/acute/caron/dot-above/stroke/etc.
+        '\u{0100}' | '\u{0102}' | '\u{0104}' => "A",
+        '\u{0101}' | '\u{0103}' | '\u{0105}' => "a",
+        '\u{0106}' | '\u{0108}' | '\u{010a}' | '\u{010c}' => "C",
+        '\u{0107}' | '\u{0109}' | '\u{010b}' | '\u{010d}' => "c",
+        '\u{010e}' | '\u{0110}' => "D",
+        '\u{010f}' | '\u{0111}' => "d",
+        '\u{0112}' | '\u{0114}' | '\u{0116}' | '\u{0118}' | '\u{011a}' => "E",
+        '\u{0113}' | '\u{0115}' | '\u{0117}' | '\u{0119}' | '\u{011b}' => "e",
+        '\u{011c}' | '\u{011e}' | '\u{0120}' | '\u{0122}' => "G",
+        '\u{011d}' | '\u{011f}' | '\u{0121}' | '\u{0123}' => "g",
+        '\u{0124}' | '\u{0126}' => "H",
+        '\u{0125}' | '\u{0127}' => "h",
+        '\u{0128}' | '\u{012a}' | '\u{012c}' | '\u{012e}' | '\u{0130}' => "I",
+        '\u{0129}' | '\u{012b}' | '\u{012d}' | '\u{012f}' | '\u{0131}' => "i",
+        '\u{0132}' => "IJ", '\u{0133}' => "ij",
+        '\u{0134}' => "J", '\u{0135}' => "j",
+        '\u{0136}' => "K", '\u{0137}' | '\u{0138}' => "k",
+        '\u{0139}' | '\u{013b}' | '\u{013d}' | '\u{013f}' | '\u{0141}' => "L",
+        '\u{013a}' | '\u{013c}' | '\u{013e}' | '\u{0140}' | '\u{0142}' => "l",
+        '\u{0143}' | '\u{0145}' | '\u{0147}' | '\u{014a}' => "N",
+        '\u{0144}' | '\u{0146}' | '\u{0148}' | '\u{0149}' | '\u{014b}' => "n",
+        '\u{014c}' | '\u{014e}' | '\u{0150}' => "O",
+        '\u{014d}' | '\u{014f}' | '\u{0151}' => "o",
+        '\u{0152}' => "OE", '\u{0153}' => "oe",
+        '\u{0154}' | '\u{0156}' | '\u{0158}' => "R",
+        '\u{0155}' | '\u{0157}' | '\u{0159}' => "r",
+        '\u{015a}' | '\u{015c}' | '\u{015e}' | '\u{0160}' => "S",
+        '\u{015b}' | '\u{015d}' | '\u{015f}' | '\u{0161}' => "s",
+        '\u{0162}' | '\u{0164}' | '\u{0166}' => "T",
+        '\u{0163}' | '\u{0165}' | '\u{0167}' => "t",
+        '\u{0168}' | '\u{016a}' | '\u{016c}' | '\u{016e}' | '\u{0170}' | '\u{0172}' => "U",
+        '\u{0169}' | '\u{016b}' | '\u{016d}' | '\u{016f}' | '\u{0171}' | '\u{0173}' => "u",
+        '\u{0174}' => "W", '\u{0175}' => "w",
+        '\u{0176}' | '\u{0178}' => "Y", '\u{0177}' => "y",
+        '\u{0179}' | '\u{017b}' | '\u{017d}' => "Z",
+        '\u{017a}' | '\u{017c}' | '\u{017e}' => "z",
+        '\u{017f}' => "s",
+        _ => return None
+    })
+}
+
+/// Builds a 256-entry code page table that's the Latin-1 identity mapping
+/// (`byte as char`) everywhere except the positions listed in `overrides` -
+/// which is all a code page like [`Cp1252`] actually needs to spell out,
+/// since its bottom half is ASCII and its `0xA0..=0xFF` half is Latin-1.
+const fn latin1_identity_with(overrides: &[(u8, Option<char>)]) -> [Option<char>; 256] {
+    let mut table = [None; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = Some(i as u8 as char);
+        i += 1;
+    }
+    let mut j = 0;
+    while j < overrides.len() {
+        let (byte, ch) = overrides[j];
+        table[byte as usize] = ch;
+        j += 1;
+    }
+    table
+}
+
+/// ISO-8859-1 - every byte is its own Unicode scalar, unlike [`Cp1252`]'s
+/// special-cased `0x80..=0x9F` range. Round-trips every byte value, so it's
+/// the encoding to reach for when a format's string table is just raw
+/// Latin-1 bytes rather than Windows' version of it.
+pub struct Latin1;
+impl CodePage for Latin1 {
+    const TABLE: [Option<char>; 256] = latin1_identity_with(&[]);
+}
+code_page_encoding!(Latin1);
+
+/// Windows-1252 ("ANSI" on Western-locale Windows) - PD2's default single-byte
+/// encoding for older localization assets.
+pub struct Cp1252;
+impl CodePage for Cp1252 {
+    const TABLE: [Option<char>; 256] = latin1_identity_with(&[
+        (0x80, Some('\u{20ac}')), (0x81, None),                 (0x82, Some('\u{201a}')), (0x83, Some('\u{0192}')),
+        (0x84, Some('\u{201e}')), (0x85, Some('\u{2026}')),     (0x86, Some('\u{2020}')), (0x87, Some('\u{2021}')),
+        (0x88, Some('\u{02c6}')), (0x89, Some('\u{2030}')),     (0x8a, Some('\u{0160}')), (0x8b, Some('\u{2039}')),
+        (0x8c, Some('\u{0152}')), (0x8d, None),                 (0x8e, Some('\u{017d}')), (0x8f, None),
+        (0x90, None),             (0x91, Some('\u{2018}')),     (0x92, Some('\u{2019}')), (0x93, Some('\u{201c}')),
+        (0x94, Some('\u{201d}')), (0x95, Some('\u{2022}')),     (0x96, Some('\u{2013}')), (0x97, Some('\u{2014}')),
+        (0x98, Some('\u{02dc}')), (0x99, Some('\u{2122}')),     (0x9a, Some('\u{0161}')), (0x9b, Some('\u{203a}')),
+        (0x9c, Some('\u{0153}')), (0x9d, None),                 (0x9e, Some('\u{017e}')), (0x9f, Some('\u{0178}')),
+    ]);
+}
+code_page_encoding!(Cp1252);
+
+/// Windows-1250 (Central European) - older Polish/Czech/Hungarian/etc. localizations.
+pub struct Cp1250;
+impl CodePage for Cp1250 {
+    const TABLE: [Option<char>; 256] = latin1_identity_with(&[
+        (0x80, Some('\u{20ac}')), (0x81, None),                 (0x82, Some('\u{201a}')), (0x83, None),
+        (0x84, Some('\u{201e}')), (0x85, Some('\u{2026}')),     (0x86, Some('\u{2020}')), (0x87, Some('\u{2021}')),
+        (0x88, None),             (0x89, Some('\u{2030}')),     (0x8a, Some('\u{0160}')), (0x8b, Some('\u{2039}')),
+        (0x8c, Some('\u{015a}')), (0x8d, Some('\u{0164}')),     (0x8e, Some('\u{017d}')), (0x8f, Some('\u{0179}')),
+        (0x90, None),             (0x91, Some('\u{2018}')),     (0x92, Some('\u{2019}')), (0x93, Some('\u{201c}')),
+        (0x94, Some('\u{201d}')), (0x95, Some('\u{2022}')),     (0x96, Some('\u{2013}')), (0x97, Some('\u{2014}')),
+        (0x98, None),             (0x99, Some('\u{2122}')),     (0x9a, Some('\u{0161}')), (0x9b, Some('\u{203a}')),
+        (0x9c, Some('\u{015b}')), (0x9d, Some('\u{0165}')),     (0x9e, Some('\u{017e}')), (0x9f, Some('\u{017a}')),
+        (0xa0, Some('\u{00a0}')), (0xa1, Some('\u{02c7}')),     (0xa2, Some('\u{02d8}')), (0xa3, Some('\u{0141}')),
+        (0xa4, Some('\u{00a4}')), (0xa5, Some('\u{0104}')),     (0xa6, Some('\u{00a6}')), (0xa7, Some('\u{00a7}')),
+        (0xa8, Some('\u{00a8}')), (0xa9, Some('\u{00a9}')),     (0xaa, Some('\u{015e}')), (0xab, Some('\u{00ab}')),
+        (0xac, Some('\u{00ac}')), (0xad, Some('\u{00ad}')),     (0xae, Some('\u{00ae}')), (0xaf, Some('\u{017b}')),
+        (0xb0, Some('\u{00b0}')), (0xb1, Some('\u{00b1}')),     (0xb2, Some('\u{02db}')), (0xb3, Some('\u{0142}')),
+        (0xb4, Some('\u{00b4}')), (0xb5, Some('\u{00b5}')),     (0xb6, Some('\u{00b6}')), (0xb7, Some('\u{00b7}')),
+        (0xb8, Some('\u{00b8}')), (0xb9, Some('\u{0105}')),     (0xba, Some('\u{015f}')), (0xbb, Some('\u{00bb}')),
+        (0xbc, Some('\u{013d}')), (0xbd, Some('\u{02dd}')),     (0xbe, Some('\u{013e}')), (0xbf, Some('\u{017c}')),
+        (0xc0, Some('\u{0154}')), (0xc1, Some('\u{00c1}')),     (0xc2, Some('\u{00c2}')), (0xc3, Some('\u{0102}')),
+        (0xc4, Some('\u{00c4}')), (0xc5, Some('\u{0139}')),     (0xc6, Some('\u{0106}')), (0xc7, Some('\u{00c7}')),
+        (0xc8, Some('\u{010c}')), (0xc9, Some('\u{00c9}')),     (0xca, Some('\u{0118}')), (0xcb, Some('\u{00cb}')),
+        (0xcc, Some('\u{011a}')), (0xcd, Some('\u{00cd}')),     (0xce, Some('\u{00ce}')), (0xcf, Some('\u{010e}')),
+        (0xd0, Some('\u{0110}')), (0xd1, Some('\u{0143}')),     (0xd2, Some('\u{0147}')), (0xd3, Some('\u{00d3}')),
+        (0xd4, Some('\u{00d4}')), (0xd5, Some('\u{0150}')),     (0xd6, Some('\u{00d6}')), (0xd7, Some('\u{00d7}')),
+        (0xd8, Some('\u{0158}')), (0xd9, Some('\u{016e}')),     (0xda, Some('\u{00da}')), (0xdb, Some('\u{0170}')),
+        (0xdc, Some('\u{00dc}')), (0xdd, Some('\u{00dd}')),     (0xde, Some('\u{0162}')), (0xdf, Some('\u{00df}')),
+        (0xe0, Some('\u{0155}')), (0xe1, Some('\u{00e1}')),     (0xe2, Some('\u{00e2}')), (0xe3, Some('\u{0103}')),
+        (0xe4, Some('\u{00e4}')), (0xe5, Some('\u{013a}')),     (0xe6, Some('\u{0107}')), (0xe7, Some('\u{00e7}')),
+        (0xe8, Some('\u{010d}')), (0xe9, Some('\u{00e9}')),     (0xea, Some('\u{0119}')), (0xeb, Some('\u{00eb}')),
+        (0xec, Some('\u{011b}')), (0xed, Some('\u{00ed}')),     (0xee, Some('\u{00ee}')), (0xef, Some('\u{010f}')),
+        (0xf0, Some('\u{0111}')), (0xf1, Some('\u{0144}')),     (0xf2, Some('\u{0148}')), (0xf3, Some('\u{00f3}')),
+        (0xf4, Some('\u{00f4}')), (0xf5, Some('\u{0151}')),     (0xf6, Some('\u{00f6}')), (0xf7, Some('\u{00f7}')),
+        (0xf8, Some('\u{0159}')), (0xf9, Some('\u{016f}')),     (0xfa, Some('\u{00fa}')), (0xfb, Some('\u{0171}')),
+        (0xfc, Some('\u{00fc}')), (0xfd, Some('\u{00fd}')),     (0xfe, Some('\u{0163}')), (0xff, Some('\u{02d9}')),
+    ]);
+}
+code_page_encoding!(Cp1250);
+
+/// Windows-1251 (Cyrillic) - older Russian/Bulgarian/etc. localizations.
+pub struct Cp1251;
+impl CodePage for Cp1251 {
+    const TABLE: [Option<char>; 256] = latin1_identity_with(&[
+        (0x80, Some('\u{0402}')), (0x81, Some('\u{0403}')),     (0x82, Some('\u{201a}')), (0x83, Some('\u{0453}')),
+        (0x84, Some('\u{201e}')), (0x85, Some('\u{2026}')),     (0x86, Some('\u{2020}')), (0x87, Some('\u{2021}')),
+        (0x88, Some('\u{20ac}')), (0x89, Some('\u{2030}')),     (0x8a, Some('\u{0409}')), (0x8b, Some('\u{2039}')),
+        (0x8c, Some('\u{040a}')), (0x8d, Some('\u{040c}')),     (0x8e, Some('\u{040b}')), (0x8f, Some('\u{040f}')),
+        (0x90, Some('\u{0452}')), (0x91, Some('\u{2018}')),     (0x92, Some('\u{2019}')), (0x93, Some('\u{201c}')),
+        (0x94, Some('\u{201d}')), (0x95, Some('\u{2022}')),     (0x96, Some('\u{2013}')), (0x97, Some('\u{2014}')),
+        (0x98, None),             (0x99, Some('\u{2122}')),     (0x9a, Some('\u{0459}')), (0x9b, Some('\u{203a}')),
+        (0x9c, Some('\u{045a}')), (0x9d, Some('\u{045c}')),     (0x9e, Some('\u{045b}')), (0x9f, Some('\u{045f}')),
+        (0xa0, Some('\u{00a0}')), (0xa1, Some('\u{040e}')),     (0xa2, Some('\u{045e}')), (0xa3, Some('\u{0408}')),
+        (0xa4, Some('\u{00a4}')), (0xa5, Some('\u{0490}')),     (0xa6, Some('\u{00a6}')), (0xa7, Some('\u{00a7}')),
+        (0xa8, Some('\u{0401}')), (0xa9, Some('\u{00a9}')),     (0xaa, Some('\u{0404}')), (0xab, Some('\u{00ab}')),
+        (0xac, Some('\u{00ac}')), (0xad, Some('\u{00ad}')),     (0xae, Some('\u{00ae}')), (0xaf, Some('\u{0407}')),
+        (0xb0, Some('\u{00b0}')), (0xb1, Some('\u{00b1}')),     (0xb2, Some('\u{0406}')), (0xb3, Some('\u{0456}')),
+        (0xb4, Some('\u{0491}')), (0xb5, Some('\u{00b5}')),     (0xb6, Some('\u{00b6}')), (0xb7, Some('\u{00b7}')),
+        (0xb8, Some('\u{0451}')), (0xb9, Some('\u{2116}')),     (0xba, Some('\u{0454}')), (0xbb, Some('\u{00bb}')),
+        (0xbc, Some('\u{0458}')), (0xbd, Some('\u{0405}')),     (0xbe, Some('\u{0455}')), (0xbf, Some('\u{0457}')),
+        (0xc0, Some('\u{0410}')), (0xc1, Some('\u{0411}')),     (0xc2, Some('\u{0412}')), (0xc3, Some('\u{0413}')),
+        (0xc4, Some('\u{0414}')), (0xc5, Some('\u{0415}')),     (0xc6, Some('\u{0416}')), (0xc7, Some('\u{0417}')),
+        (0xc8, Some('\u{0418}')), (0xc9, Some('\u{0419}')),     (0xca, Some('\u{041a}')), (0xcb, Some('\u{041b}')),
+        (0xcc, Some('\u{041c}')), (0xcd, Some('\u{041d}')),     (0xce, Some('\u{041e}')), (0xcf, Some('\u{041f}')),
+        (0xd0, Some('\u{0420}')), (0xd1, Some('\u{0421}')),     (0xd2, Some('\u{0422}')), (0xd3, Some('\u{0423}')),
+        (0xd4, Some('\u{0424}')), (0xd5, Some('\u{0425}')),     (0xd6, Some('\u{0426}')), (0xd7, Some('\u{0427}')),
+        (0xd8, Some('\u{0428}')), (0xd9, Some('\u{0429}')),     (0xda, Some('\u{042a}')), (0xdb, Some('\u{042b}')),
+        (0xdc, Some('\u{042c}')), (0xdd, Some('\u{042d}')),     (0xde, Some('\u{042e}')), (0xdf, Some('\u{042f}')),
+        (0xe0, Some('\u{0430}')), (0xe1, Some('\u{0431}')),     (0xe2, Some('\u{0432}')), (0xe3, Some('\u{0433}')),
+        (0xe4, Some('\u{0434}')), (0xe5, Some('\u{0435}')),     (0xe6, Some('\u{0436}')), (0xe7, Some('\u{0437}')),
+        (0xe8, Some('\u{0438}')), (0xe9, Some('\u{0439}')),     (0xea, Some('\u{043a}')), (0xeb, Some('\u{043b}')),
+        (0xec, Some('\u{043c}')), (0xed, Some('\u{043d}')),     (0xee, Some('\u{043e}')), (0xef, Some('\u{043f}')),
+        (0xf0, Some('\u{0440}')), (0xf1, Some('\u{0441}')),     (0xf2, Some('\u{0442}')), (0xf3, Some('\u{0443}')),
+        (0xf4, Some('\u{0444}')), (0xf5, Some('\u{0445}')),     (0xf6, Some('\u{0446}')), (0xf7, Some('\u{0447}')),
+        (0xf8, Some('\u{0448}')), (0xf9, Some('\u{0449}')),     (0xfa, Some('\u{044a}')), (0xfb, Some('\u{044b}')),
+        (0xfc, Some('\u{044c}')), (0xfd, Some('\u{044d}')),     (0xfe, Some('\u{044e}')), (0xff, Some('\u{044f}')),
+    ]);
+}
+code_page_encoding!(Cp1251);
+
+/// Wraps a [`CodePage`] `C` so any char it can't represent - and any literal
+/// `&`, so the result is unambiguous to parse back - is escaped as an ASCII
+/// numeric character reference (`&#x4e2d;`, lowercase hex), HTML-style,
+/// instead of lost. Unlike [`Encoding::encode_lossy`], this never loses data:
+/// [`Entities::decode`] recognizes and re-expands those references, so
+/// strings that must survive a round trip through a single-byte string table
+/// (non-Latin scripts, CJK, emoji) can still be stored in it.
+pub struct Entities<C>(PhantomData<C>);
+impl<C: CodePage> Encoding for Entities<C> {
+    fn decode(bytes: &[u8]) -> Result<String, ReadError> {
+        let mut out = String::with_capacity(bytes.len());
+        let mut rest = bytes;
+        while let Some(&b) = rest.first() {
+            if let Some((c, len)) = decode_entity(rest) {
+                out.push(c);
+                rest = &rest[len..];
+                continue;
+            }
+            let c = <C as CodePage>::TABLE[b as usize]
+                .ok_or(ReadError::BadConvert("byte", "Entities"))?;
+            out.push(c);
+            rest = &rest[1..];
+        }
+        Ok(out)
+    }
+
+    fn encode(text: &str) -> Result<Vec<u8>, ReadError> {
+        // Entities never fails to represent a char - the worst case is an
+        // escape - so the lossy and strict paths coincide.
+        Ok(Self::encode_lossy(text).bytes)
+    }
+
+    fn encode_lossy(text: &str) -> EncodeOutcome {
+        let mut bytes = Vec::with_capacity(text.len());
+        for c in text.chars() {
+            if c == '&' || encode_char::<C>(c).is_none() {
+                bytes.extend_from_slice(format!("&#x{:x};", c as u32).as_bytes());
+            }
+            else {
+                bytes.extend_from_slice(&encode_char::<C>(c).unwrap());
+            }
+        }
+        EncodeOutcome { bytes, replacements: Vec::new() }
+    }
+}
+
+/// If `bytes` starts with a numeric character reference (`&#x` + hex digits
+/// + `;`), returns the char it refers to and the reference's length in
+/// bytes. Used by [`Entities::decode`] to undo [`Entities::encode`]'s
+/// escaping.
+fn decode_entity(bytes: &[u8]) -> Option<(char, usize)> {
+    let rest = bytes.strip_prefix(b"&#x")?;
+    let end = rest.iter().position(|&b| b == b';')?;
+    let hex = std::str::from_utf8(&rest[..end]).ok()?;
+    let codepoint = u32::from_str_radix(hex, 16).ok()?;
+    let c = char::from_u32(codepoint)?;
+    Some((c, 3 + end + 1))
+}
+
+/// Picks [`Latin1`] or [`Cp1252`] at runtime, for callers that don't know
+/// which one a string table uses until they've read a flag out of the file
+/// itself (a header byte, a format version). Everywhere the encoding is
+/// known up front, parameterize over [`Encoding`] directly instead (e.g.
+/// `NullTerminatedString<Cp1252>`) rather than paying for this dispatch.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StringEncoding {
+    Iso8859_1,
+    Cp1252,
+}
+impl StringEncoding {
+    pub fn decode(self, bytes: &[u8]) -> Result<String, ReadError> {
+        match self {
+            StringEncoding::Iso8859_1 => Latin1::decode(bytes),
+            StringEncoding::Cp1252 => Cp1252::decode(bytes),
+        }
+    }
+
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, ReadError> {
+        match self {
+            StringEncoding::Iso8859_1 => Latin1::encode(text),
+            StringEncoding::Cp1252 => Cp1252::encode(text),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::ops::{Index, IndexMut};
+
+/// A gap this large in a single [`IndexSlab::insert`] tips the slab over into its sparse
+/// [`HashMap`] fallback rather than padding the gap with `None`.
+const MAX_DENSE_GAP: usize = 1 << 16;
+
+/// Store for values keyed by small, mostly-contiguous `u32` ids - FDM section ids and the like -
+/// so lookups resolve with a direct array index instead of a hash probe. Ids that would blow the
+/// backing array out disproportionately (a handful of entries scattered across a huge id range)
+/// tip the slab over to a sparse `HashMap` instead of padding the gap with `None`.
+pub enum IndexSlab<T> {
+    Dense(Vec<Option<T>>),
+    Sparse(HashMap<u32, T>)
+}
+
+impl<T> IndexSlab<T> {
+    pub fn new() -> IndexSlab<T> {
+        IndexSlab::Dense(Vec::new())
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn get(&self, id: u32) -> Option<&T> {
+        match self {
+            IndexSlab::Dense(v) => v.get(id as usize).and_then(Option::as_ref),
+            IndexSlab::Sparse(m) => m.get(&id)
+        }
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut T> {
+        match self {
+            IndexSlab::Dense(v) => v.get_mut(id as usize).and_then(Option::as_mut),
+            IndexSlab::Sparse(m) => m.get_mut(&id)
+        }
+    }
+
+    pub fn insert(&mut self, id: u32, value: T) {
+        if let IndexSlab::Dense(v) = self {
+            let idx = id as usize;
+            if idx >= v.len() && idx - v.len() > MAX_DENSE_GAP {
+                let sparse = v.drain(..).enumerate()
+                    .filter_map(|(i, x)| x.map(|x| (i as u32, x)))
+                    .collect();
+                *self = IndexSlab::Sparse(sparse);
+            }
+        }
+
+        match self {
+            IndexSlab::Dense(v) => {
+                let idx = id as usize;
+                if idx >= v.len() {
+                    v.resize_with(idx + 1, || None);
+                }
+                v[idx] = Some(value);
+            },
+            IndexSlab::Sparse(m) => { m.insert(id, value); }
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (u32, &T)> + '_> {
+        match self {
+            IndexSlab::Dense(v) => Box::new(v.iter().enumerate().filter_map(|(i, x)| x.as_ref().map(|x| (i as u32, x)))),
+            IndexSlab::Sparse(m) => Box::new(m.iter().map(|(&k, v)| (k, v)))
+        }
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> + '_ {
+        self.iter().map(|(_, v)| v)
+    }
+}
+
+impl<T> Default for IndexSlab<T> {
+    fn default() -> Self {
+        IndexSlab::new()
+    }
+}
+
+impl<'a, T> From<&'a HashMap<u32, T>> for IndexSlab<&'a T> {
+    fn from(src: &'a HashMap<u32, T>) -> Self {
+        let mut slab = IndexSlab::new();
+        for (&k, v) in src {
+            slab.insert(k, v);
+        }
+        slab
+    }
+}
+
+impl<T> Index<u32> for IndexSlab<T> {
+    type Output = T;
+    fn index(&self, id: u32) -> &T {
+        self.get(id).unwrap_or_else(|| panic!("IndexSlab: no entry for id {}", id))
+    }
+}
+
+impl<T> IndexMut<u32> for IndexSlab<T> {
+    fn index_mut(&mut self, id: u32) -> &mut T {
+        self.get_mut(id).unwrap_or_else(|| panic!("IndexSlab: no entry for id {}", id))
+    }
+}
@@ -0,0 +1,59 @@
+//! A small string interner: each distinct string is stored once, in a
+//! shared arena, and handed back as a cheap `Copy` [`Sym`] instead of a
+//! fresh allocation. Useful anywhere the same handful of strings (table
+//! keys, metatable names, and the like) recur across many parses - dedup
+//! it once here instead of paying an `Rc<str>` comparison and possible
+//! allocation per occurrence.
+
+use std::rc::Rc;
+
+use fnv::FnvHashMap;
+
+/// A handle into an [`Interner`]'s arena. Only meaningful relative to the
+/// `Interner` that produced it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Sym(u32);
+
+#[derive(Default)]
+pub struct Interner {
+    arena: Vec<Rc<str>>,
+    by_string: FnvHashMap<Rc<str>, Sym>
+}
+
+impl Interner {
+    pub fn new() -> Interner { Default::default() }
+
+    /// Interns `text`, returning its existing `Sym` if this interner has
+    /// already seen an identical string, or allocating a new arena slot
+    /// (and `Rc<str>`) for it otherwise.
+    pub fn intern(&mut self, text: &str) -> Sym {
+        if let Some(&sym) = self.by_string.get(text) {
+            return sym;
+        }
+        let rc: Rc<str> = Rc::from(text);
+        let sym = Sym(self.arena.len() as u32);
+        self.arena.push(rc.clone());
+        self.by_string.insert(rc, sym);
+        sym
+    }
+
+    pub fn resolve(&self, sym: Sym) -> &str {
+        &self.arena[sym.0 as usize]
+    }
+
+    /// Like [`Self::resolve`], but hands back the interner's own `Rc<str>`
+    /// rather than a borrow tied to `&self` - for callers (like
+    /// [`crate::formats::scriptdata::document::Document`]) that want to
+    /// hold on to the string themselves.
+    pub fn resolve_rc(&self, sym: Sym) -> Rc<str> {
+        self.arena[sym.0 as usize].clone()
+    }
+
+    /// [`Self::intern`] followed by [`Self::resolve_rc`], for callers that
+    /// want the shared `Rc<str>` immediately and have no use for the `Sym`
+    /// itself.
+    pub fn intern_rc(&mut self, text: &str) -> Rc<str> {
+        let sym = self.intern(text);
+        self.resolve_rc(sym)
+    }
+}
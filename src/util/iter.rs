@@ -16,6 +16,7 @@ where
     func_two: TF2,
     iter_one: TI1,
     iter_two: TI2,
+    current: Option<TInItem>,
     state: FlatMapChainState
 }
 
@@ -26,17 +27,72 @@ enum FlatMapChainState {
     Done
 }
 
+impl<TIn, TInItem, TOut, TF1, TF2, TI1, TI2> FlatMapChain<TIn, TInItem, TOut, TF1, TF2, TI1, TI2>
+where
+    TIn: Iterator<Item=TInItem>,
+    TInItem: Clone,
+    TF1: FnMut(TIn::Item) -> Option<TI1>,
+    TF2: FnMut(TIn::Item) -> Option<TI2>,
+    TI1: Iterator<Item=TOut> + Default,
+    TI2: Iterator<Item=TOut> + Default
+{
+    /// For each item `source` yields, concatenates whatever `func_one` and
+    /// then `func_two` produce for it (skipping either when it returns
+    /// `None`), before moving on to the next source item.
+    pub fn new(source: TIn, func_one: TF1, func_two: TF2) -> Self {
+        FlatMapChain {
+            source,
+            func_one,
+            func_two,
+            iter_one: TI1::default(),
+            iter_two: TI2::default(),
+            current: None,
+            state: FlatMapChainState::Next
+        }
+    }
+}
+
 impl<TIn, TInItem, TOut, TF1, TF2, TI1, TI2> Iterator for FlatMapChain<TIn, TInItem, TOut, TF1, TF2, TI1, TI2>
 where
     TIn: Iterator<Item=TInItem>,
     TInItem: Clone,
     TF1: FnMut(TIn::Item) -> Option<TI1>,
     TF2: FnMut(TIn::Item) -> Option<TI2>,
-    TI1: Iterator<Item=TOut>,
-    TI2: Iterator<Item=TOut>
+    TI1: Iterator<Item=TOut> + Default,
+    TI2: Iterator<Item=TOut> + Default
 {
     type Item = TOut;
     fn next(&mut self) -> Option<Self::Item> {
-        None
+        loop {
+            match self.state {
+                FlatMapChainState::Next => {
+                    let item = match self.source.next() {
+                        Some(item) => item,
+                        None => {
+                            self.state = FlatMapChainState::Done;
+                            return None;
+                        }
+                    };
+                    self.iter_one = (self.func_one)(item.clone()).unwrap_or_default();
+                    self.current = Some(item);
+                    self.state = FlatMapChainState::YieldOne;
+                },
+                FlatMapChainState::YieldOne => {
+                    if let Some(v) = self.iter_one.next() {
+                        return Some(v);
+                    }
+                    let item = self.current.take().expect("FlatMapChain: lost current item");
+                    self.iter_two = (self.func_two)(item).unwrap_or_default();
+                    self.state = FlatMapChainState::YieldTwo;
+                },
+                FlatMapChainState::YieldTwo => {
+                    if let Some(v) = self.iter_two.next() {
+                        return Some(v);
+                    }
+                    self.state = FlatMapChainState::Next;
+                },
+                FlatMapChainState::Done => return None
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,302 @@
+//! Lets a Lua script describe a binary record layout at runtime and read/write it, instead of
+//! every format needing a compiled-in Rust type. PD2 modding is Lua-centric, so this is the
+//! escape hatch for a mod that wants to round-trip some small file of its own without forcing a
+//! recompile of the tool: the script builds a [`RecordSchema`] from a table of
+//! `{name, type_token}` pairs, then calls [`RecordSchema::read`]/[`RecordSchema::write`] on a
+//! Lua string standing in for the byte stream (mlua strings are just byte buffers, so this needs
+//! no separate stream/handle userdata).
+//!
+//! A type token is either a bare string (`"u32"`, `"f32"`, `"vec3f"`, ...) or, for the
+//! parameterized readers, a table whose first element names the kind and whose remaining
+//! elements are its arguments: `{"counted_string", "u16"}` is a `CountedString<u16>`,
+//! `{"array", "u8", 16}` is 16 back-to-back `u8`s.
+//!
+//! Internally every token resolves to a [`DynItemReader`] - an object-safe stand-in for
+//! [`ItemReader`], which can't be a trait object itself since its methods are generic over the
+//! stream type. [`DynReader`] closes that gap the usual way: it's a concrete, `Sized` newtype
+//! around a `&mut dyn BufRead`, so it still picks up the blanket [`ReadExt`] impl and can be
+//! passed to [`ItemReader::read_from_stream`] like any other reader.
+
+use std::io::{BufRead, Cursor, Read, Write};
+use std::rc::Rc;
+
+use mlua::{Lua, Table, UserData, UserDataMethods, Value as LuaValue};
+
+use super::binaryreader::{CountedString, ReadError, ReadExt, WriteExt};
+
+/// Wraps a `&mut dyn BufRead` so it's `Sized` again and can stand in for the generic `R` that
+/// [`ItemReader::read_from_stream`] expects, letting a boxed [`DynItemReader`] still bottom out
+/// in the ordinary static reader machinery.
+pub struct DynReader<'a> {
+    inner: &'a mut dyn BufRead
+}
+impl<'a> Read for DynReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+impl<'a> BufRead for DynReader<'a> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+/// A value read or to be written by a runtime-described record, kept to the shapes Lua itself
+/// distinguishes (`mlua` already collapses every Rust integer width to its own `Integer`, so
+/// there's no point keeping them apart here either).
+#[derive(Debug, Clone)]
+pub enum DynValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Array(Vec<DynValue>)
+}
+impl DynValue {
+    fn into_lua(self, lua: &Lua) -> mlua::Result<LuaValue> {
+        Ok(match self {
+            DynValue::Int(i) => LuaValue::Integer(i),
+            DynValue::Float(f) => LuaValue::Number(f),
+            DynValue::Bool(b) => LuaValue::Boolean(b),
+            DynValue::Str(s) => LuaValue::String(lua.create_string(&s)?),
+            DynValue::Array(items) => {
+                let t = lua.create_table()?;
+                for (i, item) in items.into_iter().enumerate() {
+                    t.set(i + 1, item.into_lua(lua)?)?;
+                }
+                LuaValue::Table(t)
+            }
+        })
+    }
+
+    fn from_lua(v: LuaValue) -> mlua::Result<DynValue> {
+        Ok(match v {
+            LuaValue::Integer(i) => DynValue::Int(i),
+            LuaValue::Number(f) => DynValue::Float(f),
+            LuaValue::Boolean(b) => DynValue::Bool(b),
+            LuaValue::String(s) => DynValue::Str(s.to_str()?.to_owned()),
+            LuaValue::Table(t) => {
+                let mut items = Vec::new();
+                for item in t.sequence_values::<LuaValue>() {
+                    items.push(DynValue::from_lua(item?)?);
+                }
+                DynValue::Array(items)
+            },
+            other => return Err(mlua::Error::FromLuaConversionError {
+                from: other.type_name(),
+                to: "DynValue",
+                message: Some("expected a number, string, boolean or table".into())
+            })
+        })
+    }
+}
+
+/// Object-safe stand-in for [`ItemReader`], so a type token parsed at runtime can be boxed and
+/// stored in a [`RecordSchema`] instead of needing a distinct monomorphization per field.
+trait DynItemReader {
+    fn dyn_read(&self, stream: &mut DynReader) -> Result<DynValue, ReadError>;
+    fn dyn_write(&self, stream: &mut dyn Write, value: &DynValue) -> Result<(), ReadError>;
+}
+
+macro_rules! scalar_reader {
+    ($name:ident, $ty:ty, $from:expr, $to:expr) => {
+        struct $name;
+        impl DynItemReader for $name {
+            fn dyn_read(&self, stream: &mut DynReader) -> Result<DynValue, ReadError> {
+                let v = stream.read_item_as::<$ty>()?;
+                Ok($from(v))
+            }
+            fn dyn_write(&self, mut stream: &mut dyn Write, value: &DynValue) -> Result<(), ReadError> {
+                let v = $to(value)?;
+                stream.write_item_as::<$ty>(&v)
+            }
+        }
+    }
+}
+
+fn expect_int(v: &DynValue) -> Result<i64, ReadError> {
+    match v { DynValue::Int(i) => Ok(*i), _ => Err(ReadError::BadConvert("DynValue", "integer")) }
+}
+fn expect_float(v: &DynValue) -> Result<f64, ReadError> {
+    match v { DynValue::Float(f) => Ok(*f), DynValue::Int(i) => Ok(*i as f64), _ => Err(ReadError::BadConvert("DynValue", "float")) }
+}
+fn expect_bool(v: &DynValue) -> Result<bool, ReadError> {
+    match v { DynValue::Bool(b) => Ok(*b), _ => Err(ReadError::BadConvert("DynValue", "bool")) }
+}
+
+scalar_reader!(U8Reader, u8, |v: u8| DynValue::Int(v as i64), |v: &DynValue| expect_int(v).map(|i| i as u8));
+scalar_reader!(U16Reader, u16, |v: u16| DynValue::Int(v as i64), |v: &DynValue| expect_int(v).map(|i| i as u16));
+scalar_reader!(U32Reader, u32, |v: u32| DynValue::Int(v as i64), |v: &DynValue| expect_int(v).map(|i| i as u32));
+scalar_reader!(U64Reader, u64, |v: u64| DynValue::Int(v as i64), |v: &DynValue| expect_int(v).map(|i| i as u64));
+scalar_reader!(I8Reader, i8, |v: i8| DynValue::Int(v as i64), |v: &DynValue| expect_int(v).map(|i| i as i8));
+scalar_reader!(I16Reader, i16, |v: i16| DynValue::Int(v as i64), |v: &DynValue| expect_int(v).map(|i| i as i16));
+scalar_reader!(I32Reader, i32, |v: i32| DynValue::Int(v as i64), |v: &DynValue| expect_int(v).map(|i| i as i32));
+scalar_reader!(I64Reader, i64, |v: i64| DynValue::Int(v), |v: &DynValue| expect_int(v));
+scalar_reader!(F32Reader, f32, |v: f32| DynValue::Float(v as f64), |v: &DynValue| expect_float(v).map(|f| f as f32));
+scalar_reader!(F64Reader, f64, |v: f64| DynValue::Float(v), |v: &DynValue| expect_float(v));
+scalar_reader!(BoolReader, bool, DynValue::Bool, expect_bool);
+
+/// `{"counted_string", "u8"|"u16"|"u32"|"u64"}` - a [`CountedString`] whose length prefix is
+/// whichever integer width the script asked for.
+struct CountedStringReader { width: IntWidth }
+#[derive(Clone, Copy)]
+enum IntWidth { U8, U16, U32, U64 }
+impl DynItemReader for CountedStringReader {
+    fn dyn_read(&self, stream: &mut DynReader) -> Result<DynValue, ReadError> {
+        let s = match self.width {
+            IntWidth::U8 => stream.read_item_as::<CountedString<u8>>()?,
+            IntWidth::U16 => stream.read_item_as::<CountedString<u16>>()?,
+            IntWidth::U32 => stream.read_item_as::<CountedString<u32>>()?,
+            IntWidth::U64 => stream.read_item_as::<CountedString<u64>>()?
+        };
+        Ok(DynValue::Str(s))
+    }
+    fn dyn_write(&self, mut stream: &mut dyn Write, value: &DynValue) -> Result<(), ReadError> {
+        let s = match value { DynValue::Str(s) => s.clone(), _ => return Err(ReadError::BadConvert("DynValue", "string")) };
+        match self.width {
+            IntWidth::U8 => stream.write_item_as::<CountedString<u8>>(&s),
+            IntWidth::U16 => stream.write_item_as::<CountedString<u16>>(&s),
+            IntWidth::U32 => stream.write_item_as::<CountedString<u32>>(&s),
+            IntWidth::U64 => stream.write_item_as::<CountedString<u64>>(&s)
+        }
+    }
+}
+
+/// `{"array", <inner token>, <count>}` - `count` back-to-back reads of `inner`.
+struct ArrayReader { inner: Rc<dyn DynItemReader>, count: usize }
+impl DynItemReader for ArrayReader {
+    fn dyn_read(&self, stream: &mut DynReader) -> Result<DynValue, ReadError> {
+        let mut items = Vec::with_capacity(self.count.min(1024));
+        for _ in 0..self.count {
+            items.push(self.inner.dyn_read(stream)?);
+        }
+        Ok(DynValue::Array(items))
+    }
+    fn dyn_write(&self, stream: &mut dyn Write, value: &DynValue) -> Result<(), ReadError> {
+        let items = match value { DynValue::Array(a) => a, _ => return Err(ReadError::BadConvert("DynValue", "array")) };
+        if items.len() != self.count {
+            return Err(ReadError::Schema("array length doesn't match the declared count"));
+        }
+        for item in items {
+            self.inner.dyn_write(stream, item)?;
+        }
+        Ok(())
+    }
+}
+
+/// `vec3f`/`vec4f` - a fixed-length `f32` array, spelled as its own token since it's by far the
+/// commonest shape in PD2's formats.
+fn vecf_reader(len: usize) -> Rc<dyn DynItemReader> {
+    Rc::new(ArrayReader { inner: Rc::new(F32Reader), count: len })
+}
+
+fn parse_token(token: &LuaValue) -> mlua::Result<Rc<dyn DynItemReader>> {
+    match token {
+        LuaValue::String(s) => {
+            let name = s.to_str()?;
+            Ok(match name {
+                "u8" => Rc::new(U8Reader),
+                "u16" => Rc::new(U16Reader),
+                "u32" => Rc::new(U32Reader),
+                "u64" => Rc::new(U64Reader),
+                "i8" => Rc::new(I8Reader),
+                "i16" => Rc::new(I16Reader),
+                "i32" => Rc::new(I32Reader),
+                "i64" => Rc::new(I64Reader),
+                "f32" => Rc::new(F32Reader),
+                "f64" => Rc::new(F64Reader),
+                "bool" => Rc::new(BoolReader),
+                "vec2f" => return Ok(vecf_reader(2)),
+                "vec3f" => return Ok(vecf_reader(3)),
+                "vec4f" => return Ok(vecf_reader(4)),
+                other => return Err(mlua::Error::RuntimeError(format!("unknown type token '{}'", other)))
+            })
+        },
+        LuaValue::Table(t) => {
+            let kind: String = t.get(1)?;
+            match kind.as_str() {
+                "counted_string" => {
+                    let width_token: String = t.get(2)?;
+                    let width = match width_token.as_str() {
+                        "u8" => IntWidth::U8,
+                        "u16" => IntWidth::U16,
+                        "u32" => IntWidth::U32,
+                        "u64" => IntWidth::U64,
+                        other => return Err(mlua::Error::RuntimeError(format!("counted_string length must be an integer width, not '{}'", other)))
+                    };
+                    Ok(Rc::new(CountedStringReader { width }))
+                },
+                "array" => {
+                    let inner_token: LuaValue = t.get(2)?;
+                    let count: usize = t.get(3)?;
+                    let inner = parse_token(&inner_token)?;
+                    Ok(Rc::new(ArrayReader { inner, count }))
+                },
+                other => Err(mlua::Error::RuntimeError(format!("unknown type token kind '{}'", other)))
+            }
+        },
+        other => Err(mlua::Error::RuntimeError(format!("type token must be a string or table, got {}", other.type_name())))
+    }
+}
+
+/// A record layout described from Lua as an ordered list of `{name, type_token}` pairs,
+/// exposed back to Lua as userdata with `read`/`write` methods. This is the whole point of the
+/// module: it lets a mod declare a binary format and parse it without the tool being recompiled
+/// to add a matching Rust struct.
+pub struct RecordSchema {
+    fields: Vec<(String, Rc<dyn DynItemReader>)>
+}
+impl RecordSchema {
+    fn from_lua_table(table: &Table) -> mlua::Result<RecordSchema> {
+        let mut fields = Vec::new();
+        for pair in table.sequence_values::<Table>() {
+            let pair = pair?;
+            let name: String = pair.get(1)?;
+            let token: LuaValue = pair.get(2)?;
+            fields.push((name, parse_token(&token)?));
+        }
+        Ok(RecordSchema { fields })
+    }
+
+    fn read(&self, lua: &Lua, bytes: &[u8]) -> mlua::Result<Table> {
+        let mut cursor = Cursor::new(bytes);
+        let mut stream = DynReader { inner: &mut cursor };
+        let result = lua.create_table()?;
+        for (name, reader) in &self.fields {
+            let value = reader.dyn_read(&mut stream).map_err(|e| mlua::Error::RuntimeError(e.to_string()))?;
+            result.set(name.as_str(), value.into_lua(lua)?)?;
+        }
+        Ok(result)
+    }
+
+    fn write(&self, record: &Table) -> mlua::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        for (name, writer) in &self.fields {
+            let lua_value: LuaValue = record.get(name.as_str())?;
+            let value = DynValue::from_lua(lua_value)?;
+            writer.dyn_write(&mut out, &value).map_err(|e| mlua::Error::RuntimeError(e.to_string()))?;
+        }
+        Ok(out)
+    }
+}
+impl UserData for RecordSchema {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("read", |lua, this, bytes: mlua::String| {
+            this.read(lua, bytes.as_bytes())
+        });
+        methods.add_method("write", |lua, this, record: Table| {
+            let bytes = this.write(&record)?;
+            lua.create_string(&bytes)
+        });
+    }
+}
+
+/// Installs the `define_record(fields)` global a script uses to build a [`RecordSchema`].
+pub fn register(lua: &Lua) -> mlua::Result<()> {
+    let define_record = lua.create_function(|_, fields: Table| RecordSchema::from_lua_table(&fields))?;
+    lua.globals().set("define_record", define_record)?;
+    Ok(())
+}
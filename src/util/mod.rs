@@ -2,6 +2,16 @@ pub mod ordered_float;
 pub mod read_helpers;
 pub mod rc_cell;
 pub mod binaryreader;
+pub mod encoding;
+pub mod hyperloglog;
+pub mod iter;
+pub mod parse_helpers;
+pub mod dynamic;
+pub mod query;
+pub mod lua_binding;
+pub mod interner;
+pub mod subslice;
+pub mod index_slab;
 
 use std::fmt::{Write, Debug};
 
@@ -2,6 +2,7 @@
 //!
 //! The macros in the macro crate assume that this is imported as `parse_helpers`.
 
+use std::borrow::Cow;
 use std::convert::TryInto;
 use std::io::{Result as IoResult};
 use std::io::{Write};
@@ -16,17 +17,112 @@ use nom::sequence::{tuple, terminated};
 use pd2tools_macros::gen_tuple_parsers;
 
 pub trait Parse where Self: Sized {
-    fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self>;
+    fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self, ParseError>;
     fn serialize<O: Write>(&self, output: &mut O) -> IoResult<()>;
 }
 
+/// One frame of the breadcrumb a [`ParseError`] carries back up through
+/// nested calls - which struct, which field, which element of a counted
+/// list was being parsed when the failure happened, or what leaf type the
+/// failing primitive was trying to read.
+///
+/// Pushed from innermost to outermost as the error unwinds (the field that
+/// actually failed is pushed first), so [`ParseError::context`] reads back
+/// to front when rendering a dotted path like `Model.geometry[14].count`;
+/// see [`Breadcrumb`]'s `Display` impl.
+#[derive(Debug, Clone)]
+pub enum ContextFrame {
+    Struct(&'static str),
+    Field(&'static str),
+    Element(usize),
+    Expected(&'static str)
+}
+
+/// Error type for [`Parse`]/[`WireFormat`]: besides the [`nom::error::ErrorKind`]
+/// every `nom` parser already reports, this carries the length of the input
+/// that remained when the error was raised (so a caller holding the original
+/// buffer can recover an absolute byte offset via [`ParseError::offset`]) and
+/// a [`ContextFrame`] breadcrumb built up by `#[derive(Parse)]` and the
+/// counted-collection impls as the error passes back up through them.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub remaining_len: usize,
+    pub kind: nom::error::ErrorKind,
+    pub context: Vec<ContextFrame>
+}
+
+impl ParseError {
+    /// The absolute byte offset this error occurred at, given the same
+    /// buffer the top-level `Parse::parse` call was made against.
+    pub fn offset(&self, original_input: &[u8]) -> usize {
+        original_input.len() - self.remaining_len
+    }
+
+    fn push(mut self, frame: ContextFrame) -> Self {
+        self.context.push(frame);
+        self
+    }
+}
+
+impl<'a> nom::error::ParseError<&'a [u8]> for ParseError {
+    fn from_error_kind(input: &'a [u8], kind: nom::error::ErrorKind) -> Self {
+        ParseError { remaining_len: input.len(), kind, context: Vec::new() }
+    }
+
+    fn append(_input: &'a [u8], _kind: nom::error::ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl<'a, E> nom::error::FromExternalError<&'a [u8], E> for ParseError {
+    fn from_external_error(input: &'a [u8], kind: nom::error::ErrorKind, _e: E) -> Self {
+        ParseError { remaining_len: input.len(), kind, context: Vec::new() }
+    }
+}
+
+/// Pushes a [`ContextFrame`] onto whichever [`ParseError`] is inside `err`,
+/// regardless of whether it's a recoverable [`nom::Err::Error`] or a
+/// [`nom::Err::Failure`]. `#[derive(Parse)]` and the counted-collection
+/// `Parse`/[`WireFormat`] impls call this from a `map_err` on each nested
+/// parse so the breadcrumb accumulates as the error bubbles up.
+pub fn push_frame(err: nom::Err<ParseError>, frame: ContextFrame) -> nom::Err<ParseError> {
+    match err {
+        nom::Err::Error(e) => nom::Err::Error(e.push(frame)),
+        nom::Err::Failure(e) => nom::Err::Failure(e.push(frame)),
+        nom::Err::Incomplete(n) => nom::Err::Incomplete(n)
+    }
+}
+
+/// Renders a [`ParseError`] as `offset 0x..: in Struct.field[N]: expected T,
+/// found <nom error kind>` - pairs the error with the original buffer it was
+/// raised against, since that's what [`ParseError::offset`] needs.
+pub struct Breadcrumb<'a>(pub &'a ParseError, pub &'a [u8]);
+impl<'a> std::fmt::Display for Breadcrumb<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "offset {:#x}: in ", self.0.offset(self.1))?;
+        let mut expected = None;
+        for frame in self.0.context.iter().rev() {
+            match frame {
+                ContextFrame::Struct(name) => write!(f, "{}", name)?,
+                ContextFrame::Field(name) => write!(f, ".{}", name)?,
+                ContextFrame::Element(i) => write!(f, "[{}]", i)?,
+                ContextFrame::Expected(name) => expected = Some(*name)
+            }
+        }
+        match expected {
+            Some(name) => write!(f, ": expected {}, found {:?}", name, self.0.kind),
+            None => write!(f, ": {:?}", self.0.kind)
+        }
+    }
+}
+
 macro_rules! simple_parse {
     ($t:ty, $parser:expr) => {
         impl Parse for $t {
-            fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self> {
-                $parser(input)
+            fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self, ParseError> {
+                $parser(input).map_err(|e| push_frame(e, ContextFrame::Expected(stringify!($t))))
             }
-        
+
             fn serialize<O: Write>(&self, output: &mut O) -> IoResult<()> {
                 output.write_all(&self.to_le_bytes())
             }
@@ -46,12 +142,12 @@ simple_parse!(f32, le_f32);
 simple_parse!(f64, le_f64);
 
 impl Parse for bool {
-    fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+    fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self, ParseError> {
         map_res(le_u8, |i| match i {
             0 => Ok(false),
             1 => Ok(true),
             _ => Err(())
-        })(input)
+        })(input).map_err(|e| push_frame(e, ContextFrame::Expected("bool")))
     }
 
     fn serialize<O: Write>(&self, output: &mut O) -> IoResult<()> {
@@ -65,7 +161,7 @@ macro_rules! vek_parse {
     (@parser $discard:ident) => { <T as Parse>::parse };
     ($name:ident, $($field:ident),* ) => {
         impl<T: Parse> Parse for vek::$name<T> {
-            fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+            fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self, ParseError> {
                 let (rest, ( $($field),*) ) = tuple(( $(vek_parse!(@parser $field)),* ))(input)?;
                 Ok((rest, vek::$name { $($field),* }))
             }
@@ -85,7 +181,7 @@ vek_parse!(Rgb, r, g, b);
 vek_parse!(Rgba, r, g, b, a);
 
 impl<T: Parse + Default> Parse for vek::Mat4<T> {
-    fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+    fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self, ParseError> {
         let mut out: [T; 16] = Default::default();
         let (rest, ()) = fill(<T as Parse>::parse, &mut out)(input)?;
         Ok((rest, vek::Mat4::from_col_array(out)))
@@ -101,7 +197,7 @@ impl<T: Parse + Default> Parse for vek::Mat4<T> {
 }
 
 impl Parse for String {
-    fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+    fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self, ParseError> {
         CountedString::<u32>::parse_into(input)
     }
     fn serialize<O: Write>(&self, output: &mut O) -> IoResult<()> {
@@ -110,8 +206,9 @@ impl Parse for String {
 }
 
 impl Parse for crate::hashindex::Hash {
-    fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+    fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self, ParseError> {
         map(le_u64, crate::hashindex::Hash)(input)
+            .map_err(|e| push_frame(e, ContextFrame::Expected("Hash")))
     }
     fn serialize<O: Write>(&self, output: &mut O) -> IoResult<()> {
         self.0.serialize(output)
@@ -119,8 +216,16 @@ impl Parse for crate::hashindex::Hash {
 }
 
 impl<T: Parse> Parse for Vec<T> {
-    fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self> {
-        length_count(le_u32, <T as Parse>::parse)(input)
+    fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], Self, ParseError> {
+        let (mut rest, count) = le_u32(input).map_err(|e| push_frame(e, ContextFrame::Expected("u32")))?;
+        let mut items = Vec::with_capacity(count as usize);
+        for i in 0..count {
+            let (r, item) = <T as Parse>::parse(rest)
+                .map_err(|e| push_frame(e, ContextFrame::Element(i as usize)))?;
+            items.push(item);
+            rest = r;
+        }
+        Ok((rest, items))
     }
 
     fn serialize<O: Write>(&self, output: &mut O) -> IoResult<()> {
@@ -135,12 +240,12 @@ impl<T: Parse> Parse for Vec<T> {
 
 pub struct NullTerminatedString;
 impl WireFormat<String> for NullTerminatedString {
-    fn parse_into<'a>(input: &'a [u8]) -> IResult<&'a [u8], String> {
+    fn parse_into<'a>(input: &'a [u8]) -> IResult<&'a [u8], String, ParseError> {
         let ts = terminated(take_until("\0"), tag(b"\0"));
         let mut tstr = map(ts, |v| {
             String::from_utf8_lossy(v).into_owned()
         });
-        tstr(input)
+        tstr(input).map_err(|e| push_frame(e, ContextFrame::Expected("NullTerminatedString")))
     }
 
     fn serialize_from<O: Write>(data: &String, output: &mut O) -> IoResult<()> {
@@ -157,8 +262,17 @@ where
     IF: WireFormat<I>,
     I: Parse
 {
-    fn parse_into<'a>(input: &'a [u8]) -> IResult<&'a [u8], Vec<I>> {
-        length_count(<C as Parse>::parse, <IF as WireFormat<I>>::parse_into)(input)
+    fn parse_into<'a>(input: &'a [u8]) -> IResult<&'a [u8], Vec<I>, ParseError> {
+        let (mut rest, count) = <C as Parse>::parse(input)?;
+        let count = count.to_usize();
+        let mut items = Vec::with_capacity(count);
+        for i in 0..count {
+            let (r, item) = <IF as WireFormat<I>>::parse_into(rest)
+                .map_err(|e| push_frame(e, ContextFrame::Element(i)))?;
+            items.push(item);
+            rest = r;
+        }
+        Ok((rest, items))
     }
 
     fn serialize_from<O: Write>(data: &Vec<I>, output: &mut O) -> IoResult<()> {
@@ -177,11 +291,11 @@ where
     C: Parse + nom:: ToUsize,
     usize: TryInto<C>
 {
-    fn parse_into<'a>(input: &'a [u8]) -> IResult<&'a [u8], String> {
+    fn parse_into<'a>(input: &'a [u8]) -> IResult<&'a [u8], String, ParseError> {
         nom::combinator::map_res(
             <CountedVec<C, u8> as WireFormat<Vec<u8>>>::parse_into,
             String::from_utf8
-        )(input)
+        )(input).map_err(|e| push_frame(e, ContextFrame::Expected("CountedString")))
     }
 
     fn serialize_from<O>(data: &String, output: &mut O) -> Result<(), std::io::Error>
@@ -197,12 +311,12 @@ where
 gen_tuple_parsers!(16);
 
 pub trait WireFormat<T> {
-    fn parse_into<'a>(input: &'a [u8]) -> IResult<&'a [u8], T>;
+    fn parse_into<'a>(input: &'a [u8]) -> IResult<&'a [u8], T, ParseError>;
     fn serialize_from<O: Write>(data: &T, output: &mut O) -> IoResult<()>;
 }
 
 impl<T: Parse> WireFormat<T> for T {
-    fn parse_into<'a>(input: &'a [u8]) -> IResult<&'a [u8], T> {
+    fn parse_into<'a>(input: &'a [u8]) -> IResult<&'a [u8], T, ParseError> {
         <T as Parse>::parse(input)
     }
 
@@ -211,3 +325,77 @@ impl<T: Parse> WireFormat<T> for T {
     }
 }
 
+/// Lifetime-parameterised companion to [`Parse`]: for a type whose on-disk
+/// bytes can stand in for its in-memory representation, this borrows
+/// straight out of `input` instead of copying it. There's deliberately no
+/// blanket relationship to `Parse` (and no default method on it) - most
+/// `Parse` impls, an aggregate struct or a `Vec<T>` of non-POD items, have
+/// no borrowed form at all, so this only gets implemented where borrowing
+/// is actually possible.
+///
+/// `#[derive(Parse)]` (from `pd2tools_macros`, used e.g. by
+/// [`crate::bundles::bundledb_reader`]) doesn't emit `ParseRef` impls: it
+/// has no way to know whether every field of a given struct is itself
+/// borrowable, so there's nothing to hang a "borrowed when every field is
+/// borrowable" mode off yet. [`Str`] and [`Slice`] below are usable
+/// standalone in hand-written parsers in the meantime.
+pub trait ParseRef<'a> where Self: Sized {
+    fn parse_ref(input: &'a [u8]) -> IResult<&'a [u8], Self>;
+}
+
+/// Borrowed counterpart to [`Parse for String`](Parse): same `u32`-counted
+/// layout as [`CountedString<u32>`], but holds a `Cow::Borrowed` of the
+/// counted bytes when they're valid UTF-8, rather than always allocating a
+/// fresh `String` up front.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Str<'a>(pub Cow<'a, str>);
+impl<'a> ParseRef<'a> for Str<'a> {
+    fn parse_ref(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+        let (rest, len) = le_u32(input)?;
+        let (rest, bytes) = nom::bytes::complete::take(len as usize)(rest)?;
+        Ok((rest, Str(String::from_utf8_lossy(bytes))))
+    }
+}
+impl<'a> From<Str<'a>> for String {
+    fn from(src: Str<'a>) -> String { src.0.into_owned() }
+}
+
+/// Borrowed counterpart to [`NullTerminatedString`]: same take-until-nul
+/// layout, holding a `Cow::Borrowed` of the bytes before the nul when
+/// they're valid UTF-8.
+pub struct NullTerminatedStringRef;
+impl<'a> ParseRef<'a> for Cow<'a, str> {
+    fn parse_ref(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+        let ts = terminated(take_until("\0"), tag(b"\0"));
+        map(ts, String::from_utf8_lossy)(input)
+    }
+}
+
+/// Borrowed counterpart to a `u32`-counted `Vec<T>` of plain-old-data
+/// elements (see [`Parse for Vec<T>`](Parse)): reinterprets the counted
+/// bytes as `&[T]` via [`bytemuck`] instead of parsing one element at a
+/// time, falling back to a copy if the input isn't aligned for `T` - the
+/// borrow is only ever a speed-up, never something a caller can rely on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slice<'a, T: bytemuck::Pod>(pub Cow<'a, [T]>);
+impl<'a, T: bytemuck::Pod> ParseRef<'a> for Slice<'a, T> {
+    fn parse_ref(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+        let (rest, count) = le_u32(input)?;
+        let byte_len = (count as usize).checked_mul(std::mem::size_of::<T>())
+            .ok_or_else(|| nom::Err::Failure(nom::error::Error::new(input, nom::error::ErrorKind::TooLarge)))?;
+        let (rest, bytes) = nom::bytes::complete::take(byte_len)(rest)?;
+        let cow = match bytemuck::try_cast_slice::<u8, T>(bytes) {
+            Ok(s) => Cow::Borrowed(s),
+            Err(_) => Cow::Owned(
+                bytes.chunks_exact(std::mem::size_of::<T>())
+                    .map(bytemuck::pod_read_unaligned::<T>)
+                    .collect()
+            )
+        };
+        Ok((rest, Slice(cow)))
+    }
+}
+impl<'a, T: bytemuck::Pod> From<Slice<'a, T>> for Vec<T> {
+    fn from(src: Slice<'a, T>) -> Vec<T> { src.0.into_owned() }
+}
+
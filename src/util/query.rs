@@ -0,0 +1,489 @@
+//! Selector/predicate query language for locating nodes inside a parsed
+//! [`Value`] tree - turns "parse the whole file" into "parse, then pull out
+//! the one mesh/material/etc you actually wanted" - plus, via [`ToValue`],
+//! any `#[derive(Parse)]` struct that also derives it.
+//!
+//! A [`Selector`] is an ordered list of [`Step`]s applied left to right:
+//! - a bare name - descend into the child field (or matching [`Tagged`]
+//!   variant) called that
+//! - a bare number - descend into the Nth positional child
+//! - `*` - fan out to every child
+//! - `//` - fan out to the current nodes and every descendant, at any depth
+//! - `[pred]` - keep only nodes where `pred` holds
+//!
+//! A [`Predicate`] tests a sub-[`Selector`] applied relative to the current
+//! node: `field == "x"` compares the value(s) it selects against a
+//! constant (numeric comparisons cross integer/float kinds; everything
+//! else compares by exact value), a bare sub-selector like `[geometry]`
+//! asks whether it selects anything at all, and predicates combine with
+//! `&&`, `||`, `!`, and parentheses.
+//!
+//! `objects/*[name == "root"]//geometry` means: from `objects`, fan out to
+//! every child, keep the ones whose `name` field is `"root"`, then find a
+//! `geometry` node among each one's descendants (at any depth).
+//!
+//! [`Tagged`]: Value::Tagged
+
+use anyhow::{anyhow, bail, Result};
+
+use super::dynamic::{ToValue, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Child(String),
+    Index(usize),
+    Wildcard,
+    Descendant,
+    Filter(Predicate)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selector(pub Vec<Step>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp { Eq, Ne, Lt, Le, Gt, Ge }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    Compare(Selector, CompareOp, Value),
+    NonEmpty(Selector),
+    And(Vec<Predicate>),
+    Or(Vec<Predicate>),
+    Not(Box<Predicate>)
+}
+
+/// Anything queryable as a [`Value`] tree: the tree itself, or a concrete
+/// `Parse` type that also derives [`ToValue`]. No new methods - this is
+/// just [`ToValue`] under the name this module calls it by.
+pub trait Reflect: ToValue {}
+impl<T: ToValue> Reflect for T {}
+
+/// Applies `selector` to `root`, returning every node it matches, borrowed
+/// out of `root`.
+pub fn select<'a>(root: &'a Value, selector: &Selector) -> Vec<&'a Value> {
+    let mut working = vec![root];
+    for step in &selector.0 {
+        working = apply_step(working, step);
+    }
+    working
+}
+
+/// As [`select`], but against anything implementing [`Reflect`] rather than
+/// a [`Value`] directly. Since [`ToValue::to_value`] builds a fresh tree,
+/// the result is owned rather than borrowed from `root`.
+pub fn select_typed<T: Reflect>(root: &T, selector: &Selector) -> Vec<Value> {
+    let root = root.to_value();
+    select(&root, selector).into_iter().cloned().collect()
+}
+
+fn apply_step<'a>(nodes: Vec<&'a Value>, step: &Step) -> Vec<&'a Value> {
+    match step {
+        Step::Child(name) => nodes.into_iter().filter_map(|n| child_named(n, name)).collect(),
+        Step::Index(i) => nodes.into_iter().filter_map(|n| child_indexed(n, *i)).collect(),
+        Step::Wildcard => nodes.into_iter().flat_map(children_of).collect(),
+        Step::Descendant => {
+            let mut out = Vec::new();
+            for n in nodes {
+                out.extend(descendants_of(n));
+            }
+            out
+        }
+        Step::Filter(pred) => nodes.into_iter().filter(|n| eval_predicate(n, pred)).collect()
+    }
+}
+
+fn children_of(node: &Value) -> Vec<&Value> {
+    match node {
+        Value::Record(fields) => fields.iter().map(|(_, v)| v).collect(),
+        Value::List(items) => items.iter().collect(),
+        Value::Tagged(_, payload) => vec![payload.as_ref()],
+        _ => Vec::new()
+    }
+}
+
+fn child_named<'a>(node: &'a Value, name: &str) -> Option<&'a Value> {
+    match node {
+        Value::Record(fields) => fields.iter().find(|(n, _)| n == name).map(|(_, v)| v),
+        Value::Tagged(tag, payload) if tag == name => Some(payload.as_ref()),
+        _ => None
+    }
+}
+
+fn child_indexed(node: &Value, index: usize) -> Option<&Value> {
+    match node {
+        Value::Record(fields) => fields.get(index).map(|(_, v)| v),
+        Value::List(items) => items.get(index),
+        Value::Tagged(_, payload) if index == 0 => Some(payload.as_ref()),
+        _ => None
+    }
+}
+
+/// Every node reachable from `node` by repeatedly following [`children_of`],
+/// including `node` itself - a descendant-or-self DFS, since a later
+/// [`Step::Child`]/[`Step::Filter`] is what actually narrows it down to the
+/// nodes named or shaped the way the query wants.
+fn descendants_of(node: &Value) -> Vec<&Value> {
+    let mut out = vec![node];
+    let mut stack: Vec<&Value> = vec![node];
+    while let Some(n) = stack.pop() {
+        for c in children_of(n) {
+            out.push(c);
+            stack.push(c);
+        }
+    }
+    out
+}
+
+fn eval_predicate(node: &Value, pred: &Predicate) -> bool {
+    match pred {
+        Predicate::Compare(sel, op, constant) => select(node, sel).iter().any(|v| compare(v, *op, constant)),
+        Predicate::NonEmpty(sel) => !select(node, sel).is_empty(),
+        Predicate::And(preds) => preds.iter().all(|p| eval_predicate(node, p)),
+        Predicate::Or(preds) => preds.iter().any(|p| eval_predicate(node, p)),
+        Predicate::Not(p) => !eval_predicate(node, p)
+    }
+}
+
+fn compare(v: &Value, op: CompareOp, constant: &Value) -> bool {
+    match (as_f64(v), as_f64(constant)) {
+        (Some(a), Some(b)) => match op {
+            CompareOp::Eq => a == b,
+            CompareOp::Ne => a != b,
+            CompareOp::Lt => a < b,
+            CompareOp::Le => a <= b,
+            CompareOp::Gt => a > b,
+            CompareOp::Ge => a >= b
+        },
+        _ => match op {
+            CompareOp::Eq => v == constant,
+            CompareOp::Ne => v != constant,
+            _ => false
+        }
+    }
+}
+
+fn as_f64(v: &Value) -> Option<f64> {
+    match v {
+        Value::U8(n) => Some(*n as f64),
+        Value::U16(n) => Some(*n as f64),
+        Value::U32(n) => Some(*n as f64),
+        Value::U64(n) => Some(*n as f64),
+        Value::I8(n) => Some(*n as f64),
+        Value::I16(n) => Some(*n as f64),
+        Value::I32(n) => Some(*n as f64),
+        Value::I64(n) => Some(*n as f64),
+        Value::F32(n) => Some(*n as f64),
+        Value::F64(n) => Some(*n),
+        _ => None
+    }
+}
+
+/// Parses the compact textual syntax described in the module doc comment
+/// into a [`Selector`].
+pub fn parse_selector(src: &str) -> Result<Selector> {
+    let mut p = Parser { input: src, pos: 0 };
+    p.skip_trivia();
+    let sel = p.parse_selector()?;
+    if !p.at_end() {
+        bail!("unexpected trailing input at byte {}: {:.20}", p.pos, p.rest());
+    }
+    Ok(sel)
+}
+
+/// Cursor-based recursive-descent parser, in the same style as
+/// [`crate::formats::scriptdata::lua_like`]'s `Parser` and
+/// `schema_compiler`'s: a position in the source text plus one method per
+/// grammar production.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str { &self.input[self.pos..] }
+    fn at_end(&self) -> bool { self.rest().is_empty() }
+
+    fn skip_trivia(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek_ident(&self) -> Option<&'a str> {
+        let rest = self.rest();
+        let end = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+        if end == 0 { None } else { Some(&rest[..end]) }
+    }
+
+    fn peek_uint(&self) -> Option<&'a str> {
+        let rest = self.rest();
+        let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        if end == 0 { None } else { Some(&rest[..end]) }
+    }
+
+    fn expect_ident(&mut self) -> Result<&'a str> {
+        let ident = match self.peek_ident() {
+            Some(i) => i,
+            None => bail!("expected identifier at byte {}", self.pos)
+        };
+        self.pos += ident.len();
+        self.skip_trivia();
+        Ok(ident)
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<()> {
+        if !self.rest().starts_with(punct) {
+            bail!("expected '{}' at byte {}, found: {:.20}", punct, self.pos, self.rest());
+        }
+        self.pos += punct.len();
+        self.skip_trivia();
+        Ok(())
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        if self.rest().starts_with(punct) {
+            self.pos += punct.len();
+            self.skip_trivia();
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    fn parse_selector(&mut self) -> Result<Selector> {
+        let mut steps = Vec::new();
+        if self.eat_punct("//") {
+            steps.push(Step::Descendant);
+        }
+        self.parse_step(&mut steps)?;
+        loop {
+            if self.eat_punct("//") {
+                steps.push(Step::Descendant);
+                self.parse_step(&mut steps)?;
+            }
+            else if self.eat_punct("/") {
+                self.parse_step(&mut steps)?;
+            }
+            else {
+                break;
+            }
+        }
+        Ok(Selector(steps))
+    }
+
+    fn parse_step(&mut self, out: &mut Vec<Step>) -> Result<()> {
+        let base = if self.eat_punct("*") {
+            Step::Wildcard
+        }
+        else if let Some(digits) = self.peek_uint() {
+            self.pos += digits.len();
+            self.skip_trivia();
+            Step::Index(digits.parse()?)
+        }
+        else {
+            Step::Child(self.expect_ident()?.to_owned())
+        };
+        out.push(base);
+        while self.eat_punct("[") {
+            let pred = self.parse_predicate_or()?;
+            self.expect_punct("]")?;
+            out.push(Step::Filter(pred));
+        }
+        Ok(())
+    }
+
+    fn parse_predicate_or(&mut self) -> Result<Predicate> {
+        let mut parts = vec![self.parse_predicate_and()?];
+        while self.eat_punct("||") {
+            parts.push(self.parse_predicate_and()?);
+        }
+        Ok(if parts.len() == 1 { parts.pop().unwrap() } else { Predicate::Or(parts) })
+    }
+
+    fn parse_predicate_and(&mut self) -> Result<Predicate> {
+        let mut parts = vec![self.parse_predicate_not()?];
+        while self.eat_punct("&&") {
+            parts.push(self.parse_predicate_not()?);
+        }
+        Ok(if parts.len() == 1 { parts.pop().unwrap() } else { Predicate::And(parts) })
+    }
+
+    fn parse_predicate_not(&mut self) -> Result<Predicate> {
+        if self.eat_punct("!") {
+            Ok(Predicate::Not(Box::new(self.parse_predicate_not()?)))
+        }
+        else {
+            self.parse_predicate_atom()
+        }
+    }
+
+    fn parse_predicate_atom(&mut self) -> Result<Predicate> {
+        if self.eat_punct("(") {
+            let pred = self.parse_predicate_or()?;
+            self.expect_punct(")")?;
+            return Ok(pred);
+        }
+
+        let sub = self.parse_selector()?;
+        let op = if self.eat_punct("==") { Some(CompareOp::Eq) }
+            else if self.eat_punct("!=") { Some(CompareOp::Ne) }
+            else if self.eat_punct("<=") { Some(CompareOp::Le) }
+            else if self.eat_punct(">=") { Some(CompareOp::Ge) }
+            else if self.eat_punct("<") { Some(CompareOp::Lt) }
+            else if self.eat_punct(">") { Some(CompareOp::Gt) }
+            else { None };
+
+        match op {
+            Some(op) => Ok(Predicate::Compare(sub, op, self.parse_literal()?)),
+            None => Ok(Predicate::NonEmpty(sub))
+        }
+    }
+
+    /// A comparison constant: a quoted string (no escapes), `true`/`false`,
+    /// or a number, rendered straight into the [`Value`] variant a literal
+    /// of that shape would parse as.
+    fn parse_literal(&mut self) -> Result<Value> {
+        if self.rest().starts_with('"') {
+            self.parse_string_literal()
+        }
+        else if self.peek_ident() == Some("true") {
+            self.expect_ident()?;
+            Ok(Value::Bool(true))
+        }
+        else if self.peek_ident() == Some("false") {
+            self.expect_ident()?;
+            Ok(Value::Bool(false))
+        }
+        else {
+            self.parse_number_literal()
+        }
+    }
+
+    fn parse_string_literal(&mut self) -> Result<Value> {
+        self.pos += 1;
+        let rest = self.rest();
+        let end = rest.find('"').ok_or_else(|| anyhow!("unterminated string literal at byte {}", self.pos))?;
+        let text = rest[..end].to_owned();
+        self.pos += end + 1;
+        self.skip_trivia();
+        Ok(Value::Text(text))
+    }
+
+    fn parse_number_literal(&mut self) -> Result<Value> {
+        let rest = self.rest();
+        let bytes = rest.as_bytes();
+        let start = if rest.starts_with('-') { 1 } else { 0 };
+        let mut end = start;
+        while end < bytes.len() && bytes[end].is_ascii_digit() { end += 1; }
+        if end == start {
+            bail!("expected a number literal at byte {}", self.pos);
+        }
+        let mut is_float = false;
+        if end < bytes.len() && bytes[end] == b'.' {
+            is_float = true;
+            end += 1;
+            while end < bytes.len() && bytes[end].is_ascii_digit() { end += 1; }
+        }
+        let text = &rest[..end];
+        let value = if is_float { Value::F64(text.parse()?) } else { Value::I64(text.parse()?) };
+        self.pos += end;
+        self.skip_trivia();
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(fields: Vec<(&str, Value)>) -> Value {
+        Value::Record(fields.into_iter().map(|(n, v)| (n.to_owned(), v)).collect())
+    }
+
+    fn sample_tree() -> Value {
+        record(vec![
+            ("name", Value::Text("root".to_owned())),
+            ("objects", Value::List(vec![
+                record(vec![
+                    ("name", Value::Text("root".to_owned())),
+                    ("geometry", Value::Tagged("Mesh".to_owned(), Box::new(Value::I64(1)))),
+                ]),
+                record(vec![
+                    ("name", Value::Text("child".to_owned())),
+                    ("geometry", Value::Tagged("Mesh".to_owned(), Box::new(Value::I64(2)))),
+                ]),
+            ])),
+        ])
+    }
+
+    fn sel(src: &str) -> Selector {
+        parse_selector(src).unwrap_or_else(|e| panic!("failed to parse {:?}: {}", src, e))
+    }
+
+    #[test]
+    fn child_by_name_descends_into_a_record_field() {
+        let tree = sample_tree();
+        let found = select(&tree, &sel("name"));
+        assert_eq!(found, vec![&Value::Text("root".to_owned())]);
+    }
+
+    #[test]
+    fn positional_index_selects_the_nth_child() {
+        let tree = sample_tree();
+        let found = select(&tree, &sel("objects/0/name"));
+        assert_eq!(found, vec![&Value::Text("root".to_owned())]);
+    }
+
+    #[test]
+    fn wildcard_fans_out_to_every_child() {
+        let tree = sample_tree();
+        let found = select(&tree, &sel("objects/*/name"));
+        assert_eq!(found, vec![&Value::Text("root".to_owned()), &Value::Text("child".to_owned())]);
+    }
+
+    #[test]
+    fn descendant_step_finds_nodes_at_any_depth() {
+        let tree = sample_tree();
+        let found = select(&tree, &sel("//geometry"));
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn equality_predicate_narrows_by_field_value() {
+        let tree = sample_tree();
+        let found = select(&tree, &sel(r#"objects/*[name == "child"]/geometry"#));
+        assert_eq!(found, vec![&Value::Tagged("Mesh".to_owned(), Box::new(Value::I64(2)))]);
+    }
+
+    #[test]
+    fn numeric_comparison_crosses_integer_and_float_kinds() {
+        let node = record(vec![("n", Value::I64(5))]);
+        assert!(eval_predicate(&node, &Predicate::Compare(Selector(vec![Step::Child("n".to_owned())]), CompareOp::Gt, Value::F64(4.5))));
+        assert!(!eval_predicate(&node, &Predicate::Compare(Selector(vec![Step::Child("n".to_owned())]), CompareOp::Lt, Value::F64(4.5))));
+    }
+
+    #[test]
+    fn bare_subselector_predicate_tests_non_emptiness() {
+        let tree = sample_tree();
+        let found = select(&tree, &sel("objects/*[geometry]"));
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn predicates_combine_with_and_or_not() {
+        let tree = sample_tree();
+        let and_found = select(&tree, &sel(r#"objects/*[name == "root" && geometry]"#));
+        assert_eq!(and_found.len(), 1);
+
+        let or_found = select(&tree, &sel(r#"objects/*[name == "root" || name == "child"]"#));
+        assert_eq!(or_found.len(), 2);
+
+        let not_found = select(&tree, &sel(r#"objects/*[!(name == "root")]"#));
+        assert_eq!(not_found.len(), 1);
+    }
+
+    #[test]
+    fn parse_selector_rejects_trailing_garbage() {
+        assert!(parse_selector("name extra").is_err());
+    }
+}
@@ -20,6 +20,15 @@ impl<T> RcCell<T> {
     pub fn borrow_mut(&self) -> RefMut<T> {
         self.0.borrow_mut()
     }
+
+    /// Whether `self` and `other` are the same cell, not merely two cells
+    /// with equal contents. Spelled out explicitly (rather than leaning on
+    /// `PartialEq`, which already compares by pointer) because callers
+    /// asking "is this the same table" read clearer than callers asking
+    /// "are these tables equal".
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
 }
 
 impl<T: Default> Default for RcCell<T> {
@@ -1,6 +1,6 @@
 use std::{iter::{Copied, Enumerate}, ops::Range};
 
-use nom::Slice;
+use nom::{InputLength, InputTake, Slice};
 
 /// Slice that remembers where it came from
 #[derive(Copy, Clone, Debug)]
@@ -175,8 +175,54 @@ where
     }
 }
 
-fn t() -> nom::IResult<Subslice<'static, u8>, Subslice<'static, u8>> {
-    let src = b"parser goes brrr";
-    let ss = Subslice::from(&src[..]);
-    nom::bytes::complete::tag(b"parse")(ss)
+impl<'a, 'b> nom::FindSubstring<&'b [u8]> for Subslice<'a, u8> {
+    fn find_substring(&self, substr: &'b [u8]) -> Option<usize> {
+        if substr.is_empty() {
+            return Some(0);
+        }
+        self.inner.windows(substr.len()).position(|w| w == substr)
+    }
+}
+
+/// Byte-at-a-time version of the position-scanning combinators (`take_while`, `is_not`,
+/// `digit1`...), so those can run over a `Subslice<u8>` the same way they would over `&[u8]`.
+/// There's no streaming mode here - a `Subslice` is always a view of a complete buffer - so the
+/// non-`_complete` variants report `Incomplete` rather than trying to ask for more input.
+impl<'a> nom::InputTakeAtPosition for Subslice<'a, u8> {
+    type Item = u8;
+
+    fn split_at_position<P, E: nom::error::ParseError<Self>>(&self, predicate: P) -> nom::IResult<Self, Self, E>
+    where P: Fn(Self::Item) -> bool {
+        match self.inner.iter().position(|&c| predicate(c)) {
+            Some(i) => Ok(self.take_split(i)),
+            None => Err(nom::Err::Incomplete(nom::Needed::new(1)))
+        }
+    }
+
+    fn split_at_position1<P, E: nom::error::ParseError<Self>>(&self, predicate: P, e: nom::error::ErrorKind) -> nom::IResult<Self, Self, E>
+    where P: Fn(Self::Item) -> bool {
+        match self.inner.iter().position(|&c| predicate(c)) {
+            Some(0) => Err(nom::Err::Error(E::from_error_kind(*self, e))),
+            Some(i) => Ok(self.take_split(i)),
+            None => Err(nom::Err::Incomplete(nom::Needed::new(1)))
+        }
+    }
+
+    fn split_at_position_complete<P, E: nom::error::ParseError<Self>>(&self, predicate: P) -> nom::IResult<Self, Self, E>
+    where P: Fn(Self::Item) -> bool {
+        match self.inner.iter().position(|&c| predicate(c)) {
+            Some(i) => Ok(self.take_split(i)),
+            None => Ok(self.take_split(self.input_len()))
+        }
+    }
+
+    fn split_at_position1_complete<P, E: nom::error::ParseError<Self>>(&self, predicate: P, e: nom::error::ErrorKind) -> nom::IResult<Self, Self, E>
+    where P: Fn(Self::Item) -> bool {
+        match self.inner.iter().position(|&c| predicate(c)) {
+            Some(0) => Err(nom::Err::Error(E::from_error_kind(*self, e))),
+            Some(i) => Ok(self.take_split(i)),
+            None if self.inner.is_empty() => Err(nom::Err::Error(E::from_error_kind(*self, e))),
+            None => Ok(self.take_split(self.input_len()))
+        }
+    }
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 use libraryfolders::LibraryFolders;
@@ -23,7 +24,42 @@ pub fn steam_directory() -> Result<String, Error> {
 
 #[cfg(not(windows))]
 pub fn steam_directory() -> Result<String, Error> {
-    Error::SteamLookupUnimplemented
+    for candidate in candidate_steam_dirs() {
+        let mut libraryfolders = candidate.clone();
+        libraryfolders.push("steamapps");
+        libraryfolders.push("libraryfolders.vdf");
+        if libraryfolders.is_file() {
+            return candidate.into_os_string().into_string().map_err(|_| Error::BadSteamPath);
+        }
+    }
+    Err(Error::BadSteamPath)
+}
+
+/// Steam roots worth probing on Linux/macOS, in the order they should be
+/// tried. Doesn't check which of these actually contain a Steam install;
+/// [`steam_directory`] does that by looking for `steamapps/libraryfolders.vdf`.
+#[cfg(not(windows))]
+fn candidate_steam_dirs() -> Vec<PathBuf> {
+    let home = match std::env::var_os("HOME") {
+        Some(h) => PathBuf::from(h),
+        None => return Vec::new()
+    };
+
+    if cfg!(target_os = "macos") {
+        return vec![home.join("Library/Application Support/Steam")];
+    }
+
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".local/share"));
+
+    vec![
+        data_home.join("Steam"),
+        home.join(".local/share/Steam"),
+        home.join(".steam/steam"),
+        home.join(".steam/root"),
+        home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"),
+    ]
 }
 
 #[derive(Error, Debug)]
@@ -58,7 +94,10 @@ pub enum Error {
     BadAppmanifestSchema,
 
     #[error("Game with id {0} not detected")]
-    GameNotDetected(String)
+    GameNotDetected(String),
+
+    #[error("Failed to resolve #base include {0:?}: {1}")]
+    BadInclude(PathBuf, Box<Error>)
 }
 
 pub fn try_get_app_directory(appid: &str) -> Result<PathBuf, Error> {
@@ -100,7 +139,70 @@ pub fn try_get_app_directory(appid: &str) -> Result<PathBuf, Error> {
 }
 
 fn read_vdf(file: &Path) -> Result<vdf::Node, Error> {
+    let mut visited = HashSet::new();
+    visited.insert(file.canonicalize().unwrap_or_else(|_| file.to_owned()));
+    read_vdf_with_visited(file, &mut visited)
+}
+
+fn read_vdf_with_visited(file: &Path, visited: &mut HashSet<PathBuf>) -> Result<vdf::Node, Error> {
     let bytes = std::fs::read(file).map_err(|e| Error::IoError(file.to_owned(), e) )?;
     let text = String::from_utf8(bytes).map_err(|e| Error::BadEncoding(file.to_owned(), e))?;
-    vdf::parse(&text).map_err(|e| Error::BadVdfParse(file.to_owned(), e))
+    let node = vdf::parse(&text).map_err(|e| Error::BadVdfParse(file.to_owned(), e))?;
+    let base_dir = file.parent().unwrap_or_else(|| Path::new("."));
+    resolve_includes(node, base_dir, visited)
+}
+
+/// Recursively splices the contents of any `#base "other.vdf"` node into the
+/// section that contains it, the way Valve's own KeyValues readers do: each
+/// included file's top-level keys come first, in the order their `#base`
+/// statements appear, and a literal key occurring later in the same section
+/// overrides one of the same name pulled in from an earlier include.
+/// `visited` holds the canonical paths already on the current include chain,
+/// so a cycle just stops recursing instead of looping forever.
+fn resolve_includes(mut node: vdf::Node, base_dir: &Path, visited: &mut HashSet<PathBuf>) -> Result<vdf::Node, Error> {
+    let children = match node.data {
+        vdf::Data::Section(children) => children,
+        vdf::Data::String(_) => return Ok(node)
+    };
+
+    let mut merged = Vec::<vdf::Node>::new();
+    let mut index_of = HashMap::<String, usize>::new();
+
+    for child in children {
+        if child.name == "#base" {
+            let path_str = match &child.data {
+                vdf::Data::String(s) => s.clone(),
+                vdf::Data::Section(_) => continue
+            };
+            let inc_path = base_dir.join(&path_str);
+            let canon = inc_path.canonicalize().unwrap_or_else(|_| inc_path.clone());
+            if !visited.insert(canon) {
+                continue;
+            }
+            let inc_node = read_vdf_with_visited(&inc_path, visited)
+                .map_err(|e| Error::BadInclude(inc_path.clone(), Box::new(e)))?;
+            if let vdf::Data::Section(inc_children) = inc_node.data {
+                for inc_child in inc_children {
+                    insert_or_override(&mut merged, &mut index_of, inc_child);
+                }
+            }
+            continue;
+        }
+
+        let merged_child = resolve_includes(child, base_dir, visited)?;
+        insert_or_override(&mut merged, &mut index_of, merged_child);
+    }
+
+    node.data = vdf::Data::Section(merged);
+    Ok(node)
+}
+
+fn insert_or_override(merged: &mut Vec<vdf::Node>, index_of: &mut HashMap<String, usize>, node: vdf::Node) {
+    match index_of.get(&node.name) {
+        Some(&i) => merged[i] = node,
+        None => {
+            index_of.insert(node.name.clone(), merged.len());
+            merged.push(node);
+        }
+    }
 }
\ No newline at end of file
@@ -1,3 +1,5 @@
+use std::collections::{HashMap, HashSet};
+
 use thiserror::Error;
 use logos::{Lexer, Logos};
 
@@ -10,7 +12,16 @@ pub enum Error {
     BadEscape(char),
 
     #[error("Unexpected end of file after {0} chars")]
-    EarlyEof(usize)
+    EarlyEof(usize),
+
+    #[error("\"{0}\" needs a single string argument")]
+    BadInclude(String),
+
+    #[error("Include cycle at \"{0}\"")]
+    IncludeCycle(String),
+
+    #[error("In \"{0}\": {1}")]
+    InFile(String, Box<Error>)
 }
 
 #[derive(Logos, PartialEq, Eq, Debug)]
@@ -88,6 +99,86 @@ impl Node {
     pub fn section_data(&self) -> Option<&[Node]> {
         if let Data::Section(s) = &self.data { Some(s.as_slice()) } else { None }
     }
+
+    /// Walks this node's children, dropping any whose `condition` isn't satisfied by `defined`
+    /// and recursing into the ones that survive. This is a separate pass over an already-parsed
+    /// tree rather than something `parse` does itself, so the raw, unfiltered tree is still
+    /// available to callers that want it (e.g. to report on what a different `defined` set
+    /// would keep).
+    pub fn evaluate_conditions(&mut self, defined: &HashSet<String>) -> ConditionSummary {
+        let mut summary = ConditionSummary::default();
+
+        if let Data::Section(children) = &mut self.data {
+            let mut kept = Vec::with_capacity(children.len());
+            for mut child in std::mem::take(children) {
+                let satisfied = match &child.condition {
+                    Some(cond) => evaluate_condition(cond, defined),
+                    None => true
+                };
+
+                if !satisfied {
+                    summary.removed += 1;
+                    continue;
+                }
+
+                summary.kept += 1;
+                let child_summary = child.evaluate_conditions(defined);
+                summary.kept += child_summary.kept;
+                summary.removed += child_summary.removed;
+                kept.push(child);
+            }
+            *children = kept;
+        }
+
+        summary
+    }
+}
+
+/// How many nodes [`Node::evaluate_conditions`] kept vs. dropped, across the whole tree it
+/// walked - lets callers confirm a platform/quality gate actually matched something instead of
+/// the section just happening to already be empty.
+#[derive(Default, Debug)]
+pub struct ConditionSummary {
+    pub kept: usize,
+    pub removed: usize
+}
+
+/// Evaluates one `[...]` conditional body against `defined`: a bare symbol is true iff it's in
+/// `defined`, a leading `!` negates that, and terms are combined strictly left-to-right (no
+/// operator precedence) by a separating `&&`/`||` - or by nothing at all, which behaves as
+/// `&&`, since these conditionals are mostly written as plain space-separated symbols.
+/// Parentheses are accepted but don't change the grouping, since left-to-right evaluation of a
+/// conditional this short rarely needs it.
+fn evaluate_condition(condition: &str, defined: &HashSet<String>) -> bool {
+    let cleaned = condition.replace(['(', ')'], " ");
+    let mut tokens = cleaned.split_whitespace();
+
+    let first = match tokens.next() {
+        Some(t) => t,
+        None => return true
+    };
+    let mut result = evaluate_term(first, defined);
+
+    while let Some(tok) = tokens.next() {
+        let (is_and, term) = match tok {
+            "&&" => (true, tokens.next()),
+            "||" => (false, tokens.next()),
+            other => (true, Some(other))
+        };
+        let Some(term) = term else { break };
+
+        let value = evaluate_term(term, defined);
+        result = if is_and { result && value } else { result || value };
+    }
+
+    result
+}
+
+fn evaluate_term(token: &str, defined: &HashSet<String>) -> bool {
+    match token.strip_prefix('!') {
+        Some(rest) => !defined.contains(rest),
+        None => defined.contains(token)
+    }
 }
 
 #[derive(Debug)]
@@ -133,6 +224,8 @@ fn parse_node(tokens: &mut Lexer<Token>) -> NodeParseResult {
             }
        },
        Some(Token::Text(t)) => node.name = t,
+       Some(Token::Base) => node.name = "#base".to_owned(),
+       Some(Token::Include) => node.name = "#include".to_owned(),
        Some(t) => return Error::BadToken(t, tokens.span().start).into(),
        None => return Error::EarlyEof(tokens.span().end).into()
     }
@@ -154,6 +247,89 @@ fn parse_node(tokens: &mut Lexer<Token>) -> NodeParseResult {
         Some(t) => return Error::BadToken(t, tokens.span().start).into(),
         None => return Error::EarlyEof(tokens.span().end).into()
     }
-    
+
     NodeParseResult::Node(node)
+}
+
+/// Supplies the text a `#include`/`#base` directive names, so [`parse_with_includes`] can be
+/// pointed at a real filesystem, a package database, or anything else the caller has a file
+/// tree in.
+pub trait NodeSource {
+    fn load(&self, path: &str) -> Result<String, Error>;
+}
+
+/// Parses `root` (a path meaningful to `source`, not read from disk directly here) and resolves
+/// every `#include`/`#base` it contains: an `#include "file"` splices that file's own top-level
+/// children in at its exact position, while a `#base "file"` merges its children in as
+/// preceding siblings the way Valve's own KeyValues readers do - within each section, a key
+/// occurring later overrides an earlier one of the same name, so a file's own definitions win
+/// over anything it `#base`s in. A directive whose target is already on the current include
+/// chain is an `IncludeCycle` error rather than infinite recursion.
+pub fn parse_with_includes(root: &str, source: &impl NodeSource) -> Result<Node, Error> {
+    let mut stack = Vec::new();
+    load_and_resolve(root, source, &mut stack)
+}
+
+fn load_and_resolve(path: &str, source: &impl NodeSource, stack: &mut Vec<String>) -> Result<Node, Error> {
+    if stack.iter().any(|p| p == path) {
+        return Err(Error::IncludeCycle(path.to_owned()));
+    }
+
+    let text = source.load(path)?;
+    let mut node = parse(&text).map_err(|e| Error::InFile(path.to_owned(), Box::new(e)))?;
+
+    stack.push(path.to_owned());
+    resolve_includes(&mut node, source, stack)?;
+    stack.pop();
+
+    Ok(node)
+}
+
+fn resolve_includes(node: &mut Node, source: &impl NodeSource, stack: &mut Vec<String>) -> Result<(), Error> {
+    let children = match &mut node.data {
+        Data::Section(children) => children,
+        Data::String(_) => return Ok(())
+    };
+
+    let mut merged = Vec::<Node>::new();
+    let mut index_of = HashMap::<String, usize>::new();
+
+    for mut child in std::mem::take(children) {
+        match child.name.as_str() {
+            "#include" => {
+                let path = child.string_data().ok_or_else(|| Error::BadInclude(child.name.clone()))?.to_owned();
+                let included = load_and_resolve(&path, source, stack)?;
+                match included.data {
+                    Data::Section(inc_children) => merged.extend(inc_children),
+                    Data::String(_) => merged.push(included)
+                }
+            },
+            "#base" => {
+                let path = child.string_data().ok_or_else(|| Error::BadInclude(child.name.clone()))?.to_owned();
+                let included = load_and_resolve(&path, source, stack)?;
+                if let Data::Section(inc_children) = included.data {
+                    for inc_child in inc_children {
+                        insert_or_override(&mut merged, &mut index_of, inc_child);
+                    }
+                }
+            },
+            _ => {
+                resolve_includes(&mut child, source, stack)?;
+                insert_or_override(&mut merged, &mut index_of, child);
+            }
+        }
+    }
+
+    *children = merged;
+    Ok(())
+}
+
+fn insert_or_override(merged: &mut Vec<Node>, index_of: &mut HashMap<String, usize>, node: Node) {
+    match index_of.get(&node.name) {
+        Some(&i) => merged[i] = node,
+        None => {
+            index_of.insert(node.name.clone(), merged.len());
+            merged.push(node);
+        }
+    }
 }
\ No newline at end of file